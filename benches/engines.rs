@@ -0,0 +1,95 @@
+//! 比较两种执行引擎以及不带类型标记的栈：`cargo bench --bench engines`，
+//! 加上 `--features jit` 时同时比较 Cranelift 编译后端
+//!
+//! 没有引入 criterion，每个用例预热后重复执行，输出最快和平均耗时。
+
+use std::time::{Duration, Instant};
+
+use oxygen::leb::encode_leb_u32 as leb_u32;
+use oxygen::runtime::config::{Engine, RuntimeConfig};
+use oxygen::runtime::decoder::{WasmModule, WasmValue};
+
+fn section(id: u8, items: &[Vec<u8>], out: &mut Vec<u8>) {
+    let mut content = vec![];
+    leb_u32(items.len() as u32, &mut content);
+    items.iter().for_each(|item| content.extend(item));
+    out.push(id);
+    leb_u32(content.len() as u32, out);
+    out.extend(content);
+}
+
+fn body(locals: &[u8], code: &[u8]) -> Vec<u8> {
+    let mut body = locals.to_vec();
+    body.extend(code);
+    body.push(0x0b);
+    let mut buf = vec![];
+    leb_u32(body.len() as u32, &mut buf);
+    buf.extend(body);
+    buf
+}
+
+/// func 0: 递归 fib(n)，func 1: 循环累加 n..1，签名均为 [i32] -> [i32]
+fn module() -> Vec<u8> {
+    let fib: &[u8] = &[
+        0x20, 0x00, 0x41, 0x02, 0x48, 0x04, 0x7f, 0x20, 0x00, 0x05, 0x20, 0x00, 0x41, 0x01, 0x6b,
+        0x10, 0x00, 0x20, 0x00, 0x41, 0x02, 0x6b, 0x10, 0x00, 0x6a, 0x0b,
+    ];
+    let sum: &[u8] = &[
+        0x02, 0x40, 0x03, 0x40, 0x20, 0x00, 0x45, 0x0d, 0x01, 0x20, 0x01, 0x20, 0x00, 0x6a, 0x21,
+        0x01, 0x20, 0x00, 0x41, 0x01, 0x6b, 0x21, 0x00, 0x0c, 0x00, 0x0b, 0x0b, 0x20, 0x01,
+    ];
+    let mut buf = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    section(1, &[vec![0x60, 0x01, 0x7f, 0x01, 0x7f]], &mut buf);
+    section(3, &[vec![0x00], vec![0x00]], &mut buf);
+    section(
+        10,
+        &[body(&[0x00], fib), body(&[0x01, 0x01, 0x7f], sum)],
+        &mut buf,
+    );
+    buf
+}
+
+fn configs() -> Vec<(&'static str, RuntimeConfig)> {
+    vec![
+        ("Match", RuntimeConfig::default().engine(Engine::Match)),
+        (
+            "Threaded",
+            RuntimeConfig::default().engine(Engine::Threaded),
+        ),
+        ("Untyped", RuntimeConfig::default().untyped_stack(true)),
+        #[cfg(feature = "jit")]
+        ("Jit", RuntimeConfig::default().engine(Engine::Jit)),
+    ]
+}
+
+fn bench(name: &str, label: &str, config: RuntimeConfig, func: usize, arg: i32) {
+    let mut wasm = WasmModule::default(module());
+    wasm.config = config;
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+
+    let mut run = || {
+        wasm.sp = 0;
+        wasm.fp = 0;
+        wasm.stack_check();
+        wasm.sp += 1;
+        wasm.stack[wasm.sp] = WasmValue::I32(arg);
+        let start = Instant::now();
+        wasm.call(func).unwrap();
+        start.elapsed()
+    };
+    run();
+    let samples = (0..10).map(|_| run()).collect::<Vec<_>>();
+    let best = samples.iter().min().unwrap();
+    let mean = samples.iter().sum::<Duration>() / samples.len() as u32;
+    println!("{name:<12} {label:<10} best {best:>10.2?}  mean {mean:>10.2?}");
+}
+
+fn main() {
+    for (label, config) in configs() {
+        bench("fib(25)", label, config, 0, 25);
+    }
+    for (label, config) in configs() {
+        bench("sum(1M)", label, config, 1, 1_000_000);
+    }
+}
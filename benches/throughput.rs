@@ -0,0 +1,281 @@
+//! 解码吞吐、实例化耗时和解释器吞吐的基线：`cargo bench --bench throughput`，
+//! 加上 `--features jit` 时同时测量 Cranelift 编译后端
+//!
+//! 用例是 fib、sha256 和以拷贝内存为主的 memcpy，由 wat 源码编译得到。
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use oxygen::runtime::config::{Engine, RuntimeConfig};
+use oxygen::runtime::decoder::{WasmModule, WasmValue};
+use oxygen::runtime::wat;
+
+const FIB: &str = r#"(module
+  (func $fib (export "fib") (param $n i32) (result i32)
+    (if (result i32) (i32.lt_s (local.get $n) (i32.const 2))
+      (then (local.get $n))
+      (else
+        (i32.add
+          (call $fib (i32.sub (local.get $n) (i32.const 1)))
+          (call $fib (i32.sub (local.get $n) (i32.const 2))))))))"#;
+
+/// 以 8 字节为单位把 [0, len) 拷贝到 [len, 2 * len)，重复 times 次
+const MEMCPY: &str = r#"(module
+  (memory (export "memory") 2)
+  (func (export "memcpy") (param $len i32) (param $times i32) (result i32)
+    (local $i i32)
+    (block $done
+      (loop $repeat
+        (br_if $done (i32.eqz (local.get $times)))
+        (local.set $i (i32.const 0))
+        (block $copied
+          (loop $copy
+            (br_if $copied (i32.ge_u (local.get $i) (local.get $len)))
+            (i64.store
+              (i32.add (local.get $len) (local.get $i))
+              (i64.load (local.get $i)))
+            (local.set $i (i32.add (local.get $i) (i32.const 8)))
+            (br $copy)))
+        (local.set $times (i32.sub (local.get $times) (i32.const 1)))
+        (br $repeat)))
+    (local.get $i)))"#;
+
+/// SHA-256 的轮常量
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// 对 1024 开始的 blocks 个 64 字节分组做 SHA-256 压缩，返回 H0；
+/// 0 存放轮常量，256 存放消息扩展 W，512 存放状态 H
+fn sha256() -> String {
+    let k = K
+        .iter()
+        .flat_map(|k| k.to_le_bytes())
+        .map(|b| format!("\\{b:02x}"))
+        .collect::<String>();
+    let h = [
+        0x6a09e667u32,
+        0xbb67ae85,
+        0x3c6ef372,
+        0xa54ff53a,
+        0x510e527f,
+        0x9b05688c,
+        0x1f83d9ab,
+        0x5be0cd19,
+    ]
+    .iter()
+    .flat_map(|h| h.to_le_bytes())
+    .map(|b| format!("\\{b:02x}"))
+    .collect::<String>();
+    format!(
+        r#"(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{k}")
+  (data (i32.const 512) "{h}")
+  (func $rotr (param $x i32) (param $n i32) (result i32)
+    (i32.or
+      (i32.shr_u (local.get $x) (local.get $n))
+      (i32.shl (local.get $x) (i32.sub (i32.const 32) (local.get $n)))))
+  (func $be (param $p i32) (result i32)
+    (i32.or
+      (i32.or
+        (i32.shl (i32.load8_u (local.get $p)) (i32.const 24))
+        (i32.shl (i32.load8_u offset=1 (local.get $p)) (i32.const 16)))
+      (i32.or
+        (i32.shl (i32.load8_u offset=2 (local.get $p)) (i32.const 8))
+        (i32.load8_u offset=3 (local.get $p)))))
+  (func (export "sha256") (param $blocks i32) (result i32)
+    (local $p i32) (local $i i32) (local $x i32) (local $t1 i32) (local $t2 i32)
+    (local $a i32) (local $b i32) (local $c i32) (local $d i32)
+    (local $e i32) (local $f i32) (local $g i32) (local $h i32)
+    (local.set $p (i32.const 1024))
+    (block $done
+      (loop $block
+        (br_if $done (i32.eqz (local.get $blocks)))
+        (local.set $i (i32.const 0))
+        (loop $load
+          (i32.store offset=256
+            (i32.shl (local.get $i) (i32.const 2))
+            (call $be (i32.add (local.get $p) (i32.shl (local.get $i) (i32.const 2)))))
+          (local.set $i (i32.add (local.get $i) (i32.const 1)))
+          (br_if $load (i32.lt_u (local.get $i) (i32.const 16))))
+        (loop $extend
+          (local.set $x (i32.load offset=248 (i32.shl (local.get $i) (i32.const 2))))
+          (local.set $t1
+            (i32.xor
+              (i32.xor (call $rotr (local.get $x) (i32.const 17)) (call $rotr (local.get $x) (i32.const 19)))
+              (i32.shr_u (local.get $x) (i32.const 10))))
+          (local.set $x (i32.load offset=196 (i32.shl (local.get $i) (i32.const 2))))
+          (local.set $t2
+            (i32.xor
+              (i32.xor (call $rotr (local.get $x) (i32.const 7)) (call $rotr (local.get $x) (i32.const 18)))
+              (i32.shr_u (local.get $x) (i32.const 3))))
+          (i32.store offset=256
+            (i32.shl (local.get $i) (i32.const 2))
+            (i32.add
+              (i32.add (local.get $t1) (i32.load offset=228 (i32.shl (local.get $i) (i32.const 2))))
+              (i32.add (local.get $t2) (i32.load offset=192 (i32.shl (local.get $i) (i32.const 2))))))
+          (local.set $i (i32.add (local.get $i) (i32.const 1)))
+          (br_if $extend (i32.lt_u (local.get $i) (i32.const 64))))
+        (local.set $a (i32.load offset=512 (i32.const 0)))
+        (local.set $b (i32.load offset=516 (i32.const 0)))
+        (local.set $c (i32.load offset=520 (i32.const 0)))
+        (local.set $d (i32.load offset=524 (i32.const 0)))
+        (local.set $e (i32.load offset=528 (i32.const 0)))
+        (local.set $f (i32.load offset=532 (i32.const 0)))
+        (local.set $g (i32.load offset=536 (i32.const 0)))
+        (local.set $h (i32.load offset=540 (i32.const 0)))
+        (local.set $i (i32.const 0))
+        (loop $round
+          (local.set $t1
+            (i32.add
+              (i32.add
+                (local.get $h)
+                (i32.xor
+                  (i32.xor (call $rotr (local.get $e) (i32.const 6)) (call $rotr (local.get $e) (i32.const 11)))
+                  (call $rotr (local.get $e) (i32.const 25))))
+              (i32.add
+                (i32.xor
+                  (i32.and (local.get $e) (local.get $f))
+                  (i32.and (i32.xor (local.get $e) (i32.const -1)) (local.get $g)))
+                (i32.add
+                  (i32.load (i32.shl (local.get $i) (i32.const 2)))
+                  (i32.load offset=256 (i32.shl (local.get $i) (i32.const 2)))))))
+          (local.set $t2
+            (i32.add
+              (i32.xor
+                (i32.xor (call $rotr (local.get $a) (i32.const 2)) (call $rotr (local.get $a) (i32.const 13)))
+                (call $rotr (local.get $a) (i32.const 22)))
+              (i32.xor
+                (i32.xor
+                  (i32.and (local.get $a) (local.get $b))
+                  (i32.and (local.get $a) (local.get $c)))
+                (i32.and (local.get $b) (local.get $c)))))
+          (local.set $h (local.get $g))
+          (local.set $g (local.get $f))
+          (local.set $f (local.get $e))
+          (local.set $e (i32.add (local.get $d) (local.get $t1)))
+          (local.set $d (local.get $c))
+          (local.set $c (local.get $b))
+          (local.set $b (local.get $a))
+          (local.set $a (i32.add (local.get $t1) (local.get $t2)))
+          (local.set $i (i32.add (local.get $i) (i32.const 1)))
+          (br_if $round (i32.lt_u (local.get $i) (i32.const 64))))
+        (i32.store offset=512 (i32.const 0) (i32.add (i32.load offset=512 (i32.const 0)) (local.get $a)))
+        (i32.store offset=516 (i32.const 0) (i32.add (i32.load offset=516 (i32.const 0)) (local.get $b)))
+        (i32.store offset=520 (i32.const 0) (i32.add (i32.load offset=520 (i32.const 0)) (local.get $c)))
+        (i32.store offset=524 (i32.const 0) (i32.add (i32.load offset=524 (i32.const 0)) (local.get $d)))
+        (i32.store offset=528 (i32.const 0) (i32.add (i32.load offset=528 (i32.const 0)) (local.get $e)))
+        (i32.store offset=532 (i32.const 0) (i32.add (i32.load offset=532 (i32.const 0)) (local.get $f)))
+        (i32.store offset=536 (i32.const 0) (i32.add (i32.load offset=536 (i32.const 0)) (local.get $g)))
+        (i32.store offset=540 (i32.const 0) (i32.add (i32.load offset=540 (i32.const 0)) (local.get $h)))
+        (local.set $p (i32.add (local.get $p) (i32.const 64)))
+        (local.set $blocks (i32.sub (local.get $blocks) (i32.const 1)))
+        (br $block)))
+    (i32.load offset=512 (i32.const 0))))"#
+    )
+}
+
+fn modules() -> Vec<(&'static str, Arc<[u8]>)> {
+    vec![
+        ("fib", wat::compile(FIB).unwrap().into()),
+        ("sha256", wat::compile(&sha256()).unwrap().into()),
+        ("memcpy", wat::compile(MEMCPY).unwrap().into()),
+    ]
+}
+
+fn configs() -> Vec<(&'static str, RuntimeConfig)> {
+    vec![
+        ("Match", RuntimeConfig::default().engine(Engine::Match)),
+        (
+            "Threaded",
+            RuntimeConfig::default().engine(Engine::Threaded),
+        ),
+        ("Untyped", RuntimeConfig::default().untyped_stack(true)),
+        #[cfg(feature = "jit")]
+        ("Jit", RuntimeConfig::default().engine(Engine::Jit)),
+    ]
+}
+
+fn decoded(raw: &Arc<[u8]>, config: RuntimeConfig) -> WasmModule {
+    let mut wasm = WasmModule::default(raw.clone());
+    wasm.config = config;
+    wasm.decode().unwrap();
+    wasm
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    let mut modules = modules();
+    if let Ok(raw) = std::fs::read("examples/oxygen.wasm") {
+        modules.push(("oxygen.wasm", raw.into()));
+    }
+    for (name, raw) in &modules {
+        group.throughput(Throughput::Bytes(raw.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), raw, |b, raw| {
+            b.iter(|| decoded(raw, RuntimeConfig::default()))
+        });
+    }
+    group.finish();
+}
+
+fn instantiate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("instantiate");
+    for (name, raw) in &modules() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), raw, |b, raw| {
+            b.iter_batched(
+                || decoded(raw, RuntimeConfig::default()),
+                |mut wasm| {
+                    wasm.instance(None).unwrap();
+                    wasm
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// name 为导出函数，bytes 为每次调用处理的字节数，为 0 时不统计吞吐
+fn execute(c: &mut Criterion, module: &str, args: &[WasmValue], bytes: u64) {
+    let (_, raw) = modules().into_iter().find(|(n, _)| *n == module).unwrap();
+    let mut group = c.benchmark_group(format!("execute/{module}{args:?}"));
+    if bytes > 0 {
+        group.throughput(Throughput::Bytes(bytes));
+    }
+    let mut expected = None;
+    for (label, config) in configs() {
+        let mut wasm = decoded(&raw, config);
+        wasm.instance(None).unwrap();
+        // 各引擎的结果必须一致，否则比较耗时没有意义
+        let results = wasm.invoke(module, args).unwrap();
+        assert_eq!(
+            *expected.get_or_insert_with(|| results.clone()),
+            results,
+            "{label}"
+        );
+        group.bench_function(label, |b| b.iter(|| wasm.invoke(module, args).unwrap()));
+    }
+    group.finish();
+}
+
+fn interpret(c: &mut Criterion) {
+    execute(c, "fib", &[WasmValue::I32(20)], 0);
+    execute(c, "sha256", &[WasmValue::I32(16)], 16 * 64);
+    execute(
+        c,
+        "memcpy",
+        &[WasmValue::I32(32 * 1024), WasmValue::I32(4)],
+        4 * 32 * 1024,
+    );
+}
+
+criterion_group!(benches, decode, instantiate, interpret);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use std::{env, fs};
+
+/// 头文件生成在 OUT_DIR 中，不改动源码目录；设置 OXYGEN_UPDATE_HEADER 时复制到 include/oxygen.h。
+/// 检入的头文件与生成结果不一致时 `test_header` 失败
+fn main() {
+    let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out = format!("{}/oxygen.h", env::var("OUT_DIR").unwrap());
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=OXYGEN_UPDATE_HEADER");
+    let config = cbindgen::Config::from_file(format!("{dir}/cbindgen.toml")).unwrap();
+    cbindgen::Builder::new()
+        .with_crate(&dir)
+        .with_config(config)
+        .generate()
+        .expect("failed to generate oxygen.h")
+        .write_to_file(&out);
+    if env::var_os("OXYGEN_UPDATE_HEADER").is_some() {
+        fs::copy(&out, format!("{dir}/include/oxygen.h"))
+            .expect("failed to update include/oxygen.h");
+    }
+}
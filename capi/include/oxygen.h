@@ -0,0 +1,253 @@
+#ifndef OXYGEN_H
+#define OXYGEN_H
+
+/* 由 cbindgen 根据 capi/src/lib.rs 生成，不要手动修改 */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * `oxygen_linker_define_memory` 的 max 为此值时内存没有上限
+ */
+#define OXYGEN_NO_MAXIMUM UINT32_MAX
+
+typedef enum OxygenValKind {
+  OXYGEN_VAL_KIND_I32,
+  OXYGEN_VAL_KIND_I64,
+  OXYGEN_VAL_KIND_F32,
+  OXYGEN_VAL_KIND_F64,
+  OXYGEN_VAL_KIND_V128,
+  OXYGEN_VAL_KIND_FUNC_REF,
+  OXYGEN_VAL_KIND_EXTERN_REF,
+} OxygenValKind;
+
+/**
+ * 模块的实例，由 `oxygen_module_instantiate` 或 `oxygen_linker_instantiate` 创建；
+ * 宿主函数的 caller 也是这个类型，借用调用它的实例
+ */
+typedef struct OxygenInstance OxygenInstance;
+
+/**
+ * 导入项的集合，由 `oxygen_linker_new` 创建
+ */
+typedef struct OxygenLinker OxygenLinker;
+
+/**
+ * 解码后的模块，由 `oxygen_module_load` 创建
+ */
+typedef struct OxygenModule OxygenModule;
+
+typedef union OxygenValUnion {
+  int32_t i32;
+  int64_t i64;
+  float f32;
+  double f64;
+  /**
+   * 小端序
+   */
+  uint8_t v128[16];
+  /**
+   * 函数索引，空引用为 SIZE_MAX
+   */
+  size_t funcref;
+  /**
+   * 空引用为 SIZE_MAX
+   */
+  size_t externref;
+} OxygenValUnion;
+
+/**
+ * 参数和返回值，kind 决定 of 中有效的字段
+ */
+typedef struct OxygenVal {
+  enum OxygenValKind kind;
+  union OxygenValUnion of;
+} OxygenVal;
+
+/**
+ * 由 C 实现的宿主函数。args 和 results 的个数与定义时的签名一致，results 预先填好
+ * 对应类型的零值，只需要写入 of；成功时返回 NULL，否则返回 trap 的信息（返回后即被复制）。
+ * caller 为调用它的实例，在回调返回前可以传给 `oxygen_instance_memory_*` 读写内存，
+ * 不能释放，也不能用 `oxygen_instance_invoke` 再次调用 wasm 函数。
+ *
+ * 同一个宿主函数被所有导入它的实例共享，这些实例可能在不同的线程上同时执行，
+ * 所以回调和 env 必须是线程安全的：回调可能在多个线程上同时以同一个 env 调用
+ */
+typedef const char *(*OxygenHostFunc)(void *env,
+                                      struct OxygenInstance *caller,
+                                      const struct OxygenVal *args,
+                                      size_t nargs,
+                                      struct OxygenVal *results,
+                                      size_t nresults);
+
+/**
+ * 宿主函数被释放（所有定义了它的 linker 和导入它的实例都已释放）时以 env 调用，
+ * 调用所在的线程为最后释放它的线程
+ */
+typedef void (*OxygenFinalizer)(void *env);
+
+/**
+ * 当前线程上最近一次失败的错误信息，没有时返回 NULL；
+ * 字符串在本线程下一次失败之前有效
+ */
+const char *oxygen_last_error(void);
+
+/**
+ * 解码 bytes 开始的 len 个字节，失败时返回 NULL
+ *
+ * # Safety
+ *
+ * bytes 指向至少 len 个可读的字节
+ */
+struct OxygenModule *oxygen_module_load(const uint8_t *bytes, size_t len);
+
+/**
+ * # Safety
+ *
+ * module 由 `oxygen_module_load` 返回且没有被释放，或为 NULL
+ */
+void oxygen_module_delete(struct OxygenModule *module);
+
+/**
+ * 创建 module 的一个新实例并执行 start 函数，失败时返回 NULL；
+ * 实例之间互相独立，module 可以在之后释放。module 有导入时使用 `oxygen_linker_instantiate`
+ *
+ * # Safety
+ *
+ * module 由 `oxygen_module_load` 返回且没有被释放
+ */
+struct OxygenInstance *oxygen_module_instantiate(const struct OxygenModule *module);
+
+/**
+ * # Safety
+ *
+ * instance 由 `oxygen_module_instantiate` 或 `oxygen_linker_instantiate` 返回且没有被释放，
+ * 或为 NULL
+ */
+void oxygen_instance_delete(struct OxygenInstance *instance);
+
+/**
+ * 以 nargs 个参数 args 调用导出函数 name，返回值写入 results；
+ * nresults 必须等于函数的返回值个数。trap 或参数不匹配时返回 false
+ *
+ * # Safety
+ *
+ * instance 有效，name 为 NUL 结尾的 UTF-8 字符串，args 和 results 分别指向
+ * nargs 和 nresults 个 `OxygenVal`（个数为 0 时可以为 NULL）
+ */
+bool oxygen_instance_invoke(struct OxygenInstance *instance,
+                            const char *name,
+                            const struct OxygenVal *args,
+                            size_t nargs,
+                            struct OxygenVal *results,
+                            size_t nresults);
+
+struct OxygenLinker *oxygen_linker_new(void);
+
+/**
+ * 已经实例化的实例不受影响
+ *
+ * # Safety
+ *
+ * linker 由 `oxygen_linker_new` 返回且没有被释放，或为 NULL
+ */
+void oxygen_linker_delete(struct OxygenLinker *linker);
+
+/**
+ * 定义宿主函数 `module::name`，参数和返回值的类型分别为 params 和 results 中的 nparams、
+ * nresults 个。失败时（例如同名的项已经存在）返回 false，这时 finalizer 立即被调用
+ *
+ * # Safety
+ *
+ * linker 有效，module 和 name 为 NUL 结尾的 UTF-8 字符串，params 和 results 分别指向
+ * nparams 和 nresults 个 `OxygenValKind`（个数为 0 时可以为 NULL）；func 和 env 是线程安全的，
+ * func 可以在任何调用导入它的实例的线程上以 env 调用，包括多个线程同时调用，
+ * finalizer 可以在任意线程上以 env 调用
+ */
+bool oxygen_linker_define_func(struct OxygenLinker *linker,
+                               const char *module,
+                               const char *name,
+                               const enum OxygenValKind *params,
+                               size_t nparams,
+                               const enum OxygenValKind *results,
+                               size_t nresults,
+                               OxygenHostFunc func,
+                               void *env,
+                               OxygenFinalizer finalizer);
+
+/**
+ * 定义内存 `module::name`，每个导入它的实例各自创建一块 min 页的内存；
+ * max 为 `OXYGEN_NO_MAXIMUM` 时没有上限
+ *
+ * # Safety
+ *
+ * linker 有效，module 和 name 为 NUL 结尾的 UTF-8 字符串
+ */
+bool oxygen_linker_define_memory(struct OxygenLinker *linker,
+                                 const char *module,
+                                 const char *name,
+                                 uint32_t min,
+                                 uint32_t max);
+
+/**
+ * 定义全局变量 `module::name`，类型为 value.kind，每个导入它的实例各自保存一份
+ *
+ * # Safety
+ *
+ * linker 有效，module 和 name 为 NUL 结尾的 UTF-8 字符串
+ */
+bool oxygen_linker_define_global(struct OxygenLinker *linker,
+                                 const char *module,
+                                 const char *name,
+                                 struct OxygenVal value,
+                                 bool mutability);
+
+/**
+ * 以 linker 中的定义满足 module 的导入，创建新实例并执行 start 函数；
+ * 导入缺失或类型不匹配时返回 NULL。linker 可以继续用于其他实例
+ *
+ * # Safety
+ *
+ * linker 有效，module 由 `oxygen_module_load` 返回且没有被释放
+ */
+struct OxygenInstance *oxygen_linker_instantiate(const struct OxygenLinker *linker,
+                                                 const struct OxygenModule *module);
+
+/**
+ * 第 0 个内存的起始地址，长度写入 len；没有内存时返回 NULL。
+ * 内存增长后地址可能改变，需要重新获取
+ *
+ * # Safety
+ *
+ * instance 有效，len 指向可写的 size_t
+ */
+uint8_t *oxygen_instance_memory_data(struct OxygenInstance *instance, size_t *len);
+
+/**
+ * 把第 0 个内存 offset 开始的 len 个字节复制到 buf，越界时返回 false
+ *
+ * # Safety
+ *
+ * instance 有效，buf 指向至少 len 个可写的字节
+ */
+bool oxygen_instance_memory_read(const struct OxygenInstance *instance,
+                                 size_t offset,
+                                 uint8_t *buf,
+                                 size_t len);
+
+/**
+ * 把 buf 中的 len 个字节写到第 0 个内存的 offset，越界时返回 false
+ *
+ * # Safety
+ *
+ * instance 有效，buf 指向至少 len 个可读的字节
+ */
+bool oxygen_instance_memory_write(struct OxygenInstance *instance,
+                                  size_t offset,
+                                  const uint8_t *buf,
+                                  size_t len);
+
+#endif  /* OXYGEN_H */
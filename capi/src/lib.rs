@@ -0,0 +1,915 @@
+//! oxygen 的 C API，头文件为 include/oxygen.h
+//!
+//! 失败的函数返回 NULL 或 false，错误信息由 `oxygen_last_error` 取得；wasm 中的 panic
+//! 同样转为错误，不会越过 C 边界。带导入的模块先在 `OxygenLinker` 中定义导入的函数、
+//! 内存和全局变量，再用 `oxygen_linker_instantiate` 实例化。
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::{ptr, slice};
+
+use anyhow::{anyhow, bail, ensure};
+use oxygen::runtime::decoder::{WasmModule, WasmValue, NULL_REF};
+use oxygen::runtime::host::{HostExport, HostModule};
+use oxygen::runtime::linker::Linker;
+use oxygen::runtime::minimize::panic_message;
+use oxygen::runtime::section::typings::ValueType;
+use oxygen::runtime::trap::Trap;
+
+/// 解码后的模块，由 `oxygen_module_load` 创建
+pub struct OxygenModule(WasmModule);
+
+/// 模块的实例，由 `oxygen_module_instantiate` 或 `oxygen_linker_instantiate` 创建；
+/// 宿主函数的 caller 也是这个类型，借用调用它的实例
+pub struct OxygenInstance(InstanceKind);
+
+enum InstanceKind {
+    Owned(Box<WasmModule>),
+    Caller(*mut WasmModule),
+}
+
+impl OxygenInstance {
+    unsafe fn wasm<'a>(instance: *mut OxygenInstance) -> anyhow::Result<&'a mut WasmModule> {
+        let instance = instance
+            .as_mut()
+            .ok_or_else(|| anyhow!("instance is NULL"))?;
+        Ok(match &mut instance.0 {
+            InstanceKind::Owned(wasm) => wasm,
+            InstanceKind::Caller(wasm) => &mut **wasm,
+        })
+    }
+}
+
+/// 导入项的集合，由 `oxygen_linker_new` 创建
+pub struct OxygenLinker(Linker);
+
+/// `oxygen_linker_define_memory` 的 max 为此值时内存没有上限
+pub const OXYGEN_NO_MAXIMUM: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OxygenValKind {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union OxygenValUnion {
+    pub i32: i32,
+    pub i64: i64,
+    pub f32: f32,
+    pub f64: f64,
+    /// 小端序
+    pub v128: [u8; 16],
+    /// 函数索引，空引用为 SIZE_MAX
+    pub funcref: usize,
+    /// 空引用为 SIZE_MAX
+    pub externref: usize,
+}
+
+/// 参数和返回值，kind 决定 of 中有效的字段
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct OxygenVal {
+    pub kind: OxygenValKind,
+    pub of: OxygenValUnion,
+}
+
+impl From<OxygenVal> for WasmValue {
+    fn from(val: OxygenVal) -> Self {
+        // SAFETY: 由 kind 选择字段，所有字段都是 Copy 的整数或浮点数
+        unsafe {
+            match val.kind {
+                OxygenValKind::I32 => WasmValue::I32(val.of.i32),
+                OxygenValKind::I64 => WasmValue::I64(val.of.i64),
+                OxygenValKind::F32 => WasmValue::F32(val.of.f32),
+                OxygenValKind::F64 => WasmValue::F64(val.of.f64),
+                OxygenValKind::V128 => WasmValue::V128(i128::from_le_bytes(val.of.v128)),
+                OxygenValKind::FuncRef => WasmValue::FuncRef(val.of.funcref),
+                OxygenValKind::ExternRef => WasmValue::ExternRef(val.of.externref),
+            }
+        }
+    }
+}
+
+impl From<OxygenValKind> for ValueType {
+    fn from(kind: OxygenValKind) -> Self {
+        match kind {
+            OxygenValKind::I32 => ValueType::I32,
+            OxygenValKind::I64 => ValueType::I64,
+            OxygenValKind::F32 => ValueType::F32,
+            OxygenValKind::F64 => ValueType::F64,
+            OxygenValKind::V128 => ValueType::V128,
+            OxygenValKind::FuncRef => ValueType::FuncRef,
+            OxygenValKind::ExternRef => ValueType::ExternRef,
+        }
+    }
+}
+
+impl OxygenVal {
+    /// kind 类型的零值，引用为空引用
+    fn zero(kind: OxygenValKind) -> Self {
+        let of = match kind {
+            OxygenValKind::I32 => OxygenValUnion { i32: 0 },
+            OxygenValKind::I64 => OxygenValUnion { i64: 0 },
+            OxygenValKind::F32 => OxygenValUnion { f32: 0.0 },
+            OxygenValKind::F64 => OxygenValUnion { f64: 0.0 },
+            OxygenValKind::V128 => OxygenValUnion { v128: [0; 16] },
+            OxygenValKind::FuncRef => OxygenValUnion { funcref: NULL_REF },
+            OxygenValKind::ExternRef => OxygenValUnion {
+                externref: NULL_REF,
+            },
+        };
+        Self { kind, of }
+    }
+}
+
+impl TryFrom<WasmValue> for OxygenVal {
+    type Error = anyhow::Error;
+
+    fn try_from(value: WasmValue) -> anyhow::Result<Self> {
+        let (kind, of) = match value {
+            WasmValue::I32(i32) => (OxygenValKind::I32, OxygenValUnion { i32 }),
+            WasmValue::I64(i64) => (OxygenValKind::I64, OxygenValUnion { i64 }),
+            WasmValue::F32(f32) => (OxygenValKind::F32, OxygenValUnion { f32 }),
+            WasmValue::F64(f64) => (OxygenValKind::F64, OxygenValUnion { f64 }),
+            WasmValue::V128(v) => (
+                OxygenValKind::V128,
+                OxygenValUnion {
+                    v128: v.to_le_bytes(),
+                },
+            ),
+            WasmValue::FuncRef(funcref) => (OxygenValKind::FuncRef, OxygenValUnion { funcref }),
+            WasmValue::ExternRef(externref) => {
+                (OxygenValKind::ExternRef, OxygenValUnion { externref })
+            }
+            WasmValue::NOP => bail!("value has no type"),
+        };
+        Ok(Self { kind, of })
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String) {
+    // 信息中的 NUL 会截断 C 字符串，替换掉
+    let message = CString::new(message.replace('\0', "\\0")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// 执行 f，出错或 panic 时记录错误并返回 default
+fn guard<T>(default: T, f: impl FnOnce() -> anyhow::Result<T>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            set_error(format!("{err:#}"));
+            default
+        }
+        Err(payload) => {
+            set_error(format!("panic: {}", panic_message(payload)));
+            default
+        }
+    }
+}
+
+/// NUL 结尾的 UTF-8 字符串
+unsafe fn c_str<'a>(ptr: *const c_char, what: &str) -> anyhow::Result<&'a str> {
+    ensure!(!ptr.is_null(), "{what} is NULL");
+    Ok(CStr::from_ptr(ptr).to_str()?)
+}
+
+/// 当前线程上最近一次失败的错误信息，没有时返回 NULL；
+/// 字符串在本线程下一次失败之前有效
+#[no_mangle]
+pub extern "C" fn oxygen_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |msg| msg.as_ptr())
+    })
+}
+
+/// 解码 bytes 开始的 len 个字节，失败时返回 NULL
+///
+/// # Safety
+///
+/// bytes 指向至少 len 个可读的字节
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_module_load(bytes: *const u8, len: usize) -> *mut OxygenModule {
+    guard(ptr::null_mut(), || {
+        ensure!(!bytes.is_null(), "bytes is NULL");
+        let buf = slice::from_raw_parts(bytes, len).to_vec();
+        let mut wasm = WasmModule::default(buf);
+        wasm.decode()?;
+        Ok(Box::into_raw(Box::new(OxygenModule(wasm))))
+    })
+}
+
+/// # Safety
+///
+/// module 由 `oxygen_module_load` 返回且没有被释放，或为 NULL
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_module_delete(module: *mut OxygenModule) {
+    if !module.is_null() {
+        drop(Box::from_raw(module));
+    }
+}
+
+/// 创建 module 的一个新实例并执行 start 函数，失败时返回 NULL；
+/// 实例之间互相独立，module 可以在之后释放。module 有导入时使用 `oxygen_linker_instantiate`
+///
+/// # Safety
+///
+/// module 由 `oxygen_module_load` 返回且没有被释放
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_module_instantiate(
+    module: *const OxygenModule,
+) -> *mut OxygenInstance {
+    guard(ptr::null_mut(), || instantiate(&Linker::new(), module))
+}
+
+unsafe fn instantiate(
+    linker: &Linker,
+    module: *const OxygenModule,
+) -> anyhow::Result<*mut OxygenInstance> {
+    let module = &module.as_ref().ok_or_else(|| anyhow!("module is NULL"))?.0;
+    let mut wasm = WasmModule::default(module.raw.clone());
+    wasm.config = module.config.clone();
+    wasm.decode()?;
+    linker.instantiate(&mut wasm)?;
+    let instance = OxygenInstance(InstanceKind::Owned(Box::new(wasm)));
+    Ok(Box::into_raw(Box::new(instance)))
+}
+
+/// # Safety
+///
+/// instance 由 `oxygen_module_instantiate` 或 `oxygen_linker_instantiate` 返回且没有被释放，
+/// 或为 NULL
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_instance_delete(instance: *mut OxygenInstance) {
+    if !instance.is_null() {
+        drop(Box::from_raw(instance));
+    }
+}
+
+/// 以 nargs 个参数 args 调用导出函数 name，返回值写入 results；
+/// nresults 必须等于函数的返回值个数。trap 或参数不匹配时返回 false
+///
+/// # Safety
+///
+/// instance 有效，name 为 NUL 结尾的 UTF-8 字符串，args 和 results 分别指向
+/// nargs 和 nresults 个 `OxygenVal`（个数为 0 时可以为 NULL）
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_instance_invoke(
+    instance: *mut OxygenInstance,
+    name: *const c_char,
+    args: *const OxygenVal,
+    nargs: usize,
+    results: *mut OxygenVal,
+    nresults: usize,
+) -> bool {
+    guard(false, || {
+        if let Some(OxygenInstance(InstanceKind::Caller(_))) = instance.as_ref() {
+            bail!("cannot invoke functions of the caller of a host function");
+        }
+        let wasm = OxygenInstance::wasm(instance)?;
+        let name = c_str(name, "name")?;
+        let args = match nargs {
+            0 => vec![],
+            n => slice::from_raw_parts(args, n)
+                .iter()
+                .map(|&arg| arg.into())
+                .collect(),
+        };
+        let values = wasm.invoke(name, &args)?;
+        ensure!(
+            values.len() == nresults,
+            "`{name}` returns {} values, but space for {nresults} was given",
+            values.len()
+        );
+        for (i, value) in values.into_iter().enumerate() {
+            results.add(i).write(value.try_into()?);
+        }
+        Ok(true)
+    })
+}
+
+/// 由 C 实现的宿主函数。args 和 results 的个数与定义时的签名一致，results 预先填好
+/// 对应类型的零值，只需要写入 of；成功时返回 NULL，否则返回 trap 的信息（返回后即被复制）。
+/// caller 为调用它的实例，在回调返回前可以传给 `oxygen_instance_memory_*` 读写内存，
+/// 不能释放，也不能用 `oxygen_instance_invoke` 再次调用 wasm 函数。
+///
+/// 同一个宿主函数被所有导入它的实例共享，这些实例可能在不同的线程上同时执行，
+/// 所以回调和 env 必须是线程安全的：回调可能在多个线程上同时以同一个 env 调用
+pub type OxygenHostFunc = Option<
+    unsafe extern "C" fn(
+        env: *mut c_void,
+        caller: *mut OxygenInstance,
+        args: *const OxygenVal,
+        nargs: usize,
+        results: *mut OxygenVal,
+        nresults: usize,
+    ) -> *const c_char,
+>;
+
+/// 宿主函数被释放（所有定义了它的 linker 和导入它的实例都已释放）时以 env 调用，
+/// 调用所在的线程为最后释放它的线程
+pub type OxygenFinalizer = Option<unsafe extern "C" fn(env: *mut c_void)>;
+
+/// `oxygen_linker_define_func` 定义的函数，作为只有一个导出的 `HostModule` 注册到 linker
+#[derive(Debug)]
+struct CHostFunc {
+    module: String,
+    export: String,
+    params: Vec<OxygenValKind>,
+    results: Vec<OxygenValKind>,
+    callback: unsafe extern "C" fn(
+        *mut c_void,
+        *mut OxygenInstance,
+        *const OxygenVal,
+        usize,
+        *mut OxygenVal,
+        usize,
+    ) -> *const c_char,
+    env: *mut c_void,
+    finalizer: OxygenFinalizer,
+}
+
+// SAFETY: `HostModule` 要求 Send + Sync，linker 以 Arc 共享同一个 CHostFunc 给所有导入它的
+// 实例，这些实例可以在不同的线程上同时执行。除了 env 以外的字段都是只读的，callback 和
+// finalizer 是普通的函数指针；env 只被原样传给它们，Rust 侧从不解引用。因此线程安全完全取决于
+// C 侧的回调和 env，`OxygenHostFunc` 和 `oxygen_linker_define_func` 的文档（也就是生成的头文件）
+// 要求调用方保证回调可以在多个线程上同时以 env 调用，finalizer 可以在任意线程上调用
+unsafe impl Send for CHostFunc {}
+unsafe impl Sync for CHostFunc {}
+
+impl Drop for CHostFunc {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer {
+            // SAFETY: 由定义时的调用方保证 finalizer 可以以 env 调用
+            unsafe { finalizer(self.env) }
+        }
+    }
+}
+
+impl HostModule for CHostFunc {
+    fn name(&self) -> &str {
+        &self.module
+    }
+
+    fn exports(&self) -> Vec<HostExport> {
+        let types = |kinds: &[OxygenValKind]| -> Vec<ValueType> {
+            kinds.iter().map(|&kind| kind.into()).collect()
+        };
+        vec![HostExport::new(
+            &self.export,
+            &types(&self.params),
+            &types(&self.results),
+        )]
+    }
+
+    fn call(
+        &self,
+        wasm: &mut WasmModule,
+        _: usize,
+        args: &[WasmValue],
+    ) -> Result<Vec<WasmValue>, Trap> {
+        let host = |message: String| Trap::Host { message };
+        let args = args
+            .iter()
+            .map(|&arg| OxygenVal::try_from(arg))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|err| host(format!("{err:#}")))?;
+        let mut results: Vec<_> = self
+            .results
+            .iter()
+            .map(|&kind| OxygenVal::zero(kind))
+            .collect();
+        let mut caller = OxygenInstance(InstanceKind::Caller(wasm));
+        // SAFETY: args 和 results 的长度与传入的个数一致，caller 只在回调期间使用
+        let message = unsafe {
+            (self.callback)(
+                self.env,
+                &mut caller,
+                args.as_ptr(),
+                args.len(),
+                results.as_mut_ptr(),
+                results.len(),
+            )
+        };
+        if !message.is_null() {
+            // SAFETY: 回调返回的非 NULL 指针为 NUL 结尾的字符串
+            let message = unsafe { CStr::from_ptr(message) };
+            return Err(host(message.to_string_lossy().into_owned()));
+        }
+        for (result, &kind) in results.iter().zip(&self.results) {
+            if result.kind != kind {
+                return Err(host(format!(
+                    "`{}::{}` returned {:?}, expected {kind:?}",
+                    self.module, self.export, result.kind
+                )));
+            }
+        }
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn oxygen_linker_new() -> *mut OxygenLinker {
+    Box::into_raw(Box::new(OxygenLinker(Linker::new())))
+}
+
+/// 已经实例化的实例不受影响
+///
+/// # Safety
+///
+/// linker 由 `oxygen_linker_new` 返回且没有被释放，或为 NULL
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_linker_delete(linker: *mut OxygenLinker) {
+    if !linker.is_null() {
+        drop(Box::from_raw(linker));
+    }
+}
+
+unsafe fn define<'a>(
+    linker: *mut OxygenLinker,
+    module: *const c_char,
+    name: *const c_char,
+) -> anyhow::Result<(&'a mut Linker, &'a str, &'a str)> {
+    let linker = &mut linker.as_mut().ok_or_else(|| anyhow!("linker is NULL"))?.0;
+    Ok((linker, c_str(module, "module")?, c_str(name, "name")?))
+}
+
+/// 定义宿主函数 `module::name`，参数和返回值的类型分别为 params 和 results 中的 nparams、
+/// nresults 个。失败时（例如同名的项已经存在）返回 false，这时 finalizer 立即被调用
+///
+/// # Safety
+///
+/// linker 有效，module 和 name 为 NUL 结尾的 UTF-8 字符串，params 和 results 分别指向
+/// nparams 和 nresults 个 `OxygenValKind`（个数为 0 时可以为 NULL）；func 和 env 是线程安全的，
+/// func 可以在任何调用导入它的实例的线程上以 env 调用，包括多个线程同时调用，
+/// finalizer 可以在任意线程上以 env 调用
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_linker_define_func(
+    linker: *mut OxygenLinker,
+    module: *const c_char,
+    name: *const c_char,
+    params: *const OxygenValKind,
+    nparams: usize,
+    results: *const OxygenValKind,
+    nresults: usize,
+    func: OxygenHostFunc,
+    env: *mut c_void,
+    finalizer: OxygenFinalizer,
+) -> bool {
+    guard(false, || {
+        let kinds = |kinds: *const OxygenValKind, n: usize| match n {
+            0 => vec![],
+            n => slice::from_raw_parts(kinds, n).to_vec(),
+        };
+        let Some(callback) = func else {
+            if let Some(finalizer) = finalizer {
+                finalizer(env);
+            }
+            bail!("func is NULL");
+        };
+        // 之后失败时由 drop 调用 finalizer
+        let mut func = CHostFunc {
+            module: String::new(),
+            export: String::new(),
+            params: kinds(params, nparams),
+            results: kinds(results, nresults),
+            callback,
+            env,
+            finalizer,
+        };
+        let (linker, module, name) = define(linker, module, name)?;
+        func.module = module.to_string();
+        func.export = name.to_string();
+        linker.define_shared_host_module(Arc::new(func))?;
+        Ok(true)
+    })
+}
+
+/// 定义内存 `module::name`，每个导入它的实例各自创建一块 min 页的内存；
+/// max 为 `OXYGEN_NO_MAXIMUM` 时没有上限
+///
+/// # Safety
+///
+/// linker 有效，module 和 name 为 NUL 结尾的 UTF-8 字符串
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_linker_define_memory(
+    linker: *mut OxygenLinker,
+    module: *const c_char,
+    name: *const c_char,
+    min: u32,
+    max: u32,
+) -> bool {
+    guard(false, || {
+        let (linker, module, name) = define(linker, module, name)?;
+        let max = (max != OXYGEN_NO_MAXIMUM).then_some(max);
+        linker.define_memory(module, name, min, max)?;
+        Ok(true)
+    })
+}
+
+/// 定义全局变量 `module::name`，类型为 value.kind，每个导入它的实例各自保存一份
+///
+/// # Safety
+///
+/// linker 有效，module 和 name 为 NUL 结尾的 UTF-8 字符串
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_linker_define_global(
+    linker: *mut OxygenLinker,
+    module: *const c_char,
+    name: *const c_char,
+    value: OxygenVal,
+    mutability: bool,
+) -> bool {
+    guard(false, || {
+        let (linker, module, name) = define(linker, module, name)?;
+        linker.define_global(module, name, value.into(), mutability)?;
+        Ok(true)
+    })
+}
+
+/// 以 linker 中的定义满足 module 的导入，创建新实例并执行 start 函数；
+/// 导入缺失或类型不匹配时返回 NULL。linker 可以继续用于其他实例
+///
+/// # Safety
+///
+/// linker 有效，module 由 `oxygen_module_load` 返回且没有被释放
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_linker_instantiate(
+    linker: *const OxygenLinker,
+    module: *const OxygenModule,
+) -> *mut OxygenInstance {
+    guard(ptr::null_mut(), || {
+        let linker = &linker.as_ref().ok_or_else(|| anyhow!("linker is NULL"))?.0;
+        instantiate(linker, module)
+    })
+}
+
+/// 第 0 个内存的起始地址，长度写入 len；没有内存时返回 NULL。
+/// 内存增长后地址可能改变，需要重新获取
+///
+/// # Safety
+///
+/// instance 有效，len 指向可写的 size_t
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_instance_memory_data(
+    instance: *mut OxygenInstance,
+    len: *mut usize,
+) -> *mut u8 {
+    guard(ptr::null_mut(), || {
+        let wasm = OxygenInstance::wasm(instance)?;
+        let mem = wasm
+            .mem
+            .first_mut()
+            .ok_or_else(|| anyhow!("instance has no memory"))?;
+        if !len.is_null() {
+            len.write(mem.len());
+        }
+        Ok(mem.as_mut_ptr())
+    })
+}
+
+/// 把第 0 个内存 offset 开始的 len 个字节复制到 buf，越界时返回 false
+///
+/// # Safety
+///
+/// instance 有效，buf 指向至少 len 个可写的字节
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_instance_memory_read(
+    instance: *const OxygenInstance,
+    offset: usize,
+    buf: *mut u8,
+    len: usize,
+) -> bool {
+    guard(false, || {
+        let wasm = OxygenInstance::wasm(instance.cast_mut())?;
+        let mem = wasm
+            .mem
+            .first()
+            .ok_or_else(|| anyhow!("instance has no memory"))?;
+        let src = offset
+            .checked_add(len)
+            .and_then(|end| mem.get(offset..end))
+            .ok_or_else(|| anyhow!("out of bounds memory access"))?;
+        ptr::copy_nonoverlapping(src.as_ptr(), buf, len);
+        Ok(true)
+    })
+}
+
+/// 把 buf 中的 len 个字节写到第 0 个内存的 offset，越界时返回 false
+///
+/// # Safety
+///
+/// instance 有效，buf 指向至少 len 个可读的字节
+#[no_mangle]
+pub unsafe extern "C" fn oxygen_instance_memory_write(
+    instance: *mut OxygenInstance,
+    offset: usize,
+    buf: *const u8,
+    len: usize,
+) -> bool {
+    guard(false, || {
+        let wasm = OxygenInstance::wasm(instance)?;
+        let mem = wasm
+            .mem
+            .first_mut()
+            .ok_or_else(|| anyhow!("instance has no memory"))?;
+        let dst = offset
+            .checked_add(len)
+            .and_then(|end| mem.get_mut(offset..end))
+            .ok_or_else(|| anyhow!("out of bounds memory access"))?;
+        ptr::copy_nonoverlapping(buf, dst.as_mut_ptr(), len);
+        Ok(true)
+    })
+}
+
+#[test]
+fn test_capi() {
+    use oxygen::runtime::wat;
+
+    let i32 = |v: i32| OxygenVal {
+        kind: OxygenValKind::I32,
+        of: OxygenValUnion { i32: v },
+    };
+    let last_error = || unsafe { CStr::from_ptr(oxygen_last_error()).to_str().unwrap() };
+    let src = r#"(module
+      (memory (export "memory") 1)
+      (data (i32.const 8) "hi")
+      (func (export "add") (param i32 i32) (result i32) (i32.add (local.get 0) (local.get 1)))
+      (func (export "peek") (param i32) (result i32) (i32.load8_u (local.get 0)))
+      (func (export "boom") (unreachable)))"#;
+    let buf = wat::compile(src).unwrap();
+    unsafe {
+        assert!(oxygen_module_load(buf.as_ptr(), buf.len() - 1).is_null());
+        assert!(!last_error().is_empty());
+
+        let module = oxygen_module_load(buf.as_ptr(), buf.len());
+        assert!(!module.is_null());
+        let instance = oxygen_module_instantiate(module);
+        oxygen_module_delete(module);
+        assert!(!instance.is_null());
+
+        let mut results = [i32(0)];
+        let args = [i32(2), i32(3)];
+        assert!(oxygen_instance_invoke(
+            instance,
+            c"add".as_ptr(),
+            args.as_ptr(),
+            2,
+            results.as_mut_ptr(),
+            1
+        ));
+        assert_eq!(WasmValue::from(results[0]), WasmValue::I32(5));
+        assert!(!oxygen_instance_invoke(
+            instance,
+            c"add".as_ptr(),
+            args.as_ptr(),
+            2,
+            ptr::null_mut(),
+            0
+        ));
+        assert!(
+            last_error().contains("returns 1 values"),
+            "{}",
+            last_error()
+        );
+        assert!(!oxygen_instance_invoke(
+            instance,
+            c"boom".as_ptr(),
+            ptr::null(),
+            0,
+            ptr::null_mut(),
+            0
+        ));
+        assert!(last_error().contains("unreachable"), "{}", last_error());
+
+        let mut len = 0;
+        let data = oxygen_instance_memory_data(instance, &mut len);
+        assert_eq!(len, 65536);
+        assert_eq!(*data.add(8), b'h');
+        let mut bytes = [0; 2];
+        assert!(oxygen_instance_memory_read(
+            instance,
+            8,
+            bytes.as_mut_ptr(),
+            2
+        ));
+        assert_eq!(&bytes, b"hi");
+        assert!(oxygen_instance_memory_write(instance, 0, b"!".as_ptr(), 1));
+        let args = [i32(0)];
+        assert!(oxygen_instance_invoke(
+            instance,
+            c"peek".as_ptr(),
+            args.as_ptr(),
+            1,
+            results.as_mut_ptr(),
+            1
+        ));
+        assert_eq!(WasmValue::from(results[0]), WasmValue::I32(b'!' as i32));
+        assert!(!oxygen_instance_memory_read(
+            instance,
+            65535,
+            bytes.as_mut_ptr(),
+            2
+        ));
+        oxygen_instance_delete(instance);
+    }
+}
+
+#[test]
+fn test_header() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/oxygen.h"));
+    let checked_in = include_str!("../include/oxygen.h");
+    assert!(
+        generated == checked_in,
+        "include/oxygen.h is out of date, run `OXYGEN_UPDATE_HEADER=1 cargo build -p oxygen-capi`"
+    );
+}
+
+#[test]
+fn test_capi_linker() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use oxygen::runtime::wat;
+
+    /// env 为调用次数；把参数指向的两个字节作为 i32 返回，参数为负数时 trap
+    unsafe extern "C" fn peek(
+        env: *mut c_void,
+        caller: *mut OxygenInstance,
+        args: *const OxygenVal,
+        _: usize,
+        results: *mut OxygenVal,
+        _: usize,
+    ) -> *const c_char {
+        (*(env as *const AtomicUsize)).fetch_add(1, Ordering::SeqCst);
+        let addr = (*args).of.i32;
+        if addr < 0 {
+            return c"negative address".as_ptr();
+        }
+        let mut bytes = [0; 2];
+        if !oxygen_instance_memory_read(caller, addr as usize, bytes.as_mut_ptr(), 2) {
+            return c"out of bounds".as_ptr();
+        }
+        (*results).of.i32 = u16::from_le_bytes(bytes) as i32;
+        ptr::null()
+    }
+    unsafe extern "C" fn reenter(
+        _: *mut c_void,
+        caller: *mut OxygenInstance,
+        _: *const OxygenVal,
+        _: usize,
+        _: *mut OxygenVal,
+        _: usize,
+    ) -> *const c_char {
+        match oxygen_instance_invoke(caller, c"run".as_ptr(), ptr::null(), 0, ptr::null_mut(), 0) {
+            true => ptr::null(),
+            false => oxygen_last_error(),
+        }
+    }
+    static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+    unsafe extern "C" fn finalize(env: *mut c_void) {
+        FINALIZED.fetch_add(1, Ordering::SeqCst);
+        drop(Box::from_raw(env as *mut AtomicUsize));
+    }
+
+    let i32 = |v: i32| OxygenVal {
+        kind: OxygenValKind::I32,
+        of: OxygenValUnion { i32: v },
+    };
+    let last_error = || unsafe { CStr::from_ptr(oxygen_last_error()).to_str().unwrap() };
+    let src = r#"(module
+      (import "env" "peek" (func $peek (param i32) (result i32)))
+      (import "env" "reenter" (func $reenter))
+      (import "env" "memory" (memory 1))
+      (import "env" "base" (global $base i32))
+      (data (i32.const 16) "\01\02")
+      (func (export "run") (param i32) (result i32)
+        (call $peek (i32.add (global.get $base) (local.get 0))))
+      (func (export "reenter") (call $reenter)))"#;
+    let buf = wat::compile(src).unwrap();
+    unsafe {
+        let module = oxygen_module_load(buf.as_ptr(), buf.len());
+        assert!(oxygen_module_instantiate(module).is_null());
+
+        let linker = oxygen_linker_new();
+        let calls = Box::into_raw(Box::new(AtomicUsize::new(0)));
+        let kinds = [OxygenValKind::I32];
+        let define_peek = || {
+            oxygen_linker_define_func(
+                linker,
+                c"env".as_ptr(),
+                c"peek".as_ptr(),
+                kinds.as_ptr(),
+                1,
+                kinds.as_ptr(),
+                1,
+                Some(peek),
+                calls as *mut c_void,
+                Some(finalize),
+            )
+        };
+        assert!(define_peek());
+        assert!(oxygen_linker_define_func(
+            linker,
+            c"env".as_ptr(),
+            c"reenter".as_ptr(),
+            ptr::null(),
+            0,
+            ptr::null(),
+            0,
+            Some(reenter),
+            ptr::null_mut(),
+            None,
+        ));
+        assert!(oxygen_linker_define_memory(
+            linker,
+            c"env".as_ptr(),
+            c"memory".as_ptr(),
+            1,
+            OXYGEN_NO_MAXIMUM
+        ));
+        // 缺少 env::base
+        assert!(oxygen_linker_instantiate(linker, module).is_null());
+        assert!(last_error().contains("base"), "{}", last_error());
+        assert!(oxygen_linker_define_global(
+            linker,
+            c"env".as_ptr(),
+            c"base".as_ptr(),
+            i32(8),
+            false
+        ));
+        let instance = oxygen_linker_instantiate(linker, module);
+        assert!(!instance.is_null(), "{}", last_error());
+        oxygen_module_delete(module);
+
+        let mut results = [i32(0)];
+        assert!(oxygen_instance_invoke(
+            instance,
+            c"run".as_ptr(),
+            [i32(8)].as_ptr(),
+            1,
+            results.as_mut_ptr(),
+            1
+        ));
+        assert_eq!(WasmValue::from(results[0]), WasmValue::I32(0x0201));
+        assert!(!oxygen_instance_invoke(
+            instance,
+            c"run".as_ptr(),
+            [i32(-9)].as_ptr(),
+            1,
+            results.as_mut_ptr(),
+            1
+        ));
+        assert!(
+            last_error().contains("negative address"),
+            "{}",
+            last_error()
+        );
+        assert_eq!((*calls).load(Ordering::SeqCst), 2);
+        assert!(!oxygen_instance_invoke(
+            instance,
+            c"reenter".as_ptr(),
+            ptr::null(),
+            0,
+            ptr::null_mut(),
+            0
+        ));
+        assert!(last_error().contains("cannot invoke"), "{}", last_error());
+
+        // 重复定义时立即调用 finalizer，已定义的函数在 linker 和实例都释放后才调用
+        let calls = Box::into_raw(Box::new(AtomicUsize::new(0)));
+        let define_again = oxygen_linker_define_func(
+            linker,
+            c"env".as_ptr(),
+            c"peek".as_ptr(),
+            kinds.as_ptr(),
+            1,
+            kinds.as_ptr(),
+            1,
+            Some(peek),
+            calls as *mut c_void,
+            Some(finalize),
+        );
+        assert!(!define_again);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+        oxygen_linker_delete(linker);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+        oxygen_instance_delete(instance);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 2);
+    }
+}
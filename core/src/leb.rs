@@ -0,0 +1,260 @@
+use std::fmt;
+
+/// LEB128 解码错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LebError {
+    /// 超过最大字节数，或最后一个字节中有超出位宽的位
+    Overflow,
+    /// 缓冲区结束时仍有后续字节
+    Unterminated,
+}
+
+impl fmt::Display for LebError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LebError::Overflow => write!(f, "integer too large"),
+            LebError::Unterminated => write!(f, "unexpected end of LEB128 integer"),
+        }
+    }
+}
+
+impl std::error::Error for LebError {}
+
+/// 编码占用的字节数，必须在 max 个字节内结束
+pub fn leb_encode_len(buf: &[u8], max: usize) -> Result<usize, LebError> {
+    match buf.iter().take(max).position(|byte| byte & 0x80 == 0) {
+        Some(end) => Ok(end + 1),
+        None if buf.len() >= max => Err(LebError::Overflow),
+        None => Err(LebError::Unterminated),
+    }
+}
+
+/// 按位宽 bits 解码无符号整数，最后一个字节中超出位宽的位必须为 0
+fn decode_unsigned(buf: &[u8], bits: u32) -> Result<(u64, usize), LebError> {
+    let max = bits.div_ceil(7) as usize;
+    let length = leb_encode_len(buf, max)?;
+    let mut r = 0u64;
+    for (i, byte) in buf[..length].iter().enumerate() {
+        let byte = (byte & 0b0111_1111) as u64;
+        let shift = 7 * i as u32;
+        if i == max - 1 && byte >> (bits - shift) != 0 {
+            return Err(LebError::Overflow);
+        }
+        r |= byte << shift;
+    }
+    Ok((r, length))
+}
+
+/// 按位宽 bits 解码有符号整数，最后一个字节中超出位宽的位必须是符号位的扩展
+fn decode_signed(buf: &[u8], bits: u32) -> Result<(i64, usize), LebError> {
+    let max = bits.div_ceil(7) as usize;
+    let length = leb_encode_len(buf, max)?;
+    let mut r = 0i64;
+    let mut shift = 0;
+    for (i, byte) in buf[..length].iter().enumerate() {
+        let byte = byte & 0b0111_1111;
+        if i == max - 1 {
+            let rest = byte >> (bits - shift - 1);
+            if rest != 0 && rest != 0b0111_1111 >> (bits - shift - 1) {
+                return Err(LebError::Overflow);
+            }
+        }
+        r |= (byte as i64) << shift;
+        shift += 7;
+    }
+    // 符号位为 1 时将高位全部补全为 1
+    if shift < 64 && buf[length - 1] & 0b0100_0000 != 0 {
+        r |= -1 << shift;
+    }
+    Ok((r, length))
+}
+
+/// LEB128（Little Endian Base 128） 变长编码格式目的是节约空间
+/// 对于 32 位整数，编码后可能是 1 到 5 个字节
+/// 对于 64 位整数，编码后可能是 1 到 10 个字节
+/// 越小的整数，编码后占用的字节数就越小
+///
+/// https://en.wikipedia.org/wiki/LEB128#Decode_unsigned_integer
+///
+/// 针对无符号整数的 LEB128 编码特点：
+/// 1. 采用小端编码方式，即低位字节在前，高位字节在后
+/// 2. 采用 128 进制，每 7 个比特为一组，由一个字节的后 7 位承载，空出来的最高位是标记位，1 表示后面还有后续字节，0 表示没有
+/// 例如：LEB128 编码为 11100101 10001110 00100110，解码为 000 0100110 0001110 1100101
+/// 1100101 0001110 0100110
+/// 000 0100110 0001110 1100101
+/// 0000_1001 1000_0111 0110_0101
+/// 0110_0101 1000_0111 0000_1001
+/// 01100101 10000111 00001001
+/// 624485
+/// 注：0x80 -- 10000000    0x7f -- 01111111
+///
+/// 针对有符号整数的 LEB128 编码，与上面无符号的完全相同，
+/// 只有最后一个字节的第二高位是符号位，如果是 1，表示这是一个负数，需将高位全部补全为 1，如果是 0，表示这是一个正数，需将高位全部补全为 0
+pub fn decode_leb_i32(buf: &[u8]) -> Result<(i32, usize), LebError> {
+    let (r, length) = decode_signed(buf, 32)?;
+    Ok((r as i32, length))
+}
+
+pub fn decode_leb_i64(buf: &[u8]) -> Result<(i64, usize), LebError> {
+    decode_signed(buf, 64)
+}
+
+pub fn decode_leb_u32(buf: &[u8]) -> Result<(u32, usize), LebError> {
+    let (r, length) = decode_unsigned(buf, 32)?;
+    Ok((r as u32, length))
+}
+
+pub fn decode_leb_u64(buf: &[u8]) -> Result<(u64, usize), LebError> {
+    decode_unsigned(buf, 64)
+}
+
+/// 以最少的字节编码无符号整数
+pub fn encode_leb_u64(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+pub fn encode_leb_u32(value: u32, out: &mut Vec<u8>) {
+    encode_leb_u64(value as u64, out)
+}
+
+/// 以最少的字节编码有符号整数，剩余的高位与最后一个字节的符号位一致时结束
+pub fn encode_leb_i64(mut value: i64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+pub fn encode_leb_i32(value: i32, out: &mut Vec<u8>) {
+    encode_leb_i64(value as i64, out)
+}
+
+#[test]
+fn test_bit_write() {
+    let buffer: Vec<u8> = vec![0x8c, 0x80, 0x80, 0x80, 0x00];
+
+    let buf = decode_leb_u32(&buffer);
+
+    assert_eq!(buf, Ok((12, 5)));
+}
+#[test]
+fn test_decode_leb_u32() {
+    assert_eq!(decode_leb_u32(&[0xe5, 0x8e, 0x26]), Ok((624485, 3)));
+    assert_eq!(
+        decode_leb_u32(&[0xff, 0xff, 0xff, 0xff, 0x0f, 0x01]),
+        Ok((u32::MAX, 5))
+    );
+    // 第 5 个字节只能使用低 4 位
+    assert_eq!(
+        decode_leb_u32(&[0xf0, 0xff, 0xff, 0xff, 0x1f]),
+        Err(LebError::Overflow)
+    );
+    assert_eq!(
+        decode_leb_u32(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]),
+        Err(LebError::Overflow)
+    );
+    assert_eq!(decode_leb_u32(&[0x80, 0x80]), Err(LebError::Unterminated));
+    assert_eq!(decode_leb_u32(&[]), Err(LebError::Unterminated));
+}
+#[test]
+fn test_decode_leb_u64() {
+    let max = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    assert_eq!(decode_leb_u64(&max), Ok((u64::MAX, 10)));
+    let mut large = max;
+    large[9] = 0x02;
+    assert_eq!(decode_leb_u64(&large), Err(LebError::Overflow));
+    large[9] = 0x81;
+    assert_eq!(decode_leb_u64(&large), Err(LebError::Overflow));
+    assert_eq!(decode_leb_u64(&max[..9]), Err(LebError::Unterminated));
+    // 第 6 个字节对 u32 来说过长，对 u64 合法
+    let long = [0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+    assert_eq!(decode_leb_u64(&long), Ok((1 << 35, 6)));
+    assert_eq!(decode_leb_i32(&long), Err(LebError::Overflow));
+    assert_eq!(decode_leb_i64(&[0x80]), Err(LebError::Unterminated));
+}
+#[test]
+fn test_decode_leb_signed() {
+    assert_eq!(decode_leb_i32(&[0x7f]), Ok((-1, 1)));
+    assert_eq!(decode_leb_i32(&[0x3f]), Ok((63, 1)));
+    assert_eq!(decode_leb_i32(&[0xc0, 0x00]), Ok((64, 2)));
+    assert_eq!(decode_leb_i32(&[0xc0, 0xbb, 0x78]), Ok((-123456, 3)));
+    assert_eq!(decode_leb_i32(&[0x80, 0x7f]), Ok((-128, 2)));
+    assert_eq!(
+        decode_leb_i32(&[0x80, 0x80, 0x80, 0x80, 0x78]),
+        Ok((i32::MIN, 5))
+    );
+    assert_eq!(
+        decode_leb_i32(&[0xff, 0xff, 0xff, 0xff, 0x07]),
+        Ok((i32::MAX, 5))
+    );
+    // 非最短编码同样合法
+    assert_eq!(decode_leb_i32(&[0xff, 0xff, 0xff, 0xff, 0x7f]), Ok((-1, 5)));
+    // 第 5 个字节的高 3 位必须与符号位一致
+    assert_eq!(
+        decode_leb_i32(&[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        Err(LebError::Overflow)
+    );
+    assert_eq!(
+        decode_leb_i32(&[0x80, 0x80, 0x80, 0x80, 0x70]),
+        Err(LebError::Overflow)
+    );
+    let mut min = [0x80; 10];
+    min[9] = 0x7f;
+    assert_eq!(decode_leb_i64(&min), Ok((i64::MIN, 10)));
+    min[9] = 0x01;
+    assert_eq!(decode_leb_i64(&min), Err(LebError::Overflow));
+}
+
+/// 伪随机整数，覆盖各种长度的编码
+#[cfg(test)]
+fn samples() -> impl Iterator<Item = u64> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let edges = (0..64).flat_map(|bit| {
+        let v = 1u64 << bit;
+        [v, v - 1, v.wrapping_neg(), !v]
+    });
+    edges.chain(std::iter::from_fn(move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        // 随机右移使较短的编码也能出现
+        Some(state >> (state % 64))
+    }))
+}
+
+#[test]
+fn test_leb_round_trip() {
+    for v in samples().take(20000) {
+        let mut out = vec![];
+        encode_leb_u64(v, &mut out);
+        assert_eq!(decode_leb_u64(&out), Ok((v, out.len())));
+        out.clear();
+        encode_leb_i64(v as i64, &mut out);
+        assert_eq!(decode_leb_i64(&out), Ok((v as i64, out.len())));
+        out.clear();
+        encode_leb_u32(v as u32, &mut out);
+        assert_eq!(decode_leb_u32(&out), Ok((v as u32, out.len())));
+        out.clear();
+        encode_leb_i32(v as i32, &mut out);
+        assert_eq!(decode_leb_i32(&out), Ok((v as i32, out.len())));
+        // 截断任意一个字节都会得到未结束的错误
+        assert_eq!(
+            decode_leb_i32(&out[..out.len() - 1]),
+            Err(LebError::Unterminated)
+        );
+    }
+}
@@ -0,0 +1,5 @@
+//! oxygen 的解码、验证和执行。嵌入时通常只需要 `prelude`，各部分的实现见 `runtime`；
+//! 一般通过重新导出它的 `oxygen` 使用
+pub mod leb;
+pub mod prelude;
+pub mod runtime;
@@ -0,0 +1,31 @@
+//! 嵌入 oxygen 常用的类型：`use oxygen::prelude::*;`，直接依赖本 crate 时为 `oxygen_core::prelude`
+//!
+//! 模块和实例是同一个 `WasmModule`：`decode` 之后为模块，`Linker::instantiate` 之后为实例；
+//! `Store` 为 `OxygenRuntime`，保存加载的模块和运行配置。
+//!
+//! ```
+//! use oxygen_core::prelude::*;
+//!
+//! let buf = oxygen_core::runtime::wat::compile(
+//!     r#"(module (func (export "add") (param i32 i32) (result i32)
+//!          (i32.add (local.get 0) (local.get 1))))"#,
+//! )?;
+//! let mut instance = Module::default(buf);
+//! instance.decode()?;
+//! Linker::new().instantiate(&mut instance)?;
+//! let result = instance.invoke("add", &[WasmValue::I32(1), WasmValue::I32(2)])?;
+//! assert_eq!(result, [WasmValue::I32(3)]);
+//! # anyhow::Ok(())
+//! ```
+
+pub use crate::runtime::config::{Engine, Limits, RuntimeConfig};
+pub use crate::runtime::decoder::{WasmModule as Instance, WasmModule as Module};
+pub use crate::runtime::decoder::{WasmModule, WasmValue};
+pub use crate::runtime::error::{DecodeError, InstantiationError};
+pub use crate::runtime::global::SharedGlobal;
+pub use crate::runtime::linear::Memory;
+pub use crate::runtime::linker::{Extern, Func, Linker};
+pub use crate::runtime::section::typings::ValueType;
+pub use crate::runtime::trap::Trap;
+pub use crate::runtime::OxygenRuntime as Store;
+pub use crate::runtime::OxygenRuntime;
@@ -0,0 +1,690 @@
+//! 预编译模块缓存：解码和降级的结果以二进制保存，再次运行同一个模块时不用重新解码
+//!
+//! 文件格式：`\0oxc` | 格式版本 (u32 LE) | crate 版本 | 模块字节数 (u64 LE) | 模块哈希 (u64 LE) | 内容。
+//! 内容是按字段顺序编码的 serde 数据：整数为 LEB128（有符号的先 zigzag），
+//! 浮点数为小端字节，字符串、序列和 map 先写长度，enum 先写变体索引，Option 先写 0/1。
+//! 格式版本、crate 版本或模块不一致时拒绝加载。
+//!
+//! `oxygen compile` 的产物自带模块字节，可以直接运行：`\0oxy` | 模块字节数 (u64 LE) | 模块字节 | 预编译模块。
+//! 目前保存的是降级后的 IR；JIT 生成的机器码包含进程内的地址，加载时重新编译。
+
+use std::fmt::{self, Display};
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::{ser, Deserialize, Serialize};
+
+pub const MAGIC: &[u8; 4] = b"\0oxc";
+pub const ARTIFACT_MAGIC: &[u8; 4] = b"\0oxy";
+/// 内容的编码或其中的类型变化时增加
+pub const FORMAT_VERSION: u32 = 7;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// 模块字节的 FNV-1a 哈希，与平台和 Rust 版本无关
+pub fn module_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 加上文件头，module 为缓存对应的模块字节
+pub fn encode<T: Serialize>(module: &[u8], value: &T) -> Result<Vec<u8>> {
+    let mut encoder = Encoder {
+        out: MAGIC.to_vec(),
+    };
+    encoder.out.extend(FORMAT_VERSION.to_le_bytes());
+    env!("CARGO_PKG_VERSION").serialize(&mut encoder)?;
+    encoder.out.extend((module.len() as u64).to_le_bytes());
+    encoder.out.extend(module_hash(module).to_le_bytes());
+    value.serialize(&mut encoder)?;
+    Ok(encoder.out)
+}
+
+/// 检查文件头后解码内容，module 为要加载的模块字节
+pub fn decode<'de, T: Deserialize<'de>>(module: &[u8], bytes: &'de [u8]) -> Result<T> {
+    let mut decoder = Decoder { input: bytes };
+    if decoder.take(MAGIC.len())? != MAGIC {
+        return Err(Error("not an oxygen compiled module".into()));
+    }
+    let version = u32::from_le_bytes(decoder.take(4)?.try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(Error(format!(
+            "compiled module format {version} is not supported (expected {FORMAT_VERSION})"
+        )));
+    }
+    let crate_version = <&str>::deserialize(&mut decoder)?;
+    if crate_version != env!("CARGO_PKG_VERSION") {
+        return Err(Error(format!(
+            "compiled module is from oxygen {crate_version}, this is {}",
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+    let size = u64::from_le_bytes(decoder.take(8)?.try_into().unwrap());
+    let hash = u64::from_le_bytes(decoder.take(8)?.try_into().unwrap());
+    if size != module.len() as u64 || hash != module_hash(module) {
+        return Err(Error(
+            "compiled module was made from a different module".into(),
+        ));
+    }
+    let value = T::deserialize(&mut decoder)?;
+    if !decoder.input.is_empty() {
+        return Err(Error(format!(
+            "{} trailing bytes after compiled module",
+            decoder.input.len()
+        )));
+    }
+    Ok(value)
+}
+
+/// 在预编译模块前加上模块字节，得到独立的产物
+pub fn artifact(module: &[u8], compiled: &[u8]) -> Vec<u8> {
+    let mut out = ARTIFACT_MAGIC.to_vec();
+    out.extend((module.len() as u64).to_le_bytes());
+    out.extend(module);
+    out.extend(compiled);
+    out
+}
+
+pub fn is_artifact(bytes: &[u8]) -> bool {
+    bytes.starts_with(ARTIFACT_MAGIC)
+}
+
+/// 拆分产物，返回模块字节和预编译模块
+pub fn split_artifact(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+    let mut decoder = Decoder { input: bytes };
+    if decoder.take(ARTIFACT_MAGIC.len())? != ARTIFACT_MAGIC {
+        return Err(Error("not an oxygen artifact".into()));
+    }
+    let size = u64::from_le_bytes(decoder.take(8)?.try_into().unwrap());
+    let size = usize::try_from(size).map_err(|_| Error("artifact is too large".into()))?;
+    let module = decoder.take(size)?;
+    Ok((module, decoder.input))
+}
+
+struct Encoder {
+    out: Vec<u8>,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.out.push(byte);
+                return;
+            }
+            self.out.push(byte | 0x80);
+        }
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    fn len(&mut self, len: Option<usize>) -> Result<()> {
+        let len = len.ok_or_else(|| Error("sequence length is unknown".into()))?;
+        self.varint(len as u64);
+        Ok(())
+    }
+}
+
+impl ser::Serializer for &mut Encoder {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.out.push(v as u8);
+        Ok(())
+    }
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.signed(v as i64);
+        Ok(())
+    }
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.signed(v as i64);
+        Ok(())
+    }
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.signed(v as i64);
+        Ok(())
+    }
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.signed(v);
+        Ok(())
+    }
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.out.push(v);
+        Ok(())
+    }
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.varint(v as u64);
+        Ok(())
+    }
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.varint(v as u64);
+        Ok(())
+    }
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.varint(v);
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.out.extend(v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.out.extend(v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.varint(v as u64);
+        Ok(())
+    }
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.varint(v.len() as u64);
+        self.out.extend(v);
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<()> {
+        self.out.push(0);
+        Ok(())
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        idx: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.varint(idx as u64);
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        idx: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.varint(idx as u64);
+        value.serialize(self)
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        self.len(len)?;
+        Ok(self)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        idx: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.varint(idx as u64);
+        Ok(self)
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        self.len(len)?;
+        Ok(self)
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        idx: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.varint(idx as u64);
+        Ok(self)
+    }
+}
+
+/// 复合类型的各项依次编码，不需要分隔
+macro_rules! serialize_items {
+    ($($trait:ident :: $method:ident),*) => {$(
+        impl ser::$trait for &mut Encoder {
+            type Ok = ();
+            type Error = Error;
+            fn $method<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+                value.serialize(&mut **self)
+            }
+            fn end(self) -> Result<()> {
+                Ok(())
+            }
+        }
+    )*};
+}
+
+serialize_items!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl ser::SerializeMap for &mut Encoder {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Encoder {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Encoder {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct Decoder<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Decoder<'de> {
+    fn take(&mut self, len: usize) -> Result<&'de [u8]> {
+        if self.input.len() < len {
+            return Err(Error("unexpected end of compiled module".into()));
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error("integer too large".into()))
+    }
+
+    fn signed(&mut self) -> Result<i64> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        let len = self.varint()? as usize;
+        // 每项至少一个字节，防止错误的长度导致分配过多内存
+        if len > self.input.len() {
+            return Err(Error(format!("bad length {len}")));
+        }
+        Ok(len)
+    }
+
+    fn int<T: TryFrom<i64> + TryFrom<u64>>(&mut self, signed: bool) -> Result<T> {
+        let value = match signed {
+            true => T::try_from(self.signed()?).ok(),
+            false => T::try_from(self.varint()?).ok(),
+        };
+        value.ok_or_else(|| Error("integer out of range".into()))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error(
+            "compiled module format is not self-describing".into(),
+        ))
+    }
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.byte()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            b => Err(Error(format!("bad bool {b}"))),
+        }
+    }
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.int(true)?)
+    }
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(self.int(true)?)
+    }
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(self.int(true)?)
+    }
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(self.signed()?)
+    }
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.byte()?)
+    }
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(self.int(false)?)
+    }
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(self.int(false)?)
+    }
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(self.varint()?)
+    }
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = self.int::<u32>(false)?;
+        visitor.visit_char(char::from_u32(value).ok_or_else(|| Error(format!("bad char {value}")))?)
+    }
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.len()?;
+        let text = std::str::from_utf8(self.take(len)?).map_err(|err| Error(err.to_string()))?;
+        visitor.visit_borrowed_str(text)
+    }
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.len()?;
+        visitor.visit_borrowed_bytes(self.take(len)?)
+    }
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.byte()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            b => Err(Error(format!("bad option tag {b}"))),
+        }
+    }
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.len()?;
+        visitor.visit_seq(Items { decoder: self, len })
+    }
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Items { decoder: self, len })
+    }
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.len()?;
+        visitor.visit_map(Items { decoder: self, len })
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error("compiled module format has no identifiers".into()))
+    }
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error("compiled module format can't skip values".into()))
+    }
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// 序列、元组、结构体和 map 的各项
+struct Items<'a, 'de> {
+    decoder: &'a mut Decoder<'de>,
+    len: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Items<'_, 'de> {
+    type Error = Error;
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Items<'_, 'de> {
+    type Error = Error;
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.decoder)
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'de> {
+    type Error = Error;
+    type Variant = Self;
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let idx = self.int::<u32>(false)?;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(idx))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'de> {
+    type Error = Error;
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Items { decoder: self, len })
+    }
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Items {
+            decoder: self,
+            len: fields.len(),
+        })
+    }
+}
+
+#[test]
+fn test_cache_format() {
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Unit,
+        Newtype(i64),
+        Tuple(u8, f32),
+        Struct {
+            name: String,
+            tags: Option<Vec<char>>,
+        },
+    }
+
+    let value = (
+        vec![
+            Shape::Unit,
+            Shape::Newtype(-300),
+            Shape::Tuple(255, -0.5),
+            Shape::Struct {
+                name: "wasm".into(),
+                tags: Some(vec!['a', '中']),
+            },
+            Shape::Struct {
+                name: String::new(),
+                tags: None,
+            },
+        ],
+        HashMap::from([(u64::MAX, 1.5f64)]),
+        (true, i32::MIN, 7..9usize),
+    );
+    let module = b"\0asm\x01\0\0\0";
+    let bytes = encode(module, &value).unwrap();
+    assert_eq!(&bytes[..4], MAGIC);
+    assert_eq!(
+        decode::<(
+            Vec<Shape>,
+            HashMap<u64, f64>,
+            (bool, i32, std::ops::Range<usize>)
+        )>(module, &bytes)
+        .unwrap(),
+        value
+    );
+
+    // 模块、格式版本不一致或内容损坏时拒绝加载
+    let err = decode::<(Vec<Shape>,)>(b"\0asm\x01\0\0\x01", &bytes).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "compiled module was made from a different module"
+    );
+    let mut old = bytes.clone();
+    old[4] = 0;
+    assert!(decode::<Vec<Shape>>(module, &old)
+        .unwrap_err()
+        .to_string()
+        .contains("format 0"));
+    assert!(decode::<Vec<Shape>>(module, &bytes[..bytes.len() - 1]).is_err());
+    assert!(decode::<Vec<Shape>>(module, b"\0asm").is_err());
+    assert_ne!(module_hash(b"a"), module_hash(b"b"));
+
+    let art = artifact(module, &bytes);
+    assert!(is_artifact(&art) && !is_artifact(&bytes));
+    assert_eq!(split_artifact(&art).unwrap(), (&module[..], &bytes[..]));
+    assert!(split_artifact(&art[..12]).is_err());
+}
@@ -0,0 +1,329 @@
+//! 静态调用图和可达性分析：从导出函数和 start 函数出发，找出不可能被调用的函数
+//!
+//! 边有三种：call 的直接调用；函数体中的 ref.func（引用的函数可能被调用）；
+//! call_indirect / call_ref 的间接调用，目标为元素段或 ref.func 引用过、签名相同的所有函数。
+//! 模块导出了表时，元素段中的函数也是起点（宿主可以直接调用它们）。
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display};
+
+use serde_json::json;
+
+use super::decoder::WasmModule;
+use super::profile::func_names;
+use super::section::element::Element;
+use super::section::export::ExportKind;
+use super::section::import;
+use super::section::opcode::Opcode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    Call,
+    Ref,
+    Indirect,
+}
+
+impl EdgeKind {
+    fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Call => "call",
+            EdgeKind::Ref => "ref",
+            EdgeKind::Indirect => "indirect",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CallGraph {
+    /// 导入函数的个数，函数索引小于它的是导入函数
+    pub imported: usize,
+    /// 每个函数体的字节数，按函数索引（导入函数为 0）
+    pub sizes: Vec<usize>,
+    /// (调用者, 被调用者) -> 边的种类
+    pub edges: BTreeMap<(usize, usize), EdgeKind>,
+    /// 分析的起点：导出函数、start 函数和导出的表中的函数
+    pub roots: BTreeSet<usize>,
+    pub reachable: BTreeSet<usize>,
+    pub names: HashMap<usize, String>,
+}
+
+impl CallGraph {
+    pub fn new(wasm: &WasmModule) -> Self {
+        let section = &wasm.section;
+        let imported = wasm.imported_funcs();
+        let func_types = section
+            .import
+            .entries
+            .iter()
+            .filter_map(|ipt| match ipt.kind {
+                import::Kind::Func(ty) => Some(ty),
+                _ => None,
+            })
+            .chain(section.func.entries.iter().copied())
+            .collect::<Vec<_>>();
+        let signature = |ty: usize| {
+            section
+                .types
+                .entries
+                .get(ty)
+                .map(|ty| (&ty.params, &ty.results))
+        };
+
+        // 元素段中的函数和所有 ref.func 引用的函数都可能被间接调用
+        let mut elements = BTreeSet::new();
+        for element in &section.element.entries {
+            let funcs = match element {
+                Element::E0x00(k) => &k.ele.1,
+                Element::E0x01(k) | Element::E0x03(k) => &k.ele.1,
+                Element::E0x02(k) => &k.ele.3,
+                _ => continue,
+            };
+            elements.extend(funcs.iter().copied());
+        }
+        let mut address_taken = elements.clone();
+        address_taken.extend(wasm.ops.iter().filter_map(|op| match op {
+            Opcode::RefFunc(idx) => Some(*idx as usize),
+            _ => None,
+        }));
+
+        let mut sizes = vec![0; imported];
+        let mut edges = BTreeMap::new();
+        for (idx, body) in section.code.entries.iter().enumerate() {
+            let caller = imported + idx;
+            sizes.push(body.size);
+            if body.pending {
+                continue;
+            }
+            let (start, _, last) = body.code;
+            for op in &wasm.ops[start..=last] {
+                let (ty, kind) = match op {
+                    Opcode::Call(callee) => {
+                        edges.insert((caller, *callee as usize), EdgeKind::Call);
+                        continue;
+                    }
+                    Opcode::RefFunc(callee) => {
+                        edges
+                            .entry((caller, *callee as usize))
+                            .or_insert(EdgeKind::Ref);
+                        continue;
+                    }
+                    Opcode::CallIndirect(ty, _) | Opcode::CallRef(ty) => {
+                        (*ty as usize, EdgeKind::Indirect)
+                    }
+                    _ => continue,
+                };
+                for &callee in &address_taken {
+                    let matches = func_types
+                        .get(callee)
+                        .is_some_and(|&callee_ty| signature(callee_ty) == signature(ty));
+                    if matches {
+                        edges.entry((caller, callee)).or_insert(kind);
+                    }
+                }
+            }
+        }
+
+        let mut roots = section
+            .export
+            .entries
+            .iter()
+            .filter_map(|export| match export.kind {
+                ExportKind::Func(idx) => Some(idx),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        if section.start.has_start {
+            roots.insert(section.start.start_func);
+        }
+        let table_exported = section
+            .export
+            .entries
+            .iter()
+            .any(|export| matches!(export.kind, ExportKind::Table(_)));
+        if table_exported {
+            roots.extend(elements);
+        }
+
+        let mut graph = Self {
+            imported,
+            sizes,
+            edges,
+            roots,
+            reachable: BTreeSet::new(),
+            names: func_names(wasm),
+        };
+        graph.reachable = graph.reach();
+        graph
+    }
+
+    fn reach(&self) -> BTreeSet<usize> {
+        let mut reachable = BTreeSet::new();
+        let mut queue = self.roots.iter().copied().collect::<Vec<_>>();
+        while let Some(func) = queue.pop() {
+            if !reachable.insert(func) {
+                continue;
+            }
+            queue.extend(self.callees(func).map(|(callee, _)| callee));
+        }
+        reachable
+    }
+
+    /// func 可能调用（或引用）的函数
+    pub fn callees(&self, func: usize) -> impl Iterator<Item = (usize, EdgeKind)> + '_ {
+        self.edges
+            .range((func, 0)..=(func, usize::MAX))
+            .map(|(&(_, callee), &kind)| (callee, kind))
+    }
+
+    /// 不可达的函数（不包括导入函数），按函数索引
+    pub fn dead(&self) -> Vec<usize> {
+        (self.imported..self.sizes.len())
+            .filter(|func| !self.reachable.contains(func))
+            .collect()
+    }
+
+    fn label(&self, func: usize) -> String {
+        match self.names.get(&func) {
+            Some(name) => format!("func{func} ({name})"),
+            None => format!("func{func}"),
+        }
+    }
+
+    /// Graphviz 格式，不可达的函数为灰色，导入函数为方框，间接调用为虚线
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph callgraph {\n");
+        for func in 0..self.sizes.len() {
+            let mut attrs = vec![format!("label={:?}", self.label(func))];
+            if func < self.imported {
+                attrs.push("shape=box".to_string());
+            }
+            if !self.reachable.contains(&func) {
+                attrs.push("color=gray fontcolor=gray".to_string());
+            }
+            out += &format!("  f{func} [{}];\n", attrs.join(" "));
+        }
+        for (&(caller, callee), kind) in &self.edges {
+            let style = match kind {
+                EdgeKind::Call => "",
+                EdgeKind::Ref => " [style=dotted]",
+                EdgeKind::Indirect => " [style=dashed]",
+            };
+            out += &format!("  f{caller} -> f{callee}{style};\n");
+        }
+        out += "}\n";
+        out
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let dead = self.dead();
+        json!({
+            "functions": self.sizes.len(),
+            "imported": self.imported,
+            "roots": self.roots,
+            "reachable": self.reachable.len(),
+            "dead": dead
+                .iter()
+                .map(|&func| json!({
+                    "index": func,
+                    "name": self.names.get(&func),
+                    "size": self.sizes[func],
+                }))
+                .collect::<Vec<_>>(),
+            "dead_bytes": dead.iter().map(|&func| self.sizes[func]).sum::<usize>(),
+            "edges": self
+                .edges
+                .iter()
+                .map(|(&(caller, callee), kind)| json!({
+                    "caller": caller,
+                    "callee": callee,
+                    "kind": kind.name(),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl Display for CallGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dead = self.dead();
+        let bytes = dead.iter().map(|&func| self.sizes[func]).sum::<usize>();
+        let total = self.sizes.iter().sum::<usize>();
+        writeln!(
+            f,
+            "Functions: {} ({} imported), {} roots, {} reachable",
+            self.sizes.len(),
+            self.imported,
+            self.roots.len(),
+            self.reachable.len()
+        )?;
+        writeln!(
+            f,
+            "Dead: {} functions, {bytes} of {total} code bytes",
+            dead.len()
+        )?;
+        for func in dead {
+            writeln!(
+                f,
+                "  {:<32} {:>10} bytes",
+                self.label(func),
+                self.sizes[func]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_call_graph() {
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "log" (func $log (param i32)))
+          (type $unary (func (param i32) (result i32)))
+          (table 2 funcref)
+          (elem (i32.const 0) $double $noop)
+          (func $main (export "main") (param i32) (result i32)
+            (call $log (local.get 0))
+            (call_indirect (type $unary) (local.get 0) (i32.const 0)))
+          (func $double (param i32) (result i32)
+            (i32.add (local.get 0) (local.get 0)))
+          (func $noop)
+          (func $dead (param i32) (result i32)
+            (call $dead2 (local.get 0)))
+          (func $dead2 (param i32) (result i32)
+            (local.get 0)))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let graph = CallGraph::new(&wasm);
+
+    assert_eq!(graph.imported, 1);
+    assert_eq!(graph.roots, BTreeSet::from([1]));
+    let callees = graph.callees(1).collect::<Vec<_>>();
+    // 签名不同的 $noop 不是 call_indirect 的目标，元素段之外的 $dead 也不是
+    assert_eq!(callees, [(0, EdgeKind::Call), (2, EdgeKind::Indirect)]);
+    assert_eq!(graph.reachable, BTreeSet::from([0, 1, 2]));
+    assert_eq!(graph.dead(), [3, 4, 5]);
+
+    let dot = graph.to_dot();
+    assert!(dot.contains("f1 -> f2 [style=dashed];"), "{dot}");
+    assert!(dot.contains("f4 -> f5;"), "{dot}");
+    assert!(dot.contains(r#"f1 [label="func1 (main)"];"#), "{dot}");
+    let text = graph.to_string();
+    assert!(text.contains("Dead: 3 functions"), "{text}");
+    assert_eq!(graph.to_json()["dead"][0]["index"], 3);
+
+    // 导出表之后元素段中的函数都可能被宿主调用
+    let buf = wat::compile(
+        r#"(module
+          (table (export "table") 1 funcref)
+          (elem (i32.const 0) $f)
+          (func $f))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    assert!(CallGraph::new(&wasm).dead().is_empty());
+}
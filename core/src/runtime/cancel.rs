@@ -0,0 +1,75 @@
+//! 取消执行的句柄，接口与 tokio-util 的 `CancellationToken` 保持一致
+//!
+//! ```ignore
+//! let token = CancellationToken::new();
+//! wasm.config = wasm.config.clone().cancellation_token(token.clone());
+//! // 其他线程中
+//! token.cancel();
+//! // wasm.call(..) 返回 Err(Trap::Cancelled)
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    parent: Option<Arc<Inner>>,
+}
+
+impl Inner {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+}
+
+/// 可跨线程共享，clone 得到的是同一个句柄
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 取消当前句柄及其所有子句柄
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    /// 父句柄取消时子句柄同时取消，取消子句柄不影响父句柄
+    pub fn child_token(&self) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                parent: Some(self.inner.clone()),
+            }),
+        }
+    }
+}
+
+#[test]
+fn test_cancellation_token() {
+    let parent = CancellationToken::new();
+    let child = parent.child_token();
+    let other = parent.child_token();
+    assert!(!child.is_cancelled());
+
+    child.cancel();
+    assert!(child.is_cancelled());
+    assert!(!parent.is_cancelled());
+    assert!(!other.is_cancelled());
+
+    let cloned = parent.clone();
+    std::thread::spawn(move || cloned.cancel()).join().unwrap();
+    assert!(parent.is_cancelled());
+    assert!(other.is_cancelled());
+    assert!(other.child_token().is_cancelled());
+}
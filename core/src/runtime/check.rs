@@ -0,0 +1,215 @@
+//! `oxygen check`：不执行模块，判断它在给定的宿主导入下能否运行：
+//! 缺失或不匹配的导入、未实现的指令、还不能执行的提案和内存需求
+
+use std::fmt::{self, Display};
+
+use serde_json::json;
+
+use super::coverage::Coverage;
+use super::decoder::WasmModule;
+use super::error::InstantiationError;
+use super::linker::Linker;
+use super::section::import;
+use super::section::typings::Limit;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryRequirement {
+    /// 导入的内存为 `模块.名字`
+    pub import: Option<String>,
+    /// 初始页数（64 KiB）
+    pub minimum: u32,
+    pub maximum: Option<u32>,
+    /// 初始页数超出 `Limits::max_memory_pages` 时的错误
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckReport {
+    /// 缺失或类型不匹配的导入
+    pub imports: Vec<InstantiationError>,
+    pub coverage: Coverage,
+    /// 模块用到的还不能执行的提案
+    pub features: Vec<&'static str>,
+    /// 每个内存（包括导入的），按内存索引
+    pub memories: Vec<MemoryRequirement>,
+}
+
+impl CheckReport {
+    /// linker 为运行时提供的宿主导入，内存限制取模块的 `RuntimeConfig::limits`
+    pub fn new(wasm: &WasmModule, linker: &Linker) -> Self {
+        let limits = &wasm.config.limits;
+        let requirement = |import, limit: &Limit| MemoryRequirement {
+            import,
+            minimum: limit.minimum,
+            maximum: (limit.flag & 0x01 == 1).then_some(limit.maximum),
+            error: limits
+                .check_memory(limit.minimum)
+                .err()
+                .map(|err| err.to_string()),
+        };
+        let imported = wasm.section.import.entries.iter().filter_map(|ipt| {
+            let import::Kind::Memory(limit) = &ipt.kind else {
+                return None;
+            };
+            let name = format!("{}.{}", ipt.mod_name, ipt.field_name);
+            Some(requirement(Some(name), limit))
+        });
+        let defined = wasm
+            .section
+            .memory
+            .entries
+            .iter()
+            .map(|mem| requirement(None, &mem.limits));
+        Self {
+            imports: linker.check_imports(wasm),
+            coverage: Coverage::new(wasm),
+            features: wasm.required_features(),
+            memories: imported.chain(defined).collect(),
+        }
+    }
+
+    /// 初始内存的总字节数
+    pub fn memory_bytes(&self) -> u64 {
+        self.memories
+            .iter()
+            .map(|mem| mem.minimum as u64 * 65536)
+            .sum()
+    }
+
+    /// 没有发现任何会阻止模块运行的问题
+    pub fn is_ready(&self) -> bool {
+        self.imports.is_empty()
+            && self.coverage.is_complete()
+            && self.features.is_empty()
+            && self.memories.iter().all(|mem| mem.error.is_none())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "ready": self.is_ready(),
+            "imports": self.imports.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+            "unimplemented": self.coverage.to_json()["unimplemented"],
+            "features": self.features,
+            "memory": {
+                "bytes": self.memory_bytes(),
+                "memories": self
+                    .memories
+                    .iter()
+                    .map(|mem| json!({
+                        "import": mem.import,
+                        "minimum": mem.minimum,
+                        "maximum": mem.maximum,
+                        "error": mem.error,
+                    }))
+                    .collect::<Vec<_>>(),
+            },
+        })
+    }
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.imports.len() {
+            0 => writeln!(f, "Imports: ok")?,
+            n => writeln!(f, "Imports: {n} unresolved")?,
+        }
+        for err in &self.imports {
+            writeln!(f, "  {err}")?;
+        }
+
+        writeln!(f)?;
+        write!(f, "{}", self.coverage)?;
+
+        match self.features.is_empty() {
+            true => writeln!(f, "\nFeatures: none")?,
+            false => writeln!(
+                f,
+                "\nFeatures: {} (not executable)",
+                self.features.join(", ")
+            )?,
+        }
+
+        writeln!(f, "\nMemory: {} bytes initially", self.memory_bytes())?;
+        for (idx, mem) in self.memories.iter().enumerate() {
+            let name = mem.import.clone().unwrap_or_else(|| format!("memory{idx}"));
+            let maximum = mem.maximum.map_or("-".to_string(), |max| max.to_string());
+            write!(f, "  {name:<24} {:>6} .. {maximum:<6} pages", mem.minimum)?;
+            match &mem.error {
+                Some(err) => writeln!(f, "  ({err})")?,
+                None => writeln!(f)?,
+            }
+        }
+
+        match self.is_ready() {
+            true => writeln!(f, "\nready"),
+            false => writeln!(f, "\nnot ready"),
+        }
+    }
+}
+
+#[test]
+fn test_check_report() {
+    use super::config::{Limits, RuntimeConfig};
+    use super::linker::Func;
+    use super::section::typings::ValueType::I32;
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "log" (func $log (param i32)))
+      (import "env" "now" (func $now (result i64)))
+      (import "env" "memory" (memory 2 8))
+      (memory 1)
+      (func (export "main") (param i32)
+        (call $log (i32.popcnt (local.get 0)))))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.decode().unwrap();
+
+    let mut linker = Linker::new();
+    linker
+        .define("env", "log", Func::wrap(&[I32], &[I32], |_, _| vec![]))
+        .unwrap();
+    let report = CheckReport::new(&wasm, &linker);
+    assert!(!report.is_ready());
+    let imports = report
+        .imports
+        .iter()
+        .map(|err| err.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(imports.len(), 3);
+    assert!(imports[0].starts_with("incompatible import type for `env::log`"));
+    assert!(imports[1].starts_with("unknown import: `env::now`"));
+    assert!(imports[2].starts_with("unknown import: `env::memory`"));
+    assert!(report.coverage.is_complete());
+    assert!(report.features.is_empty());
+    assert_eq!(report.memory_bytes(), 3 * 65536);
+    assert_eq!(report.memories[0].import.as_deref(), Some("env.memory"));
+    assert_eq!(report.memories[0].maximum, Some(8));
+    assert_eq!(report.memories[1].maximum, None);
+    let text = report.to_string();
+    assert!(text.contains("Imports: 3 unresolved"), "{text}");
+    assert!(text.ends_with("not ready\n"), "{text}");
+    assert_eq!(report.to_json()["ready"], false);
+
+    // 所有导入都提供且指令都已实现
+    let src = r#"(module
+      (import "env" "log" (func $log (param i32)))
+      (memory 4)
+      (func (export "main") (call $log (i32.const 1))))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.config = RuntimeConfig::default().limits(Limits {
+        max_memory_pages: Some(2),
+        ..Default::default()
+    });
+    wasm.decode().unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define("env", "log", Func::wrap(&[I32], &[], |_, _| vec![]))
+        .unwrap();
+    let report = CheckReport::new(&wasm, &linker);
+    assert!(report.imports.is_empty() && report.coverage.is_complete());
+    assert_eq!(
+        report.memories[0].error.as_deref(),
+        Some("memory of 4 pages exceeds limit 2")
+    );
+    assert!(!report.is_ready());
+}
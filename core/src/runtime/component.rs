@@ -0,0 +1,139 @@
+//! 组件模型（component model）二进制的最小解析
+//!
+//! 组件与 core module 的魔数相同，版本字段的高 16 位（layer）为 1。
+//! 这里只识别组件并列出它的段，不支持组件的实例化、canonical ABI 和嵌套组件；
+//! 最常见的情况是组件只内嵌一个 core module，可以直接把它当作模块运行。
+
+use std::ops::Range;
+
+use anyhow::{bail, ensure, Context};
+
+use super::constants::MAGIC_NUMBER;
+use crate::leb;
+
+/// 版本字段中的 layer，core module 为 0
+pub const LAYER: [u8; 2] = [0x01, 0x00];
+/// 内嵌 core module 的段
+const CORE_MODULE: u8 = 0x01;
+
+/// bytes 是否为组件（魔数正确且 layer 为 1）
+pub fn is_component(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC_NUMBER) && bytes.get(6..8) == Some(&LAYER[..])
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    /// 版本字段的低 16 位
+    pub version: u16,
+    /// (段 id, 内容在组件中的范围)，按出现顺序
+    pub sections: Vec<(u8, Range<usize>)>,
+}
+
+impl Component {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Component> {
+        ensure!(is_component(bytes), "not a WebAssembly component");
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        let mut sections = vec![];
+        let mut offset = 8;
+        while offset < bytes.len() {
+            let id = bytes[offset];
+            let (size, len) = leb::decode_leb_u32(&bytes[offset + 1..])
+                .with_context(|| format!("malformed section size at offset {offset:#x}"))?;
+            let start = offset + 1 + len;
+            let end = start + size as usize;
+            ensure!(
+                end <= bytes.len(),
+                "section {id} at offset {offset:#x} is out of bounds"
+            );
+            sections.push((id, start..end));
+            offset = end;
+        }
+        Ok(Component { version, sections })
+    }
+
+    /// 内嵌的 core module 在组件中的范围，按出现顺序（不包括嵌套组件中的）
+    pub fn core_modules(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.sections
+            .iter()
+            .filter(|(id, _)| *id == CORE_MODULE)
+            .map(|(_, range)| range.clone())
+    }
+}
+
+/// 组件中唯一的 core module；没有或有多个时出错
+pub fn core_module(bytes: &[u8]) -> anyhow::Result<&[u8]> {
+    let component = Component::parse(bytes)?;
+    let modules = component.core_modules().collect::<Vec<_>>();
+    match &modules[..] {
+        [range] => Ok(&bytes[range.clone()]),
+        [] => bail!("component doesn't embed a core module"),
+        _ => bail!(
+            "component embeds {} core modules, only one is supported",
+            modules.len()
+        ),
+    }
+}
+
+/// 组件时取出其中唯一的 core module，其他二进制原样返回
+pub fn unwrap_core(bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    match is_component(&bytes) {
+        true => Ok(core_module(&bytes)?.to_vec()),
+        false => Ok(bytes),
+    }
+}
+
+#[test]
+fn test_component() {
+    use super::decoder::{WasmModule, WasmValue};
+    use super::error::{DecodeError, DecodeErrorKind};
+    use super::testing::leb_u32;
+    use super::wat;
+
+    let core =
+        wat::compile(r#"(module (func (export "answer") (result i32) (i32.const 42)))"#).unwrap();
+    let section = |id: u8, content: &[u8]| {
+        let mut buf = vec![id];
+        buf.extend(leb_u32(content.len() as u32));
+        buf.extend(content);
+        buf
+    };
+    let header = [&MAGIC_NUMBER[..], &[0x0d, 0x00], &LAYER[..]].concat();
+    let custom = section(0x00, b"\x04name");
+    let component = [&header[..], &custom, &section(0x01, &core)].concat();
+    assert!(is_component(&component));
+    assert!(!is_component(&core));
+
+    let parsed = Component::parse(&component).unwrap();
+    assert_eq!(parsed.version, 0x0d);
+    assert_eq!(
+        parsed
+            .sections
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>(),
+        [0x00, 0x01]
+    );
+    assert_eq!(core_module(&component).unwrap(), &core[..]);
+    assert_eq!(unwrap_core(core.clone()).unwrap(), core);
+
+    // 直接解码组件时给出明确的错误
+    let mut wasm = WasmModule::default(component.clone());
+    let err: DecodeError = wasm.decode().unwrap_err();
+    assert_eq!(err.kind, DecodeErrorKind::Component { version: 0x0d });
+
+    let mut rt = super::OxygenRuntime::default();
+    rt.load(component).unwrap();
+    let module = &mut rt.modes[0];
+    module.instance(None).unwrap();
+    assert_eq!(module.invoke("answer", &[]).unwrap(), [WasmValue::I32(42)]);
+
+    let twice = [&header[..], &section(0x01, &core), &section(0x01, &core)].concat();
+    let err = core_module(&twice).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "component embeds 2 core modules, only one is supported"
+    );
+    assert!(core_module(&header).is_err());
+    assert!(Component::parse(&[&header[..], &[0x01, 0x10, 0x00]].concat()).is_err());
+    assert!(Component::parse(&core).is_err());
+}
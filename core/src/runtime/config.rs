@@ -0,0 +1,262 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::ensure;
+
+use super::cancel::CancellationToken;
+use super::debug::SharedDebugger;
+use super::linear::MemoryBackend;
+use super::trace::{SharedTracer, Tracer};
+use super::wasi::WasiCtx;
+
+/// 运行时配置，由 OxygenRuntime 传递给每个加载的模块
+#[derive(Debug, Default, Clone)]
+pub struct RuntimeConfig {
+    /// 将浮点运算和 reinterpret 产生的 NaN 统一规范为 canonical NaN，
+    /// 保证不同宿主平台上的执行结果逐位一致（共识类场景需要）
+    pub canonicalize_nans: bool,
+    /// 触发后解释器在下一个检查点停止执行并返回 `Trap::Cancelled`
+    pub cancellation: Option<CancellationToken>,
+    /// 禁止执行的函数，调用时返回 `Trap::Forbidden`
+    pub denied_funcs: Vec<FuncSelector>,
+    /// 设置后 call_indirect 只能调用列表中的函数
+    pub indirect_allowlist: Option<Vec<FuncSelector>>,
+    /// 解释器的分派方式
+    pub engine: Engine,
+    /// 通过校验的函数在不带类型标记的 u64 栈上执行（类型由校验得到），
+    /// 关闭时使用带类型的 WasmValue 栈，类型错误能报告为 TypeMismatch，便于调试
+    pub untyped_stack: bool,
+    /// 解码时只读取函数体的位置和大小，函数第一次调用时再解析，
+    /// 适合只调用少数导出的大模块；`WasmModule::decode_bodies` 可以随时全部解析
+    pub lazy_decode: bool,
+    /// 线性内存的存放方式，`MemoryBackend::GuardPages` 依靠 guard page 省略访问时的边界检查
+    pub memory_backend: MemoryBackend,
+    /// 设置后每条指令执行前调用
+    pub tracer: Option<SharedTracer>,
+    /// 设置后每条指令执行前检查断点，暂停时交给调试器
+    pub debugger: Option<SharedDebugger>,
+    /// 资源限制，执行不受信任的插件时使用
+    pub limits: Limits,
+    /// WASI 宿主函数提供给 guest 的命令行参数和环境变量
+    pub wasi: WasiCtx,
+}
+
+/// 资源限制，None 表示不限制
+///
+/// 超出限制时：解码和实例化返回错误，memory.grow / table.grow 返回 -1，
+/// 调用时超出栈限制返回 `Trap::StackOverflow`。
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// 每个内存的最大页数（64 KiB）
+    pub max_memory_pages: Option<u32>,
+    /// 每个表的最大表项数
+    pub max_table_size: Option<u32>,
+    /// 值栈的最大槽数（参数、局部变量和操作数），进入函数时检查
+    pub max_value_stack: Option<usize>,
+    /// 最大调用深度
+    pub max_call_depth: Option<usize>,
+    /// 模块的最大字节数
+    pub max_module_size: Option<usize>,
+    /// 最大函数个数（包括导入函数）
+    pub max_functions: Option<usize>,
+}
+
+impl Limits {
+    /// 声明的最大页数与限制中较小的一个
+    pub(crate) fn memory_pages(&self, maximum: u32) -> u32 {
+        self.max_memory_pages
+            .map_or(maximum, |max| max.min(maximum))
+    }
+
+    pub(crate) fn table_size(&self, maximum: u32) -> u32 {
+        self.max_table_size.map_or(maximum, |max| max.min(maximum))
+    }
+
+    /// 实例化时检查内存的初始页数
+    pub(crate) fn check_memory(&self, pages: u32) -> anyhow::Result<()> {
+        if let Some(max) = self.max_memory_pages {
+            ensure!(pages <= max, "memory of {pages} pages exceeds limit {max}");
+        }
+        Ok(())
+    }
+
+    /// 实例化时检查表的初始大小
+    pub(crate) fn check_table(&self, size: u32) -> anyhow::Result<()> {
+        if let Some(max) = self.max_table_size {
+            ensure!(size <= max, "table of {size} elements exceeds limit {max}");
+        }
+        Ok(())
+    }
+}
+
+/// 执行引擎，两者执行结果一致
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    /// 对降级后的指令逐条 match
+    #[default]
+    Match,
+    /// 预先把指令解析为处理函数指针（间接线程化）
+    Threaded,
+    /// 用 Cranelift 把函数编译为本地代码，不能编译的函数由 `Match` 执行
+    #[cfg(feature = "jit")]
+    Jit,
+}
+
+/// 按函数索引或导出名指定函数，实例化时解析为函数索引
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuncSelector {
+    Index(usize),
+    Export(String),
+}
+
+impl From<usize> for FuncSelector {
+    fn from(value: usize) -> Self {
+        FuncSelector::Index(value)
+    }
+}
+
+impl From<&str> for FuncSelector {
+    fn from(value: &str) -> Self {
+        FuncSelector::Export(value.to_string())
+    }
+}
+
+impl RuntimeConfig {
+    pub fn canonicalize_nans(mut self, enable: bool) -> Self {
+        self.canonicalize_nans = enable;
+        self
+    }
+
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    pub fn deny_func(mut self, func: impl Into<FuncSelector>) -> Self {
+        self.denied_funcs.push(func.into());
+        self
+    }
+
+    pub fn allow_indirect(mut self, func: impl Into<FuncSelector>) -> Self {
+        self.indirect_allowlist
+            .get_or_insert_with(Vec::new)
+            .push(func.into());
+        self
+    }
+
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    pub fn untyped_stack(mut self, enable: bool) -> Self {
+        self.untyped_stack = enable;
+        self
+    }
+
+    pub fn lazy_decode(mut self, enable: bool) -> Self {
+        self.lazy_decode = enable;
+        self
+    }
+
+    pub fn memory_backend(mut self, backend: MemoryBackend) -> Self {
+        self.memory_backend = backend;
+        self
+    }
+
+    pub fn tracer(mut self, tracer: impl Tracer + Send + 'static) -> Self {
+        self.tracer = Some(Arc::new(Mutex::new(tracer)));
+        self
+    }
+
+    pub fn debugger(mut self, debugger: SharedDebugger) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn wasi(mut self, ctx: WasiCtx) -> Self {
+        self.wasi = ctx;
+        self
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(|t| t.is_cancelled())
+    }
+}
+
+#[test]
+fn test_limits() {
+    use super::decoder::{WasmModule, WasmValue};
+    use super::testing::{func_bytes, invoke, vec};
+    use super::trap::Trap;
+
+    // func 0: memory.grow (local.get 0)，func 1: 无限递归
+    let bodies: &[&[u8]] = &[&[0x20, 0x00, 0x40, 0x00], &[0x20, 0x00, 0x10, 0x01]];
+    let memory = |min: u8| (5, vec(&[vec![0x01, min, 0x04]]));
+    let load = |limits: Limits, min: u8| {
+        let buf = func_bytes(&[0x7f], &[0x7f], bodies, &[memory(min)]);
+        let mut wasm = WasmModule::default(buf);
+        wasm.config = RuntimeConfig::default().limits(limits);
+        wasm.decode()?;
+        wasm.instance(None)?;
+        anyhow::Ok(wasm)
+    };
+
+    let size = func_bytes(&[0x7f], &[0x7f], bodies, &[memory(1)]).len();
+    let limits = |limits: Limits| Limits {
+        max_module_size: Some(size),
+        max_functions: Some(2),
+        ..limits
+    };
+    assert!(load(limits(Limits::default()), 1).is_ok());
+    let err = load(
+        Limits {
+            max_module_size: Some(size - 1),
+            ..Default::default()
+        },
+        1,
+    );
+    assert!(err.unwrap_err().to_string().contains("module size"));
+    let err = load(
+        Limits {
+            max_functions: Some(1),
+            ..Default::default()
+        },
+        1,
+    );
+    assert!(err.unwrap_err().to_string().contains("function count"));
+
+    let pages = Limits {
+        max_memory_pages: Some(2),
+        ..Default::default()
+    };
+    assert!(load(pages.clone(), 3).is_err());
+    let mut wasm = load(pages, 1).unwrap();
+    let grow = |wasm: &mut WasmModule| invoke(wasm, 0, &[WasmValue::I32(1)]).unwrap();
+    assert_eq!(grow(&mut wasm), [WasmValue::I32(1)]);
+    assert_eq!(grow(&mut wasm), [WasmValue::I32(-1)]);
+    assert!(wasm.memory(0).unwrap().grow(1).is_err());
+
+    for untyped in [false, true] {
+        for limits in [
+            Limits {
+                max_call_depth: Some(20),
+                ..Default::default()
+            },
+            Limits {
+                max_value_stack: Some(20),
+                ..Default::default()
+            },
+        ] {
+            let mut wasm = load(limits, 1).unwrap();
+            wasm.config = wasm.config.untyped_stack(untyped);
+            let trap = invoke(&mut wasm, 1, &[WasmValue::I32(0)]).unwrap_err();
+            assert_eq!(trap, Trap::StackOverflow);
+            assert_eq!(wasm.csp, 0);
+        }
+    }
+}
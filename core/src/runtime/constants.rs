@@ -7,5 +7,7 @@ pub const CALLSTACK_SIZE: usize = 4 * 1024;
 pub const STACK_SIZE: usize = 4 * 1024;
 
 pub const MAX_BR_TABLE: usize = 4 * 1024;
+/// 解码时结构化指令的最大嵌套层数，避免畸形模块耗尽原生栈
+pub const MAX_BLOCK_DEPTH: usize = 1024;
 
 pub const PAGE_SIZE: usize = 64 * 1024;
@@ -0,0 +1,213 @@
+//! 常量表达式：全局变量的初始值、元素段和数据段的偏移量、元素段的表项
+//!
+//! 实例化时直接求值，不经过解释器：只允许 t.const、导入的不可变全局变量的 global.get、
+//! ref.null 和 ref.func，以 end 结束并且恰好留下一个值。
+//! 开启 `extended-const` feature 时还允许 i32/i64 的 add、sub 和 mul（extended-const 提案）。
+
+use anyhow::{bail, ensure};
+
+use super::decoder::{WasmModule, WasmValue, NULL_REF};
+use super::section::opcode::Opcode;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    Num(WasmValue),
+    /// 函数索引，空引用为 `NULL_REF`
+    Ref(usize),
+}
+
+impl WasmModule {
+    /// 求值 ops 中 expr 范围内的常量表达式，imported 为导入的全局变量个数
+    pub fn eval_const(
+        &self,
+        expr: &(usize, usize, usize),
+        imported: usize,
+    ) -> anyhow::Result<ConstValue> {
+        let mut stack = vec![];
+        for op in &self.ops[expr.0..=expr.2] {
+            let value = match op {
+                Opcode::I32Const(v) => ConstValue::Num(WasmValue::I32(*v)),
+                Opcode::I64Const(v) => ConstValue::Num(WasmValue::I64(*v)),
+                Opcode::F32Const(v) => ConstValue::Num(WasmValue::F32(*v)),
+                Opcode::F64Const(v) => ConstValue::Num(WasmValue::F64(*v)),
+                Opcode::GlobalGet(idx) => {
+                    let idx = *idx as usize;
+                    ensure!(
+                        idx < imported,
+                        "constant expression can only read imported globals, found global {idx}"
+                    );
+                    match self.global.get(idx) {
+                        Some(g) if g.is_mutable() => {
+                            bail!("constant expression can't read mutable global {idx}")
+                        }
+                        Some(g) => ConstValue::Num(g.get()),
+                        None => bail!("unknown global {idx}"),
+                    }
+                }
+                Opcode::RefNull(_) => ConstValue::Ref(NULL_REF),
+                Opcode::RefFunc(idx) => {
+                    let idx = *idx as usize;
+                    ensure!(idx < self.func.len(), "unknown function {idx}");
+                    ConstValue::Ref(idx)
+                }
+                #[cfg(feature = "extended-const")]
+                Opcode::I32Add
+                | Opcode::I32Sub
+                | Opcode::I32Mul
+                | Opcode::I64Add
+                | Opcode::I64Sub
+                | Opcode::I64Mul => {
+                    let (Some(b), Some(a)) = (stack.pop(), stack.pop()) else {
+                        bail!("{op:?} needs two operands in a constant expression");
+                    };
+                    ConstValue::Num(extended(op, a, b)?)
+                }
+                Opcode::End(_) => break,
+                op => bail!("{op:?} is not allowed in a constant expression"),
+            };
+            stack.push(value);
+        }
+        match stack[..] {
+            [value] => Ok(value),
+            _ => bail!(
+                "constant expression must produce one value, found {}",
+                stack.len()
+            ),
+        }
+    }
+
+    /// 元素段或数据段的偏移量，必须是 i32；what 为出错时的说明
+    pub fn eval_offset(
+        &self,
+        what: &str,
+        expr: &(usize, usize, usize),
+        imported: usize,
+    ) -> anyhow::Result<u32> {
+        match self.eval_const(expr, imported)? {
+            ConstValue::Num(WasmValue::I32(v)) => Ok(v as u32),
+            ConstValue::Num(v) => bail!("{what} offset must be i32, found {v:?}"),
+            ConstValue::Ref(_) => bail!("{what} offset must be i32, found a reference"),
+        }
+    }
+
+    /// 元素段的表项，必须是引用：ref.func、ref.null 或读取引用类型的全局变量
+    pub fn eval_ref(&self, expr: &(usize, usize, usize), imported: usize) -> anyhow::Result<usize> {
+        match self.eval_const(expr, imported)? {
+            ConstValue::Ref(idx)
+            | ConstValue::Num(WasmValue::FuncRef(idx) | WasmValue::ExternRef(idx)) => Ok(idx),
+            v => bail!("element must be a reference, found {v:?}"),
+        }
+    }
+}
+
+/// 整数运算按补码回绕
+#[cfg(feature = "extended-const")]
+fn extended(op: &Opcode, a: ConstValue, b: ConstValue) -> anyhow::Result<WasmValue> {
+    use ConstValue::Num;
+    use WasmValue::{I32, I64};
+
+    Ok(match (op, a, b) {
+        (Opcode::I32Add, Num(I32(a)), Num(I32(b))) => I32(a.wrapping_add(b)),
+        (Opcode::I32Sub, Num(I32(a)), Num(I32(b))) => I32(a.wrapping_sub(b)),
+        (Opcode::I32Mul, Num(I32(a)), Num(I32(b))) => I32(a.wrapping_mul(b)),
+        (Opcode::I64Add, Num(I64(a)), Num(I64(b))) => I64(a.wrapping_add(b)),
+        (Opcode::I64Sub, Num(I64(a)), Num(I64(b))) => I64(a.wrapping_sub(b)),
+        (Opcode::I64Mul, Num(I64(a)), Num(I64(b))) => I64(a.wrapping_mul(b)),
+        _ => bail!("{op:?} operands have the wrong type: {a:?}, {b:?}"),
+    })
+}
+
+#[test]
+fn test_const_expr() {
+    use super::decoder::Global;
+    use super::wat;
+
+    let load = |src: &str| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        wasm
+    };
+    let wasm = load(
+        r#"(module
+          (global i64 (i64.const -7))
+          (global f64 (f64.const 1.5))
+          (global (mut i32) (i32.const 2)))"#,
+    );
+    let exprs = wasm
+        .section
+        .global
+        .entries
+        .iter()
+        .map(|g| wasm.eval_const(&g.expr, 0).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exprs,
+        [
+            ConstValue::Num(WasmValue::I64(-7)),
+            ConstValue::Num(WasmValue::F64(1.5)),
+            ConstValue::Num(WasmValue::I32(2))
+        ]
+    );
+    assert!(wasm
+        .eval_offset("data", &wasm.section.global.entries[0].expr, 0)
+        .is_err());
+
+    // 只能读取导入的全局变量，不能执行其他指令
+    let mut wasm = load(
+        r#"(module
+          (import "env" "base" (global i32))
+          (global i32 (global.get 0))
+          (global i32 (global.get 1))
+          (global i32 (i32.add (i32.const 1) (i32.const 2))))"#,
+    );
+    wasm.global.push(Global::Const(WasmValue::I32(1024)));
+    let globals = &wasm.section.global.entries;
+    assert_eq!(wasm.eval_offset("data", &globals[0].expr, 1).unwrap(), 1024);
+    assert!(wasm.eval_const(&globals[1].expr, 1).is_err());
+    assert_eq!(
+        wasm.eval_const(&globals[2].expr, 1).is_ok(),
+        cfg!(feature = "extended-const")
+    );
+    wasm.global[0] = Global::Var(WasmValue::I32(1024));
+    assert!(wasm.eval_const(&globals[0].expr, 1).is_err());
+}
+
+#[cfg(feature = "extended-const")]
+#[test]
+fn test_extended_const() {
+    use super::decoder::Global;
+    use super::wat;
+
+    // LLVM 为 PIC 模块生成的数据段偏移：__memory_base + 常量
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "__memory_base" (global i32))
+          (memory 1)
+          (global i64 (i64.mul (i64.const -3) (i64.sub (i64.const 1) (i64.const 5))))
+          (global i32 (i32.add (i32.const 2147483647) (i32.const 1)))
+          (data (i32.add (global.get 0) (i32.const 16)) "hi"))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let env = [(
+        "__memory_base".to_string(),
+        super::decoder::ImportKind::Value(WasmValue::I32(1024)),
+    )];
+    let object = [("env".to_string(), env.into_iter().collect())];
+    wasm.instance(Some(object.into_iter().collect())).unwrap();
+    assert_eq!(&wasm.mem[0][1040..1042], b"hi");
+    let values = wasm.global[1..].iter().map(Global::get).collect::<Vec<_>>();
+    assert_eq!(values, [WasmValue::I64(12), WasmValue::I32(i32::MIN)]);
+
+    let wasm = {
+        let mut wasm = WasmModule::default(
+            wat::compile("(module (global i32 (i32.add (i32.const 1) (i64.const 2))))").unwrap(),
+        );
+        wasm.decode().unwrap();
+        wasm
+    };
+    assert!(wasm
+        .eval_const(&wasm.section.global.entries[0].expr, 0)
+        .is_err());
+}
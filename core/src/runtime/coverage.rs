@@ -0,0 +1,205 @@
+//! `oxygen coverage`：模块中出现的指令与解释器已实现的指令对照，
+//! 列出执行时会遇到 todo!() 的指令以及它们所在的函数
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display};
+
+use serde_json::json;
+
+use super::decoder::WasmModule;
+use super::profile::func_names;
+use super::stats::op_name;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpcodeUse {
+    pub name: String,
+    pub count: usize,
+    pub implemented: bool,
+    /// 包含该指令的函数索引（包括导入函数），从小到大
+    pub funcs: Vec<usize>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Coverage {
+    /// 未实现的在前，其次按次数从多到少
+    pub opcodes: Vec<OpcodeUse>,
+    /// 导入和导出函数的名字，见 `profile::func_names`
+    pub names: HashMap<usize, String>,
+}
+
+impl Coverage {
+    pub fn new(wasm: &WasmModule) -> Self {
+        let imported = wasm.imported_funcs();
+        let mut opcodes = BTreeMap::<String, (usize, bool, BTreeSet<usize>)>::new();
+        for (idx, body) in wasm.section.code.entries.iter().enumerate() {
+            if body.pending {
+                continue;
+            }
+            let (start, _, last) = body.code;
+            for op in &wasm.ops[start..=last] {
+                let entry = opcodes
+                    .entry(op_name(op))
+                    .or_insert_with(|| (0, op.is_implemented(), BTreeSet::new()));
+                entry.0 += 1;
+                entry.2.insert(imported + idx);
+            }
+        }
+        let mut opcodes = opcodes
+            .into_iter()
+            .map(|(name, (count, implemented, funcs))| OpcodeUse {
+                name,
+                count,
+                implemented,
+                funcs: funcs.into_iter().collect(),
+            })
+            .collect::<Vec<_>>();
+        opcodes.sort_by(|a, b| {
+            a.implemented
+                .cmp(&b.implemented)
+                .then_with(|| b.count.cmp(&a.count))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Self {
+            opcodes,
+            names: func_names(wasm),
+        }
+    }
+
+    /// 模块中出现的未实现指令
+    pub fn unimplemented(&self) -> impl Iterator<Item = &OpcodeUse> {
+        self.opcodes.iter().filter(|op| !op.implemented)
+    }
+
+    /// 模块中的指令是否全部已实现
+    pub fn is_complete(&self) -> bool {
+        self.unimplemented().next().is_none()
+    }
+
+    fn func_name(&self, idx: usize) -> String {
+        match self.names.get(&idx) {
+            Some(name) => format!("func{idx} ({name})"),
+            None => format!("func{idx}"),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let opcodes = |implemented: bool| {
+            self.opcodes
+                .iter()
+                .filter(|op| op.implemented == implemented)
+                .map(|op| {
+                    json!({
+                        "name": op.name,
+                        "count": op.count,
+                        "funcs": op
+                            .funcs
+                            .iter()
+                            .map(|&idx| json!({ "index": idx, "name": self.names.get(&idx) }))
+                            .collect::<Vec<_>>(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        json!({
+            "complete": self.is_complete(),
+            "implemented": opcodes(true),
+            "unimplemented": opcodes(false),
+        })
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let missing = self.unimplemented().count();
+        writeln!(
+            f,
+            "Opcodes: {} kinds, {} implemented, {missing} unimplemented",
+            self.opcodes.len(),
+            self.opcodes.len() - missing
+        )?;
+        if missing == 0 {
+            return Ok(());
+        }
+        writeln!(f, "\nUnimplemented:")?;
+        for op in self.unimplemented() {
+            let funcs = op
+                .funcs
+                .iter()
+                .map(|&idx| self.func_name(idx))
+                .collect::<Vec<_>>();
+            writeln!(
+                f,
+                "  {:<24} {:>10}  in {}",
+                op.name,
+                op.count,
+                funcs.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_coverage() {
+    use super::wat;
+
+    // 解释器只有 SIMD 指令没有实现，wat 不支持 SIMD，这里直接写二进制
+    let simd = [[0x15, 0x00, 0xfd, 0x0c].as_slice(), &[0; 16], &[0x1a, 0x0b]].concat(); // v128.const 0, drop
+    let buf = [
+        [
+            0x00, 0x61, 0x73, 0x6d, // magic = \0asm
+            0x01, 0x00, 0x00, 0x00, // version  = 1 (little endian)
+            //
+            0x01, 0x08, 0x02, // type section
+            0x60, 0x01, 0x7f, 0x00, // (i32) -> ()
+            0x60, 0x00, 0x00, // () -> ()
+            //
+            0x02, 0x0b, 0x01, // import section
+            0x03, 0x65, 0x6e, 0x76, 0x03, 0x6c, 0x6f, 0x67, 0x00, 0x00, // env.log: type 0
+            //
+            0x03, 0x04, 0x03, 0x01, 0x01, 0x01, // function section
+            //
+            0x07, 0x0d, 0x02, // export section
+            0x04, 0x73, 0x69, 0x6d, 0x64, 0x00, 0x01, // "simd": func 1
+            0x02, 0x6f, 0x6b, 0x00, 0x03, // "ok": func 3
+            //
+            0x0a, 0x34, 0x03, // code section
+        ]
+        .as_slice(),
+        &simd,
+        &simd,
+        &[0x06, 0x00, 0x41, 0x01, 0x10, 0x00, 0x0b], // i32.const 1, call 0
+    ]
+    .concat();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let coverage = Coverage::new(&wasm);
+
+    assert!(!coverage.is_complete());
+    let missing = coverage
+        .unimplemented()
+        .map(|op| (op.name.as_str(), op.count, op.funcs.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(missing, [("FD", 2, vec![1, 2])]);
+    assert!(coverage.opcodes[1..].iter().all(|op| op.implemented));
+
+    let text = coverage.to_string();
+    assert!(text.contains("1 unimplemented"), "{text}");
+    assert!(text.contains("func1 (simd), func2"), "{text}");
+    let json = coverage.to_json();
+    assert_eq!(json["complete"], false);
+    assert_eq!(json["unimplemented"][0]["funcs"][1]["index"], 2);
+    assert_eq!(json["unimplemented"][0]["funcs"][1]["name"], json!(null));
+
+    let buf = wat::compile(
+        r#"(module
+          (func (param i32) (result i32)
+            (i32.add (i32.popcnt (local.get 0)) (i32.clz (local.get 0))))
+          (func (param i64) (result i64)
+            (i64.xor (local.get 0) (i64.const 1))))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    assert!(Coverage::new(&wasm).is_complete());
+}
@@ -0,0 +1,363 @@
+//! 单步调试，由 `RuntimeConfig::debugger` 开启
+//!
+//! 解释器在每条指令执行前询问 `Debugger` 是否暂停，暂停时把当前帧交给它查看，
+//! 由返回的 `Resume` 决定单步、继续还是中止。`CommandDebugger` 从输入逐行读取
+//! 命令，供 `oxygen debug` 使用。
+//!
+//! 开启调试器时使用 `Engine::Match` 执行且不使用 `untyped_stack`；
+//! 融合指令只在第一条原指令处暂停。
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug};
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context};
+
+use super::debuginfo::SourceLocation;
+use super::decoder::{FuncKind, WasmModule, WasmValue};
+use super::literal::format_value;
+use super::repl::hex_dump;
+use super::section::opcode::Opcode;
+
+/// 暂停后如何继续执行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// 执行一条指令后再次暂停
+    Step,
+    /// 运行到下一个断点
+    Continue,
+    /// 停止执行，调用返回 `Trap::Cancelled`
+    Abort,
+}
+
+pub trait Debugger: Debug {
+    /// 每条指令执行前调用，pc 相对函数体开头，返回 true 时暂停
+    fn should_pause(&mut self, func: usize, pc: usize) -> bool;
+    /// 暂停时调用，返回之后如何继续
+    fn pause(&mut self, frame: &Frame<'_>) -> Resume;
+}
+
+pub type SharedDebugger = Arc<Mutex<dyn Debugger + Send>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Breakpoint {
+    /// 函数的第一条指令
+    Func(usize),
+    /// 函数体中的第 pc 条指令
+    At { func: usize, pc: usize },
+}
+
+impl Breakpoint {
+    /// `FUNC` 或 `FUNC:PC`
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .with_context(|| format!("bad breakpoint `{text}`, expected FUNC or FUNC:PC"))
+        };
+        Ok(match text.split_once(':') {
+            Some((func, pc)) => Breakpoint::At {
+                func: number(func)?,
+                pc: number(pc)?,
+            },
+            None => Breakpoint::Func(number(text)?),
+        })
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Func(func) => write!(f, "{func}"),
+            Breakpoint::At { func, pc } => write!(f, "{func}:{pc}"),
+        }
+    }
+}
+
+/// 断点和单步状态，实现 `Debugger` 时使用
+#[derive(Debug, Default, Clone)]
+pub struct Breakpoints {
+    pub set: BTreeSet<Breakpoint>,
+    /// 下一条指令是否暂停
+    pub stepping: bool,
+}
+
+impl Breakpoints {
+    /// 单步中或 (func, pc) 处有断点
+    pub fn hit(&self, func: usize, pc: usize) -> bool {
+        self.stepping
+            || self.set.contains(&Breakpoint::At { func, pc })
+            || (pc == 0 && self.set.contains(&Breakpoint::Func(func)))
+    }
+
+    /// 记录 pause 的返回值
+    pub fn resume(&mut self, resume: Resume) -> Resume {
+        self.stepping = resume == Resume::Step;
+        resume
+    }
+}
+
+/// 暂停时的执行状态
+pub struct Frame<'a> {
+    pub wasm: &'a WasmModule,
+    /// 当前函数的索引
+    pub func: usize,
+    /// 下一条要执行的指令，相对函数体开头
+    pub pc: usize,
+}
+
+impl Frame<'_> {
+    pub fn op(&self) -> &Opcode {
+        &self.wasm.ops[self.wasm.pc]
+    }
+
+    /// 参数和局部变量的个数
+    fn local_count(&self) -> usize {
+        let FuncKind::Local((ty, body)) = &self.wasm.func[self.func] else {
+            return 0;
+        };
+        let params = self.wasm.section.types.entries[*ty].params.len();
+        params + body.locales.iter().map(|(n, _)| *n as usize).sum::<usize>()
+    }
+
+    /// 参数和局部变量
+    pub fn locals(&self) -> &[WasmValue] {
+        let fp = self.wasm.fp;
+        &self.wasm.stack[fp..fp + self.local_count()]
+    }
+
+    /// 当前函数的操作数栈，栈顶在最后
+    pub fn stack(&self) -> &[WasmValue] {
+        let start = self.wasm.fp + self.local_count();
+        self.wasm
+            .stack
+            .get(start..self.wasm.sp + 1)
+            .unwrap_or_default()
+    }
+
+    /// 第 0 个内存中 [addr, addr + len) 的内容，越界时为 None
+    pub fn memory(&self, addr: usize, len: usize) -> Option<&[u8]> {
+        self.wasm.mem.first()?.get(addr..addr.checked_add(len)?)
+    }
+
+    /// 调用链上的函数，当前函数在最后
+    pub fn backtrace(&self) -> &[usize] {
+        &self.wasm.frames
+    }
+
+    /// 当前指令的源码位置，模块没有调试信息时为 None
+    pub fn location(&self) -> Option<SourceLocation> {
+        self.wasm.source_location(self.wasm.pc)
+    }
+}
+
+const HELP: &str = "\
+step (s)                 execute one instruction
+continue (c)             run until the next breakpoint
+break (b) FUNC[:PC]      set a breakpoint at a function entry or instruction
+delete (d) FUNC[:PC]     remove a breakpoint
+breakpoints              list breakpoints
+locals                   print parameters and locals
+stack                    print the operand stack
+memory (x) ADDR [LEN]    hex dump LEN (default 16) bytes of memory 0
+backtrace (bt)           print the call chain
+quit (q)                 abort execution";
+
+/// 从 input 逐行读取命令的调试器，第一条指令处就会暂停
+pub struct CommandDebugger {
+    pub breakpoints: Breakpoints,
+    input: Box<dyn BufRead + Send>,
+    output: Box<dyn Write + Send>,
+}
+
+impl Debug for CommandDebugger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandDebugger")
+            .field("breakpoints", &self.breakpoints)
+            .finish_non_exhaustive()
+    }
+}
+
+fn values(values: &[WasmValue]) -> String {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| format!("  [{i}] {}", format_value(v)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl CommandDebugger {
+    pub fn new(input: impl BufRead + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        Self {
+            breakpoints: Breakpoints {
+                stepping: true,
+                ..Default::default()
+            },
+            input: Box::new(input),
+            output: Box::new(output),
+        }
+    }
+
+    /// 执行一条命令，返回 Some 时结束暂停
+    fn command(&mut self, frame: &Frame<'_>, line: &str) -> anyhow::Result<Option<Resume>> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let Some((&cmd, args)) = words.split_first() else {
+            return Ok(None);
+        };
+        let text = match (cmd, args) {
+            ("step" | "s", []) => return Ok(Some(Resume::Step)),
+            ("continue" | "c", []) => return Ok(Some(Resume::Continue)),
+            ("quit" | "q", []) => return Ok(Some(Resume::Abort)),
+            ("help", _) => HELP.to_string(),
+            ("break" | "b", [at]) => {
+                let at = Breakpoint::parse(at)?;
+                self.breakpoints.set.insert(at);
+                format!("breakpoint at {at}")
+            }
+            ("delete" | "d", [at]) => {
+                let at = Breakpoint::parse(at)?;
+                if !self.breakpoints.set.remove(&at) {
+                    bail!("no breakpoint at {at}");
+                }
+                format!("deleted breakpoint at {at}")
+            }
+            ("breakpoints", []) => (self.breakpoints.set.iter())
+                .map(|at| at.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ("locals", []) => values(frame.locals()),
+            ("stack", []) => values(frame.stack()),
+            ("memory" | "x", [addr, len @ ..]) if len.len() <= 1 => {
+                let addr = addr.parse::<u32>().context("bad address")?;
+                let len = len.first().map_or(Ok(16), |len| len.parse::<u32>());
+                let len = len.context("bad length")?;
+                let bytes = frame
+                    .memory(addr as usize, len as usize)
+                    .context("memory access out of bounds")?;
+                hex_dump(addr, bytes)
+            }
+            ("backtrace" | "bt", []) => (frame.backtrace().iter().rev())
+                .enumerate()
+                .map(|(i, func)| format!("  #{i} func {func}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => bail!("unknown command `{line}`, see `help`"),
+        };
+        if !text.is_empty() {
+            writeln!(self.output, "{text}")?;
+        }
+        Ok(None)
+    }
+
+    fn prompt(&mut self, frame: &Frame<'_>) -> anyhow::Result<Resume> {
+        write!(
+            self.output,
+            "func {} pc {}: {:?}",
+            frame.func,
+            frame.pc,
+            frame.op()
+        )?;
+        match frame.location() {
+            Some(loc) => writeln!(self.output, " at {loc}")?,
+            None => writeln!(self.output)?,
+        }
+        let mut line = String::new();
+        loop {
+            write!(self.output, "(debug) ")?;
+            self.output.flush()?;
+            line.clear();
+            // 输入结束时运行到底，不再暂停
+            if self.input.read_line(&mut line)? == 0 {
+                self.breakpoints.set.clear();
+                return Ok(Resume::Continue);
+            }
+            match self.command(frame, line.trim()) {
+                Ok(Some(resume)) => return Ok(resume),
+                Ok(None) => {}
+                Err(err) => writeln!(self.output, "error: {err:#}")?,
+            }
+        }
+    }
+}
+
+impl Debugger for CommandDebugger {
+    fn should_pause(&mut self, func: usize, pc: usize) -> bool {
+        self.breakpoints.hit(func, pc)
+    }
+
+    fn pause(&mut self, frame: &Frame<'_>) -> Resume {
+        let resume = self.prompt(frame).unwrap_or(Resume::Abort);
+        self.breakpoints.resume(resume)
+    }
+}
+
+#[test]
+fn test_command_debugger() {
+    use super::config::RuntimeConfig;
+    use super::trap::Trap;
+    use super::wat;
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buf = wat::compile(
+        r#"(module
+          (memory 1)
+          (data (i32.const 4) "hi")
+          (func $double (param i32) (result i32)
+            (i32.add (local.get 0) (local.get 0)))
+          (func (export "main") (param i32) (result i32)
+            (local i32)
+            (local.set 1 (call $double (local.get 0)))
+            (i32.add (local.get 1) (i32.const 1))))"#,
+    )
+    .unwrap();
+    let run = |script: &'static str| {
+        let out = Output::default();
+        let debugger = CommandDebugger::new(script.as_bytes(), out.clone());
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default().debugger(Arc::new(Mutex::new(debugger)));
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        let result = wasm.invoke("main", &[WasmValue::I32(20)]);
+        let out = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        (result, out)
+    };
+
+    let (result, out) = run("b 0:2\nbreakpoints\nc\nlocals\nstack\nbt\nx 4 2\ns\nstack\nc\n");
+    assert_eq!(result.unwrap(), vec![WasmValue::I32(41)]);
+    let expected = [
+        "func 1 pc 0: LocalGet(0)",
+        "(debug) breakpoint at 0:2",
+        "(debug) 0:2",
+        "(debug) func 0 pc 2: I32Add",
+        "(debug)   [0] 20 (0x14)",
+        "(debug)   [0] 20 (0x14)\n  [1] 20 (0x14)",
+        "(debug)   #0 func 0\n  #1 func 1",
+        format!("(debug) 00000004: 68 69{}  hi", " ".repeat(42)).leak(),
+        "(debug) func 0 pc 3: End(0)",
+        "(debug)   [0] 40 (0x28)",
+        "(debug) ",
+    ];
+    assert_eq!(out, expected.join("\n"));
+
+    // 函数入口断点、错误命令和中止
+    let (result, out) = run("b 0\nd 1\nc\nq\n");
+    let err = result.unwrap_err();
+    assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::Cancelled));
+    assert!(out.contains("error: no breakpoint at 1"));
+    assert!(out.contains("func 0 pc 0: LocalGet(0)"));
+
+    // 输入结束后运行到底
+    let (result, _) = run("");
+    assert_eq!(result.unwrap(), vec![WasmValue::I32(41)]);
+}
@@ -0,0 +1,716 @@
+//! 源码位置：DWARF 行号表（`.debug_line`）和 source map
+//!
+//! clang / rustc 加 `-g` 生成的模块带有 `.debug_*` custom section，DWARF 中的地址是
+//! 相对 code 段内容开头的偏移；emscripten `-gsource-map` 生成的模块只带一个
+//! `sourceMappingURL` custom section，source map 中的列是相对模块开头的偏移，
+//! 需要宿主读取 map 文件后通过 `DebugInfo::with_source_map` 加入。
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Range;
+
+use anyhow::{bail, ensure, Context};
+
+use crate::leb::{decode_leb_i64, decode_leb_u64};
+
+/// 源码中的位置，line 和 column 从 1 开始，column 为 0 表示未知
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if self.column > 0 {
+            write!(f, ":{}", self.column)?;
+        }
+        Ok(())
+    }
+}
+
+/// 按字节读取 DWARF 数据
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .context("unexpected end of DWARF data")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// 小端的 len 字节无符号整数
+    fn uint(&mut self, len: usize) -> anyhow::Result<u64> {
+        let bytes = self.bytes(len)?;
+        Ok(bytes.iter().rev().fold(0, |acc, &b| acc << 8 | b as u64))
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn uleb(&mut self) -> anyhow::Result<u64> {
+        let (value, len) = decode_leb_u64(&self.bytes[self.pos.min(self.bytes.len())..])?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    fn sleb(&mut self) -> anyhow::Result<i64> {
+        let (value, len) = decode_leb_i64(&self.bytes[self.pos.min(self.bytes.len())..])?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    /// 以 \0 结尾的字符串
+    fn cstr(&mut self) -> anyhow::Result<String> {
+        let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .context("unterminated DWARF string")?;
+        self.pos += len + 1;
+        Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+    }
+}
+
+/// `.debug_str` / `.debug_line_str` 中 offset 处的字符串
+fn str_at(section: &[u8], offset: u64) -> anyhow::Result<String> {
+    let mut reader = Reader::new(section);
+    reader.pos = usize::try_from(offset)?;
+    reader.cstr()
+}
+
+// DWARF 5 文件表的内容类型和属性格式
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_DATA1: u64 = 0x0b;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_DATA16: u64 = 0x1e;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_STRP: u64 = 0x0e;
+const DW_FORM_UDATA: u64 = 0x0f;
+const DW_FORM_LINE_STRP: u64 = 0x1f;
+
+/// 文件表中的一项
+#[derive(Debug, Default, Clone)]
+struct FileEntry {
+    path: String,
+    dir: u64,
+}
+
+/// 一行地址到源码位置的映射
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row {
+    address: u64,
+    file: usize,
+    line: u64,
+    column: u64,
+}
+
+/// 地址连续的一段行号，end 为最后一条指令之后的地址
+#[derive(Debug, Clone)]
+struct Sequence {
+    rows: Vec<Row>,
+    end: u64,
+}
+
+/// `.debug_line` 中所有编译单元的行号表
+#[derive(Debug, Default, Clone)]
+pub struct LineTable {
+    /// 所有编译单元的文件路径
+    files: Vec<String>,
+    sequences: Vec<Sequence>,
+}
+
+/// 解析 DWARF 5 目录表或文件表的一项
+struct EntryReader<'a> {
+    format: Vec<(u64, u64)>,
+    offset_size: usize,
+    line_str: &'a [u8],
+    str: &'a [u8],
+}
+
+impl EntryReader<'_> {
+    fn read(&self, reader: &mut Reader) -> anyhow::Result<FileEntry> {
+        let mut entry = FileEntry::default();
+        for &(content, form) in &self.format {
+            let (string, number) = match form {
+                DW_FORM_STRING => (Some(reader.cstr()?), None),
+                DW_FORM_LINE_STRP => {
+                    let offset = reader.uint(self.offset_size)?;
+                    (Some(str_at(self.line_str, offset)?), None)
+                }
+                DW_FORM_STRP => {
+                    let offset = reader.uint(self.offset_size)?;
+                    (Some(str_at(self.str, offset)?), None)
+                }
+                DW_FORM_UDATA => (None, Some(reader.uleb()?)),
+                DW_FORM_DATA1 => (None, Some(reader.uint(1)?)),
+                DW_FORM_DATA2 => (None, Some(reader.uint(2)?)),
+                DW_FORM_DATA4 => (None, Some(reader.uint(4)?)),
+                DW_FORM_DATA8 => (None, Some(reader.uint(8)?)),
+                DW_FORM_DATA16 => {
+                    reader.bytes(16)?;
+                    (None, None)
+                }
+                DW_FORM_BLOCK => {
+                    let len = reader.uleb()?;
+                    reader.bytes(usize::try_from(len)?)?;
+                    (None, None)
+                }
+                form => bail!("unsupported DWARF form {form:#x} in line table header"),
+            };
+            match (content, string, number) {
+                (DW_LNCT_PATH, Some(path), _) => entry.path = path,
+                (DW_LNCT_DIRECTORY_INDEX, _, Some(dir)) => entry.dir = dir,
+                _ => {}
+            }
+        }
+        Ok(entry)
+    }
+}
+
+/// 目录和文件名拼成路径，文件名已经是绝对路径时不拼接
+fn join_path(dir: Option<&str>, name: &str) -> String {
+    match dir {
+        Some(dir) if !dir.is_empty() && !name.starts_with('/') => {
+            format!("{}/{name}", dir.trim_end_matches('/'))
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// 行号程序的状态机
+#[derive(Debug, Clone)]
+struct LineState {
+    address: u64,
+    file: u64,
+    line: u64,
+    column: u64,
+}
+
+impl LineState {
+    fn new() -> Self {
+        Self {
+            address: 0,
+            file: 1,
+            line: 1,
+            column: 0,
+        }
+    }
+}
+
+impl LineTable {
+    /// 解析 `.debug_line`，line_str 和 str 是 `.debug_line_str` 和 `.debug_str`（可以为空）
+    pub fn parse(debug_line: &[u8], line_str: &[u8], str: &[u8]) -> anyhow::Result<Self> {
+        let mut table = LineTable::default();
+        let mut reader = Reader::new(debug_line);
+        while !reader.is_empty() {
+            let unit = reader.pos;
+            table
+                .parse_unit(&mut reader, line_str, str)
+                .with_context(|| format!("bad line table at offset {unit:#x}"))?;
+        }
+        for sequence in &mut table.sequences {
+            sequence.rows.sort_by_key(|row| row.address);
+        }
+        table.sequences.sort_by_key(|seq| seq.rows[0].address);
+        Ok(table)
+    }
+
+    /// 解析一个编译单元的行号表，reader 停在下一个单元的开头
+    fn parse_unit(
+        &mut self,
+        reader: &mut Reader,
+        line_str: &[u8],
+        str: &[u8],
+    ) -> anyhow::Result<()> {
+        let (length, offset_size) = match reader.uint(4)? {
+            0xffff_ffff => (reader.uint(8)?, 8),
+            length => (length, 4),
+        };
+        let unit = reader.bytes(usize::try_from(length)?)?;
+        let mut reader = Reader::new(unit);
+        let version = reader.uint(2)?;
+        ensure!(
+            (2..=5).contains(&version),
+            "unsupported DWARF version {version}"
+        );
+        if version >= 5 {
+            // address_size、segment_selector_size
+            reader.bytes(2)?;
+        }
+        let header_length = usize::try_from(reader.uint(offset_size)?)?;
+        let program = reader.pos + header_length;
+        let min_inst_length = reader.u8()? as u64;
+        if version >= 4 {
+            // maximum_operations_per_instruction，wasm 中总是 1
+            reader.u8()?;
+        }
+        reader.u8()?; // default_is_stmt
+        let line_base = reader.u8()? as i8 as i64;
+        let line_range = reader.u8()? as u64;
+        ensure!(line_range > 0, "line_range must not be 0");
+        let opcode_base = reader.u8()?;
+        let opcode_lengths = reader
+            .bytes(opcode_base.saturating_sub(1) as usize)?
+            .to_vec();
+
+        // 文件索引：DWARF 5 从 0 开始，之前的版本从 1 开始
+        let mut files = vec![];
+        if version >= 5 {
+            let entries = |reader: &mut Reader| -> anyhow::Result<Vec<FileEntry>> {
+                let count = reader.u8()?;
+                let format = (0..count)
+                    .map(|_| Ok((reader.uleb()?, reader.uleb()?)))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let entry = EntryReader {
+                    format,
+                    offset_size,
+                    line_str,
+                    str,
+                };
+                (0..reader.uleb()?).map(|_| entry.read(reader)).collect()
+            };
+            let dirs = entries(&mut reader)?;
+            for file in entries(&mut reader)? {
+                let dir = dirs.get(file.dir as usize).map(|d| d.path.as_str());
+                files.push(join_path(dir, &file.path));
+            }
+        } else {
+            let mut dirs = vec![];
+            loop {
+                let dir = reader.cstr()?;
+                if dir.is_empty() {
+                    break;
+                }
+                dirs.push(dir);
+            }
+            files.push(String::new());
+            loop {
+                let name = reader.cstr()?;
+                if name.is_empty() {
+                    break;
+                }
+                let dir = reader.uleb()?;
+                reader.uleb()?; // 修改时间
+                reader.uleb()?; // 文件大小
+                                // 目录 0 是编译目录，不在表中
+                let dir = (dir as usize).checked_sub(1).and_then(|i| dirs.get(i));
+                files.push(join_path(dir.map(|d| d.as_str()), &name));
+            }
+        }
+
+        let base = self.files.len();
+        self.files.extend(files);
+        let file_index = |state: &LineState| base + state.file as usize;
+
+        reader.pos = program;
+        let mut state = LineState::new();
+        let mut rows = vec![];
+        while !reader.is_empty() {
+            let mut emit = false;
+            match reader.u8()? {
+                0 => {
+                    let len = usize::try_from(reader.uleb()?)?;
+                    ensure!(len > 0, "empty extended opcode");
+                    let end = reader.pos + len;
+                    match reader.u8()? {
+                        // DW_LNE_end_sequence
+                        1 => {
+                            if !rows.is_empty() {
+                                self.sequences.push(Sequence {
+                                    rows: std::mem::take(&mut rows),
+                                    end: state.address,
+                                });
+                            }
+                            state = LineState::new();
+                        }
+                        // DW_LNE_set_address
+                        2 => state.address = reader.uint(len - 1)?,
+                        _ => {}
+                    }
+                    reader.pos = end;
+                }
+                // DW_LNS_copy
+                1 => emit = true,
+                // DW_LNS_advance_pc
+                2 => state.address += reader.uleb()? * min_inst_length,
+                // DW_LNS_advance_line
+                3 => state.line = state.line.wrapping_add_signed(reader.sleb()?),
+                // DW_LNS_set_file
+                4 => state.file = reader.uleb()?,
+                // DW_LNS_set_column
+                5 => state.column = reader.uleb()?,
+                // DW_LNS_const_add_pc
+                8 => {
+                    let adjusted = (255 - opcode_base) as u64;
+                    state.address += adjusted / line_range * min_inst_length;
+                }
+                // DW_LNS_fixed_advance_pc
+                9 => state.address += reader.uint(2)?,
+                // DW_LNS_set_isa
+                12 => {
+                    reader.uleb()?;
+                }
+                op if op < opcode_base => {
+                    // negate_stmt、basic_block 等不影响位置的操作码，跳过参数
+                    for _ in 0..opcode_lengths[op as usize - 1] {
+                        reader.uleb()?;
+                    }
+                }
+                op => {
+                    let adjusted = (op - opcode_base) as u64;
+                    state.address += adjusted / line_range * min_inst_length;
+                    let delta = line_base + (adjusted % line_range) as i64;
+                    state.line = state.line.wrapping_add_signed(delta);
+                    emit = true;
+                }
+            }
+            if emit {
+                rows.push(Row {
+                    address: state.address,
+                    file: file_index(&state),
+                    line: state.line,
+                    column: state.column,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// address 所在的行
+    pub fn lookup(&self, address: u64) -> Option<SourceLocation> {
+        let index = self
+            .sequences
+            .partition_point(|seq| seq.rows[0].address <= address);
+        // 序列可能重叠（被链接器丢弃的函数地址为 0），从后往前找包含 address 的
+        let sequence = self.sequences[..index]
+            .iter()
+            .rev()
+            .find(|seq| address < seq.end)?;
+        let row = sequence.rows.partition_point(|row| row.address <= address);
+        let row = &sequence.rows[row.checked_sub(1)?];
+        Some(SourceLocation {
+            file: self.files.get(row.file).cloned().unwrap_or_default(),
+            line: row.line,
+            column: row.column,
+        })
+    }
+}
+
+/// source map v3 的 mappings，只使用第一行（wasm 模块只有一行）
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    sources: Vec<String>,
+    /// (模块中的偏移, 源文件, 行, 列)，按偏移排序，行和列从 0 开始
+    mappings: Vec<(u64, usize, u64, u64)>,
+}
+
+/// base64 VLQ 编码的一个整数
+fn vlq(chars: &mut std::iter::Peekable<std::str::Chars>) -> anyhow::Result<i64> {
+    let mut value = 0i64;
+    let mut shift = 0;
+    loop {
+        let c = chars.next().context("unterminated VLQ value")?;
+        let digit = match c {
+            'A'..='Z' => c as i64 - 'A' as i64,
+            'a'..='z' => c as i64 - 'a' as i64 + 26,
+            '0'..='9' => c as i64 - '0' as i64 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => bail!("bad VLQ character {c:?}"),
+        };
+        ensure!(shift < 60, "VLQ value too large");
+        value |= (digit & 0x1f) << shift;
+        shift += 5;
+        if digit & 0x20 == 0 {
+            break;
+        }
+    }
+    Ok(if value & 1 == 1 {
+        -(value >> 1)
+    } else {
+        value >> 1
+    })
+}
+
+impl SourceMap {
+    /// 解析 source map 的 JSON 文本
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let map: serde_json::Value = serde_json::from_str(json)?;
+        let sources = map["sources"]
+            .as_array()
+            .context("source map has no `sources`")?
+            .iter()
+            .map(|s| s.as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        let text = map["mappings"]
+            .as_str()
+            .context("source map has no `mappings`")?;
+        let line = text.split(';').next().unwrap_or_default();
+        let mut mappings = vec![];
+        // 各字段都是相对上一段的增量
+        let (mut column, mut source, mut src_line, mut src_column) = (0i64, 0i64, 0i64, 0i64);
+        for segment in line.split(',').filter(|s| !s.is_empty()) {
+            let mut chars = segment.chars().peekable();
+            let mut fields = vec![];
+            while chars.peek().is_some() {
+                fields.push(vlq(&mut chars)?);
+            }
+            column += fields[0];
+            if fields.len() < 4 {
+                continue;
+            }
+            source += fields[1];
+            src_line += fields[2];
+            src_column += fields[3];
+            ensure!(
+                column >= 0 && source >= 0 && src_line >= 0 && src_column >= 0,
+                "negative position in source map"
+            );
+            mappings.push((
+                column as u64,
+                source as usize,
+                src_line as u64,
+                src_column as u64,
+            ));
+        }
+        mappings.sort_by_key(|m| m.0);
+        Ok(Self { sources, mappings })
+    }
+
+    /// 模块中 offset 处的指令对应的位置
+    pub fn lookup(&self, offset: u64) -> Option<SourceLocation> {
+        let index = self.mappings.partition_point(|m| m.0 <= offset);
+        let &(_, source, line, column) = self.mappings.get(index.checked_sub(1)?)?;
+        Some(SourceLocation {
+            file: self.sources.get(source).cloned().unwrap_or_default(),
+            line: line + 1,
+            column: column + 1,
+        })
+    }
+}
+
+/// 模块的调试信息，解码时从 custom section 中读取
+#[derive(Debug, Default, Clone)]
+pub struct DebugInfo {
+    /// code 段内容开头在模块中的偏移，DWARF 地址相对它
+    pub code_start: usize,
+    pub lines: Option<LineTable>,
+    /// `sourceMappingURL` custom section 的内容
+    pub source_mapping_url: Option<String>,
+    pub source_map: Option<SourceMap>,
+}
+
+impl DebugInfo {
+    /// sections 为 custom section 名字到内容在 raw 中的范围；
+    /// 调试信息损坏时忽略，不影响模块的使用
+    pub fn load(raw: &[u8], sections: &HashMap<String, Range<usize>>, code_start: usize) -> Self {
+        let section = |name: &str| {
+            sections
+                .get(name)
+                .and_then(|range| raw.get(range.clone()))
+                .unwrap_or_default()
+        };
+        let lines = sections.get(".debug_line").and_then(|_| {
+            LineTable::parse(
+                section(".debug_line"),
+                section(".debug_line_str"),
+                section(".debug_str"),
+            )
+            .ok()
+        });
+        let source_mapping_url = sections.get("sourceMappingURL").and_then(|_| {
+            let bytes = section("sourceMappingURL");
+            let (len, n) = decode_leb_u64(bytes).ok()?;
+            let url = bytes.get(n..n.checked_add(len as usize)?)?;
+            String::from_utf8(url.to_vec()).ok()
+        });
+        Self {
+            code_start,
+            lines,
+            source_mapping_url,
+            source_map: None,
+        }
+    }
+
+    pub fn with_source_map(mut self, map: SourceMap) -> Self {
+        self.source_map = Some(map);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_none() && self.source_map.is_none()
+    }
+
+    /// 模块中 offset 处的指令对应的源码位置，优先使用 DWARF
+    pub fn lookup(&self, offset: usize) -> Option<SourceLocation> {
+        let dwarf = self.lines.as_ref().and_then(|lines| {
+            let address = offset.checked_sub(self.code_start)?;
+            lines.lookup(address as u64)
+        });
+        dwarf.or_else(|| self.source_map.as_ref()?.lookup(offset as u64))
+    }
+}
+
+/// 构造一个行号表：/src 下的两个文件，地址 0x10 起的三行，返回 `.debug_line` 和 `.debug_line_str`
+#[cfg(test)]
+pub(crate) fn sample_debug_line(version: u16) -> (Vec<u8>, Vec<u8>) {
+    // min_inst_length、max_ops、default_is_stmt、line_base = -5、line_range、opcode_base
+    let mut header = vec![1, 1, 1, 0xfb, 14, 13];
+    header.extend([0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+    let mut line_str = vec![];
+    if version >= 5 {
+        line_str.extend(b"/src\0main.c\0util.h\0");
+        header.extend([1, 1, 0x1f]); // 目录格式：path line_strp
+        header.extend([1, 0, 0, 0, 0]); // 1 个目录，"/src"
+        header.extend([2, 1, 0x1f, 2, 0x0b]); // 文件格式：path line_strp、directory_index data1
+        header.push(2);
+        header.extend([5, 0, 0, 0, 0]); // main.c，目录 0
+        header.extend([12, 0, 0, 0, 0]); // util.h，目录 0
+    } else {
+        header.extend(b"/src\0\0");
+        header.extend(b"main.c\0\x01\0\0");
+        header.extend(b"util.h\0\x01\0\0");
+        header.push(0);
+    }
+    // 行号程序，DWARF 5 的文件索引从 0 开始
+    let first = if version >= 5 { 0 } else { 1 };
+    let program = [
+        vec![0, 5, 2, 0x10, 0, 0, 0],               // set_address 0x10
+        vec![4, first],                             // set_file
+        vec![5, 3],                                 // column 3
+        vec![3, 9],                                 // advance_line +9 -> 10
+        vec![1],                                    // copy: 0x10 main.c:10:3
+        vec![13 + 5 + 2 * 14], // special: address +2, line +0 -> 0x12 main.c:10:3
+        vec![5, 7, 13 + 5 + 1 + 14], // column 7, address +1, line +1 -> 0x13 main.c:11:7
+        vec![4, first + 1, 5, 0, 3, 0x7c, 2, 4, 1], // util.h, column 0, line -4 = 7, pc +4 -> 0x17
+        vec![2, 3, 0, 1, 1],   // advance_pc 3, end_sequence at 0x1a
+    ]
+    .concat();
+    let mut unit = vec![];
+    unit.extend(version.to_le_bytes());
+    if version >= 5 {
+        unit.extend([4, 0]);
+    }
+    unit.extend((header.len() as u32).to_le_bytes());
+    unit.extend(header);
+    unit.extend(program);
+    let mut out = (unit.len() as u32).to_le_bytes().to_vec();
+    out.extend(unit);
+    (out, line_str)
+}
+
+#[test]
+fn test_line_table() {
+    for version in [4, 5] {
+        let (debug_line, line_str) = sample_debug_line(version);
+        let table = LineTable::parse(&debug_line, &line_str, &[]).unwrap();
+        let at = |address| table.lookup(address).map(|loc| loc.to_string());
+        assert_eq!(at(0x0f), None, "version {version}");
+        assert_eq!(at(0x10).as_deref(), Some("/src/main.c:10:3"));
+        assert_eq!(at(0x12).as_deref(), Some("/src/main.c:10:3"));
+        assert_eq!(at(0x13).as_deref(), Some("/src/main.c:11:7"));
+        assert_eq!(at(0x16).as_deref(), Some("/src/main.c:11:7"));
+        assert_eq!(at(0x17).as_deref(), Some("/src/util.h:7"));
+        assert_eq!(at(0x19).as_deref(), Some("/src/util.h:7"));
+        assert_eq!(at(0x1a), None);
+    }
+    assert!(LineTable::parse(&[4, 0, 0, 0, 9, 0, 0, 0], &[], &[]).is_err());
+}
+
+#[test]
+fn test_source_map() {
+    // 偏移 0x20 -> a.c:1:1，0x25 -> a.c:3:5，0x30 -> b.c:2:1
+    let map = SourceMap::parse(
+        r#"{"version":3,"sources":["a.c","b.c"],"names":[],"mappings":"gCAAA,KAEI,WCDJ"}"#,
+    )
+    .unwrap();
+    let at = |offset| map.lookup(offset).map(|loc| loc.to_string());
+    assert_eq!(at(0x1f), None);
+    assert_eq!(at(0x20).as_deref(), Some("a.c:1:1"));
+    assert_eq!(at(0x27).as_deref(), Some("a.c:3:5"));
+    assert_eq!(at(0x30).as_deref(), Some("b.c:2:1"));
+    assert!(SourceMap::parse(r#"{"sources":[],"mappings":"!"}"#).is_err());
+}
+
+#[test]
+fn test_trap_location() {
+    use super::config::RuntimeConfig;
+    use super::decoder::WasmModule;
+    use super::trace::{TraceFilter, TraceFormat, TraceLog};
+    use super::trap::Trap;
+    use super::wat;
+
+    #[derive(Clone, Default)]
+    struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // code 段内容：函数个数、函数体大小、局部变量个数之后是 13 个 nop，
+    // unreachable 在 0x10，对应 main.c:10:3
+    let mut buf = wat::compile(&format!(
+        r#"(module (func (export "f") {} unreachable))"#,
+        "nop ".repeat(13)
+    ))
+    .unwrap();
+    let (debug_line, _) = sample_debug_line(4);
+    let mut payload = vec![11];
+    payload.extend(b".debug_line");
+    payload.extend(debug_line);
+    buf.push(0);
+    crate::leb::encode_leb_u32(payload.len() as u32, &mut buf);
+    buf.extend(payload);
+
+    let out = Buffer::default();
+    let mut wasm = WasmModule::default(buf);
+    wasm.config = RuntimeConfig::default().tracer(TraceLog::new(
+        TraceFilter::default(),
+        TraceFormat::Text,
+        out.clone(),
+    ));
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    let code = wasm.debug_info.code_start;
+    assert_eq!(wasm.section.code.op_offset(13), Some(code + 0x10));
+    assert_eq!(wasm.source_location(0), None);
+
+    let err = wasm.invoke("f", &[]).unwrap_err();
+    assert_eq!(err.to_string(), "trap at /src/main.c:10:3");
+    assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::Unreachable));
+    let trace = String::from_utf8(std::mem::take(&mut *out.0.lock().unwrap())).unwrap();
+    assert!(trace.contains("at /src/main.c:10:3\n"), "{trace}");
+
+    // 只有 source map 时按模块中的偏移查找
+    let map = SourceMap::parse(r#"{"sources":["a.c"],"mappings":"gCAAA"}"#).unwrap();
+    let info = DebugInfo::default().with_source_map(map);
+    assert_eq!(info.lookup(0x20).unwrap().to_string(), "a.c:1:1");
+    assert_eq!(info.lookup(0x1f), None);
+}
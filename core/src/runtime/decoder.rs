@@ -0,0 +1,5161 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Shl, Sub};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, Weak};
+
+use anyhow::{bail, ensure, Context};
+
+#[cfg(feature = "serde")]
+use super::cache;
+use super::component;
+use super::config::{Engine, FuncSelector, RuntimeConfig};
+use super::constants;
+use super::constexpr::ConstValue;
+use super::debug::{Frame, Resume, SharedDebugger};
+use super::debuginfo::{DebugInfo, SourceLocation};
+use super::error::{
+    BodyAt, DecodeError, DecodeErrorKind, FuncLocation, ImportType, InstantiationError, InstrAt,
+};
+use super::float;
+use super::global::SharedGlobal;
+use super::ir::{self, Instr};
+use super::linear::{LinearMemory, Memory, MAX_PAGES};
+use super::logging::{debug, info, trace};
+use super::section::code::FuncBody;
+use super::section::custom::{Dylink, Producers, TargetFeatures};
+use super::section::export::ExportKind;
+use super::section::opcode::{MemArg, Opcode};
+use super::section::types::FunctionType;
+use super::section::typings::{Limit, RefKind, ValueType};
+use super::section::{self, import, ByteParse, ByteRead, Decode, Section};
+use super::table::{Elem, Table};
+use super::threaded::{self, ThreadedOp};
+use super::trace::Access;
+use super::trap::Trap;
+use super::untyped;
+
+#[derive(Debug)]
+pub struct WasmModule {
+    pub raw: Arc<[u8]>,
+    pub offset: usize,
+    pub length: usize,
+    pub magic_number: Vec<u8>,
+    pub version: u32,
+    pub section: Section,
+
+    /// progma count
+    pub pc: usize,
+    /// stack poniter
+    pub sp: usize,
+    /// frame pointer
+    pub fp: usize,
+    /// callstack pointer（当前调用深度）
+    pub csp: usize,
+    // pub callstack: Vec<Frame>,
+    // pub blocks: HashMap<usize, Rc<Block>>,
+    pub stack: Vec<WasmValue>,
+    pub table: Vec<Table>,
+    pub mem: Vec<LinearMemory>,
+    /// 数据段内容，按数据段索引存放，active 段和 data.drop 之后为空
+    pub data: Vec<Vec<u8>>,
+    /// 元素段内容（函数索引），按元素段索引存放，active/declarative 段和 elem.drop 之后为空
+    pub elem: Vec<Vec<usize>>,
+    pub global: Vec<Global>,
+    pub exports: HashMap<String, ExportKind>,
+    pub func: Vec<FuncKind>,
+    pub ops: Vec<Opcode>,
+    /// 由 ops 降级得到的指令，执行时使用
+    pub code: ir::Code,
+    /// `Engine::Threaded` 使用的指令，由 code 生成
+    pub threaded: Vec<ThreadedOp>,
+    /// `RuntimeConfig::untyped_stack` 开启时按需编译的函数
+    pub raw_funcs: untyped::RawFuncs,
+    /// 不带类型标记的栈，raw_sp 为当前函数操作数栈顶之上的位置
+    pub raw_stack: Vec<u64>,
+    pub raw_sp: usize,
+    /// `Engine::Jit` 按需编译的函数
+    #[cfg(feature = "jit")]
+    pub jit: super::jit::JitFuncs,
+    pub config: RuntimeConfig,
+    pub stats: ExecStats,
+    pub policy: CallPolicy,
+    /// 调用链上的函数索引，设置了调试器时记录
+    pub frames: Vec<usize>,
+    /// 源码位置信息，来自 DWARF 或 source map
+    pub debug_info: DebugInfo,
+    /// 宿主函数调用了 `suspend`，返回后挂起
+    suspending: bool,
+    /// 宿主函数调用了 `raise`，返回后以它终止执行
+    raised: Option<Trap>,
+    /// 正在执行的宿主函数的层数，不为 0 时 `invoke` 在调用者的栈之上执行
+    host_depth: usize,
+    /// 挂起的执行，由内向外的函数帧；设为 None 即放弃挂起的执行
+    pub suspended: Option<Vec<SuspendedFrame>>,
+    /// 执行的指令数达到它后，在下一个检查点让出（`Trap::Yielded`），之后用 `resume` 继续
+    pub yield_at: Option<u64>,
+    /// 实例的编号，实例化时分配，表项以它记录写入者
+    pub id: u64,
+    /// 从共享表中读到的其他实例的函数，引用值为 func.len() 加上在这里的下标
+    foreign: Vec<ForeignFunc>,
+    /// 从其他实例导入的函数，按函数索引，见 `Linker::define_instance`
+    pub linked_funcs: HashMap<usize, (super::linker::SharedInstance, usize)>,
+    /// 宿主模块提供的函数，按函数索引，见 `Linker::define_host_module`
+    pub host_funcs: HashMap<usize, (super::host::SharedHostModule, usize)>,
+    /// 异步宿主函数，按函数索引
+    #[cfg(feature = "async")]
+    pub async_funcs: HashMap<usize, super::future::AsyncHostFunc>,
+    /// 等待中的异步宿主函数调用
+    #[cfg(feature = "async")]
+    pub(crate) pending: super::future::Pending,
+}
+
+/// 让出时最内层的函数从当前指令继续，其余函数从 call 的下一条指令继续
+fn resume_pc(trap: &Trap, inner: &[SuspendedFrame], pc: usize) -> usize {
+    if *trap == Trap::Yielded && inner.is_empty() {
+        pc
+    } else {
+        pc + 1
+    }
+}
+
+/// 其他实例写入共享表的函数
+#[derive(Debug, Clone)]
+struct ForeignFunc {
+    owner: u64,
+    func: usize,
+    instance: Weak<Mutex<WasmModule>>,
+}
+
+/// 实例化时建立的实例状态；实例化失败时把调用前的状态原样放回
+#[derive(Default)]
+struct InstanceState {
+    func: Vec<FuncKind>,
+    global: Vec<Global>,
+    table: Vec<Table>,
+    mem: Vec<LinearMemory>,
+    elem: Vec<Vec<usize>>,
+    data: Vec<Vec<u8>>,
+    exports: HashMap<String, ExportKind>,
+    policy: CallPolicy,
+    id: u64,
+    foreign: Vec<ForeignFunc>,
+}
+
+impl InstanceState {
+    fn take(wasm: &mut WasmModule) -> InstanceState {
+        InstanceState {
+            func: std::mem::take(&mut wasm.func),
+            global: std::mem::take(&mut wasm.global),
+            table: std::mem::take(&mut wasm.table),
+            mem: std::mem::take(&mut wasm.mem),
+            elem: std::mem::take(&mut wasm.elem),
+            data: std::mem::take(&mut wasm.data),
+            exports: std::mem::take(&mut wasm.exports),
+            policy: std::mem::take(&mut wasm.policy),
+            id: wasm.id,
+            foreign: std::mem::take(&mut wasm.foreign),
+        }
+    }
+
+    fn restore(self, wasm: &mut WasmModule) {
+        wasm.func = self.func;
+        wasm.global = self.global;
+        wasm.table = self.table;
+        wasm.mem = self.mem;
+        wasm.elem = self.elem;
+        wasm.data = self.data;
+        wasm.exports = self.exports;
+        wasm.policy = self.policy;
+        wasm.id = self.id;
+        wasm.foreign = self.foreign;
+    }
+}
+
+/// 挂起时保存的函数帧，值栈留在实例中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuspendedFrame {
+    pub func: usize,
+    /// 函数体的开始
+    pub offset: usize,
+    /// 恢复时继续执行的指令：让出时为尚未执行的指令，否则为 call 的下一条
+    pub pc: usize,
+    pub fp: usize,
+    /// 弹出被调函数参数后的栈顶
+    pub sp: usize,
+    /// 返回时恢复的调用者 pc、fp、sp（已弹出参数）
+    pub ret: (usize, usize, usize),
+    pub result_count: usize,
+}
+
+/// 由 RuntimeConfig 中的函数列表解析得到的调用限制
+#[derive(Debug, Default, Clone)]
+pub struct CallPolicy {
+    pub denied: HashSet<usize>,
+    /// None 表示不限制 call_indirect
+    pub indirect_allowed: Option<HashSet<usize>>,
+}
+
+/// 执行统计，供运行报告使用
+#[derive(Debug, Default, Clone)]
+pub struct ExecStats {
+    /// 已执行的指令数
+    pub instructions: u64,
+    /// 各宿主函数的调用次数，按函数索引
+    pub host_calls: HashMap<usize, u64>,
+}
+
+pub(crate) const CANCEL_CHECK_INTERVAL: u64 = 1024;
+
+/// 表中的空引用（ref.null func）
+pub const NULL_REF: usize = usize::MAX;
+
+/// 宿主函数可以通过 module 读写内存、全局变量，也可以 `invoke` 导出函数（例如 guest 的 malloc），
+/// 被调函数在当前调用之上执行，返回后宿主函数继续
+pub type HostFunc = fn(module: &mut WasmModule, arg: &Vec<WasmValue>) -> Vec<WasmValue>;
+
+#[derive(Debug, Clone)]
+pub enum FuncKind {
+    Import(usize, HostFunc),  // ty
+    Local((usize, FuncBody)), // (ty, code index)
+}
+
+#[derive(Debug)]
+pub enum Global {
+    Const(WasmValue),
+    Var(WasmValue),
+    /// 导入的宿主全局变量，与宿主和其他导入它的实例共享
+    Shared(SharedGlobal),
+}
+
+impl Global {
+    pub fn get(&self) -> WasmValue {
+        match self {
+            Global::Const(v) | Global::Var(v) => *v,
+            Global::Shared(global) => global.get(),
+        }
+    }
+    pub fn is_mutable(&self) -> bool {
+        match self {
+            Global::Const(_) => false,
+            Global::Var(_) => true,
+            Global::Shared(global) => global.mutability(),
+        }
+    }
+    /// global.set，不检查可变性和类型
+    pub fn set(&mut self, value: WasmValue) {
+        match self {
+            Global::Shared(global) => global.store(value),
+            _ => *self = Global::Var(value),
+        }
+    }
+}
+
+/// 整数只有 I32/I64 两种表示，按位模式保存；有无符号由指令决定，
+/// 无符号的比较、除法、移位和转换先把位模式解释为 u32/u64
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WasmValue {
+    #[default]
+    NOP,
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(i128),
+    /// 函数索引，空引用为 `NULL_REF`
+    FuncRef(usize),
+    /// 宿主给出的不透明值，空引用为 `NULL_REF`
+    ExternRef(usize),
+}
+
+impl WasmValue {
+    pub fn value_type(&self) -> Option<ValueType> {
+        match self {
+            WasmValue::NOP => None,
+            WasmValue::I32(_) => Some(ValueType::I32),
+            WasmValue::I64(_) => Some(ValueType::I64),
+            WasmValue::F32(_) => Some(ValueType::F32),
+            WasmValue::F64(_) => Some(ValueType::F64),
+            WasmValue::V128(_) => Some(ValueType::V128),
+            WasmValue::FuncRef(_) => Some(ValueType::FuncRef),
+            WasmValue::ExternRef(_) => Some(ValueType::ExternRef),
+        }
+    }
+}
+
+impl ByteRead for WasmModule {}
+impl ByteParse for WasmModule {
+    fn offset(&self) -> usize {
+        return self.offset;
+    }
+
+    fn length(&self) -> usize {
+        return self.length;
+    }
+
+    fn skip(&mut self, num: u32) {
+        self.offset += num as usize;
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.raw
+    }
+}
+
+impl WasmModule
+where
+    Self: ByteRead,
+{
+    pub fn decode(&mut self) -> Result<(), DecodeError> {
+        self.parse_header()?;
+        self.parse_sections()?;
+        self.check_counts()?;
+        self.debug_info = DebugInfo::load(
+            &self.raw,
+            &self.section.custom.payloads,
+            self.section.code.content_offset,
+        );
+        info!(
+            "decoded module: {} bytes, {} functions, {} instructions",
+            self.raw.len(),
+            self.section.code.entries.len(),
+            self.ops.len()
+        );
+        Ok(())
+    }
+    fn parse_header(&mut self) -> Result<(), DecodeError> {
+        let limits = &self.config.limits;
+        if let Some(max) = limits.max_module_size {
+            if self.length > max {
+                return Err(DecodeError::new(
+                    None,
+                    0,
+                    DecodeErrorKind::LimitExceeded {
+                        what: "module size",
+                        found: self.length,
+                        limit: max,
+                    },
+                ));
+            }
+        }
+        self.magic_number = self.parse_magic()?;
+        self.version = self.parse_version()?;
+        Ok(())
+    }
+    fn parse_sections(&mut self) -> Result<(), DecodeError> {
+        while self.offset < self.length {
+            self.parse_section()?;
+        }
+        Ok(())
+    }
+    fn check_counts(&self) -> Result<(), DecodeError> {
+        let data_count = &self.section.data_count;
+        if data_count.has_count && data_count.u32 != self.section.data.data_count {
+            return Err(DecodeError::new(
+                Some(11),
+                self.section.data.offset,
+                DecodeErrorKind::DataCountMismatch {
+                    expected: data_count.u32,
+                    found: self.section.data.data_count,
+                },
+            ));
+        }
+        if let Some(max) = self.config.limits.max_functions {
+            let count = self.imported_funcs() + self.section.func.entries.len();
+            if count > max {
+                return Err(DecodeError::new(
+                    Some(3),
+                    self.section.func.offset,
+                    DecodeErrorKind::LimitExceeded {
+                        what: "function count",
+                        found: count,
+                        limit: max,
+                    },
+                ));
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn imported_funcs(&self) -> usize {
+        self.section
+            .import
+            .entries
+            .iter()
+            .filter(|ipt| matches!(ipt.kind, import::Kind::Func(_)))
+            .count()
+    }
+    /// section 解析失败时还原出错的指令和 code 段中的函数
+    fn section_error(&self, id: u8, offset: usize, err: anyhow::Error) -> DecodeError {
+        let body = err.downcast_ref::<BodyAt>().copied();
+        let instr = err.downcast_ref::<InstrAt>().map(|at| at.0);
+        // 指令中的错误指向指令的开始
+        let offset = instr.unwrap_or(offset);
+        let mut error = DecodeError::new(Some(id), offset, err);
+        if let Some(body) = body {
+            error.func = Some(FuncLocation {
+                index: self.imported_funcs() + body.index,
+                instr_offset: offset - body.start,
+            });
+        }
+        error
+    }
+    /// 单遍校验 code 段：data_count 段在 code 段之前，
+    /// memory.init / data.drop 引用的数据段索引此时已经可以检查
+    fn check_data_refs(&self, start: usize) -> Result<(), DecodeErrorKind> {
+        for op in &self.ops[start..] {
+            let idx = match op {
+                Opcode::MemoryInit(idx, _) | Opcode::DataDrop(idx) => *idx,
+                _ => continue,
+            };
+            let data_count = &self.section.data_count;
+            if !data_count.has_count {
+                return Err(DecodeErrorKind::DataCountRequired);
+            }
+            if idx >= data_count.u32 as usize {
+                return Err(DecodeErrorKind::UnknownDataSegment { index: idx as u32 });
+            }
+        }
+        Ok(())
+    }
+    /// 解析所有延迟解码的函数体，实例化前后都可以调用
+    pub fn decode_bodies(&mut self) -> anyhow::Result<()> {
+        for index in 0..self.section.code.entries.len() {
+            let mut body = std::mem::take(&mut self.section.code.entries[index]);
+            let (offset, start) = (body.offset, self.ops.len());
+            let res = self.section.code.decode_pending(&mut body, &mut self.ops);
+            self.section.code.entries[index] = body;
+            res.map_err(|err| {
+                err.context(BodyAt {
+                    index,
+                    start: offset,
+                })
+            })?;
+            self.check_data_refs(start)?;
+        }
+        for idx in 0..self.func.len() {
+            self.decode_pending(idx)?;
+        }
+        Ok(())
+    }
+    /// 延迟解码时，函数第一次调用前解析函数体
+    pub(crate) fn decode_pending(&mut self, idx: usize) -> Result<(), Trap> {
+        let Some(FuncKind::Local((_, body))) = self.func.get_mut(idx) else {
+            return Ok(());
+        };
+        if !body.pending {
+            return Ok(());
+        }
+        let start = self.ops.len();
+        let mut res = self.section.code.decode_pending(body, &mut self.ops);
+        if res.is_ok() {
+            res = self.check_data_refs(start).map_err(Into::into);
+        }
+        res.map_err(|err| {
+            self.ops.truncate(start);
+            let code = &mut self.section.code;
+            code.instr_offsets.retain(|&(op, _)| op < start);
+            if let FuncKind::Local((_, body)) = &mut self.func[idx] {
+                body.pending = true;
+            }
+            Trap::MalformedBody {
+                func: idx,
+                message: format!("{err:#}"),
+            }
+        })
+    }
+    fn parse_version(&mut self) -> Result<u32, DecodeError> {
+        let offset = self.offset;
+        let version: [u8; 4] = self
+            .peek_bytes(4)
+            .map_err(|err| DecodeError::new(None, offset, err))?
+            .try_into()
+            .unwrap();
+        let found = u32::from_le_bytes(version);
+        if version[2..] == component::LAYER {
+            return Err(DecodeError::new(
+                None,
+                offset,
+                DecodeErrorKind::Component {
+                    version: found as u16,
+                },
+            ));
+        }
+        if version != constants::VERSION {
+            return Err(DecodeError::new(
+                None,
+                offset,
+                DecodeErrorKind::UnknownVersion { found },
+            ));
+        }
+        self.skip(4);
+        Ok(found)
+    }
+    fn parse_magic(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let found = self.remaining().get(..4).unwrap_or(self.remaining());
+        if found != constants::MAGIC_NUMBER {
+            let found = found.to_vec();
+            return Err(DecodeError::new(
+                None,
+                0,
+                DecodeErrorKind::MagicMismatch { found },
+            ));
+        }
+        let header = found.to_vec();
+        self.skip(4);
+        Ok(header)
+    }
+
+    fn parse_section(&mut self) -> Result<(), DecodeError> {
+        let offset = self.offset;
+        let section_id = self
+            .read_leb_u32()
+            .map_err(|err| DecodeError::new(None, offset, err))?;
+        if section_id > 12 {
+            return Err(DecodeError::new(
+                None,
+                offset,
+                DecodeErrorKind::UnknownSection { id: section_id },
+            ));
+        }
+        let id = Some(section_id as u8);
+
+        let section_byte_count = self
+            .read_leb_u32()
+            .map_err(|err| DecodeError::new(id, self.offset, err))?;
+
+        macro_rules! decode_section {
+            ( $x:ident ) => {{
+                self.section.$x.offset = self.offset;
+                self.section.$x.byte_count = self.offset as u32 + section_byte_count;
+
+                // 出错时 section 自己的 offset 停在出错的位置
+                if let Err(err) = self.section.$x.decode(&mut self.ops) {
+                    return Err(self.section_error(section_id as u8, self.section.$x.offset, err));
+                }
+
+                self.section.$x.offset = offset;
+                self.section.$x.byte_count = section_byte_count;
+                self.skip(section_byte_count);
+                debug!(
+                    "decoded section {section_id} ({}) at {offset:#x}, {section_byte_count} bytes",
+                    stringify!($x)
+                );
+            }};
+        }
+
+        match section_id {
+            0 => decode_section!(custom),
+            1 => decode_section!(types),
+            2 => decode_section!(import),
+            3 => decode_section!(func),
+            4 => decode_section!(table),
+            5 => decode_section!(memory),
+            6 => decode_section!(global),
+            7 => decode_section!(export),
+            8 => decode_section!(start),
+            9 => decode_section!(element),
+            10 => {
+                let start = self.ops.len();
+                self.section.code.lazy = self.config.lazy_decode;
+                decode_section!(code);
+                self.check_data_refs(start)
+                    .map_err(|kind| DecodeError::new(id, offset, kind))?;
+            }
+            11 => decode_section!(data),
+            12 => decode_section!(data_count),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// raw 可以是 `Vec<u8>`，也可以是多个实例共用的 `Arc<[u8]>`
+    pub fn default(raw: impl Into<Arc<[u8]>>) -> WasmModule {
+        let raw: Arc<[u8]> = raw.into();
+        Self {
+            raw: raw.clone(),
+            offset: 0,
+            length: raw.len(),
+            magic_number: vec![],
+            version: 0,
+            section: Section {
+                custom: section::custom::default(raw.clone()),
+                types: section::types::default(raw.clone()),
+                import: section::import::default(raw.clone()),
+                func: section::func::default(raw.clone()),
+                table: section::table::default(raw.clone()),
+                memory: section::memory::default(raw.clone()),
+                global: section::global::default(raw.clone()),
+                export: section::export::default(raw.clone()),
+                start: section::start::default(raw.clone()),
+                element: section::element::default(raw.clone()),
+                code: section::code::default(raw.clone()),
+                data: section::data::default(raw.clone()),
+                data_count: section::data_count::default(raw.clone()),
+            },
+            pc: 0,
+            sp: 0,
+            fp: 0,
+            csp: 0,
+            stack: Default::default(),
+            table: Default::default(),
+            mem: Default::default(),
+            data: Default::default(),
+            elem: Default::default(),
+            global: Default::default(),
+            exports: Default::default(),
+            func: Default::default(),
+            ops: Default::default(),
+            code: Default::default(),
+            threaded: Default::default(),
+            raw_funcs: Default::default(),
+            raw_stack: Default::default(),
+            raw_sp: 0,
+            #[cfg(feature = "jit")]
+            jit: Default::default(),
+            config: Default::default(),
+            stats: Default::default(),
+            policy: Default::default(),
+            frames: vec![],
+            debug_info: Default::default(),
+            suspending: false,
+            raised: None,
+            host_depth: 0,
+            suspended: None,
+            yield_at: None,
+            id: 0,
+            foreign: vec![],
+            linked_funcs: Default::default(),
+            host_funcs: Default::default(),
+            #[cfg(feature = "async")]
+            async_funcs: Default::default(),
+            #[cfg(feature = "async")]
+            pending: Default::default(),
+        }
+    }
+}
+
+/// 解码结果，开启 `serde` feature 时可以序列化缓存，
+/// 之后用 `WasmModule::from_decoded` 加载而不用重新解码
+#[cfg(feature = "serde")]
+#[derive(Debug, serde::Deserialize)]
+pub struct Decoded {
+    /// 模块的字节数，加载时用来检查是否是同一个模块
+    pub size: usize,
+    pub magic_number: Vec<u8>,
+    pub version: u32,
+    pub section: Section,
+    pub ops: Vec<Opcode>,
+}
+
+/// 与 `Decoded` 相同的结构，序列化时借用模块中的数据
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct DecodedRef<'a> {
+    size: usize,
+    magic_number: &'a [u8],
+    version: u32,
+    section: &'a Section,
+    ops: &'a [Opcode],
+}
+
+#[cfg(feature = "serde")]
+impl WasmModule {
+    /// 可以序列化的解码结果，反序列化为 `Decoded`
+    pub fn decoded(&self) -> impl serde::Serialize + '_ {
+        DecodedRef {
+            size: self.raw.len(),
+            magic_number: &self.magic_number,
+            version: self.version,
+            section: &self.section,
+            ops: &self.ops,
+        }
+    }
+
+    /// 由缓存的解码结果和模块原始字节得到与 `decode` 之后相同的模块
+    pub fn from_decoded(raw: Vec<u8>, decoded: Decoded) -> anyhow::Result<WasmModule> {
+        ensure!(
+            decoded.size == raw.len(),
+            "decoded module is {} bytes, but the module is {} bytes",
+            decoded.size,
+            raw.len()
+        );
+        let mut wasm = WasmModule::default(raw);
+        let raw = wasm.raw.clone();
+        let mut section = decoded.section;
+        // 各段的原始字节不序列化，指向同一份模块字节
+        section.custom.raw = raw.clone();
+        section.types.raw = raw.clone();
+        section.import.raw = raw.clone();
+        section.func.raw = raw.clone();
+        section.table.raw = raw.clone();
+        section.memory.raw = raw.clone();
+        section.global.raw = raw.clone();
+        section.export.raw = raw.clone();
+        section.start.raw = raw.clone();
+        section.element.raw = raw.clone();
+        section.code.raw = raw.clone();
+        section.data.raw = raw.clone();
+        section.data_count.raw = raw;
+        wasm.section = section;
+        wasm.ops = decoded.ops;
+        wasm.magic_number = decoded.magic_number;
+        wasm.version = decoded.version;
+        wasm.offset = wasm.length;
+        wasm.debug_info = DebugInfo::load(
+            &wasm.raw,
+            &wasm.section.custom.payloads,
+            wasm.section.code.content_offset,
+        );
+        Ok(wasm)
+    }
+
+    /// 预编译模块：解码和降级的结果，加上格式版本和模块哈希，见 `cache` 模块
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        let lowered;
+        let code = match self.code.instrs.len() == self.ops.len() {
+            true => &self.code,
+            false => {
+                lowered = ir::lower(self);
+                &lowered
+            }
+        };
+        Ok(cache::encode(&self.raw, &(self.decoded(), code))?)
+    }
+
+    /// 加载 `serialize` 得到的预编译模块，raw 为原来的模块字节，不一致时返回错误
+    pub fn deserialize(raw: Vec<u8>, bytes: &[u8]) -> anyhow::Result<WasmModule> {
+        let (decoded, code): (Decoded, ir::Code) = cache::decode(&raw, bytes)?;
+        ensure!(
+            code.instrs.len() == decoded.ops.len(),
+            "compiled module has {} instructions for {} ops",
+            code.instrs.len(),
+            decoded.ops.len()
+        );
+        let mut wasm = Self::from_decoded(raw, decoded)?;
+        wasm.code = code;
+        Ok(wasm)
+    }
+
+    /// `oxygen compile` 的产物：模块字节加上预编译模块，不需要原来的文件就能加载
+    pub fn compile_artifact(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(cache::artifact(&self.raw, &self.serialize()?))
+    }
+
+    /// 加载 `compile_artifact` 得到的产物
+    pub fn from_artifact(bytes: &[u8]) -> anyhow::Result<WasmModule> {
+        let (raw, compiled) = cache::split_artifact(bytes)?;
+        Self::deserialize(raw.to_vec(), compiled)
+    }
+}
+
+/// 解码任意字节，供模糊测试使用：不输出任何内容，不会 panic，所有问题都以错误返回
+pub fn decode_unchecked(bytes: &[u8]) -> Result<WasmModule, DecodeError> {
+    let mut wasm = WasmModule::default(bytes.to_vec());
+    wasm.parse_header()?;
+    wasm.parse_sections()?;
+    wasm.check_counts()?;
+    Ok(wasm)
+}
+
+impl Display for WasmModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Type: \\0asm")?;
+        writeln!(f, "Version: {:x?}", self.version)?;
+        writeln!(f, "Size: {:?}\n", self.raw.len())?;
+        write!(f, "{}", self.section.custom)?;
+        write!(f, "{}", self.section.types)?;
+        write!(f, "{}", self.section.import)?;
+        write!(f, "{}", self.section.func)?;
+
+        write!(f, "{}", self.section.table)?;
+        write!(f, "{}", self.section.memory)?;
+        write!(f, "{}", self.section.global)?;
+
+        write!(f, "{}", self.section.export)?;
+
+        write!(f, "{}", self.section.start)?;
+        write!(f, "{}", self.section.element)?;
+
+        write!(f, "{}", self.section.code)?;
+
+        write!(f, "{}", self.section.data)?;
+
+        writeln!(f, "-----ops------------------")?;
+        let mut level = 0isize;
+        for item in self.ops.iter().enumerate() {
+            writeln!(
+                f,
+                "{} {}{:?}",
+                item.0,
+                "    ".repeat(level as usize),
+                item.1
+            )?;
+            match item.1 {
+                Opcode::Block(_, _) => level += 1,
+                Opcode::Loop(_, _) => level += 1,
+                Opcode::If(_, _) => level += 1,
+                Opcode::Else(_) => level += 1,
+                Opcode::End(_) => level -= 1,
+                Opcode::Br(_, _) => level -= 1,
+                Opcode::BrIf(_, _) => level -= 1,
+                Opcode::BrTable(_, _, _) => level -= 1,
+                Opcode::Return => level -= 1,
+                _ => {}
+            }
+            level = level.max(0) as isize;
+        }
+
+        Ok(())
+    }
+}
+
+pub enum ImportKind {
+    Func(HostFunc),
+    Value(WasmValue),
+    Memory(Limit),
+    /// 宿主的内存，导入它的实例共享同一份字节
+    SharedMemory(Memory),
+    /// 宿主的表，导入它的实例共享同一份元素
+    Table(Table),
+    /// 宿主的全局变量，导入它的实例共享同一个值
+    SharedGlobal(SharedGlobal),
+}
+pub type ImportObject = HashMap<String, HashMap<String, ImportKind>>;
+
+impl Display for ImportKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportKind::Func(_) => write!(f, "host func"),
+            ImportKind::Value(v) => match v.value_type() {
+                Some(ty) => write!(f, "global {ty}"),
+                None => write!(f, "global {v:?}"),
+            },
+            ImportKind::Memory(limit) => {
+                write!(f, "memory [{} ~ {}] pages", limit.minimum, limit.maximum)
+            }
+            ImportKind::SharedMemory(mem) => {
+                write!(f, "memory [{} ~ {}] pages", mem.size(), mem.maximum())
+            }
+            ImportKind::Table(table) => write!(
+                f,
+                "table {} [{} ~ {}]",
+                table.ty(),
+                table.size(),
+                table.maximum()
+            ),
+            ImportKind::SharedGlobal(global) => match global.mutability() {
+                true => write!(f, "global mut {}", global.ty()),
+                false => write!(f, "global {}", global.ty()),
+            },
+        }
+    }
+}
+
+impl WasmModule {
+    pub fn instance(&mut self, import_object: Option<ImportObject>) -> anyhow::Result<()> {
+        self.init_instance(import_object)?;
+        // 实例化的最后一步执行 start 段指定的函数
+        if self.section.start.has_start {
+            self.run_start_func(self.section.start.start_func)?;
+        }
+        Ok(())
+    }
+
+    /// 解析导入，初始化函数、全局变量、表和内存，不执行 start 函数
+    pub(crate) fn init_instance(
+        &mut self,
+        import_object: Option<ImportObject>,
+    ) -> anyhow::Result<()> {
+        self.pc = 0;
+        self.sp = 0;
+        self.csp = 0;
+        self.fp = 0;
+        self.stack_check();
+        if let Some(feature) = self.required_features().first() {
+            return Err(InstantiationError::Unsupported { feature }.into());
+        }
+
+        // 在 self 上建立新的状态，调用前的状态先放在 previous 中：
+        // 任何一步失败都把函数体放回 section，并恢复 previous 和 section
+        let mut section = std::mem::take(&mut self.section);
+        let previous = InstanceState::take(self);
+        let result = self.init_state(&mut section, import_object);
+        if result.is_err() {
+            let failed = InstanceState::take(self);
+            let bodies = failed.func.into_iter().filter_map(|f| match f {
+                FuncKind::Local((_, body)) => Some(body),
+                FuncKind::Import(..) => None,
+            });
+            for (entry, body) in section.code.entries.iter_mut().zip(bodies) {
+                *entry = body;
+            }
+            previous.restore(self);
+        }
+        self.section = section;
+        result?;
+        info!(
+            "instantiated module: {} functions, {} memories, {} tables, {} globals",
+            self.func.len(),
+            self.mem.len(),
+            self.table.len(),
+            self.global.len()
+        );
+        Ok(())
+    }
+
+    fn init_state(
+        &mut self,
+        section: &mut Section,
+        import_object: Option<ImportObject>,
+    ) -> anyhow::Result<()> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        self.id = NEXT_ID.fetch_add(1, AtomicOrdering::Relaxed);
+
+        for ipt in section.import.entries.iter() {
+            let expected = || ImportType::new(&section.types.entries, &ipt.kind);
+            let v = import_object
+                .as_ref()
+                .and_then(|object| object.get(&ipt.mod_name)?.get(&ipt.field_name))
+                .ok_or_else(|| InstantiationError::MissingImport {
+                    module: ipt.mod_name.clone(),
+                    field: ipt.field_name.clone(),
+                    expected: expected(),
+                })?;
+            match (&ipt.kind, v) {
+                (import::Kind::Func(tyidx), ImportKind::Func(f)) => {
+                    self.func.push(FuncKind::Import(*tyidx, *f));
+                }
+                (import::Kind::Table(ty, limit), ImportKind::Table(table))
+                    if ValueType::from_u8(*ty).ok() == Some(table.ty())
+                        && table.size() >= limit.minimum
+                        && (limit.flag & 0x01 == 0 || table.maximum() <= limit.maximum) =>
+                {
+                    self.config.limits.check_table(table.size())?;
+                    self.table.push(table.clone());
+                }
+                (import::Kind::Memory(mem), ImportKind::Memory(limit))
+                    if limit.minimum >= mem.minimum
+                        && (mem.flag & 0x01 == 0 || limit.maximum <= mem.maximum) =>
+                {
+                    let limits = &self.config.limits;
+                    limits.check_memory(limit.minimum)?;
+                    let maximum =
+                        limits.memory_pages(mem.maximum.min(limit.maximum).min(MAX_PAGES));
+                    let backend = self.config.memory_backend;
+                    self.mem
+                        .push(LinearMemory::new(backend, limit.minimum, maximum)?);
+                }
+                (import::Kind::Memory(limit), ImportKind::SharedMemory(mem))
+                    if mem.size() >= limit.minimum
+                        && (limit.flag & 0x01 == 0 || mem.maximum() <= limit.maximum) =>
+                {
+                    let limits = &self.config.limits;
+                    limits.check_memory(mem.size())?;
+                    let maximum = limits.memory_pages(limit.maximum.min(MAX_PAGES));
+                    self.mem.push(LinearMemory::shared(mem.clone(), maximum));
+                }
+                (import::Kind::Global(g), ImportKind::Value(v))
+                    if v.value_type() == Some(g.val_ty) =>
+                {
+                    self.global.push(if g.mutability {
+                        Global::Var(*v)
+                    } else {
+                        Global::Const(*v)
+                    });
+                }
+                (import::Kind::Global(g), ImportKind::SharedGlobal(global))
+                    if global.ty() == g.val_ty && global.mutability() == g.mutability =>
+                {
+                    self.global.push(Global::Shared(global.clone()));
+                }
+                (_, v) => {
+                    return Err(InstantiationError::IncompatibleImport {
+                        module: ipt.mod_name.clone(),
+                        field: ipt.field_name.clone(),
+                        expected: expected(),
+                        found: v.to_string(),
+                    }
+                    .into())
+                }
+            }
+        }
+
+        for (index, ty) in section.func.entries.iter().enumerate() {
+            let code = std::mem::take(&mut section.code.entries[index]);
+            self.func.push(FuncKind::Local((*ty, code)));
+        }
+
+        // init global
+        let imported = self.global.len();
+        for (index, g) in section.global.entries.iter().enumerate() {
+            let r = match self.eval_const(&g.expr, imported)? {
+                ConstValue::Num(v) if v.value_type() == Some(g.val_ty) => v,
+                ConstValue::Num(v) => {
+                    bail!(
+                        "global {index} is {} but its initializer is {v:?}",
+                        g.val_ty
+                    )
+                }
+                ConstValue::Ref(idx) => match g.val_ty {
+                    ValueType::FuncRef => WasmValue::FuncRef(idx),
+                    ValueType::ExternRef if idx == NULL_REF => WasmValue::ExternRef(idx),
+                    ty => {
+                        bail!("global {index} is {ty} but its initializer is a function reference")
+                    }
+                },
+            };
+            self.global.push(if g.mutability {
+                Global::Var(r)
+            } else {
+                Global::Const(r)
+            });
+        }
+
+        // init table
+        for table in section.table.entries.iter() {
+            self.config.limits.check_table(table.limits.minimum)?;
+            let ty = match table.kind {
+                RefKind::FuncRef => ValueType::FuncRef,
+                RefKind::ExternRef => ValueType::ExternRef,
+            };
+            self.table.push(Table::new(table.limits.clone(), ty)?);
+        }
+
+        for ele in section.element.entries.iter() {
+            use section::element::Element;
+            // passive 段保留内容供 table.init 使用，其余段实例化后即视为已丢弃
+            let items = match ele {
+                Element::E0x00(ele) => {
+                    self.init_table(0, &ele.ele.0, &ele.ele.1, imported)?;
+                    vec![]
+                }
+                Element::E0x02(ele) => {
+                    self.init_table(ele.ele.0, &ele.ele.1, &ele.ele.3, imported)?;
+                    vec![]
+                }
+                Element::E0x04(ele) => {
+                    let items = self.elem_items(&ele.ele.1, imported)?;
+                    self.init_table(0, &ele.ele.0, &items, imported)?;
+                    vec![]
+                }
+                Element::E0x06(ele) => {
+                    let items = self.elem_items(&ele.ele.3, imported)?;
+                    self.init_table(ele.ele.0, &ele.ele.1, &items, imported)?;
+                    vec![]
+                }
+                Element::E0x01(ele) => ele.ele.1.clone(),
+                Element::E0x05(ele) => self.elem_items(&ele.ele.1, imported)?,
+                Element::E0x03(_) | Element::E0x07(_) => vec![],
+            };
+            self.elem.push(items);
+        }
+
+        // init memory
+        for mem in section.memory.entries.iter() {
+            let limits = &self.config.limits;
+            let minimum = mem.limits.minimum;
+            limits.check_memory(minimum)?;
+            let maximum = limits.memory_pages(mem.limits.maximum.min(MAX_PAGES));
+            let backend = self.config.memory_backend;
+            self.mem.push(LinearMemory::new(backend, minimum, maximum)?);
+        }
+
+        for data in section.data.entries.iter() {
+            let (memory, code, bytes) = match &data.kind {
+                section::data::DataKind::Expr(code, bytes) => (0, code, bytes),
+                section::data::DataKind::MemIdx(memory, code, bytes) => (*memory, code, bytes),
+                section::data::DataKind::Vec(bytes) => {
+                    // passive 段保留内容供 memory.init 使用
+                    self.data.push(bytes.clone());
+                    continue;
+                }
+            };
+            let offset = self.eval_offset("data", code, imported)?;
+            let mem = self
+                .mem
+                .get_mut(memory)
+                .with_context(|| format!("unknown memory {memory}"))?;
+            // 超出内存当前大小时实例化失败，不自动增长
+            let dst = bulk_range(offset, bytes.len() as u32, mem.len()).ok_or(
+                Trap::MemoryOutOfBounds {
+                    addr: offset as u64 + bytes.len() as u64,
+                    size: mem.len(),
+                },
+            )?;
+            mem[dst].copy_from_slice(bytes);
+            self.data.push(vec![]);
+        }
+
+        for export in section.export.entries.iter() {
+            self.exports
+                .insert(export.name.clone(), export.kind.clone());
+        }
+        self.policy = self.resolve_policy()?;
+        Ok(())
+    }
+    fn resolve_policy(&self) -> anyhow::Result<CallPolicy> {
+        let resolve = |funcs: &Vec<FuncSelector>| -> anyhow::Result<HashSet<usize>> {
+            funcs
+                .iter()
+                .map(|func| match func {
+                    FuncSelector::Index(idx) => {
+                        ensure!(*idx < self.func.len(), "unknown function {idx}");
+                        Ok(*idx)
+                    }
+                    FuncSelector::Export(name) => match self.exports.get(name) {
+                        Some(ExportKind::Func(idx)) => Ok(*idx),
+                        _ => bail!("unknown exported function `{name}`"),
+                    },
+                })
+                .collect()
+        };
+        Ok(CallPolicy {
+            denied: resolve(&self.config.denied_funcs)?,
+            indirect_allowed: self
+                .config
+                .indirect_allowlist
+                .as_ref()
+                .map(resolve)
+                .transpose()?,
+        })
+    }
+    /// active 元素段：计算偏移量后把 items 写入第 table 个表
+    fn init_table(
+        &mut self,
+        table: usize,
+        offset: &(usize, usize, usize),
+        items: &[usize],
+        imported: usize,
+    ) -> anyhow::Result<()> {
+        let offset = self.eval_offset("element", offset, imported)?;
+        ensure!(table < self.table.len(), "unknown table {table}");
+        let items: Vec<_> = items.iter().map(|&v| self.ref_elem(table, v)).collect();
+        let mut table = self.table[table].elements_mut();
+        let range =
+            bulk_range(offset, items.len() as u32, table.len()).ok_or(Trap::TableOutOfBounds {
+                index: offset as u64 + items.len() as u64,
+                size: table.len(),
+            })?;
+        table[range].copy_from_slice(&items);
+        Ok(())
+    }
+    fn elem_items(
+        &self,
+        exprs: &[(usize, usize, usize)],
+        imported: usize,
+    ) -> anyhow::Result<Vec<usize>> {
+        exprs
+            .iter()
+            .map(|expr| self.eval_ref(expr, imported))
+            .collect()
+    }
+    fn run_start_func(&mut self, idx: usize) -> anyhow::Result<()> {
+        let ty = match self.func.get(idx) {
+            Some(FuncKind::Import(ty, _)) | Some(FuncKind::Local((ty, _))) => *ty,
+            None => bail!("unknown start function {idx}"),
+        };
+        let ty = &self.section.types.entries[ty];
+        ensure!(
+            ty.params.is_empty() && ty.results.is_empty(),
+            "start function {idx} must have type [] -> []"
+        );
+        debug!("running start function {idx}");
+        self.sp = 0;
+        self.fp = 0;
+        self.call(idx)?;
+        Ok(())
+    }
+    pub fn stack_check(&mut self) {
+        if self.stack.len() <= self.sp {
+            self.stack.resize_with(self.sp + 512, Default::default);
+        }
+    }
+    pub fn run(&mut self, offset: usize) -> Result<(), Trap> {
+        self.run_at(offset, offset)
+    }
+    /// 从 pc 开始执行 offset 处开始的函数体
+    fn run_at(&mut self, offset: usize, pc: usize) -> Result<(), Trap> {
+        // ops 有变化（或还没有降级）时重新生成
+        if self.code.instrs.len() != self.ops.len() {
+            self.code = ir::lower(self);
+        }
+        match self.config.engine {
+            _ if self.config.debugger.is_some() => self.run_match(offset, pc),
+            Engine::Match => self.run_match(offset, pc),
+            Engine::Threaded => threaded::run(self, pc),
+            #[cfg(feature = "jit")]
+            Engine::Jit => self.run_match(offset, pc),
+        }
+    }
+    fn run_match(&mut self, offset: usize, pc: usize) -> Result<(), Trap> {
+        self.pc = pc;
+        loop {
+            let instr = self.code.instrs[self.pc];
+            let count = self.stats.instructions;
+            self.stats.instructions += instr.width();
+            // 每执行 1024 条指令检查一次是否被取消或需要让出
+            if count / CANCEL_CHECK_INTERVAL != self.stats.instructions / CANCEL_CHECK_INTERVAL {
+                self.checkpoint(count)?;
+            }
+            if let Some(tracer) = &self.config.tracer {
+                if let Some(loc) = self.source_location(self.pc) {
+                    tracer.lock().unwrap().location(&loc);
+                }
+                let stack = self.stack.get(self.fp..self.sp + 1).unwrap_or_default();
+                tracer
+                    .lock()
+                    .unwrap()
+                    .op(self.pc, &self.ops[self.pc], stack);
+            }
+            if let Some(debugger) = self.config.debugger.clone() {
+                self.debug_pause(&debugger, offset)?;
+            }
+            match instr {
+                Instr::Op => self.step()?,
+                Instr::Nop => {}
+                Instr::Jump(branch) => {
+                    self.branch(&branch);
+                    continue;
+                }
+                Instr::BrIf(branch) => {
+                    if self.pop_i32()? != 0 {
+                        self.branch(&branch);
+                        continue;
+                    }
+                }
+                Instr::BrTable(table) => {
+                    let v = self.pop_i32()? as u32 as usize;
+                    let targets = &self.code.tables[table];
+                    let branch = targets[v.min(targets.len() - 1)];
+                    self.branch(&branch);
+                    continue;
+                }
+                Instr::If(other) => {
+                    if self.pop_i32()? == 0 {
+                        self.pc = other;
+                        continue;
+                    }
+                }
+                Instr::Return => break,
+                Instr::LocalGet(idx) => {
+                    // 将指定局部变量压入到操作数栈顶
+                    self.sp += 1;
+                    self.stack[self.sp] = self.stack[self.fp + idx as usize];
+                }
+                Instr::LocalSet(idx) => {
+                    // 将操作数栈顶的值弹出并保存到指定局部变量中
+                    self.stack[self.fp + idx as usize] = self.stack[self.sp];
+                    self.sp -= 1;
+                }
+                Instr::LocalTee(idx) => {
+                    // 将操作数栈顶值保存到指定局部变量中，但不弹出栈顶值
+                    self.stack[self.fp + idx as usize] = self.stack[self.sp];
+                }
+                Instr::I32Const(value) => {
+                    self.sp += 1;
+                    self.stack[self.sp] = WasmValue::I32(value);
+                }
+                Instr::LocalGet2(a, b) => {
+                    self.stack[self.sp + 1] = self.stack[self.fp + a as usize];
+                    self.stack[self.sp + 2] = self.stack[self.fp + b as usize];
+                    self.sp += 2;
+                    self.pc += 2;
+                    continue;
+                }
+                Instr::I32AddImm(value) | Instr::I32SubImm(value) => {
+                    let v = self.stack[self.sp];
+                    if v.value_type() != Some(ValueType::I32) {
+                        return Err(Trap::mismatch(ValueType::I32, v));
+                    }
+                    self.stack[self.sp] = match instr {
+                        Instr::I32AddImm(_) => v + WasmValue::I32(value),
+                        _ => v - WasmValue::I32(value),
+                    };
+                    self.pc += 2;
+                    continue;
+                }
+                Instr::BrIfEqz(branch) => {
+                    if self.pop_i32()? == 0 {
+                        self.branch(&branch);
+                    } else {
+                        self.pc += 2;
+                    }
+                    continue;
+                }
+                Instr::BrOnNull(branch) => {
+                    if self.top_is_null()? {
+                        self.sp -= 1;
+                        self.branch(&branch);
+                        continue;
+                    }
+                }
+                Instr::BrOnNonNull(branch) => {
+                    if !self.top_is_null()? {
+                        self.branch(&branch);
+                        continue;
+                    }
+                    self.sp -= 1;
+                }
+            }
+            self.pc += 1;
+        }
+        Ok(())
+    }
+    /// 保留栈顶 arity 个值并丢弃它们下面的 drop 个值，然后跳转到 target
+    pub(crate) fn branch(&mut self, branch: &ir::Branch) {
+        if branch.drop > 0 {
+            let top = self.sp + 1;
+            let arity = branch.arity as usize;
+            let drop = branch.drop as usize;
+            self.stack.copy_within(top - arity..top, top - arity - drop);
+            self.sp -= drop;
+        }
+        self.pc = branch.target;
+    }
+    /// 按原 Opcode 执行 ops[pc]（没有降级的指令），不修改 pc
+    pub(crate) fn step(&mut self) -> Result<(), Trap> {
+        let op = &self.ops[self.pc];
+        // 数值指令执行前统一检查操作数类型
+        if let Some(ty) = op.operand_type() {
+            for depth in 0..op.operand_count() {
+                let val = self.stack[self.sp - depth];
+                if val.value_type() != Some(ty) {
+                    return Err(Trap::mismatch(ty, val));
+                }
+            }
+        }
+        match op {
+            Opcode::Unreachable => return Err(Trap::Unreachable),
+            // 控制指令、局部变量和 i32.const 已经降级，由执行引擎直接处理
+            Opcode::Nop
+            | Opcode::Block(..)
+            | Opcode::Loop(..)
+            | Opcode::If(..)
+            | Opcode::Else(_)
+            | Opcode::End(_)
+            | Opcode::Return
+            | Opcode::LocalGet(_)
+            | Opcode::LocalSet(_)
+            | Opcode::LocalTee(_)
+            | Opcode::I32Const(_) => unreachable!("{op:?} is lowered"),
+            // 降级时推导不出要保留和丢弃的值的分支，函数体不合法
+            Opcode::Br(..)
+            | Opcode::BrIf(..)
+            | Opcode::BrTable(..)
+            | Opcode::BrOnNull(..)
+            | Opcode::BrOnNonNull(..) => {
+                let pc = self.pc;
+                let func = self.func.iter().position(|f| {
+                    matches!(f, FuncKind::Local((_, body)) if (body.code.0..=body.code.2).contains(&pc))
+                });
+                return Err(Trap::MalformedBody {
+                    func: func.unwrap_or_default(),
+                    message: format!("invalid operand stack height at {op:?}"),
+                });
+            }
+            Opcode::Call(idx) => {
+                let res = self.call(*idx as usize)?;
+                for v in res {
+                    // push return value and clear stack
+                    self.sp += 1;
+                    self.stack[self.sp] = v;
+                }
+            }
+            Opcode::CallIndirect(tyidx, tableidx) => {
+                let (tyidx, tableidx) = (*tyidx as usize, *tableidx as usize);
+                let index = self.pop_i32()? as u32;
+                let elem = self
+                    .table
+                    .get(tableidx)
+                    .and_then(|table| table.get_elem(index))
+                    .ok_or(Trap::UndefinedElement { index })?;
+                if elem.value == NULL_REF {
+                    return Err(Trap::UninitializedElement { index });
+                }
+                let idx = self.elem_ref(tableidx, elem);
+                let allowed = &self.policy.indirect_allowed;
+                if allowed.as_ref().is_some_and(|funcs| !funcs.contains(&idx)) {
+                    return Err(Trap::Forbidden { func: idx });
+                }
+                if idx >= self.func.len() {
+                    return self.call_foreign(tyidx, idx);
+                }
+                self.check_signature(tyidx, idx)?;
+                let res = self.call(idx)?;
+                for v in res {
+                    // push return value and clear stack
+                    self.sp += 1;
+                    self.stack[self.sp] = v;
+                }
+            }
+            Opcode::CallRef(tyidx) => {
+                let idx = match self.stack[self.sp] {
+                    WasmValue::FuncRef(NULL_REF) => return Err(Trap::NullReference),
+                    WasmValue::FuncRef(idx) => idx,
+                    v => return Err(Trap::mismatch(ValueType::FuncRef, v)),
+                };
+                self.sp -= 1;
+                if idx >= self.func.len() {
+                    return self.call_foreign(*tyidx as usize, idx);
+                }
+                // 引用的类型索引在解码时被擦除，这里检查被调函数的签名
+                self.check_signature(*tyidx as usize, idx)?;
+                for value in self.call(idx)? {
+                    self.sp += 1;
+                    self.stack[self.sp] = value;
+                }
+            }
+            Opcode::RefNull(ty) => {
+                self.sp += 1;
+                self.stack[self.sp] = match ty {
+                    0x6f => WasmValue::ExternRef(NULL_REF),
+                    _ => WasmValue::FuncRef(NULL_REF),
+                };
+            }
+            Opcode::RefIsNull => {
+                let null = self.top_is_null()?;
+                self.stack[self.sp] = WasmValue::I32(null as i32);
+            }
+            Opcode::RefAsNonNull => {
+                if self.top_is_null()? {
+                    return Err(Trap::NullReference);
+                }
+            }
+            Opcode::RefFunc(idx) => {
+                self.sp += 1;
+                self.stack[self.sp] = WasmValue::FuncRef(*idx as usize);
+            }
+            Opcode::Drop => {
+                self.sp -= 1;
+            }
+            // 条件非 0 时选第一个操作数，负数也是真
+            Opcode::Select | Opcode::SelectType(_, _) => {
+                let con = self.top_i32()?;
+                let second = self.stack[self.sp - 1];
+                self.sp -= 2;
+                if con == 0 {
+                    self.stack[self.sp] = second;
+                }
+            }
+            Opcode::GlobalGet(v) => {
+                // 将指定全局变量压入到操作数栈顶
+                let r = self.global[*v as usize].get();
+                self.sp += 1;
+                self.stack[self.sp] = r;
+            }
+            Opcode::GlobalSet(idx) => {
+                // 操作数栈顶的值弹出并保存到指定全局变量中
+                let v = self.stack[self.sp];
+                self.sp -= 1;
+                self.global[*idx as usize].set(v);
+            }
+            Opcode::TableGet(idx) => {
+                let idx = *idx as usize;
+                let table = &self.table[idx];
+                let index = self.top_i32()? as u32;
+                let elem = table.get_elem(index).ok_or(Trap::TableOutOfBounds {
+                    index: index as u64,
+                    size: table.size() as usize,
+                })?;
+                let ty = table.ty();
+                let value = self.elem_ref(idx, elem);
+                self.stack[self.sp] = match ty {
+                    ValueType::ExternRef => WasmValue::ExternRef(value),
+                    _ => WasmValue::FuncRef(value),
+                };
+            }
+            Opcode::TableSet(idx) => {
+                let idx = *idx as usize;
+                let elem = self.pop_ref()?;
+                let elem = self.ref_elem(idx, elem);
+                let index = self.pop_i32()? as u32;
+                let table = &self.table[idx];
+                if !table.set_elem(index, elem) {
+                    return Err(Trap::TableOutOfBounds {
+                        index: index as u64,
+                        size: table.size() as usize,
+                    });
+                }
+            }
+            Opcode::I32Load(memarg) => {
+                let b = self.load::<4>(memarg)?;
+                self.stack[self.sp] = WasmValue::I32(i32::from_le_bytes(b));
+            }
+            Opcode::I64Load(memarg) => {
+                let b = self.load::<8>(memarg)?;
+                self.stack[self.sp] = WasmValue::I64(i64::from_le_bytes(b));
+            }
+            Opcode::F32Load(memarg) => {
+                let b = self.load::<4>(memarg)?;
+                self.stack[self.sp] = WasmValue::F32(f32::from_le_bytes(b));
+            }
+            Opcode::F64Load(memarg) => {
+                let b = self.load::<8>(memarg)?;
+                self.stack[self.sp] = WasmValue::F64(f64::from_le_bytes(b));
+            }
+            Opcode::I32Load8s(memarg) => {
+                let b = self.load::<1>(memarg)?;
+                self.stack[self.sp] = WasmValue::I32(b[0] as i8 as i32);
+            }
+            Opcode::I32Load8u(memarg) => {
+                let b = self.load::<1>(memarg)?;
+                self.stack[self.sp] = WasmValue::I32(b[0] as i32);
+            }
+            Opcode::I32Load16s(memarg) => {
+                let b = self.load::<2>(memarg)?;
+                self.stack[self.sp] = WasmValue::I32(i16::from_le_bytes(b) as i32);
+            }
+            Opcode::I32Load16u(memarg) => {
+                let b = self.load::<2>(memarg)?;
+                self.stack[self.sp] = WasmValue::I32(u16::from_le_bytes(b) as i32);
+            }
+            Opcode::I64Load8s(memarg) => {
+                let b = self.load::<1>(memarg)?;
+                self.stack[self.sp] = WasmValue::I64(b[0] as i8 as i64);
+            }
+            Opcode::I64Load8u(memarg) => {
+                let b = self.load::<1>(memarg)?;
+                self.stack[self.sp] = WasmValue::I64(b[0] as i64);
+            }
+            Opcode::I64Load16s(memarg) => {
+                let b = self.load::<2>(memarg)?;
+                self.stack[self.sp] = WasmValue::I64(i16::from_le_bytes(b) as i64);
+            }
+            Opcode::I64Load16u(memarg) => {
+                let b = self.load::<2>(memarg)?;
+                self.stack[self.sp] = WasmValue::I64(u16::from_le_bytes(b) as i64);
+            }
+            Opcode::I64Load32s(memarg) => {
+                let b = self.load::<4>(memarg)?;
+                self.stack[self.sp] = WasmValue::I64(i32::from_le_bytes(b) as i64);
+            }
+            Opcode::I64Load32u(memarg) => {
+                let b = self.load::<4>(memarg)?;
+                self.stack[self.sp] = WasmValue::I64(u32::from_le_bytes(b) as i64);
+            }
+            Opcode::I32Store(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_i32()?;
+                self.store(memarg, &value.to_le_bytes())?;
+            }
+            Opcode::I64Store(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_i64()?;
+                self.store(memarg, &value.to_le_bytes())?;
+            }
+            Opcode::F32Store(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_f32()?;
+                self.store(memarg, &value.to_le_bytes())?;
+            }
+            Opcode::F64Store(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_f64()?;
+                self.store(memarg, &value.to_le_bytes())?;
+            }
+            Opcode::I32Store8(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_i32()?;
+                self.store(memarg, &value.to_le_bytes()[..1])?;
+            }
+            Opcode::I32Store16(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_i32()?;
+                self.store(memarg, &value.to_le_bytes()[..2])?;
+            }
+            Opcode::I64Store8(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_i64()?;
+                self.store(memarg, &value.to_le_bytes()[..1])?;
+            }
+            Opcode::I64Store16(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_i64()?;
+                self.store(memarg, &value.to_le_bytes()[..2])?;
+            }
+            Opcode::I64Store32(memarg) => {
+                let memarg = *memarg;
+                let value = self.pop_i64()?;
+                self.store(memarg, &value.to_le_bytes()[..4])?;
+            }
+            Opcode::MemorySize(idx) => {
+                let size = self
+                    .memory(*idx as usize)
+                    .map(|mem| mem.size())
+                    .unwrap_or(0);
+                self.sp += 1;
+                self.stack[self.sp] = WasmValue::I32(size as i32);
+            }
+            Opcode::MemoryGrow(idx) => {
+                let idx = *idx as usize;
+                let delta = self.top_i32()?;
+                // 失败时返回 -1
+                let old = self.memory(idx).and_then(|mut mem| mem.grow(delta as u32));
+                self.stack[self.sp] = WasmValue::I32(old.map(|v| v as i32).unwrap_or(-1));
+            }
+            Opcode::I64Const(val) => {
+                self.sp += 1;
+                self.stack[self.sp] = WasmValue::I64(*val);
+            }
+            Opcode::F32Const(val) => {
+                self.sp += 1;
+                self.stack[self.sp] = WasmValue::F32(*val);
+            }
+            Opcode::F64Const(val) => {
+                self.sp += 1;
+                self.stack[self.sp] = WasmValue::F64(*val);
+            }
+            Opcode::I32Eqz | Opcode::I64Eqz => {
+                // is or else not zero
+                let zero = match self.stack[self.sp] {
+                    WasmValue::I32(val) => val == 0,
+                    WasmValue::I64(val) => val == 0,
+                    v => return Err(Trap::mismatch(ValueType::I32, v)),
+                };
+                self.stack[self.sp] = WasmValue::I32(if zero { 1 } else { 0 });
+            }
+            Opcode::I32Eq | Opcode::I64Eq | Opcode::F32Eq | Opcode::F64Eq => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 == v2 { 1 } else { 0 });
+            }
+            Opcode::I32Ne | Opcode::I64Ne | Opcode::F32Ne | Opcode::F64Ne => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 != v2 { 1 } else { 0 });
+            }
+            Opcode::I32Lts | Opcode::I64Lts => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 < v2 { 1 } else { 0 });
+            }
+            Opcode::I32Ltu | Opcode::I64Ltu => self.int_compare(
+                |a, b| (a as u32) < (b as u32),
+                |a, b| (a as u64) < (b as u64),
+            )?,
+            Opcode::I32Gts | Opcode::I64Gts => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 > v2 { 1 } else { 0 });
+            }
+            Opcode::I32Gtu | Opcode::I64Gtu => self.int_compare(
+                |a, b| (a as u32) > (b as u32),
+                |a, b| (a as u64) > (b as u64),
+            )?,
+            Opcode::I32Les | Opcode::I64Les => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 <= v2 { 1 } else { 0 });
+            }
+            Opcode::I32Leu | Opcode::I64Leu => self.int_compare(
+                |a, b| (a as u32) <= (b as u32),
+                |a, b| (a as u64) <= (b as u64),
+            )?,
+            Opcode::I32Ges | Opcode::I64Ges => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 >= v2 { 1 } else { 0 });
+            }
+            Opcode::I32Geu | Opcode::I64Geu => self.int_compare(
+                |a, b| (a as u32) >= (b as u32),
+                |a, b| (a as u64) >= (b as u64),
+            )?,
+            Opcode::F32Lt | Opcode::F64Lt => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 < v2 { 1 } else { 0 });
+            }
+            Opcode::F32Gt | Opcode::F64Gt => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 > v2 { 1 } else { 0 });
+            }
+            Opcode::F32Le | Opcode::F64Le => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 <= v2 { 1 } else { 0 });
+            }
+            Opcode::F32Ge | Opcode::F64Ge => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = WasmValue::I32(if v1 >= v2 { 1 } else { 0 });
+            }
+            Opcode::I32Clz | Opcode::I64Clz => {
+                self.int_unary(|a| a.leading_zeros() as i32, |a| a.leading_zeros() as i64)?
+            }
+            Opcode::I32Ctz | Opcode::I64Ctz => {
+                self.int_unary(|a| a.trailing_zeros() as i32, |a| a.trailing_zeros() as i64)?
+            }
+            Opcode::I32Popcnt | Opcode::I64Popcnt => {
+                self.int_unary(|a| a.count_ones() as i32, |a| a.count_ones() as i64)?
+            }
+            Opcode::I32Add | Opcode::I64Add | Opcode::F32Add | Opcode::F64Add => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = self.float_result(v1 + v2);
+            }
+            Opcode::I32Sub | Opcode::I64Sub | Opcode::F32Sub | Opcode::F64Sub => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = self.float_result(v1 - v2);
+            }
+            Opcode::I32Mul | Opcode::I64Mul | Opcode::F32Mul | Opcode::F64Mul => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = self.float_result(v1 * v2);
+            }
+            Opcode::F32Div | Opcode::F64Div => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = self.float_result(v1 / v2);
+            }
+            Opcode::I32DivS | Opcode::I64DivS => {
+                self.int_division(i32::checked_div, i64::checked_div)?
+            }
+            Opcode::I32DivU | Opcode::I64DivU => self.int_division(
+                |a, b| Some((a as u32 / b as u32) as i32),
+                |a, b| Some((a as u64 / b as u64) as i64),
+            )?,
+            // INT_MIN % -1 为 0，不会溢出
+            Opcode::I32RemS | Opcode::I64RemS => self.int_division(
+                |a, b| Some(a.wrapping_rem(b)),
+                |a, b| Some(a.wrapping_rem(b)),
+            )?,
+            Opcode::I32RemU | Opcode::I64RemU => self.int_division(
+                |a, b| Some((a as u32 % b as u32) as i32),
+                |a, b| Some((a as u64 % b as u64) as i64),
+            )?,
+            Opcode::I32And | Opcode::I64And => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = v1 & v2;
+            }
+            Opcode::I32Or | Opcode::I64Or => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = v1 | v2;
+            }
+            Opcode::I32Xor | Opcode::I64Xor => {
+                let v1 = self.stack[self.sp - 1];
+                let v2 = self.stack[self.sp];
+                self.sp -= 1;
+                self.stack[self.sp] = v1 ^ v2;
+            }
+            Opcode::I32Shl | Opcode::I64Shl => {
+                let val = self.stack[self.sp - 1];
+                let shift = self.stack[self.sp];
+                self.stack[self.sp - 1] = val << shift;
+                self.sp -= 1;
+            }
+            // 移位的位数按位宽取模
+            Opcode::I32ShlS | Opcode::I64ShlS => self.int_binary(
+                |a, b| a.wrapping_shr(b as u32),
+                |a, b| a.wrapping_shr(b as u32),
+            )?,
+            Opcode::I32ShlU | Opcode::I64ShlU => self.int_binary(
+                |a, b| (a as u32).wrapping_shr(b as u32) as i32,
+                |a, b| (a as u64).wrapping_shr(b as u32) as i64,
+            )?,
+            Opcode::I32Rotl | Opcode::I64Rotl => self.int_binary(
+                |a, b| a.rotate_left(b as u32),
+                |a, b| a.rotate_left(b as u32),
+            )?,
+            Opcode::I32Rotr | Opcode::I64Rotr => self.int_binary(
+                |a, b| a.rotate_right(b as u32),
+                |a, b| a.rotate_right(b as u32),
+            )?,
+            // abs/neg/copysign 只操作符号位，按规范不做 NaN 规范化
+            Opcode::F32Abs | Opcode::F64Abs => self.float_unary(f32::abs, f64::abs, false)?,
+            Opcode::F32Neg | Opcode::F64Neg => self.float_unary(|v| -v, |v| -v, false)?,
+            Opcode::F32Ceil | Opcode::F64Ceil => self.float_unary(f32::ceil, f64::ceil, true)?,
+            Opcode::F32Floor | Opcode::F64Floor => {
+                self.float_unary(f32::floor, f64::floor, true)?
+            }
+            Opcode::F32Trunc | Opcode::F64Trunc => {
+                self.float_unary(f32::trunc, f64::trunc, true)?
+            }
+            Opcode::F32Nearest | Opcode::F64Nearest => {
+                self.float_unary(float::f32_nearest, float::f64_nearest, true)?
+            }
+            Opcode::F32Sqrt | Opcode::F64Sqrt => self.float_unary(f32::sqrt, f64::sqrt, true)?,
+            Opcode::F32Min | Opcode::F64Min => {
+                self.float_binary(float::f32_min, float::f64_min, true)?
+            }
+            Opcode::F32Max | Opcode::F64Max => {
+                self.float_binary(float::f32_max, float::f64_max, true)?
+            }
+            Opcode::F32Copysign | Opcode::F64Copysign => {
+                self.float_binary(f32::copysign, f64::copysign, false)?
+            }
+            Opcode::I32WrapI64 => {
+                let val = self.top_i64()?;
+                self.stack[self.sp] = WasmValue::I32((val & 0x00000000_ffffffffi64) as i32);
+            }
+            // 截断后超出目标类型范围时 trap，范围的两端都不包含
+            Opcode::I32TruncF32s | Opcode::I32TruncF64s => {
+                let val = self.float_trunc(-2147483649.0, 2147483648.0)?;
+                self.stack[self.sp] = WasmValue::I32(val as i32);
+            }
+            Opcode::I32TruncF32u | Opcode::I32TruncF64u => {
+                let val = self.float_trunc(-1.0, 4294967296.0)?;
+                self.stack[self.sp] = WasmValue::I32(val as u32 as i32);
+            }
+            Opcode::I64ExtendsI32s => {
+                let val = self.top_i32()?;
+                self.stack[self.sp] = WasmValue::I64(val as i64);
+            }
+            Opcode::I64ExtendsI32u => {
+                let val = self.top_i32()?;
+                self.stack[self.sp] = WasmValue::I64(val as u32 as i64);
+            }
+            // -2^63 可以精确表示，下界取它前面的一个 f64
+            Opcode::I64TruncF32s | Opcode::I64TruncF64s => {
+                let val = self.float_trunc(-9223372036854777856.0, 9223372036854775808.0)?;
+                self.stack[self.sp] = WasmValue::I64(val as i64);
+            }
+            Opcode::I64TruncF32u | Opcode::I64TruncF64u => {
+                let val = self.float_trunc(-1.0, 18446744073709551616.0)?;
+                self.stack[self.sp] = WasmValue::I64(val as u64 as i64);
+            }
+            Opcode::F32ConvertI32s => {
+                self.stack[self.sp] = WasmValue::F32(self.top_i32()? as f32);
+            }
+            Opcode::F32ConvertI32u => {
+                self.stack[self.sp] = WasmValue::F32(self.top_i32()? as u32 as f32);
+            }
+            Opcode::F32ConvertI64s => {
+                self.stack[self.sp] = WasmValue::F32(self.top_i64()? as f32);
+            }
+            Opcode::F32ConvertI64u => {
+                self.stack[self.sp] = WasmValue::F32(self.top_i64()? as u64 as f32);
+            }
+            Opcode::F32DemoteF64 => {
+                let val = self.top_f64()?;
+                self.stack[self.sp] = self.float_result(WasmValue::F32(val as f32));
+            }
+            Opcode::F64ConvertI32s => {
+                self.stack[self.sp] = WasmValue::F64(self.top_i32()? as f64);
+            }
+            Opcode::F64ConvertI32u => {
+                self.stack[self.sp] = WasmValue::F64(self.top_i32()? as u32 as f64);
+            }
+            Opcode::F64ConvertI64s => {
+                self.stack[self.sp] = WasmValue::F64(self.top_i64()? as f64);
+            }
+            Opcode::F64ConvertI64u => {
+                self.stack[self.sp] = WasmValue::F64(self.top_i64()? as u64 as f64);
+            }
+            Opcode::F64DemoteF32 => {
+                // f64.promote_f32
+                let val = self.top_f32()?;
+                self.stack[self.sp] = self.float_result(WasmValue::F64(val as f64));
+            }
+            Opcode::I32ReinterpretF32 => {
+                let val = self.top_f32()?;
+                self.stack[self.sp] = WasmValue::I32(val.to_bits() as i32);
+            }
+            Opcode::I64ReinterpretF64 => {
+                let val = self.top_f64()?;
+                self.stack[self.sp] = WasmValue::I64(val.to_bits() as i64);
+            }
+            Opcode::F32ReinterpretI32 => {
+                let val = WasmValue::F32(f32::from_bits(self.top_i32()? as u32));
+                self.stack[self.sp] = self.float_result(val);
+            }
+            Opcode::F64ReinterpretI64 => {
+                let val = WasmValue::F64(f64::from_bits(self.top_i64()? as u64));
+                self.stack[self.sp] = self.float_result(val);
+            }
+            Opcode::I32Extends8s | Opcode::I64Extends8s => {
+                self.int_unary(|a| a as i8 as i32, |a| a as i8 as i64)?
+            }
+            Opcode::I32Extends16s | Opcode::I64Extends16s => {
+                self.int_unary(|a| a as i16 as i32, |a| a as i16 as i64)?
+            }
+            Opcode::I64Extends32s => {
+                let val = self.top_i64()?;
+                self.stack[self.sp] = WasmValue::I64(val as i32 as i64);
+            }
+            Opcode::FD(_) => todo!("Opcode::FD"),
+            // Rust 的 as 转换与 trunc_sat 一致：超出范围时取边界值，NaN 为 0
+            Opcode::I32TruncSatF32s | Opcode::I32TruncSatF64s => {
+                let val = self.top_float()?;
+                self.stack[self.sp] = WasmValue::I32(val as i32);
+            }
+            Opcode::I32TruncSatF32u | Opcode::I32TruncSatF64u => {
+                let val = self.top_float()?;
+                self.stack[self.sp] = WasmValue::I32(val as u32 as i32);
+            }
+            Opcode::I64TruncSatF32s | Opcode::I64TruncSatF64s => {
+                let val = self.top_float()?;
+                self.stack[self.sp] = WasmValue::I64(val as i64);
+            }
+            Opcode::I64TruncSatF32u | Opcode::I64TruncSatF64u => {
+                let val = self.top_float()?;
+                self.stack[self.sp] = WasmValue::I64(val as u64 as i64);
+            }
+            Opcode::MemoryInit(dataidx, memidx) => {
+                let (dataidx, memidx) = (*dataidx, *memidx as usize);
+                let n = self.pop_i32()? as u32;
+                let s = self.pop_i32()? as u32;
+                let d = self.pop_i32()? as u32;
+                let data = &self.data[dataidx];
+                let src = bulk_range(s, n, data.len()).ok_or(Trap::MemoryOutOfBounds {
+                    addr: s as u64 + n as u64,
+                    size: data.len(),
+                })?;
+                let mem = &mut self.mem[memidx];
+                let dst = bulk_range(d, n, mem.len()).ok_or(Trap::MemoryOutOfBounds {
+                    addr: d as u64 + n as u64,
+                    size: mem.len(),
+                })?;
+                mem[dst].copy_from_slice(&data[src]);
+            }
+            Opcode::DataDrop(idx) => {
+                self.data[*idx] = vec![];
+            }
+            Opcode::MemoryCopy(dstidx, srcidx) => {
+                let (dstidx, srcidx) = (*dstidx as usize, *srcidx as usize);
+                let n = self.pop_i32()? as u32;
+                let s = self.pop_i32()? as u32;
+                let d = self.pop_i32()? as u32;
+                let size = self.mem[srcidx].len();
+                let src = bulk_range(s, n, size).ok_or(Trap::MemoryOutOfBounds {
+                    addr: s as u64 + n as u64,
+                    size,
+                })?;
+                let size = self.mem[dstidx].len();
+                let dst = bulk_range(d, n, size).ok_or(Trap::MemoryOutOfBounds {
+                    addr: d as u64 + n as u64,
+                    size,
+                })?;
+                // 同一块内存的源和目标可以重叠
+                if dstidx == srcidx {
+                    self.mem[dstidx].copy_within(src, dst.start);
+                } else {
+                    let bytes = self.mem[srcidx][src].to_vec();
+                    self.mem[dstidx][dst].copy_from_slice(&bytes);
+                }
+            }
+            Opcode::MemoryFill(idx) => {
+                let idx = *idx as usize;
+                let n = self.pop_i32()? as u32;
+                let val = self.pop_i32()?;
+                let d = self.pop_i32()? as u32;
+                let mem = &mut self.mem[idx];
+                let dst = bulk_range(d, n, mem.len()).ok_or(Trap::MemoryOutOfBounds {
+                    addr: d as u64 + n as u64,
+                    size: mem.len(),
+                })?;
+                mem[dst].fill(val as u8);
+            }
+            Opcode::TableInit(elemidx, tableidx) => {
+                let (elemidx, tableidx) = (*elemidx, *tableidx);
+                let n = self.pop_i32()? as u32;
+                let s = self.pop_i32()? as u32;
+                let d = self.pop_i32()? as u32;
+                let elem = &self.elem[elemidx];
+                let src = bulk_range(s, n, elem.len()).ok_or(Trap::TableOutOfBounds {
+                    index: s as u64 + n as u64,
+                    size: elem.len(),
+                })?;
+                let items: Vec<_> = elem[src]
+                    .iter()
+                    .map(|&v| self.ref_elem(tableidx, v))
+                    .collect();
+                let mut table = self.table[tableidx].elements_mut();
+                let dst = bulk_range(d, n, table.len()).ok_or(Trap::TableOutOfBounds {
+                    index: d as u64 + n as u64,
+                    size: table.len(),
+                })?;
+                table[dst].copy_from_slice(&items);
+            }
+            Opcode::ElemDrop(idx) => {
+                self.elem[*idx] = vec![];
+            }
+            Opcode::TableCopy(dstidx, srcidx) => {
+                let (dstidx, srcidx) = (*dstidx, *srcidx);
+                let n = self.pop_i32()? as u32;
+                let s = self.pop_i32()? as u32;
+                let d = self.pop_i32()? as u32;
+                // 先复制出源表项：两个表可能是同一个表，范围也可能重叠
+                let elems = {
+                    let table = self.table[srcidx].elements();
+                    let src = bulk_range(s, n, table.len()).ok_or(Trap::TableOutOfBounds {
+                        index: s as u64 + n as u64,
+                        size: table.len(),
+                    })?;
+                    table[src].to_vec()
+                };
+                let mut table = self.table[dstidx].elements_mut();
+                let dst = bulk_range(d, n, table.len()).ok_or(Trap::TableOutOfBounds {
+                    index: d as u64 + n as u64,
+                    size: table.len(),
+                })?;
+                table[dst].copy_from_slice(&elems);
+            }
+            Opcode::TableGrow(idx) => {
+                let idx = *idx;
+                let n = self.pop_i32()? as u32;
+                let init = self.pop_ref()?;
+                let init = self.ref_elem(idx, init);
+                let maximum = self.config.limits.table_size(u32::MAX);
+                // 失败时返回 -1
+                let old = self.table[idx].grow(n, init, maximum);
+                self.sp += 1;
+                self.stack[self.sp] = WasmValue::I32(old.map(|v| v as i32).unwrap_or(-1));
+            }
+            Opcode::TableSize(idx) => {
+                self.sp += 1;
+                self.stack[self.sp] = WasmValue::I32(self.table[*idx].size() as i32);
+            }
+            Opcode::TableFill(idx) => {
+                let idx = *idx;
+                let n = self.pop_i32()? as u32;
+                let val = self.pop_ref()?;
+                let val = self.ref_elem(idx, val);
+                let i = self.pop_i32()? as u32;
+                let mut table = self.table[idx].elements_mut();
+                let dst = bulk_range(i, n, table.len()).ok_or(Trap::TableOutOfBounds {
+                    index: i as u64 + n as u64,
+                    size: table.len(),
+                })?;
+                table[dst].fill(val);
+            }
+            Opcode::Reserved(_) => todo!("Opcode::Reserved"),
+        }
+        Ok(())
+    }
+    fn top_i32(&self) -> Result<i32, Trap> {
+        match self.stack[self.sp] {
+            WasmValue::I32(v) => Ok(v),
+            v => Err(Trap::mismatch(ValueType::I32, v)),
+        }
+    }
+    fn top_i64(&self) -> Result<i64, Trap> {
+        match self.stack[self.sp] {
+            WasmValue::I64(v) => Ok(v),
+            v => Err(Trap::mismatch(ValueType::I64, v)),
+        }
+    }
+    fn top_f32(&self) -> Result<f32, Trap> {
+        match self.stack[self.sp] {
+            WasmValue::F32(v) => Ok(v),
+            v => Err(Trap::mismatch(ValueType::F32, v)),
+        }
+    }
+    fn top_f64(&self) -> Result<f64, Trap> {
+        match self.stack[self.sp] {
+            WasmValue::F64(v) => Ok(v),
+            v => Err(Trap::mismatch(ValueType::F64, v)),
+        }
+    }
+    /// 栈顶的 f32 或 f64，f32 转为 f64 不损失精度
+    fn top_float(&self) -> Result<f64, Trap> {
+        match self.stack[self.sp] {
+            WasmValue::F32(v) => Ok(v as f64),
+            WasmValue::F64(v) => Ok(v),
+            v => Err(Trap::mismatch(ValueType::F64, v)),
+        }
+    }
+    /// 截断栈顶的浮点数，NaN 或不在 (min, max) 范围内时 trap
+    fn float_trunc(&self, min: f64, max: f64) -> Result<f64, Trap> {
+        let val = self.top_float()?;
+        if val.is_nan() {
+            return Err(Trap::InvalidConversion);
+        }
+        if val <= min || val >= max {
+            return Err(Trap::IntegerOverflow);
+        }
+        Ok(val.trunc())
+    }
+    /// 栈顶的引用是否为空，不弹出
+    pub(crate) fn top_is_null(&self) -> Result<bool, Trap> {
+        match self.stack[self.sp] {
+            WasmValue::FuncRef(idx) | WasmValue::ExternRef(idx) => Ok(idx == NULL_REF),
+            v => Err(Trap::mismatch(ValueType::FuncRef, v)),
+        }
+    }
+    /// 弹出栈顶的引用，返回函数索引（或 externref 的值）
+    fn pop_ref(&mut self) -> Result<usize, Trap> {
+        let v = match self.stack[self.sp] {
+            WasmValue::FuncRef(idx) | WasmValue::ExternRef(idx) => idx,
+            v => return Err(Trap::mismatch(ValueType::FuncRef, v)),
+        };
+        self.sp -= 1;
+        Ok(v)
+    }
+    pub(crate) fn pop_i32(&mut self) -> Result<i32, Trap> {
+        let v = self.top_i32()?;
+        self.sp -= 1;
+        Ok(v)
+    }
+    fn pop_i64(&mut self) -> Result<i64, Trap> {
+        let v = self.top_i64()?;
+        self.sp -= 1;
+        Ok(v)
+    }
+    fn pop_f32(&mut self) -> Result<f32, Trap> {
+        let v = self.top_f32()?;
+        self.sp -= 1;
+        Ok(v)
+    }
+    fn pop_f64(&mut self) -> Result<f64, Trap> {
+        let v = self.top_f64()?;
+        self.sp -= 1;
+        Ok(v)
+    }
+    /// 浮点运算结果按配置做 NaN 规范化
+    fn float_result(&self, value: WasmValue) -> WasmValue {
+        if self.config.canonicalize_nans {
+            float::canonicalize(value)
+        } else {
+            value
+        }
+    }
+    fn float_unary(
+        &mut self,
+        f32op: fn(f32) -> f32,
+        f64op: fn(f64) -> f64,
+        canonical: bool,
+    ) -> Result<(), Trap> {
+        let val = match self.stack[self.sp] {
+            WasmValue::F32(v) => WasmValue::F32(f32op(v)),
+            WasmValue::F64(v) => WasmValue::F64(f64op(v)),
+            v => return Err(Trap::mismatch(ValueType::F64, v)),
+        };
+        self.stack[self.sp] = if canonical {
+            self.float_result(val)
+        } else {
+            val
+        };
+        Ok(())
+    }
+    fn float_binary(
+        &mut self,
+        f32op: fn(f32, f32) -> f32,
+        f64op: fn(f64, f64) -> f64,
+        canonical: bool,
+    ) -> Result<(), Trap> {
+        let v1 = self.stack[self.sp - 1];
+        let v2 = self.stack[self.sp];
+        let val = match (v1, v2) {
+            (WasmValue::F32(a), WasmValue::F32(b)) => WasmValue::F32(f32op(a, b)),
+            (WasmValue::F64(a), WasmValue::F64(b)) => WasmValue::F64(f64op(a, b)),
+            (WasmValue::F32(_), b) => return Err(Trap::mismatch(ValueType::F32, b)),
+            (WasmValue::F64(_), b) => return Err(Trap::mismatch(ValueType::F64, b)),
+            (a, _) => return Err(Trap::mismatch(ValueType::F64, a)),
+        };
+        self.sp -= 1;
+        self.stack[self.sp] = if canonical {
+            self.float_result(val)
+        } else {
+            val
+        };
+        Ok(())
+    }
+    /// 整数二元运算，操作数类型已经由 step 检查；无符号运算在 op 中转换位模式
+    fn int_binary(
+        &mut self,
+        i32op: fn(i32, i32) -> i32,
+        i64op: fn(i64, i64) -> i64,
+    ) -> Result<(), Trap> {
+        let v1 = self.stack[self.sp - 1];
+        let v2 = self.stack[self.sp];
+        let val = match (v1, v2) {
+            (WasmValue::I32(a), WasmValue::I32(b)) => WasmValue::I32(i32op(a, b)),
+            (WasmValue::I64(a), WasmValue::I64(b)) => WasmValue::I64(i64op(a, b)),
+            (WasmValue::I32(_), b) => return Err(Trap::mismatch(ValueType::I32, b)),
+            (a, _) => return Err(Trap::mismatch(ValueType::I64, a)),
+        };
+        self.sp -= 1;
+        self.stack[self.sp] = val;
+        Ok(())
+    }
+    /// 整数一元运算，结果写回栈顶
+    fn int_unary(&mut self, i32op: fn(i32) -> i32, i64op: fn(i64) -> i64) -> Result<(), Trap> {
+        self.stack[self.sp] = match self.stack[self.sp] {
+            WasmValue::I32(a) => WasmValue::I32(i32op(a)),
+            WasmValue::I64(a) => WasmValue::I64(i64op(a)),
+            v => return Err(Trap::mismatch(ValueType::I64, v)),
+        };
+        Ok(())
+    }
+    /// 整数除法和取余：除数为 0 时 trap，op 返回 None 表示结果溢出（INT_MIN / -1）
+    fn int_division(
+        &mut self,
+        i32op: fn(i32, i32) -> Option<i32>,
+        i64op: fn(i64, i64) -> Option<i64>,
+    ) -> Result<(), Trap> {
+        let v1 = self.stack[self.sp - 1];
+        let v2 = self.stack[self.sp];
+        let val = match (v1, v2) {
+            (WasmValue::I32(_), WasmValue::I32(0)) | (WasmValue::I64(_), WasmValue::I64(0)) => {
+                return Err(Trap::DivideByZero)
+            }
+            (WasmValue::I32(a), WasmValue::I32(b)) => {
+                WasmValue::I32(i32op(a, b).ok_or(Trap::IntegerOverflow)?)
+            }
+            (WasmValue::I64(a), WasmValue::I64(b)) => {
+                WasmValue::I64(i64op(a, b).ok_or(Trap::IntegerOverflow)?)
+            }
+            (WasmValue::I32(_), b) => return Err(Trap::mismatch(ValueType::I32, b)),
+            (a, _) => return Err(Trap::mismatch(ValueType::I64, a)),
+        };
+        self.sp -= 1;
+        self.stack[self.sp] = val;
+        Ok(())
+    }
+    /// 整数比较，结果为 i32 的 0 或 1
+    fn int_compare(
+        &mut self,
+        i32op: fn(i32, i32) -> bool,
+        i64op: fn(i64, i64) -> bool,
+    ) -> Result<(), Trap> {
+        let v1 = self.stack[self.sp - 1];
+        let v2 = self.stack[self.sp];
+        let res = match (v1, v2) {
+            (WasmValue::I32(a), WasmValue::I32(b)) => i32op(a, b),
+            (WasmValue::I64(a), WasmValue::I64(b)) => i64op(a, b),
+            (WasmValue::I32(_), b) => return Err(Trap::mismatch(ValueType::I32, b)),
+            (a, _) => return Err(Trap::mismatch(ValueType::I64, a)),
+        };
+        self.sp -= 1;
+        self.stack[self.sp] = WasmValue::I32(res as i32);
+        Ok(())
+    }
+    /// 访问 addr 越界时的 trap，size 为内存当前的大小
+    fn out_of_bounds(&self, memarg: &MemArg, addr: u64) -> Trap {
+        let size = self
+            .mem
+            .get(memarg.memory as usize)
+            .map_or(0, |mem| mem.len());
+        Trap::MemoryOutOfBounds { addr, size }
+    }
+    /// 以栈顶为地址读取 N 个字节，结果由调用方写回栈顶
+    fn load<const N: usize>(&self, memarg: &MemArg) -> Result<[u8; N], Trap> {
+        let addr = self.top_i32()? as u32 as u64 + memarg.offset as u64;
+        let bytes = self
+            .mem
+            .get(memarg.memory as usize)
+            .and_then(|mem| mem.load::<N>(addr))
+            .ok_or_else(|| self.out_of_bounds(memarg, addr))?;
+        if let Some(tracer) = &self.config.tracer {
+            tracer
+                .lock()
+                .unwrap()
+                .memory(Access::Load, memarg.memory, addr as usize, &bytes);
+        }
+        Ok(bytes)
+    }
+    /// 值已经出栈，弹出地址后写入
+    fn store(&mut self, memarg: MemArg, bytes: &[u8]) -> Result<(), Trap> {
+        let addr = self.pop_i32()? as u32 as u64 + memarg.offset as u64;
+        let stored = self
+            .mem
+            .get_mut(memarg.memory as usize)
+            .is_some_and(|mem| mem.store(addr, bytes));
+        if !stored {
+            return Err(self.out_of_bounds(&memarg, addr));
+        }
+        if let Some(tracer) = &self.config.tracer {
+            tracer
+                .lock()
+                .unwrap()
+                .memory(Access::Store, memarg.memory, addr as usize, bytes);
+        }
+        Ok(())
+    }
+    /// 第 table 个表的表项转为本实例中的引用值，其他实例写入的函数映射到 func 之后的代理索引
+    fn elem_ref(&mut self, table: usize, elem: Elem) -> usize {
+        let table = &self.table[table];
+        let foreign = elem.owner != 0 && elem.owner != self.id;
+        if table.ty() == ValueType::ExternRef || elem.value == NULL_REF || !foreign {
+            return elem.value;
+        }
+        let same = |f: &ForeignFunc| f.owner == elem.owner && f.func == elem.value;
+        let idx = match self.foreign.iter().position(same) {
+            Some(idx) => idx,
+            None => {
+                self.foreign.push(ForeignFunc {
+                    owner: elem.owner,
+                    func: elem.value,
+                    instance: table.owner(elem.owner),
+                });
+                self.foreign.len() - 1
+            }
+        };
+        self.func.len() + idx
+    }
+    /// 本实例中的引用值转为写入第 table 个表的表项
+    fn ref_elem(&self, table: usize, value: usize) -> Elem {
+        if self.table[table].ty() == ValueType::ExternRef || value == NULL_REF {
+            return Elem::host(value);
+        }
+        match value.checked_sub(self.func.len()) {
+            Some(idx) => Elem {
+                owner: self.foreign[idx].owner,
+                value: self.foreign[idx].func,
+            },
+            None => Elem {
+                owner: self.id,
+                value,
+            },
+        }
+    }
+    /// 调用其他实例写入共享表的函数（代理索引 idx），参数在栈顶，结果压栈
+    fn call_foreign(&mut self, tyidx: usize, idx: usize) -> Result<(), Trap> {
+        let foreign = self.foreign[idx - self.func.len()].clone();
+        let instance = foreign
+            .instance
+            .upgrade()
+            .ok_or(Trap::ForeignFunction { func: idx })?;
+        let expected = &self.section.types.entries[tyidx];
+        let (params, results) = (expected.params.clone(), expected.results.clone());
+        let args = self.stack[self.sp + 1 - params.len()..self.sp + 1].to_vec();
+        let Ok(mut wasm) = instance.try_lock() else {
+            return Err(Trap::InstanceBusy { func: foreign.func });
+        };
+        let ty = match wasm.func.get(foreign.func) {
+            Some(FuncKind::Import(ty, _) | FuncKind::Local((ty, _))) => *ty,
+            None => return Err(Trap::SignatureMismatch { func: idx }),
+        };
+        let found = &wasm.section.types.entries[ty];
+        if found.params != params || found.results != results {
+            return Err(Trap::SignatureMismatch { func: idx });
+        }
+        let res = wasm.call_with(foreign.func, &args)?;
+        drop(wasm);
+        self.sp -= params.len();
+        for value in res {
+            self.sp += 1;
+            self.stack[self.sp] = value;
+        }
+        Ok(())
+    }
+    /// call_indirect、call_ref 的被调函数 idx 的类型必须与指令声明的类型 tyidx 一致，
+    /// 否则参数个数不同时会破坏栈帧
+    fn check_signature(&self, tyidx: usize, idx: usize) -> Result<(), Trap> {
+        let ty = match self.func.get(idx) {
+            Some(FuncKind::Import(ty, _) | FuncKind::Local((ty, _))) => *ty,
+            None => return Err(Trap::SignatureMismatch { func: idx }),
+        };
+        let types = &self.section.types.entries;
+        let (expected, found) = (&types[tyidx], &types[ty]);
+        if expected.params != found.params || expected.results != found.results {
+            return Err(Trap::SignatureMismatch { func: idx });
+        }
+        Ok(())
+    }
+    /// 调用函数 idx，参数已经在栈顶；结果按函数类型中声明的顺序返回，
+    /// 函数体中的 return 和最外层的分支都回到这里，由 `leave` 恢复调用方的栈帧
+    pub fn call(&mut self, idx: usize) -> Result<Vec<WasmValue>, Trap> {
+        if self.policy.denied.contains(&idx) {
+            return Err(Trap::Forbidden { func: idx });
+        }
+        self.decode_pending(idx)?;
+        let limits = &self.config.limits;
+        if limits.max_call_depth.is_some_and(|max| self.csp >= max) {
+            return Err(Trap::StackOverflow);
+        }
+        self.csp += 1;
+        let res = self.call_traced(idx);
+        self.csp -= 1;
+        res
+    }
+    /// 当前指令处需要暂停时调用调试器，offset 为当前函数体的开始
+    fn debug_pause(&mut self, debugger: &SharedDebugger, offset: usize) -> Result<(), Trap> {
+        let Some(&func) = self.frames.last() else {
+            return Ok(());
+        };
+        let pc = self.pc - offset;
+        let mut debugger = debugger.lock().unwrap();
+        if !debugger.should_pause(func, pc) {
+            return Ok(());
+        }
+        let frame = Frame {
+            wasm: self,
+            func,
+            pc,
+        };
+        match debugger.pause(&frame) {
+            Resume::Abort => Err(Trap::Cancelled),
+            Resume::Step | Resume::Continue => Ok(()),
+        }
+    }
+    fn call_traced(&mut self, idx: usize) -> Result<Vec<WasmValue>, Trap> {
+        let Some(tracer) = self.config.tracer.clone() else {
+            #[cfg(feature = "jit")]
+            if self.config.engine == Engine::Jit && self.config.debugger.is_none() {
+                if let Some(func) = super::jit::jit_func(self, idx) {
+                    return match super::jit::call(self, func) {
+                        // 本地代码的函数帧不能保存
+                        Err(Trap::Suspended | Trap::Yielded) => {
+                            self.suspended = None;
+                            Err(Trap::SuspendUnsupported)
+                        }
+                        res => res,
+                    };
+                }
+            }
+            if self.config.untyped_stack && self.config.debugger.is_none() {
+                if let Some(func) = untyped::raw_func(self, idx) {
+                    return match untyped::call(self, func) {
+                        // u64 栈上的函数帧不能保存
+                        Err(Trap::Suspended | Trap::Yielded) => {
+                            self.suspended = None;
+                            Err(Trap::SuspendUnsupported)
+                        }
+                        res => res,
+                    };
+                }
+            }
+            return self.call_func(idx);
+        };
+        let ty = match &self.func[idx] {
+            FuncKind::Import(ty, _) | FuncKind::Local((ty, _)) => *ty,
+        };
+        let param_count = self.section.types.entries[ty].param_count as usize;
+        let args = &self.stack[self.sp + 1 - param_count..self.sp + 1];
+        tracer.lock().unwrap().call(idx, args);
+        let res = self.call_func(idx);
+        match &res {
+            Ok(results) => tracer.lock().unwrap().ret(idx, results),
+            Err(trap) => tracer.lock().unwrap().trap(idx, trap),
+        }
+        res
+    }
+    fn call_func(&mut self, idx: usize) -> Result<Vec<WasmValue>, Trap> {
+        let func = &self.func[idx];
+        let pc = self.pc;
+        let fp = self.fp;
+        let sp = self.sp;
+        match func {
+            FuncKind::Import(ty, f) => {
+                let ty = *ty;
+                let param_count = self.section.types.entries[ty].param_count as usize;
+                let mut params = vec![];
+                self.fp = self.sp - param_count + 1;
+
+                for i in 0..param_count {
+                    params.push(self.stack[self.fp + i].clone());
+                }
+                let f = *f;
+                if self.config.is_cancelled() {
+                    return Err(Trap::Cancelled);
+                }
+                *self.stats.host_calls.entry(idx).or_default() += 1;
+                if let Some((instance, func)) = self.linked_funcs.get(&idx).cloned() {
+                    let res = super::linker::call_linked(&instance, func, &params);
+                    self.pc = pc;
+                    self.fp = fp;
+                    self.sp = sp - param_count;
+                    return res;
+                }
+                if let Some((module, func)) = self.host_funcs.get(&idx).cloned() {
+                    self.host_depth += 1;
+                    let res = module.call(self, func, &params);
+                    self.host_depth -= 1;
+                    self.pc = pc;
+                    self.fp = fp;
+                    self.sp = sp - param_count;
+                    return match self.raised.take() {
+                        Some(trap) => Err(trap),
+                        None => self.check_results(ty, idx, res?),
+                    };
+                }
+                #[cfg(feature = "async")]
+                if let Some(func) = self.async_funcs.get(&idx).copied() {
+                    let future = func(self, &params);
+                    *self.pending.0.get_mut().unwrap() = Some(future);
+                }
+                self.host_depth += 1;
+                let res = f(self, &params);
+                self.host_depth -= 1;
+                self.pc = pc;
+                self.fp = fp;
+                self.sp = sp - param_count;
+                if let Some(trap) = self.raised.take() {
+                    return Err(trap);
+                }
+                if std::mem::take(&mut self.suspending) {
+                    self.suspended = Some(vec![]);
+                    return Err(Trap::Suspended);
+                }
+                self.check_results(ty, idx, res)
+            }
+            FuncKind::Local((ty, func)) => {
+                let param_count = self.section.types.entries[*ty].param_count as usize;
+                let result_count = self.section.types.entries[*ty].result_count as usize;
+                self.fp = self.sp - param_count + 1;
+                let locals = func.locales.iter().map(|(n, _)| *n as usize).sum::<usize>();
+                if let Some(max) = self.config.limits.max_value_stack {
+                    if self.sp + locals >= max {
+                        self.fp = fp;
+                        return Err(Trap::StackOverflow);
+                    }
+                }
+                let new_len = self.sp + locals + 512;
+
+                if self.stack.len() < new_len {
+                    self.stack.resize_with(new_len, Default::default);
+                }
+
+                for item in func.locales.iter() {
+                    use section::typings::ValueType::*;
+                    for _ in 0..item.0 {
+                        self.sp += 1;
+                        self.stack[self.sp] = match item.1 {
+                            ExternRef => WasmValue::ExternRef(NULL_REF),
+                            FuncRef => WasmValue::FuncRef(NULL_REF),
+                            I32 => WasmValue::I32(0),
+                            I64 => WasmValue::I64(0),
+                            F32 => WasmValue::F32(0.0),
+                            F64 => WasmValue::F64(0.0),
+                            V128 => WasmValue::V128(0),
+                            AnyRef => unreachable!("GC modules are rejected by instance"),
+                        };
+                    }
+                }
+                trace!(
+                    "call func{idx}({:?}) fp={}, sp={}",
+                    self.stack[self.fp..self.fp + param_count].to_vec(),
+                    self.fp,
+                    self.sp
+                );
+                let debugging = self.config.debugger.is_some();
+                if debugging {
+                    self.frames.push(idx);
+                }
+                let offset = func.code.0;
+                let res = self.run(offset);
+                if debugging {
+                    self.frames.pop();
+                }
+                if let (Err(trap @ (Trap::Suspended | Trap::Yielded)), Some(frames)) =
+                    (&res, &self.suspended)
+                {
+                    let next = resume_pc(trap, frames, self.pc);
+                    self.suspended.as_mut().unwrap().push(SuspendedFrame {
+                        func: idx,
+                        offset,
+                        pc: next,
+                        fp: self.fp,
+                        sp: self.sp,
+                        ret: (pc, fp, sp - param_count),
+                        result_count,
+                    });
+                    (self.pc, self.fp, self.sp) = (pc, fp, sp - param_count);
+                }
+                res?;
+                Ok(self.leave((pc, fp, sp - param_count), result_count))
+            }
+        }
+    }
+    /// 宿主函数返回的值必须与导入声明的结果类型一致，否则压栈后会破坏调用方的栈
+    fn check_results(
+        &self,
+        ty: usize,
+        idx: usize,
+        results: Vec<WasmValue>,
+    ) -> Result<Vec<WasmValue>, Trap> {
+        let expected = &self.section.types.entries[ty].results;
+        let matches = results.len() == expected.len()
+            && results
+                .iter()
+                .zip(expected)
+                .all(|(value, ty)| value.value_type() == Some(*ty));
+        if !matches {
+            return Err(Trap::Host {
+                message: format!("host function {idx} returned {results:?}, expected {expected:?}"),
+            });
+        }
+        Ok(results)
+    }
+    /// 取消或让出的检查点，count 为执行当前指令之前的指令数
+    pub(crate) fn checkpoint(&mut self, count: u64) -> Result<(), Trap> {
+        if self.config.is_cancelled() {
+            return Err(Trap::Cancelled);
+        }
+        if self
+            .yield_at
+            .is_some_and(|at| self.stats.instructions >= at)
+        {
+            // 当前指令恢复后才执行，不计入
+            self.stats.instructions = count;
+            self.yield_at = None;
+            self.suspended = Some(vec![]);
+            return Err(Trap::Yielded);
+        }
+        Ok(())
+    }
+    /// 函数返回：按声明的顺序取出栈顶的结果，恢复调用者的 pc、fp、sp
+    fn leave(
+        &mut self,
+        (pc, fp, sp): (usize, usize, usize),
+        result_count: usize,
+    ) -> Vec<WasmValue> {
+        let top = self.sp + 1;
+        (self.pc, self.fp, self.sp) = (pc, fp, sp);
+        self.stack[top - result_count..top].to_vec()
+    }
+    /// 在宿主函数中调用，返回值作为宿主函数的结果：宿主函数返回后挂起执行，
+    /// `invoke` 或 `resume` 返回 `Trap::Suspended`，之后用 `resume` 传入这次调用的结果继续。
+    /// 挂起时不能再调用 `invoke`；`untyped_stack` 上执行的函数不能挂起
+    pub fn suspend(&mut self) -> Vec<WasmValue> {
+        self.suspending = true;
+        vec![]
+    }
+    /// 在宿主函数中调用，返回值作为宿主函数的结果：宿主函数返回后以 trap 终止执行
+    pub fn raise(&mut self, trap: Trap) -> Vec<WasmValue> {
+        self.raised = Some(trap);
+        vec![]
+    }
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+    /// 以 values 作为挂起的宿主函数调用的结果继续执行，返回值与 `invoke` 相同
+    pub fn resume(&mut self, values: &[WasmValue]) -> anyhow::Result<Vec<WasmValue>> {
+        let frames = self.suspended.take().context("instance is not suspended")?;
+        self.resume_frames(&frames, values.to_vec())
+            .map_err(|trap| self.trap_error(trap))
+    }
+    fn resume_frames(
+        &mut self,
+        frames: &[SuspendedFrame],
+        mut res: Vec<WasmValue>,
+    ) -> Result<Vec<WasmValue>, Trap> {
+        for (i, frame) in frames.iter().enumerate() {
+            (self.pc, self.fp, self.sp) = (frame.pc, frame.fp, frame.sp);
+            self.csp = frames.len() - i;
+            if self.config.debugger.is_some() {
+                self.frames = frames[i..].iter().rev().map(|f| f.func).collect();
+            }
+            // 与 call 指令相同，压入被调函数的结果；让出的帧没有结果
+            for value in res {
+                self.sp += 1;
+                self.stack_check();
+                self.stack[self.sp] = value;
+            }
+            let run = self.run_at(frame.offset, frame.pc);
+            if let (Err(trap @ (Trap::Suspended | Trap::Yielded)), Some(inner)) =
+                (&run, &mut self.suspended)
+            {
+                let pc = resume_pc(trap, inner, self.pc);
+                inner.push(SuspendedFrame {
+                    pc,
+                    fp: self.fp,
+                    sp: self.sp,
+                    ..*frame
+                });
+                inner.extend_from_slice(&frames[i + 1..]);
+            }
+            run?;
+            res = self.leave(frame.ret, frame.result_count);
+        }
+        self.csp = 0;
+        self.frames.clear();
+        Ok(res)
+    }
+    /// 调用导出的 `_start` 函数（WASI command 约定），与 start 段无关
+    pub fn start(&mut self) -> anyhow::Result<()> {
+        self.invoke("_start", &[])?;
+        Ok(())
+    }
+
+    /// 第 pc 个操作码对应的源码位置，模块没有调试信息时为 None
+    pub fn source_location(&self, pc: usize) -> Option<SourceLocation> {
+        if self.debug_info.is_empty() {
+            return None;
+        }
+        let offset = self.section.code.op_offset(pc)?;
+        self.debug_info.lookup(offset)
+    }
+
+    /// 所有 custom section 的名字和内容，按在模块中的顺序
+    pub fn custom_sections(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.section
+            .custom
+            .entries
+            .iter()
+            .map(|(name, range)| (&name[..], self.raw.get(range.clone()).unwrap_or_default()))
+    }
+
+    /// 名为 name 的 custom section 的内容，同名的以后出现的为准
+    pub fn custom_section(&self, name: &str) -> Option<&[u8]> {
+        let range = self.section.custom.payloads.get(name)?;
+        self.raw.get(range.clone())
+    }
+
+    /// 解析 `producers` custom section，模块没有该段时为 None
+    pub fn producers(&self) -> anyhow::Result<Option<Producers>> {
+        self.custom_section("producers")
+            .map(Producers::parse)
+            .transpose()
+    }
+
+    /// 解析 `target_features` custom section，模块没有该段时为 None
+    pub fn target_features(&self) -> anyhow::Result<Option<TargetFeatures>> {
+        self.custom_section("target_features")
+            .map(TargetFeatures::parse)
+            .transpose()
+    }
+
+    /// 解析 `dylink.0` custom section，模块不是动态库时为 None
+    pub fn dylink(&self) -> anyhow::Result<Option<Dylink>> {
+        self.custom_section("dylink.0")
+            .map(Dylink::parse)
+            .transpose()
+    }
+
+    /// 模块用到的、还不能执行的提案（目前只有 `gc`），解码之后、实例化之前调用
+    pub fn required_features(&self) -> Vec<&'static str> {
+        let section = &self.section;
+        let any_ref = |ty: &ValueType| *ty == ValueType::AnyRef;
+        let gc = section.types.subtypes.iter().any(|sub| sub.is_gc())
+            || section
+                .types
+                .entries
+                .iter()
+                .any(|ty| ty.params.iter().chain(&ty.results).any(any_ref))
+            || section.global.entries.iter().any(|g| any_ref(&g.val_ty))
+            || section
+                .import
+                .entries
+                .iter()
+                .any(|ipt| matches!(&ipt.kind, import::Kind::Global(g) if any_ref(&g.val_ty)))
+            || section
+                .code
+                .entries
+                .iter()
+                .any(|body| body.locales.iter().any(|(_, ty)| any_ref(ty)));
+        match gc {
+            true => vec!["gc"],
+            false => vec![],
+        }
+    }
+
+    /// 导出函数 name 的索引和签名
+    pub fn export_func(&self, name: &str) -> anyhow::Result<(usize, &FunctionType)> {
+        let idx = match self.exports.get(name) {
+            Some(ExportKind::Func(idx)) => *idx,
+            Some(_) => bail!("export `{name}` is not a function"),
+            None => bail!("unknown export `{name}`"),
+        };
+        let ty = match self.func.get(idx) {
+            Some(FuncKind::Import(ty, _) | FuncKind::Local((ty, _))) => *ty,
+            None => bail!("unknown function {idx}"),
+        };
+        let ty = self
+            .section
+            .types
+            .entries
+            .get(ty)
+            .with_context(|| format!("unknown type {ty}"))?;
+        Ok((idx, ty))
+    }
+
+    /// 以 args 调用导出函数 name，返回按声明顺序排列的结果
+    pub fn invoke(&mut self, name: &str, args: &[WasmValue]) -> anyhow::Result<Vec<WasmValue>> {
+        let (idx, ty) = self.export_func(name)?;
+        let params = ty.params.clone();
+        ensure!(
+            args.len() == params.len()
+                && args
+                    .iter()
+                    .zip(&params)
+                    .all(|(a, p)| a.value_type() == Some(*p)),
+            "`{name}` expects ({}), got {} arguments",
+            params
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            args.len()
+        );
+        ensure!(
+            self.suspended.is_none(),
+            "instance is suspended, resume it or drop the suspended execution first"
+        );
+        self.call_with(idx, args)
+            .map_err(|trap| self.trap_error(trap))
+    }
+
+    /// 从空栈开始以 args 调用函数 idx，不检查参数的类型
+    pub fn call_with(&mut self, idx: usize, args: &[WasmValue]) -> Result<Vec<WasmValue>, Trap> {
+        if self.host_depth > 0 {
+            return self.call_nested(idx, args);
+        }
+        self.sp = 0;
+        self.fp = 0;
+        self.pc = 0;
+        self.csp = 0;
+        self.frames.clear();
+        self.stack_check();
+        for arg in args {
+            self.sp += 1;
+            self.stack[self.sp] = *arg;
+        }
+        self.call(idx)
+    }
+    /// 宿主函数中的调用：参数压在调用者的栈之上，返回后恢复调用者的状态
+    fn call_nested(&mut self, idx: usize, args: &[WasmValue]) -> Result<Vec<WasmValue>, Trap> {
+        let (pc, fp, sp, csp) = (self.pc, self.fp, self.sp, self.csp);
+        let frames = self.frames.len();
+        for arg in args {
+            self.sp += 1;
+            self.stack_check();
+            self.stack[self.sp] = *arg;
+        }
+        let res = self.call(idx);
+        (self.pc, self.fp, self.sp, self.csp) = (pc, fp, sp, csp);
+        self.frames.truncate(frames);
+        match res {
+            // 宿主函数之外的函数帧还在执行，不能挂起
+            Err(Trap::Suspended | Trap::Yielded) => {
+                self.suspended = None;
+                Err(Trap::SuspendUnsupported)
+            }
+            res => res,
+        }
+    }
+    fn trap_error(&self, trap: Trap) -> anyhow::Error {
+        let err = anyhow::Error::new(trap);
+        match self.source_location(self.pc) {
+            Some(loc) => err.context(format!("trap at {loc}")),
+            None => err,
+        }
+    }
+}
+
+impl Add for WasmValue {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        use WasmValue::*;
+        match (self, rhs) {
+            (I32(v1), I32(v2)) => I32(v1.wrapping_add(v2)),
+            (I64(v1), I64(v2)) => I64(v1.wrapping_add(v2)),
+            (F32(v1), F32(v2)) => F32(v1 + v2),
+            (F64(v1), F64(v2)) => F64(v1 + v2),
+            (V128(v1), V128(v2)) => V128(v1 + v2),
+            _ => todo!("{:?} + {:?} not support", self, rhs),
+        }
+    }
+}
+
+impl Sub for WasmValue {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        use WasmValue::*;
+        match (self, rhs) {
+            (I32(v1), I32(v2)) => I32(v1.wrapping_sub(v2)),
+            (I64(v1), I64(v2)) => I64(v1.wrapping_sub(v2)),
+            (F32(v1), F32(v2)) => F32(v1 - v2),
+            (F64(v1), F64(v2)) => F64(v1 - v2),
+            (V128(v1), V128(v2)) => V128(v1 - v2),
+            _ => todo!("{:?} - {:?} not support", self, rhs),
+        }
+    }
+}
+impl Mul for WasmValue {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        use WasmValue::*;
+        match (self, rhs) {
+            (I32(v1), I32(v2)) => I32(v1.wrapping_mul(v2)),
+            (I64(v1), I64(v2)) => I64(v1.wrapping_mul(v2)),
+            (F32(v1), F32(v2)) => F32(v1 * v2),
+            (F64(v1), F64(v2)) => F64(v1 * v2),
+            (V128(v1), V128(v2)) => V128(v1 * v2),
+            _ => todo!("{:?} * {:?} not support", self, rhs),
+        }
+    }
+}
+impl Div for WasmValue {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        use WasmValue::*;
+        match (self, rhs) {
+            (I32(v1), I32(v2)) => I32(v1.wrapping_div(v2)),
+            (I64(v1), I64(v2)) => I64(v1.wrapping_div(v2)),
+            (F32(v1), F32(v2)) => F32(v1 / v2),
+            (F64(v1), F64(v2)) => F64(v1 / v2),
+            (V128(v1), V128(v2)) => V128(v1 / v2),
+            _ => todo!("{:?} / {:?} not support", self, rhs),
+        }
+    }
+}
+
+impl BitAnd for WasmValue {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        use WasmValue::*;
+        match (self, rhs) {
+            (I32(v1), I32(v2)) => I32(v1 & v2),
+            (I64(v1), I64(v2)) => I64(v1 & v2),
+            _ => todo!("{:?} & {:?} not support", self, rhs),
+        }
+    }
+}
+
+impl BitOr for WasmValue {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        use WasmValue::*;
+        match (self, rhs) {
+            (I32(v1), I32(v2)) => I32(v1 | v2),
+            (I64(v1), I64(v2)) => I64(v1 | v2),
+            _ => todo!("{:?} & {:?} not support", self, rhs),
+        }
+    }
+}
+
+impl BitXor for WasmValue {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        use WasmValue::*;
+        match (self, rhs) {
+            (I32(v1), I32(v2)) => I32(v1 ^ v2),
+            (I64(v1), I64(v2)) => I64(v1 ^ v2),
+            _ => todo!("{:?} & {:?} not support", self, rhs),
+        }
+    }
+}
+
+impl PartialOrd for WasmValue {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        use WasmValue::*;
+        match (self, other) {
+            (NOP, NOP) => todo!(),
+            (I32(v1), I32(v2)) => {
+                if v1 == v2 {
+                    return Some(Ordering::Equal);
+                } else if v1 > v2 {
+                    return Some(Ordering::Greater);
+                } else {
+                    return Some(Ordering::Less);
+                }
+            }
+            (I64(v1), I64(v2)) => {
+                if v1 == v2 {
+                    return Some(Ordering::Equal);
+                } else if v1 > v2 {
+                    return Some(Ordering::Greater);
+                } else {
+                    return Some(Ordering::Less);
+                }
+            }
+            (F32(v1), F32(v2)) => {
+                if v1 == v2 {
+                    return Some(Ordering::Equal);
+                } else if v1 > v2 {
+                    return Some(Ordering::Greater);
+                } else {
+                    return Some(Ordering::Less);
+                }
+            }
+            (F64(v1), F64(v2)) => {
+                if v1 == v2 {
+                    return Some(Ordering::Equal);
+                } else if v1 > v2 {
+                    return Some(Ordering::Greater);
+                } else {
+                    return Some(Ordering::Less);
+                }
+            }
+            (V128(v1), V128(v2)) => {
+                if v1 == v2 {
+                    return Some(Ordering::Equal);
+                } else if v1 > v2 {
+                    return Some(Ordering::Greater);
+                } else {
+                    return Some(Ordering::Less);
+                }
+            }
+            (v1, v2) => todo!("{v1:?} compare {v2:?} isn't support"),
+        }
+    }
+
+    fn lt(&self, other: &Self) -> bool {
+        matches!(self.partial_cmp(other), Some(Ordering::Less))
+    }
+
+    fn le(&self, other: &Self) -> bool {
+        matches!(
+            self.partial_cmp(other),
+            Some(Ordering::Less | Ordering::Equal)
+        )
+    }
+
+    fn gt(&self, other: &Self) -> bool {
+        matches!(self.partial_cmp(other), Some(Ordering::Greater))
+    }
+
+    fn ge(&self, other: &Self) -> bool {
+        matches!(
+            self.partial_cmp(other),
+            Some(Ordering::Greater | Ordering::Equal)
+        )
+    }
+}
+
+impl Shl for WasmValue {
+    type Output = WasmValue;
+
+    fn shl(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (WasmValue::I32(a), WasmValue::I32(b)) => WasmValue::I32(a.wrapping_shl(b as u32)),
+            (WasmValue::I64(a), WasmValue::I64(b)) => WasmValue::I64(a.wrapping_shl(b as u32)),
+            (WasmValue::F32(_), WasmValue::F32(_)) => todo!(),
+            (WasmValue::F64(_), WasmValue::F64(_)) => todo!(),
+            (WasmValue::V128(_), WasmValue::V128(_)) => todo!(),
+            _ => todo!("{:?} << {:?}", self, rhs),
+        }
+    }
+}
+
+/// 批量操作的 [start, start + len) 范围，超出 size 时返回 None
+fn bulk_range(start: u32, len: u32, size: usize) -> Option<std::ops::Range<usize>> {
+    let end = start as usize + len as usize;
+    (end <= size).then_some(start as usize..end)
+}
+
+#[cfg(test)]
+fn float_module(config: RuntimeConfig) -> WasmModule {
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, // magic = \0asm
+        0x01, 0x00, 0x00, 0x00, // version  = 1 (little endian)
+        //
+        0x01, 0x12, 0x03, // type section
+        0x60, 0x02, 0x7d, 0x7d, 0x01, 0x7d, // (f32, f32) -> f32
+        0x60, 0x02, 0x7c, 0x7c, 0x01, 0x7c, // (f64, f64) -> f64
+        0x60, 0x01, 0x7f, 0x01, 0x7d, // (i32) -> f32
+        //
+        0x03, 0x06, 0x05, 0x00, 0x00, 0x01, 0x02, 0x00, // func section
+        //
+        0x0a, 0x27, 0x05, // code section
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x92, 0x0b, // f32.add
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x95, 0x0b, // f32.div
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0xa1, 0x0b, // f64.sub
+        0x05, 0x00, 0x20, 0x00, 0xbe, 0x0b, // f32.reinterpret_i32
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x96, 0x0b, // f32.min
+    ];
+    let mut wasm = WasmModule::default(buf);
+    wasm.config = config;
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    wasm
+}
+
+#[test]
+fn test_canonicalize_nans() {
+    use super::testing::call_with;
+    use WasmValue::*;
+    let mut wasm = float_module(RuntimeConfig::default().canonicalize_nans(true));
+    let snan = F32(f32::from_bits(0x7fa0_0001));
+    let bits = |v: WasmValue| match v {
+        F32(v) => v.to_bits() as u64,
+        F64(v) => v.to_bits(),
+        v => panic!("unexpected {v:?}"),
+    };
+
+    let cases = [
+        (0, vec![snan, F32(1.0)]),
+        (1, vec![F32(0.0), F32(0.0)]),
+        (3, vec![I32(0x7f80_0001)]),
+        (3, vec![I32(0xffc0_1234u32 as i32)]),
+        (4, vec![F32(f32::from_bits(0xffff_ffff)), F32(1.0)]),
+    ];
+    for (idx, args) in cases {
+        let r = call_with(&mut wasm, idx, &args);
+        assert_eq!(bits(r), float::F32_CANONICAL_NAN as u64, "func {idx}");
+    }
+    let r = call_with(&mut wasm, 2, &[F64(f64::INFINITY), F64(f64::INFINITY)]);
+    assert_eq!(bits(r), float::F64_CANONICAL_NAN);
+
+    // 非 NaN 的结果不受影响
+    assert_eq!(call_with(&mut wasm, 0, &[F32(1.5), F32(2.0)]), F32(3.5));
+    assert_eq!(call_with(&mut wasm, 3, &[I32(0x3fc0_0000)]), F32(1.5));
+}
+
+#[test]
+fn test_nan_without_canonicalize() {
+    use super::testing::call_with;
+    use WasmValue::*;
+    let mut wasm = float_module(RuntimeConfig::default());
+    // 规范只要求 arithmetic NaN（quiet 位为 1），payload 与符号位不确定
+    let arithmetic_nan = |v: WasmValue| match v {
+        F32(v) => v.is_nan() && v.to_bits() & 0x0040_0000 != 0,
+        F64(v) => v.is_nan() && v.to_bits() & 0x0008_0000_0000_0000 != 0,
+        _ => false,
+    };
+    let r = call_with(&mut wasm, 1, &[F32(0.0), F32(0.0)]);
+    assert!(arithmetic_nan(r), "{r:?}");
+    let r = call_with(&mut wasm, 2, &[F64(f64::INFINITY), F64(f64::INFINITY)]);
+    assert!(arithmetic_nan(r), "{r:?}");
+    let r = call_with(&mut wasm, 0, &[F32(f32::from_bits(0x7fa0_0001)), F32(1.0)]);
+    assert!(arithmetic_nan(r), "{r:?}");
+
+    // reinterpret 必须逐位保留 payload
+    match call_with(&mut wasm, 3, &[I32(0x7f80_0001)]) {
+        F32(v) => assert_eq!(v.to_bits(), 0x7f80_0001),
+        v => panic!("unexpected {v:?}"),
+    }
+}
+
+#[test]
+fn test_trap_type_mismatch() {
+    use super::testing::{func_module, invoke};
+    use ValueType as T;
+    use WasmValue::*;
+
+    let f32_zero: &[u8] = &[0x43, 0x00, 0x00, 0x00, 0x00];
+    let cases: Vec<(Vec<u8>, ValueType, WasmValue)> = vec![
+        // f32.const 0; if end
+        ([f32_zero, &[0x04, 0x40, 0x0b]].concat(), T::I32, F32(0.0)),
+        // block i64.const 1; br_if 0 end
+        (
+            vec![0x02, 0x40, 0x42, 0x01, 0x0d, 0x00, 0x0b],
+            T::I32,
+            I64(1),
+        ),
+        // block i64.const 0; br_table 0 end
+        (
+            vec![0x02, 0x40, 0x42, 0x00, 0x0e, 0x00, 0x00, 0x0b],
+            T::I32,
+            I64(0),
+        ),
+        // f32.const 0; call_indirect 0 0
+        ([f32_zero, &[0x11, 0x00, 0x00]].concat(), T::I32, F32(0.0)),
+        // i32.const 1; i32.const 2; f32.const 0; select
+        (
+            [&[0x41, 0x01, 0x41, 0x02], f32_zero, &[0x1b, 0x1a]].concat(),
+            T::I32,
+            F32(0.0),
+        ),
+        // i32.const 1; i64.const 2; i32.add
+        (vec![0x41, 0x01, 0x42, 0x02, 0x6a, 0x1a], T::I32, I64(2)),
+        // i64.const 1; i32.const 2; i32.add
+        (vec![0x42, 0x01, 0x41, 0x02, 0x6a, 0x1a], T::I32, I64(1)),
+        // i64.const 0; i32.eqz
+        (vec![0x42, 0x00, 0x45, 0x1a], T::I32, I64(0)),
+        // i32.const 4; f32.sqrt
+        (vec![0x41, 0x04, 0x91, 0x1a], T::F32, I32(4)),
+        // f32.const 0; f64.const 0; f64.min
+        (
+            [f32_zero, &[0x44, 0, 0, 0, 0, 0, 0, 0, 0, 0xa4, 0x1a]].concat(),
+            T::F64,
+            F32(0.0),
+        ),
+        // i32.const 1; i32.wrap_i64
+        (vec![0x41, 0x01, 0xa7, 0x1a], T::I64, I32(1)),
+        // i64.const 1; i64.extend_i32_u
+        (vec![0x42, 0x01, 0xad, 0x1a], T::I32, I64(1)),
+        // i32.const 1; f64.promote_f32
+        (vec![0x41, 0x01, 0xbb, 0x1a], T::F32, I32(1)),
+        // i32.const 1; f32.reinterpret_i32 on i64
+        (vec![0x42, 0x01, 0xbe, 0x1a], T::I32, I64(1)),
+        // f32.const 0; i32.load
+        (
+            [f32_zero, &[0x28, 0x02, 0x00, 0x1a]].concat(),
+            T::I32,
+            F32(0.0),
+        ),
+        // i32.const 0; i64.const 1; i32.store
+        (
+            vec![0x41, 0x00, 0x42, 0x01, 0x36, 0x02, 0x00],
+            T::I32,
+            I64(1),
+        ),
+        // i64.const 0; i32.const 1; i32.store8
+        (
+            vec![0x42, 0x00, 0x41, 0x01, 0x3a, 0x00, 0x00],
+            T::I32,
+            I64(0),
+        ),
+        // i64.const 1; memory.grow
+        (vec![0x42, 0x01, 0x40, 0x00, 0x1a], T::I32, I64(1)),
+    ];
+    let extra = [
+        (4, vec![0x01, 0x70, 0x00, 0x01]), // table funcref 1
+        (5, vec![0x01, 0x00, 0x01]),       // memory 1
+    ];
+    for (index, (body, expected, found)) in cases.into_iter().enumerate() {
+        let mut wasm = func_module(&[], &[], &[&body], &extra);
+        let trap = invoke(&mut wasm, 0, &[]).unwrap_err();
+        assert_eq!(trap, Trap::TypeMismatch { expected, found }, "case {index}");
+    }
+}
+
+#[test]
+fn test_trap_control() {
+    use super::testing::{func_module, invoke};
+
+    let extra = [(4, vec![0x01, 0x70, 0x00, 0x01])];
+    let bodies: [&[u8]; 2] = [
+        &[0x00],                         // unreachable
+        &[0x41, 0x05, 0x11, 0x00, 0x00], // call_indirect 越界
+    ];
+    let mut wasm = func_module(&[], &[], &bodies, &extra);
+    assert_eq!(invoke(&mut wasm, 0, &[]), Err(Trap::Unreachable));
+    assert_eq!(
+        invoke(&mut wasm, 1, &[]),
+        Err(Trap::UndefinedElement { index: 5 })
+    );
+    assert_eq!(
+        Trap::UndefinedElement { index: 5 }.to_string(),
+        "RuntimeError: undefined element 5"
+    );
+}
+
+#[test]
+fn test_instance_offset_type() {
+    use super::testing::func_bytes;
+
+    // 元素段 offset 为 f32.const 0
+    let element = vec![0x01, 0x00, 0x43, 0x00, 0x00, 0x00, 0x00, 0x0b, 0x01, 0x00];
+    let buf = func_bytes(
+        &[],
+        &[],
+        &[&[]],
+        &[(4, vec![0x01, 0x70, 0x00, 0x01]), (9, element)],
+    );
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let err = wasm.instance(None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "element offset must be i32, found F32(0.0)"
+    );
+
+    // 数据段 offset 为 i64.const 0
+    let data = vec![0x01, 0x00, 0x42, 0x00, 0x0b, 0x01, 0x61];
+    let buf = func_bytes(&[], &[], &[&[]], &[(5, vec![0x01, 0x00, 0x01]), (11, data)]);
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let err = wasm.instance(None).unwrap_err();
+    assert_eq!(err.to_string(), "data offset must be i32, found I64(0)");
+}
+
+#[test]
+fn test_multi_memory_memarg() {
+    use super::section::opcode::MemArg;
+    use super::testing::{call_with, func_bytes, func_module};
+
+    let body: &[u8] = &[
+        0x41, 0x04, 0x41, 0x2a, 0x36, 0x42, 0x01, 0x00, // i32.store (memory 1) 4 42
+        0x41, 0x04, 0x28, 0x42, 0x01, 0x00, // i32.load (memory 1) 4
+        0x3f, 0x01, // memory.size 1
+        0x6a, // i32.add
+    ];
+    let memory = (5, vec![0x02, 0x00, 0x01, 0x00, 0x02]); // memory [1], [2]
+    let mut wasm = func_module(&[], &[0x7f], &[body], std::slice::from_ref(&memory));
+    let memarg = MemArg {
+        align: 2,
+        offset: 0,
+        memory: 1,
+    };
+    assert!(wasm
+        .ops
+        .iter()
+        .any(|op| matches!(op, Opcode::I32Store(m) if *m == memarg)));
+    assert_eq!(call_with(&mut wasm, 0, &[]), WasmValue::I32(44));
+    assert_eq!(wasm.mem[0][4], 0);
+    assert_eq!(wasm.mem[1][4], 42);
+
+    // align 超过 7 位为 malformed
+    let buf = func_bytes(
+        &[],
+        &[],
+        &[&[0x41, 0x00, 0x28, 0x80, 0x01, 0x00, 0x1a]],
+        &[memory],
+    );
+    let mut wasm = WasmModule::default(buf);
+    assert!(wasm.decode().is_err());
+}
+
+#[test]
+fn test_malformed_utf8_names() {
+    use super::testing::{vec, wasm};
+
+    let name = |bytes: &[u8]| vec(&bytes.iter().map(|b| vec![*b]).collect::<Vec<_>>());
+    let decode = |sections: &[(u8, Vec<u8>)]| {
+        let mut module = WasmModule::default(wasm(sections));
+        module.decode().map(|_| module)
+    };
+
+    // 合法的多字节名字
+    let module = decode(&[
+        (0, [name("名字".as_bytes()), vec![0x01]].concat()),
+        (5, vec![0x01, 0x00, 0x01]),
+        (
+            7,
+            [vec![0x01], name("内存".as_bytes()), vec![0x02, 0x00]].concat(),
+        ),
+    ])
+    .unwrap();
+    assert_eq!(module.section.custom.name, "名字");
+    assert_eq!(module.section.export.entries[0].name, "内存");
+
+    // 截断的多字节序列、非法的起始字节、UTF-16 代理区
+    for bad in [&[0x61, 0xe5, 0x90][..], &[0xff], &[0xed, 0xa0, 0x80]] {
+        let err = decode(&[(0, name(bad))]).err().unwrap();
+        assert!(
+            err.to_string().starts_with("malformed UTF-8 encoding"),
+            "{err}"
+        );
+    }
+    let err = decode(&[
+        (5, vec![0x01, 0x00, 0x01]),
+        (
+            7,
+            [vec![0x01], name(&[0x6d, 0xc0, 0x80]), vec![0x02, 0x00]].concat(),
+        ),
+    ])
+    .err()
+    .unwrap();
+    // 头部 8 字节 + memory section 5 字节 + export section 头 2 字节 + 数量、长度、`m` 各 1 字节
+    assert_eq!(
+        err.kind.to_string(),
+        "malformed UTF-8 encoding in name at offset 18"
+    );
+    assert_eq!(err.section, Some(7));
+    let import = [
+        vec![0x01],
+        name(b"env"),
+        name(&[0x80]),
+        vec![0x02, 0x00, 0x01],
+    ]
+    .concat();
+    assert!(decode(&[(2, import)]).is_err());
+}
+
+#[test]
+fn test_start_section() {
+    use super::testing::{func_bytes, func_module};
+
+    let global = (6, vec![0x01, 0x7f, 0x01, 0x41, 0x00, 0x0b]); // (mut i32) 0
+    let start = (8, vec![0x01]); // start func 1
+    let bodies: [&[u8]; 2] = [
+        &[0x41, 0x01, 0x24, 0x00],                   // global.set 0 1
+        &[0x23, 0x00, 0x41, 0x07, 0x6a, 0x24, 0x00], // global.set 0 (global.get 0 + 7)
+    ];
+    let wasm = func_module(&[], &[], &bodies, &[global.clone(), start.clone()]);
+    assert!(matches!(wasm.global[0], Global::Var(WasmValue::I32(7))));
+
+    // start 函数 trap 时实例化失败
+    let buf = func_bytes(&[], &[], &[&[], &[0x00]], std::slice::from_ref(&start));
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    assert_eq!(
+        wasm.instance(None).unwrap_err().to_string(),
+        "RuntimeError: unreachable"
+    );
+
+    // start 函数的类型必须是 [] -> []
+    let buf = func_bytes(&[0x7f], &[], &[&[], &[]], &[start]);
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    assert!(wasm.instance(None).is_err());
+}
+
+#[test]
+fn test_data_count() {
+    use super::testing::func_bytes;
+
+    let decode = |body: &[u8], extra: &[(u8, Vec<u8>)]| {
+        let mut wasm = WasmModule::default(func_bytes(&[], &[], &[body], extra));
+        wasm.decode().map(|_| wasm).map_err(|e| e.kind)
+    };
+    let memory = (5, vec![0x01, 0x00, 0x01]);
+    let data = (11, vec![0x01, 0x01, 0x02, 0x61, 0x62]); // 1 个 passive 数据段
+    let drop0: &[u8] = &[0xfc, 0x09, 0x00]; // data.drop 0
+    let init1: &[u8] = &[0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xfc, 0x08, 0x01, 0x00]; // memory.init 1
+
+    let wasm = decode(drop0, &[memory.clone(), (12, vec![0x01]), data.clone()]).unwrap();
+    assert_eq!(wasm.section.data_count.u32, 1);
+    assert_eq!(
+        decode(drop0, &[memory.clone(), data.clone()]).unwrap_err(),
+        DecodeErrorKind::DataCountRequired
+    );
+    assert_eq!(
+        decode(init1, &[memory.clone(), (12, vec![0x01]), data.clone()]).unwrap_err(),
+        DecodeErrorKind::UnknownDataSegment { index: 1 }
+    );
+    assert_eq!(
+        decode(&[], &[memory, (12, vec![0x02]), data]).unwrap_err(),
+        DecodeErrorKind::DataCountMismatch {
+            expected: 2,
+            found: 1
+        }
+    );
+    // 没有 data 段时数量为 0
+    assert!(decode(&[], &[(12, vec![0x00])]).is_ok());
+}
+
+#[test]
+fn test_passive_segments() {
+    use super::testing::{call_with, func_module, invoke, vec};
+    use WasmValue::I32;
+
+    let memory = (5, vec![0x01, 0x00, 0x01]);
+    let table = (4, vec![0x01, 0x70, 0x00, 0x04]);
+    // passive 数据段 "abcd" 和 active 数据段
+    let data = (
+        11,
+        vec(&[
+            vec![0x01, 0x04, 0x61, 0x62, 0x63, 0x64],
+            vec![0x00, 0x41, 0x00, 0x0b, 0x01, 0x7a],
+        ]),
+    );
+    // passive 元素段：函数 [3, 4]，表达式形式：[ref.func 4, ref.null func]
+    let elem = (
+        9,
+        vec(&[
+            vec![0x01, 0x00, 0x02, 0x03, 0x04],
+            vec![0x05, 0x70, 0x02, 0xd2, 0x04, 0x0b, 0xd0, 0x70, 0x0b],
+        ]),
+    );
+    let extra = [memory, table, (12, vec![0x02]), data, elem];
+    let bodies: &[&[u8]] = &[
+        // 0: memory.init 0 (dst = p, src = 1, len = 3)，返回 i32.load8_u p
+        &[
+            0x20, 0x00, 0x41, 0x01, 0x41, 0x03, 0xfc, 0x08, 0x00, 0x00, 0x20, 0x00, 0x2d, 0x00,
+            0x00,
+        ],
+        // 1: data.drop 0 / 1
+        &[0xfc, 0x09, 0x00, 0x20, 0x00],
+        // 2: table.init s (dst = 0, src = p, len = 2)，返回 call_indirect 0
+        &[
+            0x41, 0x00, 0x20, 0x00, 0x41, 0x02, 0xfc, 0x0c, 0x00, 0x00, 0x41, 0x00, 0x11, 0x00,
+            0x00,
+        ],
+        &[0x41, 0x03],
+        &[0x41, 0x04],
+        // 5: table.init 1 后调用表项 p，再 elem.drop 0
+        &[
+            0x41, 0x00, 0x41, 0x00, 0x41, 0x02, 0xfc, 0x0c, 0x01, 0x00, 0x20, 0x00, 0x11, 0x00,
+            0x00,
+        ],
+        &[0xfc, 0x0d, 0x00, 0x20, 0x00],
+        // 7: memory.init 1（active 段实例化后已丢弃，长度为 0）
+        &[
+            0x41, 0x00, 0x41, 0x00, 0x20, 0x00, 0xfc, 0x08, 0x01, 0x00, 0x20, 0x00,
+        ],
+    ];
+    let mut wasm = func_module(&[0x7f], &[0x7f], bodies, &extra);
+    assert_eq!(wasm.data[1], Vec::<u8>::new());
+    assert_eq!(wasm.mem[0][0], b'z');
+
+    assert_eq!(call_with(&mut wasm, 0, &[I32(8)]), I32(b'b' as i32));
+    assert_eq!(&wasm.mem[0][8..11], b"bcd");
+    assert_eq!(
+        invoke(&mut wasm, 0, &[I32(65535)]).unwrap_err(),
+        Trap::MemoryOutOfBounds {
+            addr: 65538,
+            size: 65536
+        }
+    );
+    assert_eq!(call_with(&mut wasm, 7, &[I32(0)]), I32(0));
+    assert_eq!(
+        invoke(&mut wasm, 7, &[I32(1)]).unwrap_err(),
+        Trap::MemoryOutOfBounds { addr: 1, size: 0 }
+    );
+
+    assert_eq!(call_with(&mut wasm, 2, &[I32(0)]), I32(3));
+    assert_eq!(call_with(&mut wasm, 5, &[I32(0)]), I32(4));
+    assert_eq!(
+        invoke(&mut wasm, 5, &[I32(1)]).unwrap_err(),
+        Trap::UninitializedElement { index: 1 }
+    );
+    assert_eq!(
+        invoke(&mut wasm, 2, &[I32(1)]).map(|_| ()),
+        Err(Trap::TableOutOfBounds { index: 3, size: 2 })
+    );
+
+    // drop 之后段长度为 0，只允许长度为 0 的 init
+    call_with(&mut wasm, 1, &[I32(0)]);
+    assert_eq!(
+        invoke(&mut wasm, 0, &[I32(0)]).unwrap_err(),
+        Trap::MemoryOutOfBounds { addr: 4, size: 0 }
+    );
+    call_with(&mut wasm, 6, &[I32(0)]);
+    assert_eq!(
+        invoke(&mut wasm, 2, &[I32(0)]).unwrap_err(),
+        Trap::TableOutOfBounds { index: 2, size: 0 }
+    );
+}
+
+#[test]
+fn test_active_element_segments() {
+    use super::testing::{call_with, func_bytes, func_module, vec};
+    use WasmValue::I32;
+
+    // 两个表，每个 4 项
+    let tables = (4, vec(&[vec![0x70, 0x00, 0x04], vec![0x70, 0x00, 0x04]]));
+    let elem = (
+        9,
+        vec(&[
+            // 0x02: table 1, offset 1, 函数 [2]
+            vec![0x02, 0x01, 0x41, 0x01, 0x0b, 0x00, 0x01, 0x02],
+            // 0x04: table 0, offset 2, [ref.func 3]
+            vec![0x04, 0x41, 0x02, 0x0b, 0x01, 0xd2, 0x03, 0x0b],
+            // 0x06: table 1, offset 2, [ref.func 3, ref.null func]
+            vec![
+                0x06, 0x01, 0x41, 0x02, 0x0b, 0x70, 0x02, 0xd2, 0x03, 0x0b, 0xd0, 0x70, 0x0b,
+            ],
+        ]),
+    );
+    let bodies: &[&[u8]] = &[
+        &[0x20, 0x00, 0x11, 0x00, 0x00], // call_indirect table 0
+        &[0x20, 0x00, 0x11, 0x00, 0x01], // call_indirect table 1
+        &[0x41, 0x02],
+        &[0x41, 0x03],
+    ];
+    let mut wasm = func_module(&[0x7f], &[0x7f], bodies, &[tables.clone(), elem]);
+    assert_eq!(wasm.table[0].to_vec(), [NULL_REF, NULL_REF, 3, NULL_REF]);
+    assert_eq!(wasm.table[1].to_vec(), [NULL_REF, 2, 3, NULL_REF]);
+    assert_eq!(call_with(&mut wasm, 0, &[I32(2)]), I32(3));
+    assert_eq!(call_with(&mut wasm, 1, &[I32(1)]), I32(2));
+    assert_eq!(call_with(&mut wasm, 1, &[I32(2)]), I32(3));
+    // active 段实例化后即丢弃
+    assert!(wasm.elem.iter().all(|items| items.is_empty()));
+
+    // 越界的 active 段导致实例化失败
+    let elem = (
+        9,
+        vec(&[vec![0x02, 0x01, 0x41, 0x03, 0x0b, 0x00, 0x02, 0x02, 0x02]]),
+    );
+    let mut wasm = WasmModule::default(func_bytes(&[0x7f], &[0x7f], bodies, &[tables, elem]));
+    wasm.decode().unwrap();
+    assert_eq!(
+        wasm.instance(None).unwrap_err().to_string(),
+        "RuntimeError: out of bounds table access, index = 5, size = 4"
+    );
+}
+
+#[test]
+fn test_call_indirect_signature() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    // 表中的函数 1 有两个参数，通过 (type $unary) 调用时参数个数不同
+    let src = r#"(module
+      (type $unary (func (param i32) (result i32)))
+      (table 2 funcref)
+      (elem (i32.const 0) $inc $add)
+      (func $inc (param i32) (result i32) (i32.add (local.get 0) (i32.const 1)))
+      (func $add (param i32 i32) (result i32) (i32.add (local.get 0) (local.get 1)))
+      (func (export "call") (param i32) (result i32)
+        (call_indirect (type $unary) (i32.const 41) (local.get 0))))"#;
+    for config in all_engine_configs() {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        let result = wasm.invoke("call", &[WasmValue::I32(0)]).unwrap();
+        assert_eq!(result, [WasmValue::I32(42)]);
+        let err = wasm.invoke("call", &[WasmValue::I32(1)]).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Trap>(),
+            Some(&Trap::SignatureMismatch { func: 1 })
+        );
+        assert!(err.to_string().contains("indirect call type mismatch"));
+        // trap 之后实例仍然可用
+        let result = wasm.invoke("call", &[WasmValue::I32(0)]).unwrap();
+        assert_eq!(result, [WasmValue::I32(42)]);
+    }
+}
+
+#[test]
+fn test_ref_globals() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    let src = r#"(module
+      (type $t (func (result i32)))
+      (table 1 funcref)
+      (global $f (export "f") (mut funcref) (ref.func $seven))
+      (global $null funcref (ref.null func))
+      (global $ext (mut externref) (ref.null extern))
+      (func $seven (result i32) (i32.const 7))
+      (func $nine (result i32) (i32.const 9))
+      (elem declare func $nine)
+      (func (export "call") (result i32)
+        (table.set (i32.const 0) (global.get $f))
+        (call_indirect (type $t) (i32.const 0)))
+      (func (export "swap") (global.set $f (ref.func $nine)))
+      (func (export "null") (result i32) (ref.is_null (global.get $null)))
+      (func (export "ext") (param externref) (result externref)
+        (global.get $ext)
+        (global.set $ext (local.get 0))))"#;
+    for config in all_engine_configs() {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        assert_eq!(wasm.get_global("f").unwrap().get(), WasmValue::FuncRef(0));
+        assert_eq!(wasm.invoke("call", &[]).unwrap(), [WasmValue::I32(7)]);
+        wasm.invoke("swap", &[]).unwrap();
+        assert_eq!(wasm.invoke("call", &[]).unwrap(), [WasmValue::I32(9)]);
+        assert_eq!(wasm.invoke("null", &[]).unwrap(), [WasmValue::I32(1)]);
+        let ext = |wasm: &mut WasmModule, v| wasm.invoke("ext", &[WasmValue::ExternRef(v)]);
+        assert_eq!(ext(&mut wasm, 5).unwrap(), [WasmValue::ExternRef(NULL_REF)]);
+        assert_eq!(ext(&mut wasm, 6).unwrap(), [WasmValue::ExternRef(5)]);
+    }
+
+    // 初始值的引用类型与全局变量不一致
+    let src = r#"(module (global externref (ref.func 0)) (func))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.decode().unwrap();
+    assert!(wasm.instance(None).is_err());
+}
+
+#[test]
+fn test_instance_failure_keeps_module() {
+    use super::linker::Linker;
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "tab" (table 1 funcref))
+      (global $g (mut i32) (i32.const 5))
+      (func $f (result i32) (global.get $g))
+      (func (export "run") (result i32) (call_indirect (result i32) (i32.const 1)))
+      (elem (i32.const 1) $f))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.decode().unwrap();
+    let bodies = wasm.section.code.entries.len();
+
+    // 元素段超出表的大小，这时函数、全局变量已经建立
+    let mut linker = Linker::new();
+    linker
+        .define_table("env", "tab", ValueType::FuncRef, 1, None)
+        .unwrap();
+    assert!(linker.instantiate(&mut wasm).is_err());
+    assert!(wasm.func.is_empty() && wasm.global.is_empty() && wasm.exports.is_empty());
+    assert_eq!(wasm.section.code.entries.len(), bodies);
+    assert_eq!(wasm.section.import.entries.len(), 1);
+
+    let mut linker = Linker::new();
+    linker
+        .define_table("env", "tab", ValueType::FuncRef, 2, None)
+        .unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+    assert_eq!(wasm.func.len(), 2);
+    assert_eq!(wasm.invoke("run", &[]).unwrap(), [WasmValue::I32(5)]);
+}
+
+#[test]
+fn test_host_results() {
+    use super::linker::{Func, Linker};
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "f" (func $f (result i32)))
+      (func (export "run") (result i32) (call $f)))"#;
+    let wrong: [HostFunc; 3] = [
+        |_, _| vec![],
+        |_, _| vec![WasmValue::I64(1)],
+        |_, _| vec![WasmValue::I32(1), WasmValue::I32(2)],
+    ];
+    for func in wrong {
+        let mut linker = Linker::new();
+        linker
+            .define("env", "f", Func::wrap(&[], &[ValueType::I32], func))
+            .unwrap();
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).unwrap();
+        let err = wasm.invoke("run", &[]).unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<Trap>(), Some(Trap::Host { .. })),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn test_active_data_segments() {
+    use super::linker::Linker;
+    use super::wat;
+
+    let instance = |src: &str| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        wasm.instance(None).map(|_| wasm)
+    };
+    // 恰好写到内存结尾，长度为 0 的段可以在结尾
+    let wasm = instance(
+        r#"(module (memory 1)
+          (data (i32.const 65534) "ab")
+          (data (i32.const 65536) ""))"#,
+    )
+    .unwrap();
+    assert_eq!(&wasm.mem[0][65534..], b"ab");
+
+    // 越界时实例化失败，不截断也不自动增长；偏移量按无符号数处理
+    let cases = [
+        (65535, "ab", 65537),
+        (65537, "", 65537),
+        (-1, "ab", u32::MAX as u64 + 2),
+    ];
+    for (offset, bytes, addr) in cases {
+        let src = format!(r#"(module (memory 1) (data (i32.const {offset}) "{bytes}"))"#);
+        let err = instance(&src).err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<Trap>(),
+            Some(&Trap::MemoryOutOfBounds { addr, size: 65536 }),
+            "{err}"
+        );
+    }
+    let err = instance(r#"(module (memory 0) (data (i32.const 0) "a"))"#)
+        .err()
+        .unwrap();
+    assert!(
+        err.to_string().contains("out of bounds memory access"),
+        "{err}"
+    );
+
+    // 按顺序初始化，越界之前的段已经写入导入的内存
+    let memory = Memory::new(Limit {
+        flag: 0,
+        minimum: 1,
+        maximum: 0,
+    })
+    .unwrap();
+    let mut linker = Linker::new();
+    linker.define("env", "memory", memory.clone()).unwrap();
+    let mut wasm = WasmModule::default(
+        wat::compile(
+            r#"(module (import "env" "memory" (memory 1))
+              (data (i32.const 0) "ok")
+              (data (i32.const 65535) "no"))"#,
+        )
+        .unwrap(),
+    );
+    wasm.decode().unwrap();
+    assert!(linker.instantiate(&mut wasm).is_err());
+    let mut buf = [0; 2];
+    memory.read(0, &mut buf).unwrap();
+    assert_eq!(&buf, b"ok");
+}
+
+#[test]
+fn test_cancellation() {
+    use super::cancel::CancellationToken;
+    use super::testing::{func_module, invoke};
+
+    // 0: loop br 0 end（死循环）
+    let mut wasm = func_module(&[], &[], &[&[0x03, 0x40, 0x0c, 0x00, 0x0b]], &[]);
+    let token = CancellationToken::new();
+    wasm.config = wasm.config.clone().cancellation_token(token.child_token());
+
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        token.cancel();
+    });
+    assert_eq!(invoke(&mut wasm, 0, &[]).unwrap_err(), Trap::Cancelled);
+    handle.join().unwrap();
+    // 取消后再次调用立即返回
+    assert_eq!(invoke(&mut wasm, 0, &[]).unwrap_err(), Trap::Cancelled);
+}
+
+#[test]
+fn test_memory_out_of_bounds() {
+    use super::testing::{call_with, func_module, invoke};
+    use WasmValue::*;
+
+    let memory = (5, vec![0x01, 0x00, 0x01]); // 1 页
+    let oob = |addr| Trap::MemoryOutOfBounds { addr, size: 65536 };
+    let mut loads = func_module(
+        &[0x7f],
+        &[0x7f],
+        &[
+            &[0x20, 0x00, 0x28, 0x02, 0x00],       // 0: i32.load
+            &[0x20, 0x00, 0x2c, 0x00, 0x00],       // 1: i32.load8_s
+            &[0x20, 0x00, 0x2e, 0x01, 0x00],       // 2: i32.load16_s
+            &[0x20, 0x00, 0x29, 0x03, 0x04, 0xa7], // 3: i64.load offset=4; wrap
+            // 4: f64.load; f64.const 1.0; f64.eq
+            &[
+                0x20, 0x00, 0x2b, 0x03, 0x00, 0x44, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f, 0x61,
+            ],
+        ],
+        std::slice::from_ref(&memory),
+    );
+    assert_eq!(call_with(&mut loads, 0, &[I32(65532)]), I32(0));
+    assert_eq!(
+        invoke(&mut loads, 0, &[I32(65533)]).unwrap_err(),
+        oob(65533)
+    );
+    // 地址按无符号处理
+    assert_eq!(
+        invoke(&mut loads, 0, &[I32(-1)]).unwrap_err(),
+        oob(0xffff_ffff)
+    );
+    assert_eq!(call_with(&mut loads, 1, &[I32(65535)]), I32(0));
+    assert_eq!(
+        invoke(&mut loads, 1, &[I32(65536)]).unwrap_err(),
+        oob(65536)
+    );
+    assert_eq!(
+        invoke(&mut loads, 2, &[I32(65535)]).unwrap_err(),
+        oob(65535)
+    );
+    // 有效地址包含 offset
+    assert_eq!(
+        invoke(&mut loads, 3, &[I32(65528)]).unwrap_err(),
+        oob(65532)
+    );
+    assert_eq!(
+        invoke(&mut loads, 4, &[I32(65529)]).unwrap_err(),
+        oob(65529)
+    );
+
+    let mut stores = func_module(
+        &[0x7f],
+        &[],
+        &[
+            &[0x20, 0x00, 0x41, 0x7f, 0x36, 0x02, 0x00], // 0: i32.store -1
+            &[0x20, 0x00, 0x41, 0x81, 0x7f, 0x3b, 0x01, 0x00], // 1: i32.store16 -127
+            // 2: i64.store8 offset=0xffffffff
+            &[
+                0x20, 0x00, 0x42, 0x01, 0x3c, 0x00, 0xff, 0xff, 0xff, 0xff, 0x0f,
+            ],
+            &[
+                0x20, 0x00, 0x44, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f, 0x39, 0x03, 0x00,
+            ], // 3: f64.store 1.0
+        ],
+        &[memory],
+    );
+    invoke(&mut stores, 0, &[I32(65532)]).unwrap();
+    assert_eq!(
+        invoke(&mut stores, 0, &[I32(65534)]).unwrap_err(),
+        oob(65534)
+    );
+    assert_eq!(
+        invoke(&mut stores, 2, &[I32(1)]).unwrap_err(),
+        oob(0x1_0000_0000)
+    );
+    assert_eq!(
+        invoke(&mut stores, 3, &[I32(65529)]).unwrap_err(),
+        oob(65529)
+    );
+    // 越界的写入不会修改内存
+    assert_eq!(
+        &stores.mem[0][65528..],
+        &[0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]
+    );
+
+    invoke(&mut stores, 1, &[I32(8)]).unwrap();
+    invoke(&mut stores, 3, &[I32(16)]).unwrap();
+    loads.mem[0] = stores.mem[0].clone();
+    assert_eq!(call_with(&mut loads, 2, &[I32(8)]), I32(-127));
+    assert_eq!(call_with(&mut loads, 1, &[I32(8)]), I32(-127));
+    assert_eq!(call_with(&mut loads, 4, &[I32(16)]), I32(1));
+    assert_eq!(call_with(&mut loads, 0, &[I32(65532)]), I32(-1));
+}
+
+#[test]
+fn test_call_policy() {
+    use super::testing::{call_with, func_bytes, invoke};
+    use WasmValue::I32;
+
+    let table = (4, vec![0x01, 0x70, 0x00, 0x02]);
+    let elem = (9, vec![0x01, 0x00, 0x41, 0x00, 0x0b, 0x02, 0x01, 0x02]);
+    let export = (7, vec![0x01, 0x03, b't', b'w', b'o', 0x00, 0x02]);
+    let bodies: &[&[u8]] = &[
+        &[0x20, 0x00, 0x11, 0x00, 0x00], // call_indirect
+        &[0x41, 0x01],
+        &[0x41, 0x02],
+    ];
+    let instance = |config: RuntimeConfig| {
+        let buf = func_bytes(
+            &[0x7f],
+            &[0x7f],
+            bodies,
+            &[table.clone(), elem.clone(), export.clone()],
+        );
+        let mut wasm = WasmModule::default(buf);
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).map(|_| wasm)
+    };
+
+    let mut wasm = instance(RuntimeConfig::default().deny_func("two")).unwrap();
+    assert_eq!(call_with(&mut wasm, 1, &[I32(0)]), I32(1));
+    assert_eq!(
+        invoke(&mut wasm, 2, &[I32(0)]),
+        Err(Trap::Forbidden { func: 2 })
+    );
+    // 间接调用同样被禁止
+    assert_eq!(
+        invoke(&mut wasm, 0, &[I32(1)]),
+        Err(Trap::Forbidden { func: 2 })
+    );
+
+    let mut wasm = instance(RuntimeConfig::default().allow_indirect(1)).unwrap();
+    assert_eq!(call_with(&mut wasm, 0, &[I32(0)]), I32(1));
+    assert_eq!(
+        invoke(&mut wasm, 0, &[I32(1)]),
+        Err(Trap::Forbidden { func: 2 })
+    );
+    // 允许列表只限制 call_indirect
+    assert_eq!(call_with(&mut wasm, 2, &[I32(0)]), I32(2));
+
+    let err = instance(RuntimeConfig::default().deny_func("three")).unwrap_err();
+    assert_eq!(err.to_string(), "unknown exported function `three`");
+    let err = instance(RuntimeConfig::default().allow_indirect(3)).unwrap_err();
+    assert_eq!(err.to_string(), "unknown function 3");
+}
+
+#[test]
+fn test_decode_unchecked() {
+    use super::testing::{func_bytes, vec, wasm};
+
+    use crate::leb::LebError;
+    use DecodeErrorKind::*;
+
+    let header = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    let cases: &[(Vec<u8>, Option<u8>, DecodeErrorKind)] = &[
+        (header[..6].to_vec(), None, UnexpectedEof),
+        (
+            b"\0ELF".to_vec(),
+            None,
+            MagicMismatch {
+                found: b"\0ELF".to_vec(),
+            },
+        ),
+        (
+            [&header[..4], &[0x02, 0, 0, 0]].concat(),
+            None,
+            UnknownVersion { found: 2 },
+        ),
+        (
+            [&header[..], &[0x0d, 0x00]].concat(),
+            None,
+            UnknownSection { id: 13 },
+        ),
+        // section 长度的 LEB128 没有结束
+        (
+            [&header[..], &[0x01, 0x80, 0x80]].concat(),
+            Some(1),
+            Leb(LebError::Unterminated),
+        ),
+        (
+            [&header[..], &[0x01, 0xff, 0xff, 0xff, 0xff, 0xff]].concat(),
+            Some(1),
+            Leb(LebError::Overflow),
+        ),
+        // br 1 只有一层块
+        (
+            func_bytes(&[], &[], &[&[0x0c, 0x01]], &[]),
+            Some(10),
+            UnknownLabel { label: 1 },
+        ),
+        (
+            func_bytes(&[], &[], &[&[0xfc, 0x12]], &[]),
+            Some(10),
+            UnknownOpcode {
+                prefix: Some(0xfc),
+                op: 18,
+            },
+        ),
+        // global 的值类型非法
+        (
+            wasm(&[(6, vec(&[vec![0x01, 0x00, 0x41, 0x00, 0x0b]]))]),
+            Some(6),
+            UnknownTag {
+                what: "value type",
+                found: 0x01,
+            },
+        ),
+        // 参数个数远大于剩余字节
+        (
+            wasm(&[(1, vec(&[vec![0x60, 0xff, 0xff, 0xff, 0xff, 0x0f]]))]),
+            Some(1),
+            UnexpectedEof,
+        ),
+    ];
+    for (buf, section, kind) in cases {
+        let err = decode_unchecked(buf).unwrap_err();
+        assert_eq!((&err.section, &err.kind), (section, kind), "{err}");
+    }
+    let err = decode_unchecked(&cases[4].0).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unexpected end of LEB128 integer in type section at offset 0x9"
+    );
+    // debug 构建中每层嵌套占用的栈较多，在更大的线程栈上检查嵌套上限
+    let nested = func_bytes(&[], &[], &[&[0x02, 0x40].repeat(2000)], &[]);
+    let err = std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(move || decode_unchecked(&nested).unwrap_err().kind)
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(err, TooDeep { limit: 1024 });
+    assert!(decode_unchecked(&func_bytes(&[], &[], &[&[0x01]], &[])).is_ok());
+}
+
+#[test]
+fn test_decode_diagnostic() {
+    use super::testing::func_bytes;
+
+    // 第二个函数体在 block 中遇到未知的操作码 0xff
+    let buf = func_bytes(&[], &[], &[&[0x01], &[0x02, 0x40, 0x01, 0xff, 0x0b]], &[]);
+    let err = decode_unchecked(&buf).unwrap_err();
+    assert_eq!(
+        err.kind,
+        DecodeErrorKind::UnknownOpcode {
+            prefix: None,
+            op: 0xff
+        }
+    );
+    assert_eq!(buf[err.offset], 0xff);
+    assert_eq!(
+        err.func,
+        Some(FuncLocation {
+            index: 1,
+            instr_offset: 5
+        })
+    );
+    assert_eq!(
+        err.to_string(),
+        "unknown opcode 0xff in code section (function 1) at offset 0x1f"
+    );
+    assert_eq!(
+        err.diagnostic(&buf),
+        "error: unknown opcode 0xff\n  --> offset 0x1f, code section, function 1, instruction +0x5\n\
+         00000012: 00 0a 0d 02 03 00 01 0b 07 00 02 40 01 ff 0b 0b\n\
+         \x20                                                ^^"
+    );
+    // 函数体之外的错误没有函数位置
+    let err = decode_unchecked(&buf[..20]).unwrap_err();
+    assert_eq!((err.section, err.func), (Some(10), None));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_decoded_roundtrip() {
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (memory 1)
+          (data (i32.const 4) "\2a")
+          (func (export "f") (param i32) (result i32)
+            (if (result i32) (local.get 0)
+              (then (i32.load (i32.const 4)))
+              (else (i32.const -1)))))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf.clone());
+    wasm.decode().unwrap();
+    let json = serde_json::to_string(&wasm.decoded()).unwrap();
+
+    let decoded = serde_json::from_str::<Decoded>(&json).unwrap();
+    let mut cached = WasmModule::from_decoded(buf.clone(), decoded).unwrap();
+    assert_eq!(cached.ops.len(), wasm.ops.len());
+    assert_eq!(cached.section.code.entries.len(), 1);
+    assert_eq!(serde_json::to_string(&cached.decoded()).unwrap(), json);
+    cached.instance(None).unwrap();
+    assert_eq!(
+        cached.invoke("f", &[WasmValue::I32(1)]).unwrap(),
+        [WasmValue::I32(42)]
+    );
+    assert_eq!(
+        cached.invoke("f", &[WasmValue::I32(0)]).unwrap(),
+        [WasmValue::I32(-1)]
+    );
+
+    let decoded = serde_json::from_str::<Decoded>(&json).unwrap();
+    assert!(WasmModule::from_decoded(buf[..buf.len() - 1].to_vec(), decoded).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_compiled_module() {
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (func (export "sum") (param i32) (result i32) (local i32)
+            (block
+              (loop
+                (br_if 1 (i32.eqz (local.get 0)))
+                (local.set 1 (i32.add (local.get 1) (local.get 0)))
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br 0)))
+            (local.get 1)))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf.clone());
+    wasm.decode().unwrap();
+    let compiled = wasm.serialize().unwrap();
+
+    let mut cached = WasmModule::deserialize(buf.clone(), &compiled).unwrap();
+    assert_eq!(cached.code.instrs, ir::lower(&wasm).instrs);
+    assert_eq!(cached.serialize().unwrap(), compiled);
+    cached.instance(None).unwrap();
+    assert_eq!(
+        cached.invoke("sum", &[WasmValue::I32(10)]).unwrap(),
+        [WasmValue::I32(55)]
+    );
+
+    let mut other = buf.clone();
+    *other.last_mut().unwrap() ^= 1;
+    assert!(WasmModule::deserialize(other, &compiled).is_err());
+    assert!(WasmModule::deserialize(buf, &compiled[..compiled.len() / 2]).is_err());
+
+    // 产物自带模块字节
+    let mut loaded = WasmModule::from_artifact(&wasm.compile_artifact().unwrap()).unwrap();
+    loaded.instance(None).unwrap();
+    assert_eq!(
+        loaded.invoke("sum", &[WasmValue::I32(4)]).unwrap(),
+        [WasmValue::I32(10)]
+    );
+    assert!(WasmModule::from_artifact(&compiled).is_err());
+}
+
+#[test]
+fn test_instances_on_threads() {
+    use super::wat;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<WasmModule>();
+
+    let raw: Arc<[u8]> = wat::compile(
+        r#"(module
+          (global $n (mut i32) (i32.const 0))
+          (func (export "bump") (param i32) (result i32)
+            (global.set $n (i32.add (global.get $n) (local.get 0)))
+            (global.get $n)))"#,
+    )
+    .unwrap()
+    .into();
+    // 同一份模块字节在不同线程上各自实例化，状态互不影响
+    let handles = (1..=4)
+        .map(|i| {
+            let raw = raw.clone();
+            std::thread::spawn(move || {
+                let mut wasm = WasmModule::default(raw);
+                wasm.decode().unwrap();
+                wasm.instance(None).unwrap();
+                wasm.invoke("bump", &[WasmValue::I32(i)]).unwrap();
+                wasm.invoke("bump", &[WasmValue::I32(i)]).unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        (1..=4)
+            .map(|i| vec![WasmValue::I32(2 * i)])
+            .collect::<Vec<_>>()
+    );
+
+    // 实例化之后的模块可以移到其他线程继续执行
+    let mut wasm = WasmModule::default(raw.clone());
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    wasm.invoke("bump", &[WasmValue::I32(5)]).unwrap();
+    let result = std::thread::spawn(move || wasm.invoke("bump", &[WasmValue::I32(1)]).unwrap())
+        .join()
+        .unwrap();
+    assert_eq!(result, [WasmValue::I32(6)]);
+    assert_eq!(Arc::strong_count(&raw), 1);
+}
+
+#[test]
+fn test_lazy_decode() {
+    use super::testing::{func_bytes, invoke};
+
+    // func 0: call 2 (local.get 0)，func 1: 未知的 0xfc 前缀操作码，func 2: local.get 0 + 1
+    let bodies: &[&[u8]] = &[
+        &[0x20, 0x00, 0x10, 0x02],
+        &[0x20, 0x00, 0xfc, 0x20],
+        &[0x20, 0x00, 0x41, 0x01, 0x6a],
+    ];
+    let buf = func_bytes(&[0x7f], &[0x7f], bodies, &[]);
+    for untyped_stack in [false, true] {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default()
+            .lazy_decode(true)
+            .untyped_stack(untyped_stack);
+        wasm.decode().unwrap();
+        assert!(wasm.ops.is_empty());
+        assert!(wasm.section.code.entries.iter().all(|body| body.pending));
+        wasm.instance(None).unwrap();
+
+        let res = invoke(&mut wasm, 0, &[WasmValue::I32(41)]).unwrap();
+        assert_eq!(res, [WasmValue::I32(42)]);
+        // 只解析了调用到的函数
+        let pending = |wasm: &WasmModule| {
+            wasm.func
+                .iter()
+                .map(|f| matches!(f, FuncKind::Local((_, body)) if body.pending))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pending(&wasm), [false, true, false]);
+
+        let err = invoke(&mut wasm, 1, &[WasmValue::I32(0)]).unwrap_err();
+        assert!(matches!(err, Trap::MalformedBody { func: 1, .. }), "{err}");
+        assert_eq!(pending(&wasm), [false, true, false]);
+        assert!(wasm.decode_bodies().is_err());
+        let res = invoke(&mut wasm, 2, &[WasmValue::I32(1)]).unwrap();
+        assert_eq!(res, [WasmValue::I32(2)]);
+    }
+
+    // decode_bodies 之后与直接解码的结果一致
+    let bodies = [bodies[0], bodies[2], bodies[2]];
+    let buf = func_bytes(&[0x7f], &[0x7f], &bodies, &[]);
+    let mut eager = WasmModule::default(buf.clone());
+    eager.decode().unwrap();
+    let mut lazy = WasmModule::default(buf);
+    lazy.config = RuntimeConfig::default().lazy_decode(true);
+    lazy.decode().unwrap();
+    lazy.decode_bodies().unwrap();
+    assert_eq!(format!("{:?}", lazy.ops), format!("{:?}", eager.ops));
+    assert_eq!(
+        lazy.section.code.instr_offsets,
+        eager.section.code.instr_offsets
+    );
+    lazy.instance(None).unwrap();
+    let res = invoke(&mut lazy, 0, &[WasmValue::I32(1)]).unwrap();
+    assert_eq!(res, [WasmValue::I32(2)]);
+}
+
+#[test]
+fn test_suspend_resume() {
+    use super::linker::{Func, Linker};
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "fetch" (func $fetch (param i32) (result i32)))
+          (func $inner (param i32) (result i32)
+            (i32.add (call $fetch (local.get 0)) (i32.const 1)))
+          (func (export "main") (param i32) (result i32) (local i32)
+            (local.set 1 (call $inner (local.get 0)))
+            (i32.mul (local.get 1) (call $inner (i32.const 10)))))"#,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define(
+            "env",
+            "fetch",
+            Func::wrap(&[ValueType::I32], &[ValueType::I32], |wasm, _| {
+                wasm.suspend()
+            }),
+        )
+        .unwrap();
+    let load = |config: RuntimeConfig| {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).unwrap();
+        wasm
+    };
+    let suspended = |res: anyhow::Result<Vec<WasmValue>>| {
+        matches!(
+            res.unwrap_err().downcast_ref::<Trap>(),
+            Some(Trap::Suspended)
+        )
+    };
+
+    for engine in [Engine::Match, Engine::Threaded] {
+        let mut wasm = load(RuntimeConfig::default().engine(engine));
+        assert!(suspended(wasm.invoke("main", &[WasmValue::I32(5)])));
+        assert!(wasm.is_suspended());
+        assert_eq!(wasm.suspended.as_ref().unwrap().len(), 2);
+        assert!(wasm.invoke("main", &[WasmValue::I32(5)]).is_err());
+        // 第二次调用 $fetch 时再次挂起
+        assert!(suspended(wasm.resume(&[WasmValue::I32(100)])));
+        let res = wasm.resume(&[WasmValue::I32(2)]).unwrap();
+        assert_eq!(res, [WasmValue::I32(101 * 3)]);
+        assert!(!wasm.is_suspended());
+        assert!(wasm.resume(&[]).is_err());
+        // 恢复后实例可以继续使用
+        assert!(suspended(wasm.invoke("main", &[WasmValue::I32(5)])));
+    }
+
+    let mut wasm = load(RuntimeConfig::default().untyped_stack(true));
+    let err = wasm.invoke("main", &[WasmValue::I32(5)]).unwrap_err();
+    assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::SuspendUnsupported));
+    assert!(!wasm.is_suspended());
+}
+
+#[test]
+fn test_host_reentrancy() {
+    use super::linker::{Func, Linker};
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    // 宿主函数调用 guest 的 malloc 分配内存并写入字符串，再返回给 guest
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "greet" (func $greet (param i32) (result i32)))
+          (import "env" "nested" (func $nested (param i32) (result i32)))
+          (memory (export "memory") 1)
+          (global $heap (mut i32) (i32.const 1024))
+          (func (export "malloc") (param i32) (result i32)
+            (global.get $heap)
+            (global.set $heap (i32.add (global.get $heap) (local.get 0))))
+          (func (export "main") (param i32) (result i32 i32 i32)
+            (local $ptr i32)
+            (local.set $ptr (call $greet (local.get 0)))
+            (local.get $ptr)
+            (i32.load8_u (local.get $ptr))
+            (i32.add (local.get 0) (i32.const 100)))
+          (func (export "depth") (param i32) (result i32)
+            (if (result i32) (i32.eqz (local.get 0))
+              (then (i32.const 0))
+              (else (i32.add (call $nested (i32.sub (local.get 0) (i32.const 1))) (i32.const 1)))))
+          (func (export "fail") (unreachable)))"#,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define(
+            "env",
+            "greet",
+            Func::wrap(&[ValueType::I32], &[ValueType::I32], |wasm, arg| {
+                let ptr = match wasm.invoke("malloc", &[WasmValue::I32(8)]).as_deref() {
+                    Ok([WasmValue::I32(ptr)]) => *ptr,
+                    _ => return vec![WasmValue::I32(0)],
+                };
+                let text = format!("hi{:?}", arg[0]);
+                wasm.memory_view(0)
+                    .and_then(|mut mem| mem.write_bytes(ptr as u32, text.as_bytes()))
+                    .unwrap();
+                // guest 中的 trap 返回给宿主函数，由它决定是否继续
+                assert!(wasm.invoke("fail", &[]).is_err());
+                vec![WasmValue::I32(ptr)]
+            }),
+        )
+        .unwrap()
+        .define(
+            "env",
+            "nested",
+            Func::wrap(&[ValueType::I32], &[ValueType::I32], |wasm, arg| match wasm
+                .invoke("depth", arg)
+            {
+                Ok(res) => res,
+                Err(err) => wasm.raise(err.downcast::<Trap>().unwrap()),
+            }),
+        )
+        .unwrap();
+
+    let configs = all_engine_configs();
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).unwrap();
+        let res = wasm.invoke("main", &[WasmValue::I32(7)]).unwrap();
+        assert_eq!(
+            res,
+            [
+                WasmValue::I32(1024),
+                WasmValue::I32(b'h' as i32),
+                WasmValue::I32(107)
+            ]
+        );
+        let res = wasm.invoke("main", &[WasmValue::I32(7)]).unwrap();
+        assert_eq!(res[0], WasmValue::I32(1032));
+        assert_eq!(
+            wasm.invoke("depth", &[WasmValue::I32(20)]).unwrap(),
+            [WasmValue::I32(20)]
+        );
+
+        wasm.config.limits.max_call_depth = Some(30);
+        let err = wasm.invoke("depth", &[WasmValue::I32(40)]).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::StackOverflow));
+        assert_eq!(wasm.csp, 0);
+        assert_eq!(
+            wasm.invoke("depth", &[WasmValue::I32(3)]).unwrap(),
+            [WasmValue::I32(3)]
+        );
+    }
+}
+
+#[test]
+fn test_instance_import_errors() {
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "g" (global i64))
+          (import "env" "mem" (memory 2)))"#,
+    )
+    .unwrap();
+    let instance = |object: Option<ImportObject>| {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.decode().unwrap();
+        wasm.instance(object)
+            .unwrap_err()
+            .downcast::<InstantiationError>()
+            .unwrap()
+    };
+    assert_eq!(
+        instance(None),
+        InstantiationError::MissingImport {
+            module: "env".into(),
+            field: "g".into(),
+            expected: ImportType::Global(ValueType::I64, false),
+        }
+    );
+
+    let mut env = HashMap::new();
+    env.insert("g".to_string(), ImportKind::Value(WasmValue::I64(1)));
+    env.insert(
+        "mem".to_string(),
+        ImportKind::Memory(Limit {
+            flag: 0,
+            minimum: 1,
+            maximum: 0x10000,
+        }),
+    );
+    let err = instance(Some(HashMap::from([("env".to_string(), env)])));
+    assert_eq!(
+        err.to_string(),
+        "incompatible import type for `env::mem`: expected memory [2 ~ 65536] pages, found memory [1 ~ 65536] pages"
+    );
+}
+
+#[test]
+fn test_import_shared_table() {
+    use super::wat;
+
+    let table = Table::new(
+        Limit {
+            flag: 1,
+            minimum: 2,
+            maximum: 4,
+        },
+        ValueType::FuncRef,
+    )
+    .unwrap();
+    let instance = |src: &str| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        let env = HashMap::from([("tbl".to_string(), ImportKind::Table(table.clone()))]);
+        wasm.instance(Some(HashMap::from([("env".to_string(), env)])))
+            .map(|_| wasm)
+    };
+    let writer = instance(
+        r#"(module
+          (import "env" "tbl" (table 2 funcref))
+          (func $f)
+          (elem (i32.const 1) $f))"#,
+    )
+    .unwrap();
+    let mut reader = instance(
+        r#"(module
+          (import "env" "tbl" (table 1 4 funcref))
+          (export "tbl" (table 0)))"#,
+    )
+    .unwrap();
+    // 两个实例和宿主看到同一份元素
+    assert!(writer.table[0].same(&reader.table[0]));
+    assert_eq!(reader.get_table("tbl").unwrap().get(1), Some(0));
+    reader.get_table("tbl").unwrap().grow(1, NULL_REF).unwrap();
+    assert_eq!(table.size(), 3);
+
+    // 声明的大小超出宿主的表
+    let err = instance(r#"(module (import "env" "tbl" (table 4 funcref)))"#)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "incompatible import type for `env::tbl`: expected table FuncRef [4 ~ 65536], found table FuncRef [3 ~ 4]"
+    );
+}
+
+#[test]
+fn test_select() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (func (export "select") (param i32) (result i32)
+            (select (i32.const 10) (i32.const 20) (local.get 0)))
+          (func (export "select_i64") (param i32) (result i64)
+            (select (result i64) (i64.const -1) (i64.const 2) (local.get 0)))
+          (func (export "select_f64") (param i32) (result f64)
+            (select (result f64) (f64.const 0.5) (f64.const 1.5) (local.get 0))))"#,
+    )
+    .unwrap();
+    let configs = all_engine_configs();
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        // 非 0 都是真，包括负数
+        for (cond, first) in [(1, true), (0, false), (-1, true), (i32::MIN, true)] {
+            let args = [WasmValue::I32(cond)];
+            let pick = |a, b| if first { a } else { b };
+            assert_eq!(
+                wasm.invoke("select", &args).unwrap(),
+                [pick(WasmValue::I32(10), WasmValue::I32(20))]
+            );
+            assert_eq!(
+                wasm.invoke("select_i64", &args).unwrap(),
+                [pick(WasmValue::I64(-1), WasmValue::I64(2))]
+            );
+            assert_eq!(
+                wasm.invoke("select_f64", &args).unwrap(),
+                [pick(WasmValue::F64(0.5), WasmValue::F64(1.5))]
+            );
+        }
+        if wasm.config.untyped_stack {
+            assert!(wasm.raw_funcs.values().all(|f| f.is_some()));
+        }
+    }
+
+    // 带类型的 select 只能有一个结果类型
+    let mut buf = wat::compile(
+        r#"(module (func (result i32)
+          (select (result i32) (i32.const 1) (i32.const 2) (i32.const 0))))"#,
+    )
+    .unwrap();
+    let at = buf
+        .windows(3)
+        .position(|w| w == [0x1c, 0x01, 0x7f])
+        .unwrap();
+    buf[at + 1] = 0x02;
+    assert!(WasmModule::default(buf).decode().is_err());
+}
+
+#[test]
+fn test_branch_conditions() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (func (export "if") (param i32) (result i32)
+            (if (result i32) (local.get 0) (then (i32.const 1)) (else (i32.const 0))))
+          (func (export "br_if") (param i32) (result i32)
+            (block (result i32)
+              (drop (br_if 0 (i32.const 1) (local.get 0)))
+              (i32.const 0)))
+          (func (export "br_table") (param i32) (result i32)
+            (block (block (block
+              (br_table 0 1 2 (local.get 0)))
+              (return (i32.const 0)))
+              (return (i32.const 1)))
+            (i32.const 2)))"#,
+    )
+    .unwrap();
+    let configs = all_engine_configs();
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        // 非 0 都是真，包括负数
+        for (cond, taken) in [(1, 1), (0, 0), (-1, 1), (i32::MIN, 1), (i32::MAX, 1)] {
+            let args = [WasmValue::I32(cond)];
+            assert_eq!(wasm.invoke("if", &args).unwrap(), [WasmValue::I32(taken)]);
+            assert_eq!(
+                wasm.invoke("br_if", &args).unwrap(),
+                [WasmValue::I32(taken)]
+            );
+        }
+        // br_table 的索引按无符号数处理，负数越界后取默认分支
+        for (index, target) in [(0, 0), (1, 1), (2, 2), (3, 2), (-1, 2), (i32::MIN, 2)] {
+            assert_eq!(
+                wasm.invoke("br_table", &[WasmValue::I32(index)]).unwrap(),
+                [WasmValue::I32(target)]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_branch_unwind() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (func (export "block") (param i32) (result i32)
+            (i32.const 10)
+            (block (result i32)
+              (i32.const 5) (i32.const 1) (local.get 0) (br_if 0) (i32.add))
+            (i32.add))
+          (func (export "br_table") (param i32) (result i32)
+            (i32.const 100)
+            (block (result i32)
+              (i32.const 7)
+              (block (result i32)
+                (i32.const 8) (i32.const 1) (local.get 0) (br_table 0 1))
+              (i32.add))
+            (i32.add))
+          (func (export "loop") (param i32) (result i32)
+            (i32.const 100)
+            (block (result i32)
+              (loop
+                (i32.const 9)
+                (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br_if 0)
+                (drop))
+              (i32.const 1))
+            (i32.add))
+          (func (export "return") (param i32) (result i32)
+            (i32.const 7)
+            (i32.const 5)
+            (local.get 0)
+            (br_if 0)
+            (drop)
+            (i32.const 3)
+            (br 0))
+          (func (export "else") (param i32) (result i32)
+            (block (result i32)
+              (if (local.get 0)
+                (then (nop))
+                (else (br 1 (i32.const 7))))
+              (i32.const 3))))"#,
+    )
+    .unwrap();
+    let configs = all_engine_configs();
+    let cases = [
+        ("block", 1, 11),
+        ("block", 0, 16),
+        ("br_table", 0, 108),
+        ("br_table", 1, 101),
+        ("loop", 1000, 101),
+        ("return", 1, 5),
+        ("return", 0, 3),
+        ("else", 0, 7),
+        ("else", 1, 3),
+    ];
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        for (name, arg, expected) in cases {
+            assert_eq!(
+                wasm.invoke(name, &[WasmValue::I32(arg)]).unwrap(),
+                [WasmValue::I32(expected)],
+                "{name}({arg})"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_br_table() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    // 1000 个表项交替跳到标签 0 和 1，越界时跳到默认标签 2
+    let entries = (0..1000).map(|i| ["0 ", "1 "][i % 2]).collect::<String>();
+    let buf = wat::compile(&format!(
+        r#"(module
+          (func (export "nested") (param i32) (result i32)
+            (block (result i32)
+              (drop (i32.const -1))
+              (i32.add
+                (i32.const 1)
+                (block (result i32)
+                  (i32.add
+                    (i32.const 2)
+                    (block (result i32)
+                      (drop (i32.const 4))
+                      (i32.add
+                        (i32.const 8)
+                        (br_table 0 1 2 (i32.const 16) (local.get 0)))))))))
+          (func (export "index") (param i32) (result i32)
+            (block (result i32)
+              (i32.const 5)
+              (block (result i32)
+                (br_table 0 1 (i32.const 8) (br_table 0 1 (i32.const 1) (local.get 0))))
+              (i32.add)))
+          (func (export "pair") (param i32) (result i32)
+            (i32.sub
+              (block (result i32 i32)
+                (i32.const 100)
+                (block (result i32 i32)
+                  (i32.const 7) (i32.const 3) (local.get 0)
+                  (br_table 0 1))
+                (i32.add))))
+          (func (export "loop") (param i32) (result i32) (local i32)
+            (block
+              (loop
+                (local.set 1 (i32.add (local.get 1) (i32.const 1)))
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br_table 0 1 (i32.eqz (local.get 0)))))
+            (local.get 1))
+          (func (export "large") (param i32) (result i32)
+            (block (block (block
+              (br_table {entries} 2 (local.get 0)))
+              (return (i32.const 0)))
+              (return (i32.const 1)))
+            (i32.const 2)))"#
+    ))
+    .unwrap();
+    let configs = all_engine_configs();
+    let cases = [
+        ("nested", 0, 19),
+        ("nested", 1, 17),
+        ("nested", 2, 16),
+        ("nested", 10, 16),
+        ("nested", -1, 16),
+        ("nested", 100000, 16),
+        ("index", 0, 6),
+        ("index", 1, 1),
+        ("index", -1, 1),
+        // 跳出外层块时丢弃 100
+        ("pair", 0, 90),
+        ("pair", 1, 4),
+        ("pair", i32::MAX, 4),
+        ("loop", 1, 1),
+        ("loop", 5, 5),
+        ("large", 0, 0),
+        ("large", 1, 1),
+        ("large", 998, 0),
+        ("large", 999, 1),
+        ("large", 1000, 2),
+        ("large", -1, 2),
+    ];
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        assert!(wasm.validate().is_empty(), "{:?}", wasm.validate());
+        wasm.instance(None).unwrap();
+        for (name, arg, expected) in cases {
+            assert_eq!(
+                wasm.invoke(name, &[WasmValue::I32(arg)]).unwrap(),
+                [WasmValue::I32(expected)],
+                "{name}({arg})"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_function_end() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    // $first 从 ops 的下标 0 开始，块的 end 和函数的 end 相邻
+    let buf = wat::compile(
+        r#"(module
+          (func $first (param i32) (result i32)
+            (block (result i32) (block (result i32) (local.get 0))))
+          (func $empty)
+          (func (export "main") (param i32) (result i32)
+            (call $empty)
+            (i32.add
+              (call $first (local.get 0))
+              (block (result i32) (call $first (i32.const 1))))))"#,
+    )
+    .unwrap();
+    let mut configs = all_engine_configs();
+    configs.push(RuntimeConfig::default().lazy_decode(true));
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        assert_eq!(
+            wasm.invoke("main", &[WasmValue::I32(41)]).unwrap(),
+            [WasmValue::I32(42)]
+        );
+    }
+}
+
+#[test]
+fn test_block_params() {
+    use super::section::opcode::BlockType;
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    // 前 70 个类型占位，类型索引 70 编码为两个字节的 s33
+    let types = "(type (func))".repeat(70);
+    let buf = wat::compile(&format!(
+        r#"(module
+          {types}
+          (type $sum (func (param i32 i32) (result i32)))
+          (func (export "sum") (param i32) (result i32)
+            (i32.const 0)
+            (local.get 0)
+            (loop (type $sum)
+              (local.set 0)
+              (i32.add (local.get 0))
+              (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))
+              (br_if 0 (local.get 0))
+              (drop)))
+          (func (export "swap") (param i32) (result i32)
+            (i32.const 3)
+            (i32.const 1)
+            (if (param i32 i32) (result i32) (local.get 0)
+              (then (i32.sub))
+              (else (drop)))))"#
+    ))
+    .unwrap();
+    let configs = all_engine_configs();
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        assert!(matches!(
+            wasm.ops.iter().find(|op| matches!(op, Opcode::Loop(..))),
+            Some(Opcode::Loop(BlockType::Value(70), _))
+        ));
+        wasm.instance(None).unwrap();
+        let invoke = |wasm: &mut WasmModule, name, arg| wasm.invoke(name, &[WasmValue::I32(arg)]);
+        assert_eq!(
+            invoke(&mut wasm, "sum", 100).unwrap(),
+            [WasmValue::I32(5050)]
+        );
+        assert_eq!(invoke(&mut wasm, "swap", 1).unwrap(), [WasmValue::I32(2)]);
+        assert_eq!(invoke(&mut wasm, "swap", 0).unwrap(), [WasmValue::I32(3)]);
+    }
+}
+
+#[test]
+fn test_return_results() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (func $pair (param i32) (result i32 i32)
+            (i32.const 9)
+            (block
+              (loop
+                (i32.const 1)
+                (i32.const 2)
+                (br_if 2 (local.get 0))
+                (return)))
+            (unreachable))
+          (func (export "sub") (param i32) (result i32)
+            (i32.sub (call $pair (local.get 0))))
+          (func (export "pair") (param i32) (result i32 i32)
+            (call $pair (local.get 0))))"#,
+    )
+    .unwrap();
+    let configs = all_engine_configs();
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        // 无论从 return 还是最外层的 br_if 返回，结果都按声明的顺序传给调用方
+        for arg in [0, 1] {
+            let args = [WasmValue::I32(arg)];
+            assert_eq!(wasm.invoke("sub", &args).unwrap(), [WasmValue::I32(-1)]);
+            assert_eq!(
+                wasm.invoke("pair", &args).unwrap(),
+                [WasmValue::I32(1), WasmValue::I32(2)]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_unsigned_ops() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+    use WasmValue::{F32, F64, I32, I64};
+
+    let buf = wat::compile(
+        r#"(module
+          (func (export "i32.lt_u") (param i32 i32) (result i32) (i32.lt_u (local.get 0) (local.get 1)))
+          (func (export "i32.gt_u") (param i32 i32) (result i32) (i32.gt_u (local.get 0) (local.get 1)))
+          (func (export "i32.le_u") (param i32 i32) (result i32) (i32.le_u (local.get 0) (local.get 1)))
+          (func (export "i32.ge_u") (param i32 i32) (result i32) (i32.ge_u (local.get 0) (local.get 1)))
+          (func (export "i32.lt_s") (param i32 i32) (result i32) (i32.lt_s (local.get 0) (local.get 1)))
+          (func (export "i32.div_u") (param i32 i32) (result i32) (i32.div_u (local.get 0) (local.get 1)))
+          (func (export "i32.shr_u") (param i32 i32) (result i32) (i32.shr_u (local.get 0) (local.get 1)))
+          (func (export "i32.shr_s") (param i32 i32) (result i32) (i32.shr_s (local.get 0) (local.get 1)))
+          (func (export "i64.lt_u") (param i64 i64) (result i32) (i64.lt_u (local.get 0) (local.get 1)))
+          (func (export "i64.ge_u") (param i64 i64) (result i32) (i64.ge_u (local.get 0) (local.get 1)))
+          (func (export "i64.div_u") (param i64 i64) (result i64) (i64.div_u (local.get 0) (local.get 1)))
+          (func (export "i64.shr_u") (param i64 i64) (result i64) (i64.shr_u (local.get 0) (local.get 1)))
+          (func (export "i64.extend_i32_u") (param i32) (result i64) (i64.extend_i32_u (local.get 0)))
+          (func (export "i64.extend_i32_s") (param i32) (result i64) (i64.extend_i32_s (local.get 0)))
+          (func (export "f64.convert_i32_u") (param i32) (result f64) (f64.convert_i32_u (local.get 0)))
+          (func (export "f32.convert_i64_u") (param i64) (result f32) (f32.convert_i64_u (local.get 0))))"#,
+    )
+    .unwrap();
+    let cases: &[(&str, &[WasmValue], WasmValue)] = &[
+        ("i32.lt_u", &[I32(1), I32(-1)], I32(1)),
+        ("i32.lt_u", &[I32(i32::MIN), I32(i32::MAX)], I32(0)),
+        ("i32.gt_u", &[I32(i32::MIN), I32(i32::MAX)], I32(1)),
+        ("i32.le_u", &[I32(-1), I32(-1)], I32(1)),
+        ("i32.ge_u", &[I32(0), I32(i32::MIN)], I32(0)),
+        ("i32.lt_s", &[I32(i32::MIN), I32(i32::MAX)], I32(1)),
+        ("i32.div_u", &[I32(-1), I32(2)], I32(i32::MAX)),
+        ("i32.div_u", &[I32(i32::MIN), I32(-1)], I32(0)),
+        ("i32.shr_u", &[I32(i32::MIN), I32(31)], I32(1)),
+        ("i32.shr_u", &[I32(-1), I32(33)], I32(i32::MAX)),
+        ("i32.shr_s", &[I32(i32::MIN), I32(31)], I32(-1)),
+        ("i64.lt_u", &[I64(1), I64(-1)], I32(1)),
+        ("i64.ge_u", &[I64(i64::MIN), I64(i64::MAX)], I32(1)),
+        ("i64.div_u", &[I64(-1), I64(2)], I64(i64::MAX)),
+        ("i64.shr_u", &[I64(i64::MIN), I64(63)], I64(1)),
+        ("i64.extend_i32_u", &[I32(-1)], I64(0xffff_ffff)),
+        ("i64.extend_i32_s", &[I32(-1)], I64(-1)),
+        ("f64.convert_i32_u", &[I32(i32::MIN)], F64(2147483648.0)),
+        ("f32.convert_i64_u", &[I64(-1)], F32(18446744073709551616.0)),
+    ];
+    let configs = all_engine_configs();
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        for (name, args, expected) in cases {
+            assert_eq!(
+                wasm.invoke(name, args).unwrap(),
+                [*expected],
+                "{name}{args:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_custom_sections() {
+    use super::testing::{vec, wasm};
+
+    let name = |s: &str| vec(&s.bytes().map(|b| vec![b]).collect::<Vec<_>>());
+    let producers = [
+        vec![0x01],
+        name("language"),
+        vec![0x01],
+        name("C99"),
+        name(""),
+    ]
+    .concat();
+    let mut module = WasmModule::default(wasm(&[
+        (0, [name("producers"), producers].concat()),
+        (0, [name("note"), b"first".to_vec()].concat()),
+        (1, vec![0x00]),
+        (0, [name("note"), b"second".to_vec()].concat()),
+    ]));
+    module.decode().unwrap();
+    let sections = module.custom_sections().collect::<Vec<_>>();
+    assert_eq!(
+        sections.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+        ["producers", "note", "note"]
+    );
+    assert_eq!(sections[1].1, b"first");
+    assert_eq!(module.custom_section("note"), Some(&b"second"[..]));
+    assert_eq!(module.custom_section("name"), None);
+    let producers = module.producers().unwrap().unwrap();
+    assert_eq!(producers.field("language"), [("C99".into(), "".into())]);
+
+    let mut module = WasmModule::default(wasm(&[(0, [name("producers"), vec![0x01]].concat())]));
+    module.decode().unwrap();
+    assert!(module.producers().is_err());
+    let mut module = WasmModule::default(wasm(&[]));
+    module.decode().unwrap();
+    assert_eq!(module.producers().unwrap(), None);
+}
+
+#[cfg(feature = "function-references")]
+#[test]
+fn test_function_references() {
+    use super::testing::all_engine_configs;
+    use super::testing::{vec, wasm};
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (type $t (func (param i32) (result i32)))
+          (type $v (func))
+          (func $double (type $t) (i32.mul (local.get 0) (i32.const 2)))
+          (func $inc (type $t) (i32.add (local.get 0) (i32.const 1)))
+          (func (export "apply") (param i32 i32) (result i32)
+            (call_ref $t
+              (local.get 1)
+              (if (result funcref) (local.get 0)
+                (then (ref.func $double))
+                (else (ref.func $inc)))))
+          (func (export "or_default") (param i32) (result i32)
+            (block $null
+              (i32.const 7)
+              (if (result funcref) (local.get 0)
+                (then (ref.func $double))
+                (else (ref.null func)))
+              (br_on_null $null)
+              (call_ref $t)
+              (return))
+            (i32.const -1))
+          (func (export "non_null") (param i32) (result i32)
+            (local $f funcref)
+            (local.set $f
+              (block $some (result funcref)
+                (i32.const 5)
+                (if (result funcref) (local.get 0)
+                  (then (ref.func $inc))
+                  (else (ref.null func)))
+                (br_on_non_null $some)
+                (drop)
+                (ref.func $double)))
+            (call_ref $t (i32.const 10) (local.get $f)))
+          (func (export "null_call") (result i32)
+            (call_ref $t (i32.const 1) (ref.null func)))
+          (func (export "as_non_null") (result i32)
+            (ref.is_null (ref.as_non_null (ref.null func))))
+          (func (export "mismatch")
+            (call_ref $v (ref.func $inc))))"#,
+    )
+    .unwrap();
+    let configs = all_engine_configs();
+    let cases = [
+        ("apply", &[1, 21][..], 42),
+        ("apply", &[0, 21], 22),
+        ("or_default", &[1], 14),
+        ("or_default", &[0], -1),
+        ("non_null", &[1], 11),
+        ("non_null", &[0], 20),
+    ];
+    for config in configs {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        for (name, args, expected) in cases {
+            let args = args.iter().map(|v| WasmValue::I32(*v)).collect::<Vec<_>>();
+            assert_eq!(
+                wasm.invoke(name, &args).unwrap(),
+                [WasmValue::I32(expected)],
+                "{name}({args:?})"
+            );
+        }
+        let err = wasm.invoke("null_call", &[]).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::NullReference));
+        let err = wasm.invoke("as_non_null", &[]).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::NullReference));
+        let err = wasm.invoke("mismatch", &[]).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Trap>(),
+            Some(&Trap::SignatureMismatch { func: 1 })
+        );
+    }
+
+    // 参数为 (ref 0)，局部变量为 (ref null func)，类型索引被擦除为 funcref
+    let types = vec(&[
+        vec![0x60, 0x00, 0x01, 0x7f],
+        vec![0x60, 0x01, 0x64, 0x00, 0x01, 0x7f],
+    ]);
+    let caller = vec![0x08, 0x01, 0x01, 0x63, 0x70, 0x20, 0x00, 0x14, 0x00, 0x0b];
+    let callee = vec![0x04, 0x00, 0x41, 0x2a, 0x0b];
+    let export = vec(&[vec![0x04, b'c', b'a', b'l', b'l', 0x00, 0x00]]);
+    let buf = wasm(&[
+        (1, types),
+        (3, vec(&[vec![0x01], vec![0x00]])),
+        (7, export),
+        (10, vec(&[caller, callee])),
+    ]);
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    assert_eq!(wasm.section.types.entries[1].params, [ValueType::FuncRef]);
+    assert_eq!(
+        wasm.section.code.entries[0].locales,
+        [(1, ValueType::FuncRef)]
+    );
+    wasm.instance(None).unwrap();
+    assert_eq!(
+        wasm.invoke("call", &[WasmValue::FuncRef(1)]).unwrap(),
+        [WasmValue::I32(42)]
+    );
+}
+
+#[cfg(feature = "gc")]
+#[test]
+fn test_gc_types() {
+    use super::section::types::{CompositeType, FieldType, StorageType};
+    use super::testing::{vec, wasm};
+
+    let types = vec(&[
+        // (rec (type (sub (struct (field (mut i8)) (field i32))))
+        //      (type (sub final 0 (struct (field (mut i8)) (field i32) (field (mut (ref null any)))))))
+        vec![
+            0x4e, 0x02, 0x50, 0x00, 0x5f, 0x02, 0x78, 0x01, 0x7f, 0x00, 0x4f, 0x01, 0x00, 0x5f,
+            0x03, 0x78, 0x01, 0x7f, 0x00, 0x63, 0x6e, 0x01,
+        ],
+        // (type (array (mut eqref)))
+        vec![0x5e, 0x6d, 0x01],
+        // (type (func (param anyref) (result i32)))
+        vec![0x60, 0x01, 0x6e, 0x01, 0x7f],
+    ]);
+    let mut wasm = WasmModule::default(wasm(&[(1, types)]));
+    wasm.decode().unwrap();
+    let types = &wasm.section.types;
+    assert_eq!(types.type_count, 3);
+    assert_eq!(types.entries.len(), 4);
+    let subs = &types.subtypes;
+    assert_eq!(
+        subs.iter().map(|sub| sub.rec_group).collect::<Vec<_>>(),
+        [0, 0, 1, 2]
+    );
+    assert!(!subs[0].is_final && subs[1].is_final);
+    assert_eq!(subs[1].supertypes, [0]);
+    let field = |storage, mutable| FieldType { storage, mutable };
+    assert_eq!(
+        subs[1].composite,
+        CompositeType::Struct(vec![
+            field(StorageType::I8, true),
+            field(StorageType::Value(ValueType::I32), false),
+            field(StorageType::Value(ValueType::AnyRef), true),
+        ])
+    );
+    assert_eq!(
+        subs[2].composite,
+        CompositeType::Array(field(StorageType::Value(ValueType::AnyRef), true))
+    );
+    assert_eq!(types.entries[3].params, [ValueType::AnyRef]);
+    assert!(!subs[3].is_gc());
+    let text = types.to_string();
+    assert!(
+        text.contains("(0)Struct: (mut I8,I32) open rec 0"),
+        "{text}"
+    );
+    assert!(text.contains("(3)Type: (AnyRef) => I32"), "{text}");
+
+    assert_eq!(wasm.required_features(), ["gc"]);
+    let err = wasm.instance(None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "module uses the gc proposal, which can't be executed yet"
+    );
+}
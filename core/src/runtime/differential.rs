@@ -0,0 +1,517 @@
+//! 差分测试：同一个导出函数以同样的参数分别在 oxygen 和参考引擎 wasmi 中执行，
+//! 比较结果和 trap，用来系统地发现解释器的语义错误
+//!
+//! 每次调用都重新实例化模块，调用之间互不影响，结果只取决于函数和参数。
+//! 参数由固定种子的伪随机数生成，偏向边界值（0、-1、最小最大值、NaN、无穷等），
+//! 同一个种子得到同样的调用序列，发现的问题可以复现。
+
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+
+use super::cancel::CancellationToken;
+use super::config::RuntimeConfig;
+use super::decoder::WasmModule;
+use super::decoder::WasmValue;
+use super::section::export::ExportKind;
+use super::section::typings::ValueType;
+use super::spectest::guarded;
+use super::trap::Trap;
+
+/// 不依赖错误信息的 trap 种类，两个引擎的 trap 只比较种类
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrapKind {
+    Unreachable,
+    MemoryOutOfBounds,
+    TableOutOfBounds,
+    IndirectCallToNull,
+    SignatureMismatch,
+    DivideByZero,
+    IntegerOverflow,
+    InvalidConversion,
+    StackOverflow,
+    /// 没有对应种类的 trap，保留原来的信息
+    Other(String),
+}
+
+impl From<&Trap> for TrapKind {
+    fn from(trap: &Trap) -> Self {
+        match trap {
+            Trap::Unreachable => TrapKind::Unreachable,
+            Trap::MemoryOutOfBounds { .. } => TrapKind::MemoryOutOfBounds,
+            Trap::TableOutOfBounds { .. } | Trap::UndefinedElement { .. } => {
+                TrapKind::TableOutOfBounds
+            }
+            Trap::UninitializedElement { .. } | Trap::NullReference => TrapKind::IndirectCallToNull,
+            Trap::SignatureMismatch { .. } => TrapKind::SignatureMismatch,
+            Trap::DivideByZero => TrapKind::DivideByZero,
+            Trap::IntegerOverflow => TrapKind::IntegerOverflow,
+            Trap::InvalidConversion => TrapKind::InvalidConversion,
+            Trap::StackOverflow => TrapKind::StackOverflow,
+            trap => TrapKind::Other(trap.to_string()),
+        }
+    }
+}
+
+impl From<wasmi::TrapCode> for TrapKind {
+    fn from(code: wasmi::TrapCode) -> Self {
+        use wasmi::TrapCode;
+        match code {
+            TrapCode::UnreachableCodeReached => TrapKind::Unreachable,
+            TrapCode::MemoryOutOfBounds => TrapKind::MemoryOutOfBounds,
+            TrapCode::TableOutOfBounds => TrapKind::TableOutOfBounds,
+            TrapCode::IndirectCallToNull => TrapKind::IndirectCallToNull,
+            TrapCode::BadSignature => TrapKind::SignatureMismatch,
+            TrapCode::IntegerDivisionByZero => TrapKind::DivideByZero,
+            TrapCode::IntegerOverflow => TrapKind::IntegerOverflow,
+            TrapCode::BadConversionToInteger => TrapKind::InvalidConversion,
+            TrapCode::StackOverflow => TrapKind::StackOverflow,
+            code => TrapKind::Other(code.to_string()),
+        }
+    }
+}
+
+impl Display for TrapKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrapKind::Unreachable => write!(f, "unreachable"),
+            TrapKind::MemoryOutOfBounds => write!(f, "out of bounds memory access"),
+            TrapKind::TableOutOfBounds => write!(f, "out of bounds table access"),
+            TrapKind::IndirectCallToNull => write!(f, "uninitialized element"),
+            TrapKind::SignatureMismatch => write!(f, "indirect call type mismatch"),
+            TrapKind::DivideByZero => write!(f, "integer divide by zero"),
+            TrapKind::IntegerOverflow => write!(f, "integer overflow"),
+            TrapKind::InvalidConversion => write!(f, "invalid conversion to integer"),
+            TrapKind::StackOverflow => write!(f, "call stack exhausted"),
+            TrapKind::Other(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+/// 一个引擎执行一次调用的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Values(Vec<WasmValue>),
+    Trap(TrapKind),
+    /// 执行中 panic（例如还没有实现的指令），只会出现在 oxygen 中
+    Panic(String),
+    /// 超时或燃料耗尽，这次调用不参与比较
+    Timeout,
+}
+
+impl Outcome {
+    /// 两个结果语义相同：NaN 的负载位是不确定的，任意两个 NaN 视为相同，其他浮点数按位比较
+    pub fn agrees(&self, other: &Outcome) -> bool {
+        let same = |a: &WasmValue, b: &WasmValue| match (a, b) {
+            (WasmValue::F32(a), WasmValue::F32(b)) => {
+                (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+            }
+            (WasmValue::F64(a), WasmValue::F64(b)) => {
+                (a.is_nan() && b.is_nan()) || a.to_bits() == b.to_bits()
+            }
+            (a, b) => a == b,
+        };
+        match (self, other) {
+            (Outcome::Values(a), Outcome::Values(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+            }
+            (Outcome::Trap(a), Outcome::Trap(b)) => a == b,
+            (Outcome::Timeout, _) | (_, Outcome::Timeout) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Values(values) => write!(f, "{}", format_values(values)),
+            Outcome::Trap(kind) => write!(f, "trap: {kind}"),
+            Outcome::Panic(msg) => write!(f, "panic: {msg}"),
+            Outcome::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+fn format_values(values: &[WasmValue]) -> String {
+    let values = values
+        .iter()
+        .map(|value| match value {
+            WasmValue::I32(v) => format!("i32:{v}"),
+            WasmValue::I64(v) => format!("i64:{v}"),
+            WasmValue::F32(v) => format!("f32:{v:?}"),
+            WasmValue::F64(v) => format!("f64:{v:?}"),
+            WasmValue::V128(v) => format!("v128:{v:#034x}"),
+            value => format!("{value:?}"),
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", values.join(", "))
+}
+
+/// 两个引擎结果不一致的调用
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub func: String,
+    pub args: Vec<WasmValue>,
+    pub oxygen: Outcome,
+    pub reference: Outcome,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}{}", self.func, format_values(&self.args))?;
+        writeln!(f, "  oxygen: {}", self.oxygen)?;
+        writeln!(f, "  wasmi:  {}", self.reference)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// oxygen 的配置，cancellation 会被每次调用的超时覆盖
+    pub config: RuntimeConfig,
+    /// 有参数的函数调用的次数，没有参数的函数只调用一次
+    pub iterations: usize,
+    pub seed: u64,
+    /// oxygen 每次调用的时间限制
+    pub timeout: Duration,
+    /// wasmi 每次调用的燃料（约为执行的指令数）
+    pub fuel: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            config: RuntimeConfig::default(),
+            iterations: 100,
+            seed: 0,
+            timeout: Duration::from_secs(1),
+            fuel: 100_000_000,
+        }
+    }
+}
+
+/// 一次差分测试的统计和发现的问题
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub calls: usize,
+    pub timeouts: usize,
+    /// 参数含引用类型、无法构造的导出函数
+    pub skipped: Vec<String>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mismatch in &self.mismatches {
+            write!(f, "{mismatch}")?;
+        }
+        if !self.skipped.is_empty() {
+            writeln!(f, "skipped: {}", self.skipped.join(", "))?;
+        }
+        writeln!(
+            f,
+            "{} calls, {} mismatches, {} timeouts",
+            self.calls,
+            self.mismatches.len(),
+            self.timeouts
+        )
+    }
+}
+
+/// 模块和它导出的函数，按导出名排序
+pub struct Differential {
+    buf: Vec<u8>,
+    options: Options,
+    engine: wasmi::Engine,
+    module: wasmi::Module,
+    funcs: Vec<(String, Vec<ValueType>)>,
+}
+
+impl Differential {
+    /// 两个引擎都要能加载模块；模块不能有导入，两边提供的宿主函数无法保证一致
+    pub fn new(buf: Vec<u8>, options: Options) -> anyhow::Result<Self> {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = options.config.clone();
+        wasm.decode()?;
+        ensure!(
+            wasm.section.import.entries.is_empty(),
+            "modules with imports are not supported"
+        );
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = wasmi::Engine::new(&config);
+        let module = wasmi::Module::new(&engine, &buf[..])
+            .map_err(|err| anyhow::anyhow!("wasmi can't load the module: {err}"))?;
+
+        let mut funcs = vec![];
+        for export in &wasm.section.export.entries {
+            let ExportKind::Func(idx) = export.kind else {
+                continue;
+            };
+            let ty = wasm.section.func.entries[idx];
+            let params = wasm.section.types.entries[ty].params.clone();
+            funcs.push((export.name.clone(), params));
+        }
+        funcs.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(Self {
+            buf,
+            options,
+            engine,
+            module,
+            funcs,
+        })
+    }
+
+    /// 导出的函数名
+    pub fn funcs(&self) -> impl Iterator<Item = &str> {
+        self.funcs.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// 用随机参数调用 only 指定的（默认为所有）导出函数，比较两个引擎的结果
+    pub fn run(&self, only: Option<&str>) -> anyhow::Result<Report> {
+        if let Some(name) = only {
+            ensure!(
+                self.funcs().any(|func| func == name),
+                "unknown function export `{name}`"
+            );
+        }
+        let mut rng = Rng(self.options.seed ^ 0x9e37_79b9_7f4a_7c15);
+        let mut report = Report::default();
+        for (name, params) in &self.funcs {
+            if only.is_some_and(|only| only != name) {
+                continue;
+            }
+            let iterations = match params.is_empty() {
+                true => 1,
+                false => self.options.iterations,
+            };
+            for _ in 0..iterations {
+                let Some(args) = params
+                    .iter()
+                    .map(|ty| rng.value(*ty))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    report.skipped.push(name.clone());
+                    break;
+                };
+                let (oxygen, reference) = self.compare(name, &args)?;
+                report.calls += 1;
+                if oxygen == Outcome::Timeout || reference == Outcome::Timeout {
+                    report.timeouts += 1;
+                }
+                if !oxygen.agrees(&reference) {
+                    report.mismatches.push(Mismatch {
+                        func: name.clone(),
+                        args,
+                        oxygen,
+                        reference,
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// 以 args 调用导出函数 name，返回 (oxygen 的结果, wasmi 的结果)；
+    /// 实例化失败时出错
+    pub fn compare(&self, name: &str, args: &[WasmValue]) -> anyhow::Result<(Outcome, Outcome)> {
+        Ok((self.oxygen(name, args)?, self.reference(name, args)?))
+    }
+
+    fn oxygen(&self, name: &str, args: &[WasmValue]) -> anyhow::Result<Outcome> {
+        let token = CancellationToken::new();
+        let mut wasm = WasmModule::default(self.buf.clone());
+        wasm.config = self
+            .options
+            .config
+            .clone()
+            .cancellation_token(token.clone());
+        wasm.decode()?;
+        let result = guarded(token, self.options.timeout, || -> anyhow::Result<_> {
+            wasm.instance(None)?;
+            let (idx, _) = wasm.export_func(name)?;
+            Ok(wasm.call_with(idx, args))
+        });
+        Ok(match result {
+            Ok(result) => match result.context("oxygen can't instantiate the module")? {
+                Ok(values) => Outcome::Values(values),
+                Err(Trap::Cancelled) => Outcome::Timeout,
+                Err(trap) => Outcome::Trap(TrapKind::from(&trap)),
+            },
+            Err(msg) => Outcome::Panic(msg.trim_start_matches("panic: ").to_string()),
+        })
+    }
+
+    fn reference(&self, name: &str, args: &[WasmValue]) -> anyhow::Result<Outcome> {
+        let mut store = wasmi::Store::new(&self.engine, ());
+        store.set_fuel(self.options.fuel)?;
+        let linker = wasmi::Linker::<()>::new(&self.engine);
+        let instance = linker
+            .instantiate_and_start(&mut store, &self.module)
+            .map_err(|err| anyhow::anyhow!("wasmi can't instantiate the module: {err}"))?;
+        let func = instance
+            .get_func(&store, name)
+            .with_context(|| format!("unknown function export `{name}`"))?;
+        let inputs = args
+            .iter()
+            .map(to_wasmi)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut outputs = func
+            .ty(&store)
+            .results()
+            .iter()
+            .map(|ty| wasmi::Val::default_for_ty(*ty))
+            .collect::<Vec<_>>();
+        if let Err(err) = func.call(&mut store, &inputs, &mut outputs) {
+            return Ok(match err.as_trap_code() {
+                Some(wasmi::TrapCode::OutOfFuel) => Outcome::Timeout,
+                Some(code) => Outcome::Trap(code.into()),
+                None => Outcome::Trap(TrapKind::Other(err.to_string())),
+            });
+        }
+        let values = outputs
+            .iter()
+            .map(from_wasmi)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Outcome::Values(values))
+    }
+}
+
+fn to_wasmi(value: &WasmValue) -> anyhow::Result<wasmi::Val> {
+    Ok(match *value {
+        WasmValue::I32(v) => wasmi::Val::I32(v),
+        WasmValue::I64(v) => wasmi::Val::I64(v),
+        WasmValue::F32(v) => wasmi::Val::F32(v.into()),
+        WasmValue::F64(v) => wasmi::Val::F64(v.into()),
+        WasmValue::V128(v) => wasmi::Val::V128((v as u128).into()),
+        value => bail!("can't pass {value:?} to wasmi"),
+    })
+}
+
+fn from_wasmi(value: &wasmi::Val) -> anyhow::Result<WasmValue> {
+    Ok(match value {
+        wasmi::Val::I32(v) => WasmValue::I32(*v),
+        wasmi::Val::I64(v) => WasmValue::I64(*v),
+        wasmi::Val::F32(v) => WasmValue::F32(f32::from_bits(v.to_bits())),
+        wasmi::Val::F64(v) => WasmValue::F64(f64::from_bits(v.to_bits())),
+        wasmi::Val::V128(v) => WasmValue::V128(v.as_u128() as i128),
+        value => bail!("can't compare {value:?} returned by wasmi"),
+    })
+}
+
+/// xorshift64*，只用来生成可复现的参数
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.next() as usize % items.len()]
+    }
+
+    /// 一半取边界值，一半取随机的位模式；引用类型返回 None
+    fn value(&mut self, ty: ValueType) -> Option<WasmValue> {
+        let special = self.next() & 1 == 0;
+        Some(match ty {
+            ValueType::I32 if special => {
+                WasmValue::I32(self.pick(&[0, 1, -1, 2, 31, 32, i32::MIN, i32::MAX]))
+            }
+            ValueType::I32 => WasmValue::I32(self.next() as i32),
+            ValueType::I64 if special => {
+                WasmValue::I64(self.pick(&[0, 1, -1, 2, 63, 64, i64::MIN, i64::MAX]))
+            }
+            ValueType::I64 => WasmValue::I64(self.next() as i64),
+            ValueType::F32 if special => WasmValue::F32(self.pick(&[
+                0.0,
+                -0.0,
+                1.0,
+                -1.5,
+                f32::MIN_POSITIVE,
+                f32::MAX,
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+                f32::NAN,
+                2147483648.0,
+            ])),
+            ValueType::F32 => WasmValue::F32(f32::from_bits(self.next() as u32)),
+            ValueType::F64 if special => WasmValue::F64(self.pick(&[
+                0.0,
+                -0.0,
+                1.0,
+                -1.5,
+                f64::MIN_POSITIVE,
+                f64::MAX,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NAN,
+                9223372036854775808.0,
+            ])),
+            ValueType::F64 => WasmValue::F64(f64::from_bits(self.next())),
+            ValueType::V128 => {
+                WasmValue::V128(((self.next() as u128) << 64 | self.next() as u128) as i128)
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[test]
+fn test_differential() {
+    use super::wat;
+
+    let src = r#"(module
+      (memory 1)
+      (func (export "add") (param i32 i32) (result i32)
+        (i32.add (local.get 0) (local.get 1)))
+      (func (export "div") (param i32 i32) (result i32)
+        (i32.div_s (local.get 0) (local.get 1)))
+      (func (export "load") (param i32) (result i64)
+        (i64.load (local.get 0)))
+      (func (export "max") (param f64 f64) (result f64)
+        (f64.max (local.get 0) (local.get 1)))
+      (func (export "spin") (loop (br 0)))
+      (func (export "ref") (param funcref)))"#;
+    let options = Options {
+        iterations: 50,
+        timeout: Duration::from_millis(100),
+        fuel: 1_000_000,
+        ..Default::default()
+    };
+    let diff = Differential::new(wat::compile(src).unwrap(), options).unwrap();
+    assert_eq!(
+        diff.funcs().collect::<Vec<_>>(),
+        ["add", "div", "load", "max", "ref", "spin"]
+    );
+
+    let (oxygen, reference) = diff
+        .compare("div", &[WasmValue::I32(i32::MIN), WasmValue::I32(-1)])
+        .unwrap();
+    assert_eq!(oxygen, Outcome::Trap(TrapKind::IntegerOverflow));
+    assert_eq!(reference, oxygen);
+    let (oxygen, reference) = diff.compare("load", &[WasmValue::I32(65530)]).unwrap();
+    assert_eq!(oxygen, Outcome::Trap(TrapKind::MemoryOutOfBounds));
+    assert!(oxygen.agrees(&reference));
+
+    let report = diff.run(None).unwrap();
+    assert!(report.mismatches.is_empty(), "{report}");
+    assert_eq!(report.calls, 4 * 50 + 1);
+    assert_eq!(report.timeouts, 1);
+    assert_eq!(report.skipped, ["ref"]);
+    assert!(diff.run(Some("missing")).is_err());
+
+    // NaN 的负载不参与比较，其他浮点数按位比较
+    let nan = Outcome::Values(vec![WasmValue::F32(f32::from_bits(0x7fc0_0001))]);
+    assert!(nan.agrees(&Outcome::Values(vec![WasmValue::F32(f32::NAN)])));
+    let zero = Outcome::Values(vec![WasmValue::F64(0.0)]);
+    assert!(!zero.agrees(&Outcome::Values(vec![WasmValue::F64(-0.0)])));
+    assert!(!zero.agrees(&Outcome::Trap(TrapKind::Unreachable)));
+
+    let src = r#"(module (import "env" "f" (func)))"#;
+    assert!(Differential::new(wat::compile(src).unwrap(), Options::default()).is_err());
+}
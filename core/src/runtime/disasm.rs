@@ -0,0 +1,170 @@
+//! 单个函数的反汇编：按顺序给出函数体中的操作码和它在 `WasmModule::ops` 中的位置（pc），
+//! 以及每条指令所在的块嵌套深度，供外部工具渲染或分析
+
+use std::fmt::{self, Display};
+
+use anyhow::{bail, Context};
+
+use super::decoder::{FuncKind, WasmModule};
+use super::section::code::FuncBody;
+use super::section::opcode::Opcode;
+
+/// `WasmModule::disassemble` 返回的迭代器，产生 (pc, 操作码)
+#[derive(Debug, Clone)]
+pub struct Disassembly<'a> {
+    ops: &'a [Opcode],
+    /// 下一条指令的 pc
+    pc: usize,
+    last: usize,
+    /// 下一条指令之前打开的块的个数
+    open: usize,
+    depth: usize,
+}
+
+impl<'a> Disassembly<'a> {
+    /// 最近一次 `next` 返回的指令的嵌套深度：函数体最外层为 0，
+    /// block/loop/if 内加 1，块的 else 和 end 与块的开始相同
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// 同时给出嵌套深度的迭代器，产生 (pc, 深度, 操作码)
+    pub fn with_depth(self) -> impl Iterator<Item = (usize, usize, &'a Opcode)> {
+        let mut disasm = self;
+        std::iter::from_fn(move || {
+            let (pc, op) = disasm.next()?;
+            Some((pc, disasm.depth, op))
+        })
+    }
+}
+
+impl<'a> Iterator for Disassembly<'a> {
+    type Item = (usize, &'a Opcode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pc > self.last {
+            return None;
+        }
+        let pc = self.pc;
+        let op = self.ops.get(pc)?;
+        self.pc += 1;
+        if matches!(op, Opcode::Else(_) | Opcode::End(_)) {
+            self.open = self.open.saturating_sub(1);
+        }
+        self.depth = self.open;
+        if matches!(
+            op,
+            Opcode::Block(..) | Opcode::Loop(..) | Opcode::If(..) | Opcode::Else(_)
+        ) {
+            self.open += 1;
+        }
+        Some((pc, op))
+    }
+}
+
+/// 每条指令一行：pc 和按嵌套深度缩进的操作码
+impl Display for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (pc, depth, op) in self.clone().with_depth() {
+            writeln!(f, "{pc:>6} {}{op:?}", "    ".repeat(depth))?;
+        }
+        Ok(())
+    }
+}
+
+impl WasmModule {
+    /// 反汇编索引为 idx 的函数（包括导入函数的索引），导入函数和还没有解析的函数体出错；
+    /// 不需要实例化
+    pub fn disassemble(&self, idx: usize) -> anyhow::Result<Disassembly<'_>> {
+        let imported = self.imported_funcs();
+        if idx < imported {
+            bail!("function {idx} is imported and has no body");
+        }
+        let body: &FuncBody = match self.func.get(idx) {
+            Some(FuncKind::Local((_, body))) => body,
+            _ => self
+                .section
+                .code
+                .entries
+                .get(idx - imported)
+                .with_context(|| format!("unknown function {idx}"))?,
+        };
+        if body.pending {
+            bail!("function {idx} hasn't been decoded yet");
+        }
+        let (start, _, last) = body.code;
+        Ok(Disassembly {
+            ops: &self.ops,
+            pc: start,
+            last,
+            open: 0,
+            depth: 0,
+        })
+    }
+}
+
+#[test]
+fn test_disassemble() {
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "log" (func $log (param i32)))
+      (func (result i32) (i32.const 7))
+      (func (param i32) (result i32)
+        (block
+          (loop
+            (br_if 1 (local.get 0))))
+        (if (result i32) (local.get 0)
+          (then (i32.const 1))
+          (else (i32.const 2)))))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.decode().unwrap();
+
+    let ops = wasm.disassemble(1).unwrap().collect::<Vec<_>>();
+    assert!(matches!(
+        ops[..],
+        [(_, Opcode::I32Const(7)), (_, Opcode::End(_))]
+    ));
+
+    let lines = wasm
+        .disassemble(2)
+        .unwrap()
+        .with_depth()
+        .map(|(_, depth, op)| (depth, super::stats::op_name(op)))
+        .collect::<Vec<_>>();
+    let lines = lines
+        .iter()
+        .map(|(depth, name)| (*depth, name.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            (0, "Block"),
+            (1, "Loop"),
+            (2, "LocalGet"),
+            (2, "BrIf"),
+            (1, "End"),
+            (0, "End"),
+            (0, "LocalGet"),
+            (0, "If"),
+            (1, "I32Const"),
+            (1, "Br"),
+            (0, "Else"),
+            (1, "I32Const"),
+            (0, "End"),
+            (0, "End"),
+        ]
+    );
+    // pc 是在整个模块的操作码中的位置，与调试器和 trace 一致
+    let (pc, op) = wasm.disassemble(2).unwrap().next().unwrap();
+    assert!(std::ptr::eq(&wasm.ops[pc], op));
+    let text = wasm.disassemble(2).unwrap().to_string();
+    assert!(text
+        .lines()
+        .nth(2)
+        .unwrap()
+        .ends_with("        LocalGet(0)"));
+
+    assert!(wasm.disassemble(0).is_err());
+    assert!(wasm.disassemble(3).is_err());
+}
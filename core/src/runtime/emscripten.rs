@@ -0,0 +1,184 @@
+//! emscripten 编译的模块常用的 `env` 导入
+//!
+//! 不是完整的 emscripten 运行时：只提供 abort、断言失败、内存增长通知和大块内存复制等，
+//! 让只依赖 WASI 系统调用的简单模块可以直接运行；模块导入的 `env` 内存和表按导入声明的大小创建。
+
+use super::decoder::{WasmModule, WasmValue};
+use super::linker::{Func, Linker};
+use super::section::import;
+use super::section::typings::ValueType;
+use super::trap::Trap;
+
+const PAGE_SIZE: u32 = 0x10000;
+
+impl Linker {
+    /// 定义 wasm 需要的 `env` 宿主函数，以及它导入的 `env` 内存和表（已经定义的除外）
+    pub fn define_emscripten(&mut self, wasm: &WasmModule) -> anyhow::Result<&mut Self> {
+        use ValueType::{F64, I32};
+        let module = "env";
+        self.define(
+            module,
+            "abort",
+            Func::wrap(&[], &[], |wasm, _| {
+                wasm.raise(Trap::Host {
+                    message: "abort() called".to_string(),
+                })
+            }),
+        )?
+        .define(
+            module,
+            "__assert_fail",
+            Func::wrap(&[I32, I32, I32, I32], &[], |wasm, arg| {
+                let message =
+                    assert_message(wasm, arg).unwrap_or_else(|| "Assertion failed".into());
+                wasm.raise(Trap::Host { message })
+            }),
+        )?
+        .define(
+            module,
+            "emscripten_notify_memory_growth",
+            Func::wrap(&[I32], &[], |_, _| vec![]),
+        )?
+        .define(
+            module,
+            "emscripten_resize_heap",
+            Func::wrap(&[I32], &[I32], |wasm, arg| {
+                let WasmValue::I32(size) = arg[0] else {
+                    return vec![WasmValue::I32(0)];
+                };
+                let pages = (size as u32).div_ceil(PAGE_SIZE);
+                let grown = wasm.memory(0).and_then(|mut mem| {
+                    let delta = pages.saturating_sub(mem.size());
+                    mem.grow(delta)
+                });
+                vec![WasmValue::I32(grown.is_ok() as i32)]
+            }),
+        )?
+        .define(
+            module,
+            "emscripten_memcpy_big",
+            Func::wrap(&[I32, I32, I32], &[], |wasm, arg| memcpy(wasm, arg)),
+        )?
+        .define(
+            module,
+            "_emscripten_memcpy_js",
+            Func::wrap(&[I32, I32, I32], &[], |wasm, arg| memcpy(wasm, arg)),
+        )?
+        .define(
+            module,
+            "emscripten_date_now",
+            Func::wrap(&[], &[F64], |wasm, _| {
+                let clock = wasm.config.wasi.clock.clone();
+                let now = clock.lock().unwrap().now(super::wasi::ClockId::Realtime);
+                vec![WasmValue::F64(now.unwrap_or(0) as f64 / 1e6)]
+            }),
+        )?;
+        for ipt in &wasm.section.import.entries {
+            if ipt.mod_name != module || self.get(module, &ipt.field_name).is_some() {
+                continue;
+            }
+            match &ipt.kind {
+                import::Kind::Memory(limit) => {
+                    let maximum = (limit.flag & 0x01 == 1).then_some(limit.maximum);
+                    self.define_memory(module, &ipt.field_name, limit.minimum, maximum)?;
+                }
+                import::Kind::Table(ty, limit) => {
+                    let maximum = (limit.flag & 0x01 == 1).then_some(limit.maximum);
+                    let ty = ValueType::from_u8(*ty)?;
+                    self.define_table(module, &ipt.field_name, ty, limit.minimum, maximum)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// `Assertion failed: 条件, at: 文件,行,函数`，与 emscripten 的 JS 运行时相同
+fn assert_message(wasm: &mut WasmModule, arg: &[WasmValue]) -> Option<String> {
+    let [WasmValue::I32(cond), WasmValue::I32(file), WasmValue::I32(line), WasmValue::I32(func)] =
+        arg[..]
+    else {
+        return None;
+    };
+    let mem = wasm.memory_view(0).ok()?;
+    let text = |ptr: i32| {
+        mem.read_cstr(ptr as u32)
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "unknown".to_string())
+    };
+    Some(format!(
+        "Assertion failed: {}, at: {},{line},{}",
+        text(cond),
+        text(file),
+        text(func)
+    ))
+}
+
+/// (dest, src, num)，区间可以重叠
+fn memcpy(wasm: &mut WasmModule, arg: &[WasmValue]) -> Vec<WasmValue> {
+    let [WasmValue::I32(dest), WasmValue::I32(src), WasmValue::I32(num)] = arg[..] else {
+        return vec![];
+    };
+    let copied = wasm.memory_view(0).and_then(|mut mem| {
+        let bytes = mem.read_bytes(src as u32, num as u32)?.to_vec();
+        mem.write_bytes(dest as u32, &bytes)
+    });
+    match copied {
+        Ok(()) => vec![],
+        Err(_) => wasm.raise(Trap::MemoryOutOfBounds {
+            addr: (src as u32).max(dest as u32) as u64,
+            size: num as u32 as usize,
+        }),
+    }
+}
+
+#[test]
+fn test_emscripten() {
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "memory" (memory 1 4))
+      (import "env" "__indirect_function_table" (table 2 funcref))
+      (import "env" "abort" (func $abort))
+      (import "env" "__assert_fail" (func $assert_fail (param i32 i32 i32 i32)))
+      (import "env" "emscripten_resize_heap" (func $resize (param i32) (result i32)))
+      (import "env" "emscripten_memcpy_big" (func $memcpy (param i32 i32 i32)))
+      (data (i32.const 16) "x == 1\00main.c\00main\00")
+      (func (export "abort") (call $abort))
+      (func (export "assert") (call $assert_fail (i32.const 16) (i32.const 23) (i32.const 7) (i32.const 30)))
+      (func (export "resize") (param i32) (result i32 i32)
+        (call $resize (local.get 0))
+        (memory.size))
+      (func (export "copy") (result i32)
+        (call $memcpy (i32.const 100) (i32.const 16) (i32.const 6))
+        (i32.load8_u (i32.const 105))))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.decode().unwrap();
+    let mut linker = Linker::new();
+    linker.define_emscripten(&wasm).unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+
+    let err = wasm.invoke("abort", &[]).unwrap_err();
+    assert!(err.to_string().contains("abort() called"), "{err}");
+    let err = wasm.invoke("assert", &[]).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Assertion failed: x == 1, at: main.c,7,main"),
+        "{err}"
+    );
+    // trap 之后实例仍然可以调用
+    assert_eq!(
+        wasm.invoke("resize", &[WasmValue::I32(0x20001)]).unwrap(),
+        [WasmValue::I32(1), WasmValue::I32(3)]
+    );
+    assert_eq!(
+        wasm.invoke("resize", &[WasmValue::I32(0x50000)]).unwrap(),
+        [WasmValue::I32(0), WasmValue::I32(3)]
+    );
+    assert_eq!(
+        wasm.invoke("copy", &[]).unwrap(),
+        [WasmValue::I32(b'1' as i32)]
+    );
+    assert_eq!(wasm.table_limit(0).map(|limit| limit.minimum), Some(2));
+}
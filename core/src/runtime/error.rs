@@ -0,0 +1,377 @@
+use std::fmt::Display;
+
+use crate::leb::LebError;
+
+use super::section::import;
+use super::section::types::FunctionType;
+use super::section::typings::ValueType;
+
+/// 解码失败的具体原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeErrorKind {
+    /// 模块（或 section）的字节提前结束
+    UnexpectedEof,
+    /// LEB128 整数过长、超出位宽或没有结束
+    Leb(LebError),
+    /// 开头不是 `\0asm`
+    MagicMismatch {
+        found: Vec<u8>,
+    },
+    UnknownVersion {
+        found: u32,
+    },
+    /// 版本字段的 layer 为 1：这是组件而不是 core module，见 `component`
+    Component {
+        version: u16,
+    },
+    UnknownSection {
+        id: u32,
+    },
+    /// 标记字节（值类型、段的 flag、导入类型等）不是允许的取值
+    UnknownTag {
+        what: &'static str,
+        found: u32,
+    },
+    /// 字段必须是固定的值
+    Unexpected {
+        what: &'static str,
+        expected: u32,
+        found: u32,
+    },
+    UnknownOpcode {
+        prefix: Option<u8>,
+        op: u32,
+    },
+    UnknownLabel {
+        label: u32,
+    },
+    /// 结构化指令嵌套超过 `MAX_BLOCK_DEPTH`
+    TooDeep {
+        limit: usize,
+    },
+    /// memory.init / data.drop 之前没有 data count 段
+    DataCountRequired,
+    UnknownDataSegment {
+        index: u32,
+    },
+    /// data count 段与 data 段的数量不一致
+    DataCountMismatch {
+        expected: u32,
+        found: u32,
+    },
+    /// 超出 `Limits` 中的限制
+    LimitExceeded {
+        what: &'static str,
+        found: usize,
+        limit: usize,
+    },
+    Other(String),
+}
+
+impl Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "unexpected end"),
+            Self::Leb(err) => write!(f, "{err}"),
+            Self::MagicMismatch { found } => {
+                write!(f, "magic header not detected, found {found:02x?}")
+            }
+            Self::UnknownVersion { found } => write!(f, "unknown binary version {found:#x}"),
+            Self::Component { version } => write!(
+                f,
+                "binary is a WebAssembly component (version {version:#x}), not a core module"
+            ),
+            Self::UnknownSection { id } => write!(f, "unknown section id {id}"),
+            Self::UnknownTag { what, found } => write!(f, "unknown {what} {found:#x}"),
+            Self::Unexpected {
+                what,
+                expected,
+                found,
+            } => write!(f, "{what} must be {expected:#x}, found {found:#x}"),
+            Self::UnknownOpcode {
+                prefix: Some(prefix),
+                op,
+            } => write!(f, "unknown opcode {prefix:#x} {op}"),
+            Self::UnknownOpcode { prefix: None, op } => write!(f, "unknown opcode {op:#x}"),
+            Self::UnknownLabel { label } => write!(f, "unknown label {label}"),
+            Self::TooDeep { limit } => write!(f, "blocks nested deeper than {limit}"),
+            Self::DataCountRequired => write!(f, "data count section required"),
+            Self::UnknownDataSegment { index } => write!(f, "unknown data segment {index}"),
+            Self::DataCountMismatch { expected, found } => write!(
+                f,
+                "data count and data section have inconsistent lengths, expected {expected}, found {found}"
+            ),
+            Self::LimitExceeded { what, found, limit } => {
+                write!(f, "{what} {found} exceeds limit {limit}")
+            }
+            Self::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeErrorKind {}
+
+/// section 解析内部仍然使用 anyhow，这里还原出其中携带的错误类型
+impl From<anyhow::Error> for DecodeErrorKind {
+    fn from(err: anyhow::Error) -> Self {
+        if let Some(kind) = err.downcast_ref::<DecodeErrorKind>() {
+            return kind.clone();
+        }
+        if let Some(err) = err.downcast_ref::<LebError>() {
+            return Self::Leb(*err);
+        }
+        // 跳过 InstrAt / BodyAt 这些位置标注
+        Self::Other(err.root_cause().to_string())
+    }
+}
+
+impl From<LebError> for DecodeErrorKind {
+    fn from(err: LebError) -> Self {
+        Self::Leb(err)
+    }
+}
+
+/// 出错的指令开始的位置，由 parse_code 附加在最内层的错误上
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InstrAt(pub usize);
+
+impl Display for InstrAt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "instruction at offset {:#x}", self.0)
+    }
+}
+
+/// 出错的函数体：code 段中的序号和函数体开始的位置
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BodyAt {
+    pub index: usize,
+    pub start: usize,
+}
+
+impl Display for BodyAt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "function body {} at offset {:#x}",
+            self.index, self.start
+        )
+    }
+}
+
+/// code 段中出错的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuncLocation {
+    /// 函数索引，包含导入的函数
+    pub index: usize,
+    /// 出错的指令相对函数体开头的偏移
+    pub instr_offset: usize,
+}
+
+/// 解码错误：出错的 section、字节偏移和原因
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    /// 出错时所在的 section id，模块头或 section 之外为 None
+    pub section: Option<u8>,
+    /// 相对模块开头的字节偏移，函数体中的错误为出错指令的开始
+    pub offset: usize,
+    pub kind: DecodeErrorKind,
+    /// 错误发生在函数体中时的函数和指令位置
+    pub func: Option<FuncLocation>,
+}
+
+impl DecodeError {
+    pub fn new(section: Option<u8>, offset: usize, kind: impl Into<DecodeErrorKind>) -> Self {
+        Self {
+            section,
+            offset,
+            kind: kind.into(),
+            func: None,
+        }
+    }
+
+    /// 附带位置和十六进制上下文的多行诊断，bytes 是解码的整个模块
+    pub fn diagnostic(&self, bytes: &[u8]) -> String {
+        let mut out = format!("error: {}\n  --> offset {:#x}", self.kind, self.offset);
+        if let Some(id) = self.section {
+            out += &format!(", {} section", section_name(id));
+        }
+        if let Some(func) = self.func {
+            out += &format!(
+                ", function {}, instruction +{:#x}",
+                func.index, func.instr_offset
+            );
+        }
+        out += "\n";
+        out += &hex_window(bytes, self.offset);
+        out
+    }
+}
+
+/// offset 附近 16 字节的十六进制，下一行用 ^ 指向 offset 处的字节；
+/// offset 在末尾时指向最后一个字节之后
+pub fn hex_window(bytes: &[u8], offset: usize) -> String {
+    let offset = offset.min(bytes.len());
+    let start = offset.saturating_sub(8).min(bytes.len().saturating_sub(16));
+    let end = (start + 16).min(bytes.len());
+    let hex = bytes[start..end]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let prefix = format!("{start:08x}: ");
+    let caret = " ".repeat(prefix.len() + 3 * (offset - start));
+    format!("{prefix}{hex}\n{caret}^^")
+}
+
+pub fn section_name(id: u8) -> &'static str {
+    match id {
+        0 => "custom",
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        _ => "unknown",
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(id) = self.section {
+            write!(f, " in {} section", section_name(id))?;
+        }
+        if let Some(func) = self.func {
+            write!(f, " (function {})", func.index)?;
+        }
+        write!(f, " at offset {:#x}", self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// 导入项的类型，用于实例化错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportType {
+    Func(Vec<ValueType>, Vec<ValueType>),
+    Memory(u32, u32),
+    Global(ValueType, bool),
+    Table(Option<ValueType>, u32, u32),
+}
+
+impl ImportType {
+    /// 模块声明的导入类型，types 为模块的类型段
+    pub fn new(types: &[FunctionType], kind: &import::Kind) -> Self {
+        match kind {
+            import::Kind::Func(ty) => match types.get(*ty) {
+                Some(ty) => ImportType::Func(ty.params.clone(), ty.results.clone()),
+                None => ImportType::Func(vec![], vec![]),
+            },
+            import::Kind::Table(ty, limit) => {
+                ImportType::Table(ValueType::from_u8(*ty).ok(), limit.minimum, limit.maximum)
+            }
+            import::Kind::Memory(limit) => ImportType::Memory(limit.minimum, limit.maximum),
+            import::Kind::Global(g) => ImportType::Global(g.val_ty, g.mutability),
+        }
+    }
+}
+
+impl Display for ImportType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |types: &Vec<ValueType>| {
+            types
+                .iter()
+                .map(|item| format!("{}", item))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            ImportType::Func(params, results) => {
+                write!(f, "func ({}) -> ({})", join(params), join(results))
+            }
+            ImportType::Memory(min, max) => write!(f, "memory [{min} ~ {max}] pages"),
+            ImportType::Global(ty, mutability) => {
+                write!(
+                    f,
+                    "{} global {ty}",
+                    if *mutability { "var" } else { "const" }
+                )
+            }
+            ImportType::Table(Some(ty), min, max) => write!(f, "table {ty} [{min} ~ {max}]"),
+            ImportType::Table(None, min, max) => write!(f, "table [{min} ~ {max}]"),
+        }
+    }
+}
+
+/// 实例化失败：导入项缺失或与声明的类型不匹配
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstantiationError {
+    MissingImport {
+        module: String,
+        field: String,
+        expected: ImportType,
+    },
+    /// found 为宿主提供的项的描述
+    IncompatibleImport {
+        module: String,
+        field: String,
+        expected: ImportType,
+        found: String,
+    },
+    /// 模块用到了只能解码、还不能执行的提案
+    Unsupported { feature: &'static str },
+}
+
+impl Display for InstantiationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingImport {
+                module,
+                field,
+                expected,
+            } => write!(
+                f,
+                "unknown import: `{module}::{field}` has not been defined (expected {expected})"
+            ),
+            Self::IncompatibleImport {
+                module,
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "incompatible import type for `{module}::{field}`: expected {expected}, found {found}"
+            ),
+            Self::Unsupported { feature } => write!(
+                f,
+                "module uses the {feature} proposal, which can't be executed yet"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InstantiationError {}
+
+#[test]
+fn test_hex_window() {
+    let bytes = (0..40).collect::<Vec<u8>>();
+    assert_eq!(
+        hex_window(&bytes, 20),
+        "0000000c: 0c 0d 0e 0f 10 11 12 13 14 15 16 17 18 19 1a 1b\n\
+         \x20                                 ^^"
+    );
+    // 开头和末尾的窗口不超出字节范围
+    assert!(hex_window(&bytes, 2).starts_with("00000000: 00 01"));
+    assert_eq!(
+        hex_window(&bytes, 40).lines().last().unwrap().len(),
+        "00000018: ".len() + 3 * 16 + 2
+    );
+    assert_eq!(hex_window(&[], 0), "00000000: \n          ^^");
+}
@@ -0,0 +1,286 @@
+//! 导出的内存、全局变量和表的访问接口，供宿主与 wasm 交换数据
+
+use anyhow::{anyhow, ensure, Context};
+
+use super::decoder::{Global, WasmModule, WasmValue};
+use super::linear::LinearMemory;
+use super::section::export::ExportKind;
+use super::section::import;
+use super::section::typings::{Limit, ValueType};
+use super::table::{Elem, Table};
+
+pub struct MemoryRef<'a> {
+    data: &'a mut LinearMemory,
+}
+
+impl MemoryRef<'_> {
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+    /// 当前页数
+    pub fn size(&self) -> u32 {
+        self.data.pages()
+    }
+    /// 最大页数，已经算上宿主内存和 `Limits` 的限制
+    pub fn maximum(&self) -> u32 {
+        self.data.maximum()
+    }
+    pub fn read(&self, offset: usize, buf: &mut [u8]) -> anyhow::Result<()> {
+        let bytes = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.data.get(offset..end))
+            .with_context(|| {
+                format!(
+                    "memory read out of bounds: offset = {offset}, len = {}, size = {}",
+                    buf.len(),
+                    self.data.len()
+                )
+            })?;
+        buf.copy_from_slice(bytes);
+        Ok(())
+    }
+    pub fn write(&mut self, offset: usize, buf: &[u8]) -> anyhow::Result<()> {
+        let len = self.data.len();
+        let bytes = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.data.get_mut(offset..end))
+            .with_context(|| {
+                format!(
+                    "memory write out of bounds: offset = {offset}, len = {}, size = {len}",
+                    buf.len(),
+                )
+            })?;
+        bytes.copy_from_slice(buf);
+        Ok(())
+    }
+    /// 增加 delta 页，返回增长前的页数
+    pub fn grow(&mut self, delta: u32) -> anyhow::Result<u32> {
+        let old = self.size();
+        self.data.grow(delta).with_context(|| {
+            format!(
+                "memory grow failed: {old} + {delta} pages exceeds maximum {}",
+                self.maximum()
+            )
+        })
+    }
+}
+
+pub struct GlobalRef<'a> {
+    global: &'a mut Global,
+    ty: ValueType,
+}
+
+impl GlobalRef<'_> {
+    pub fn ty(&self) -> ValueType {
+        self.ty
+    }
+    pub fn mutability(&self) -> bool {
+        self.global.is_mutable()
+    }
+    pub fn get(&self) -> WasmValue {
+        self.global.get()
+    }
+    pub fn set(&mut self, value: WasmValue) -> anyhow::Result<()> {
+        ensure!(self.mutability(), "can't set an immutable global");
+        ensure!(
+            value.value_type() == Some(self.ty),
+            "global type mismatch: expected {}, found {value:?}",
+            self.ty
+        );
+        self.global.set(value);
+        Ok(())
+    }
+}
+
+pub struct TableRef<'a> {
+    table: &'a Table,
+    maximum: u32,
+}
+
+impl TableRef<'_> {
+    pub fn size(&self) -> u32 {
+        self.table.size()
+    }
+    /// 读取表项，返回函数在写入它的实例中的索引
+    pub fn get(&self, index: u32) -> Option<usize> {
+        self.table.get(index)
+    }
+    pub fn set(&mut self, index: u32, func: usize) -> anyhow::Result<()> {
+        ensure!(
+            self.table.set(index, func),
+            "table index {index} out of bounds, size = {}",
+            self.size()
+        );
+        Ok(())
+    }
+    /// 增加 delta 个表项并用 init 填充，返回增长前的大小
+    pub fn grow(&mut self, delta: u32, init: usize) -> anyhow::Result<u32> {
+        let old = self.size();
+        let init = Elem::host(init);
+        self.table.grow(delta, init, self.maximum).with_context(|| {
+            format!(
+                "table grow failed: {old} + {delta} exceeds maximum {}",
+                self.maximum.min(self.table.maximum())
+            )
+        })
+    }
+}
+
+impl WasmModule {
+    fn export(&self, name: &str) -> anyhow::Result<&ExportKind> {
+        self.exports
+            .get(name)
+            .with_context(|| format!("unknown export `{name}`"))
+    }
+
+    /// 第 idx 个内存（导入的内存在前）的声明 limits
+    pub fn memory_limit(&self, idx: usize) -> Option<&Limit> {
+        let imports = self
+            .section
+            .import
+            .entries
+            .iter()
+            .filter_map(|ipt| match &ipt.kind {
+                import::Kind::Memory(limit) => Some(limit),
+                _ => None,
+            });
+        let locals = self.section.memory.entries.iter().map(|mem| &mem.limits);
+        imports.chain(locals).nth(idx)
+    }
+
+    /// 第 idx 个表（导入的表在前）的声明 limits
+    pub fn table_limit(&self, idx: usize) -> Option<&Limit> {
+        let imports = self
+            .section
+            .import
+            .entries
+            .iter()
+            .filter_map(|ipt| match &ipt.kind {
+                import::Kind::Table(_, limit) => Some(limit),
+                _ => None,
+            });
+        let locals = self.section.table.entries.iter().map(|table| &table.limits);
+        imports.chain(locals).nth(idx)
+    }
+
+    /// 第 idx 个全局变量的类型
+    pub fn global_type(&self, idx: usize) -> Option<ValueType> {
+        let imports = self
+            .section
+            .import
+            .entries
+            .iter()
+            .filter_map(|ipt| match &ipt.kind {
+                import::Kind::Global(g) => Some(g.val_ty),
+                _ => None,
+            });
+        let locals = self.section.global.entries.iter().map(|g| g.val_ty);
+        imports.chain(locals).nth(idx)
+    }
+
+    pub fn memory(&mut self, idx: usize) -> anyhow::Result<MemoryRef<'_>> {
+        let data = self
+            .mem
+            .get_mut(idx)
+            .with_context(|| format!("memory {idx} not found"))?;
+        Ok(MemoryRef { data })
+    }
+
+    pub fn get_memory(&mut self, name: &str) -> anyhow::Result<MemoryRef<'_>> {
+        match self.export(name)? {
+            ExportKind::Memory(idx) => self.memory(*idx),
+            kind => Err(anyhow!("export `{name}` is not a memory: {kind}")),
+        }
+    }
+
+    pub fn get_global(&mut self, name: &str) -> anyhow::Result<GlobalRef<'_>> {
+        let idx = match self.export(name)? {
+            ExportKind::GLobal(idx) => *idx,
+            kind => return Err(anyhow!("export `{name}` is not a global: {kind}")),
+        };
+        let ty = self
+            .global_type(idx)
+            .with_context(|| format!("global {idx} not found"))?;
+        let global = self
+            .global
+            .get_mut(idx)
+            .with_context(|| format!("global {idx} not found"))?;
+        Ok(GlobalRef { global, ty })
+    }
+
+    pub fn get_table(&mut self, name: &str) -> anyhow::Result<TableRef<'_>> {
+        let idx = match self.export(name)? {
+            ExportKind::Table(idx) => *idx,
+            kind => return Err(anyhow!("export `{name}` is not a table: {kind}")),
+        };
+        let maximum = self.table_limit(idx).map(|l| l.maximum).unwrap_or(u32::MAX);
+        let maximum = self.config.limits.table_size(maximum);
+        let table = self
+            .table
+            .get(idx)
+            .with_context(|| format!("table {idx} not found"))?;
+        Ok(TableRef { table, maximum })
+    }
+}
+
+#[test]
+fn test_exports_access() {
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, // magic = \0asm
+        0x01, 0x00, 0x00, 0x00, // version  = 1 (little endian)
+        //
+        0x04, 0x05, 0x01, 0x70, 0x01, 0x02, 0x04, // table funcref [2 ~ 4]
+        //
+        0x05, 0x04, 0x01, 0x01, 0x01, 0x02, // memory [1 ~ 2]
+        //
+        0x06, 0x0b, 0x02, // global section
+        0x7f, 0x01, 0x41, 0x2a, 0x0b, // (mut i32) i32.const 42
+        0x7e, 0x00, 0x42, 0x07, 0x0b, // i64 i64.const 7
+        //
+        0x07, 0x15, 0x04, // export section
+        0x03, 0x6d, 0x65, 0x6d, 0x02, 0x00, // "mem" memory 0
+        0x01, 0x67, 0x03, 0x00, // "g" global 0
+        0x01, 0x63, 0x03, 0x01, // "c" global 1
+        0x03, 0x74, 0x62, 0x6c, 0x01, 0x00, // "tbl" table 0
+        //
+        0x0b, 0x08, 0x01, 0x00, 0x41, 0x02, 0x0b, 0x02, 0x68, 0x69, // data "hi" at 2
+    ];
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+
+    let mut mem = wasm.get_memory("mem").unwrap();
+    assert_eq!(mem.size(), 1);
+    let mut hi = [0u8; 2];
+    mem.read(2, &mut hi).unwrap();
+    assert_eq!(&hi, b"hi");
+    mem.write(8, &[1, 2, 3]).unwrap();
+    assert_eq!(&mem.data()[8..11], &[1, 2, 3]);
+    assert!(mem.read(super::constants::PAGE_SIZE - 1, &mut hi).is_err());
+    assert_eq!(mem.grow(1).unwrap(), 1);
+    assert_eq!(mem.size(), 2);
+    assert!(mem.grow(1).is_err());
+
+    let mut g = wasm.get_global("g").unwrap();
+    assert_eq!(g.get(), WasmValue::I32(42));
+    g.set(WasmValue::I32(-1)).unwrap();
+    assert!(g.set(WasmValue::I64(1)).is_err());
+    assert_eq!(wasm.get_global("g").unwrap().get(), WasmValue::I32(-1));
+    let mut c = wasm.get_global("c").unwrap();
+    assert_eq!(c.ty(), ValueType::I64);
+    assert!(c.set(WasmValue::I64(1)).is_err());
+
+    let mut tbl = wasm.get_table("tbl").unwrap();
+    assert_eq!(tbl.size(), 2);
+    tbl.set(1, 9).unwrap();
+    assert_eq!(tbl.get(1), Some(9));
+    assert!(tbl.set(2, 0).is_err());
+    assert_eq!(tbl.grow(2, 0).unwrap(), 2);
+    assert!(tbl.grow(1, 0).is_err());
+
+    assert!(wasm.get_memory("g").is_err());
+    assert!(wasm.get_global("missing").is_err());
+}
@@ -0,0 +1,707 @@
+//! 从模块中抽取指定函数及其依赖，生成一个更小的模块（用于缩小复现用例）
+//!
+//! 保留的内容：
+//! - 选中的函数及其通过 call / ref.func 可达的函数（包括导入函数）
+//! - 上述函数用到的类型，函数和类型会重新编号
+//! - 函数体用到表时保留元素段，元素段引用的函数也一并保留
+//! - table、memory、global、data 段原样保留（索引不变）
+//!
+//! custom 段会被丢弃；start 函数不在保留范围内时去掉 start 段。
+
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, bail, ensure, Context};
+
+use super::decoder::WasmModule;
+use crate::leb::encode_leb_u32 as leb_u32;
+
+/// 指令中需要重新编号（或用于依赖分析）的索引
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Index {
+    Func(u32),
+    Type(u32),
+    Table(u32),
+}
+
+pub(super) struct Reader<'a> {
+    pub(super) buf: &'a [u8],
+    pub(super) pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    pub(super) fn byte(&mut self) -> anyhow::Result<u8> {
+        let byte = *self
+            .buf
+            .get(self.pos)
+            .with_context(|| format!("unexpected end at offset {}", self.pos))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    pub(super) fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos + len;
+        ensure!(
+            end <= self.buf.len(),
+            "unexpected end at offset {}",
+            self.pos
+        );
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// 读取一个 LEB128 编码（有符号无符号结构相同），返回原始字节
+    pub(super) fn leb(&mut self) -> anyhow::Result<&'a [u8]> {
+        let start = self.pos;
+        while self.byte()? & 0x80 != 0 {}
+        Ok(&self.buf[start..self.pos])
+    }
+
+    pub(super) fn u32(&mut self) -> anyhow::Result<u32> {
+        let mut value = 0u64;
+        for (i, byte) in self.leb()?.iter().enumerate() {
+            value |= ((byte & 0x7f) as u64) << (i * 7);
+        }
+        u32::try_from(value).map_err(|_| anyhow!("integer too large"))
+    }
+
+    fn s64(&mut self) -> anyhow::Result<i64> {
+        let bytes = self.leb()?;
+        let mut value = 0i64;
+        let mut shift = 0;
+        for byte in bytes {
+            value |= ((byte & 0x7f) as i64) << shift.min(63);
+            shift += 7;
+        }
+        if shift < 64 && bytes.last().is_some_and(|b| b & 0x40 != 0) {
+            value |= -1 << shift;
+        }
+        Ok(value)
+    }
+
+    /// 带长度前缀的名字，返回包括前缀在内的原始字节
+    pub(super) fn name(&mut self) -> anyhow::Result<&'a [u8]> {
+        let start = self.pos;
+        let len = self.u32()? as usize;
+        self.bytes(len)?;
+        Ok(&self.buf[start..self.pos])
+    }
+}
+
+/// 复制一条以 end 结尾的指令序列，遇到函数、类型索引时通过 map 替换
+pub(super) fn copy_expr(
+    r: &mut Reader,
+    out: &mut Vec<u8>,
+    map: &mut impl FnMut(Index) -> u32,
+) -> anyhow::Result<()> {
+    let mut depth = 0;
+    loop {
+        match copy_instr(r, out, map)? {
+            0x02..=0x04 => depth += 1,
+            0x0b if depth == 0 => return Ok(()),
+            0x0b => depth -= 1,
+            _ => {}
+        }
+    }
+}
+
+/// 复制一条指令，返回它的操作码
+pub(super) fn copy_instr(
+    r: &mut Reader,
+    out: &mut Vec<u8>,
+    map: &mut impl FnMut(Index) -> u32,
+) -> anyhow::Result<u8> {
+    let op = r.byte()?;
+    out.push(op);
+    match op {
+        0x02..=0x04 => {
+            // blocktype 为 s33，非负数表示类型索引
+            let start = r.pos;
+            let bt = r.s64()?;
+            if bt >= 0 {
+                leb_u32(map(Index::Type(bt as u32)), out);
+            } else {
+                out.extend(&r.buf[start..r.pos]);
+            }
+        }
+        0x0c | 0x0d | 0x20..=0x24 | 0x3f | 0x40 | 0x41 | 0x42 => out.extend(r.leb()?),
+        0x0e => {
+            let count = r.u32()?;
+            leb_u32(count, out);
+            for _ in 0..=count {
+                out.extend(r.leb()?);
+            }
+        }
+        0x10 | 0xd2 => leb_u32(map(Index::Func(r.u32()?)), out),
+        0x11 => {
+            leb_u32(map(Index::Type(r.u32()?)), out);
+            leb_u32(map(Index::Table(r.u32()?)), out);
+        }
+        0x25 | 0x26 => leb_u32(map(Index::Table(r.u32()?)), out),
+        0xd0 => out.push(r.byte()?),
+        0x1c => {
+            let count = r.u32()?;
+            leb_u32(count, out);
+            out.extend(r.bytes(count as usize)?);
+        }
+        0x28..=0x3e => copy_memarg(r, out)?,
+        0x43 => out.extend(r.bytes(4)?),
+        0x44 => out.extend(r.bytes(8)?),
+        0xfc => {
+            let sub = r.u32()?;
+            leb_u32(sub, out);
+            match sub {
+                0..=7 => {}
+                8 | 10 | 14 => {
+                    out.extend(r.leb()?);
+                    out.extend(r.leb()?);
+                }
+                9 | 11 => out.extend(r.leb()?),
+                12 => {
+                    out.extend(r.leb()?);
+                    leb_u32(map(Index::Table(r.u32()?)), out);
+                }
+                13 => {
+                    // elem.drop 没有表索引，只用于标记函数体用到了元素段
+                    map(Index::Table(0));
+                    out.extend(r.leb()?);
+                }
+                15..=17 => leb_u32(map(Index::Table(r.u32()?)), out),
+                _ => bail!("unsupported opcode 0xfc {sub}"),
+            }
+        }
+        0xfd => {
+            let sub = r.u32()?;
+            leb_u32(sub, out);
+            match sub {
+                0..=11 | 92 | 93 => copy_memarg(r, out)?,
+                12 | 13 => out.extend(r.bytes(16)?),
+                21..=34 => out.push(r.byte()?),
+                84..=91 => {
+                    copy_memarg(r, out)?;
+                    out.push(r.byte()?);
+                }
+                _ => {}
+            }
+        }
+        0x00 | 0x01 | 0x05 | 0x0b | 0x0f | 0x1a | 0x1b | 0xd1 | 0x45..=0xc4 => {}
+        _ => bail!("unsupported opcode {op:#04x} at offset {}", r.pos - 1),
+    }
+    Ok(op)
+}
+
+fn copy_memarg(r: &mut Reader, out: &mut Vec<u8>) -> anyhow::Result<()> {
+    let start = r.pos;
+    let flags = r.u32()?;
+    if flags & 0x40 != 0 {
+        r.leb()?;
+    }
+    r.leb()?;
+    out.extend(&r.buf[start..r.pos]);
+    Ok(())
+}
+
+/// 复制函数体（局部变量声明 + 指令）
+fn copy_body(
+    body: &[u8],
+    out: &mut Vec<u8>,
+    map: &mut impl FnMut(Index) -> u32,
+) -> anyhow::Result<()> {
+    let mut r = Reader::new(body);
+    let count = r.u32()?;
+    leb_u32(count, out);
+    for _ in 0..count {
+        out.extend(r.leb()?);
+        out.push(r.byte()?);
+    }
+    copy_expr(&mut r, out, map)
+}
+
+/// 复制一个元素段，函数索引（或表达式中的 ref.func）通过 map 替换
+pub(super) fn copy_element(
+    r: &mut Reader,
+    out: &mut Vec<u8>,
+    map: &mut impl FnMut(Index) -> u32,
+) -> anyhow::Result<()> {
+    let flags = r.u32()?;
+    ensure!(flags <= 7, "unknown element flag {flags}");
+    leb_u32(flags, out);
+    // bit 0: passive/declarative，bit 1: 显式表索引（或 declarative），bit 2: 表达式形式
+    if flags & 1 == 0 {
+        if flags & 2 != 0 {
+            leb_u32(map(Index::Table(r.u32()?)), out);
+        }
+        copy_expr(r, out, map)?;
+    }
+    if flags & 3 != 0 {
+        out.push(r.byte()?);
+    }
+    let count = r.u32()?;
+    leb_u32(count, out);
+    for _ in 0..count {
+        if flags & 4 != 0 {
+            copy_expr(r, out, map)?;
+        } else {
+            leb_u32(map(Index::Func(r.u32()?)), out);
+        }
+    }
+    Ok(())
+}
+
+fn skip_limits(r: &mut Reader) -> anyhow::Result<()> {
+    let flags = r.byte()?;
+    r.leb()?;
+    if flags & 1 != 0 {
+        r.leb()?;
+    }
+    Ok(())
+}
+
+struct Import<'a> {
+    /// module 和 field 名字
+    names: &'a [u8],
+    kind: u8,
+    /// 函数导入为类型索引，其他导入为原始描述
+    ty: u32,
+    desc: &'a [u8],
+}
+
+/// 按 section 拆开的模块
+#[derive(Default)]
+struct Module<'a> {
+    types: Vec<&'a [u8]>,
+    imports: Vec<Import<'a>>,
+    funcs: Vec<u32>,
+    tables: Option<&'a [u8]>,
+    memories: Option<&'a [u8]>,
+    globals: Vec<(&'a [u8], &'a [u8])>,
+    exports: Vec<(&'a [u8], u8, u32)>,
+    start: Option<u32>,
+    elements: Vec<&'a [u8]>,
+    data_count: Option<&'a [u8]>,
+    codes: Vec<&'a [u8]>,
+    data: Option<&'a [u8]>,
+}
+
+impl<'a> Module<'a> {
+    fn parse(buf: &'a [u8]) -> anyhow::Result<Self> {
+        let mut module = Module::default();
+        let mut r = Reader::new(buf);
+        r.bytes(8)?;
+        while !r.is_empty() {
+            let id = r.byte()?;
+            let len = r.u32()? as usize;
+            let content = r.bytes(len)?;
+            let mut s = Reader::new(content);
+            let vec_len = |s: &mut Reader| s.u32().map(|n| 0..n);
+            match id {
+                0 => {}
+                1 => {
+                    for _ in vec_len(&mut s)? {
+                        let start = s.pos;
+                        ensure!(s.byte()? == 0x60, "unsupported type form");
+                        for _ in 0..2 {
+                            let count = s.u32()?;
+                            s.bytes(count as usize)?;
+                        }
+                        module.types.push(&content[start..s.pos]);
+                    }
+                }
+                2 => {
+                    for _ in vec_len(&mut s)? {
+                        let start = s.pos;
+                        s.name()?;
+                        s.name()?;
+                        let names = &content[start..s.pos];
+                        let kind = s.byte()?;
+                        let desc_start = s.pos;
+                        let mut ty = 0;
+                        match kind {
+                            0 => ty = s.u32()?,
+                            1 => {
+                                s.byte()?;
+                                skip_limits(&mut s)?;
+                            }
+                            2 => skip_limits(&mut s)?,
+                            3 => {
+                                s.bytes(2)?;
+                            }
+                            _ => bail!("unknown import kind {kind}"),
+                        }
+                        let desc = &content[desc_start..s.pos];
+                        module.imports.push(Import {
+                            names,
+                            kind,
+                            ty,
+                            desc,
+                        });
+                    }
+                }
+                3 => {
+                    for _ in vec_len(&mut s)? {
+                        module.funcs.push(s.u32()?);
+                    }
+                }
+                4 => module.tables = Some(content),
+                5 => module.memories = Some(content),
+                6 => {
+                    for _ in vec_len(&mut s)? {
+                        let head = s.bytes(2)?;
+                        let start = s.pos;
+                        copy_expr(&mut s, &mut vec![], &mut |idx| match idx {
+                            Index::Func(v) | Index::Type(v) | Index::Table(v) => v,
+                        })?;
+                        module.globals.push((head, &content[start..s.pos]));
+                    }
+                }
+                7 => {
+                    for _ in vec_len(&mut s)? {
+                        let name = s.name()?;
+                        let kind = s.byte()?;
+                        module.exports.push((name, kind, s.u32()?));
+                    }
+                }
+                8 => module.start = Some(s.u32()?),
+                9 => {
+                    for _ in vec_len(&mut s)? {
+                        let start = s.pos;
+                        copy_element(&mut s, &mut vec![], &mut |idx| match idx {
+                            Index::Func(v) | Index::Type(v) | Index::Table(v) => v,
+                        })?;
+                        module.elements.push(&content[start..s.pos]);
+                    }
+                }
+                10 => {
+                    for _ in vec_len(&mut s)? {
+                        let size = s.u32()? as usize;
+                        module.codes.push(s.bytes(size)?);
+                    }
+                }
+                11 => module.data = Some(content),
+                12 => module.data_count = Some(content),
+                _ => bail!("unknown section id {id}"),
+            }
+        }
+        ensure!(
+            module.funcs.len() == module.codes.len(),
+            "function and code section have inconsistent lengths"
+        );
+        Ok(module)
+    }
+
+    fn func_imports(&self) -> impl Iterator<Item = &Import<'a>> {
+        self.imports.iter().filter(|ipt| ipt.kind == 0)
+    }
+
+    /// 函数索引空间中每个函数的类型
+    fn func_types(&self) -> Vec<u32> {
+        self.func_imports()
+            .map(|ipt| ipt.ty)
+            .chain(self.funcs.iter().copied())
+            .collect()
+    }
+}
+
+/// 带元素个数前缀的 section，没有元素时省略
+fn vec_section(id: u8, count: usize, content: Vec<u8>) -> Option<(u8, Vec<u8>)> {
+    (count > 0).then(|| {
+        let mut buf = vec![];
+        leb_u32(count as u32, &mut buf);
+        buf.extend(content);
+        (id, buf)
+    })
+}
+
+/// 按导出名（或函数索引）选出函数，返回只包含这些函数及其依赖的新模块
+pub fn extract(buf: &[u8], funcs: &[String]) -> anyhow::Result<Vec<u8>> {
+    // 先完整解码一遍，保证输入是合法的模块
+    WasmModule::default(buf.to_vec()).decode()?;
+
+    let module = Module::parse(buf)?;
+    let func_types = module.func_types();
+    let import_count = func_types.len() - module.funcs.len();
+
+    let mut queue = vec![];
+    for name in funcs {
+        let mut raw_name = vec![];
+        leb_u32(name.len() as u32, &mut raw_name);
+        raw_name.extend(name.as_bytes());
+        let export = module
+            .exports
+            .iter()
+            .find(|(export, kind, _)| *kind == 0 && *export == raw_name);
+        let idx = match (export, name.parse::<u32>()) {
+            (Some((_, _, idx)), _) => *idx,
+            (None, Ok(idx)) if (idx as usize) < func_types.len() => idx,
+            _ => bail!("unknown function `{name}`"),
+        };
+        queue.push(idx);
+    }
+
+    // 依赖分析
+    let mut kept = BTreeSet::new();
+    let mut types = BTreeSet::new();
+    let mut uses_table = false;
+    let mut elements_scanned = false;
+    for (_, expr) in &module.globals {
+        copy_expr(&mut Reader::new(expr), &mut vec![], &mut |idx| {
+            if let Index::Func(v) = idx {
+                queue.push(v);
+            }
+            0
+        })?;
+    }
+    loop {
+        while let Some(idx) = queue.pop() {
+            if !kept.insert(idx) {
+                continue;
+            }
+            types.insert(func_types[idx as usize]);
+            let Some(local) = (idx as usize).checked_sub(import_count) else {
+                continue;
+            };
+            copy_body(module.codes[local], &mut vec![], &mut |idx| {
+                match idx {
+                    Index::Func(v) => queue.push(v),
+                    Index::Type(v) => {
+                        types.insert(v);
+                    }
+                    Index::Table(_) => uses_table = true,
+                }
+                0
+            })?;
+        }
+        if !uses_table || elements_scanned {
+            break;
+        }
+        // 用到了表：元素段中的函数都可能被间接调用
+        elements_scanned = true;
+        for element in &module.elements {
+            copy_element(&mut Reader::new(element), &mut vec![], &mut |idx| {
+                if let Index::Func(v) = idx {
+                    queue.push(v);
+                }
+                0
+            })?;
+        }
+    }
+
+    let func_map: HashMap<u32, u32> = kept
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (*v, i as u32))
+        .collect();
+    let type_map: HashMap<u32, u32> = types
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (*v, i as u32))
+        .collect();
+    let mut map = |idx| match idx {
+        Index::Func(v) => func_map[&v],
+        Index::Type(v) => type_map[&v],
+        Index::Table(v) => v,
+    };
+
+    let mut sections: Vec<(u8, Vec<u8>)> = vec![];
+
+    let mut content = vec![];
+    for ty in &types {
+        content.extend(module.types[*ty as usize]);
+    }
+    sections.extend(vec_section(1, types.len(), content));
+
+    let mut content = vec![];
+    let mut count = 0;
+    let mut func_idx = 0;
+    for ipt in &module.imports {
+        content.extend(ipt.names);
+        content.push(ipt.kind);
+        if ipt.kind == 0 {
+            func_idx += 1;
+            if !kept.contains(&(func_idx - 1)) {
+                content.truncate(content.len() - ipt.names.len() - 1);
+                continue;
+            }
+            leb_u32(type_map[&ipt.ty], &mut content);
+        } else {
+            content.extend(ipt.desc);
+        }
+        count += 1;
+    }
+    sections.extend(vec_section(2, count, content));
+
+    let locals = kept
+        .iter()
+        .filter_map(|idx| (*idx as usize).checked_sub(import_count))
+        .collect::<Vec<_>>();
+    let mut content = vec![];
+    for local in &locals {
+        leb_u32(type_map[&module.funcs[*local]], &mut content);
+    }
+    sections.extend(vec_section(3, locals.len(), content));
+
+    sections.extend(module.tables.map(|raw| (4, raw.to_vec())));
+    sections.extend(module.memories.map(|raw| (5, raw.to_vec())));
+
+    let mut content = vec![];
+    for (head, expr) in &module.globals {
+        content.extend(*head);
+        copy_expr(&mut Reader::new(expr), &mut content, &mut map)?;
+    }
+    sections.extend(vec_section(6, module.globals.len(), content));
+
+    let mut content = vec![];
+    let mut count = 0;
+    for (name, kind, idx) in &module.exports {
+        if *kind == 0 && !kept.contains(idx) {
+            continue;
+        }
+        content.extend(*name);
+        content.push(*kind);
+        leb_u32(if *kind == 0 { func_map[idx] } else { *idx }, &mut content);
+        count += 1;
+    }
+    sections.extend(vec_section(7, count, content));
+
+    if let Some(start) = module.start.filter(|start| kept.contains(start)) {
+        let mut content = vec![];
+        leb_u32(func_map[&start], &mut content);
+        sections.push((8, content));
+    }
+
+    // 没有用到表时元素段没有意义
+    let mut content = vec![];
+    let count = if uses_table { module.elements.len() } else { 0 };
+    for element in &module.elements[..count] {
+        copy_element(&mut Reader::new(element), &mut content, &mut map)?;
+    }
+    sections.extend(vec_section(9, count, content));
+
+    sections.extend(module.data_count.map(|raw| (12, raw.to_vec())));
+
+    let mut content = vec![];
+    for local in &locals {
+        let mut body = vec![];
+        copy_body(module.codes[*local], &mut body, &mut map)?;
+        leb_u32(body.len() as u32, &mut content);
+        content.extend(body);
+    }
+    sections.extend(vec_section(10, locals.len(), content));
+
+    sections.extend(module.data.map(|raw| (11, raw.to_vec())));
+
+    let mut out = buf[..8].to_vec();
+    for (id, content) in sections {
+        out.push(id);
+        leb_u32(content.len() as u32, &mut out);
+        out.extend(content);
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_extract() {
+    use super::decoder::WasmValue;
+    use super::testing::{call_with, vec, wasm};
+
+    let name = |s: &str| {
+        let mut buf = vec![s.len() as u8];
+        buf.extend(s.as_bytes());
+        buf
+    };
+    let export = |s: &str, idx: u8| {
+        let mut buf = name(s);
+        buf.extend([0x00, idx]);
+        buf
+    };
+    let body = |code: &[u8]| {
+        let mut buf = vec![code.len() as u8 + 2, 0x00];
+        buf.extend(code);
+        buf.push(0x0b);
+        buf
+    };
+    let mut import = name("env");
+    import.extend(name("log"));
+    import.extend([0x00, 0x01]);
+    let buf = wasm(&[
+        // t0: [] -> [i32], t1: [i32] -> [], t2: [] -> []
+        (
+            1,
+            vec(&[
+                vec![0x60, 0x00, 0x01, 0x7f],
+                vec![0x60, 0x01, 0x7f, 0x00],
+                vec![0x60, 0x00, 0x00],
+            ]),
+        ),
+        (2, vec(&[import])),
+        (3, vec(&[vec![0x00], vec![0x00], vec![0x02]])),
+        (7, vec(&[export("a", 1), export("c", 3)])),
+        (
+            10,
+            vec(&[
+                // 1: call 2; i32.const 1; i32.add
+                body(&[0x10, 0x02, 0x41, 0x01, 0x6a]),
+                // 2: block (type 0) i32.const 41 end
+                body(&[0x02, 0x00, 0x41, 0x29, 0x0b]),
+                // 3: i32.const 7; call 0
+                body(&[0x41, 0x07, 0x10, 0x00]),
+            ]),
+        ),
+        (0, name("debug")),
+    ]);
+
+    let out = extract(&buf, &["a".to_string()]).unwrap();
+    let mut module = WasmModule::default(out);
+    module.decode().unwrap();
+    assert_eq!(module.section.import.entries.len(), 0);
+    assert_eq!(module.section.types.entries.len(), 1);
+    assert_eq!(module.section.func.entries.len(), 2);
+    assert_eq!(module.section.custom.name, "");
+    module.instance(None).unwrap();
+    assert_eq!(module.exports.len(), 1);
+    assert_eq!(call_with(&mut module, 0, &[]), WasmValue::I32(42));
+
+    // 按函数索引选择，保留导入函数
+    let out = extract(&buf, &["3".to_string()]).unwrap();
+    let mut module = WasmModule::default(out);
+    module.decode().unwrap();
+    assert_eq!(module.section.import.entries.len(), 1);
+    assert_eq!(module.section.types.entries.len(), 2);
+    assert_eq!(module.section.func.entries.len(), 1);
+    assert_eq!(module.exports.len(), 0);
+
+    assert_eq!(
+        extract(&buf, &["b".to_string()]).unwrap_err().to_string(),
+        "unknown function `b`"
+    );
+}
+
+#[test]
+fn test_extract_table() {
+    use super::decoder::WasmValue;
+    use super::testing::{call_with, func_bytes, vec};
+
+    let table = (4, vec![0x01, 0x70, 0x00, 0x02]);
+    let elem = (9, vec(&[vec![0x00, 0x41, 0x00, 0x0b, 0x01, 0x02]]));
+    let bodies: &[&[u8]] = &[
+        &[0x41, 0x01],                   // 0: 不可达
+        &[0x41, 0x00, 0x11, 0x00, 0x00], // 1: call_indirect 0
+        &[0x41, 0x05],                   // 2: 只通过表调用
+    ];
+    let buf = func_bytes(&[], &[0x7f], bodies, &[table, elem]);
+    let out = extract(&buf, &["1".to_string()]).unwrap();
+    let mut module = WasmModule::default(out);
+    module.decode().unwrap();
+    assert_eq!(module.section.func.entries.len(), 2);
+    module.instance(None).unwrap();
+    assert_eq!(module.table[0].get(0), Some(1));
+    assert_eq!(call_with(&mut module, 0, &[]), WasmValue::I32(5));
+}
@@ -0,0 +1,79 @@
+//! 浮点运算的 wasm 语义
+//!
+//! https://webassembly.github.io/spec/core/exec/numerics.html#nan-propagation
+//! 规范只要求运算结果中的 NaN 为 arithmetic NaN（quiet 位为 1），具体 payload 和符号位由实现决定，
+//! 不同 CPU 上的结果可能不同（例如 x86 上 0.0 / 0.0 得到的是负号 NaN）。
+//! 开启 `RuntimeConfig::canonicalize_nans` 后所有产生的 NaN 统一替换为 canonical NaN。
+
+use super::decoder::WasmValue;
+
+pub const F32_CANONICAL_NAN: u32 = 0x7fc0_0000;
+pub const F64_CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+/// 把 NaN 替换为 canonical NaN，非浮点数和非 NaN 原样返回
+pub fn canonicalize(value: WasmValue) -> WasmValue {
+    match value {
+        WasmValue::F32(v) if v.is_nan() => WasmValue::F32(f32::from_bits(F32_CANONICAL_NAN)),
+        WasmValue::F64(v) if v.is_nan() => WasmValue::F64(f64::from_bits(F64_CANONICAL_NAN)),
+        v => v,
+    }
+}
+
+macro_rules! float_ops {
+    ($min:ident, $max:ident, $nearest:ident, $ty:ty) => {
+        /// fmin: 任一操作数为 NaN 时结果为 NaN，且 -0 < +0
+        pub fn $min(a: $ty, b: $ty) -> $ty {
+            if a.is_nan() || b.is_nan() {
+                return a + b;
+            }
+            if a == b {
+                return if a.is_sign_negative() { a } else { b };
+            }
+            a.min(b)
+        }
+
+        /// fmax: 任一操作数为 NaN 时结果为 NaN，且 +0 > -0
+        pub fn $max(a: $ty, b: $ty) -> $ty {
+            if a.is_nan() || b.is_nan() {
+                return a + b;
+            }
+            if a == b {
+                return if a.is_sign_positive() { a } else { b };
+            }
+            a.max(b)
+        }
+
+        /// fnearest: 四舍六入五取偶
+        pub fn $nearest(a: $ty) -> $ty {
+            a.round_ties_even()
+        }
+    };
+}
+
+float_ops!(f32_min, f32_max, f32_nearest, f32);
+float_ops!(f64_min, f64_max, f64_nearest, f64);
+
+#[test]
+fn test_canonicalize() {
+    let payload = WasmValue::F32(f32::from_bits(0xffa0_0001));
+    match canonicalize(payload) {
+        WasmValue::F32(v) => assert_eq!(v.to_bits(), F32_CANONICAL_NAN),
+        v => panic!("unexpected {v:?}"),
+    }
+    let payload = WasmValue::F64(f64::from_bits(0x7ff0_0000_dead_beef));
+    match canonicalize(payload) {
+        WasmValue::F64(v) => assert_eq!(v.to_bits(), F64_CANONICAL_NAN),
+        v => panic!("unexpected {v:?}"),
+    }
+    assert_eq!(canonicalize(WasmValue::F32(1.5)), WasmValue::F32(1.5));
+    assert_eq!(canonicalize(WasmValue::I32(-1)), WasmValue::I32(-1));
+}
+
+#[test]
+fn test_min_max_signed_zero() {
+    assert!(f32_min(0.0, -0.0).is_sign_negative());
+    assert!(f32_max(-0.0, 0.0).is_sign_positive());
+    assert!(f64_min(f64::NAN, 1.0).is_nan());
+    assert_eq!(f64_nearest(2.5), 2.0);
+    assert_eq!(f32_nearest(-3.5), -4.0);
+}
@@ -0,0 +1,153 @@
+//! 异步宿主函数，由 `async` feature 开启，不依赖具体的异步运行时
+//!
+//! 异步宿主函数返回的 future 不能借用实例。调用时实例挂起（见 `WasmModule::suspend`），
+//! `invoke_async` 等待 future 完成后以它的结果继续执行。
+
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use super::decoder::{WasmModule, WasmValue};
+use super::linker::Func;
+use super::section::typings::ValueType;
+
+pub type HostFuture = Pin<Box<dyn Future<Output = Vec<WasmValue>> + Send>>;
+
+/// 调用时取得参数和实例，返回的 future 完成后得到宿主函数的结果
+pub type AsyncHostFunc = fn(&mut WasmModule, &[WasmValue]) -> HostFuture;
+
+/// 放在 Mutex 中使 WasmModule 仍是 Sync
+#[derive(Default)]
+pub struct Pending(pub(crate) Mutex<Option<HostFuture>>);
+
+impl Debug for Pending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pending = self.0.lock().map(|future| future.is_some());
+        f.debug_tuple("Pending")
+            .field(&pending.unwrap_or(false))
+            .finish()
+    }
+}
+
+impl Func {
+    /// ```ignore
+    /// linker.define("env", "fetch", Func::wrap_async(&[I32], &[I32], |_, args| {
+    ///     let key = args[0];
+    ///     Box::pin(async move { vec![lookup(key).await] })
+    /// }))?;
+    /// ```
+    pub fn wrap_async(params: &[ValueType], results: &[ValueType], func: AsyncHostFunc) -> Self {
+        Self {
+            async_func: Some(func),
+            ..Func::wrap(params, results, |wasm, _| wasm.suspend())
+        }
+    }
+}
+
+impl WasmModule {
+    /// 与 `invoke` 相同，调用异步宿主函数时等待它完成后继续执行
+    pub async fn invoke_async(
+        &mut self,
+        name: &str,
+        args: &[WasmValue],
+    ) -> anyhow::Result<Vec<WasmValue>> {
+        self.take_pending();
+        let mut res = self.invoke(name, args);
+        while self.is_suspended() {
+            let Some(future) = self.take_pending() else {
+                break;
+            };
+            let values = future.await;
+            res = self.resume(&values);
+        }
+        res
+    }
+
+    /// 取出等待中的异步宿主函数调用；不使用 `invoke_async` 时可以自行等待后调用 `resume`
+    pub fn take_pending(&mut self) -> Option<HostFuture> {
+        self.pending.0.get_mut().unwrap().take()
+    }
+}
+
+#[test]
+fn test_invoke_async() {
+    use std::task::{Context, Poll, Waker};
+
+    use super::linker::Linker;
+    use super::wat;
+
+    /// 第一次 poll 时返回 Pending
+    struct YieldOnce(bool);
+    impl Future for YieldOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "double" (func $double (param i32) (result i32)))
+          (import "env" "log" (func $log (param i32)))
+          (func (export "main") (param i32) (result i32)
+            (call $log (local.get 0))
+            (i32.add
+              (call $double (local.get 0))
+              (call $double (i32.add (local.get 0) (i32.const 1))))))"#,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define(
+            "env",
+            "double",
+            Func::wrap_async(&[ValueType::I32], &[ValueType::I32], |_, args| {
+                let WasmValue::I32(v) = args[0] else {
+                    unreachable!()
+                };
+                Box::pin(async move {
+                    YieldOnce(false).await;
+                    vec![WasmValue::I32(v * 2)]
+                })
+            }),
+        )
+        .unwrap()
+        .define(
+            "env",
+            "log",
+            Func::wrap(&[ValueType::I32], &[], |_, _| vec![]),
+        )
+        .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+
+    let res = block_on(wasm.invoke_async("main", &[WasmValue::I32(5)])).unwrap();
+    assert_eq!(res, [WasmValue::I32(10 + 12)]);
+    assert!(!wasm.is_suspended());
+    assert_eq!(wasm.stats.host_calls[&0], 2);
+
+    // 同步调用时挂起，由调用方等待 future 后继续
+    assert!(wasm.invoke("main", &[WasmValue::I32(1)]).is_err());
+    let future = wasm.take_pending().unwrap();
+    assert!(wasm.resume(&block_on(future)).is_err());
+    let future = wasm.take_pending().unwrap();
+    let res = wasm.resume(&block_on(future)).unwrap();
+    assert_eq!(res, [WasmValue::I32(2 + 4)]);
+}
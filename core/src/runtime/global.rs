@@ -0,0 +1,73 @@
+//! 宿主创建的全局变量：值放在 `Arc` 中，导入它的实例和宿主读写同一个值，
+//! guest 的 global.set 之后宿主立即可以看到，反之亦然
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, ensure};
+
+use super::decoder::WasmValue;
+use super::section::typings::ValueType;
+
+#[derive(Debug, Clone)]
+pub struct SharedGlobal {
+    value: Arc<Mutex<WasmValue>>,
+    ty: ValueType,
+    mutable: bool,
+}
+
+impl SharedGlobal {
+    /// 类型由 value 决定
+    pub fn new(value: WasmValue, mutable: bool) -> anyhow::Result<SharedGlobal> {
+        let ty = value
+            .value_type()
+            .ok_or_else(|| anyhow!("global value must not be NOP"))?;
+        Ok(SharedGlobal {
+            value: Arc::new(Mutex::new(value)),
+            ty,
+            mutable,
+        })
+    }
+
+    pub fn ty(&self) -> ValueType {
+        self.ty
+    }
+
+    pub fn mutability(&self) -> bool {
+        self.mutable
+    }
+
+    pub fn get(&self) -> WasmValue {
+        *self.value.lock().unwrap()
+    }
+
+    /// 宿主修改值，不可变的全局变量或类型不一致时出错
+    pub fn set(&self, value: WasmValue) -> anyhow::Result<()> {
+        ensure!(self.mutable, "can't set an immutable global");
+        ensure!(
+            value.value_type() == Some(self.ty),
+            "global type mismatch: expected {}, found {value:?}",
+            self.ty
+        );
+        self.store(value);
+        Ok(())
+    }
+
+    /// global.set 的写入，可变性和类型由验证保证
+    pub(crate) fn store(&self, value: WasmValue) {
+        *self.value.lock().unwrap() = value;
+    }
+}
+
+#[test]
+fn test_shared_global() {
+    let global = SharedGlobal::new(WasmValue::I64(1), true).unwrap();
+    let other = global.clone();
+    global.set(WasmValue::I64(2)).unwrap();
+    assert_eq!(other.get(), WasmValue::I64(2));
+    assert!(global.set(WasmValue::I32(3)).is_err());
+
+    let constant = SharedGlobal::new(WasmValue::F32(1.5), false).unwrap();
+    assert_eq!(constant.ty(), ValueType::F32);
+    assert!(constant.set(WasmValue::F32(2.5)).is_err());
+    assert!(SharedGlobal::new(WasmValue::NOP, true).is_err());
+}
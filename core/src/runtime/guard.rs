@@ -0,0 +1,388 @@
+//! guard page 内存后端（64 位 Linux），由 `RuntimeConfig::memory_backend` 选择
+//!
+//! 预留 8 GiB 不可访问的虚拟地址，只开放内存当前大小的部分；u32 地址加 u32 偏移总落在
+//! 预留范围内，所以访问时不检查边界。越界访问触发 SIGSEGV，信号处理函数把出错的页改为
+//! 可读写后返回，让这次访问完成并在当前线程记下 fault 地址；访问之后检查 fault，
+//! 丢弃这些页并返回越界。Rust 中不能从信号处理函数跳回解释器，所以用这种方式把信号转换为 trap。
+//!
+//! 出错的页开放期间其他线程对这一页的越界访问不会 fault，所以不能在多个线程上同时访问
+//! 同一块 guard page 内存：执行 wasm 需要 `&mut WasmModule`，多个实例共享的 `Memory`
+//! 总是使用堆内存。
+
+use std::cell::Cell;
+use std::io;
+use std::sync::atomic::{compiler_fence, AtomicBool, AtomicUsize, Ordering};
+
+/// 预留的地址空间
+const RESERVE: usize = 1 << 33;
+/// 省略边界检查的访问不超过这个地址（u32 地址 + u32 偏移 + 8 字节）
+const MAX_ADDR: u64 = u32::MAX as u64 * 2 + 8;
+/// 同时存在的 guard page 内存个数上限，信号处理函数只能查固定大小的表
+const MAX_REGIONS: usize = 64;
+
+struct Region {
+    start: AtomicUsize,
+    end: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Region = Region {
+    start: AtomicUsize::new(0),
+    end: AtomicUsize::new(0),
+};
+static REGIONS: [Region; MAX_REGIONS] = [EMPTY; MAX_REGIONS];
+
+thread_local! {
+    /// 当前线程上一次落在 guard page 上的地址，0 表示没有；信号处理函数在出错的线程上执行，
+    /// 常量初始化且没有析构函数，在信号处理函数中访问不会分配
+    static FAULT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// 线性内存，地址空间在创建时一次预留，增长时不移动
+#[derive(Debug)]
+pub struct GuardedMemory {
+    base: *mut u8,
+    len: usize,
+    capacity: usize,
+    slot: usize,
+    /// 越界后没能重新保护开放的页，之后的访问都检查边界
+    poisoned: AtomicBool,
+}
+
+// 只通过 &self / &mut self 访问，与 Vec<u8> 相同；fault 按线程记录，同时访问的限制见模块说明
+unsafe impl Send for GuardedMemory {}
+unsafe impl Sync for GuardedMemory {}
+
+impl GuardedMemory {
+    /// 开放 len 字节，最多增长到 capacity 字节
+    pub fn new(len: usize, capacity: usize) -> io::Result<Self> {
+        if capacity > RESERVE / 2 || len > capacity {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "guarded memory is limited to 4 GiB",
+            ));
+        }
+        sys::install_handler()?;
+        let base = sys::reserve(RESERVE)?;
+        let Some(slot) = REGIONS.iter().position(|r| {
+            r.start
+                .compare_exchange(0, base as usize, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        }) else {
+            sys::release(base, RESERVE);
+            return Err(io::Error::other("too many guarded memories"));
+        };
+        REGIONS[slot]
+            .end
+            .store(base as usize + RESERVE, Ordering::Release);
+        let mut mem = Self {
+            base,
+            len: 0,
+            capacity,
+            slot,
+            poisoned: AtomicBool::new(false),
+        };
+        mem.resize(len)?;
+        Ok(mem)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 开放或收回页，新增的部分为 0
+    pub fn resize(&mut self, len: usize) -> io::Result<()> {
+        if len > self.capacity {
+            return Err(io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                "guarded memory grows beyond its capacity",
+            ));
+        }
+        if len < self.len {
+            self.as_mut_slice()[len..].fill(0);
+        }
+        let page = sys::page_size();
+        let (old, new) = (self.len.div_ceil(page) * page, len.div_ceil(page) * page);
+        if new > old {
+            sys::open(unsafe { self.base.add(old) }, new - old)?;
+        } else if new < old {
+            sys::discard(unsafe { self.base.add(new) }, old - new)?;
+        }
+        self.len = len;
+        Ok(())
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.base, self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.base, self.len) }
+    }
+
+    /// 长度是系统页大小的整数倍时越界一定落在 guard page 上，才能省略边界检查
+    fn elided(&self) -> bool {
+        self.len.is_multiple_of(sys::page_size()) && !self.poisoned.load(Ordering::Relaxed)
+    }
+
+    /// 取出并清除当前线程的 fault，有 fault 时重新保护开放范围之外的页
+    fn faulted(&self) -> bool {
+        compiler_fence(Ordering::SeqCst);
+        if FAULT.with(|fault| fault.replace(0)) == 0 {
+            return false;
+        }
+        let open = self.len.div_ceil(sys::page_size()) * sys::page_size();
+        // 失败时这些页保持可读写，之后的越界访问不会再 fault，改为每次检查边界
+        if sys::discard(unsafe { self.base.add(open) }, RESERVE - open).is_err() {
+            self.poisoned.store(true, Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// 读取 addr 开始的 N 个字节，越界时返回 None
+    pub fn load<const N: usize>(&self, addr: u64) -> Option<[u8; N]> {
+        if !self.elided() || addr + N as u64 > MAX_ADDR {
+            let start = usize::try_from(addr).ok()?;
+            return self
+                .as_slice()
+                .get(start..start.checked_add(N)?)?
+                .try_into()
+                .ok();
+        }
+        // 可能读到不可访问的页，必须是 volatile 读，编译器不能假设它一定成功
+        let buf = unsafe {
+            self.base
+                .add(addr as usize)
+                .cast::<[u8; N]>()
+                .read_volatile()
+        };
+        (!self.faulted()).then_some(buf)
+    }
+
+    /// 把 bytes 写到 addr，越界时返回 false，内存不变
+    pub fn store(&mut self, addr: u64, bytes: &[u8]) -> bool {
+        if !self.elided() || bytes.is_empty() || addr + bytes.len() as u64 > MAX_ADDR {
+            let dst = usize::try_from(addr).ok().and_then(|start| {
+                let end = start.checked_add(bytes.len())?;
+                self.as_mut_slice().get_mut(start..end)
+            });
+            return dst.map(|dst| dst.copy_from_slice(bytes)).is_some();
+        }
+        let dst = unsafe { self.base.add(addr as usize) };
+        // 先读首尾两个字节，越界时在写入之前 fault，不会只写入开放的一部分
+        unsafe {
+            dst.read_volatile();
+            dst.add(bytes.len() - 1).read_volatile();
+        }
+        if self.faulted() {
+            return false;
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+        }
+        true
+    }
+}
+
+impl Drop for GuardedMemory {
+    fn drop(&mut self) {
+        sys::release(self.base, RESERVE);
+        REGIONS[self.slot].end.store(0, Ordering::Release);
+        REGIONS[self.slot].start.store(0, Ordering::Release);
+    }
+}
+
+/// 信号处理函数：地址落在某个 guard page 内存中时开放这一页并在当前线程记下 fault
+fn on_fault(addr: usize) -> bool {
+    for region in REGIONS.iter() {
+        let start = region.start.load(Ordering::Acquire);
+        if start == 0 || addr < start || addr >= region.end.load(Ordering::Acquire) {
+            continue;
+        }
+        let page = addr & !(sys::page_size() - 1);
+        if sys::open(page as *mut u8, sys::page_size()).is_err() {
+            return false;
+        }
+        FAULT.with(|fault| fault.set(addr));
+        return true;
+    }
+    false
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_pointer_width = "64"))]
+mod sys {
+    use std::ffi::{c_int, c_long, c_void};
+    use std::io;
+    use std::sync::OnceLock;
+
+    const PROT_NONE: c_int = 0;
+    const PROT_READ: c_int = 1;
+    const PROT_WRITE: c_int = 2;
+    const MAP_PRIVATE: c_int = 0x02;
+    const MAP_FIXED: c_int = 0x10;
+    const MAP_ANONYMOUS: c_int = 0x20;
+    const MAP_NORESERVE: c_int = 0x4000;
+    const SIGSEGV: c_int = 11;
+    const SA_ONSTACK: c_int = 0x0800_0000;
+    const SA_SIGINFO: c_int = 4;
+    const SC_PAGESIZE: c_int = 30;
+    const SIG_DFL: usize = 0;
+    const SIG_IGN: usize = 1;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct SigAction {
+        sa_sigaction: usize,
+        sa_mask: [u64; 16],
+        sa_flags: c_int,
+        sa_restorer: usize,
+    }
+
+    #[repr(C)]
+    struct SigInfo {
+        si_signo: c_int,
+        si_errno: c_int,
+        si_code: c_int,
+        _pad: c_int,
+        si_addr: usize,
+    }
+
+    extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+        fn mprotect(addr: *mut c_void, len: usize, prot: c_int) -> c_int;
+        fn sigaction(sig: c_int, act: *const SigAction, old: *mut SigAction) -> c_int;
+        fn sysconf(name: c_int) -> c_long;
+    }
+
+    static PREVIOUS: OnceLock<SigAction> = OnceLock::new();
+
+    pub fn page_size() -> usize {
+        static SIZE: OnceLock<usize> = OnceLock::new();
+        *SIZE.get_or_init(|| unsafe { sysconf(SC_PAGESIZE) } as usize)
+    }
+
+    fn map(addr: *mut u8, len: usize, flags: c_int) -> io::Result<*mut u8> {
+        let flags = MAP_PRIVATE | MAP_ANONYMOUS | MAP_NORESERVE | flags;
+        let ptr = unsafe { mmap(addr.cast(), len, PROT_NONE, flags, -1, 0) };
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ptr.cast())
+    }
+
+    pub fn reserve(len: usize) -> io::Result<*mut u8> {
+        map(std::ptr::null_mut(), len, 0)
+    }
+
+    /// 换成新的不可访问映射，原来的内容丢弃
+    pub fn discard(addr: *mut u8, len: usize) -> io::Result<()> {
+        map(addr, len, MAP_FIXED).map(|_| ())
+    }
+
+    pub fn release(addr: *mut u8, len: usize) {
+        unsafe { munmap(addr.cast(), len) };
+    }
+
+    /// 改为可读写，新开放的页内容为 0
+    pub fn open(addr: *mut u8, len: usize) -> io::Result<()> {
+        if unsafe { mprotect(addr.cast(), len, PROT_READ | PROT_WRITE) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    extern "C" fn handler(sig: c_int, info: *mut SigInfo, ctx: *mut c_void) {
+        let addr = unsafe { (*info).si_addr };
+        if super::on_fault(addr) {
+            return;
+        }
+        // 不是 guard page：交给原来的处理函数，本处理函数保持安装
+        let Some(previous) = PREVIOUS.get() else {
+            return;
+        };
+        match previous.sa_sigaction {
+            SIG_DFL | SIG_IGN => {
+                // 默认处理是结束进程：恢复默认处理，返回后重新触发
+                let default = SigAction {
+                    sa_sigaction: SIG_DFL,
+                    sa_mask: [0; 16],
+                    sa_flags: 0,
+                    sa_restorer: 0,
+                };
+                unsafe { sigaction(SIGSEGV, &default, std::ptr::null_mut()) };
+            }
+            f if previous.sa_flags & SA_SIGINFO != 0 => {
+                let f: extern "C" fn(c_int, *mut SigInfo, *mut c_void) =
+                    unsafe { std::mem::transmute(f) };
+                f(sig, info, ctx);
+            }
+            f => {
+                let f: extern "C" fn(c_int) = unsafe { std::mem::transmute(f) };
+                f(sig);
+            }
+        }
+    }
+
+    pub fn install_handler() -> io::Result<()> {
+        let mut result = Ok(());
+        PREVIOUS.get_or_init(|| {
+            let action = SigAction {
+                sa_sigaction: handler as *const () as usize,
+                sa_mask: [0; 16],
+                sa_flags: SA_SIGINFO | SA_ONSTACK,
+                sa_restorer: 0,
+            };
+            let mut previous = action;
+            if unsafe { sigaction(SIGSEGV, &action, &mut previous) } != 0 {
+                result = Err(io::Error::last_os_error());
+            }
+            previous
+        });
+        result
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu", target_pointer_width = "64")))]
+mod sys {
+    use std::io;
+
+    fn unsupported<T>() -> io::Result<T> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "guarded memory is only supported on 64-bit linux",
+        ))
+    }
+
+    pub fn page_size() -> usize {
+        4096
+    }
+    pub fn reserve(_len: usize) -> io::Result<*mut u8> {
+        unsupported()
+    }
+    pub fn discard(_addr: *mut u8, _len: usize) -> io::Result<()> {
+        unsupported()
+    }
+    pub fn release(_addr: *mut u8, _len: usize) {}
+    pub fn open(_addr: *mut u8, _len: usize) -> io::Result<()> {
+        unsupported()
+    }
+    pub fn install_handler() -> io::Result<()> {
+        unsupported()
+    }
+}
@@ -0,0 +1,252 @@
+//! `oxygen inspect --hex`：带注释的十六进制转储，标出段的边界、LEB 字段的值和每条指令
+//!
+//! 段和函数体的结构直接扫描原始字节得到，模块解码失败时也能显示出错之前的部分；
+//! 指令的边界来自解码时记录的 `CodeSection::instr_offsets`。
+
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use super::decoder::WasmModule;
+use super::error::{section_name, DecodeError};
+use super::extract::Reader;
+
+const CUSTOM: u8 = 0;
+const START: u8 = 8;
+const CODE: u8 = 10;
+/// 每行最多显示的字节数
+const ROW: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct HexDump<'a> {
+    raw: &'a [u8],
+    /// 按开始位置排序、互不重叠的注释
+    pub notes: Vec<(Range<usize>, String)>,
+    /// 解码错误的位置和信息，显示在所在行的下方
+    pub error: Option<(usize, String)>,
+}
+
+impl<'a> HexDump<'a> {
+    pub fn new(wasm: &'a WasmModule) -> Self {
+        let mut dump = Self {
+            raw: &wasm.raw,
+            notes: vec![],
+            error: None,
+        };
+        let mut bodies = vec![];
+        // 扫描到格式错误处为止，之后的字节不加注释
+        let _ = dump.scan(&mut bodies);
+        dump.instructions(wasm, &bodies);
+        dump.notes.sort_by_key(|(range, _)| range.start);
+        dump
+    }
+
+    /// 在出错的位置标出解码错误
+    pub fn with_error(mut self, err: &DecodeError) -> Self {
+        self.error = Some((err.offset, err.to_string()));
+        self
+    }
+
+    fn note(&mut self, range: Range<usize>, text: impl Into<String>) {
+        if !range.is_empty() {
+            self.notes.push((range, text.into()));
+        }
+    }
+
+    /// 一个 LEB 字段，返回它的值
+    fn leb(&mut self, r: &mut Reader, what: &str) -> anyhow::Result<u32> {
+        let start = r.pos;
+        let value = r.u32()?;
+        self.note(start..r.pos, format!("{what} = {value}"));
+        Ok(value)
+    }
+
+    /// 模块头和各个段的结构，bodies 收集函数体中指令序列的范围
+    fn scan(&mut self, bodies: &mut Vec<Range<usize>>) -> anyhow::Result<()> {
+        let mut r = Reader::new(self.raw);
+        r.bytes(4)?;
+        self.note(0..4, "magic \"\\0asm\"");
+        let version = r.bytes(4)?;
+        let version = u32::from_le_bytes(version.try_into()?);
+        self.note(4..8, format!("version {version}"));
+
+        while !r.is_empty() {
+            let id = r.byte()?;
+            self.note(
+                r.pos - 1..r.pos,
+                format!("section {} ({id})", section_name(id)),
+            );
+            let size = self.leb(&mut r, "size")? as usize;
+            let end = r.pos + size;
+            anyhow::ensure!(end <= self.raw.len(), "section out of module");
+            let mut section = Reader::new(&self.raw[..end]);
+            section.pos = r.pos;
+            match id {
+                CUSTOM => {
+                    let start = section.pos;
+                    let len = section.u32()? as usize;
+                    let name = String::from_utf8_lossy(section.bytes(len)?);
+                    self.note(start..section.pos, format!("name = {name:?}"));
+                }
+                START => {
+                    self.leb(&mut section, "start func")?;
+                }
+                CODE => self.code(&mut section, bodies)?,
+                _ => {
+                    self.leb(&mut section, "count")?;
+                }
+            }
+            r.pos = end;
+        }
+        Ok(())
+    }
+
+    /// 每个函数体的大小和局部变量声明
+    fn code(&mut self, r: &mut Reader, bodies: &mut Vec<Range<usize>>) -> anyhow::Result<()> {
+        let count = self.leb(r, "count")?;
+        for _ in 0..count {
+            let size = self.leb(r, "body size")? as usize;
+            let end = r.pos + size;
+            anyhow::ensure!(end <= r.buf.len(), "function body out of section");
+            let mut body = Reader::new(&r.buf[..end]);
+            body.pos = r.pos;
+            let groups = self.leb(&mut body, "local groups")?;
+            let mut simple = true;
+            for _ in 0..groups {
+                let start = body.pos;
+                let n = body.u32()?;
+                let ty = body.byte()?;
+                let Some(ty) = value_type(ty) else {
+                    // 引用类型带有堆类型，不再继续解析局部变量
+                    self.note(start..body.pos - 1, format!("{n} locals"));
+                    simple = false;
+                    break;
+                };
+                self.note(start..body.pos, format!("{n} x {ty}"));
+            }
+            if simple {
+                bodies.push(body.pos..end);
+            }
+            r.pos = end;
+        }
+        Ok(())
+    }
+
+    /// 已解码的函数体中每条指令的范围，标注为对应的操作码
+    fn instructions(&mut self, wasm: &WasmModule, bodies: &[Range<usize>]) {
+        let mut offsets = wasm.section.code.instr_offsets.clone();
+        offsets.sort_by_key(|&(_, offset)| offset);
+        for (i, &(op, start)) in offsets.iter().enumerate() {
+            let Some(body) = bodies.iter().find(|body| body.contains(&start)) else {
+                continue;
+            };
+            let next = offsets.get(i + 1);
+            let end = next.map_or(body.end, |&(_, next)| next.min(body.end));
+            // 不产生操作码的指令与下一条指令的操作码索引相同
+            let text = match (next, wasm.ops.get(op)) {
+                (Some(&(next, _)), _) if next == op => "(no opcode)".to_string(),
+                (_, Some(op)) => format!("{op:?}"),
+                (_, None) => continue,
+            };
+            self.note(start..end, text);
+        }
+    }
+
+    fn row(&self, f: &mut fmt::Formatter<'_>, range: Range<usize>, note: &str) -> fmt::Result {
+        let hex = self.raw[range.clone()]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match note.is_empty() {
+            true => writeln!(f, "{:08x}: {hex}", range.start)?,
+            false => writeln!(
+                f,
+                "{:08x}: {hex:<w$}  ; {note}",
+                range.start,
+                w = ROW * 3 - 1
+            )?,
+        }
+        if let Some((offset, err)) = &self.error {
+            if range.contains(offset) || (range.end == self.raw.len() && *offset >= range.end) {
+                let caret = " ".repeat(10 + 3 * (offset - range.start).min(ROW));
+                writeln!(f, "{caret}^^ {err}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 范围内的字节按每行 ROW 个显示，注释只在第一行
+    fn rows(&self, f: &mut fmt::Formatter<'_>, range: Range<usize>, note: &str) -> fmt::Result {
+        let mut start = range.start;
+        while start < range.end {
+            let end = (start + ROW).min(range.end);
+            let note = if start == range.start { note } else { "" };
+            self.row(f, start..end, note)?;
+            start = end;
+        }
+        Ok(())
+    }
+}
+
+fn value_type(ty: u8) -> Option<&'static str> {
+    Some(match ty {
+        0x7f => "i32",
+        0x7e => "i64",
+        0x7d => "f32",
+        0x7c => "f64",
+        0x7b => "v128",
+        0x70 => "funcref",
+        0x6f => "externref",
+        _ => return None,
+    })
+}
+
+impl Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pos = 0;
+        for (range, note) in &self.notes {
+            if range.start < pos {
+                continue;
+            }
+            self.rows(f, pos..range.start, "")?;
+            self.rows(f, range.clone(), note)?;
+            pos = range.end;
+        }
+        self.rows(f, pos..self.raw.len(), "")
+    }
+}
+
+#[test]
+fn test_hex_dump() {
+    use super::wat;
+
+    let src = r#"(module
+      (func (export "main") (param i32) (result i32)
+        (local i64)
+        (i32.add (local.get 0) (i32.const 300))))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.decode().unwrap();
+    let text = HexDump::new(&wasm).to_string();
+    let lines = text.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("00000000: 00 61 73 6d"), "{text}");
+    assert!(lines[0].ends_with("; magic \"\\0asm\""), "{text}");
+    assert!(lines[1].ends_with("; version 1"), "{text}");
+    assert!(lines[2].ends_with("; section type (1)"), "{text}");
+    assert!(text.contains("; section code (10)"), "{text}");
+    assert!(text.contains("; 1 x i64"), "{text}");
+    // 300 的 LEB 编码占两个字节
+    let line = lines.iter().find(|line| line.contains("I32Const")).unwrap();
+    assert!(line.contains(": 41 ac 02 "), "{text}");
+    assert!(text.contains("; LocalGet(0)"), "{text}");
+    assert!(text.contains("; End("), "{text}");
+
+    // 解码失败时仍然显示出错之前的结构，并标出错误位置
+    let mut raw = wat::compile(src).unwrap();
+    let len = raw.len();
+    raw.truncate(len - 2);
+    let mut wasm = WasmModule::default(raw);
+    let err = wasm.decode().unwrap_err();
+    let text = HexDump::new(&wasm).with_error(&err).to_string();
+    assert!(text.contains("; section type (1)"), "{text}");
+    assert!(text.contains("^^ "), "{text}");
+}
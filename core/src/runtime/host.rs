@@ -0,0 +1,152 @@
+//! 可插拔的宿主模块：把一组宿主函数和它们共用的状态打包为一个模块，按模块名注册到 `Linker`
+//!
+//! wasi-nn、wasi-crypto 或私有 ABI 这样的扩展可以放在单独的 crate 中实现 `HostModule`，
+//! 嵌入方用 `Linker::define_host_module` 接入。状态由实现者自己保护（例如放在 `Mutex` 中），
+//! 同一个宿主模块可以同时被多个实例导入。
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use super::decoder::{WasmModule, WasmValue};
+use super::linker::{Func, Linker};
+use super::section::typings::ValueType;
+use super::trap::Trap;
+
+/// 宿主模块导出的函数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostExport {
+    pub name: String,
+    pub params: Vec<ValueType>,
+    pub results: Vec<ValueType>,
+}
+
+impl HostExport {
+    pub fn new(name: impl Into<String>, params: &[ValueType], results: &[ValueType]) -> Self {
+        Self {
+            name: name.into(),
+            params: params.to_vec(),
+            results: results.to_vec(),
+        }
+    }
+}
+
+pub trait HostModule: Debug + Send + Sync {
+    /// 导入时使用的模块名，例如 `wasi_ephemeral_nn`
+    fn name(&self) -> &str;
+
+    /// 模块导出的所有函数，注册时读取一次
+    fn exports(&self) -> Vec<HostExport>;
+
+    /// 调用 `exports` 中的第 func 个函数，args 的类型已经检查过；
+    /// 返回的 trap 结束 guest 的执行
+    fn call(
+        &self,
+        wasm: &mut WasmModule,
+        func: usize,
+        args: &[WasmValue],
+    ) -> Result<Vec<WasmValue>, Trap>;
+}
+
+pub type SharedHostModule = Arc<dyn HostModule>;
+
+impl Linker {
+    /// 以 `HostModule::name` 注册宿主模块的所有导出函数
+    pub fn define_host_module(
+        &mut self,
+        module: impl HostModule + 'static,
+    ) -> anyhow::Result<&mut Self> {
+        self.define_shared_host_module(Arc::new(module))
+    }
+
+    /// 与 `define_host_module` 相同，module 可以同时注册到多个 `Linker`
+    pub fn define_shared_host_module(
+        &mut self,
+        module: SharedHostModule,
+    ) -> anyhow::Result<&mut Self> {
+        let name = module.name().to_string();
+        for (idx, export) in module.exports().into_iter().enumerate() {
+            let mut func = Func::wrap(&export.params, &export.results, |_, _| vec![]);
+            func.host = Some((module.clone(), idx));
+            self.define(&name, &export.name, func)?;
+        }
+        Ok(self)
+    }
+}
+
+#[test]
+fn test_host_module() {
+    use std::sync::Mutex;
+
+    use super::wat;
+
+    /// 累加器：add 返回累加后的值，fail 总是 trap
+    #[derive(Debug, Default)]
+    struct Counter {
+        total: Mutex<i64>,
+    }
+
+    impl HostModule for Counter {
+        fn name(&self) -> &str {
+            "counter"
+        }
+        fn exports(&self) -> Vec<HostExport> {
+            use ValueType::{I32, I64};
+            vec![
+                HostExport::new("add", &[I32], &[I64]),
+                HostExport::new("fail", &[], &[]),
+            ]
+        }
+        fn call(
+            &self,
+            _: &mut WasmModule,
+            func: usize,
+            args: &[WasmValue],
+        ) -> Result<Vec<WasmValue>, Trap> {
+            match (func, args) {
+                (0, [WasmValue::I32(n)]) => {
+                    let mut total = self.total.lock().unwrap();
+                    *total += *n as i64;
+                    Ok(vec![WasmValue::I64(*total)])
+                }
+                _ => Err(Trap::Host {
+                    message: "counter failed".to_string(),
+                }),
+            }
+        }
+    }
+
+    let src = r#"(module
+      (import "counter" "add" (func $add (param i32) (result i64)))
+      (import "counter" "fail" (func $fail))
+      (func (export "add") (param i32) (result i64) (call $add (local.get 0)))
+      (func (export "fail") (call $fail)))"#;
+    let counter: SharedHostModule = Arc::new(Counter::default());
+    let mut linker = Linker::new();
+    linker.define_shared_host_module(counter.clone()).unwrap();
+    assert!(linker.define_shared_host_module(counter).is_err());
+    let instance = || {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).unwrap();
+        wasm
+    };
+    let (mut a, mut b) = (instance(), instance());
+    assert_eq!(
+        a.invoke("add", &[WasmValue::I32(2)]).unwrap(),
+        [WasmValue::I64(2)]
+    );
+    // 两个实例共用宿主模块的状态
+    assert_eq!(
+        b.invoke("add", &[WasmValue::I32(3)]).unwrap(),
+        [WasmValue::I64(5)]
+    );
+    let err = a.invoke("fail", &[]).unwrap_err();
+    assert!(err.to_string().contains("counter failed"), "{err}");
+
+    // 签名不匹配的导入
+    let mut wasm = WasmModule::default(
+        wat::compile(r#"(module (import "counter" "add" (func (param i64))))"#).unwrap(),
+    );
+    wasm.decode().unwrap();
+    assert!(linker.instantiate(&mut wasm).is_err());
+}
@@ -0,0 +1,507 @@
+//! 由 Opcode 降级得到的内部指令
+//!
+//! 与 `WasmModule::ops` 一一对应（下标相同），因此函数入口、调用返回地址不需要转换：
+//! - 分支指令的目标在降级时解析为绝对位置，执行时不再查找块的 Location；
+//!   按指令的栈效果推导出分支处要保留和丢弃的值，跳转时整理操作数栈。
+//!   跳出函数的 br 降级为 `Instr::Return`；推导不出的分支（函数体不合法）保持为 `Instr::Op`，
+//!   执行时 trap
+//! - 函数体最后的 end 降级为 `Instr::Return`，按函数体的范围确定，不依赖执行入口
+//! - 常见的指令组合融合为一条指令，被融合的后续指令保持原样，但不会被执行到
+//!   （它们的前一条不是控制指令，不可能是分支目标）
+//! - 其余指令为 `Instr::Op`，按原 Opcode 执行
+
+use std::collections::{HashMap, HashSet};
+
+use super::decoder::{FuncKind, WasmModule};
+use super::section::code::FuncBody;
+use super::section::import;
+use super::section::opcode::Opcode;
+use super::section::types::FunctionType;
+
+/// 跳转：保留栈顶 arity 个值，丢弃它们下面的 drop 个值，然后跳转到 target
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Branch {
+    pub target: usize,
+    pub arity: u32,
+    pub drop: u32,
+}
+
+impl Branch {
+    fn to(target: usize) -> Branch {
+        Branch {
+            target,
+            arity: 0,
+            drop: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Instr {
+    /// 按 `ops` 中的原指令执行
+    Op,
+    /// block、loop、else、nop 和块的 end
+    Nop,
+    Jump(Branch),
+    BrIf(Branch),
+    /// br_table，下标指向 `Code::tables`
+    BrTable(usize),
+    /// 条件为假时跳转到 else（或 end）
+    If(usize),
+    /// return 和函数体最后的 end
+    Return,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    I32Const(i32),
+    /// local.get a; local.get b
+    LocalGet2(u32, u32),
+    /// i32.const c; i32.add
+    I32AddImm(i32),
+    /// i32.const c; i32.sub
+    I32SubImm(i32),
+    /// i32.eqz; br_if
+    BrIfEqz(Branch),
+    /// 栈顶为空引用时弹出并跳转
+    BrOnNull(Branch),
+    /// 栈顶不是空引用时连同它一起跳转，否则弹出
+    BrOnNonNull(Branch),
+}
+
+impl Instr {
+    /// 对应的原指令条数
+    pub fn width(&self) -> u64 {
+        match self {
+            Instr::LocalGet2(..)
+            | Instr::I32AddImm(_)
+            | Instr::I32SubImm(_)
+            | Instr::BrIfEqz(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Code {
+    pub instrs: Vec<Instr>,
+    /// br_table 的跳转目标，最后一项为默认目标
+    pub tables: Vec<Box<[Branch]>>,
+}
+
+struct Label {
+    /// 进入块时操作数栈高度（不含参数）
+    height: usize,
+    params: usize,
+    results: usize,
+    /// 分支保留的值个数：loop 为参数个数，其他为结果个数
+    arity: usize,
+    target: usize,
+    /// 块本身在不可达的代码中
+    dead: bool,
+    /// br、return 等之后直到块结束（或 else）的代码不可达
+    unreachable: bool,
+}
+
+/// 推导函数体 code 中每个分支指令的 Branch（br_table 按表项顺序，最后一项为默认目标），
+/// 函数最外层的标签跳到函数的 end。不可达代码中的分支不会执行，不记录；
+/// 栈效果未知的指令（SIMD）解释器不能执行，执行不会越过它，之后直到块结束按不可达处理；
+/// 栈高度不合法时返回 None
+fn branches(
+    module: &WasmModule,
+    funcs: &[usize],
+    ty: &FunctionType,
+    code: (usize, usize, usize),
+) -> Option<HashMap<usize, Vec<Branch>>> {
+    let types = &module.section.types.entries;
+    let mut labels = vec![Label {
+        height: 0,
+        params: 0,
+        results: ty.results.len(),
+        arity: ty.results.len(),
+        target: code.2,
+        dead: false,
+        unreachable: false,
+    }];
+    let mut height = 0usize;
+    let mut branches = HashMap::new();
+    for pc in code.0..code.2 {
+        let op = module.ops.get(pc)?;
+        let unreachable = labels.last()?.unreachable;
+        let branch = |labels: &[Label], height: usize, label: usize| {
+            let label = &labels[labels.len().checked_sub(label + 1)?];
+            Some(Branch {
+                target: label.target,
+                arity: label.arity as u32,
+                drop: height.checked_sub(label.height + label.arity)? as u32,
+            })
+        };
+        match op {
+            Opcode::Block(bt, location) | Opcode::Loop(bt, location) | Opcode::If(bt, location) => {
+                if matches!(op, Opcode::If(..)) && !unreachable {
+                    height = height.checked_sub(1)?;
+                }
+                let (params, results) = bt.arity(types)?;
+                let (target, arity) = match op {
+                    Opcode::Loop(..) => (location.0, params),
+                    _ => (location.2, results),
+                };
+                labels.push(Label {
+                    height: match unreachable {
+                        true => 0,
+                        false => height.checked_sub(params)?,
+                    },
+                    params,
+                    results,
+                    arity,
+                    target,
+                    dead: unreachable,
+                    unreachable,
+                });
+                continue;
+            }
+            Opcode::Else(_) => {
+                let label = labels.last_mut()?;
+                label.unreachable = label.dead;
+                height = label.height + label.params;
+                continue;
+            }
+            Opcode::End(_) => {
+                let label = labels.pop()?;
+                height = label.height + label.results;
+                continue;
+            }
+            _ if unreachable => continue,
+            Opcode::Br(label, _) => {
+                branches.insert(pc, vec![branch(&labels, height, *label)?]);
+                labels.last_mut()?.unreachable = true;
+                continue;
+            }
+            Opcode::BrIf(label, _) => {
+                height = height.checked_sub(1)?;
+                branches.insert(pc, vec![branch(&labels, height, *label)?]);
+                continue;
+            }
+            Opcode::BrOnNull(label, _) => {
+                // 跳转时引用已经弹出，不跳转时留在栈上
+                height = height.checked_sub(1)?;
+                branches.insert(pc, vec![branch(&labels, height, *label)?]);
+                height += 1;
+                continue;
+            }
+            Opcode::BrOnNonNull(label, _) => {
+                branches.insert(pc, vec![branch(&labels, height, *label)?]);
+                height = height.checked_sub(1)?;
+                continue;
+            }
+            Opcode::BrTable(_, entries, default) => {
+                height = height.checked_sub(1)?;
+                let targets = entries
+                    .iter()
+                    .chain([default])
+                    .map(|(label, _)| branch(&labels, height, *label))
+                    .collect::<Option<_>>()?;
+                branches.insert(pc, targets);
+                labels.last_mut()?.unreachable = true;
+                continue;
+            }
+            Opcode::Unreachable | Opcode::Return => {
+                labels.last_mut()?.unreachable = true;
+                continue;
+            }
+            _ => {}
+        }
+        let Some((pops, pushes)) = stack_effect(types, funcs, op) else {
+            labels.last_mut()?.unreachable = true;
+            continue;
+        };
+        height = height.checked_sub(pops)? + pushes;
+    }
+    Some(branches)
+}
+
+/// 非控制指令从栈顶弹出和压入的值个数
+fn stack_effect(types: &[FunctionType], funcs: &[usize], op: &Opcode) -> Option<(usize, usize)> {
+    use Opcode::*;
+    Some(match op {
+        Nop => (0, 0),
+        Call(idx) => {
+            let ty = types.get(*funcs.get(*idx as usize)?)?;
+            (ty.params.len(), ty.results.len())
+        }
+        CallIndirect(idx, _) => {
+            let ty = types.get(*idx as usize)?;
+            (ty.params.len() + 1, ty.results.len())
+        }
+        CallRef(idx) => {
+            let ty = types.get(*idx as usize)?;
+            (ty.params.len() + 1, ty.results.len())
+        }
+        RefNull(_) | RefFunc(_) => (0, 1),
+        RefIsNull | RefAsNonNull => (1, 1),
+        Drop => (1, 0),
+        Select | SelectType(..) => (3, 1),
+        LocalGet(_) | GlobalGet(_) => (0, 1),
+        LocalSet(_) | GlobalSet(_) => (1, 0),
+        LocalTee(_) => (1, 1),
+        TableGet(_) => (1, 1),
+        TableSet(_) => (2, 0),
+        TableGrow(_) => (2, 1),
+        TableSize(_) => (0, 1),
+        TableInit(..) | TableCopy(..) | TableFill(_) => (3, 0),
+        ElemDrop(_) | DataDrop(_) => (0, 0),
+        I32Load(_) | I64Load(_) | F32Load(_) | F64Load(_) | I32Load8s(_) | I32Load8u(_)
+        | I32Load16s(_) | I32Load16u(_) | I64Load8s(_) | I64Load8u(_) | I64Load16s(_)
+        | I64Load16u(_) | I64Load32s(_) | I64Load32u(_) => (1, 1),
+        I32Store(_) | I64Store(_) | F32Store(_) | F64Store(_) | I32Store8(_) | I32Store16(_)
+        | I64Store8(_) | I64Store16(_) | I64Store32(_) => (2, 0),
+        MemorySize(_) => (0, 1),
+        MemoryGrow(_) => (1, 1),
+        MemoryInit(..) | MemoryCopy(..) | MemoryFill(_) => (3, 0),
+        I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_) => (0, 1),
+        op if op.operand_type().is_some() => (op.operand_count(), 1),
+        _ => return None,
+    })
+}
+
+/// 已解码的函数体及其类型，延迟解码还没有解析的函数体跳过
+fn func_bodies(module: &WasmModule) -> Vec<(&FunctionType, &FuncBody)> {
+    let section = &module.section;
+    let imported = module_funcs(module).len() - section.func.entries.len();
+    let mut bodies = vec![];
+    for (index, ty) in section.func.entries.iter().enumerate() {
+        // 实例化之后函数体从 code 段移到了 `WasmModule::func` 中
+        let body = match module.func.get(imported + index) {
+            Some(FuncKind::Local((_, body))) => body,
+            _ => match section.code.entries.get(index) {
+                Some(body) => body,
+                None => continue,
+            },
+        };
+        let Some(ty) = section.types.entries.get(*ty) else {
+            continue;
+        };
+        if body.pending || body.code.2 >= module.ops.len() {
+            continue;
+        }
+        bodies.push((ty, body));
+    }
+    bodies
+}
+
+/// 函数索引对应的类型索引
+fn module_funcs(module: &WasmModule) -> Vec<usize> {
+    let section = &module.section;
+    section
+        .import
+        .entries
+        .iter()
+        .filter_map(|ipt| match ipt.kind {
+            import::Kind::Func(ty) => Some(ty),
+            _ => None,
+        })
+        .chain(section.func.entries.iter().copied())
+        .collect()
+}
+
+/// 每个分支指令外层的块数和所在函数体最后的 end，下标为分支指令在 ops 中的位置；
+/// 标签等于块数的分支跳出函数
+fn branch_depths(
+    bodies: &[(&FunctionType, &FuncBody)],
+    ops: &[Opcode],
+) -> HashMap<usize, (usize, usize)> {
+    let mut depths = HashMap::new();
+    for (_, body) in bodies {
+        let (start, _, end) = body.code;
+        let mut depth = 0usize;
+        for (pc, op) in ops.iter().enumerate().take(end).skip(start) {
+            match op {
+                Opcode::Block(..) | Opcode::Loop(..) | Opcode::If(..) => depth += 1,
+                Opcode::End(_) => depth = depth.saturating_sub(1),
+                Opcode::Br(..)
+                | Opcode::BrIf(..)
+                | Opcode::BrTable(..)
+                | Opcode::BrOnNull(..)
+                | Opcode::BrOnNonNull(..) => {
+                    depths.insert(pc, (depth, end));
+                }
+                _ => {}
+            }
+        }
+    }
+    depths
+}
+
+/// 每个函数体中分支指令的 Branch，下标为分支指令在 ops 中的位置
+fn func_branches(
+    module: &WasmModule,
+    bodies: &[(&FunctionType, &FuncBody)],
+) -> HashMap<usize, Vec<Branch>> {
+    let funcs = module_funcs(module);
+    let mut branches = HashMap::new();
+    for (ty, body) in bodies {
+        if let Some(found) = self::branches(module, &funcs, ty, body.code) {
+            branches.extend(found);
+        }
+    }
+    branches
+}
+
+pub fn lower(module: &WasmModule) -> Code {
+    let ops = &module.ops;
+    let bodies = func_bodies(module);
+    let branches = func_branches(module, &bodies);
+    let depths = branch_depths(&bodies, ops);
+    // 函数体最后的 end 等于 return，其他块的 end 不需要执行任何操作
+    let ends = bodies
+        .iter()
+        .map(|(_, body)| body.code.2)
+        .collect::<HashSet<_>>();
+    // 跳出函数的分支跳到函数体最后的 end（`Instr::Return`），返回值从栈顶取，不需要整理栈
+    let exits = |pc: usize, label: usize| depths.get(&pc).is_some_and(|&(depth, _)| label == depth);
+    let branch = |pc: usize, i: usize, label: usize| match branches.get(&pc) {
+        Some(found) => Some(found[i]),
+        None if exits(pc, label) => Some(Branch::to(depths[&pc].1)),
+        None => None,
+    };
+    let mut code = Code::default();
+    for (pc, op) in ops.iter().enumerate() {
+        let instr = match op {
+            Opcode::Block(..) | Opcode::Loop(..) | Opcode::Else(_) | Opcode::Nop => Instr::Nop,
+            Opcode::End(_) if ends.contains(&pc) => Instr::Return,
+            Opcode::End(_) => Instr::Nop,
+            Opcode::Br(label, _) if exits(pc, *label) => Instr::Return,
+            Opcode::Br(label, _) => branch(pc, 0, *label).map_or(Instr::Op, Instr::Jump),
+            Opcode::BrIf(label, _) => branch(pc, 0, *label).map_or(Instr::Op, Instr::BrIf),
+            Opcode::BrOnNull(label, _) => branch(pc, 0, *label).map_or(Instr::Op, Instr::BrOnNull),
+            Opcode::BrOnNonNull(label, _) => {
+                branch(pc, 0, *label).map_or(Instr::Op, Instr::BrOnNonNull)
+            }
+            Opcode::BrTable(_, entries, default) => {
+                let targets = entries
+                    .iter()
+                    .chain([default])
+                    .enumerate()
+                    .map(|(i, (label, _))| branch(pc, i, *label))
+                    .collect::<Option<_>>();
+                match targets {
+                    Some(targets) => {
+                        code.tables.push(targets);
+                        Instr::BrTable(code.tables.len() - 1)
+                    }
+                    None => Instr::Op,
+                }
+            }
+            Opcode::If(_, location) => Instr::If(location.1),
+            Opcode::Return => Instr::Return,
+            Opcode::LocalGet(idx) => Instr::LocalGet(*idx),
+            Opcode::LocalSet(idx) => Instr::LocalSet(*idx),
+            Opcode::LocalTee(idx) => Instr::LocalTee(*idx),
+            Opcode::I32Const(value) => Instr::I32Const(*value),
+            _ => Instr::Op,
+        };
+        code.instrs.push(instr);
+    }
+
+    for pc in 0..code.instrs.len().saturating_sub(1) {
+        let fused = match (code.instrs[pc], &ops[pc + 1], code.instrs[pc + 1]) {
+            (Instr::LocalGet(a), _, Instr::LocalGet(b)) => Instr::LocalGet2(a, b),
+            (Instr::I32Const(c), Opcode::I32Add, _) => Instr::I32AddImm(c),
+            (Instr::I32Const(c), Opcode::I32Sub, _) => Instr::I32SubImm(c),
+            (Instr::Op, Opcode::BrIf(..), Instr::BrIf(branch))
+                if matches!(ops[pc], Opcode::I32Eqz) =>
+            {
+                Instr::BrIfEqz(branch)
+            }
+            _ => continue,
+        };
+        code.instrs[pc] = fused;
+    }
+    code
+}
+
+#[test]
+fn test_lower() {
+    use super::decoder::WasmModule;
+    use super::testing::func_bytes;
+
+    // block loop local.get 0 i32.eqz br_if 1 local.get 0 i32.const 1 i32.sub local.set 0 br 0 end end
+    let body: &[u8] = &[
+        0x02, 0x40, 0x03, 0x40, 0x20, 0x00, 0x45, 0x0d, 0x01, 0x20, 0x00, 0x41, 0x01, 0x6b, 0x21,
+        0x00, 0x0c, 0x00, 0x0b, 0x0b,
+    ];
+    let mut wasm = WasmModule::default(func_bytes(&[0x7f], &[], &[body], &[]));
+    wasm.decode().unwrap();
+    let code = lower(&wasm);
+    use Instr::*;
+    assert_eq!(
+        code.instrs,
+        [
+            Nop,
+            Nop,
+            LocalGet(0),
+            BrIfEqz(Branch::to(11)),
+            BrIf(Branch::to(11)),
+            LocalGet(0),
+            I32SubImm(1),
+            Op,
+            LocalSet(0),
+            Jump(Branch::to(2)),
+            Nop,
+            Nop,
+            Return,
+        ][..]
+    );
+}
+
+#[test]
+fn test_lower_simd_body() {
+    use super::decoder::WasmValue;
+    use super::testing::{all_engine_configs, func_bytes, invoke};
+
+    // 函数体中有 SIMD 指令时，其余分支仍然按栈高度保留和丢弃值；br 到函数标签即返回
+    // i32.const 100 block (result i32) i32.const 5 i32.const 9 local.get 0 br_if 0
+    //   drop drop i32.const 0 i8x16.splat drop i32.const 0 end i32.add
+    let drop: &[u8] = &[
+        0x41, 0xe4, 0x00, 0x02, 0x7f, 0x41, 0x05, 0x41, 0x09, 0x20, 0x00, 0x0d, 0x00, 0x1a, 0x1a,
+        0x41, 0x00, 0xfd, 0x0f, 0x1a, 0x41, 0x00, 0x0b, 0x6a,
+    ];
+    // i32.const 1 block local.get 0 i32.const 2 i32.lt_u br_if 0 i32.const 0 i8x16.splat drop end
+    // block block i32.const 43 local.get 0 br_if 2 drop end end i32.const 42 br 0
+    let exit: &[u8] = &[
+        0x41, 0x01, 0x02, 0x40, 0x20, 0x00, 0x41, 0x02, 0x49, 0x0d, 0x00, 0x41, 0x00, 0xfd, 0x0f,
+        0x1a, 0x0b, 0x02, 0x40, 0x02, 0x40, 0x41, 0x2b, 0x20, 0x00, 0x0d, 0x02, 0x1a, 0x0b, 0x0b,
+        0x41, 0x2a, 0x0c, 0x00,
+    ];
+    let buf = func_bytes(&[0x7f], &[0x7f], &[drop, exit], &[]);
+    for config in all_engine_configs() {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        let result = |wasm: &mut WasmModule, idx, arg| invoke(wasm, idx, &[WasmValue::I32(arg)]);
+        assert_eq!(result(&mut wasm, 0, 1).unwrap(), [WasmValue::I32(109)]);
+        assert_eq!(result(&mut wasm, 1, 0).unwrap(), [WasmValue::I32(42)]);
+        assert_eq!(result(&mut wasm, 1, 1).unwrap(), [WasmValue::I32(43)]);
+    }
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let code = lower(&wasm);
+    let br = wasm.ops.iter().rposition(|op| matches!(op, Opcode::Br(..)));
+    assert_eq!(code.instrs[br.unwrap()], Instr::Return);
+}
+
+#[test]
+fn test_lower_invalid_branch() {
+    use super::testing::func_module;
+    use super::trap::Trap;
+
+    // block (result i32) br 0 end：分支处栈上没有要保留的值
+    let mut wasm = func_module(&[], &[0x7f], &[&[0x02, 0x7f, 0x0c, 0x00, 0x0b]], &[]);
+    let code = lower(&wasm);
+    assert_eq!(code.instrs[1], Instr::Op);
+    let err = wasm.call(0).unwrap_err();
+    assert!(matches!(err, Trap::MalformedBody { func: 0, .. }), "{err}");
+}
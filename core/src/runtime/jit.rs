@@ -0,0 +1,887 @@
+//! Cranelift 编译后端，由 `jit` feature 开启，`RuntimeConfig::engine(Engine::Jit)` 选择
+//!
+//! 输入是 `untyped` 校验后得到的 `RawFunc`：值的类型和每条指令处的操作数栈高度都已确定。
+//! 局部变量和操作数栈的每个位置各对应一个 Cranelift 变量，与 u64 栈一样按 u64 存放，
+//! 分支按 `Branch` 搬移保留的值。不能编译的函数（以及导入函数）仍由解释器执行。
+//!
+//! 编译后的函数为 `fn(ctx, args) -> u32`，参数从 args 读取，结果写回 args，返回非 0 时
+//! trap 放在 ctx 中。内存 0 的基址和长度放在 ctx 中，访存在生成的代码中检查边界；
+//! 调用、全局变量和 trap 由宿主函数完成，调用返回后重新读取内存的基址和长度。
+//! 循环回边每 `CANCEL_CHECK_INTERVAL` 次检查一次取消。编译后的代码不计入
+//! `ExecStats::instructions`，也不能挂起或让出。
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::mem::offset_of;
+use std::sync::Arc;
+
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{
+    types, AbiParam, Block, Endianness, InstBuilder, MemFlagsData, Signature, StackSlot,
+    StackSlotData, StackSlotKind, Type, Value,
+};
+use cranelift_codegen::isa::CallConv;
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Switch, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+
+use super::decoder::{FuncKind, WasmModule, WasmValue, CANCEL_CHECK_INTERVAL};
+use super::trap::Trap;
+use super::untyped::{self, Branch, IntBin, IntCmp, IntUn, RawFunc, RawOp};
+
+type JitFn = unsafe extern "C" fn(*mut JitCtx, *mut u64) -> u32;
+
+/// 编译后的代码通过固定偏移访问前三个字段
+#[repr(C)]
+struct JitCtx {
+    mem_base: *mut u8,
+    mem_len: u64,
+    /// 循环回边减 1，减到 0 时检查取消
+    fuel: i64,
+    module: *mut WasmModule,
+    trap: Option<Trap>,
+}
+
+#[derive(Clone)]
+pub(crate) struct JitFunc {
+    code: JitFn,
+    raw: Arc<RawFunc>,
+}
+
+/// 实例中编译过的函数，None 表示不能编译，由解释器执行
+#[derive(Default)]
+pub struct JitFuncs {
+    module: Option<JITModule>,
+    funcs: HashMap<usize, Option<JitFunc>>,
+}
+
+// 编译后的代码只读；JITModule 只在持有 &mut WasmModule 时修改
+unsafe impl Send for JitFuncs {}
+unsafe impl Sync for JitFuncs {}
+
+impl Debug for JitFuncs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let compiled = self.funcs.values().filter(|func| func.is_some()).count();
+        f.debug_struct("JitFuncs")
+            .field("compiled", &compiled)
+            .field("interpreted", &(self.funcs.len() - compiled))
+            .finish()
+    }
+}
+
+impl Drop for JitFuncs {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // 实例已经不再使用，没有正在执行的代码
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+fn jit_module() -> Option<JITModule> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", "speed").ok()?;
+    let isa = cranelift_native::builder()
+        .ok()?
+        .finish(settings::Flags::new(flags))
+        .ok()?;
+    Some(JITModule::new(JITBuilder::with_isa(
+        isa,
+        default_libcall_names(),
+    )))
+}
+
+/// 取出（必要时编译）函数的本地代码，导入函数和不能编译的函数返回 None
+pub(crate) fn jit_func(module: &mut WasmModule, idx: usize) -> Option<JitFunc> {
+    if let Some(func) = module.jit.funcs.get(&idx) {
+        return func.clone();
+    }
+    let func = untyped::raw_func(module, idx).and_then(|raw| {
+        let mut jit = module.jit.module.take().or_else(jit_module)?;
+        let code = compile(module, &mut jit, &raw);
+        module.jit.module = Some(jit);
+        Some(JitFunc { code: code?, raw })
+    });
+    module.jit.funcs.insert(idx, func.clone());
+    func
+}
+
+fn memory(module: &mut WasmModule) -> (*mut u8, u64) {
+    module
+        .mem
+        .first_mut()
+        .map_or((std::ptr::null_mut(), 0), |mem| {
+            (mem.as_mut_ptr(), mem.len() as u64)
+        })
+}
+
+/// 由带类型的 `WasmModule::call` 进入：参数在 WasmValue 栈顶
+pub(crate) fn call(module: &mut WasmModule, func: JitFunc) -> Result<Vec<WasmValue>, Trap> {
+    let raw = &func.raw;
+    let sp = module.sp - raw.params;
+    let mut args = vec![0u64; raw.params.max(raw.results.len())];
+    for (i, arg) in args.iter_mut().take(raw.params).enumerate() {
+        *arg = untyped::to_bits(module.stack[sp + 1 + i]);
+    }
+    module.sp = sp;
+    let (mem_base, mem_len) = memory(module);
+    let mut ctx = JitCtx {
+        mem_base,
+        mem_len,
+        fuel: CANCEL_CHECK_INTERVAL as i64,
+        module,
+        trap: None,
+    };
+    if unsafe { (func.code)(&mut ctx, args.as_mut_ptr()) } != 0 {
+        return Err(ctx.trap.take().unwrap_or(Trap::Unreachable));
+    }
+    // 与带类型的调用一致，结果按声明的顺序排列
+    Ok(raw
+        .results
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| untyped::to_value(args[i], *ty))
+        .collect())
+}
+
+fn func_arity(module: &WasmModule, idx: usize) -> Option<(usize, usize)> {
+    let ty = match module.func.get(idx)? {
+        FuncKind::Import(ty, _) | FuncKind::Local((ty, _)) => *ty,
+    };
+    let ty = module.section.types.entries.get(ty)?;
+    Some((ty.params.len(), ty.results.len()))
+}
+
+/// 在编译后的代码中调用 idx，参数在 buf 中，结果写回 buf
+unsafe fn call_func(ctx: *mut JitCtx, idx: usize, buf: *mut u64) -> Result<(), Trap> {
+    let module = &mut *(*ctx).module;
+    if module.policy.denied.contains(&idx) {
+        return Err(Trap::Forbidden { func: idx });
+    }
+    if let Some(callee) = jit_func(module, idx) {
+        let limits = &module.config.limits;
+        if limits.max_call_depth.is_some_and(|max| module.csp >= max) {
+            return Err(Trap::StackOverflow);
+        }
+        module.csp += 1;
+        let code = (callee.code)(ctx, buf);
+        let module = &mut *(*ctx).module;
+        module.csp -= 1;
+        if code != 0 {
+            return Err((*ctx).trap.take().unwrap_or(Trap::Unreachable));
+        }
+        return Ok(());
+    }
+    // 导入函数或不能编译的函数，参数转为 WasmValue 后按原方式调用
+    let ty = match &module.func[idx] {
+        FuncKind::Import(ty, _) | FuncKind::Local((ty, _)) => *ty,
+    };
+    let params = module.section.types.entries[ty].params.clone();
+    for (i, ty) in params.iter().enumerate() {
+        module.sp += 1;
+        module.stack_check();
+        module.stack[module.sp] = untyped::to_value(*buf.add(i), *ty);
+    }
+    let res = module.call(idx)?;
+    for (i, v) in res.into_iter().enumerate() {
+        *buf.add(i) = untyped::to_bits(v);
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn jit_call(ctx: *mut JitCtx, idx: u64, buf: *mut u64) -> u32 {
+    let res = call_func(ctx, idx as usize, buf);
+    // 被调函数可能增长或替换了内存
+    let (mem_base, mem_len) = memory(&mut *(*ctx).module);
+    (*ctx).mem_base = mem_base;
+    (*ctx).mem_len = mem_len;
+    match res {
+        Ok(()) => 0,
+        Err(trap) => {
+            (*ctx).trap = Some(trap);
+            1
+        }
+    }
+}
+
+unsafe extern "C" fn jit_check(ctx: *mut JitCtx) -> u32 {
+    (*ctx).fuel = CANCEL_CHECK_INTERVAL as i64;
+    let module = &*(*ctx).module;
+    if module.config.is_cancelled() {
+        (*ctx).trap = Some(Trap::Cancelled);
+        return 1;
+    }
+    0
+}
+
+unsafe extern "C" fn jit_unreachable(ctx: *mut JitCtx) {
+    (*ctx).trap = Some(Trap::Unreachable);
+}
+
+unsafe extern "C" fn jit_out_of_bounds(ctx: *mut JitCtx, addr: u64) {
+    let size = (*ctx).mem_len as usize;
+    (*ctx).trap = Some(Trap::MemoryOutOfBounds { addr, size });
+}
+
+unsafe extern "C" fn jit_global_get(ctx: *mut JitCtx, idx: u64) -> u64 {
+    let module = &*(*ctx).module;
+    untyped::to_bits(module.global[idx as usize].get())
+}
+
+unsafe extern "C" fn jit_global_set(ctx: *mut JitCtx, idx: u64, bits: u64) {
+    let module = &mut *(*ctx).module;
+    let global = &mut module.global[idx as usize];
+    if let Some(ty) = untyped::global_type(global) {
+        global.set(untyped::to_value(bits, ty));
+    }
+}
+
+/// 指令的 (弹出个数, 压入个数)，控制指令返回 None
+fn effect(module: &WasmModule, op: &RawOp) -> Option<(usize, usize)> {
+    Some(match op {
+        RawOp::LocalGet(_) | RawOp::GlobalGet(_) | RawOp::Const(_) => (0, 1),
+        RawOp::LocalSet(_) | RawOp::GlobalSet(..) | RawOp::Drop => (1, 0),
+        RawOp::Select => (3, 1),
+        RawOp::Call(idx) => func_arity(module, *idx)?,
+        RawOp::Store(..) => (2, 0),
+        RawOp::I32Bin(_) | RawOp::I64Bin(_) | RawOp::I32Cmp(_) | RawOp::I64Cmp(_) => (2, 1),
+        RawOp::LocalTee(_)
+        | RawOp::Load(..)
+        | RawOp::I32Eqz
+        | RawOp::I64Eqz
+        | RawOp::I32Un(_)
+        | RawOp::I64Un(_)
+        | RawOp::I32WrapI64
+        | RawOp::I64ExtendI32S
+        | RawOp::I64ExtendI32U => (1, 1),
+        RawOp::Unreachable
+        | RawOp::Br(_)
+        | RawOp::BrIf(_)
+        | RawOp::BrTable(_)
+        | RawOp::BrUnless(_)
+        | RawOp::Return => return None,
+    })
+}
+
+/// 每条指令执行前的操作数栈高度（None 为不可达），以及需要单独成块的指令
+fn heights(module: &WasmModule, func: &RawFunc) -> Option<(Vec<Option<usize>>, Vec<bool>)> {
+    let len = func.code.len();
+    let mut heights = vec![None; len];
+    let mut starts = vec![false; len];
+    let target = |b: &Branch| (b.target, (b.height + b.arity) as usize);
+    let mut work = vec![(0, 0usize)];
+    while let Some((pc, height)) = work.pop() {
+        if heights.get(pc)?.is_some() {
+            continue;
+        }
+        heights[pc] = Some(height);
+        let succ = match &func.code[pc] {
+            RawOp::Unreachable | RawOp::Return => vec![],
+            RawOp::Br(b) => vec![target(b)],
+            RawOp::BrIf(b) => vec![target(b), (pc + 1, height.checked_sub(1)?)],
+            RawOp::BrTable(table) => func.tables.get(*table)?.iter().map(target).collect(),
+            RawOp::BrUnless(to) => {
+                let height = height.checked_sub(1)?;
+                vec![(*to, height), (pc + 1, height)]
+            }
+            op => {
+                let (pop, push) = effect(module, op)?;
+                work.push((pc + 1, height.checked_sub(pop)? + push));
+                continue;
+            }
+        };
+        for (to, _) in &succ {
+            *starts.get_mut(*to)? = true;
+        }
+        work.extend(succ);
+    }
+    Some((heights, starts))
+}
+
+struct Translator<'a> {
+    b: FunctionBuilder<'a>,
+    call_conv: CallConv,
+    ptr: Type,
+    ctx: Value,
+    args: Value,
+    locals: Vec<Variable>,
+    stack: Vec<Variable>,
+    blocks: Vec<Option<Block>>,
+    /// 返回 1 的块，trap 已经写入 ctx
+    trap_exit: Block,
+    /// 调用的参数和结果
+    call_buf: Option<StackSlot>,
+}
+
+fn flags() -> MemFlagsData {
+    MemFlagsData::new().with_endianness(Endianness::Little)
+}
+
+impl Translator<'_> {
+    fn helper(
+        &mut self,
+        f: *const u8,
+        params: &[Type],
+        ret: Option<Type>,
+        args: &[Value],
+    ) -> Option<Value> {
+        let mut sig = Signature::new(self.call_conv);
+        sig.params
+            .extend(params.iter().map(|ty| AbiParam::new(*ty)));
+        sig.returns.extend(ret.map(AbiParam::new));
+        let sig = self.b.import_signature(sig);
+        let callee = self.b.ins().iconst(self.ptr, f as i64);
+        let call = self.b.ins().call_indirect(sig, callee, args);
+        self.b.inst_results(call).first().copied()
+    }
+
+    fn get(&mut self, slot: usize) -> Value {
+        self.b.use_var(self.stack[slot])
+    }
+
+    fn set(&mut self, slot: usize, v: Value) {
+        self.b.def_var(self.stack[slot], v);
+    }
+
+    fn i32(&mut self, v: Value) -> Value {
+        self.b.ins().ireduce(types::I32, v)
+    }
+
+    fn extend(&mut self, v: Value) -> Value {
+        self.b.ins().uextend(types::I64, v)
+    }
+
+    /// 宿主函数返回非 0 时跳到 trap_exit
+    fn check_status(&mut self, status: Value) {
+        let cont = self.b.create_block();
+        self.b.ins().brif(status, self.trap_exit, &[], cont, &[]);
+        self.b.switch_to_block(cont);
+    }
+
+    /// 循环回边：fuel 减到 0 时检查取消
+    fn back_edge(&mut self) {
+        let offset = offset_of!(JitCtx, fuel) as i32;
+        let fuel = self
+            .b
+            .ins()
+            .load(types::I64, MemFlagsData::trusted(), self.ctx, offset);
+        let fuel = self.b.ins().iadd_imm_s(fuel, -1);
+        self.b
+            .ins()
+            .store(MemFlagsData::trusted(), fuel, self.ctx, offset);
+        let (check, cont) = (self.b.create_block(), self.b.create_block());
+        self.b.ins().brif(fuel, cont, &[], check, &[]);
+        self.b.switch_to_block(check);
+        let ptr = self.ptr;
+        let status = self.helper(
+            jit_check as *const u8,
+            &[ptr],
+            Some(types::I32),
+            &[self.ctx],
+        );
+        self.b
+            .ins()
+            .brif(status.unwrap(), self.trap_exit, &[], cont, &[]);
+        self.b.switch_to_block(cont);
+    }
+
+    /// 保留栈顶 arity 个值放到 branch.height 处，跳到目标
+    fn branch(&mut self, branch: &Branch, sp: usize, back: bool) -> Option<()> {
+        if back {
+            self.back_edge();
+        }
+        let arity = branch.arity as usize;
+        let values = (sp.checked_sub(arity)?..sp)
+            .map(|i| self.get(i))
+            .collect::<Vec<_>>();
+        for (i, v) in values.into_iter().enumerate() {
+            self.set(branch.height as usize + i, v);
+        }
+        let target = (*self.blocks.get(branch.target)?)?;
+        self.b.ins().jump(target, &[]);
+        Some(())
+    }
+
+    /// 检查 [addr, addr + bytes) 在内存 0 内，返回本地地址
+    fn address(&mut self, base: Value, offset: u32, bytes: u8) -> Value {
+        let addr = self.b.ins().iadd_imm_s(base, offset as i64);
+        let end = self.b.ins().iadd_imm_s(addr, bytes as i64);
+        let len_offset = offset_of!(JitCtx, mem_len) as i32;
+        let len = self
+            .b
+            .ins()
+            .load(types::I64, MemFlagsData::trusted(), self.ctx, len_offset);
+        let oob = self.b.ins().icmp(IntCC::UnsignedGreaterThan, end, len);
+        let (trap, ok) = (self.b.create_block(), self.b.create_block());
+        self.b.ins().brif(oob, trap, &[], ok, &[]);
+        self.b.switch_to_block(trap);
+        let ptr = self.ptr;
+        self.helper(
+            jit_out_of_bounds as *const u8,
+            &[ptr, types::I64],
+            None,
+            &[self.ctx, addr],
+        );
+        self.b.ins().jump(self.trap_exit, &[]);
+        self.b.switch_to_block(ok);
+        let base_offset = offset_of!(JitCtx, mem_base) as i32;
+        let mem = self
+            .b
+            .ins()
+            .load(self.ptr, MemFlagsData::trusted(), self.ctx, base_offset);
+        self.b.ins().iadd(mem, addr)
+    }
+
+    fn int_bin(&mut self, op: IntBin, a: Value, b: Value) -> Value {
+        let ins = self.b.ins();
+        match op {
+            IntBin::Add => ins.iadd(a, b),
+            IntBin::Sub => ins.isub(a, b),
+            IntBin::Mul => ins.imul(a, b),
+            IntBin::And => ins.band(a, b),
+            IntBin::Or => ins.bor(a, b),
+            IntBin::Xor => ins.bxor(a, b),
+            IntBin::Shl => ins.ishl(a, b),
+            IntBin::ShrS => ins.sshr(a, b),
+            IntBin::ShrU => ins.ushr(a, b),
+            IntBin::Rotl => ins.rotl(a, b),
+            IntBin::Rotr => ins.rotr(a, b),
+        }
+    }
+
+    fn int_cmp(&mut self, op: IntCmp, a: Value, b: Value) -> Value {
+        let cc = match op {
+            IntCmp::Eq => IntCC::Equal,
+            IntCmp::Ne => IntCC::NotEqual,
+            IntCmp::LtS => IntCC::SignedLessThan,
+            IntCmp::LtU => IntCC::UnsignedLessThan,
+            IntCmp::GtS => IntCC::SignedGreaterThan,
+            IntCmp::GtU => IntCC::UnsignedGreaterThan,
+            IntCmp::LeS => IntCC::SignedLessThanOrEqual,
+            IntCmp::LeU => IntCC::UnsignedLessThanOrEqual,
+            IntCmp::GeS => IntCC::SignedGreaterThanOrEqual,
+            IntCmp::GeU => IntCC::UnsignedGreaterThanOrEqual,
+        };
+        let r = self.b.ins().icmp(cc, a, b);
+        self.extend(r)
+    }
+
+    /// 有符号扩展：把 v 的低位截断为 from 后扩展回 v 的类型
+    fn sign_extend(&mut self, v: Value, from: Type, to: Type) -> Value {
+        let narrow = self.b.ins().ireduce(from, v);
+        self.b.ins().sextend(to, narrow)
+    }
+
+    fn int_un(&mut self, op: IntUn, a: Value, ty: Type) -> Value {
+        match op {
+            IntUn::Clz => self.b.ins().clz(a),
+            IntUn::Ctz => self.b.ins().ctz(a),
+            IntUn::Popcnt => self.b.ins().popcnt(a),
+            IntUn::Extend8S => self.sign_extend(a, types::I8, ty),
+            IntUn::Extend16S => self.sign_extend(a, types::I16, ty),
+            IntUn::Extend32S if ty == types::I64 => self.sign_extend(a, types::I32, ty),
+            // 与 u64 栈一致，i32 上按 16 位处理（校验时不会出现）
+            IntUn::Extend32S => self.sign_extend(a, types::I16, ty),
+        }
+    }
+
+    /// 翻译 pc 处的指令，sp 为执行前的操作数栈高度
+    fn op(&mut self, module: &WasmModule, func: &RawFunc, pc: usize, sp: usize) -> Option<()> {
+        let ins_i32 = |t: &mut Self, slot: usize| {
+            let v = t.get(slot);
+            t.i32(v)
+        };
+        match func.code[pc] {
+            RawOp::Unreachable => {
+                let ptr = self.ptr;
+                self.helper(jit_unreachable as *const u8, &[ptr], None, &[self.ctx]);
+                self.b.ins().jump(self.trap_exit, &[]);
+            }
+            RawOp::LocalGet(idx) => {
+                let v = self.b.use_var(*self.locals.get(idx as usize)?);
+                self.set(sp, v);
+            }
+            RawOp::LocalSet(idx) => {
+                let v = self.get(sp - 1);
+                self.b.def_var(*self.locals.get(idx as usize)?, v);
+            }
+            RawOp::LocalTee(idx) => {
+                let v = self.get(sp - 1);
+                self.b.def_var(*self.locals.get(idx as usize)?, v);
+            }
+            RawOp::GlobalGet(idx) => {
+                let ptr = self.ptr;
+                let idx = self.b.ins().iconst(types::I64, idx as i64);
+                let args = [self.ctx, idx];
+                let f = jit_global_get as *const u8;
+                let v = self.helper(f, &[ptr, types::I64], Some(types::I64), &args)?;
+                self.set(sp, v);
+            }
+            RawOp::GlobalSet(idx, _) => {
+                let ptr = self.ptr;
+                let idx = self.b.ins().iconst(types::I64, idx as i64);
+                let v = self.get(sp - 1);
+                let f = jit_global_set as *const u8;
+                self.helper(f, &[ptr, types::I64, types::I64], None, &[self.ctx, idx, v]);
+            }
+            RawOp::Const(v) => {
+                let v = self.b.ins().iconst(types::I64, v as i64);
+                self.set(sp, v);
+            }
+            RawOp::Drop => {}
+            RawOp::Select => {
+                let c = ins_i32(self, sp - 1);
+                let (a, b) = (self.get(sp - 3), self.get(sp - 2));
+                let v = self.b.ins().select(c, a, b);
+                self.set(sp - 3, v);
+            }
+            RawOp::Br(b) => self.branch(&b, sp, b.target <= pc)?,
+            RawOp::BrIf(b) => {
+                let c = ins_i32(self, sp - 1);
+                let taken = self.b.create_block();
+                let next = (*self.blocks.get(pc + 1)?)?;
+                self.b.ins().brif(c, taken, &[], next, &[]);
+                self.b.switch_to_block(taken);
+                self.branch(&b, sp - 1, b.target <= pc)?;
+            }
+            RawOp::BrTable(table) => {
+                let index = ins_i32(self, sp - 1);
+                let branches = func.tables.get(table)?;
+                let (last, entries) = branches.split_last()?;
+                let mut switch = Switch::new();
+                let mut targets = vec![];
+                for (i, b) in entries.iter().enumerate() {
+                    let block = self.b.create_block();
+                    switch.set_entry(i as u128, block);
+                    targets.push((block, b));
+                }
+                let default = self.b.create_block();
+                targets.push((default, last));
+                switch.emit(&mut self.b, index, default);
+                for (block, b) in targets {
+                    self.b.switch_to_block(block);
+                    self.branch(b, sp - 1, b.target <= pc)?;
+                }
+            }
+            RawOp::BrUnless(target) => {
+                let c = ins_i32(self, sp - 1);
+                let next = (*self.blocks.get(pc + 1)?)?;
+                let target = (*self.blocks.get(target)?)?;
+                self.b.ins().brif(c, next, &[], target, &[]);
+            }
+            RawOp::Return => {
+                let n = func.results.len();
+                for i in 0..n {
+                    let v = self.get(sp - n + i);
+                    self.b.ins().store(flags(), v, self.args, (i * 8) as i32);
+                }
+                let ok = self.b.ins().iconst(types::I32, 0);
+                self.b.ins().return_(&[ok]);
+            }
+            RawOp::Call(idx) => {
+                let (params, results) = func_arity(module, idx)?;
+                let slot = self.call_buf?;
+                let ptr = self.ptr;
+                for i in 0..params {
+                    let v = self.get(sp - params + i);
+                    self.b.ins().stack_store(ptr, v, slot, (i * 8) as i32);
+                }
+                let buf = self.b.ins().stack_addr(ptr, slot, 0);
+                let idx = self.b.ins().iconst(types::I64, idx as i64);
+                let args = [self.ctx, idx, buf];
+                let f = jit_call as *const u8;
+                let status = self.helper(f, &[ptr, types::I64, ptr], Some(types::I32), &args)?;
+                self.check_status(status);
+                for i in 0..results {
+                    let v = self
+                        .b
+                        .ins()
+                        .stack_load(ptr, types::I64, slot, (i * 8) as i32);
+                    self.set(sp - params + i, v);
+                }
+            }
+            RawOp::Load(bytes, signed, wide, memarg) => {
+                (memarg.memory == 0).then_some(())?;
+                let base = self.get(sp - 1);
+                let p = self.address(base, memarg.offset, bytes);
+                let ins = self.b.ins();
+                let v = match (bytes, signed) {
+                    (1, false) => ins.uload8(types::I64, flags(), p, 0),
+                    (1, true) => ins.sload8(types::I64, flags(), p, 0),
+                    (2, false) => ins.uload16(types::I64, flags(), p, 0),
+                    (2, true) => ins.sload16(types::I64, flags(), p, 0),
+                    (4, false) => ins.uload32(flags(), p, 0),
+                    (4, true) => ins.sload32(flags(), p, 0),
+                    _ => ins.load(types::I64, flags(), p, 0),
+                };
+                let v = if signed && !wide {
+                    let v = self.i32(v);
+                    self.extend(v)
+                } else {
+                    v
+                };
+                self.set(sp - 1, v);
+            }
+            RawOp::Store(bytes, memarg) => {
+                (memarg.memory == 0).then_some(())?;
+                let (base, v) = (self.get(sp - 2), self.get(sp - 1));
+                let p = self.address(base, memarg.offset, bytes);
+                let ins = self.b.ins();
+                match bytes {
+                    1 => ins.istore8(flags(), v, p, 0),
+                    2 => ins.istore16(flags(), v, p, 0),
+                    4 => ins.istore32(flags(), v, p, 0),
+                    _ => ins.store(flags(), v, p, 0),
+                };
+            }
+            RawOp::I32Eqz => {
+                let a = ins_i32(self, sp - 1);
+                let r = self.b.ins().icmp_imm_s(IntCC::Equal, a, 0);
+                let r = self.extend(r);
+                self.set(sp - 1, r);
+            }
+            RawOp::I64Eqz => {
+                let a = self.get(sp - 1);
+                let r = self.b.ins().icmp_imm_s(IntCC::Equal, a, 0);
+                let r = self.extend(r);
+                self.set(sp - 1, r);
+            }
+            RawOp::I32Bin(op) => {
+                let (a, b) = (ins_i32(self, sp - 2), ins_i32(self, sp - 1));
+                let r = self.int_bin(op, a, b);
+                let r = self.extend(r);
+                self.set(sp - 2, r);
+            }
+            RawOp::I64Bin(op) => {
+                let (a, b) = (self.get(sp - 2), self.get(sp - 1));
+                let r = self.int_bin(op, a, b);
+                self.set(sp - 2, r);
+            }
+            RawOp::I32Cmp(op) => {
+                let (a, b) = (ins_i32(self, sp - 2), ins_i32(self, sp - 1));
+                let r = self.int_cmp(op, a, b);
+                self.set(sp - 2, r);
+            }
+            RawOp::I64Cmp(op) => {
+                let (a, b) = (self.get(sp - 2), self.get(sp - 1));
+                let r = self.int_cmp(op, a, b);
+                self.set(sp - 2, r);
+            }
+            RawOp::I32Un(op) => {
+                let a = ins_i32(self, sp - 1);
+                let r = self.int_un(op, a, types::I32);
+                let r = self.extend(r);
+                self.set(sp - 1, r);
+            }
+            RawOp::I64Un(op) => {
+                let a = self.get(sp - 1);
+                let r = self.int_un(op, a, types::I64);
+                self.set(sp - 1, r);
+            }
+            RawOp::I32WrapI64 => {
+                let a = ins_i32(self, sp - 1);
+                let r = self.extend(a);
+                self.set(sp - 1, r);
+            }
+            RawOp::I64ExtendI32S => {
+                let a = ins_i32(self, sp - 1);
+                let r = self.b.ins().sextend(types::I64, a);
+                self.set(sp - 1, r);
+            }
+            RawOp::I64ExtendI32U => {}
+        }
+        Some(())
+    }
+}
+
+fn is_terminator(op: &RawOp) -> bool {
+    matches!(
+        op,
+        RawOp::Unreachable
+            | RawOp::Br(_)
+            | RawOp::BrIf(_)
+            | RawOp::BrTable(_)
+            | RawOp::BrUnless(_)
+            | RawOp::Return
+    )
+}
+
+fn compile(module: &WasmModule, jit: &mut JITModule, func: &RawFunc) -> Option<JitFn> {
+    let (heights, starts) = heights(module, func)?;
+    let ptr = jit.target_config().pointer_type();
+    // 访存时把 u64 地址直接加到基址上
+    (ptr == types::I64).then_some(())?;
+    let call_conv = jit.isa().default_call_conv();
+
+    let mut ctx = jit.make_context();
+    ctx.func.signature.params = vec![AbiParam::new(ptr), AbiParam::new(ptr)];
+    ctx.func.signature.returns = vec![AbiParam::new(types::I32)];
+    let mut builder_ctx = FunctionBuilderContext::new();
+    let mut b = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+
+    let entry = b.create_block();
+    b.append_block_params_for_function_params(entry);
+    b.switch_to_block(entry);
+    let (ctx_ptr, args) = (b.block_params(entry)[0], b.block_params(entry)[1]);
+    let zero = b.ins().iconst(types::I64, 0);
+    let locals = (0..func.locals.len())
+        .map(|i| {
+            let var = b.declare_var(types::I64);
+            let v = if i < func.params {
+                b.ins().load(types::I64, flags(), args, (i * 8) as i32)
+            } else {
+                zero
+            };
+            b.def_var(var, v);
+            var
+        })
+        .collect();
+    let stack = (0..=func.max_height)
+        .map(|_| {
+            let var = b.declare_var(types::I64);
+            b.def_var(var, zero);
+            var
+        })
+        .collect();
+    let call_slots = func
+        .code
+        .iter()
+        .filter_map(|op| match op {
+            RawOp::Call(idx) => func_arity(module, *idx).map(|(p, r)| p.max(r)),
+            _ => None,
+        })
+        .max();
+    let call_buf = call_slots.map(|n| {
+        let data = StackSlotData::new(StackSlotKind::ExplicitSlot, (n.max(1) * 8) as u32, 3);
+        b.create_sized_stack_slot(data)
+    });
+    let blocks = (0..func.code.len())
+        .map(|pc| (starts[pc] && heights[pc].is_some()).then(|| b.create_block()))
+        .collect();
+    let trap_exit = b.create_block();
+
+    let mut t = Translator {
+        b,
+        call_conv,
+        ptr,
+        ctx: ctx_ptr,
+        args,
+        locals,
+        stack,
+        blocks,
+        trap_exit,
+        call_buf,
+    };
+    let mut terminated = false;
+    for (pc, height) in heights.iter().enumerate() {
+        let Some(sp) = *height else {
+            continue;
+        };
+        if let Some(block) = t.blocks[pc] {
+            if !terminated {
+                t.b.ins().jump(block, &[]);
+            }
+            t.b.switch_to_block(block);
+        } else if terminated {
+            continue;
+        }
+        t.op(module, func, pc, sp)?;
+        terminated = is_terminator(&func.code[pc]);
+    }
+    // 校验保证函数以 return 结束，这里只是保证每个块都有终结指令
+    if !terminated {
+        t.b.ins().jump(t.trap_exit, &[]);
+    }
+    t.b.switch_to_block(t.trap_exit);
+    let failed = t.b.ins().iconst(types::I32, 1);
+    t.b.ins().return_(&[failed]);
+    t.b.seal_all_blocks();
+    t.b.finalize(jit.isa().frontend_config());
+
+    let id = jit.declare_anonymous_function(&ctx.func.signature).ok()?;
+    jit.define_function(id, &mut ctx).ok()?;
+    jit.clear_context(&mut ctx);
+    jit.finalize_definitions().ok()?;
+    let code = jit.get_finalized_function(id);
+    Some(unsafe { std::mem::transmute::<*const u8, JitFn>(code) })
+}
+
+#[test]
+fn test_jit() {
+    use super::config::{Engine, RuntimeConfig};
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (memory 1)
+          (global $count (mut i64) (i64.const 0))
+          (func $fib (export "fib") (param i32) (result i32)
+            (if (result i32) (i32.lt_s (local.get 0) (i32.const 2))
+              (then (local.get 0))
+              (else (i32.add (call $fib (i32.sub (local.get 0) (i32.const 1)))
+                             (call $fib (i32.sub (local.get 0) (i32.const 2)))))))
+          (func (export "sum") (param i32) (result i64) (local i64)
+            (block
+              (loop
+                (br_if 1 (i32.eqz (local.get 0)))
+                (local.set 1 (i64.add (local.get 1) (i64.extend_i32_u (local.get 0))))
+                (global.set $count (i64.add (global.get $count) (i64.const 1)))
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br 0)))
+            (local.get 1))
+          (func (export "pick") (param i32) (result i32)
+            (block (block (block
+              (br_table 0 1 2 (local.get 0)))
+              (return (i32.const 10)))
+              (return (select (i32.const 20) (i32.const 21) (local.get 0))))
+            (i32.const 30))
+          (func (export "mem") (param i32) (result i64)
+            (i32.store8 (local.get 0) (i32.mul (local.get 0) (i32.const 3)))
+            (i64.add (i64.load8_s (local.get 0)) (i64.load16_s (local.get 0))))
+          (func (export "count") (result i64) (global.get $count))
+          (func (export "float") (param i32) (result i32)
+            (drop (f32.add (f32.const 1.5) (f32.const 0.5)))
+            (call $fib (local.get 0))))"#,
+    )
+    .unwrap();
+
+    let mut results = vec![];
+    for engine in [Engine::Match, Engine::Jit] {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default().engine(engine);
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        let mut call = |name: &str, arg: Option<i32>| {
+            let args = arg.map(WasmValue::I32).into_iter().collect::<Vec<_>>();
+            wasm.invoke(name, &args).map_err(|err| err.to_string())
+        };
+        let outputs = [
+            call("fib", Some(20)),
+            call("sum", Some(1000)),
+            call("count", None),
+            call("pick", Some(0)),
+            call("pick", Some(1)),
+            call("pick", Some(7)),
+            call("mem", Some(100)),
+            call("mem", Some(65535)),
+            call("float", Some(10)),
+        ];
+        if engine == Engine::Jit {
+            let compiled = |wasm: &WasmModule, name: &str| match wasm.exports[name] {
+                super::section::export::ExportKind::Func(idx) => wasm.jit.funcs[&idx].is_some(),
+                _ => false,
+            };
+            assert!(compiled(&wasm, "fib") && compiled(&wasm, "sum"));
+            assert!(!compiled(&wasm, "float"));
+        }
+        results.push(outputs);
+    }
+    assert_eq!(results[0][0], Ok(vec![WasmValue::I32(6765)]));
+    assert_eq!(results[0][1], Ok(vec![WasmValue::I64(500500)]));
+    assert_eq!(results[0][5], Ok(vec![WasmValue::I32(30)]));
+    // 300 截断为 44，高字节为 0
+    assert_eq!(results[0][6], Ok(vec![WasmValue::I64(88)]));
+    assert!(results[0][7].is_err());
+    assert_eq!(results[0], results[1]);
+}
@@ -0,0 +1,422 @@
+//! 实例的线性内存，按 `RuntimeConfig::memory_backend` 选择存放方式
+
+use std::cell::UnsafeCell;
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use anyhow::{ensure, Context};
+
+use super::constants::PAGE_SIZE;
+use super::guard::GuardedMemory;
+use super::section::typings::Limit;
+
+/// 32 位内存最多 65536 页（4 GiB）
+pub const MAX_PAGES: u32 = 0x10000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryBackend {
+    /// 普通堆内存，每次访问检查边界
+    #[default]
+    Heap,
+    /// 预留地址空间并用 guard page 保护，访问时省略边界检查（64 位 Linux）
+    GuardPages,
+}
+
+/// 按页开放的线性内存，增长时检查最大页数
+#[derive(Debug, Clone)]
+pub struct LinearMemory {
+    storage: Storage,
+    /// 最大页数：声明的最大页数、宿主内存的最大页数和 `Limits` 中最小的一个
+    maximum: u32,
+}
+
+#[derive(Debug)]
+pub enum Storage {
+    Heap(Vec<u8>),
+    Guarded(GuardedMemory),
+    /// 导入的宿主内存，见 `Memory`
+    Shared(Memory),
+}
+
+impl LinearMemory {
+    /// 开放 pages 页，最多增长到 maximum 页
+    pub fn new(backend: MemoryBackend, pages: u32, maximum: u32) -> anyhow::Result<Self> {
+        ensure!(
+            pages <= maximum,
+            "memory of {pages} pages exceeds maximum {maximum}"
+        );
+        let len = pages as usize * PAGE_SIZE;
+        let storage = match backend {
+            // 按需分配，不预留到最大页数
+            MemoryBackend::Heap => Storage::Heap(vec![0; len]),
+            MemoryBackend::GuardPages => Storage::Guarded(
+                GuardedMemory::new(len, maximum as usize * PAGE_SIZE)
+                    .context("failed to reserve guarded memory")?,
+            ),
+        };
+        Ok(Self { storage, maximum })
+    }
+
+    /// 导入宿主内存，maximum 为模块一侧的限制，与宿主内存的最大页数取较小的一个
+    pub fn shared(mem: Memory, maximum: u32) -> Self {
+        let maximum = maximum.min(mem.maximum());
+        Self {
+            storage: Storage::Shared(mem),
+            maximum,
+        }
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    /// 当前页数
+    pub fn pages(&self) -> u32 {
+        (self.len() / PAGE_SIZE) as u32
+    }
+
+    pub fn maximum(&self) -> u32 {
+        self.maximum
+    }
+
+    /// 增加 delta 页，新增的部分为 0，返回增长前的页数；
+    /// 超出最大页数或分配失败时返回 None，内存不变
+    pub fn grow(&mut self, delta: u32) -> Option<u32> {
+        let old = self.pages();
+        let new = old.checked_add(delta).filter(|&new| new <= self.maximum)?;
+        let len = new as usize * PAGE_SIZE;
+        match &mut self.storage {
+            Storage::Heap(buf) => {
+                buf.try_reserve_exact(len - buf.len()).ok()?;
+                buf.resize(len, 0);
+            }
+            Storage::Guarded(mem) => mem.resize(len).ok()?,
+            Storage::Shared(mem) => {
+                mem.get_mut().grow(delta)?;
+            }
+        }
+        Some(old)
+    }
+
+    /// 读取 addr 开始的 N 个字节，越界时返回 None
+    #[inline]
+    pub fn load<const N: usize>(&self, addr: u64) -> Option<[u8; N]> {
+        match &self.storage {
+            Storage::Heap(buf) => {
+                let start = usize::try_from(addr).ok()?;
+                buf.get(start..start.checked_add(N)?)?.try_into().ok()
+            }
+            Storage::Guarded(mem) => mem.load(addr),
+            Storage::Shared(mem) => mem.get().load(addr),
+        }
+    }
+
+    /// 把 bytes 写到 addr，越界时返回 false，内存不变
+    #[inline]
+    pub fn store(&mut self, addr: u64, bytes: &[u8]) -> bool {
+        match &mut self.storage {
+            Storage::Heap(buf) => {
+                let dst = usize::try_from(addr).ok().and_then(|start| {
+                    let end = start.checked_add(bytes.len())?;
+                    buf.get_mut(start..end)
+                });
+                dst.map(|dst| dst.copy_from_slice(bytes)).is_some()
+            }
+            Storage::Guarded(mem) => mem.store(addr, bytes),
+            Storage::Shared(mem) => mem.get_mut().store(addr, bytes),
+        }
+    }
+}
+
+impl Default for LinearMemory {
+    fn default() -> Self {
+        Self::from(vec![])
+    }
+}
+
+impl Clone for Storage {
+    /// guard page 内存无法再预留时复制为堆内存；宿主内存不复制，仍指向同一个 `Memory`
+    fn clone(&self) -> Self {
+        match self {
+            Self::Heap(buf) => Self::Heap(buf.clone()),
+            Self::Guarded(mem) => match GuardedMemory::new(mem.len(), mem.capacity()) {
+                Ok(mut copy) => {
+                    copy.as_mut_slice().copy_from_slice(mem.as_slice());
+                    Self::Guarded(copy)
+                }
+                Err(_) => Self::Heap(mem.as_slice().to_vec()),
+            },
+            Self::Shared(mem) => Self::Shared(mem.clone()),
+        }
+    }
+}
+
+/// 没有最大页数限制的堆内存
+impl From<Vec<u8>> for LinearMemory {
+    fn from(buf: Vec<u8>) -> Self {
+        Self {
+            storage: Storage::Heap(buf),
+            maximum: MAX_PAGES,
+        }
+    }
+}
+
+impl Deref for LinearMemory {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match &self.storage {
+            Storage::Heap(buf) => buf,
+            Storage::Guarded(mem) => mem.as_slice(),
+            Storage::Shared(mem) => mem.get(),
+        }
+    }
+}
+
+impl DerefMut for LinearMemory {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.storage {
+            Storage::Heap(buf) => buf,
+            Storage::Guarded(mem) => mem.as_mut_slice(),
+            Storage::Shared(mem) => mem.get_mut(),
+        }
+    }
+}
+
+/// 宿主创建的线性内存，可以放进 `Linker` 被多个实例导入，所有实例读写同一份字节
+///
+/// 与 wasm 的共享内存一样不加锁：不同线程上同时执行导入同一个 `Memory` 的实例时，
+/// 由宿主保证它们不会同时访问这块内存。
+#[derive(Clone)]
+pub struct Memory {
+    inner: Arc<SharedCell>,
+}
+
+struct SharedCell(UnsafeCell<LinearMemory>);
+
+// 访问约束见 `Memory` 的说明
+unsafe impl Send for SharedCell {}
+unsafe impl Sync for SharedCell {}
+
+impl Memory {
+    /// 创建 limits.minimum 页的内存，没有最大页数时最多 65536 页
+    pub fn new(limits: Limit) -> anyhow::Result<Memory> {
+        let maximum = match limits.flag & 0x01 {
+            0 => MAX_PAGES,
+            _ => limits.maximum,
+        };
+        ensure!(
+            maximum <= MAX_PAGES,
+            "memory maximum {maximum} exceeds 65536 pages"
+        );
+        let mem = LinearMemory::new(MemoryBackend::Heap, limits.minimum, maximum)?;
+        Ok(Memory {
+            inner: Arc::new(SharedCell(UnsafeCell::new(mem))),
+        })
+    }
+
+    pub fn maximum(&self) -> u32 {
+        self.get().maximum()
+    }
+
+    /// 当前页数
+    pub fn size(&self) -> u32 {
+        self.get().pages()
+    }
+
+    /// 增加 delta 页，返回增长前的页数
+    pub fn grow(&self, delta: u32) -> anyhow::Result<u32> {
+        let old = self.size();
+        self.get_mut().grow(delta).with_context(|| {
+            format!(
+                "memory grow failed: {old} + {delta} pages exceeds maximum {}",
+                self.maximum()
+            )
+        })
+    }
+
+    pub fn read(&self, offset: usize, buf: &mut [u8]) -> anyhow::Result<()> {
+        let data = self.get();
+        let bytes = offset
+            .checked_add(buf.len())
+            .and_then(|end| data.get(offset..end))
+            .with_context(|| {
+                format!(
+                    "memory read out of bounds: offset = {offset}, len = {}, size = {}",
+                    buf.len(),
+                    data.len()
+                )
+            })?;
+        buf.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    pub fn write(&self, offset: usize, buf: &[u8]) -> anyhow::Result<()> {
+        let data = self.get_mut();
+        let len = data.len();
+        let bytes = offset
+            .checked_add(buf.len())
+            .and_then(|end| data.get_mut(offset..end))
+            .with_context(|| {
+                format!(
+                    "memory write out of bounds: offset = {offset}, len = {}, size = {len}",
+                    buf.len(),
+                )
+            })?;
+        bytes.copy_from_slice(buf);
+        Ok(())
+    }
+
+    /// 两个 Memory 是否是同一块内存
+    pub fn same(&self, other: &Memory) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    fn get(&self) -> &LinearMemory {
+        unsafe { &*self.inner.0.get() }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn get_mut(&self) -> &mut LinearMemory {
+        unsafe { &mut *self.inner.0.get() }
+    }
+}
+
+impl Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Memory")
+            .field("size", &self.size())
+            .field("maximum", &self.maximum())
+            .finish()
+    }
+}
+
+#[test]
+fn test_guard_pages() {
+    use super::config::RuntimeConfig;
+    use super::decoder::WasmModule;
+    use super::decoder::WasmValue;
+    use super::testing::{func_bytes, invoke};
+    use super::trap::Trap;
+
+    if cfg!(not(all(
+        target_os = "linux",
+        target_env = "gnu",
+        target_pointer_width = "64"
+    ))) {
+        return;
+    }
+
+    // func 0: i32.load (local.get 0)；func 1: i32.store (local.get 0, 42)；func 2: memory.grow 1
+    let bodies: &[&[u8]] = &[
+        &[0x20, 0x00, 0x28, 0x02, 0x00],
+        &[0x20, 0x00, 0x41, 0x2a, 0x36, 0x02, 0x00, 0x20, 0x00],
+        &[0x41, 0x01, 0x40, 0x00],
+    ];
+    let memory = (5, vec![0x01, 0x01, 0x01, 0x03]); // 1 页，最多 3 页
+    let mut wasm = WasmModule::default(func_bytes(&[0x7f], &[0x7f], bodies, &[memory]));
+    wasm.config = RuntimeConfig::default().memory_backend(MemoryBackend::GuardPages);
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    assert!(matches!(wasm.mem[0].storage(), Storage::Guarded(_)));
+
+    let oob = |addr| Trap::MemoryOutOfBounds { addr, size: 65536 };
+    let i32 = WasmValue::I32;
+    assert_eq!(invoke(&mut wasm, 1, &[i32(65532)]).unwrap(), [i32(65532)]);
+    assert_eq!(invoke(&mut wasm, 0, &[i32(65532)]).unwrap(), [i32(42)]);
+    // 跨越末尾和远超末尾的访问都由 guard page 报告
+    assert_eq!(invoke(&mut wasm, 0, &[i32(65534)]).unwrap_err(), oob(65534));
+    assert_eq!(invoke(&mut wasm, 1, &[i32(65533)]).unwrap_err(), oob(65533));
+    assert_eq!(
+        invoke(&mut wasm, 0, &[i32(-4)]).unwrap_err(),
+        oob(0xffff_fffc)
+    );
+    assert_eq!(&wasm.mem[0][65532..], [42, 0, 0, 0]);
+
+    // 增长后新的页可以访问，内容为 0，原来越界的写入没有留下
+    assert_eq!(invoke(&mut wasm, 2, &[i32(0)]).unwrap(), [i32(1)]);
+    assert_eq!(invoke(&mut wasm, 0, &[i32(65536)]).unwrap(), [i32(0)]);
+    assert_eq!(invoke(&mut wasm, 1, &[i32(131068)]).unwrap(), [i32(131068)]);
+    let size = 131072;
+    assert_eq!(
+        invoke(&mut wasm, 0, &[i32(131070)]).unwrap_err(),
+        Trap::MemoryOutOfBounds { addr: 131070, size }
+    );
+}
+
+#[test]
+fn test_guard_pages_threads() {
+    if cfg!(not(all(
+        target_os = "linux",
+        target_env = "gnu",
+        target_pointer_width = "64"
+    ))) {
+        return;
+    }
+
+    // 一个线程上的越界不会被另一个线程上同一块内存的正常访问取走
+    let mem = GuardedMemory::new(PAGE_SIZE, 2 * PAGE_SIZE).unwrap();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for _ in 0..20000 {
+                assert_eq!(mem.load::<4>(PAGE_SIZE as u64 + 8), None);
+            }
+        });
+        scope.spawn(|| {
+            for _ in 0..20000 {
+                assert_eq!(mem.load::<4>(16), Some([0; 4]));
+            }
+        });
+    });
+}
+
+#[test]
+fn test_memory_limits() {
+    use super::decoder::{ImportKind, WasmModule, WasmValue};
+    use super::trap::Trap;
+    use super::wat;
+    use std::collections::HashMap;
+
+    let load = |src: &str, host: Option<Limit>| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        let object = host.map(|limit| {
+            let env = HashMap::from([("mem".to_string(), ImportKind::Memory(limit))]);
+            HashMap::from([("env".to_string(), env)])
+        });
+        wasm.instance(object).map(|_| wasm)
+    };
+    let grow = r#"(func (export "grow") (result i32) (memory.grow (i32.const 1)))"#;
+
+    // 模块没有声明最大页数时按宿主的最大页数增长，页数按页而不是字节分配
+    let host = Limit {
+        flag: 1,
+        minimum: 1,
+        maximum: 2,
+    };
+    let mut wasm = load(
+        &format!(r#"(module (import "env" "mem" (memory 1)) {grow})"#),
+        Some(host),
+    )
+    .unwrap();
+    assert_eq!(wasm.mem[0].len(), PAGE_SIZE);
+    assert_eq!(wasm.mem[0].maximum(), 2);
+    assert_eq!(wasm.invoke("grow", &[]).unwrap(), [WasmValue::I32(1)]);
+    assert_eq!(wasm.invoke("grow", &[]).unwrap(), [WasmValue::I32(-1)]);
+    assert_eq!(wasm.memory(0).unwrap().size(), 2);
+
+    let mut wasm = load(&format!("(module (memory 0) {grow})"), None).unwrap();
+    assert_eq!(wasm.mem[0].maximum(), MAX_PAGES);
+    assert_eq!(wasm.invoke("grow", &[]).unwrap(), [WasmValue::I32(0)]);
+
+    // 数据段超出内存时实例化失败，内存不会为它增长
+    let err = load(r#"(module (memory 1) (data (i32.const 65535) "ab"))"#, None).unwrap_err();
+    assert_eq!(
+        err.downcast::<Trap>().unwrap(),
+        Trap::MemoryOutOfBounds {
+            addr: 65537,
+            size: PAGE_SIZE
+        }
+    );
+    assert!(LinearMemory::new(MemoryBackend::Heap, 3, 2).is_err());
+}
@@ -0,0 +1,1008 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, ensure};
+
+use super::decoder::{Global, HostFunc, ImportKind, ImportObject, WasmModule, WasmValue, NULL_REF};
+use super::error::{ImportType, InstantiationError};
+use super::global::SharedGlobal;
+use super::host::SharedHostModule;
+use super::linear::{LinearMemory, Memory, Storage};
+use super::logging::debug;
+use super::section::export::ExportKind;
+use super::section::import;
+use super::section::typings::{Limit, ValueType};
+use super::snapshot::Snapshot;
+use super::table::Table;
+use super::trap::Trap;
+
+/// 实例化之后放进 `Linker` 的模块，导入它的函数的实例共享同一个实例
+pub type SharedInstance = Arc<Mutex<WasmModule>>;
+
+/// 把实例放进 `SharedInstance` 并在它的表中登记，
+/// 之后其他实例可以调用它写入共享表的函数
+pub fn shared_instance(wasm: WasmModule) -> SharedInstance {
+    let instance = Arc::new(Mutex::new(wasm));
+    register_tables(&instance.lock().unwrap(), &instance);
+    instance
+}
+
+fn register_tables(wasm: &WasmModule, instance: &SharedInstance) {
+    for table in &wasm.table {
+        table.register(wasm.id, instance);
+    }
+}
+
+/// 带签名的宿主函数
+#[derive(Debug, Clone)]
+pub struct Func {
+    pub params: Vec<ValueType>,
+    pub results: Vec<ValueType>,
+    pub func: HostFunc,
+    /// 由 `Func::wrap_async` 创建时，调用时执行它并挂起，func 只负责挂起
+    #[cfg(feature = "async")]
+    pub async_func: Option<super::future::AsyncHostFunc>,
+    /// 由 `Linker::define_instance` 定义时为导出它的实例和实例中的函数索引，
+    /// 调用时执行这个函数，func 不会被调用
+    pub instance: Option<(SharedInstance, usize)>,
+    /// 由 `Linker::define_host_module` 定义时为提供它的宿主模块和导出函数的索引，
+    /// 调用时执行 `HostModule::call`，func 不会被调用
+    pub host: Option<(SharedHostModule, usize)>,
+}
+
+impl Func {
+    pub fn wrap(params: &[ValueType], results: &[ValueType], func: HostFunc) -> Self {
+        Self {
+            params: params.to_vec(),
+            results: results.to_vec(),
+            func,
+            #[cfg(feature = "async")]
+            async_func: None,
+            instance: None,
+            host: None,
+        }
+    }
+}
+
+/// 可以被 wasm 模块导入的宿主项
+#[derive(Debug, Clone)]
+pub enum Extern {
+    Func(Func),
+    Memory(Limit),
+    Global(ValueType, bool, WasmValue), // (type, mutability, value)
+    /// 宿主创建的内存，所有导入它的实例共享
+    SharedMemory(Memory),
+    /// 宿主创建的表，所有导入它的实例共享，`Linker::define_table` 也定义为这一项
+    SharedTable(Table),
+    /// 宿主创建的全局变量，宿主和所有导入它的实例读写同一个值
+    SharedGlobal(SharedGlobal),
+}
+
+impl From<Func> for Extern {
+    fn from(value: Func) -> Self {
+        Extern::Func(value)
+    }
+}
+
+impl From<Memory> for Extern {
+    fn from(value: Memory) -> Self {
+        Extern::SharedMemory(value)
+    }
+}
+
+impl From<Table> for Extern {
+    fn from(value: Table) -> Self {
+        Extern::SharedTable(value)
+    }
+}
+
+impl From<SharedGlobal> for Extern {
+    fn from(value: SharedGlobal) -> Self {
+        Extern::SharedGlobal(value)
+    }
+}
+
+/// 按 `module::name` 注册宿主项，实例化时检查导入是否存在以及类型是否匹配
+///
+/// ```ignore
+/// let mut linker = Linker::new();
+/// linker.define("env", "log", Func::wrap(&[ValueType::I32], &[], log))?;
+/// linker.define_memory("env", "memory", 1, Some(16))?;
+/// linker.define_global("env", "base", WasmValue::I32(1024), false)?;
+/// // 多个实例共享同一块内存
+/// linker.define("env", "shared", Memory::new(limits)?)?;
+/// // guest 的 global.set 对宿主和其他实例可见
+/// linker.define("env", "counter", SharedGlobal::new(WasmValue::I32(0), true)?)?;
+/// linker.instantiate(&mut wasm)?;
+/// ```
+#[derive(Debug, Default)]
+pub struct Linker {
+    items: HashMap<String, HashMap<String, Extern>>,
+}
+
+impl Linker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn define(
+        &mut self,
+        module: &str,
+        name: &str,
+        item: impl Into<Extern>,
+    ) -> anyhow::Result<&mut Self> {
+        let item = item.into();
+        match &item {
+            Extern::Func(_)
+            | Extern::SharedMemory(_)
+            | Extern::SharedTable(_)
+            | Extern::SharedGlobal(_) => {}
+            Extern::Memory(limit) => {
+                ensure!(
+                    limit.minimum <= limit.maximum,
+                    "memory `{module}::{name}`: minimum {} is larger than maximum {}",
+                    limit.minimum,
+                    limit.maximum
+                );
+                ensure!(
+                    limit.maximum <= 0x10000,
+                    "memory `{module}::{name}`: maximum {} exceeds 65536 pages",
+                    limit.maximum
+                );
+            }
+            Extern::Global(ty, _, value) => {
+                ensure!(
+                    value.value_type() == Some(*ty),
+                    "global `{module}::{name}`: declared {ty} but the value is {value:?}"
+                );
+            }
+        }
+        let entries = self.items.entry(module.to_string()).or_default();
+        ensure!(
+            !entries.contains_key(name),
+            "`{module}::{name}` has already been defined"
+        );
+        entries.insert(name.to_string(), item);
+        Ok(self)
+    }
+
+    pub fn define_memory(
+        &mut self,
+        module: &str,
+        name: &str,
+        minimum: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<&mut Self> {
+        let limit = Limit {
+            flag: maximum.is_some() as u32,
+            minimum,
+            maximum: maximum.unwrap_or(0x10000),
+        };
+        self.define(module, name, Extern::Memory(limit))
+    }
+
+    pub fn define_global(
+        &mut self,
+        module: &str,
+        name: &str,
+        value: WasmValue,
+        mutability: bool,
+    ) -> anyhow::Result<&mut Self> {
+        let ty = value
+            .value_type()
+            .ok_or_else(|| anyhow!("global `{module}::{name}`: value must not be NOP"))?;
+        self.define(module, name, Extern::Global(ty, mutability, value))
+    }
+
+    pub fn define_table(
+        &mut self,
+        module: &str,
+        name: &str,
+        ty: ValueType,
+        minimum: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<&mut Self> {
+        let limit = Limit {
+            flag: maximum.is_some() as u32,
+            minimum,
+            maximum: maximum.unwrap_or(u32::MAX),
+        };
+        let table = Table::new(limit, ty).map_err(|e| anyhow!("table `{module}::{name}`: {e}"))?;
+        self.define(module, name, table)
+    }
+
+    /// 以 name 注册已经实例化的模块的导出：函数调用时在这个实例上执行，
+    /// 内存和全局变量改为宿主内存和宿主全局变量，与导入它的实例共享，表也直接共享
+    pub fn define_instance(&mut self, name: &str, wasm: WasmModule) -> anyhow::Result<&mut Self> {
+        self.define_shared_instance(name, &shared_instance(wasm))
+    }
+
+    /// 与 `define_instance` 相同，但调用方保留实例，之后仍可以直接在实例上调用导出函数
+    pub fn define_shared_instance(
+        &mut self,
+        name: &str,
+        instance: &SharedInstance,
+    ) -> anyhow::Result<&mut Self> {
+        let mut items = vec![];
+        {
+            let mut wasm = instance
+                .try_lock()
+                .map_err(|_| anyhow!("instance `{name}` is busy"))?;
+            register_tables(&wasm, instance);
+            let mut exports = wasm.exports.clone().into_iter().collect::<Vec<_>>();
+            exports.sort_by(|a, b| a.0.cmp(&b.0));
+            for (export, kind) in exports {
+                let item = match kind {
+                    ExportKind::Func(idx) => {
+                        let (_, ty) = wasm.export_func(&export)?;
+                        let mut f = Func::wrap(&ty.params, &ty.results, |_, _| vec![]);
+                        f.instance = Some((instance.clone(), idx));
+                        Extern::Func(f)
+                    }
+                    ExportKind::Memory(idx) => Extern::SharedMemory(share_memory(&mut wasm, idx)?),
+                    ExportKind::GLobal(idx) => Extern::SharedGlobal(share_global(&mut wasm, idx)?),
+                    ExportKind::Table(idx) => Extern::SharedTable(
+                        wasm.table
+                            .get(idx)
+                            .cloned()
+                            .ok_or_else(|| anyhow!("table {idx} not found"))?,
+                    ),
+                };
+                items.push((export, item));
+            }
+        }
+        for (export, item) in items {
+            self.define(name, &export, item)?;
+        }
+        Ok(self)
+    }
+
+    pub fn get(&self, module: &str, name: &str) -> Option<&Extern> {
+        self.items.get(module)?.get(name)
+    }
+
+    /// 检查模块的所有导入后实例化
+    pub fn instantiate(&self, wasm: &mut WasmModule) -> anyhow::Result<()> {
+        let import_object = self.link(wasm)?;
+        wasm.instance(Some(import_object))
+    }
+
+    /// 与 `instantiate` 相同，但不执行 start 函数，内存、全局变量和数据段从 snapshot 恢复
+    pub fn instantiate_snapshot(
+        &self,
+        wasm: &mut WasmModule,
+        snapshot: &Snapshot,
+    ) -> anyhow::Result<()> {
+        let import_object = self.link(wasm)?;
+        wasm.instance_from_snapshot(Some(import_object), snapshot)
+    }
+
+    /// 解析模块的所有导入
+    fn link(&self, wasm: &mut WasmModule) -> anyhow::Result<ImportObject> {
+        let mut import_object = ImportObject::new();
+        for ipt in wasm.section.import.entries.iter() {
+            let kind = self.resolve(wasm, ipt)?;
+            debug!("resolved import `{}::{}`", ipt.mod_name, ipt.field_name);
+            import_object
+                .entry(ipt.mod_name.clone())
+                .or_default()
+                .insert(ipt.field_name.clone(), kind);
+        }
+        #[cfg(feature = "async")]
+        self.link_async(wasm);
+        self.link_instances(wasm);
+        Ok(import_object)
+    }
+
+    /// 检查模块的导入但不实例化，返回所有缺失或类型不匹配的导入
+    pub fn check_imports(&self, wasm: &WasmModule) -> Vec<InstantiationError> {
+        wasm.section
+            .import
+            .entries
+            .iter()
+            .filter_map(|ipt| self.resolve(wasm, ipt).err())
+            // 宿主项在 define 时已经检查过，这里只有导入检查的错误
+            .filter_map(|err| err.downcast().ok())
+            .collect()
+    }
+
+    /// 为模块中还没有定义的导入注册占位项：函数被调用时 trap，全局变量为 0（引用为空），
+    /// 内存和表取导入声明的大小
+    pub fn define_dummies(&mut self, wasm: &WasmModule) -> anyhow::Result<&mut Self> {
+        for ipt in &wasm.section.import.entries {
+            let (module, name) = (&ipt.mod_name[..], &ipt.field_name[..]);
+            if self.get(module, name).is_some() {
+                continue;
+            }
+            let max = |limit: &Limit| (limit.flag & 0x01 == 1).then_some(limit.maximum);
+            match &ipt.kind {
+                import::Kind::Func(ty) => {
+                    let ty = wasm
+                        .section
+                        .types
+                        .entries
+                        .get(*ty)
+                        .ok_or_else(|| anyhow!("unknown type {ty}"))?;
+                    let func = Func::wrap(&ty.params, &ty.results, |wasm, _| {
+                        wasm.raise(Trap::Host {
+                            message: "called a dummy import".to_string(),
+                        })
+                    });
+                    self.define(module, name, func)?
+                }
+                import::Kind::Memory(limit) => {
+                    self.define_memory(module, name, limit.minimum, max(limit))?
+                }
+                import::Kind::Table(ty, limit) => {
+                    let ty = ValueType::from_u8(*ty)?;
+                    self.define_table(module, name, ty, limit.minimum, max(limit))?
+                }
+                import::Kind::Global(global) => {
+                    let value = match global.val_ty {
+                        ValueType::I32 => WasmValue::I32(0),
+                        ValueType::I64 => WasmValue::I64(0),
+                        ValueType::F32 => WasmValue::F32(0.0),
+                        ValueType::F64 => WasmValue::F64(0.0),
+                        ValueType::V128 => WasmValue::V128(0),
+                        ValueType::FuncRef => WasmValue::FuncRef(NULL_REF),
+                        ValueType::ExternRef => WasmValue::ExternRef(NULL_REF),
+                        ValueType::AnyRef => continue,
+                    };
+                    self.define_global(module, name, value, global.mutability)?
+                }
+            };
+        }
+        Ok(self)
+    }
+
+    /// 找到满足导入声明的宿主项
+    fn resolve(&self, wasm: &WasmModule, ipt: &import::Importer) -> anyhow::Result<ImportKind> {
+        let expected = ImportType::new(&wasm.section.types.entries, &ipt.kind);
+        let item = self.get(&ipt.mod_name, &ipt.field_name).ok_or_else(|| {
+            InstantiationError::MissingImport {
+                module: ipt.mod_name.clone(),
+                field: ipt.field_name.clone(),
+                expected: expected.clone(),
+            }
+        })?;
+        Ok(match (&ipt.kind, item) {
+            (import::Kind::Func(_), Extern::Func(f))
+                if expected == ImportType::Func(f.params.clone(), f.results.clone()) =>
+            {
+                ImportKind::Func(f.func)
+            }
+            (import::Kind::Memory(mem), Extern::Memory(limit))
+                if limit.minimum >= mem.minimum
+                    && (mem.flag & 0x01 == 0 || limit.maximum <= mem.maximum) =>
+            {
+                ImportKind::Memory(limit.clone())
+            }
+            (import::Kind::Global(g), Extern::Global(ty, mutability, value))
+                if g.val_ty == *ty && g.mutability == *mutability =>
+            {
+                ImportKind::Value(*value)
+            }
+            (import::Kind::Memory(_), Extern::SharedMemory(mem)) => {
+                ImportKind::SharedMemory(mem.clone())
+            }
+            (import::Kind::Global(g), Extern::SharedGlobal(global))
+                if g.val_ty == global.ty() && g.mutability == global.mutability() =>
+            {
+                ImportKind::SharedGlobal(global.clone())
+            }
+            (import::Kind::Table(ty, limit), Extern::SharedTable(table))
+                if ValueType::from_u8(*ty).ok() == Some(table.ty())
+                    && table.size() >= limit.minimum
+                    && (limit.flag & 0x01 == 0 || table.maximum() <= limit.maximum) =>
+            {
+                ImportKind::Table(table.clone())
+            }
+            _ => {
+                return Err(InstantiationError::IncompatibleImport {
+                    module: ipt.mod_name.clone(),
+                    field: ipt.field_name.clone(),
+                    expected,
+                    found: extern_type(item).to_string(),
+                }
+                .into())
+            }
+        })
+    }
+
+    /// 记录从其他实例导入的函数，导入函数的索引按导入顺序排在前面
+    fn link_instances(&self, wasm: &mut WasmModule) {
+        let funcs = wasm
+            .section
+            .import
+            .entries
+            .iter()
+            .filter(|ipt| matches!(ipt.kind, import::Kind::Func(_)))
+            .enumerate()
+            .filter_map(
+                |(idx, ipt)| match self.get(&ipt.mod_name, &ipt.field_name) {
+                    Some(Extern::Func(f)) => Some((idx, f.instance.clone()?)),
+                    _ => None,
+                },
+            )
+            .collect::<Vec<_>>();
+        wasm.linked_funcs.extend(funcs);
+        let funcs = wasm
+            .section
+            .import
+            .entries
+            .iter()
+            .filter(|ipt| matches!(ipt.kind, import::Kind::Func(_)))
+            .enumerate()
+            .filter_map(
+                |(idx, ipt)| match self.get(&ipt.mod_name, &ipt.field_name) {
+                    Some(Extern::Func(f)) => Some((idx, f.host.clone()?)),
+                    _ => None,
+                },
+            )
+            .collect::<Vec<_>>();
+        wasm.host_funcs.extend(funcs);
+    }
+
+    /// 记录导入的异步宿主函数，导入函数的索引按导入顺序排在前面
+    #[cfg(feature = "async")]
+    fn link_async(&self, wasm: &mut WasmModule) {
+        let funcs = wasm
+            .section
+            .import
+            .entries
+            .iter()
+            .filter(|ipt| matches!(ipt.kind, import::Kind::Func(_)))
+            .enumerate()
+            .filter_map(
+                |(idx, ipt)| match self.get(&ipt.mod_name, &ipt.field_name) {
+                    Some(Extern::Func(f)) => Some((idx, f.async_func?)),
+                    _ => None,
+                },
+            )
+            .collect::<Vec<_>>();
+        wasm.async_funcs.extend(funcs);
+    }
+}
+
+/// 把实例的内存 idx 换成内容相同的宿主内存，之后实例和导入它的实例读写同一份字节
+fn share_memory(wasm: &mut WasmModule, idx: usize) -> anyhow::Result<Memory> {
+    let mem = wasm
+        .mem
+        .get_mut(idx)
+        .ok_or_else(|| anyhow!("memory {idx} not found"))?;
+    if let Storage::Shared(shared) = mem.storage() {
+        return Ok(shared.clone());
+    }
+    let shared = Memory::new(Limit {
+        flag: 1,
+        minimum: mem.pages(),
+        maximum: mem.maximum(),
+    })?;
+    shared.write(0, mem)?;
+    *mem = LinearMemory::shared(shared.clone(), mem.maximum());
+    Ok(shared)
+}
+
+/// 把实例的全局变量 idx 换成值相同的宿主全局变量
+fn share_global(wasm: &mut WasmModule, idx: usize) -> anyhow::Result<SharedGlobal> {
+    let global = wasm
+        .global
+        .get_mut(idx)
+        .ok_or_else(|| anyhow!("global {idx} not found"))?;
+    if let Global::Shared(shared) = global {
+        return Ok(shared.clone());
+    }
+    // funcref 的值是这个实例的函数索引，导入它的实例会当成自己的函数
+    ensure!(
+        global.get().value_type() != Some(ValueType::FuncRef),
+        "global {idx}: funcref globals can't be shared between instances"
+    );
+    let shared = SharedGlobal::new(global.get(), global.is_mutable())?;
+    *global = Global::Shared(shared.clone());
+    Ok(shared)
+}
+
+/// 在导出函数的实例上调用 func；实例正在执行时（例如被调函数又调回导入它的实例）返回 trap
+pub(crate) fn call_linked(
+    instance: &SharedInstance,
+    func: usize,
+    args: &[WasmValue],
+) -> Result<Vec<WasmValue>, Trap> {
+    let Ok(mut wasm) = instance.try_lock() else {
+        return Err(Trap::InstanceBusy { func });
+    };
+    wasm.call_with(func, args)
+}
+
+/// 宿主项的类型，用于错误信息
+fn extern_type(item: &Extern) -> ImportType {
+    match item {
+        Extern::Func(f) => ImportType::Func(f.params.clone(), f.results.clone()),
+        Extern::Memory(limit) => ImportType::Memory(limit.minimum, limit.maximum),
+        Extern::Global(ty, mutability, _) => ImportType::Global(*ty, *mutability),
+        Extern::SharedMemory(mem) => ImportType::Memory(mem.size(), mem.maximum()),
+        Extern::SharedTable(table) => {
+            ImportType::Table(Some(table.ty()), table.size(), table.maximum())
+        }
+        Extern::SharedGlobal(global) => ImportType::Global(global.ty(), global.mutability()),
+    }
+}
+
+#[cfg(test)]
+fn log_module() -> WasmModule {
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, // magic = \0asm
+        0x01, 0x00, 0x00, 0x00, // version  = 1 (little endian)
+        //
+        0x01, 0x05, 0x01, // type section
+        0x60, 0x01, 0x7f, 0x00, // (i32) -> ()
+        //
+        0x02, 0x19, 0x02, // import section
+        0x03, 0x65, 0x6e, 0x76, 0x03, 0x6c, 0x6f, 0x67, 0x00, 0x00, // env::log func 0
+        0x03, 0x65, 0x6e, 0x76, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00,
+        0x01, // env::memory (memory 1)
+    ];
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    wasm
+}
+
+#[cfg(test)]
+fn host_log(_: &mut WasmModule, _: &Vec<WasmValue>) -> Vec<WasmValue> {
+    vec![]
+}
+
+#[test]
+fn test_linker_instantiate() {
+    let mut linker = Linker::new();
+    linker
+        .define("env", "log", Func::wrap(&[ValueType::I32], &[], host_log))
+        .unwrap()
+        .define_memory("env", "memory", 2, None)
+        .unwrap();
+    let mut wasm = log_module();
+    linker.instantiate(&mut wasm).unwrap();
+    assert_eq!(wasm.func.len(), 1);
+    assert_eq!(wasm.mem[0].len(), 2 * super::constants::PAGE_SIZE);
+}
+
+#[test]
+fn test_linker_errors() {
+    let mut linker = Linker::new();
+    linker.define_memory("env", "memory", 1, None).unwrap();
+    let err = linker.instantiate(&mut log_module()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown import: `env::log` has not been defined (expected func (I32) -> ())"
+    );
+
+    linker
+        .define("env", "log", Func::wrap(&[ValueType::I64], &[], host_log))
+        .unwrap();
+    let err = linker.instantiate(&mut log_module()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "incompatible import type for `env::log`: expected func (I32) -> (), found func (I64) -> ()"
+    );
+
+    // 定义时检查
+    assert!(linker
+        .define("env", "log", Func::wrap(&[], &[], host_log))
+        .is_err());
+    assert!(linker.define_memory("env", "mem2", 4, Some(2)).is_err());
+    assert!(linker
+        .define(
+            "env",
+            "g",
+            Extern::Global(ValueType::I64, false, WasmValue::I32(1))
+        )
+        .is_err());
+    assert!(linker
+        .define_global("env", "nop", WasmValue::NOP, false)
+        .is_err());
+    assert!(linker
+        .define_table("env", "t", ValueType::I32, 1, None)
+        .is_err());
+}
+
+#[test]
+fn test_linker_dummies() {
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "log" (func $log (param i32)))
+      (import "env" "get" (func $get (result i64)))
+      (import "env" "memory" (memory 1 2))
+      (import "env" "table" (table 2 funcref))
+      (import "env" "base" (global $base i32))
+      (func (export "base") (result i32) (global.get $base))
+      (func (export "get") (result i64) (call $get))
+      (func (export "log") (call $log (i32.const 1))))"#;
+    let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+    wasm.decode().unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define("env", "log", Func::wrap(&[ValueType::I32], &[], host_log))
+        .unwrap()
+        .define_dummies(&wasm)
+        .unwrap();
+    assert!(linker.check_imports(&wasm).is_empty());
+    linker.instantiate(&mut wasm).unwrap();
+    // 已经定义的导入保持不变
+    assert!(wasm.invoke("log", &[]).is_ok());
+    assert_eq!(wasm.invoke("base", &[]).unwrap(), [WasmValue::I32(0)]);
+    let err = wasm.invoke("get", &[]).unwrap_err();
+    assert!(err.to_string().contains("dummy import"), "{err}");
+}
+
+#[test]
+fn test_linker_shared_memory() {
+    use super::wat;
+
+    let mut linker = Linker::new();
+    let memory = Memory::new(Limit {
+        flag: 1,
+        minimum: 1,
+        maximum: 2,
+    })
+    .unwrap();
+    let table = Table::new(Limit::default(), ValueType::FuncRef).unwrap();
+    linker
+        .define("env", "memory", memory.clone())
+        .unwrap()
+        .define("env", "table", table.clone())
+        .unwrap();
+    let instance = |src: &str| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).map(|_| wasm)
+    };
+    let mut writer = instance(
+        r#"(module
+          (import "env" "memory" (memory 1 2))
+          (import "env" "table" (table 0 funcref))
+          (func (export "grow") (result i32) (memory.grow (i32.const 1)))
+          (data (i32.const 8) "hi"))"#,
+    )
+    .unwrap();
+    let mut reader = instance(
+        r#"(module
+          (import "env" "memory" (memory 1))
+          (func (export "load") (param i32) (result i32) (i32.load8_u (local.get 0))))"#,
+    )
+    .unwrap();
+    assert_eq!(
+        reader.invoke("load", &[WasmValue::I32(9)]).unwrap(),
+        [WasmValue::I32(b'i' as i32)]
+    );
+    // 宿主写入和 memory.grow 对所有实例可见
+    memory.write(16, &[7]).unwrap();
+    assert_eq!(
+        reader.invoke("load", &[WasmValue::I32(16)]).unwrap(),
+        [WasmValue::I32(7)]
+    );
+    assert_eq!(writer.invoke("grow", &[]).unwrap(), [WasmValue::I32(1)]);
+    assert_eq!(memory.size(), 2);
+    assert_eq!(reader.memory(0).unwrap().size(), 2);
+    assert_eq!(writer.invoke("grow", &[]).unwrap(), [WasmValue::I32(-1)]);
+
+    // 声明的最大页数小于宿主内存的最大页数
+    let err = instance(r#"(module (import "env" "memory" (memory 1 1)))"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "incompatible import type for `env::memory`: expected memory [1 ~ 1] pages, found memory [2 ~ 2] pages"
+    );
+    assert!(Memory::new(Limit {
+        flag: 1,
+        minimum: 3,
+        maximum: 2
+    })
+    .is_err());
+}
+
+#[test]
+fn test_linker_shared_table() {
+    use super::config::RuntimeConfig;
+    use super::testing::all_engine_configs;
+    use super::trap::Trap;
+    use super::wat;
+
+    let table = Table::new(
+        Limit {
+            flag: 0,
+            minimum: 2,
+            maximum: 0,
+        },
+        ValueType::FuncRef,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define("env", "tab", table.clone())
+        .unwrap()
+        .define_table("env", "t", ValueType::FuncRef, 1, Some(4))
+        .unwrap();
+    let instance = |src: &str, config| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).map(|_| wasm)
+    };
+    let writer = r#"(module
+      (type $t (func (result i32)))
+      (import "env" "tab" (table 2 funcref))
+      (func $fa (result i32) (i32.const 111))
+      (func (export "call") (param i32) (result i32)
+        (call_indirect (type $t) (local.get 0)))
+      (elem (i32.const 0) $fa))"#;
+    let reader = r#"(module
+      (type $t (func (result i32)))
+      (import "env" "tab" (table 2 funcref))
+      (func $f0 (result i32) (i32.const 222))
+      (func $f1 (result i32) (i32.const 333))
+      (func (export "call") (param i32) (result i32)
+        (call_indirect (type $t) (local.get 0)))
+      (func (export "copy") (table.set (i32.const 1) (table.get (i32.const 0))))
+      (elem declare func $f0 $f1))"#;
+
+    for config in all_engine_configs() {
+        // 写入者没有共享时，读取者不能把表项当成自己的函数调用
+        let a = instance(writer, config.clone()).unwrap();
+        let mut b = instance(reader, config.clone()).unwrap();
+        let err = b.invoke("call", &[WasmValue::I32(0)]).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<Trap>(),
+                Some(Trap::ForeignFunction { .. })
+            ),
+            "{err}"
+        );
+        drop(a);
+
+        // 共享后调用的是写入者的函数，table.get/table.set 保留它的来源
+        let a = shared_instance(instance(writer, config.clone()).unwrap());
+        let mut b = instance(reader, config.clone()).unwrap();
+        assert_eq!(
+            b.invoke("call", &[WasmValue::I32(0)]).unwrap(),
+            [WasmValue::I32(111)]
+        );
+        b.invoke("copy", &[]).unwrap();
+        assert_eq!(
+            b.invoke("call", &[WasmValue::I32(1)]).unwrap(),
+            [WasmValue::I32(111)]
+        );
+        assert_eq!(
+            a.lock()
+                .unwrap()
+                .invoke("call", &[WasmValue::I32(1)])
+                .unwrap(),
+            [WasmValue::I32(111)]
+        );
+
+        // 写入者释放后不能再调用
+        drop(a);
+        let err = b.invoke("call", &[WasmValue::I32(0)]).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<Trap>(),
+                Some(Trap::ForeignFunction { .. })
+            ),
+            "{err}"
+        );
+    }
+
+    // define_table 定义的表也只有一个，导入它的实例都能看到 table.grow 的结果
+    let src = r#"(module
+      (import "env" "t" (table 1 funcref))
+      (func (export "grow") (result i32) (table.grow (ref.null func) (i32.const 1))))"#;
+    let config = RuntimeConfig::default();
+    let mut a = instance(src, config.clone()).unwrap();
+    let mut b = instance(src, config.clone()).unwrap();
+    assert_eq!(a.invoke("grow", &[]).unwrap(), [WasmValue::I32(1)]);
+    assert_eq!(b.invoke("grow", &[]).unwrap(), [WasmValue::I32(2)]);
+    assert!(matches!(
+        linker.get("env", "t"),
+        Some(Extern::SharedTable(table)) if table.size() == 3
+    ));
+    let err = instance(r#"(module (import "env" "t" (table 4 funcref)))"#, config).unwrap_err();
+    assert!(
+        err.to_string().contains("incompatible import type"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_linker_define_instance() {
+    use super::wat;
+
+    let instance = |linker: &Linker, src: &str| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).map(|_| wasm)
+    };
+    let mut linker = Linker::new();
+    let math = instance(
+        &linker,
+        r#"(module
+          (memory (export "memory") 1)
+          (global (export "base") i32 (i32.const 16))
+          (global $calls (mut i32) (i32.const 0))
+          (func (export "add") (param i32 i32) (result i32)
+            (global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+            (i32.add (local.get 0) (local.get 1)))
+          (func (export "calls") (result i32) (global.get $calls))
+          (data (i32.const 16) "math"))"#,
+    )
+    .unwrap();
+    linker.define_instance("math", math).unwrap();
+
+    let src = r#"(module
+      (import "math" "add" (func $add (param i32 i32) (result i32)))
+      (import "math" "calls" (func $calls (result i32)))
+      (import "math" "memory" (memory 1))
+      (import "math" "base" (global $base i32))
+      (func (export "main") (result i32 i32 i32)
+        (call $add (i32.const 2) (i32.const 3))
+        (drop (call $add (i32.const 0) (i32.const 0)))
+        (call $calls)
+        (i32.load8_u (global.get $base))))"#;
+    let mut main = instance(&linker, src).unwrap();
+    let mut other = instance(&linker, src).unwrap();
+    assert_eq!(
+        main.invoke("main", &[]).unwrap(),
+        [
+            WasmValue::I32(5),
+            WasmValue::I32(2),
+            WasmValue::I32(b'm' as i32)
+        ]
+    );
+    // 导入同一个实例的模块共享它的状态
+    assert_eq!(other.invoke("main", &[]).unwrap()[1], WasmValue::I32(4));
+    assert!(matches!(
+        linker.get("math", "memory"),
+        Some(Extern::SharedMemory(mem)) if mem.size() == 1
+    ));
+
+    // 签名不一致时不能导入
+    let err = instance(
+        &linker,
+        r#"(module (import "math" "add" (func (param i64 i64) (result i64))))"#,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("incompatible import type"),
+        "{err}"
+    );
+
+    // 导出的表与导入它的实例共享，表项调用的是导出它的实例中的函数
+    let lib = instance(
+        &linker,
+        r#"(module
+          (table (export "tab") 2 funcref)
+          (func $f (result i32) (i32.const 111))
+          (func (export "size") (result i32) (table.size 0))
+          (elem (i32.const 1) $f))"#,
+    )
+    .unwrap();
+    linker.define_instance("lib", lib).unwrap();
+    let mut user = instance(
+        &linker,
+        r#"(module
+          (import "lib" "tab" (table 2 funcref))
+          (import "lib" "size" (func $size (result i32)))
+          (func (result i32) (i32.const 333))
+          (func (export "call") (param i32) (result i32)
+            (call_indirect (result i32) (local.get 0)))
+          (func (export "grow") (result i32)
+            (drop (table.grow (ref.null func) (i32.const 3)))
+            (call $size)))"#,
+    )
+    .unwrap();
+    assert_eq!(
+        user.invoke("call", &[WasmValue::I32(1)]).unwrap(),
+        [WasmValue::I32(111)]
+    );
+    assert_eq!(user.invoke("grow", &[]).unwrap(), [WasmValue::I32(5)]);
+    assert!(matches!(
+        linker.get("lib", "tab"),
+        Some(Extern::SharedTable(table)) if table.size() == 5
+    ));
+
+    // funcref 全局变量的值是导出它的实例的函数索引，不能共享
+    let refs = instance(
+        &linker,
+        r#"(module (global (export "f") funcref (ref.func 0)) (func))"#,
+    )
+    .unwrap();
+    let err = linker.define_instance("refs", refs).unwrap_err();
+    assert!(err.to_string().contains("can't be shared"), "{err}");
+}
+
+#[test]
+fn test_linker_shared_global() {
+    use super::testing::all_engine_configs;
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "counter" (global $counter (mut i64)))
+      (import "env" "base" (global $base i32))
+      (func (export "bump") (result i64)
+        (global.set $counter (i64.add (global.get $counter) (i64.const 1)))
+        (global.get $counter))
+      (func (export "base") (result i32) (global.get $base)))"#;
+    let counter = SharedGlobal::new(WasmValue::I64(0), true).unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define("env", "counter", counter.clone())
+        .unwrap()
+        .define(
+            "env",
+            "base",
+            SharedGlobal::new(WasmValue::I32(8), false).unwrap(),
+        )
+        .unwrap();
+
+    let configs = all_engine_configs();
+    let mut expected = 0;
+    for config in configs {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.config = config;
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).unwrap();
+        // 每个实例都在同一个值上累加，宿主的修改对 guest 可见
+        expected += 1;
+        assert_eq!(
+            wasm.invoke("bump", &[]).unwrap(),
+            [WasmValue::I64(expected)]
+        );
+        assert_eq!(counter.get(), WasmValue::I64(expected));
+        counter.set(WasmValue::I64(expected + 10)).unwrap();
+        expected += 11;
+        assert_eq!(
+            wasm.invoke("bump", &[]).unwrap(),
+            [WasmValue::I64(expected)]
+        );
+        assert_eq!(wasm.invoke("base", &[]).unwrap(), [WasmValue::I32(8)]);
+        assert_eq!(wasm.get_global("counter").ok().map(|g| g.get()), None);
+    }
+
+    // 可变性或类型不一致
+    for global in ["(global (mut i32))", "(global i64)"] {
+        let src = format!(r#"(module (import "env" "counter" {global}))"#);
+        let mut wasm = WasmModule::default(wat::compile(&src).unwrap());
+        wasm.decode().unwrap();
+        let err = linker.instantiate(&mut wasm).unwrap_err();
+        assert!(
+            err.to_string().contains("incompatible import type"),
+            "{err}"
+        );
+    }
+
+    // 注册的实例导出的全局变量与导入它的实例共享
+    let mut helper = WasmModule::default(
+        wat::compile(
+            r#"(module
+              (global $n (export "n") (mut i32) (i32.const 1))
+              (func (export "double") (global.set $n (i32.mul (global.get $n) (i32.const 2)))))"#,
+        )
+        .unwrap(),
+    );
+    helper.decode().unwrap();
+    linker.instantiate(&mut helper).unwrap();
+    linker.define_instance("helper", helper).unwrap();
+    let mut wasm = WasmModule::default(
+        wat::compile(
+            r#"(module
+              (import "helper" "n" (global $n (mut i32)))
+              (import "helper" "double" (func $double))
+              (func (export "run") (result i32)
+                (global.set $n (i32.const 3))
+                (call $double)
+                (global.get $n)))"#,
+        )
+        .unwrap(),
+    );
+    wasm.decode().unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+    assert_eq!(wasm.invoke("run", &[]).unwrap(), [WasmValue::I32(6)]);
+}
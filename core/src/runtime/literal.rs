@@ -0,0 +1,318 @@
+//! 命令行参数与 wasm 值之间的转换
+//!
+//! 参数按目标函数签名解析，支持与 spec 测试一致的写法：
+//! - 整数：十进制、十六进制（`0xff`、`-0x1`）、下划线分隔，i32/i64 接受有符号和无符号两种范围
+//! - 浮点：十进制、十六进制浮点（`0x1.8p3`）、`inf`、`nan`、`nan:0x200000`（指定 payload）
+
+use anyhow::{anyhow, bail, ensure, Context};
+
+use super::decoder::{WasmValue, NULL_REF};
+use super::section::typings::ValueType;
+
+/// 按照目标类型解析一个参数
+pub fn parse_value(text: &str, ty: ValueType) -> anyhow::Result<WasmValue> {
+    let value = match ty {
+        ValueType::I32 => {
+            let v = parse_int(text, i32::MIN as i128, u32::MAX as i128)?;
+            WasmValue::I32(v as u32 as i32)
+        }
+        ValueType::I64 => {
+            let v = parse_int(text, i64::MIN as i128, u64::MAX as i128)?;
+            WasmValue::I64(v as u64 as i64)
+        }
+        ValueType::F32 => {
+            WasmValue::F32(f32::from_bits(parse_float(text, FloatFormat::F32)? as u32))
+        }
+        ValueType::F64 => WasmValue::F64(f64::from_bits(parse_float(text, FloatFormat::F64)?)),
+        ty => bail!("can't pass a {ty} argument from the command line"),
+    };
+    Ok(value)
+}
+
+/// 十进制和十六进制（整数为无符号形式，浮点数为位模式）两种形式
+pub fn format_value(value: &WasmValue) -> String {
+    match value {
+        WasmValue::I32(v) => format!("{v} ({:#x})", *v as u32),
+        WasmValue::I64(v) => format!("{v} ({:#x})", *v as u64),
+        WasmValue::F32(v) => {
+            let bits = v.to_bits();
+            let text = if v.is_nan() {
+                let sign = if v.is_sign_negative() { "-" } else { "" };
+                format!("{sign}nan:{:#x}", bits & 0x007f_ffff)
+            } else {
+                format!("{v}")
+            };
+            format!("{text} ({bits:#010x})")
+        }
+        WasmValue::F64(v) => {
+            let bits = v.to_bits();
+            let text = if v.is_nan() {
+                let sign = if v.is_sign_negative() { "-" } else { "" };
+                format!("{sign}nan:{:#x}", bits & 0x000f_ffff_ffff_ffff)
+            } else {
+                format!("{v}")
+            };
+            format!("{text} ({bits:#018x})")
+        }
+        WasmValue::V128(v) => format!("{:#034x}", *v as u128),
+        WasmValue::FuncRef(NULL_REF) => "ref.null func".to_string(),
+        WasmValue::ExternRef(NULL_REF) => "ref.null extern".to_string(),
+        WasmValue::FuncRef(v) => format!("ref.func {v}"),
+        WasmValue::ExternRef(v) => format!("ref.extern {v}"),
+        WasmValue::NOP => "nop".to_string(),
+    }
+}
+
+/// 按照函数的参数类型依次解析参数，个数必须一致
+pub fn parse_args<S: AsRef<str>>(
+    params: &[ValueType],
+    args: &[S],
+) -> anyhow::Result<Vec<WasmValue>> {
+    ensure!(
+        params.len() == args.len(),
+        "expected {} arguments, got {}",
+        params.len(),
+        args.len()
+    );
+    params
+        .iter()
+        .zip(args)
+        .enumerate()
+        .map(|(i, (ty, text))| {
+            let text = text.as_ref();
+            parse_value(text, *ty).with_context(|| format!("argument {i}: bad {ty} `{text}`"))
+        })
+        .collect()
+}
+
+fn split_sign(text: &str) -> (bool, &str) {
+    match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    }
+}
+
+fn parse_int(text: &str, min: i128, max: i128) -> anyhow::Result<i128> {
+    let digits = text.replace('_', "");
+    let (negative, digits) = split_sign(&digits);
+    let magnitude = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u128::from_str_radix(hex, 16),
+        None => digits.parse::<u128>(),
+    }
+    .with_context(|| format!("invalid integer `{text}`"))?;
+    let value = i128::try_from(magnitude)
+        .ok()
+        .map(|v| if negative { -v } else { v })
+        .filter(|v| (min..=max).contains(v))
+        .ok_or_else(|| anyhow!("integer `{text}` out of range"))?;
+    Ok(value)
+}
+
+#[derive(Clone, Copy)]
+enum FloatFormat {
+    F32,
+    F64,
+}
+
+impl FloatFormat {
+    /// (尾数位数, 指数位数)
+    fn bits(self) -> (u32, u32) {
+        match self {
+            FloatFormat::F32 => (23, 8),
+            FloatFormat::F64 => (52, 11),
+        }
+    }
+}
+
+/// 返回目标类型的位模式
+fn parse_float(text: &str, format: FloatFormat) -> anyhow::Result<u64> {
+    let (mantissa_bits, exponent_bits) = format.bits();
+    let sign_bit = 1u64 << (mantissa_bits + exponent_bits);
+    let infinity = ((1u64 << exponent_bits) - 1) << mantissa_bits;
+
+    let digits = text.replace('_', "");
+    let (negative, body) = split_sign(&digits);
+    let sign = if negative { sign_bit } else { 0 };
+
+    if body == "inf" || body == "infinity" {
+        return Ok(sign | infinity);
+    }
+    if body == "nan" {
+        return Ok(sign | infinity | 1 << (mantissa_bits - 1));
+    }
+    if let Some(payload) = body.strip_prefix("nan:") {
+        let payload = payload
+            .strip_prefix("0x")
+            .with_context(|| format!("nan payload must be hex: `{text}`"))?;
+        let payload = u64::from_str_radix(payload, 16)
+            .with_context(|| format!("invalid nan payload `{text}`"))?;
+        ensure!(
+            payload != 0 && payload < 1 << mantissa_bits,
+            "nan payload out of range: `{text}`"
+        );
+        return Ok(sign | infinity | payload);
+    }
+
+    let value = match body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        Some(hex) => parse_hex_float(hex).with_context(|| format!("invalid float `{text}`"))?,
+        None => body
+            .parse::<f64>()
+            .ok()
+            .filter(|_| body.starts_with(|c: char| c.is_ascii_digit() || c == '.'))
+            .with_context(|| format!("invalid float `{text}`"))?,
+    };
+    let bits = match format {
+        FloatFormat::F32 => match body.starts_with("0x") || body.starts_with("0X") {
+            true => (value as f32).to_bits() as u64,
+            // 十进制直接按 f32 解析，避免两次舍入
+            false => body.parse::<f32>()?.to_bits() as u64,
+        },
+        FloatFormat::F64 => value.to_bits(),
+    };
+    Ok(sign | bits)
+}
+
+/// 十六进制浮点数 `h.hhhp±d`（不含 `0x` 前缀和符号）
+fn parse_hex_float(text: &str) -> anyhow::Result<f64> {
+    let (mantissa, exponent) = match text.find(['p', 'P']) {
+        Some(pos) => (&text[..pos], text[pos + 1..].parse::<i32>()?),
+        None => (text, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    ensure!(!int.is_empty() || !frac.is_empty(), "empty mantissa");
+
+    // 只保留前 15 个有效十六进制位，其余位记入 sticky 用于舍入
+    let mut value = 0u64;
+    let mut shift = exponent;
+    let mut sticky = false;
+    let mut significant = 0;
+    for (index, c) in int.chars().chain(frac.chars()).enumerate() {
+        let digit = c
+            .to_digit(16)
+            .ok_or_else(|| anyhow!("invalid hex digit `{c}`"))? as u64;
+        let is_frac = index >= int.len();
+        if significant < 15 {
+            value = value << 4 | digit;
+            if value != 0 {
+                significant += 1;
+            }
+            if is_frac {
+                shift -= 4;
+            }
+        } else {
+            sticky |= digit != 0;
+            if !is_frac {
+                shift += 4;
+            }
+        }
+    }
+    // 多出的一位给 sticky，转换为 f64 时只发生一次舍入
+    let mut value = (value << 1 | sticky as u64) as f64;
+    shift -= 1;
+    // 分段缩放，避免 2^shift 本身溢出或下溢
+    while shift > 1000 {
+        value *= 2f64.powi(1000);
+        shift -= 1000;
+    }
+    while shift < -1000 {
+        value *= 2f64.powi(-1000);
+        shift += 1000;
+    }
+    Ok(value * 2f64.powi(shift))
+}
+
+#[test]
+fn test_parse_int() {
+    use WasmValue::*;
+    assert_eq!(parse_value("42", ValueType::I32).unwrap(), I32(42));
+    assert_eq!(parse_value("-1", ValueType::I32).unwrap(), I32(-1));
+    assert_eq!(parse_value("0xffffffff", ValueType::I32).unwrap(), I32(-1));
+    assert_eq!(
+        parse_value("-0x80000000", ValueType::I32).unwrap(),
+        I32(i32::MIN)
+    );
+    assert_eq!(
+        parse_value("4_294_967_295", ValueType::I32).unwrap(),
+        I32(-1)
+    );
+    assert!(parse_value("0x100000000", ValueType::I32).is_err());
+    assert!(parse_value("-0x80000001", ValueType::I32).is_err());
+    assert!(parse_value("1.5", ValueType::I32).is_err());
+    assert_eq!(
+        parse_value("0xffffffffffffffff", ValueType::I64).unwrap(),
+        I64(-1)
+    );
+    assert_eq!(
+        parse_value("-9223372036854775808", ValueType::I64).unwrap(),
+        I64(i64::MIN)
+    );
+    assert!(parse_value("18446744073709551616", ValueType::I64).is_err());
+}
+
+#[test]
+fn test_parse_float() {
+    let f32_bits = |text| match parse_value(text, ValueType::F32).unwrap() {
+        WasmValue::F32(v) => v.to_bits(),
+        v => panic!("unexpected {v:?}"),
+    };
+    let f64_bits = |text| match parse_value(text, ValueType::F64).unwrap() {
+        WasmValue::F64(v) => v.to_bits(),
+        v => panic!("unexpected {v:?}"),
+    };
+    assert_eq!(f32_bits("1.5"), 1.5f32.to_bits());
+    assert_eq!(f32_bits("-0"), (-0.0f32).to_bits());
+    assert_eq!(f32_bits("0.1"), 0.1f32.to_bits());
+    assert_eq!(f32_bits("inf"), f32::INFINITY.to_bits());
+    assert_eq!(f32_bits("-inf"), f32::NEG_INFINITY.to_bits());
+    assert_eq!(f32_bits("nan"), 0x7fc0_0000);
+    assert_eq!(f32_bits("-nan"), 0xffc0_0000);
+    assert_eq!(f32_bits("nan:0x200000"), 0x7fa0_0000);
+    assert_eq!(f32_bits("-nan:0x1"), 0xff80_0001);
+    assert_eq!(f32_bits("0x1.8p1"), 3.0f32.to_bits());
+    assert_eq!(f32_bits("-0x1p-149"), 0x8000_0001);
+    assert_eq!(f64_bits("0x1.fffffffffffffp1023"), f64::MAX.to_bits());
+    assert_eq!(f64_bits("0x.8"), 0.5f64.to_bits());
+    assert_eq!(f64_bits("0x10"), 16.0f64.to_bits());
+    assert_eq!(f64_bits("nan:0x8000000000000"), 0x7ff8_0000_0000_0000);
+    assert_eq!(f64_bits("1e308"), 1e308f64.to_bits());
+    assert!(parse_value("nan:0x0", ValueType::F32).is_err());
+    assert!(parse_value("nan:0x800000", ValueType::F32).is_err());
+    assert!(parse_value("nan:123", ValueType::F32).is_err());
+    assert!(parse_value("abc", ValueType::F64).is_err());
+    assert!(parse_value("0x1.g", ValueType::F64).is_err());
+}
+
+#[test]
+fn test_format_value() {
+    use WasmValue::*;
+    assert_eq!(format_value(&I32(-1)), "-1 (0xffffffff)");
+    assert_eq!(format_value(&I64(255)), "255 (0xff)");
+    assert_eq!(format_value(&F32(1.5)), "1.5 (0x3fc00000)");
+    assert_eq!(
+        format_value(&F32(f32::from_bits(0xffa0_0000))),
+        "-nan:0x200000 (0xffa00000)"
+    );
+    assert_eq!(format_value(&F64(-0.0)), "-0 (0x8000000000000000)");
+}
+
+#[test]
+fn test_parse_args() {
+    use ValueType::*;
+    let args = parse_args(&[I32, I64, F32, F64], &["-1", "0xff", "1.5", "inf"]).unwrap();
+    assert_eq!(
+        args,
+        vec![
+            WasmValue::I32(-1),
+            WasmValue::I64(255),
+            WasmValue::F32(1.5),
+            WasmValue::F64(f64::INFINITY)
+        ]
+    );
+    assert!(parse_args::<&str>(&[I32], &[]).is_err());
+    let err = parse_args(&[I32, I32], &["1", "x"]).unwrap_err();
+    assert_eq!(err.to_string(), "argument 1: bad I32 `x`");
+}
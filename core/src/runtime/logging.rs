@@ -0,0 +1,29 @@
+//! 内部日志宏：启用 `log` feature 时转发到 `log` crate，由使用者选择 logger 和级别；
+//! 否则不输出，参数也不会被求值
+//!
+//! 级别约定：模块解码、实例化的概要为 info，每个段为 debug，每次函数调用为 trace
+
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        ::log::$level!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    }};
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::runtime::logging::log_at!(info, $($arg)*) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::runtime::logging::log_at!(debug, $($arg)*) };
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => { $crate::runtime::logging::log_at!(trace, $($arg)*) };
+}
+
+pub(crate) use {debug, info, log_at, trace};
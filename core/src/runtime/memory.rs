@@ -0,0 +1,140 @@
+//! 宿主函数访问 guest 内存的接口，指针越界时返回错误而不是 panic
+
+use std::ffi::CStr;
+use std::ops::Range;
+
+use anyhow::{anyhow, Context};
+
+use super::decoder::WasmModule;
+
+/// 可以按小端序从内存中读写的数值类型
+pub trait LeBytes: Sized {
+    const SIZE: usize;
+    fn from_le(bytes: &[u8]) -> Self;
+    fn write_le(&self, buf: &mut [u8]);
+}
+
+macro_rules! impl_le_bytes {
+    ($($ty:ty),*) => {
+        $(impl LeBytes for $ty {
+            const SIZE: usize = std::mem::size_of::<$ty>();
+            fn from_le(bytes: &[u8]) -> Self {
+                <$ty>::from_le_bytes(bytes.try_into().unwrap())
+            }
+            fn write_le(&self, buf: &mut [u8]) {
+                buf.copy_from_slice(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+impl_le_bytes!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+pub struct MemoryView<'a> {
+    data: &'a mut [u8],
+}
+
+impl<'a> MemoryView<'a> {
+    pub fn new(data: &'a mut [u8]) -> Self {
+        Self { data }
+    }
+
+    /// 内存大小（字节）
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn range(&self, ptr: u32, len: usize) -> anyhow::Result<Range<usize>> {
+        let start = ptr as usize;
+        start
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .map(|end| start..end)
+            .with_context(|| {
+                format!(
+                    "out of bounds memory access: ptr = {ptr}, len = {len}, size = {}",
+                    self.data.len()
+                )
+            })
+    }
+
+    pub fn read_bytes(&self, ptr: u32, len: u32) -> anyhow::Result<&[u8]> {
+        let range = self.range(ptr, len as usize)?;
+        Ok(&self.data[range])
+    }
+
+    pub fn write_bytes(&mut self, ptr: u32, bytes: &[u8]) -> anyhow::Result<()> {
+        let range = self.range(ptr, bytes.len())?;
+        self.data[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// 读取从 ptr 开始、以 `\0` 结尾的字符串
+    pub fn read_cstr(&self, ptr: u32) -> anyhow::Result<&CStr> {
+        let range = self.range(ptr, 0)?;
+        CStr::from_bytes_until_nul(&self.data[range.start..])
+            .map_err(|_| anyhow!("unterminated C string at ptr = {ptr}"))
+    }
+
+    pub fn read_utf8(&self, ptr: u32, len: u32) -> anyhow::Result<&str> {
+        let bytes = self.read_bytes(ptr, len)?;
+        std::str::from_utf8(bytes).with_context(|| format!("invalid utf-8 string at ptr = {ptr}"))
+    }
+
+    /// 按小端序读取一个数值，如 `view.read_le::<u32>(ptr)`
+    pub fn read_le<T: LeBytes>(&self, ptr: u32) -> anyhow::Result<T> {
+        let range = self.range(ptr, T::SIZE)?;
+        Ok(T::from_le(&self.data[range]))
+    }
+
+    pub fn write_le<T: LeBytes>(&mut self, ptr: u32, value: T) -> anyhow::Result<()> {
+        let range = self.range(ptr, T::SIZE)?;
+        value.write_le(&mut self.data[range]);
+        Ok(())
+    }
+}
+
+impl WasmModule {
+    /// 第 idx 个内存的访问视图，供宿主函数读写 guest 指针
+    pub fn memory_view(&mut self, idx: usize) -> anyhow::Result<MemoryView<'_>> {
+        let data = self
+            .mem
+            .get_mut(idx)
+            .with_context(|| format!("memory {idx} not found"))?;
+        Ok(MemoryView::new(data))
+    }
+}
+
+#[test]
+fn test_memory_view() {
+    let mut buf = vec![0u8; 16];
+    let mut view = MemoryView::new(&mut buf);
+    view.write_le(0, 0x1234_5678u32).unwrap();
+    assert_eq!(view.read_le::<u32>(0).unwrap(), 0x1234_5678);
+    assert_eq!(view.read_le::<u16>(2).unwrap(), 0x1234);
+    view.write_le(8, -2.5f64).unwrap();
+    assert_eq!(view.read_le::<f64>(8).unwrap(), -2.5);
+    assert!(view.read_le::<u64>(9).is_err());
+    assert!(view.write_le(u32::MAX, 1u8).is_err());
+
+    view.write_bytes(4, b"hi\0").unwrap();
+    assert_eq!(view.read_bytes(4, 2).unwrap(), b"hi");
+    assert_eq!(view.read_utf8(4, 2).unwrap(), "hi");
+    assert_eq!(view.read_cstr(4).unwrap().to_bytes(), b"hi");
+    assert!(view.read_bytes(15, 2).is_err());
+    assert!(view.write_bytes(14, b"abc").is_err());
+
+    view.write_bytes(12, &[0xff, 0xfe, 0x41, 0x42]).unwrap();
+    assert!(view.read_utf8(12, 2).is_err());
+    // 直到内存末尾都没有 \0
+    assert!(view.read_cstr(14).is_err());
+    assert!(view.read_cstr(16).is_err());
+    assert_eq!(
+        view.read_bytes(16, 1).unwrap_err().to_string(),
+        "out of bounds memory access: ptr = 16, len = 1, size = 16"
+    );
+}
@@ -0,0 +1,476 @@
+//! 自动缩小触发解码错误、trap 或 panic 的模块
+//!
+//! 反复尝试下面的化简，只要失败现象不变（见 [`Outcome::matches`]）就保留结果：
+//! - 删除整个 section，删除或截短无法解析的尾部字节
+//! - 通过 extract 去掉不可达的函数，再逐个去掉导出函数或 start 函数
+//! - 删除单个导出项
+//! - 把函数体替换为 `unreachable`
+//! - 清空 data 段的内容
+//!
+//! 每次只接受更小的模块，没有化简可以继续时结束。
+
+use std::any::Any;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::ensure;
+
+use super::cancel::CancellationToken;
+use super::config::RuntimeConfig;
+use super::decoder::{FuncKind, WasmModule, WasmValue};
+use super::extract::{copy_expr, extract, Index, Reader};
+use super::section::export::ExportKind;
+use super::section::typings::ValueType;
+use super::trap::Trap;
+use crate::leb::encode_leb_u32 as leb_u32;
+
+/// 解码、实例化并依次调用所有导出函数的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Pass,
+    Decode(String),
+    Instantiate(String),
+    Trap(String),
+    Panic(String),
+    /// 超时被取消，可能是化简引入了死循环，不算作失败
+    Timeout,
+}
+
+impl Outcome {
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, Outcome::Pass | Outcome::Timeout)
+    }
+
+    /// 同一类失败且消息相同；消息中的数字（偏移、地址等）会随模块变化，比较时忽略
+    pub fn matches(&self, other: &Outcome) -> bool {
+        let normalize = |msg: &str| {
+            let mut out = String::new();
+            for c in msg.chars() {
+                match c.is_ascii_digit() {
+                    true if out.ends_with('#') => {}
+                    true => out.push('#'),
+                    false => out.push(c),
+                }
+            }
+            out
+        };
+        match (self, other) {
+            (Outcome::Decode(a), Outcome::Decode(b))
+            | (Outcome::Instantiate(a), Outcome::Instantiate(b))
+            | (Outcome::Trap(a), Outcome::Trap(b))
+            | (Outcome::Panic(a), Outcome::Panic(b)) => normalize(a) == normalize(b),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Pass => write!(f, "ok"),
+            Outcome::Decode(msg) => write!(f, "decode error: {msg}"),
+            Outcome::Instantiate(msg) => write!(f, "instantiation error: {msg}"),
+            Outcome::Trap(msg) => write!(f, "trap: {msg}"),
+            Outcome::Panic(msg) => write!(f, "panic: {msg}"),
+            Outcome::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+/// 运行一次模块，超过 timeout 时通过 CancellationToken 中断
+pub fn run_module(buf: &[u8], timeout: Duration) -> Outcome {
+    let token = CancellationToken::new();
+    let (tx, rx) = mpsc::channel::<()>();
+    let watchdog = {
+        let token = token.clone();
+        thread::spawn(move || {
+            if rx.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                token.cancel();
+            }
+        })
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| execute(buf, token)));
+    drop(tx);
+    let _ = watchdog.join();
+    result.unwrap_or_else(|payload| Outcome::Panic(panic_message(payload)))
+}
+
+/// catch_unwind 捕获到的 panic 信息
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(msg) => msg.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+fn trap_outcome(trap: Trap) -> Outcome {
+    match trap {
+        Trap::Cancelled => Outcome::Timeout,
+        trap => Outcome::Trap(trap.to_string()),
+    }
+}
+
+fn execute(buf: &[u8], token: CancellationToken) -> Outcome {
+    let mut module = WasmModule::default(buf.to_vec());
+    module.config = RuntimeConfig::default().cancellation_token(token);
+    if let Err(err) = module.decode() {
+        // 偏移会随着缩减变化，只比较错误原因
+        return Outcome::Decode(err.kind.to_string());
+    }
+    if let Err(err) = module.instance(None) {
+        return match err.downcast::<Trap>() {
+            Ok(trap) => trap_outcome(trap),
+            Err(err) => Outcome::Instantiate(format!("{err:#}")),
+        };
+    }
+
+    // 按导出名排序，保证每次调用顺序一致
+    let mut funcs = module
+        .exports
+        .iter()
+        .filter_map(|(name, kind)| match kind {
+            ExportKind::Func(idx) => Some((name.clone(), *idx)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    funcs.sort();
+    for (_, idx) in funcs {
+        let ty = match &module.func[idx] {
+            FuncKind::Import(ty, _) | FuncKind::Local((ty, _)) => *ty,
+        };
+        // 引用类型的参数无法构造，跳过
+        let Some(args) = module.section.types.entries[ty]
+            .params
+            .iter()
+            .map(|ty| match ty {
+                ValueType::I32 => Some(WasmValue::I32(0)),
+                ValueType::I64 => Some(WasmValue::I64(0)),
+                ValueType::F32 => Some(WasmValue::F32(0.0)),
+                ValueType::F64 => Some(WasmValue::F64(0.0)),
+                ValueType::V128 => Some(WasmValue::V128(0)),
+                ValueType::FuncRef | ValueType::ExternRef | ValueType::AnyRef => None,
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        module.sp = 0;
+        module.fp = 0;
+        module.csp = 0;
+        module.stack_check();
+        for arg in args {
+            module.sp += 1;
+            module.stack[module.sp] = arg;
+        }
+        if let Err(trap) = module.call(idx) {
+            return trap_outcome(trap);
+        }
+    }
+    Outcome::Pass
+}
+
+/// 按 section 拆开的模块，tail 为无法解析的尾部字节
+struct Sections<'a> {
+    header: &'a [u8],
+    sections: Vec<(u8, &'a [u8])>,
+    tail: &'a [u8],
+}
+
+impl<'a> Sections<'a> {
+    fn parse(buf: &'a [u8]) -> Self {
+        let split = buf.len().min(8);
+        let mut r = Reader::new(&buf[split..]);
+        let mut sections = vec![];
+        let mut end = 0;
+        while !r.is_empty() {
+            let section = r.byte().and_then(|id| {
+                let len = r.u32()? as usize;
+                Ok((id, r.bytes(len)?))
+            });
+            match section {
+                Ok(section) => sections.push(section),
+                Err(_) => break,
+            }
+            end = r.pos;
+        }
+        Sections {
+            header: &buf[..split],
+            sections,
+            tail: &buf[split + end..],
+        }
+    }
+
+    /// 替换（None 表示删除）第 index 个 section 后重新拼接
+    fn replace(&self, index: usize, content: Option<&[u8]>) -> Vec<u8> {
+        let mut out = self.header.to_vec();
+        for (i, (id, section)) in self.sections.iter().enumerate() {
+            let section = if i == index { content } else { Some(*section) };
+            if let Some(section) = section {
+                out.push(*id);
+                leb_u32(section.len() as u32, &mut out);
+                out.extend(section);
+            }
+        }
+        out.extend(self.tail);
+        out
+    }
+
+    fn section(&self, id: u8) -> Option<(usize, &'a [u8])> {
+        self.sections
+            .iter()
+            .enumerate()
+            .find(|(_, (section_id, _))| *section_id == id)
+            .map(|(i, (_, content))| (i, *content))
+    }
+}
+
+/// 拆出 vec 中每一项的原始字节
+fn items<'a>(
+    content: &'a [u8],
+    mut item: impl FnMut(&mut Reader<'a>) -> anyhow::Result<()>,
+) -> anyhow::Result<Vec<&'a [u8]>> {
+    let mut r = Reader::new(content);
+    let mut items = vec![];
+    for _ in 0..r.u32()? {
+        let start = r.pos;
+        item(&mut r)?;
+        items.push(&content[start..r.pos]);
+    }
+    ensure!(r.is_empty(), "trailing bytes at offset {}", r.pos);
+    Ok(items)
+}
+
+/// 把第 index 项替换为 item（None 表示删除）后重新编码 vec
+fn replace_item(items: &[&[u8]], index: usize, item: Option<&[u8]>) -> Vec<u8> {
+    let mut content: Vec<u8> = vec![];
+    let mut count = 0;
+    for (i, raw) in items.iter().enumerate() {
+        let raw = if i == index { item } else { Some(*raw) };
+        if let Some(raw) = raw {
+            content.extend(raw);
+            count += 1;
+        }
+    }
+    let mut out = vec![];
+    leb_u32(count, &mut out);
+    out.extend(content);
+    out
+}
+
+fn skip_expr(r: &mut Reader) -> anyhow::Result<()> {
+    copy_expr(r, &mut vec![], &mut |idx| match idx {
+        Index::Func(v) | Index::Type(v) | Index::Table(v) => v,
+    })
+}
+
+/// 候选的化简结果，按化简力度从大到小排列
+fn candidates(buf: &[u8]) -> Vec<Vec<u8>> {
+    let parsed = Sections::parse(buf);
+    let mut out = vec![];
+
+    for index in 0..parsed.sections.len() {
+        out.push(parsed.replace(index, None));
+    }
+    if !parsed.tail.is_empty() {
+        out.push(buf[..buf.len() - parsed.tail.len()].to_vec());
+        // 截断的 section 通常在截得更短时仍然以同样的方式失败
+        out.push(buf[..buf.len() - parsed.tail.len() / 2].to_vec());
+    }
+
+    // 导出函数和 start 函数作为 extract 的入口
+    let exports = parsed
+        .section(7)
+        .and_then(|(_, content)| {
+            items(content, |r| {
+                r.name()?;
+                r.byte()?;
+                r.u32()?;
+                Ok(())
+            })
+            .ok()
+        })
+        .unwrap_or_default();
+    let mut roots = exports
+        .iter()
+        .filter_map(|raw| {
+            let mut r = Reader::new(raw);
+            let len = r.u32().ok()? as usize;
+            let name = std::str::from_utf8(r.bytes(len).ok()?).ok()?;
+            (r.byte().ok()? == 0).then(|| name.to_string())
+        })
+        .collect::<Vec<_>>();
+    if let Some((_, content)) = parsed.section(8) {
+        if let Ok(start) = Reader::new(content).u32() {
+            roots.push(start.to_string());
+        }
+    }
+    out.extend(extract(buf, &roots));
+    for index in 0..roots.len() {
+        let mut roots = roots.clone();
+        roots.remove(index);
+        out.extend(extract(buf, &roots));
+    }
+
+    if let Some((section, _)) = parsed.section(7) {
+        for index in 0..exports.len() {
+            out.push(parsed.replace(section, Some(&replace_item(&exports, index, None))));
+        }
+    }
+
+    if let Some((section, content)) = parsed.section(10) {
+        let bodies = items(content, |r| {
+            let size = r.u32()? as usize;
+            r.bytes(size)?;
+            Ok(())
+        })
+        .unwrap_or_default();
+        // size = 3，无局部变量，unreachable，end
+        let unreachable = [0x03, 0x00, 0x00, 0x0b];
+        for (index, body) in bodies.iter().enumerate() {
+            if body.len() > unreachable.len() {
+                let content = replace_item(&bodies, index, Some(&unreachable));
+                out.push(parsed.replace(section, Some(&content)));
+            }
+        }
+    }
+
+    if let Some((section, content)) = parsed.section(11) {
+        // 每个数据段在内容之前的部分（flags、内存索引、偏移表达式）
+        let mut heads = vec![];
+        let segments = items(content, |r| {
+            let start = r.pos;
+            let flags = r.u32()?;
+            if flags == 2 {
+                r.u32()?;
+            }
+            if flags != 1 {
+                skip_expr(r)?;
+            }
+            let end = r.pos;
+            heads.push((start, end));
+            r.name()?;
+            Ok(())
+        })
+        .unwrap_or_default();
+        for (index, segment) in segments.iter().enumerate() {
+            let (start, end) = heads[index];
+            if segment.len() > end - start + 1 {
+                let mut item = content[start..end].to_vec();
+                item.push(0x00);
+                let content = replace_item(&segments, index, Some(&item));
+                out.push(parsed.replace(section, Some(&content)));
+            }
+        }
+    }
+
+    out.retain(|candidate| candidate.len() < buf.len());
+    out
+}
+
+/// 缩小一个会失败的模块，返回仍然以相同方式失败的最小模块
+pub fn minimize(buf: &[u8], timeout: Duration) -> anyhow::Result<Vec<u8>> {
+    let target = run_module(buf, timeout);
+    ensure!(target.is_failure(), "module does not fail ({target})");
+
+    let mut best = buf.to_vec();
+    'shrink: loop {
+        for candidate in candidates(&best) {
+            if run_module(&candidate, timeout).matches(&target) {
+                best = candidate;
+                continue 'shrink;
+            }
+        }
+        return Ok(best);
+    }
+}
+
+#[test]
+fn test_minimize_trap() {
+    use super::testing::{func_bytes, vec};
+
+    let name = |s: &str| {
+        let mut buf = vec![s.len() as u8];
+        buf.extend(s.as_bytes());
+        buf
+    };
+    let export = |s: &str, idx: u8| {
+        let mut buf = name(s);
+        buf.extend([0x00, idx]);
+        buf
+    };
+    let bodies: &[&[u8]] = &[
+        &[0x41, 0x01, 0x41, 0x02, 0x6a, 0x1a], // 0: 正常返回
+        &[0x41, 0x00, 0x00],                   // 1: trap
+        &[0x41, 0x01, 0x41, 0x02, 0x41, 0x03, 0x6a, 0x6a], // 2: 未导出
+    ];
+    let memory = (5, vec![0x01, 0x00, 0x01]);
+    let data = (11, vec(&[vec![0x00, 0x41, 0x00, 0x0b, 0x04, 1, 2, 3, 4]]));
+    let exports = (7, vec(&[export("a", 0), export("b", 1)]));
+    let buf = func_bytes(&[], &[], bodies, &[memory, data, exports, (0, name("x"))]);
+
+    let timeout = Duration::from_secs(5);
+    let target = run_module(&buf, timeout);
+    assert!(matches!(target, Outcome::Trap(_)), "{target}");
+
+    let out = minimize(&buf, timeout).unwrap();
+    assert!(out.len() < buf.len());
+    assert!(run_module(&out, timeout).matches(&target));
+    let mut module = WasmModule::default(out);
+    module.decode().unwrap();
+    assert_eq!(module.section.func.entries.len(), 1);
+    assert_eq!(module.section.data.entries.len(), 0);
+    assert_eq!(module.section.custom.name, "");
+
+    let pass = func_bytes(&[], &[], &[&[0x01]], &[]);
+    assert_eq!(
+        minimize(&pass, timeout).unwrap_err().to_string(),
+        "module does not fail (ok)"
+    );
+}
+
+#[test]
+fn test_minimize_decode_error() {
+    use super::testing::{func_bytes, vec};
+
+    // 导出名不是合法的 UTF-8
+    let exports = (7, vec(&[vec![0x02, 0xff, 0xfe, 0x00, 0x00]]));
+    let memory = (5, vec![0x01, 0x00, 0x01]);
+    let mut buf = func_bytes(&[], &[], &[&[0x01]], &[memory, exports]);
+    buf.extend([0x0b, 0x7f]);
+
+    let timeout = Duration::from_secs(5);
+    let target = run_module(&buf, timeout);
+    assert!(matches!(target, Outcome::Decode(_)), "{target}");
+    let out = minimize(&buf, timeout).unwrap();
+    assert!(run_module(&out, timeout).matches(&target));
+    // 只剩 header 和导出段
+    let parsed = Sections::parse(&out);
+    assert_eq!(
+        parsed
+            .sections
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>(),
+        vec![7]
+    );
+    assert!(parsed.tail.is_empty());
+}
+
+#[test]
+fn test_minimize_timeout() {
+    use super::testing::{func_bytes, vec};
+
+    // loop br 0 end
+    let exports = (7, vec(&[vec![0x01, b'f', 0x00, 0x00]]));
+    let buf = func_bytes(&[], &[], &[&[0x03, 0x40, 0x0c, 0x00, 0x0b]], &[exports]);
+    assert_eq!(
+        run_module(&buf, Duration::from_millis(50)),
+        Outcome::Timeout
+    );
+}
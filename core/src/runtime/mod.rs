@@ -0,0 +1,141 @@
+use self::config::RuntimeConfig;
+use self::decoder::WasmModule;
+use self::scheduler::Scheduler;
+
+#[cfg(feature = "serde")]
+pub mod cache;
+pub mod callgraph;
+pub mod cancel;
+pub mod check;
+pub mod component;
+pub mod config;
+pub mod constants;
+pub mod constexpr;
+pub mod coverage;
+pub mod debug;
+pub mod debuginfo;
+pub mod decoder;
+#[cfg(feature = "differential")]
+pub mod differential;
+pub mod disasm;
+pub mod emscripten;
+pub mod error;
+pub mod exports;
+pub mod extract;
+pub mod float;
+#[cfg(feature = "async")]
+pub mod future;
+pub mod global;
+pub mod guard;
+pub mod hexdump;
+pub mod host;
+pub mod ir;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod linear;
+pub mod linker;
+pub mod literal;
+pub(crate) mod logging;
+pub mod memory;
+pub mod minimize;
+pub mod pool;
+pub mod profile;
+pub mod repl;
+pub mod scheduler;
+pub mod section;
+#[cfg(feature = "smith")]
+pub mod smith;
+pub mod snapshot;
+pub mod spectest;
+pub mod stats;
+pub mod table;
+#[cfg(test)]
+pub mod testing;
+pub mod threaded;
+pub mod trace;
+pub mod transform;
+pub mod trap;
+pub mod untyped;
+pub mod validate;
+pub mod wasi;
+pub mod wast;
+pub mod wat;
+
+#[derive(Debug, Default)]
+pub struct OxygenRuntime {
+    pub modes: Vec<WasmModule>,
+    pub config: RuntimeConfig,
+    /// `spawn` 的任务，由 `run_round` 或 `run_tasks` 轮流执行
+    pub scheduler: Scheduler,
+}
+
+impl OxygenRuntime {
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self {
+            modes: vec![],
+            config,
+            scheduler: Scheduler::default(),
+        }
+    }
+    /// buf 为只内嵌一个 core module 的组件时加载其中的模块
+    pub fn load(&mut self, buf: Vec<u8>) -> anyhow::Result<()> {
+        let mut m = WasmModule::default(component::unwrap_core(buf)?);
+        m.config = self.config.clone();
+        m.decode()?;
+        self.modes.push(m);
+        Ok(())
+    }
+    /// 加载 `WasmModule::serialize` 得到的预编译模块，buf 为原来的模块字节
+    #[cfg(feature = "serde")]
+    pub fn load_compiled(&mut self, buf: Vec<u8>, compiled: &[u8]) -> anyhow::Result<()> {
+        let mut m = WasmModule::deserialize(buf, compiled)?;
+        m.config = self.config.clone();
+        self.modes.push(m);
+        Ok(())
+    }
+    /// 加载 `oxygen compile` 的产物，见 `WasmModule::compile_artifact`
+    #[cfg(feature = "serde")]
+    pub fn load_artifact(&mut self, buf: &[u8]) -> anyhow::Result<()> {
+        let mut m = WasmModule::from_artifact(buf)?;
+        m.config = self.config.clone();
+        self.modes.push(m);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse() {
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, // magic = \0asm
+        0x01, 0x00, 0x00, 0x00, // version  = 1 (little endian)
+        //
+        0x01, 0x07, 0x01, // type section
+        0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // func type 1
+        //
+        0x03, 0x05, 0x04, 0x00, 0x00, 0x00, 0x00, // func section
+        //
+        0x05, 0x03, 0x01, 0x00, 0x01, // memory
+        //
+        0x07, 0x19, 0x04, // export section
+        0x03, 0x61, 0x64, 0x64, 0x00, 0x00, // export 1
+        0x03, 0x73, 0x75, 0x62, 0x00, 0x01, // export 2
+        0x03, 0x6d, 0x75, 0x6c, 0x00, 0x02, // export 3
+        0x03, 0x64, 0x69, 0x76, 0x00, 0x03, // export 4
+        //
+        0x0a, 0x21, 0x04, // code sectiion
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b, // func body 1
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6b, 0x0b, // func body 2
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6c, 0x0b, // func body 3
+        0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6e, 0x0b, // func body 4
+        //
+        0x0b, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0x61, // data section
+    ];
+    let mut wasm = decoder::WasmModule::default(buf);
+    wasm.decode().unwrap();
+
+    assert_eq!(wasm.version, 0x01);
+    assert_eq!(wasm.section.types.type_count, 0x01);
+    assert_eq!(wasm.section.func.func_count, 0x04);
+    assert_eq!(wasm.section.export.export_count, 0x04);
+    assert_eq!(wasm.section.code.body_count, 0x04);
+}
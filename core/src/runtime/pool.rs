@@ -0,0 +1,190 @@
+//! 在工作线程池上执行 guest 调用，服务端可以并发执行很多调用
+//!
+//! 实例以 `SharedInstance`（`Arc<Mutex<WasmModule>>`）在线程之间共享：同一个实例上的调用
+//! 互斥执行，不同实例上的调用并行。通常用 `OxygenRuntime::instantiate_shared` 为同一个模块
+//! 创建多个实例，分别提交。
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::anyhow;
+
+use super::decoder::{WasmModule, WasmValue};
+use super::linker::{shared_instance, Linker, SharedInstance};
+use super::minimize::panic_message;
+use super::OxygenRuntime;
+
+// 实例在工作线程之间移动，并通过 Mutex 共享
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<WasmModule>();
+    assert_send_sync::<OxygenRuntime>();
+};
+
+/// 每层 wasm 调用占用较多原生栈，工作线程使用较大的栈
+const STACK_SIZE: usize = 64 << 20;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug)]
+pub struct WorkerPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// threads 为 0 时取 CPU 核数
+    pub fn new(threads: usize) -> anyhow::Result<Self> {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads)
+            .map(|i| {
+                let receiver = receiver.clone();
+                thread::Builder::new()
+                    .name(format!("oxygen-worker-{i}"))
+                    .stack_size(STACK_SIZE)
+                    .spawn(move || loop {
+                        // 只在取任务时持有锁，发送端关闭后退出
+                        let job = receiver.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            sender: Some(sender),
+            workers,
+        })
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// 在工作线程上以 args 调用 instance 的导出函数 export，通过返回的 `JoinHandle` 取得结果；
+    /// 调用中的 panic 转为错误，之后这个实例不能再使用
+    pub fn spawn(&self, instance: &SharedInstance, export: &str, args: &[WasmValue]) -> JoinHandle {
+        let (tx, rx) = mpsc::channel();
+        let instance = instance.clone();
+        let (export, args) = (export.to_string(), args.to_vec());
+        let job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut wasm = instance
+                    .lock()
+                    .map_err(|_| anyhow!("instance was poisoned by a panic in an earlier call"))?;
+                wasm.invoke(&export, &args)
+            }));
+            let result = result.unwrap_or_else(|payload| {
+                Err(anyhow!("`{export}` panicked: {}", panic_message(payload)))
+            });
+            // JoinHandle 已经被丢弃时不需要结果
+            let _ = tx.send(result);
+        });
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(job);
+        }
+        JoinHandle { receiver: rx }
+    }
+}
+
+/// 关闭任务队列，等待已经提交的调用执行完
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// `WorkerPool::spawn` 提交的调用
+#[derive(Debug)]
+pub struct JoinHandle {
+    receiver: Receiver<anyhow::Result<Vec<WasmValue>>>,
+}
+
+impl JoinHandle {
+    /// 等待调用完成，返回值与 `WasmModule::invoke` 相同
+    pub fn join(self) -> anyhow::Result<Vec<WasmValue>> {
+        self.receiver
+            .recv()
+            .map_err(|_| anyhow!("worker pool was shut down before the call ran"))?
+    }
+
+    /// 调用已经完成时返回结果，否则返回 None
+    pub fn try_join(&self) -> Option<anyhow::Result<Vec<WasmValue>>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl OxygenRuntime {
+    /// 以第 module 个模块的字节创建一个新的实例，导入由 linker 提供，供 `WorkerPool` 使用；
+    /// 每次调用得到互相独立的实例
+    pub fn instantiate_shared(
+        &self,
+        module: usize,
+        linker: &Linker,
+    ) -> anyhow::Result<SharedInstance> {
+        let raw = self
+            .modes
+            .get(module)
+            .ok_or_else(|| anyhow!("unknown module {module}"))?
+            .raw
+            .clone();
+        let mut wasm = WasmModule::default(raw);
+        wasm.config = self.config.clone();
+        wasm.decode()?;
+        linker.instantiate(&mut wasm)?;
+        Ok(shared_instance(wasm))
+    }
+}
+
+#[test]
+fn test_worker_pool() {
+    use super::wat;
+
+    let src = r#"(module
+      (global $calls (mut i32) (i32.const 0))
+      (func $fib (export "fib") (param i32) (result i32)
+        (global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+        (if (result i32) (i32.lt_u (local.get 0) (i32.const 2))
+          (then (local.get 0))
+          (else (i32.add
+            (call $fib (i32.sub (local.get 0) (i32.const 1)))
+            (call $fib (i32.sub (local.get 0) (i32.const 2)))))))
+      (func (export "calls") (result i32) (global.get $calls))
+      (func (export "boom") (unreachable)))"#;
+    let mut rt = OxygenRuntime::default();
+    rt.load(wat::compile(src).unwrap()).unwrap();
+    let linker = Linker::new();
+    let instances = (0..4)
+        .map(|_| rt.instantiate_shared(0, &linker).unwrap())
+        .collect::<Vec<_>>();
+
+    let pool = WorkerPool::new(4).unwrap();
+    assert_eq!(pool.threads(), 4);
+    let handles = (0..16)
+        .map(|i| pool.spawn(&instances[i % 4], "fib", &[WasmValue::I32(15)]))
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), [WasmValue::I32(610)]);
+    }
+    // 每个实例执行了 4 次调用，实例之间互不影响
+    for instance in &instances {
+        let calls = pool.spawn(instance, "calls", &[]).join().unwrap();
+        assert_eq!(calls, [WasmValue::I32(4 * 1973)]);
+    }
+
+    let err = pool.spawn(&instances[0], "boom", &[]).join().unwrap_err();
+    assert!(err.to_string().contains("unreachable"), "{err}");
+    assert!(pool.spawn(&instances[0], "missing", &[]).join().is_err());
+    assert!(rt.instantiate_shared(1, &linker).is_err());
+}
@@ -0,0 +1,298 @@
+//! 按函数统计执行情况，作为 `Tracer` 挂到 `RuntimeConfig::tracer` 上开启
+//!
+//! 统计每个函数的调用次数、执行的指令数和耗时，函数之间的调用次数，
+//! 以及循环（同一函数中跳回前面的指令）的执行次数；按调用栈统计的指令数
+//! 可以输出为 flamegraph 工具使用的 folded 格式。
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use super::decoder::{WasmModule, WasmValue};
+use super::section::export::ExportKind;
+use super::section::import;
+use super::section::opcode::Opcode;
+use super::trace::Tracer;
+use super::trap::Trap;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FuncProfile {
+    pub calls: u64,
+    /// 函数自身执行的指令数，不包括调用的函数
+    pub instructions: u64,
+    /// 不包括调用的函数的耗时
+    pub self_time: Duration,
+    /// 包括调用的函数的耗时，递归调用会重复计算
+    pub total_time: Duration,
+}
+
+/// 正在执行的函数
+#[derive(Debug)]
+struct Frame {
+    func: usize,
+    /// 调用栈在 stacks 中的编号
+    stack: usize,
+    start: Instant,
+    /// 调用的函数花费的时间
+    children: Duration,
+    /// 上一条执行的指令
+    last_pc: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiler {
+    pub funcs: HashMap<usize, FuncProfile>,
+    /// (调用者, 被调用者) 的调用次数
+    pub edges: HashMap<(usize, usize), u64>,
+    /// 循环开始的 (函数, pc) 的执行次数，pc 为模块中的操作码索引
+    pub loops: HashMap<(usize, usize), u64>,
+    /// 出现过的调用栈（从外到内的函数索引）和各自执行的指令数
+    stacks: Vec<(Vec<usize>, u64)>,
+    stack_ids: HashMap<Vec<usize>, usize>,
+    frames: Vec<Frame>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn stack_id(&mut self, stack: Vec<usize>) -> usize {
+        if let Some(&id) = self.stack_ids.get(&stack) {
+            return id;
+        }
+        let id = self.stacks.len();
+        self.stacks.push((stack.clone(), 0));
+        self.stack_ids.insert(stack, id);
+        id
+    }
+
+    fn leave(&mut self, func: usize) {
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        debug_assert_eq!(frame.func, func);
+        let elapsed = frame.start.elapsed();
+        let profile = self.funcs.entry(frame.func).or_default();
+        profile.total_time += elapsed;
+        profile.self_time += elapsed.saturating_sub(frame.children);
+        if let Some(parent) = self.frames.last_mut() {
+            parent.children += elapsed;
+        }
+    }
+
+    /// 结束还没有返回的函数（如 proc_exit 直接退出时），计入到现在为止的耗时
+    pub fn finish(&mut self) {
+        while let Some(frame) = self.frames.last() {
+            self.leave(frame.func);
+        }
+    }
+
+    /// folded 格式：每行为 `外层;...;内层 指令数`，names 给出函数名
+    pub fn folded(&self, names: &HashMap<usize, String>) -> String {
+        let name = |func: &usize| {
+            names
+                .get(func)
+                .cloned()
+                .unwrap_or_else(|| format!("func{func}"))
+        };
+        let mut lines = self
+            .stacks
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(stack, count)| {
+                let stack = stack.iter().map(name).collect::<Vec<_>>().join(";");
+                format!("{stack} {count}\n")
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.concat()
+    }
+
+    /// 按自身指令数排序的函数、调用最多的边和最热的循环，各取前 limit 个
+    pub fn summary(&self, names: &HashMap<usize, String>, limit: usize) -> String {
+        let name = |func: usize| {
+            names
+                .get(&func)
+                .cloned()
+                .unwrap_or_else(|| format!("func{func}"))
+        };
+        let mut out = String::new();
+        let mut funcs = self.funcs.iter().collect::<Vec<_>>();
+        funcs.sort_by(|a, b| {
+            (b.1.instructions, b.1.calls, a.0).cmp(&(a.1.instructions, a.1.calls, b.0))
+        });
+        let _ = writeln!(
+            out,
+            "{:>12} {:>10} {:>12} {:>12}  function",
+            "instructions", "calls", "self ms", "total ms"
+        );
+        for (func, p) in funcs.into_iter().take(limit) {
+            let _ = writeln!(
+                out,
+                "{:>12} {:>10} {:>12.3} {:>12.3}  {}",
+                p.instructions,
+                p.calls,
+                p.self_time.as_secs_f64() * 1000.0,
+                p.total_time.as_secs_f64() * 1000.0,
+                name(*func)
+            );
+        }
+        let mut edges = self.edges.iter().collect::<Vec<_>>();
+        edges.sort_by(|a, b| (b.1, a.0).cmp(&(a.1, b.0)));
+        if !edges.is_empty() {
+            let _ = writeln!(out, "\n{:>12}  call", "count");
+        }
+        for ((from, to), count) in edges.into_iter().take(limit) {
+            let _ = writeln!(out, "{count:>12}  {} -> {}", name(*from), name(*to));
+        }
+        let mut loops = self.loops.iter().collect::<Vec<_>>();
+        loops.sort_by(|a, b| (b.1, a.0).cmp(&(a.1, b.0)));
+        if !loops.is_empty() {
+            let _ = writeln!(out, "\n{:>12}  loop", "iterations");
+        }
+        for ((func, pc), count) in loops.into_iter().take(limit) {
+            let _ = writeln!(out, "{count:>12}  {} at pc {pc}", name(*func));
+        }
+        out
+    }
+}
+
+impl Tracer for Profiler {
+    fn op(&mut self, pc: usize, _op: &Opcode, _stack: &[WasmValue]) {
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        // 跳回到前面的指令是循环的下一次迭代
+        if frame.last_pc.is_some_and(|last| pc <= last) {
+            *self.loops.entry((frame.func, pc)).or_default() += 1;
+        }
+        frame.last_pc = Some(pc);
+        self.stacks[frame.stack].1 += 1;
+        self.funcs.entry(frame.func).or_default().instructions += 1;
+    }
+
+    fn call(&mut self, func: usize, _args: &[WasmValue]) {
+        let mut stack = self.frames.last().map_or(vec![], |frame| {
+            *self.edges.entry((frame.func, func)).or_default() += 1;
+            self.stacks[frame.stack].0.clone()
+        });
+        stack.push(func);
+        let stack = self.stack_id(stack);
+        self.funcs.entry(func).or_default().calls += 1;
+        self.frames.push(Frame {
+            func,
+            stack,
+            start: Instant::now(),
+            children: Duration::ZERO,
+            last_pc: None,
+        });
+    }
+
+    fn ret(&mut self, func: usize, _results: &[WasmValue]) {
+        self.leave(func);
+    }
+
+    fn trap(&mut self, func: usize, _trap: &Trap) {
+        self.leave(func);
+    }
+}
+
+/// 函数名：导入函数为 `模块.名字`，导出函数为导出名，其他的没有
+pub fn func_names(wasm: &WasmModule) -> HashMap<usize, String> {
+    let imports = wasm
+        .section
+        .import
+        .entries
+        .iter()
+        .filter(|ipt| matches!(ipt.kind, import::Kind::Func(_)))
+        .enumerate()
+        .map(|(idx, ipt)| (idx, format!("{}.{}", ipt.mod_name, ipt.field_name)));
+    // 从 export 段而不是 `wasm.exports` 读取，没有实例化的模块也有名字
+    let exports = wasm
+        .section
+        .export
+        .entries
+        .iter()
+        .filter_map(|export| match export.kind {
+            ExportKind::Func(idx) => Some((idx, export.name.clone())),
+            _ => None,
+        });
+    let mut names = HashMap::new();
+    for (idx, name) in imports.chain(exports) {
+        // 同一个函数以多个名字导出时取字典序最小的，保证输出稳定
+        let entry = names.entry(idx).or_insert_with(|| name.clone());
+        if name < *entry {
+            *entry = name;
+        }
+    }
+    names
+}
+
+#[test]
+fn test_profiler() {
+    use std::sync::{Arc, Mutex};
+
+    use super::config::RuntimeConfig;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (func $leaf (param i32) (result i32)
+            (i32.add (local.get 0) (i32.const 1)))
+          (func $sum (export "sum") (param i32) (result i32)
+            (local i32)
+            (block
+              (loop
+                (br_if 1 (i32.eqz (local.get 0)))
+                (local.set 1 (call $leaf (local.get 1)))
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br 0)))
+            (local.get 1))
+          (func (export "main") (result i32)
+            (call $sum (i32.const 3))))"#,
+    )
+    .unwrap();
+    let profiler = Arc::new(Mutex::new(Profiler::new()));
+    let mut wasm = WasmModule::default(buf);
+    wasm.config = RuntimeConfig::default();
+    wasm.config.tracer = Some(profiler.clone());
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    assert_eq!(wasm.invoke("main", &[]).unwrap(), vec![WasmValue::I32(3)]);
+
+    let profiler = profiler.lock().unwrap();
+    let names = func_names(&wasm);
+    assert_eq!(names.get(&1).map(String::as_str), Some("sum"));
+    assert_eq!(profiler.funcs[&2].calls, 1);
+    assert_eq!(profiler.funcs[&1].calls, 1);
+    assert_eq!(profiler.funcs[&0].calls, 3);
+    assert_eq!(profiler.edges[&(1, 0)], 3);
+    assert_eq!(profiler.edges[&(2, 1)], 1);
+    // 循环体执行 4 次，跳回 3 次
+    assert_eq!(profiler.loops.values().copied().collect::<Vec<_>>(), [3]);
+    assert_eq!(profiler.funcs[&0].instructions, 3 * 3);
+    assert!(profiler.funcs[&2].total_time >= profiler.funcs[&1].total_time);
+
+    let folded = profiler.folded(&names);
+    let lines = folded.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        format!("main {}", profiler.funcs[&2].instructions)
+    );
+    assert!(lines[1].starts_with("main;sum "));
+    assert_eq!(lines[2], "main;sum;func0 9");
+    let total = lines
+        .iter()
+        .map(|line| line.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+        .sum::<u64>();
+    assert_eq!(
+        total,
+        profiler.funcs.values().map(|p| p.instructions).sum::<u64>()
+    );
+
+    let summary = profiler.summary(&names, 2);
+    assert!(summary.contains("3  sum -> func0"), "{summary}");
+    assert!(summary.contains("iterations"));
+}
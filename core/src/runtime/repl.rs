@@ -0,0 +1,323 @@
+//! `oxygen repl` 的命令解释：列出导出、调用函数、读写内存、查看全局变量、重新加载模块
+
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, ensure, Context};
+
+use super::config::RuntimeConfig;
+use super::decoder::{WasmModule, WasmValue};
+use super::linker::Linker;
+use super::literal::{format_value, parse_args, parse_value};
+use super::section::export::ExportKind;
+use super::section::typings::ValueType;
+
+const HELP: &str = "\
+exports                  list exports with their types
+call NAME [VALUES...]    call an exported function
+peek ADDR [LEN]          hex dump LEN (default 16) bytes of memory 0
+poke ADDR BYTE...        write bytes to memory 0
+global [NAME]            print exported globals
+reload                   read the module file again and re-instantiate it
+help                     show this message
+quit                     leave the repl";
+
+/// 类型名按文本格式小写，如 `i32`
+fn type_name(ty: &ValueType) -> String {
+    ty.to_string().to_lowercase()
+}
+
+fn type_list(types: &[ValueType]) -> String {
+    types.iter().map(type_name).collect::<Vec<_>>().join(" ")
+}
+
+/// 按导出函数的签名解析参数并调用，每个结果为一行 `类型: 值`
+pub fn invoke_text<S: AsRef<str>>(
+    wasm: &mut WasmModule,
+    name: &str,
+    values: &[S],
+) -> anyhow::Result<Vec<String>> {
+    let (_, ty) = wasm.export_func(name)?;
+    let results = ty.results.clone();
+    let args = parse_args(&ty.params, values).with_context(|| format!("can't invoke `{name}`"))?;
+    let values = wasm.invoke(name, &args)?;
+    Ok(results
+        .iter()
+        .zip(&values)
+        .map(|(ty, value)| format!("{}: {}", type_name(ty), format_value(value)))
+        .collect())
+}
+
+/// 地址和字节数，支持十进制和十六进制
+fn parse_u32(text: &str) -> anyhow::Result<u32> {
+    match parse_value(text, ValueType::I32)? {
+        WasmValue::I32(v) if !text.starts_with('-') => Ok(v as u32),
+        _ => bail!("`{text}` is not an unsigned integer"),
+    }
+}
+
+/// 每行 16 字节的十六进制和 ASCII
+pub(crate) fn hex_dump(addr: u32, bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, line)| {
+            let hex = line
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let text = line
+                .iter()
+                .map(|&b| match b {
+                    0x20..=0x7e => b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            format!("{:08x}: {hex:<47}  {text}", addr as usize + 16 * i)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub struct Repl {
+    /// 模块文件，reload 时重新读取
+    pub path: PathBuf,
+    pub config: RuntimeConfig,
+    pub linker: Linker,
+    pub wasm: WasmModule,
+}
+
+impl Repl {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        config: RuntimeConfig,
+        linker: Linker,
+    ) -> anyhow::Result<Self> {
+        let path = path.into();
+        let wasm = Self::load(&path, &config, &linker)?;
+        Ok(Self {
+            path,
+            config,
+            linker,
+            wasm,
+        })
+    }
+
+    fn load(path: &PathBuf, config: &RuntimeConfig, linker: &Linker) -> anyhow::Result<WasmModule> {
+        let buf = std::fs::read(path).with_context(|| format!("can't read file {path:?}"))?;
+        let mut wasm = WasmModule::default(buf);
+        wasm.config = config.clone();
+        wasm.decode()?;
+        linker.instantiate(&mut wasm)?;
+        Ok(wasm)
+    }
+
+    /// 重新读取并实例化模块，失败时保留原来的实例
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        self.wasm = Self::load(&self.path, &self.config, &self.linker)?;
+        Ok(())
+    }
+
+    /// 按名字排序的导出
+    fn exports(&self) -> Vec<(&String, &ExportKind)> {
+        let mut exports = self.wasm.exports.iter().collect::<Vec<_>>();
+        exports.sort_by(|a, b| a.0.cmp(b.0));
+        exports
+    }
+
+    fn list_exports(&mut self) -> anyhow::Result<String> {
+        let exports = self
+            .exports()
+            .into_iter()
+            .map(|(name, kind)| (name.clone(), kind.clone()))
+            .collect::<Vec<_>>();
+        let mut lines = vec![];
+        for (name, kind) in exports {
+            let line = match kind {
+                ExportKind::Func(_) => {
+                    let (_, ty) = self.wasm.export_func(&name)?;
+                    format!(
+                        "func {name} ({}) -> ({})",
+                        type_list(&ty.params),
+                        type_list(&ty.results)
+                    )
+                }
+                ExportKind::Memory(_) => {
+                    let pages = self.wasm.get_memory(&name)?.size();
+                    format!("memory {name} {pages} pages")
+                }
+                ExportKind::GLobal(_) => self.global(&name)?,
+                ExportKind::Table(_) => {
+                    let size = self.wasm.get_table(&name)?.size();
+                    format!("table {name} {size} elements")
+                }
+            };
+            lines.push(line);
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn global(&mut self, name: &str) -> anyhow::Result<String> {
+        let global = self.wasm.get_global(name)?;
+        let ty = global.ty();
+        let mutability = if global.mutability() { "mut " } else { "" };
+        Ok(format!(
+            "global {name} {mutability}{} = {}",
+            type_name(&ty),
+            format_value(&global.get())
+        ))
+    }
+
+    fn globals(&mut self) -> anyhow::Result<String> {
+        let names = self
+            .exports()
+            .into_iter()
+            .filter(|(_, kind)| matches!(kind, ExportKind::GLobal(_)))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let lines = names
+            .iter()
+            .map(|name| self.global(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(lines.join("\n"))
+    }
+
+    fn peek(&mut self, addr: u32, len: u32) -> anyhow::Result<String> {
+        let mut buf = vec![0; len as usize];
+        self.wasm.memory(0)?.read(addr as usize, &mut buf)?;
+        Ok(hex_dump(addr, &buf))
+    }
+
+    fn poke(&mut self, addr: u32, bytes: &[&str]) -> anyhow::Result<String> {
+        let bytes = bytes
+            .iter()
+            .map(|text| {
+                let byte = parse_u32(text)?;
+                ensure!(byte <= 0xff, "`{text}` is not a byte");
+                Ok(byte as u8)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.wasm.memory(0)?.write(addr as usize, &bytes)?;
+        Ok(format!("wrote {} bytes at {addr:#x}", bytes.len()))
+    }
+
+    /// 执行一行命令，返回要输出的文本
+    pub fn eval(&mut self, line: &str) -> anyhow::Result<String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let Some((&cmd, args)) = words.split_first() else {
+            return Ok(String::new());
+        };
+        match (cmd, args) {
+            ("help", _) => Ok(HELP.to_string()),
+            ("exports", []) => self.list_exports(),
+            ("call", [name, values @ ..]) => {
+                Ok(invoke_text(&mut self.wasm, name, values)?.join("\n"))
+            }
+            ("peek", [addr]) => self.peek(parse_u32(addr)?, 16),
+            ("peek", [addr, len]) => self.peek(parse_u32(addr)?, parse_u32(len)?),
+            ("poke", [addr, bytes @ ..]) if !bytes.is_empty() => self.poke(parse_u32(addr)?, bytes),
+            ("global", []) => self.globals(),
+            ("global", [name]) => self.global(name),
+            ("reload", []) => {
+                self.reload()?;
+                Ok(format!("reloaded {}", self.path.display()))
+            }
+            ("exports" | "call" | "peek" | "poke" | "global" | "reload", _) => {
+                bail!("bad arguments for `{cmd}`, see `help`")
+            }
+            _ => bail!("unknown command `{cmd}`, see `help`"),
+        }
+    }
+
+    /// 逐行读取命令直到 quit 或输入结束，错误输出后继续
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> anyhow::Result<()> {
+        let mut line = String::new();
+        loop {
+            write!(output, "> ")?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(());
+            }
+            match line.trim() {
+                "quit" | "exit" => return Ok(()),
+                line => match self.eval(line) {
+                    Ok(text) if text.is_empty() => {}
+                    Ok(text) => writeln!(output, "{text}")?,
+                    Err(err) => writeln!(output, "error: {err:#}")?,
+                },
+            }
+        }
+    }
+}
+
+#[test]
+fn test_repl() {
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (memory (export "mem") 1)
+          (global (export "counter") (mut i32) (i32.const 7))
+          (func (export "add") (param i64 i64) (result i64)
+            (i64.add (local.get 0) (local.get 1)))
+          (func (export "bump") (result i32)
+            (global.set 0 (i32.add (global.get 0) (i32.const 1)))
+            (global.get 0))
+          (func (export "load") (param i32) (result i32)
+            (i32.load (local.get 0))))"#,
+    )
+    .unwrap();
+    let path = std::env::temp_dir().join(format!("oxygen-repl-{}.wasm", std::process::id()));
+    std::fs::write(&path, buf).unwrap();
+    let mut repl = Repl::new(&path, RuntimeConfig::default(), Linker::new()).unwrap();
+
+    assert_eq!(
+        repl.eval("exports").unwrap(),
+        "func add (i64 i64) -> (i64)\n\
+         func bump () -> (i32)\n\
+         global counter mut i32 = 7 (0x7)\n\
+         func load (i32) -> (i32)\n\
+         memory mem 1 pages"
+    );
+    assert_eq!(repl.eval("call add -1 0x10").unwrap(), "i64: 15 (0xf)");
+    assert_eq!(repl.eval("call bump").unwrap(), "i32: 8 (0x8)");
+    assert_eq!(
+        repl.eval("global counter").unwrap(),
+        "global counter mut i32 = 8 (0x8)"
+    );
+    assert_eq!(
+        repl.eval("poke 0x10 0x41 66 0").unwrap(),
+        "wrote 3 bytes at 0x10"
+    );
+    assert_eq!(repl.eval("call load 16").unwrap(), "i32: 16961 (0x4241)");
+    assert_eq!(
+        repl.eval("peek 14 4").unwrap(),
+        format!("0000000e: 00 00 41 42{}  ..AB", " ".repeat(36))
+    );
+    assert!(repl.eval("peek 65535 2").is_err());
+    assert!(repl.eval("poke 0 256").is_err());
+    assert!(repl.eval("call add 1").is_err());
+    assert!(repl.eval("frobnicate").is_err());
+
+    // reload 恢复初始状态
+    assert_eq!(
+        repl.eval("reload").unwrap(),
+        format!("reloaded {}", path.display())
+    );
+    assert_eq!(
+        repl.eval("global").unwrap(),
+        "global counter mut i32 = 7 (0x7)"
+    );
+
+    let mut out = vec![];
+    repl.run(&b"call bump\nnope\n\nquit\ncall bump\n"[..], &mut out)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "> i32: 8 (0x8)\n> error: unknown command `nope`, see `help`\n> > "
+    );
+    std::fs::remove_file(&path).unwrap();
+}
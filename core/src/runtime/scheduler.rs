@@ -0,0 +1,152 @@
+//! 多实例的协作式调度，按指令数分时间片轮流执行
+//!
+//! 每个实例同时只能有一个任务；时间片用完的任务以 `Trap::Yielded` 让出，排到队尾。
+//! 让出只在每 1024 条指令的检查点发生，时间片小于它时按 1024 条计。
+
+use std::collections::VecDeque;
+
+use super::decoder::WasmValue;
+use super::trap::Trap;
+use super::OxygenRuntime;
+
+pub type TaskId = usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub id: TaskId,
+    /// `OxygenRuntime::modes` 中的实例
+    pub module: usize,
+    pub name: String,
+    pub args: Vec<WasmValue>,
+    /// 已经开始执行，之后的时间片用 `resume` 继续
+    pub started: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    pub tasks: VecDeque<Task>,
+    next_id: TaskId,
+}
+
+/// 完成的任务及其返回值（或错误）
+pub type TaskResult = (TaskId, anyhow::Result<Vec<WasmValue>>);
+
+impl OxygenRuntime {
+    /// 在第 module 个实例上调用导出函数 name，由 `run_round` 或 `run_tasks` 执行
+    pub fn spawn(
+        &mut self,
+        module: usize,
+        name: &str,
+        args: &[WasmValue],
+    ) -> anyhow::Result<TaskId> {
+        anyhow::ensure!(module < self.modes.len(), "unknown module {module}");
+        anyhow::ensure!(
+            !self.scheduler.tasks.iter().any(|t| t.module == module),
+            "module {module} already has a task"
+        );
+        let id = self.scheduler.next_id;
+        self.scheduler.next_id += 1;
+        self.scheduler.tasks.push_back(Task {
+            id,
+            module,
+            name: name.to_string(),
+            args: args.to_vec(),
+            started: false,
+        });
+        Ok(id)
+    }
+
+    /// 每个任务执行一个 slice 条指令的时间片，返回这一轮完成的任务
+    pub fn run_round(&mut self, slice: u64) -> Vec<TaskResult> {
+        let mut done = vec![];
+        for _ in 0..self.scheduler.tasks.len() {
+            let Some(mut task) = self.scheduler.tasks.pop_front() else {
+                break;
+            };
+            let wasm = &mut self.modes[task.module];
+            wasm.yield_at = Some(wasm.stats.instructions + slice);
+            let res = if std::mem::replace(&mut task.started, true) {
+                wasm.resume(&[])
+            } else {
+                wasm.invoke(&task.name, &task.args)
+            };
+            wasm.yield_at = None;
+            match res {
+                Err(err) if err.downcast_ref::<Trap>() == Some(&Trap::Yielded) => {
+                    self.scheduler.tasks.push_back(task);
+                }
+                res => {
+                    // 宿主函数挂起的任务不能由调度器继续，放弃
+                    wasm.suspended = None;
+                    done.push((task.id, res));
+                }
+            }
+        }
+        done
+    }
+
+    /// 轮流执行直到所有任务完成，按完成顺序返回
+    pub fn run_tasks(&mut self, slice: u64) -> Vec<TaskResult> {
+        let mut done = vec![];
+        while !self.scheduler.tasks.is_empty() {
+            done.extend(self.run_round(slice));
+        }
+        done
+    }
+}
+
+#[test]
+fn test_round_robin() {
+    use super::config::{Engine, RuntimeConfig};
+    use super::testing::{vec, wasm};
+
+    // count(n)：循环 n 次，返回 n
+    let body = [
+        0x01, 0x01, 0x7f, // local i32
+        0x03, 0x40, // loop
+        0x20, 0x01, 0x41, 0x01, 0x6a, 0x22, 0x01, // local.tee 1 (local.get 1 + 1)
+        0x20, 0x00, 0x49, 0x0d, 0x00, // br_if 0 (local.get 1 < local.get 0)
+        0x0b, // end
+        0x20, 0x01, 0x0b, // local.get 1
+    ];
+    let mut code = vec![body.len() as u8];
+    code.extend(body);
+    let buf = wasm(&[
+        (1, vec(&[vec![0x60, 0x01, 0x7f, 0x01, 0x7f]])),
+        (3, vec(&[vec![0x00]])),
+        (7, vec(&[[&[0x05][..], b"count", &[0x00, 0x00]].concat()])),
+        (10, vec(&[code])),
+    ]);
+
+    for engine in [Engine::Match, Engine::Threaded] {
+        let mut runtime = OxygenRuntime::new(RuntimeConfig::default().engine(engine));
+        for _ in 0..3 {
+            runtime.load(buf.clone()).unwrap();
+            runtime.modes.last_mut().unwrap().instance(None).unwrap();
+        }
+        let long = runtime.spawn(0, "count", &[WasmValue::I32(20000)]).unwrap();
+        let short = runtime.spawn(1, "count", &[WasmValue::I32(10)]).unwrap();
+        let medium = runtime.spawn(2, "count", &[WasmValue::I32(2000)]).unwrap();
+        assert!(runtime.spawn(1, "count", &[WasmValue::I32(1)]).is_err());
+
+        // 短任务在第一轮完成，长任务被切成多个时间片
+        let first = runtime.run_round(1024);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].0, short);
+        assert_eq!(runtime.scheduler.tasks.len(), 2);
+        assert!(runtime.modes[0].is_suspended());
+
+        let rest = runtime.run_tasks(1024);
+        let order = rest.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(order, [medium, long]);
+        assert_eq!(rest[1].1.as_ref().unwrap(), &[WasmValue::I32(20000)]);
+        assert_eq!(rest[0].1.as_ref().unwrap(), &[WasmValue::I32(2000)]);
+        assert!(!runtime.modes[0].is_suspended());
+
+        // 结果与不分片时一致
+        let res = runtime.modes[0]
+            .invoke("count", &[WasmValue::I32(20000)])
+            .unwrap();
+        assert_eq!(res, [WasmValue::I32(20000)]);
+    }
+}
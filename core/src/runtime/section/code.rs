@@ -0,0 +1,392 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::super::error::BodyAt;
+use super::{
+    bytecode::ByteCode,
+    opcode::{Location, Opcode},
+    typings::ValueType,
+    ByteParse, ByteRead, Decode,
+};
+
+/// 函数体不少于这个数量时并行解码
+const PARALLEL_BODIES: u32 = 64;
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeSection {
+    pub offset: usize,
+    pub byte_count: u32,
+    pub body_count: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
+    pub entries: Vec<FuncBody>,
+    /// 段内容（函数个数）开始的位置
+    pub content_offset: usize,
+    /// (操作码索引, 指令在模块中的偏移)，按操作码索引排序；
+    /// 不产生操作码的指令与下一条指令的操作码索引相同，以后出现的为准
+    pub instr_offsets: Vec<(usize, usize)>,
+    /// 解码时只读取函数体的位置和大小，由 `RuntimeConfig::lazy_decode` 设置
+    pub lazy: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FuncBody {
+    pub size: usize,
+    pub local_count: u32,
+    pub locales: Vec<(u32, ValueType)>,
+    pub code: (usize, usize, usize),
+    pub offset: usize,
+    /// 延迟解码时函数体还没有解析，code 和 locales 为空
+    pub pending: bool,
+    // pub raw: [u8],
+}
+pub fn default(raw: Arc<[u8]>) -> CodeSection {
+    CodeSection {
+        offset: 0,
+        byte_count: 0,
+        body_count: 0,
+        raw,
+        entries: vec![],
+        content_offset: 0,
+        instr_offsets: vec![],
+        lazy: false,
+    }
+}
+
+// 需要记录指令位置，不使用 ByteParser 派生
+impl ByteParse for CodeSection {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+    fn length(&self) -> usize {
+        self.byte_count as usize
+    }
+    fn bytes(&self) -> &[u8] {
+        &self.raw
+    }
+    fn skip(&mut self, num: u32) {
+        self.offset += num as usize
+    }
+}
+
+impl ByteRead for CodeSection {}
+
+impl ByteCode for CodeSection {
+    fn instr_offset(&mut self, op: usize, offset: usize) {
+        self.instr_offsets.push((op, offset));
+    }
+}
+
+impl CodeSection {
+    /// 解析延迟解码的函数体，操作码追加到 ops 末尾
+    pub fn decode_pending(
+        &mut self,
+        body: &mut FuncBody,
+        ops: &mut Vec<Opcode>,
+    ) -> anyhow::Result<()> {
+        if !body.pending {
+            return Ok(());
+        }
+        let (parsed, instr_offsets) = BodyParser::parse(&self.raw, body.offset, ops)?;
+        self.instr_offsets.extend(instr_offsets);
+        *body = parsed;
+        Ok(())
+    }
+
+    /// 第 op 个操作码对应的指令在模块中的偏移
+    pub fn op_offset(&self, op: usize) -> Option<usize> {
+        let index = self.instr_offsets.partition_point(|&(i, _)| i <= op);
+        Some(self.instr_offsets.get(index.checked_sub(1)?)?.1)
+    }
+}
+
+impl Decode for CodeSection
+where
+    Self: ByteCode,
+{
+    // 代码段编码格式如下：
+    // code_sec: 0xoA|byte_count|vec<code>
+    // code: byte_count|vec<locals>|expr
+    // locals: local_count|val_type
+    fn decode(&mut self, ops: &mut Vec<Opcode>) -> anyhow::Result<()> {
+        self.content_offset = self.offset;
+        self.body_count = self.read_leb_u32()?;
+        if self.lazy {
+            return self.decode_headers();
+        }
+        if self.body_count >= PARALLEL_BODIES {
+            let (offset, op_count) = (self.offset, ops.len());
+            if self.decode_parallel(ops).is_ok() {
+                return Ok(());
+            }
+            // 出错时串行重新解码，得到准确的出错位置
+            self.offset = offset;
+            ops.truncate(op_count);
+            self.entries.clear();
+            self.instr_offsets.clear();
+        }
+        self.decode_serial(ops)
+    }
+}
+
+impl CodeSection {
+    fn decode_serial(&mut self, ops: &mut Vec<Opcode>) -> anyhow::Result<()> {
+        for index in 0..self.body_count as usize {
+            let start = self.offset;
+            let body = decode_body(self, start, ops)
+                .map_err(|err| err.context(BodyAt { index, start }))?;
+            self.entries.push(body);
+        }
+        Ok(())
+    }
+
+    fn decode_headers(&mut self) -> anyhow::Result<()> {
+        for index in 0..self.body_count as usize {
+            let start = self.offset;
+            let size = self
+                .read_leb_u32()
+                .map_err(|err| err.context(BodyAt { index, start }))?;
+            self.offset += size as usize;
+            if self.offset > self.length() {
+                let err = anyhow::anyhow!("function body out of section");
+                return Err(err.context(BodyAt { index, start }));
+            }
+            self.entries.push(FuncBody {
+                size: size as usize,
+                offset: start,
+                pending: true,
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+
+    /// 先按长度前缀切分函数体，各线程把函数体解析到各自的操作码序列，再按顺序合并。
+    /// 每个序列开头放一个占位操作码，对应串行解码时函数前一个操作码的位置。
+    fn decode_parallel(&mut self, ops: &mut Vec<Opcode>) -> anyhow::Result<()> {
+        let mut starts = vec![];
+        for _ in 0..self.body_count {
+            starts.push(self.offset);
+            self.offset += self.read_leb_u32()? as usize;
+            anyhow::ensure!(self.offset <= self.length(), "function body out of section");
+        }
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let raw = &*self.raw;
+        let parsed = std::thread::scope(|scope| {
+            starts
+                .chunks(starts.len().div_ceil(threads))
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|&start| {
+                                let mut ops = vec![Opcode::Nop];
+                                let (body, instr_offsets) =
+                                    BodyParser::parse(raw, start, &mut ops)?;
+                                Ok((body, ops, instr_offsets))
+                            })
+                            .collect::<anyhow::Result<Vec<_>>>()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?;
+        for (mut body, body_ops, instr_offsets) in parsed.into_iter().flatten() {
+            let base = ops.len();
+            let at = |i: usize| (base + i).saturating_sub(1);
+            body.code = (at(body.code.0), at(body.code.1), at(body.code.2));
+            ops.extend(body_ops.into_iter().skip(1).map(|op| relocate(op, at)));
+            self.instr_offsets.extend(
+                instr_offsets
+                    .into_iter()
+                    .map(|(op, offset)| (at(op), offset)),
+            );
+            self.entries.push(body);
+        }
+        Ok(())
+    }
+}
+
+fn decode_body(
+    parser: &mut impl ByteCode,
+    start: usize,
+    ops: &mut Vec<Opcode>,
+) -> anyhow::Result<FuncBody> {
+    let body_size = parser.read_leb_u32()?;
+    let local_count = parser.read_leb_u32()?;
+    let mut locales = vec![];
+    for _ in 0..local_count {
+        let count = parser.read_leb_u32()?;
+        locales.push((count, parser.read_value_type()?))
+    }
+    // let code = self.read_util(0x0b)?;
+    let code = parser.parse_code(ops, &mut vec![])?;
+    Ok(FuncBody {
+        size: body_size as usize,
+        local_count,
+        locales,
+        code,
+        offset: start,
+        pending: false,
+    })
+}
+
+/// 操作码中的块位置和跳转目标都是全局索引，合并时按 at 换算
+fn relocate(op: Opcode, at: impl Fn(usize) -> usize) -> Opcode {
+    let loc = |Location(start, end, last): Location| Location(at(start), at(end), at(last));
+    match op {
+        Opcode::Block(bt, l) => Opcode::Block(bt, loc(l)),
+        Opcode::Loop(bt, l) => Opcode::Loop(bt, loc(l)),
+        Opcode::If(bt, l) => Opcode::If(bt, loc(l)),
+        Opcode::Else(l) => Opcode::Else(loc(l)),
+        Opcode::End(start) => Opcode::End(at(start)),
+        Opcode::Br(label, target) => Opcode::Br(label, at(target)),
+        Opcode::BrIf(label, target) => Opcode::BrIf(label, at(target)),
+        Opcode::BrOnNull(label, target) => Opcode::BrOnNull(label, at(target)),
+        Opcode::BrOnNonNull(label, target) => Opcode::BrOnNonNull(label, at(target)),
+        Opcode::BrTable(count, entries, (label, target)) => Opcode::BrTable(
+            count,
+            entries.into_iter().map(|(i, t)| (i, at(t))).collect(),
+            (label, at(target)),
+        ),
+        op => op,
+    }
+}
+
+/// 只解析一个函数体，length 为函数体的结束位置（由开头的大小得到）
+struct BodyParser<'a> {
+    raw: &'a [u8],
+    offset: usize,
+    end: usize,
+    instr_offsets: Vec<(usize, usize)>,
+}
+
+impl<'a> BodyParser<'a> {
+    /// 解析 start 处的函数体，操作码追加到 ops，返回函数体和各指令的位置
+    fn parse(
+        raw: &'a [u8],
+        start: usize,
+        ops: &mut Vec<Opcode>,
+    ) -> anyhow::Result<(FuncBody, Vec<(usize, usize)>)> {
+        let mut parser = BodyParser {
+            raw,
+            offset: start,
+            end: raw.len(),
+            instr_offsets: vec![],
+        };
+        parser.end = parser.read_leb_u32()? as usize + parser.offset;
+        parser.offset = start;
+        let body = decode_body(&mut parser, start, ops)?;
+        anyhow::ensure!(
+            parser.offset == parser.end && matches!(ops.last(), Some(Opcode::End(_))),
+            "function body size mismatch"
+        );
+        Ok((body, parser.instr_offsets))
+    }
+}
+
+impl ByteParse for BodyParser<'_> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+    fn length(&self) -> usize {
+        self.end
+    }
+    fn bytes(&self) -> &[u8] {
+        self.raw
+    }
+    fn skip(&mut self, num: u32) {
+        self.offset += num as usize
+    }
+}
+
+impl ByteRead for BodyParser<'_> {}
+
+impl ByteCode for BodyParser<'_> {
+    fn instr_offset(&mut self, op: usize, offset: usize) {
+        self.instr_offsets.push((op, offset));
+    }
+}
+
+impl Display for CodeSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "SectionCode(offset = 0x{:0>8x?}, size = {}, count = {})",
+            self.offset,
+            self.byte_count,
+            self.entries.len()
+        )?;
+        for (index, item) in self.entries.iter().enumerate() {
+            writeln!(f, "    ({index})Code: {item}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for FuncBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let locales = self
+            .locales
+            .iter()
+            .map(|item| format!("{}[{}]", item.1, item.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        write!(
+            f,
+            "offset = 0x{:0>8x?}, local({}), code = Opcode[{:?}]",
+            self.offset, locales, self.code
+        )?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parallel_decode() {
+    use super::super::decoder::{WasmModule, WasmValue};
+    use super::super::testing::{func_bytes, invoke};
+
+    let bodies = (0..100u8)
+        .map(|i| {
+            [
+                &[0x02, 0x7f, 0x03, 0x40][..],         // block (result i32); loop
+                &[0x41, 0x00, 0x04, 0x40],             // i32.const 0; if
+                &[0x41, 0x00, 0x0e, 0x01, 0x00, 0x01], // i32.const 0; br_table 0 1
+                &[0x0c, 0x03],                         // br 3（函数本身）
+                &[0x05, 0x01, 0x0b, 0x0b],             // else; nop; end; end
+                &[0x41, i & 0x3f, 0x0b],               // i32.const i % 64; end
+            ]
+            .concat()
+        })
+        .collect::<Vec<_>>();
+    let bodies = bodies.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    let buf = func_bytes(&[], &[0x7f], &bodies, &[]);
+
+    let mut wasm = WasmModule::default(buf.clone());
+    wasm.decode().unwrap();
+    let code = &wasm.section.code;
+
+    let mut serial = default(buf.into());
+    serial.offset = code.content_offset;
+    serial.byte_count = wasm.raw.len() as u32;
+    serial.body_count = serial.read_leb_u32().unwrap();
+    let mut ops = vec![];
+    serial.decode_serial(&mut ops).unwrap();
+
+    assert_eq!(format!("{:?}", wasm.ops), format!("{ops:?}"));
+    assert_eq!(code.instr_offsets, serial.instr_offsets);
+    assert_eq!(
+        format!("{:?}", code.entries),
+        format!("{:?}", serial.entries)
+    );
+
+    wasm.instance(None).unwrap();
+    for i in [0, 63, 99] {
+        let res = invoke(&mut wasm, i, &[]).unwrap();
+        assert_eq!(res, [WasmValue::I32(i as i32 % 64)]);
+    }
+}
@@ -0,0 +1,436 @@
+use std::{collections::HashMap, fmt::Display, ops::Range, sync::Arc};
+
+use decode_derive::ByteParser;
+
+use anyhow::ensure;
+
+use super::{bytecode::ByteCode, opcode::Opcode, ByteParse, ByteRead, Decode};
+
+#[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomSection {
+    pub offset: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
+    pub byte_count: u32,
+    pub name: String,
+    /// 各 custom section 的内容在模块中的范围，按名字，同名的以后出现的为准
+    pub payloads: HashMap<String, Range<usize>>,
+    /// 所有 custom section 的名字和内容在模块中的范围，按在模块中的顺序
+    pub entries: Vec<(String, Range<usize>)>,
+}
+
+pub fn default(raw: Arc<[u8]>) -> CustomSection {
+    CustomSection {
+        offset: 0,
+        raw,
+        byte_count: 0,
+        name: String::new(),
+        payloads: HashMap::new(),
+        entries: vec![],
+    }
+}
+
+impl Decode for CustomSection {
+    // custom_sec: 0x00|byte_count|name|bytes
+    fn decode(&mut self, _ops: &mut Vec<Opcode>) -> anyhow::Result<()> {
+        self.name = self.read_name()?;
+        let payload = self.offset..self.byte_count as usize;
+        self.payloads.insert(self.name.clone(), payload.clone());
+        self.entries.push((self.name.clone(), payload));
+        Ok(())
+    }
+}
+impl Display for CustomSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "SectionCustom(offset = 0x{:0>8x?}, size ={}, name = {:?})",
+            self.offset, self.byte_count, self.name
+        )?;
+        for (name, range) in &self.entries {
+            writeln!(
+                f,
+                "    {name:?}: offset = 0x{:0>8x?}, size = {}",
+                range.start,
+                range.len()
+            )?;
+            let bytes = self.raw.get(range.clone()).unwrap_or_default();
+            let decoded = match &name[..] {
+                "producers" => Producers::parse(bytes).map(|p| p.to_string()),
+                "target_features" => TargetFeatures::parse(bytes).map(|t| t.to_string()),
+                "dylink.0" => Dylink::parse(bytes).map(|d| d.to_string()),
+                _ => continue,
+            };
+            match decoded {
+                Ok(text) => text
+                    .lines()
+                    .try_for_each(|line| writeln!(f, "        {line}"))?,
+                Err(err) => writeln!(f, "        malformed: {err}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `producers` custom section：生成模块的语言、工具和 SDK
+///
+/// producers_sec: vec(field)，field: name|vec(name|version)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Producers {
+    /// (字段名, [(名字, 版本)])，字段名一般为 language、processed-by 或 sdk
+    pub fields: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Producers {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = PayloadReader::new(bytes);
+        let mut fields: Vec<(String, Vec<(String, String)>)> = vec![];
+        for _ in 0..reader.read_leb_u32()? {
+            let field = reader.read_name()?;
+            ensure!(
+                fields.iter().all(|(name, _)| *name != field),
+                "duplicate producers field `{field}`"
+            );
+            let count = reader.read_leb_u32()?;
+            let mut values = Vec::with_capacity(reader.capacity(count));
+            for _ in 0..count {
+                values.push((reader.read_name()?, reader.read_name()?));
+            }
+            fields.push((field, values));
+        }
+        reader.finish("producers")?;
+        Ok(Self { fields })
+    }
+
+    /// 字段 name 的 (名字, 版本)，没有该字段时为空
+    pub fn field(&self, name: &str) -> &[(String, String)] {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, values)| &values[..])
+            .unwrap_or_default()
+    }
+}
+
+impl Display for Producers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (field, values) in &self.fields {
+            write!(f, "{field}:")?;
+            for (name, version) in values {
+                match version.is_empty() {
+                    true => write!(f, " {name}")?,
+                    false => write!(f, " {name} {version}")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// `target_features` 中特性的前缀
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeaturePrefix {
+    /// `+`：模块用到了该特性
+    Used,
+    /// `-`：模块不能和用到该特性的模块链接
+    Disallowed,
+    /// `=`：链接的所有模块都必须用到该特性（已废弃）
+    Required,
+}
+
+impl FeaturePrefix {
+    fn as_char(self) -> char {
+        match self {
+            Self::Used => '+',
+            Self::Disallowed => '-',
+            Self::Required => '=',
+        }
+    }
+}
+
+/// `target_features` custom section：编译模块时启用或禁用的特性
+///
+/// target_features_sec: vec(prefix|name)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TargetFeatures {
+    pub features: Vec<(FeaturePrefix, String)>,
+}
+
+impl TargetFeatures {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = PayloadReader::new(bytes);
+        let count = reader.read_leb_u32()?;
+        let mut features = Vec::with_capacity(reader.capacity(count));
+        for _ in 0..count {
+            let prefix = match reader.read_byte()? {
+                b'+' => FeaturePrefix::Used,
+                b'-' => FeaturePrefix::Disallowed,
+                b'=' => FeaturePrefix::Required,
+                b => anyhow::bail!("unknown target feature prefix {:?}", b as char),
+            };
+            features.push((prefix, reader.read_name()?));
+        }
+        reader.finish("target_features")?;
+        Ok(Self { features })
+    }
+
+    /// 运行模块需要支持的特性，即前缀为 `+` 或 `=` 的特性
+    pub fn required(&self) -> impl Iterator<Item = &str> {
+        self.features
+            .iter()
+            .filter(|(prefix, _)| *prefix != FeaturePrefix::Disallowed)
+            .map(|(_, name)| &name[..])
+    }
+}
+
+impl Display for TargetFeatures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let features = self
+            .features
+            .iter()
+            .map(|(prefix, name)| format!("{}{name}", prefix.as_char()))
+            .collect::<Vec<_>>();
+        writeln!(f, "features: {}", features.join(" "))
+    }
+}
+
+/// `dylink.0` 中 WASM_DYLINK_MEM_INFO 子段：动态库需要的内存和表空间
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DylinkMemInfo {
+    pub memory_size: u32,
+    /// 以 2 为底的对数
+    pub memory_alignment: u32,
+    pub table_size: u32,
+    /// 以 2 为底的对数
+    pub table_alignment: u32,
+}
+
+/// `dylink.0` custom section：emscripten 风格的动态库（side module）的元数据
+///
+/// dylink_sec: (id|size|payload)*，未知的子段按 size 跳过
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Dylink {
+    pub mem_info: Option<DylinkMemInfo>,
+    /// 依赖的其他动态库
+    pub needed: Vec<String>,
+    /// (导出名, 符号标志)
+    pub export_info: Vec<(String, u32)>,
+    /// (模块名, 导入名, 符号标志)
+    pub import_info: Vec<(String, String, u32)>,
+    pub runtime_path: Vec<String>,
+}
+
+impl Dylink {
+    const MEM_INFO: u8 = 1;
+    const NEEDED: u8 = 2;
+    const EXPORT_INFO: u8 = 3;
+    const IMPORT_INFO: u8 = 4;
+    const RUNTIME_PATH: u8 = 5;
+
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut dylink = Self::default();
+        let mut reader = PayloadReader::new(bytes);
+        while !reader.remaining().is_empty() {
+            let id = reader.read_byte()?;
+            let size = reader.read_leb_u32()?;
+            let mut sub = PayloadReader::new(reader.read_bytes(size)?);
+            let names = |sub: &mut PayloadReader| -> anyhow::Result<Vec<String>> {
+                let count = sub.read_leb_u32()?;
+                let mut names = Vec::with_capacity(sub.capacity(count));
+                for _ in 0..count {
+                    names.push(sub.read_name()?);
+                }
+                Ok(names)
+            };
+            match id {
+                Self::MEM_INFO => {
+                    dylink.mem_info = Some(DylinkMemInfo {
+                        memory_size: sub.read_leb_u32()?,
+                        memory_alignment: sub.read_leb_u32()?,
+                        table_size: sub.read_leb_u32()?,
+                        table_alignment: sub.read_leb_u32()?,
+                    })
+                }
+                Self::NEEDED => dylink.needed = names(&mut sub)?,
+                Self::EXPORT_INFO => {
+                    for _ in 0..sub.read_leb_u32()? {
+                        let info = (sub.read_name()?, sub.read_leb_u32()?);
+                        dylink.export_info.push(info);
+                    }
+                }
+                Self::IMPORT_INFO => {
+                    for _ in 0..sub.read_leb_u32()? {
+                        let info = (sub.read_name()?, sub.read_name()?, sub.read_leb_u32()?);
+                        dylink.import_info.push(info);
+                    }
+                }
+                Self::RUNTIME_PATH => dylink.runtime_path = names(&mut sub)?,
+                _ => continue,
+            }
+            sub.finish("dylink.0 subsection")?;
+        }
+        Ok(dylink)
+    }
+}
+
+impl Display for Dylink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(mem) = &self.mem_info {
+            writeln!(
+                f,
+                "mem_info: memory_size = {}, memory_alignment = {}, table_size = {}, table_alignment = {}",
+                mem.memory_size, mem.memory_alignment, mem.table_size, mem.table_alignment
+            )?;
+        }
+        if !self.needed.is_empty() {
+            writeln!(f, "needed: {}", self.needed.join(" "))?;
+        }
+        for (name, flags) in &self.export_info {
+            writeln!(f, "export_info: {name:?} flags = {flags:#x}")?;
+        }
+        for (module, name, flags) in &self.import_info {
+            writeln!(f, "import_info: {module:?}.{name:?} flags = {flags:#x}")?;
+        }
+        if !self.runtime_path.is_empty() {
+            writeln!(f, "runtime_path: {}", self.runtime_path.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// 读取 custom section 的内容，offset 相对内容开头
+struct PayloadReader<'a> {
+    raw: &'a [u8],
+    offset: usize,
+}
+
+impl ByteParse for PayloadReader<'_> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+    fn length(&self) -> usize {
+        self.raw.len()
+    }
+    fn bytes(&self) -> &[u8] {
+        self.raw
+    }
+    fn skip(&mut self, num: u32) {
+        self.offset += num as usize
+    }
+}
+
+impl ByteRead for PayloadReader<'_> {}
+
+impl<'a> PayloadReader<'a> {
+    fn new(raw: &'a [u8]) -> Self {
+        Self { raw, offset: 0 }
+    }
+
+    /// 内容必须恰好读完
+    fn finish(&self, what: &str) -> anyhow::Result<()> {
+        ensure!(
+            self.remaining().is_empty(),
+            "unexpected {} bytes at the end of the {what} section",
+            self.remaining().len()
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn test_producers() {
+    use super::super::testing::vec;
+
+    let name = |s: &str| vec(&s.bytes().map(|b| vec![b]).collect::<Vec<_>>());
+    let bytes = [
+        vec![0x02],
+        name("language"),
+        vec![0x01],
+        name("Rust"),
+        name(""),
+        name("processed-by"),
+        vec![0x02],
+        name("rustc"),
+        name("1.80.0"),
+        name("wasm-bindgen"),
+        name("0.2.92"),
+    ]
+    .concat();
+    let producers = Producers::parse(&bytes).unwrap();
+    assert_eq!(producers.field("language"), [("Rust".into(), "".into())]);
+    assert_eq!(producers.field("processed-by")[1].1, "0.2.92");
+    assert!(producers.field("sdk").is_empty());
+    assert_eq!(
+        producers.to_string(),
+        "language: Rust\nprocessed-by: rustc 1.80.0 wasm-bindgen 0.2.92\n"
+    );
+
+    // 截断、多余的字节和重复的字段
+    assert!(Producers::parse(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Producers::parse(&[&bytes[..], &[0]].concat()).is_err());
+    let dup = [vec![0x02], name("sdk"), vec![0x00], name("sdk"), vec![0x00]].concat();
+    assert!(Producers::parse(&dup).is_err());
+}
+
+#[test]
+fn test_target_features_and_dylink() {
+    use super::super::testing::vec;
+
+    let name = |s: &str| vec(&s.bytes().map(|b| vec![b]).collect::<Vec<_>>());
+    let bytes = [
+        vec![0x03, b'+'],
+        name("mutable-globals"),
+        vec![b'-'],
+        name("simd128"),
+        vec![b'='],
+        name("sign-ext"),
+    ]
+    .concat();
+    let features = TargetFeatures::parse(&bytes).unwrap();
+    assert_eq!(
+        features.required().collect::<Vec<_>>(),
+        ["mutable-globals", "sign-ext"]
+    );
+    assert_eq!(
+        features.to_string(),
+        "features: +mutable-globals -simd128 =sign-ext\n"
+    );
+    assert!(TargetFeatures::parse(&[0x01, b'*', 0x00]).is_err());
+
+    let sub = |id: u8, payload: Vec<u8>| [vec![id, payload.len() as u8], payload].concat();
+    let bytes = [
+        sub(1, vec![0x80, 0x01, 0x04, 0x02, 0x00]),
+        sub(2, [vec![0x01], name("libc.so")].concat()),
+        // 未知的子段
+        sub(0x7f, vec![0xff, 0xff]),
+        sub(3, [vec![0x01], name("malloc"), vec![0x02]].concat()),
+        sub(
+            4,
+            [vec![0x01], name("env"), name("memory"), vec![0x01]].concat(),
+        ),
+    ]
+    .concat();
+    let dylink = Dylink::parse(&bytes).unwrap();
+    assert_eq!(
+        dylink.mem_info,
+        Some(DylinkMemInfo {
+            memory_size: 128,
+            memory_alignment: 4,
+            table_size: 2,
+            table_alignment: 0,
+        })
+    );
+    assert_eq!(dylink.needed, ["libc.so"]);
+    assert_eq!(dylink.export_info, [("malloc".to_string(), 2)]);
+    assert_eq!(
+        dylink.import_info,
+        [("env".to_string(), "memory".to_string(), 1)]
+    );
+    assert!(dylink.runtime_path.is_empty());
+
+    // 子段的内容和声明的大小不一致
+    assert!(Dylink::parse(&sub(1, vec![0x01, 0x02, 0x03, 0x04, 0x05])).is_err());
+    assert!(Dylink::parse(&[0x02, 0x05, 0x01]).is_err());
+}
@@ -1,20 +1,22 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
-use anyhow::anyhow;
 use decode_derive::ByteParser;
 
+use super::super::error::DecodeErrorKind;
 use super::{bytecode::ByteCode, opcode::Opcode, ByteParse, ByteRead, Decode};
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataSection {
     pub offset: usize,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub byte_count: u32,
     pub data_count: u32,
     pub entries: Vec<Data>,
 }
 
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> DataSection {
+pub fn default(raw: Arc<[u8]>) -> DataSection {
     DataSection {
         offset: 0,
         raw,
@@ -25,6 +27,7 @@ pub fn default(raw: Rc<Box<Vec<u8>>>) -> DataSection {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Data {
     // pub raw: Vec<u8>,
     pub flag: u32,
@@ -33,6 +36,7 @@ pub struct Data {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataKind {
     Expr((usize, usize, usize), Vec<u8>),
     Vec(Vec<u8>),
@@ -58,19 +62,25 @@ where
                 00 => {
                     let code = self.parse_code(ops, &mut vec![])?;
                     let num = self.read_leb_u32()?;
-                    DataKind::Expr(code, self.read_bytes(num)?)
+                    DataKind::Expr(code, self.read_bytes(num)?.to_vec())
                 }
                 01 => {
                     let num = self.read_leb_u32()?;
-                    DataKind::Vec(self.read_bytes(num)?)
+                    DataKind::Vec(self.read_bytes(num)?.to_vec())
                 }
                 02 => {
                     let memidx = self.read_leb_u32()? as usize;
                     let expr = self.parse_code(ops, &mut vec![])?;
                     let num = self.read_leb_u32()?;
-                    DataKind::MemIdx(memidx, expr, self.read_bytes(num)?)
+                    DataKind::MemIdx(memidx, expr, self.read_bytes(num)?.to_vec())
+                }
+                _ => {
+                    return Err(DecodeErrorKind::UnknownTag {
+                        what: "data kind",
+                        found: flag,
+                    }
+                    .into())
                 }
-                _ => return Err(anyhow!("unkonwn data kind {flag}")),
             };
             self.entries.push(Data {
                 flag,
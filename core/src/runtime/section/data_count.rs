@@ -0,0 +1,54 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{bytecode::ByteCode, opcode::Opcode, ByteParse, ByteRead, Decode};
+use decode_derive::ByteParser;
+
+#[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataCountSection {
+    pub offset: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
+    pub byte_count: u32,
+    pub u32: u32,
+    pub has_count: bool,
+}
+
+pub fn default(raw: Arc<[u8]>) -> DataCountSection {
+    DataCountSection {
+        offset: 0,
+        raw,
+        byte_count: 0,
+        u32: 0,
+        has_count: false,
+    }
+}
+
+impl Decode for DataCountSection
+where
+    Self: ByteParse + ByteCode,
+{
+    // 数据计数段编码格式如下：
+    // datacount_sec: 0x0c|byte_count|u32
+    fn decode(&mut self, _ops: &mut Vec<Opcode>) -> anyhow::Result<()> {
+        self.u32 = self.read_leb_u32()?;
+        self.has_count = true;
+        Ok(())
+    }
+}
+
+impl Display for DataCountSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "SectionDataCount(offset = 0x{:0>8x?}, size = {}, count = {})",
+            self.offset,
+            self.byte_count,
+            if self.has_count {
+                self.u32.to_string()
+            } else {
+                "NOP".to_string()
+            }
+        )
+    }
+}
@@ -1,23 +1,25 @@
 use std::fmt::Display;
-use std::rc::Rc;
+use std::sync::Arc;
 
+use super::super::error::DecodeErrorKind;
 use super::bytecode::ByteCode;
 use super::opcode::Opcode;
 use super::typings::RefKind;
 use super::{ByteParse, ByteRead, Decode};
-use anyhow::{anyhow, ensure};
 use decode_derive::ByteParser;
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementSection {
     pub offset: usize,
     pub ele_count: u32,
     pub byte_count: u32,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub entries: Vec<Element>,
 }
 
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> ElementSection {
+pub fn default(raw: Arc<[u8]>) -> ElementSection {
     ElementSection {
         offset: 0,
         ele_count: 0,
@@ -28,6 +30,7 @@ pub fn default(raw: Rc<Box<Vec<u8>>>) -> ElementSection {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Element {
     E0x00(ElementKind<((usize, usize, usize), Vec<usize>)>),
     E0x01(ElementKind<(u8, Vec<usize>)>),
@@ -46,6 +49,7 @@ pub enum Element {
     E0x07(ElementKind<(RefKind, Vec<(usize, usize, usize)>)>),
 }
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementKind<T> {
     pub raw: Vec<u8>,
     pub offset: usize,
@@ -78,26 +82,33 @@ where
                 0x00 => {
                     let code = self.parse_code(ops, &mut vec![])?;
                     let count = self.read_leb_u32()?;
-                    let mut func = Vec::with_capacity(count as usize);
+                    let mut func = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         func.push(self.read_leb_u32()? as usize);
                     }
                     Element::E0x00(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele: (code, func),
                     })
                 }
                 0x01 => {
                     let elekind = self.read_byte()?;
-                    ensure!(elekind == 0x00, "0x01 elemnetkind  must be  0x00");
+                    if elekind != 0x00 {
+                        return Err(DecodeErrorKind::Unexpected {
+                            what: "element kind",
+                            expected: 0x00,
+                            found: elekind as u32,
+                        }
+                        .into());
+                    }
                     let count = self.read_leb_u32()?;
-                    let mut func = Vec::with_capacity(count as usize);
+                    let mut func = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         func.push(self.read_leb_u32()? as usize);
                     }
                     Element::E0x01(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele: (elekind, func),
                     })
@@ -106,29 +117,43 @@ where
                     let table_idx = self.read_leb_u32()? as usize;
                     let expr = self.parse_code(ops, &mut vec![])?;
                     let elekind = self.read_byte()?;
-                    ensure!(elekind == 0x00, "0x02 elemnet kind must be 0x00");
+                    if elekind != 0x00 {
+                        return Err(DecodeErrorKind::Unexpected {
+                            what: "element kind",
+                            expected: 0x00,
+                            found: elekind as u32,
+                        }
+                        .into());
+                    }
 
                     let count = self.read_leb_u32()?;
-                    let mut func = Vec::with_capacity(count as usize);
+                    let mut func = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         func.push(self.read_leb_u32()? as usize);
                     }
                     Element::E0x02(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele: (table_idx, expr, elekind, func),
                     })
                 }
                 0x03 => {
                     let elekind = self.read_byte()?;
-                    ensure!(elekind == 0x00, "0x03 elemnet kind must be 0x00");
+                    if elekind != 0x00 {
+                        return Err(DecodeErrorKind::Unexpected {
+                            what: "element kind",
+                            expected: 0x00,
+                            found: elekind as u32,
+                        }
+                        .into());
+                    }
                     let count = self.read_leb_u32()?;
-                    let mut func = Vec::with_capacity(count as usize);
+                    let mut func = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         func.push(self.read_leb_u32()? as usize);
                     }
                     Element::E0x03(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele: (elekind, func),
                     })
@@ -136,12 +161,12 @@ where
                 0x04 => {
                     let expr = self.parse_code(ops, &mut vec![])?;
                     let count = self.read_leb_u32()?;
-                    let mut exprs = Vec::with_capacity(count as usize);
+                    let mut exprs = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         exprs.push(self.parse_code(ops, &mut vec![])?);
                     }
                     Element::E0x04(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele: (expr, exprs),
                     })
@@ -149,13 +174,13 @@ where
                 0x05 => {
                     let ty = self.read_byte()?;
                     let count = self.read_leb_u32()?;
-                    let mut exprs = Vec::with_capacity(count as usize);
+                    let mut exprs = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         exprs.push(self.parse_code(ops, &mut vec![])?);
                     }
                     let ele = (RefKind::from_u8(ty)?, exprs);
                     Element::E0x05(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele,
                     })
@@ -165,12 +190,12 @@ where
                     let expr = self.parse_code(ops, &mut vec![])?;
                     let ref_ty = RefKind::from_u8(self.read_byte()?)?;
                     let count = self.read_leb_u32()?;
-                    let mut exprs = Vec::with_capacity(count as usize);
+                    let mut exprs = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         exprs.push(self.parse_code(ops, &mut vec![])?);
                     }
                     Element::E0x06(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele: (table_idx, expr, ref_ty, exprs),
                     })
@@ -178,17 +203,23 @@ where
                 0x07 => {
                     let ref_ty = RefKind::from_u8(self.read_byte()?)?;
                     let count = self.read_leb_u32()?;
-                    let mut exprs = Vec::with_capacity(count as usize);
+                    let mut exprs = Vec::with_capacity(self.capacity(count));
                     for _ in 0..count {
                         exprs.push(self.parse_code(ops, &mut vec![])?);
                     }
                     Element::E0x07(ElementKind {
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         offset: start,
                         ele: (ref_ty, exprs),
                     })
                 }
-                v => return Err(anyhow!("Unknown element flag {v:x}")),
+                v => {
+                    return Err(DecodeErrorKind::UnknownTag {
+                        what: "element flag",
+                        found: v,
+                    }
+                    .into())
+                }
             };
             self.entries.push(ele);
         }
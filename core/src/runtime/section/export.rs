@@ -1,19 +1,21 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
+use super::super::error::DecodeErrorKind;
 use super::{bytecode::ByteCode, opcode::Opcode, ByteParse, ByteRead, Decode};
-use anyhow::anyhow;
 use decode_derive::ByteParser;
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExportSection {
     pub offset: usize,
     pub byte_count: u32,
     pub export_count: u32,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub entries: Vec<Export>,
 }
 
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> ExportSection {
+pub fn default(raw: Arc<[u8]>) -> ExportSection {
     ExportSection {
         offset: 0,
         byte_count: 0,
@@ -24,6 +26,7 @@ pub fn default(raw: Rc<Box<Vec<u8>>>) -> ExportSection {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Export {
     pub raw: Vec<u8>,
     pub name: String,
@@ -31,6 +34,7 @@ pub struct Export {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExportKind {
     Func(usize),   //= 0x00,
     Table(usize),  // = 0x01,
@@ -45,7 +49,11 @@ impl ExportKind {
             0x01 => Ok(ExportKind::Table(index)),
             0x02 => Ok(ExportKind::Memory(index)),
             0x03 => Ok(ExportKind::GLobal(index)),
-            _ => Err(anyhow!("Error ref tag")),
+            _ => Err(DecodeErrorKind::UnknownTag {
+                what: "export kind",
+                found: value as u32,
+            }
+            .into()),
         }
     }
 }
@@ -63,16 +71,14 @@ where
 
         for _ in 0..self.export_count {
             let start = self.offset;
-            let name_len = self.read_leb_u32()?;
-            let name = self.peek_bytes(name_len)?;
-            self.skip(name_len);
+            let name = self.read_name()?;
             let kind = self.read_byte()?;
             let index = self.read_leb_u32()? as usize;
 
             self.entries.push(Export {
-                name: String::from_utf8(name)?,
+                name,
                 kind: ExportKind::from_u8(kind, index)?,
-                raw: self.raw[start..self.offset].to_vec(),
+                raw: self.consumed(start).to_vec(),
             })
         }
         Ok(())
@@ -1,18 +1,20 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
 use super::{bytecode::ByteCode, opcode::Opcode, ByteParse, ByteRead, Decode};
 use decode_derive::ByteParser;
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FuncSection {
     pub offset: usize,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub byte_count: u32,
     pub func_count: u32,
     pub entries: Vec<usize>, // index of singtures
 }
 
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> FuncSection {
+pub fn default(raw: Arc<[u8]>) -> FuncSection {
     FuncSection {
         offset: 0,
         raw,
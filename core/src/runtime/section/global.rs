@@ -1,18 +1,20 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
 // use super::typings::ValueType;
 use super::{bytecode::ByteCode, opcode::Opcode, typings::ValueType, ByteParse, ByteRead, Decode};
 use decode_derive::ByteParser;
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalSection {
     pub offset: usize,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub byte_count: u32,
     pub global_count: u32,
     pub entries: Vec<Global>,
 }
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> GlobalSection {
+pub fn default(raw: Arc<[u8]>) -> GlobalSection {
     GlobalSection {
         offset: 0,
         raw,
@@ -23,6 +25,7 @@ pub fn default(raw: Rc<Box<Vec<u8>>>) -> GlobalSection {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Global {
     pub val_ty: ValueType,
     pub mutability: bool,
@@ -45,15 +48,15 @@ where
         self.global_count = global_count;
         for _ in 0..global_count {
             let start = self.offset;
-            let val_ty = self.read_byte()?;
+            let val_ty = self.read_value_type()?;
             let mutability = self.read_byte()? > 0;
             let expr = self.parse_code(ops, &mut vec![])?;
 
             self.entries.push(Global {
-                val_ty: ValueType::from_u8(val_ty).unwrap(),
+                val_ty,
                 mutability,
                 expr,
-                raw: self.raw[start..self.offset].to_vec(),
+                raw: self.consumed(start).to_vec(),
             })
         }
         Ok(())
@@ -1,25 +1,24 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
 // use super::typings::ValueType;
+use super::super::error::DecodeErrorKind;
 use super::{
-    bytecode::ByteCode,
-    global::Global,
-    opcode::Opcode,
-    typings::{Limit, ValueType},
-    ByteParse, ByteRead, Decode,
+    bytecode::ByteCode, global::Global, opcode::Opcode, typings::Limit, ByteParse, ByteRead, Decode,
 };
-use anyhow::anyhow;
 use decode_derive::ByteParser;
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImportSection {
     pub offset: usize,
     pub byte_count: u32,
     pub import_count: u32,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub entries: Vec<Importer>,
 }
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Importer {
     pub mod_name: String,
     pub field_name: String,
@@ -28,6 +27,7 @@ pub struct Importer {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Kind {
     Func(usize),      // 0x00
     Table(u8, Limit), // 0x01, (0x70 | 0x6f,  0x00 u32 | 0x01 u32 u32 )
@@ -35,7 +35,7 @@ pub enum Kind {
     Global(Global),   // 0x03,  ( u8, 0x00 | 0x01)
 }
 
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> ImportSection {
+pub fn default(raw: Arc<[u8]>) -> ImportSection {
     ImportSection {
         offset: 0,
         byte_count: 0,
@@ -58,13 +58,8 @@ where
         self.import_count = import_count;
         for _ in 0..import_count {
             let start = self.offset;
-            let name_len = self.read_leb_u32()?;
-            let mod_name = self.peek_bytes(name_len)?;
-            self.skip(name_len);
-
-            let name_len = self.read_leb_u32()?;
-            let field_name = self.peek_bytes(name_len)?;
-            self.skip(name_len);
+            let mod_name = self.read_name()?;
+            let field_name = self.read_name()?;
 
             let tag = self.read_byte()?;
 
@@ -83,7 +78,13 @@ where
                             minimum: self.read_leb_u32()?,
                             maximum: self.read_leb_u32()?,
                         },
-                        _ => return Err(anyhow!("unkonwn table limit flag")),
+                        v => {
+                            return Err(DecodeErrorKind::UnknownTag {
+                                what: "limit flag",
+                                found: v as u32,
+                            }
+                            .into())
+                        }
                     },
                 ),
                 0x02 => Kind::Memory(match self.read_byte()? {
@@ -97,23 +98,35 @@ where
                         minimum: self.read_leb_u32()?,
                         maximum: self.read_leb_u32()?,
                     },
-                    _ => return Err(anyhow!("unkonwn limit flag")),
+                    v => {
+                        return Err(DecodeErrorKind::UnknownTag {
+                            what: "limit flag",
+                            found: v as u32,
+                        }
+                        .into())
+                    }
                 }),
                 0x03 => {
-                    let val_ty = self.read_byte()?;
+                    let val_ty = self.read_value_type()?;
                     let mutability = self.read_byte()? > 0;
                     Kind::Global(Global {
-                        val_ty: ValueType::from_u8(val_ty).unwrap(),
+                        val_ty,
                         mutability,
-                        raw: self.raw[start..self.offset].to_vec(),
+                        raw: self.consumed(start).to_vec(),
                         expr: (0, 0, 0),
                     })
                 } // 0x00 | 0x01
-                _ => return Err(anyhow!("unkonwn import kind")),
+                _ => {
+                    return Err(DecodeErrorKind::UnknownTag {
+                        what: "import kind",
+                        found: tag as u32,
+                    }
+                    .into())
+                }
             };
             self.entries.push(Importer {
-                mod_name: String::from_utf8(mod_name).unwrap(),
-                field_name: String::from_utf8(field_name).unwrap(),
+                mod_name,
+                field_name,
                 tag,
                 kind,
             })
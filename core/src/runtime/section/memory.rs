@@ -1,17 +1,19 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
 use super::{bytecode::ByteCode, opcode::Opcode, typings::Limit, ByteParse, ByteRead, Decode};
 use decode_derive::ByteParser;
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemorySection {
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub offset: usize,
     pub byte_count: u32,
     pub entries: Vec<Mem>,
 }
 
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> MemorySection {
+pub fn default(raw: Arc<[u8]>) -> MemorySection {
     MemorySection {
         raw,
         offset: 0,
@@ -21,7 +23,7 @@ pub fn default(raw: Rc<Box<Vec<u8>>>) -> MemorySection {
 }
 
 #[derive(Debug)]
-
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mem {
     pub limits: Limit,
     pub raw: Vec<u8>,
@@ -45,10 +47,10 @@ impl Decode for MemorySection {
                     maximum: if flag & 0x01 > 0 {
                         self.read_leb_u32()?
                     } else {
-                        0x8000 // default 2GB
+                        0x10000 // 4 GiB
                     },
                 },
-                raw: self.raw[start..self.offset].to_vec(),
+                raw: self.consumed(start).to_vec(),
             };
             self.entries.push(limit);
         }
@@ -0,0 +1,192 @@
+use self::{
+    code::CodeSection, custom::CustomSection, data::DataSection, data_count::DataCountSection,
+    element::ElementSection, export::ExportSection, func::FuncSection, global::GlobalSection,
+    import::ImportSection, memory::MemorySection, opcode::Opcode, start::StartSection,
+    table::TableSection, types::TypeSection, typings::ValueType,
+};
+
+use super::error::DecodeErrorKind;
+use crate::leb;
+pub mod bytecode;
+pub mod code;
+pub mod custom;
+pub mod data;
+pub mod data_count;
+pub mod element;
+pub mod export;
+pub mod func;
+pub mod global;
+pub mod import;
+pub mod memory;
+pub mod opcode;
+pub mod start;
+pub mod table;
+pub mod types;
+pub mod typings;
+
+use anyhow::anyhow;
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Section {
+    pub custom: CustomSection,
+    pub types: TypeSection,
+    pub import: ImportSection,
+    pub func: FuncSection,
+    pub table: TableSection,
+    pub memory: MemorySection,
+    pub global: GlobalSection,
+    pub export: ExportSection,
+    pub start: StartSection,
+    pub element: ElementSection,
+    pub code: CodeSection,
+    pub data: DataSection,
+    pub data_count: DataCountSection,
+}
+
+pub trait ByteParse {
+    fn offset(&self) -> usize;
+    fn length(&self) -> usize;
+    fn skip(&mut self, num: u32);
+    /// 整个模块的原始字节，offset 和 length 都是相对它的位置
+    fn bytes(&self) -> &[u8];
+}
+
+/// 所有读取都直接借用原始字节，不做拷贝
+pub trait ByteRead
+where
+    Self: ByteParse,
+{
+    fn is_eof(&self) -> bool {
+        self.offset() > self.length()
+    }
+    /// 当前位置到 length 之间剩余的字节
+    fn remaining(&self) -> &[u8] {
+        let end = self.length().min(self.bytes().len());
+        self.bytes().get(self.offset()..end).unwrap_or_default()
+    }
+    fn peek_bytes(&self, num: u32) -> anyhow::Result<&[u8]> {
+        self.remaining()
+            .get(..num as usize)
+            .ok_or_else(|| DecodeErrorKind::UnexpectedEof.into())
+    }
+
+    fn read_byte(&mut self) -> anyhow::Result<u8> {
+        let byte = self.peek_bytes(1)?[0];
+        self.skip(1);
+        Ok(byte)
+    }
+    fn read_bytes(&mut self, num: u32) -> anyhow::Result<&[u8]> {
+        let start = self.offset();
+        self.peek_bytes(num)?;
+        self.skip(num);
+        Ok(&self.bytes()[start..start + num as usize])
+    }
+
+    /// name: vec(byte)，必须是合法的 UTF-8，否则为 malformed 模块
+    fn read_name(&mut self) -> anyhow::Result<String> {
+        let len = self.read_leb_u32()?;
+        let offset = self.offset();
+        let bytes = self.read_bytes(len)?;
+        let name = std::str::from_utf8(bytes).map_err(|err| {
+            anyhow!(
+                "malformed UTF-8 encoding in name at offset {}",
+                offset + err.valid_up_to()
+            )
+        })?;
+        Ok(name.to_string())
+    }
+
+    /// 从 start 到当前位置已经读过的字节，位置异常时为空
+    fn consumed(&self, start: usize) -> &[u8] {
+        self.bytes().get(start..self.offset()).unwrap_or_default()
+    }
+
+    /// 预分配的容量：count 来自模块内容，不能超过剩余字节数
+    fn capacity(&self, count: u32) -> usize {
+        (count as usize).min(self.remaining().len())
+    }
+
+    fn read_leb_u32(&mut self) -> anyhow::Result<u32> {
+        let (val, size) = leb::decode_leb_u32(self.remaining())?;
+        self.skip(size as u32);
+        Ok(val)
+    }
+    fn read_leb_i32(&mut self) -> anyhow::Result<i32> {
+        let (val, size) = leb::decode_leb_i32(self.remaining())?;
+        self.skip(size as u32);
+        Ok(val)
+    }
+    fn read_leb_u64(&mut self) -> anyhow::Result<u64> {
+        let (val, size) = leb::decode_leb_u64(self.remaining())?;
+        self.skip(size as u32);
+        Ok(val)
+    }
+    fn read_leb_i64(&mut self) -> anyhow::Result<i64> {
+        let (val, size) = leb::decode_leb_i64(self.remaining())?;
+        self.skip(size as u32);
+        Ok(val)
+    }
+
+    /// 值类型；开启 `function-references` 时还接受 (ref null ht) 0x63 和 (ref ht) 0x64，
+    /// 开启 `gc` 时还接受 anyref、eqref 等简写
+    fn read_value_type(&mut self) -> anyhow::Result<ValueType> {
+        let byte = self.read_byte()?;
+        #[cfg(feature = "function-references")]
+        if byte == 0x63 || byte == 0x64 {
+            return self.read_heap_type();
+        }
+        #[cfg(feature = "gc")]
+        if let Some(ty) = gc_heap_type(byte) {
+            return Ok(ty);
+        }
+        ValueType::from_u8(byte)
+    }
+
+    /// 引用的堆类型（s33）：func、extern 或函数类型索引。
+    /// 类型索引和可空性都被擦除为 funcref，call_ref 在运行时检查被调函数的类型；
+    /// 开启 `gc` 时类型索引也可能指向 struct/array，目前同样擦除为 funcref
+    #[cfg(feature = "function-references")]
+    fn read_heap_type(&mut self) -> anyhow::Result<ValueType> {
+        let v = self.read_leb_i64()?;
+        match v {
+            -0x10 => return Ok(ValueType::FuncRef),
+            -0x11 => return Ok(ValueType::ExternRef),
+            0..=0xffff_ffff => return Ok(ValueType::FuncRef),
+            _ => {}
+        }
+        #[cfg(feature = "gc")]
+        if let Some(ty) = (-0x40..0)
+            .contains(&v)
+            .then(|| gc_heap_type((v + 0x80) as u8))
+        {
+            return ty.ok_or_else(|| unknown_heap_type(v));
+        }
+        Err(unknown_heap_type(v))
+    }
+}
+
+#[cfg(feature = "function-references")]
+fn unknown_heap_type(v: i64) -> anyhow::Error {
+    DecodeErrorKind::UnknownTag {
+        what: "heap type",
+        found: v as u32,
+    }
+    .into()
+}
+
+/// GC 提案的抽象堆类型，同时也是对应引用类型的简写：
+/// any、eq、i31、struct、array 和 none 擦除为 anyref，nofunc、noextern 为空的 funcref、externref
+#[cfg(feature = "gc")]
+pub(crate) fn gc_heap_type(byte: u8) -> Option<ValueType> {
+    match byte {
+        0x6a..=0x6e | 0x71 => Some(ValueType::AnyRef),
+        0x73 => Some(ValueType::FuncRef),
+        0x72 => Some(ValueType::ExternRef),
+        _ => None,
+    }
+}
+
+pub(crate) trait Decode {
+    fn decode(&mut self, ops: &mut Vec<Opcode>) -> anyhow::Result<()>;
+}
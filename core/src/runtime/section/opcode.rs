@@ -1,11 +1,25 @@
+use anyhow::ensure;
+
+use super::types::FunctionType;
 use super::typings::ValueType;
 
+/// 访存指令的立即数，multi-memory 下可以指定内存索引
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemArg {
+    pub align: u32,
+    pub offset: u32,
+    pub memory: u32,
+}
+
 /// (start, end, len)
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location(pub usize, pub usize, pub usize);
 
 // https://webassembly.github.io/spec/core/binary/instructions.html
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Opcode {
     // Control code blocktype | t:valtype | x:s33
     // 对于结构化指令，形成嵌套块的指令序列以用于 end(0x0b) 和 else(0x05) 的显式操作码终止。
@@ -22,11 +36,15 @@ pub enum Opcode {
     Return,                                              // return
     Call(u32),                                           //call <x:funcidx>
     CallIndirect(u32, u32),                              //call_indirect <x:typeidx> <y:tableidx>
+    CallRef(u32),                                        // call_ref <x:typeidx>
+    BrOnNull(usize, usize),                              // br_on_null <l:lableidx>
+    BrOnNonNull(usize, usize),                           // br_on_non_null <l:lableidx>
 
     // reference code
     RefNull(u8),  //ref.null t:reftype
     RefIsNull,    //ref.is_null
     RefFunc(u32), //ref.func x:funcidx
+    RefAsNonNull, //ref.as_non_null
 
     // Parametric code
     Drop,                          //drop
@@ -45,31 +63,31 @@ pub enum Opcode {
     TableSet(u32), // table.set x:tableidx
 
     // memory code  memarg a:u32 o:u32  {align a, offset o}
-    I32Load(u32, u32),    // i32.load m:memarg
-    I64Load(u32, u32),    // i64.load m:memarg
-    F32Load(u32, u32),    // f32.load m:memarg
-    F64Load(u32, u32),    // f64.load m:memarg
-    I32Load8s(u32, u32),  // i32.load8_s m:memarg
-    I32Load8u(u32, u32),  // i32.load 8_um:memarg
-    I32Load16s(u32, u32), // i32.load16_s m:memarg
-    I32Load16u(u32, u32), // i32.load16_u m:memarg
-    I64Load8s(u32, u32),  // i64.load8_s m:memarg
-    I64Load8u(u32, u32),  // i64.load8_u m:memarg
-    I64Load16s(u32, u32), // i64.load16_s m:memarg
-    I64Load16u(u32, u32), // i64.load16_u m:memarg
-    I64Load32s(u32, u32), // i64.load32_s m:memarg
-    I64Load32u(u32, u32), // i64.load32_u m:memarg
-    I32Store(u32, u32),   // i32.store m:memarg
-    I64Store(u32, u32),   // i64.store m:memarg
-    F32Store(u32, u32),   // f32.store m:memarg
-    F64Store(u32, u32),   // f64.store m:memarg
-    I32Store8(u32, u32),  // i32.store8 m:memarg
-    I32Store16(u32, u32), // i32.store16 m:memarg
-    I64Store8(u32, u32),  // i64.store8 m:memarg
-    I64Store16(u32, u32), // i64.store16 m:memarg
-    I64Store32(u32, u32), // i64.store32 m:memarg
-    MemorySize,           // memory.size
-    MemoryGrow,           // memory.grow
+    I32Load(MemArg),    // i32.load m:memarg
+    I64Load(MemArg),    // i64.load m:memarg
+    F32Load(MemArg),    // f32.load m:memarg
+    F64Load(MemArg),    // f64.load m:memarg
+    I32Load8s(MemArg),  // i32.load8_s m:memarg
+    I32Load8u(MemArg),  // i32.load 8_um:memarg
+    I32Load16s(MemArg), // i32.load16_s m:memarg
+    I32Load16u(MemArg), // i32.load16_u m:memarg
+    I64Load8s(MemArg),  // i64.load8_s m:memarg
+    I64Load8u(MemArg),  // i64.load8_u m:memarg
+    I64Load16s(MemArg), // i64.load16_s m:memarg
+    I64Load16u(MemArg), // i64.load16_u m:memarg
+    I64Load32s(MemArg), // i64.load32_s m:memarg
+    I64Load32u(MemArg), // i64.load32_u m:memarg
+    I32Store(MemArg),   // i32.store m:memarg
+    I64Store(MemArg),   // i64.store m:memarg
+    F32Store(MemArg),   // f32.store m:memarg
+    F64Store(MemArg),   // f64.store m:memarg
+    I32Store8(MemArg),  // i32.store8 m:memarg
+    I32Store16(MemArg), // i32.store16 m:memarg
+    I64Store8(MemArg),  // i64.store8 m:memarg
+    I64Store16(MemArg), // i64.store16 m:memarg
+    I64Store32(MemArg), // i64.store32 m:memarg
+    MemorySize(u32),    // memory.size x:memidx
+    MemoryGrow(u32),    // memory.grow x:memidx
 
     // numeric
     // https://webassembly.github.io/spec/core/binary/instructions.html#numeric-instructions
@@ -229,10 +247,10 @@ pub enum Opcode {
     I64TruncSatF64s, // op 6:u32                     => i64.trunc_sat_f64_s
     I64TruncSatF64u, // op 7:u32                     => i64.trunc_sat_f64_u
     // -- memory
-    MemoryInit(usize), // op 8:u32 x:dataidx 0x00           =>  memory.init x
-    DataDrop(usize),   // op 9:u32 x:dataidx                =>  data.drop x
-    MemoryCopy,        // op 10:u32 0x00 0x00               =>  memory.copy
-    MemoryFill,        // op 11:u32 0x00                    =>  memory.fill
+    MemoryInit(usize, u32), // op 8:u32 x:dataidx y:memidx       =>  memory.init x y
+    DataDrop(usize),        // op 9:u32 x:dataidx                =>  data.drop x
+    MemoryCopy(u32, u32),   // op 10:u32 x:memidx y:memidx      =>  memory.copy x y
+    MemoryFill(u32),        // op 11:u32 x:memidx               =>  memory.fill x
 
     // -- table
     TableInit(usize, usize), // op 12:u32 y:elemidx x:tableidx    =>  table.init x y
@@ -245,6 +263,120 @@ pub enum Opcode {
     Reserved(u8), // reserved
 }
 
+/// 指令分类，与规范中的章节对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpClass {
+    Control,
+    Reference,
+    Parametric,
+    Variable,
+    Table,
+    Memory,
+    Numeric,
+    Vector,
+}
+
+impl Opcode {
+    pub fn class(&self) -> OpClass {
+        use Opcode::*;
+        match self {
+            Unreachable | Nop | Block(..) | Loop(..) | If(..) | Else(_) | End(_) | Br(..)
+            | BrIf(..) | BrTable(..) | Return | Call(_) | CallIndirect(..) | CallRef(_)
+            | BrOnNull(..) | BrOnNonNull(..) => OpClass::Control,
+            RefNull(_) | RefIsNull | RefFunc(_) | RefAsNonNull => OpClass::Reference,
+            Drop | Select | SelectType(..) => OpClass::Parametric,
+            LocalGet(_) | LocalSet(_) | LocalTee(_) | GlobalGet(_) | GlobalSet(_) => {
+                OpClass::Variable
+            }
+            TableGet(_) | TableSet(_) | TableInit(..) | ElemDrop(_) | TableCopy(..)
+            | TableGrow(_) | TableSize(_) | TableFill(_) => OpClass::Table,
+            I32Load(_) | I64Load(_) | F32Load(_) | F64Load(_) | I32Load8s(_) | I32Load8u(_)
+            | I32Load16s(_) | I32Load16u(_) | I64Load8s(_) | I64Load8u(_) | I64Load16s(_)
+            | I64Load16u(_) | I64Load32s(_) | I64Load32u(_) | I32Store(_) | I64Store(_)
+            | F32Store(_) | F64Store(_) | I32Store8(_) | I32Store16(_) | I64Store8(_)
+            | I64Store16(_) | I64Store32(_) | MemorySize(_) | MemoryGrow(_) | MemoryInit(..)
+            | DataDrop(_) | MemoryCopy(..) | MemoryFill(_) => OpClass::Memory,
+            FD(..) => OpClass::Vector,
+            _ => OpClass::Numeric,
+        }
+    }
+
+    /// 解释器（`WasmModule::step`）是否实现了该指令，未实现的执行到时 panic
+    pub fn is_implemented(&self) -> bool {
+        !matches!(self, Opcode::FD(_) | Opcode::Reserved(_))
+    }
+
+    /// 数值指令的操作数类型（转换指令为输入类型），非数值指令返回 None
+    pub fn operand_type(&self) -> Option<ValueType> {
+        use Opcode::*;
+        match self {
+            I32Eqz | I32Eq | I32Ne | I32Lts | I32Ltu | I32Gts | I32Gtu | I32Les | I32Leu
+            | I32Ges | I32Geu | I32Clz | I32Ctz | I32Popcnt | I32Add | I32Sub | I32Mul
+            | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or | I32Xor | I32Shl
+            | I32ShlS | I32ShlU | I32Rotl | I32Rotr | I64ExtendsI32s | I64ExtendsI32u
+            | F32ConvertI32s | F32ConvertI32u | F64ConvertI32s | F64ConvertI32u
+            | F32ReinterpretI32 | I32Extends8s | I32Extends16s => Some(ValueType::I32),
+            I64Eqz | I64Eq | I64Ne | I64Lts | I64Ltu | I64Gts | I64Gtu | I64Les | I64Leu
+            | I64Ges | I64Geu | I64Clz | I64Ctz | I64Popcnt | I64Add | I64Sub | I64Mul
+            | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or | I64Xor | I64Shl
+            | I64ShlS | I64ShlU | I64Rotl | I64Rotr | I32WrapI64 | F32ConvertI64s
+            | F32ConvertI64u | F64ConvertI64s | F64ConvertI64u | F64ReinterpretI64
+            | I64Extends8s | I64Extends16s | I64Extends32s => Some(ValueType::I64),
+            F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F32Abs | F32Neg | F32Ceil
+            | F32Floor | F32Trunc | F32Nearest | F32Sqrt | F32Add | F32Sub | F32Mul | F32Div
+            | F32Min | F32Max | F32Copysign | I32TruncF32s | I32TruncF32u | I64TruncF32s
+            | I64TruncF32u | F64DemoteF32 | I32ReinterpretF32 | I32TruncSatF32s
+            | I32TruncSatF32u | I64TruncSatF32s | I64TruncSatF32u => Some(ValueType::F32),
+            F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge | F64Abs | F64Neg | F64Ceil
+            | F64Floor | F64Trunc | F64Nearest | F64Sqrt | F64Add | F64Sub | F64Mul | F64Div
+            | F64Min | F64Max | F64Copysign | I32TruncF64s | I32TruncF64u | I64TruncF64s
+            | I64TruncF64u | F32DemoteF64 | I64ReinterpretF64 | I32TruncSatF64s
+            | I32TruncSatF64u | I64TruncSatF64s | I64TruncSatF64u => Some(ValueType::F64),
+            _ => None,
+        }
+    }
+
+    /// 数值指令压入的结果类型，比较指令的结果为 i32
+    pub fn result_type(&self) -> Option<ValueType> {
+        use Opcode::*;
+        match self {
+            I32Eqz | I32Eq | I32Ne | I32Lts | I32Ltu | I32Gts | I32Gtu | I32Les | I32Leu
+            | I32Ges | I32Geu | I64Eqz | I64Eq | I64Ne | I64Lts | I64Ltu | I64Gts | I64Gtu
+            | I64Les | I64Leu | I64Ges | I64Geu | F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge
+            | F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge | I32WrapI64 | I32TruncF32s
+            | I32TruncF32u | I32TruncF64s | I32TruncF64u | I32ReinterpretF32 | I32TruncSatF32s
+            | I32TruncSatF32u | I32TruncSatF64s | I32TruncSatF64u => Some(ValueType::I32),
+            I64ExtendsI32s | I64ExtendsI32u | I64TruncF32s | I64TruncF32u | I64TruncF64s
+            | I64TruncF64u | I64ReinterpretF64 | I64TruncSatF32s | I64TruncSatF32u
+            | I64TruncSatF64s | I64TruncSatF64u => Some(ValueType::I64),
+            F32ConvertI32s | F32ConvertI32u | F32ConvertI64s | F32ConvertI64u | F32DemoteF64
+            | F32ReinterpretI32 => Some(ValueType::F32),
+            F64ConvertI32s | F64ConvertI32u | F64ConvertI64s | F64ConvertI64u | F64DemoteF32
+            | F64ReinterpretI64 => Some(ValueType::F64),
+            // 其余数值指令的结果与操作数类型相同
+            op => op.operand_type(),
+        }
+    }
+
+    /// 数值指令从栈顶消耗的操作数个数，非数值指令为 0
+    pub fn operand_count(&self) -> usize {
+        use Opcode::*;
+        match self {
+            I32Eq | I32Ne | I32Lts | I32Ltu | I32Gts | I32Gtu | I32Les | I32Leu | I32Ges
+            | I32Geu | I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU
+            | I32And | I32Or | I32Xor | I32Shl | I32ShlS | I32ShlU | I32Rotl | I32Rotr | I64Eq
+            | I64Ne | I64Lts | I64Ltu | I64Gts | I64Gtu | I64Les | I64Leu | I64Ges | I64Geu
+            | I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or
+            | I64Xor | I64Shl | I64ShlS | I64ShlU | I64Rotl | I64Rotr | F32Eq | F32Ne | F32Lt
+            | F32Gt | F32Le | F32Ge | F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max
+            | F32Copysign | F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge | F64Add | F64Sub
+            | F64Mul | F64Div | F64Min | F64Max | F64Copysign => 2,
+            op if op.operand_type().is_some() => 1,
+            _ => 0,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum OP {
     // op <u32>
@@ -252,31 +384,32 @@ enum OP {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 // https://webassembly.github.io/spec/core/binary/instructions.html#vector-instructions
 pub enum FD {
     // prefix 0xfd
-    V128Load(u32, u32),            // v128.load  m:memarg
-    V128Load8x8s(u32, u32),        // v128.load8x8_s m:memarg
-    V128Load8x8u(u32, u32),        // v128.load8x8_u m:memarg
-    V128Load16x4s(u32, u32),       // v128.load16x4_s m:memarg
-    V128Load16x4u(u32, u32),       // v128.load16x4_u m:memarg
-    V128Load32x2s(u32, u32),       // v128.load32x2_s m:memarg
-    V128Load32x2u(u32, u32),       // v128.load32x2_u m:memarg
-    V128Load8splat(u32, u32),      // v128.load8_splat m:memarg
-    V128Load16splat(u32, u32),     // v128.load16_splat m:memarg
-    V128Load32splat(u32, u32),     // v128.load32_splat m:memarg
-    V128Load64splat(u32, u32),     // v128.load64_splat m:memarg
-    V128Load32zero(u32, u32),      // v128.load32_zero m:memarg
-    V128Load64zero(u32, u32),      // v128.load64_zero m:memarg
-    V128Store(u32, u32),           // v128.store m:memarg
-    V128Load8lane(u32, u32, u8),   // v128.load8_lane m:memarg l:laneidx:byte
-    V128Load16lane(u32, u32, u8),  // v128.load16_lane  m:memarg l:laneidx:byte
-    V128Load32lane(u32, u32, u8),  // v128.load32_lane  m:memarg l:laneidx:byte
-    V128Load64lane(u32, u32, u8),  // v128.load64_lane  m:memarg l:laneidx:byte
-    V128Store8lane(u32, u32, u8),  // v128.store8_lane  m:memarg l:laneidx:byte
-    V128Store16lane(u32, u32, u8), // v128.store16_lane  m:memarg l:laneidx:byte
-    V128Store32lane(u32, u32, u8), // v128.store32_lane  m:memarg l:laneidx:byte
-    V128Store64lane(u32, u32, u8), // v128.store64_lane  m:memarg l:laneidx:byte
+    V128Load(MemArg),            // v128.load  m:memarg
+    V128Load8x8s(MemArg),        // v128.load8x8_s m:memarg
+    V128Load8x8u(MemArg),        // v128.load8x8_u m:memarg
+    V128Load16x4s(MemArg),       // v128.load16x4_s m:memarg
+    V128Load16x4u(MemArg),       // v128.load16x4_u m:memarg
+    V128Load32x2s(MemArg),       // v128.load32x2_s m:memarg
+    V128Load32x2u(MemArg),       // v128.load32x2_u m:memarg
+    V128Load8splat(MemArg),      // v128.load8_splat m:memarg
+    V128Load16splat(MemArg),     // v128.load16_splat m:memarg
+    V128Load32splat(MemArg),     // v128.load32_splat m:memarg
+    V128Load64splat(MemArg),     // v128.load64_splat m:memarg
+    V128Load32zero(MemArg),      // v128.load32_zero m:memarg
+    V128Load64zero(MemArg),      // v128.load64_zero m:memarg
+    V128Store(MemArg),           // v128.store m:memarg
+    V128Load8lane(MemArg, u8),   // v128.load8_lane m:memarg l:laneidx:byte
+    V128Load16lane(MemArg, u8),  // v128.load16_lane  m:memarg l:laneidx:byte
+    V128Load32lane(MemArg, u8),  // v128.load32_lane  m:memarg l:laneidx:byte
+    V128Load64lane(MemArg, u8),  // v128.load64_lane  m:memarg l:laneidx:byte
+    V128Store8lane(MemArg, u8),  // v128.store8_lane  m:memarg l:laneidx:byte
+    V128Store16lane(MemArg, u8), // v128.store16_lane  m:memarg l:laneidx:byte
+    V128Store32lane(MemArg, u8), // v128.store32_lane  m:memarg l:laneidx:byte
+    V128Store64lane(MemArg, u8), // v128.store64_lane  m:memarg l:laneidx:byte
 
     V128Const(i128),       // v128.const b:bytes(16):i128
     I8x16Shuffle(Vec<u8>), // i8x16.shuffle (l:laneidx:byte)*16
@@ -511,19 +644,37 @@ pub enum FD {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlockType {
     NOP,
     ValueType(ValueType),
     Value(u32),
 }
 impl BlockType {
-    pub fn from_u32(v: u32) -> Self {
+    /// 块类型编码为 s33：-64（0x40）为空，其他负数为单个值类型，非负数为类型索引
+    pub fn from_s33(v: i64) -> anyhow::Result<Self> {
         match v {
-            0x40 => Self::NOP,
-            v => match ValueType::from_u8(v as u8) {
-                Ok(v) => Self::ValueType(v),
-                Err(_) => Self::Value(v),
-            },
+            -64 => Ok(Self::NOP),
+            -63..=-1 => Ok(Self::ValueType(ValueType::from_u8((v & 0x7f) as u8)?)),
+            _ => {
+                ensure!(
+                    (0..=u32::MAX as i64).contains(&v),
+                    "malformed block type {v}"
+                );
+                Ok(Self::Value(v as u32))
+            }
+        }
+    }
+
+    /// 块的 (参数个数, 结果个数)，类型索引越界时返回 None
+    pub fn arity(&self, types: &[FunctionType]) -> Option<(usize, usize)> {
+        match self {
+            Self::NOP => Some((0, 0)),
+            Self::ValueType(_) => Some((0, 1)),
+            Self::Value(idx) => {
+                let ty = types.get(*idx as usize)?;
+                Some((ty.params.len(), ty.results.len()))
+            }
         }
     }
 }
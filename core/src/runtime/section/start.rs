@@ -1,19 +1,21 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
 use decode_derive::ByteParser;
 
 use super::{bytecode::ByteCode, opcode::Opcode, ByteParse, ByteRead, Decode};
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StartSection {
     pub offset: usize,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub byte_count: u32,
     pub start_func: usize,
     pub has_start: bool,
 }
 
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> StartSection {
+pub fn default(raw: Arc<[u8]>) -> StartSection {
     StartSection {
         offset: 0,
         raw,
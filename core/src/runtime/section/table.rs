@@ -1,4 +1,4 @@
-use std::{fmt::Display, rc::Rc};
+use std::{fmt::Display, sync::Arc};
 
 use super::{
     bytecode::ByteCode,
@@ -9,14 +9,16 @@ use super::{
 use decode_derive::ByteParser;
 
 #[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TableSection {
     pub offset: usize,
     pub byte_count: u32,
-    pub raw: Rc<Box<Vec<u8>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
     pub table_count: u32,
     pub entries: Vec<Table>,
 }
-pub fn default(raw: Rc<Box<Vec<u8>>>) -> TableSection {
+pub fn default(raw: Arc<[u8]>) -> TableSection {
     TableSection {
         offset: 0,
         byte_count: 0,
@@ -27,6 +29,7 @@ pub fn default(raw: Rc<Box<Vec<u8>>>) -> TableSection {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Table {
     pub kind: RefKind,
     pub raw: Vec<u8>,
@@ -61,7 +64,7 @@ where
                     minimum,
                     maximum,
                 },
-                raw: self.raw[start..self.offset].to_vec(),
+                raw: self.consumed(start).to_vec(),
             })
         }
 
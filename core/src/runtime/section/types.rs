@@ -0,0 +1,298 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use super::super::error::DecodeErrorKind;
+use super::opcode::Opcode;
+use super::typings::ValueType;
+use super::{bytecode::ByteCode, ByteParse, ByteRead, Decode};
+
+use decode_derive::ByteParser;
+
+#[derive(Debug, Default, ByteParser)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeSection {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Arc<[u8]>,
+    pub byte_count: u32,
+    pub offset: usize,
+    /// 类型（开启 `gc` 时为 rec 组）个数
+    pub type_count: u32,
+    /// 按类型索引；struct/array 类型占位为没有参数和结果的函数类型，由 subtypes 区分
+    pub entries: Vec<FunctionType>,
+    /// 与 entries 一一对应
+    pub subtypes: Vec<SubType>,
+}
+
+pub fn default(raw: Arc<[u8]>) -> TypeSection {
+    TypeSection {
+        raw,
+        byte_count: 0,
+        offset: 0,
+        type_count: 0,
+        entries: vec![],
+        subtypes: vec![],
+    }
+}
+
+/// struct 字段或 array 元素的存储类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StorageType {
+    I8,
+    I16,
+    Value(ValueType),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldType {
+    pub storage: StorageType,
+    pub mutable: bool,
+}
+
+/// GC 提案的复合类型，函数类型的签名在 `FunctionType` 中
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompositeType {
+    Func,
+    Struct(Vec<FieldType>),
+    Array(FieldType),
+}
+
+/// 没有 sub 声明的类型是 final 且没有父类型
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubType {
+    pub is_final: bool,
+    pub supertypes: Vec<u32>,
+    pub composite: CompositeType,
+    /// 所在 rec 组的下标，没有 rec 声明的类型单独成组
+    pub rec_group: u32,
+}
+
+impl SubType {
+    /// 只有 MVP 的函数类型才能执行
+    pub fn is_gc(&self) -> bool {
+        !self.is_final || !self.supertypes.is_empty() || self.composite != CompositeType::Func
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionType {
+    pub raw: Vec<u8>,
+    pub param_count: u32,
+    pub result_count: u32,
+    pub params: Vec<ValueType>,
+    pub results: Vec<ValueType>,
+}
+
+impl Decode for TypeSection
+where
+    Self: ByteCode + ByteParse,
+{
+    /// deocde type section
+    ///
+    /// type_sec: 0x01| byte_count | vec<func_type>
+    /// 开启 `gc` 时为 vec<rec_type>：rec_type = 0x4e vec<sub_type> | sub_type
+    /// buf 不包含 0x01 byte_count
+    fn decode(&mut self, _ops: &mut Vec<Opcode>) -> anyhow::Result<()> {
+        let type_count = self.read_leb_u32()?;
+        self.type_count = type_count;
+
+        for group in 0..type_count {
+            #[cfg(feature = "gc")]
+            if self.peek_bytes(1)?[0] == 0x4e {
+                self.skip(1);
+                for _ in 0..self.read_leb_u32()? {
+                    self.read_sub_type(group)?;
+                }
+                continue;
+            }
+            self.read_sub_type(group)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TypeSection
+where
+    Self: ByteParse + ByteRead,
+{
+    /// sub_type = 0x50 vec<typeidx> comp_type | 0x4f vec<typeidx> comp_type | comp_type
+    fn read_sub_type(&mut self, rec_group: u32) -> anyhow::Result<()> {
+        let start = self.offset;
+        let (is_final, supertypes, form) = self.read_sub_prefix()?;
+        let mut params = vec![];
+        let mut results = vec![];
+        let composite = match form {
+            0x60 => {
+                let param_count = self.read_leb_u32()?;
+                params.reserve(self.capacity(param_count));
+                for _ in 0..param_count {
+                    params.push(self.read_value_type()?);
+                }
+                let result_count = self.read_leb_u32()?;
+                results.reserve(self.capacity(result_count));
+                for _ in 0..result_count {
+                    results.push(self.read_value_type()?);
+                }
+                CompositeType::Func
+            }
+            #[cfg(feature = "gc")]
+            0x5f => {
+                let count = self.read_leb_u32()?;
+                let mut fields = Vec::with_capacity(self.capacity(count));
+                for _ in 0..count {
+                    fields.push(self.read_field_type()?);
+                }
+                CompositeType::Struct(fields)
+            }
+            #[cfg(feature = "gc")]
+            0x5e => CompositeType::Array(self.read_field_type()?),
+            _ => {
+                return Err(DecodeErrorKind::Unexpected {
+                    what: "function type",
+                    expected: 0x60,
+                    found: form as u32,
+                }
+                .into())
+            }
+        };
+        self.entries.push(FunctionType {
+            raw: self.consumed(start).to_vec(),
+            param_count: params.len() as u32,
+            result_count: results.len() as u32,
+            params,
+            results,
+        });
+        self.subtypes.push(SubType {
+            is_final,
+            supertypes,
+            composite,
+            rec_group,
+        });
+        Ok(())
+    }
+
+    /// sub 声明的 (是否 final, 父类型, 复合类型的标记)
+    fn read_sub_prefix(&mut self) -> anyhow::Result<(bool, Vec<u32>, u8)> {
+        let form = self.read_byte()?;
+        #[cfg(feature = "gc")]
+        if form == 0x50 || form == 0x4f {
+            let count = self.read_leb_u32()?;
+            let mut supertypes = Vec::with_capacity(self.capacity(count));
+            for _ in 0..count {
+                supertypes.push(self.read_leb_u32()?);
+            }
+            return Ok((form == 0x4f, supertypes, self.read_byte()?));
+        }
+        Ok((true, vec![], form))
+    }
+
+    /// field_type = storage_type mut，storage_type 为值类型、i8（0x78）或 i16（0x77）
+    #[cfg(feature = "gc")]
+    fn read_field_type(&mut self) -> anyhow::Result<FieldType> {
+        let storage = match self.peek_bytes(1)?[0] {
+            0x78 => StorageType::I8,
+            0x77 => StorageType::I16,
+            _ => StorageType::Value(self.read_value_type()?),
+        };
+        if !matches!(storage, StorageType::Value(_)) {
+            self.skip(1);
+        }
+        let mutable = match self.read_byte()? {
+            0x00 => false,
+            0x01 => true,
+            v => {
+                return Err(DecodeErrorKind::UnknownTag {
+                    what: "mutability",
+                    found: v as u32,
+                }
+                .into())
+            }
+        };
+        Ok(FieldType { storage, mutable })
+    }
+}
+
+impl Display for TypeSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "SectionType(offset = 0x{:0>8x?}, size= {}, count = {})",
+            self.offset,
+            self.byte_count,
+            self.entries.len()
+        )?;
+        for (index, item) in self.entries.iter().enumerate() {
+            match self.subtypes.get(index) {
+                Some(sub) if sub.is_gc() => writeln!(f, "    ({index}){sub}")?,
+                _ => writeln!(f, "    ({index}){}", item)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for FunctionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params = self
+            .params
+            .iter()
+            .map(|item| format!("{}", item))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let results = self
+            .results
+            .iter()
+            .map(|item| format!("{}", item))
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(
+            f,
+            "Type: ({}) => {}",
+            params,
+            if results.is_empty() {
+                "NOP"
+            } else {
+                results.as_str()
+            }
+        )
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.mutable {
+            write!(f, "mut ")?;
+        }
+        match self.storage {
+            StorageType::I8 => write!(f, "I8"),
+            StorageType::I16 => write!(f, "I16"),
+            StorageType::Value(ty) => write!(f, "{ty}"),
+        }
+    }
+}
+
+impl Display for SubType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.composite {
+            CompositeType::Func => write!(f, "Func")?,
+            CompositeType::Struct(fields) => {
+                let fields = fields.iter().map(|field| field.to_string());
+                write!(f, "Struct: ({})", fields.collect::<Vec<_>>().join(","))?
+            }
+            CompositeType::Array(field) => write!(f, "Array: {field}")?,
+        }
+        if !self.supertypes.is_empty() {
+            write!(f, " sub {:?}", self.supertypes)?;
+        }
+        if !self.is_final {
+            write!(f, " open")?;
+        }
+        write!(f, " rec {}", self.rec_group)
+    }
+}
@@ -1,8 +1,9 @@
 use std::fmt::Display;
 
-use anyhow::anyhow;
+use super::super::error::DecodeErrorKind;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValueType {
     ExternRef, //0x6f
     FuncRef,   //0x70
@@ -11,6 +12,8 @@ pub enum ValueType {
     F32,       //0x7d
     F64,       //0x7c
     V128,      //0x7b
+    /// GC 提案的内部引用（anyref、eqref、structref 等），只解码不执行
+    AnyRef, //0x6e
 }
 
 impl ValueType {
@@ -23,7 +26,11 @@ impl ValueType {
             0x7d => Ok(ValueType::F32),
             0x7c => Ok(ValueType::F64),
             0x7b => Ok(ValueType::V128),
-            _ => Err(anyhow!("error value type tag")),
+            _ => Err(DecodeErrorKind::UnknownTag {
+                what: "value type",
+                found: value as u32,
+            }
+            .into()),
         }
     }
 }
@@ -40,12 +47,14 @@ impl Display for ValueType {
                 ValueType::F32 => "F32",
                 ValueType::F64 => "F64",
                 ValueType::V128 => "V128",
+                ValueType::AnyRef => "AnyRef",
             }
         )
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Limit {
     // 0x00 u32 | 0x01 u32 u32
     pub flag: u32,
@@ -63,6 +72,7 @@ impl Display for Limit {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RefKind {
     FuncRef,   // 0x70
     ExternRef, //0x6f
@@ -86,7 +96,11 @@ impl RefKind {
         match value {
             0x6f => Ok(Self::ExternRef),
             0x70 => Ok(Self::FuncRef),
-            _ => Err(anyhow!("Error ref tag")),
+            _ => Err(DecodeErrorKind::UnknownTag {
+                what: "reference type",
+                found: value as u32,
+            }
+            .into()),
         }
     }
 }
@@ -0,0 +1,187 @@
+//! 用 wasm-smith 生成的合法模块测试解释器：解码、用占位导入实例化并调用所有导出函数，
+//! 每一步都不应该 panic，也不应该用到解释器没有实现的指令；解码失败、trap 和超时都可以接受
+//!
+//! tests/smith.rs 按固定的种子批量生成模块，fuzz/fuzz_targets/instantiate.rs 由 libFuzzer 提供输入。
+
+use std::time::Duration;
+
+use arbitrary::Unstructured;
+use wasm_smith::{InstructionKind, InstructionKinds};
+
+use super::cancel::CancellationToken;
+use super::config::{Limits, RuntimeConfig};
+use super::coverage::Coverage;
+use super::decoder::{FuncKind, WasmModule, WasmValue, NULL_REF};
+use super::linker::Linker;
+use super::section::export::ExportKind;
+use super::section::typings::ValueType;
+use super::spectest::guarded;
+use super::trap::Trap;
+
+/// 只生成 oxygen 支持的提案和解释器实现了的指令（没有 SIMD 和 GC），内存和函数的规模保持较小
+pub fn config() -> wasm_smith::Config {
+    use InstructionKind::*;
+    wasm_smith::Config {
+        allowed_instructions: InstructionKinds::new(&[
+            Numeric, Reference, Parametric, Variable, Table, Memory, Control,
+        ]),
+        compact_imports_enabled: false,
+        exceptions_enabled: false,
+        gc_enabled: false,
+        memory64_enabled: false,
+        relaxed_simd_enabled: false,
+        simd_enabled: false,
+        tail_call_enabled: false,
+        threads_enabled: false,
+        wide_arithmetic_enabled: false,
+        extended_const_enabled: cfg!(feature = "extended-const"),
+        max_memories: 1,
+        max_memory32_bytes: 16 * 65536,
+        max_funcs: 20,
+        max_instructions: 200,
+        ..Default::default()
+    }
+}
+
+/// 由任意字节生成一个合法模块
+pub fn generate(data: &[u8]) -> arbitrary::Result<Vec<u8>> {
+    let mut u = Unstructured::new(data);
+    Ok(wasm_smith::Module::new(config(), &mut u)?.to_bytes())
+}
+
+/// `exercise` 没有 panic 时的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exercise {
+    /// 解码失败或链接失败，原因见其中的信息
+    Skipped(String),
+    Ran {
+        calls: usize,
+        traps: usize,
+    },
+}
+
+/// 解码、实例化模块并以全 0 参数依次调用导出函数；每次调用（包括 start 函数）
+/// 超过 timeout 时取消。发生 panic 或模块用到了未实现的指令时返回 Err，信息中包括出错的步骤
+pub fn exercise(buf: &[u8], timeout: Duration) -> Result<Exercise, String> {
+    let mut wasm = WasmModule::default(buf.to_vec());
+    wasm.config = RuntimeConfig::default().limits(Limits {
+        max_call_depth: Some(1000),
+        ..Default::default()
+    });
+    let token = CancellationToken::new();
+    let decoded =
+        guarded(token, timeout, || wasm.decode()).map_err(|msg| format!("decode: {msg}"))?;
+    if let Err(err) = decoded {
+        return Ok(Exercise::Skipped(format!("decode error: {err}")));
+    }
+    let coverage = Coverage::new(&wasm);
+    if let Some(op) = coverage.unimplemented().next() {
+        return Err(format!("unimplemented: {}", op.name));
+    }
+
+    let mut linker = Linker::new();
+    if let Err(err) = linker.define_dummies(&wasm) {
+        return Ok(Exercise::Skipped(format!("imports: {err:#}")));
+    }
+    let token = CancellationToken::new();
+    wasm.config.cancellation = Some(token.clone());
+    let instantiated = guarded(token, timeout, || linker.instantiate(&mut wasm))
+        .map_err(|msg| format!("instantiate: {msg}"))?;
+    match instantiated {
+        // start 函数执行了，trap 与导出函数的 trap 一样可以接受
+        Err(err) if err.downcast_ref::<Trap>().is_some() => {
+            return Ok(Exercise::Ran { calls: 1, traps: 1 })
+        }
+        Err(err) => return Ok(Exercise::Skipped(format!("instantiation error: {err:#}"))),
+        Ok(()) => {}
+    }
+
+    let mut funcs = wasm
+        .exports
+        .iter()
+        .filter_map(|(name, kind)| match kind {
+            ExportKind::Func(idx) => Some((name.clone(), *idx)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    funcs.sort();
+    let (mut calls, mut traps) = (0, 0);
+    for (name, idx) in funcs {
+        let ty = match &wasm.func[idx] {
+            FuncKind::Import(ty, _) | FuncKind::Local((ty, _)) => *ty,
+        };
+        let Some(args) = wasm.section.types.entries[ty]
+            .params
+            .iter()
+            .map(|ty| zero(*ty))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let token = CancellationToken::new();
+        wasm.config.cancellation = Some(token.clone());
+        let result = guarded(token, timeout, || wasm.call_with(idx, &args))
+            .map_err(|msg| format!("call `{name}`: {msg}"))?;
+        calls += 1;
+        traps += result.is_err() as usize;
+    }
+    Ok(Exercise::Ran { calls, traps })
+}
+
+fn zero(ty: ValueType) -> Option<WasmValue> {
+    Some(match ty {
+        ValueType::I32 => WasmValue::I32(0),
+        ValueType::I64 => WasmValue::I64(0),
+        ValueType::F32 => WasmValue::F32(0.0),
+        ValueType::F64 => WasmValue::F64(0.0),
+        ValueType::V128 => WasmValue::V128(0),
+        ValueType::FuncRef => WasmValue::FuncRef(NULL_REF),
+        ValueType::ExternRef => WasmValue::ExternRef(NULL_REF),
+        ValueType::AnyRef => return None,
+    })
+}
+
+#[test]
+fn test_exercise() {
+    use super::wat;
+
+    let src = r#"(module
+      (import "env" "log" (func $log (param i32)))
+      (memory 1)
+      (func (export "a") (param i32) (result i32) (i32.add (local.get 0) (i32.const 1)))
+      (func (export "b") (call $log (i32.const 0)))
+      (func (export "c") (loop (br 0))))"#;
+    let buf = wat::compile(src).unwrap();
+    let timeout = Duration::from_millis(50);
+    // 调用占位导入和超时都算作 trap
+    assert_eq!(
+        exercise(&buf, timeout),
+        Ok(Exercise::Ran { calls: 3, traps: 2 })
+    );
+    assert!(matches!(
+        exercise(&buf[..buf.len() - 1], timeout),
+        Ok(Exercise::Skipped(msg)) if msg.starts_with("decode error")
+    ));
+
+    // 生成的模块中不应该有解释器没有实现的指令：(func (export "f") v128.const 0, drop)
+    let buf = [
+        [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section: () -> ()
+            0x03, 0x02, 0x01, 0x00, // function section
+            0x07, 0x05, 0x01, 0x01, 0x66, 0x00, 0x00, // export section: "f"
+            0x0a, 0x17, 0x01, 0x15, 0x00, 0xfd, 0x0c, // code section
+        ]
+        .as_slice(),
+        &[0; 16],
+        &[0x1a, 0x0b],
+    ]
+    .concat();
+    assert_eq!(
+        exercise(&buf, timeout),
+        Err("unimplemented: FD".to_string())
+    );
+
+    let buf = generate(&[7; 256]).unwrap();
+    assert!(exercise(&buf, timeout).is_ok());
+}
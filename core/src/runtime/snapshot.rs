@@ -0,0 +1,171 @@
+//! 预初始化快照：执行一次初始化导出函数后保存内存和全局变量，之后的实例从快照开始，
+//! 跳过 start 函数和初始化代码，减少冷启动时间
+//!
+//! 快照只包括模块自己定义的内存和全局变量，以及数据段和元素段的 drop 状态；
+//! 导入的内存和全局变量属于宿主，表的内容不保存，仍由元素段初始化。
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure};
+
+use super::constants::PAGE_SIZE;
+use super::decoder::{Global, ImportObject, WasmModule, WasmValue};
+use super::linear::Storage;
+use super::linker::Linker;
+use super::OxygenRuntime;
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// 快照所属模块的字节
+    raw: Arc<[u8]>,
+    /// 按内存索引存放，导入的内存为 None
+    memories: Vec<Option<Arc<[u8]>>>,
+    /// 按全局变量索引存放，导入的全局变量为 None
+    globals: Vec<Option<WasmValue>>,
+    data: Vec<Vec<u8>>,
+    elem: Vec<Vec<usize>>,
+}
+
+impl Snapshot {
+    /// 快照中内存的总字节数
+    pub fn memory_size(&self) -> usize {
+        self.memories.iter().flatten().map(|mem| mem.len()).sum()
+    }
+}
+
+impl WasmModule {
+    /// 保存当前实例的内存、全局变量和段状态
+    pub fn snapshot(&self) -> Snapshot {
+        let memories = self
+            .mem
+            .iter()
+            .map(|mem| match mem.storage() {
+                Storage::Shared(_) => None,
+                _ => Some(Arc::from(&mem[..])),
+            })
+            .collect();
+        let globals = self
+            .global
+            .iter()
+            .map(|global| match global {
+                Global::Shared(_) => None,
+                _ => Some(global.get()),
+            })
+            .collect();
+        Snapshot {
+            raw: self.raw.clone(),
+            memories,
+            globals,
+            data: self.data.clone(),
+            elem: self.elem.clone(),
+        }
+    }
+
+    /// 实例化但不执行 start 函数，内存、全局变量和段状态从 snapshot 恢复；
+    /// snapshot 必须来自同一个模块
+    pub fn instance_from_snapshot(
+        &mut self,
+        import_object: Option<ImportObject>,
+        snapshot: &Snapshot,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            Arc::ptr_eq(&self.raw, &snapshot.raw) || self.raw == snapshot.raw,
+            "snapshot was taken from a different module"
+        );
+        self.init_instance(import_object)?;
+        for (idx, (mem, saved)) in self.mem.iter_mut().zip(&snapshot.memories).enumerate() {
+            let Some(saved) = saved else { continue };
+            let pages = (saved.len() / PAGE_SIZE) as u32;
+            if let Some(delta) = pages.checked_sub(mem.pages()) {
+                mem.grow(delta)
+                    .ok_or_else(|| anyhow!("can't grow memory {idx} to {pages} pages"))?;
+            }
+            ensure!(
+                mem.pages() == pages,
+                "memory {idx} has more pages than the snapshot"
+            );
+            mem.copy_from_slice(saved);
+        }
+        for (global, saved) in self.global.iter_mut().zip(&snapshot.globals) {
+            if let (Global::Var(_), Some(value)) = (&global, saved) {
+                global.set(*value);
+            }
+        }
+        self.data.clone_from(&snapshot.data);
+        self.elem.clone_from(&snapshot.elem);
+        Ok(())
+    }
+}
+
+impl OxygenRuntime {
+    /// 以第 module 个模块创建实例，执行导出函数 init 后保存快照；
+    /// 导入由 linker 提供，之后用 `instantiate_snapshot` 从快照创建实例
+    pub fn wizen(&self, module: usize, init: &str, linker: &Linker) -> anyhow::Result<Snapshot> {
+        let raw = self
+            .modes
+            .get(module)
+            .ok_or_else(|| anyhow!("unknown module {module}"))?
+            .raw
+            .clone();
+        let mut wasm = WasmModule::default(raw);
+        wasm.config = self.config.clone();
+        wasm.decode()?;
+        linker.instantiate(&mut wasm)?;
+        wasm.invoke(init, &[])?;
+        Ok(wasm.snapshot())
+    }
+
+    /// 从 snapshot 创建一个新的实例，不执行 start 函数和初始化导出函数
+    pub fn instantiate_snapshot(
+        &self,
+        snapshot: &Snapshot,
+        linker: &Linker,
+    ) -> anyhow::Result<WasmModule> {
+        let mut wasm = WasmModule::default(snapshot.raw.clone());
+        wasm.config = self.config.clone();
+        wasm.decode()?;
+        linker.instantiate_snapshot(&mut wasm, snapshot)?;
+        Ok(wasm)
+    }
+}
+
+#[test]
+fn test_snapshot() {
+    use super::wat;
+
+    let src = r#"(module
+      (global $ready (mut i32) (i32.const 0))
+      (memory 1 4)
+      (data (i32.const 0) "abc")
+      (func (export "init")
+        (drop (memory.grow (i32.const 1)))
+        (i32.store (i32.const 70000) (i32.const 42))
+        (global.set $ready (i32.const 1)))
+      (func (export "ready") (result i32) (global.get $ready))
+      (func (export "load") (result i32) (i32.load (i32.const 70000)))
+      (func (export "first") (result i32) (i32.load8_u (i32.const 0)))
+      (func (export "reset") (i32.store8 (i32.const 0) (i32.const 0)))
+      (func (export "pages") (result i32) (memory.size)))"#;
+    let mut rt = OxygenRuntime::default();
+    rt.load(wat::compile(src).unwrap()).unwrap();
+    let linker = Linker::new();
+    let snapshot = rt.wizen(0, "init", &linker).unwrap();
+    assert_eq!(snapshot.memory_size(), 2 * PAGE_SIZE);
+
+    let mut a = rt.instantiate_snapshot(&snapshot, &linker).unwrap();
+    assert_eq!(a.invoke("ready", &[]).unwrap(), [WasmValue::I32(1)]);
+    assert_eq!(a.invoke("pages", &[]).unwrap(), [WasmValue::I32(2)]);
+    assert_eq!(a.invoke("load", &[]).unwrap(), [WasmValue::I32(42)]);
+    assert_eq!(a.invoke("first", &[]).unwrap(), [WasmValue::I32(97)]);
+    a.invoke("reset", &[]).unwrap();
+    assert_eq!(a.invoke("first", &[]).unwrap(), [WasmValue::I32(0)]);
+
+    // 实例之间互不影响
+    let mut b = rt.instantiate_snapshot(&snapshot, &linker).unwrap();
+    assert_eq!(b.invoke("first", &[]).unwrap(), [WasmValue::I32(97)]);
+
+    let mut wasm = WasmModule::default(wat::compile("(module)").unwrap());
+    wasm.decode().unwrap();
+    assert!(linker.instantiate_snapshot(&mut wasm, &snapshot).is_err());
+    assert!(rt.wizen(0, "missing", &linker).is_err());
+}
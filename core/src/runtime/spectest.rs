@@ -0,0 +1,658 @@
+//! 运行 .wast 脚本，用于执行 WebAssembly 官方测试套件
+//!
+//! 每个断言单独计数，失败不会中断脚本。几点限制：
+//! - `register` 通过 [`Linker::define_shared_instance`] 注册实例，导出的函数、内存和全局变量
+//!   与导入它们的模块共享；表中的函数索引属于导出它的实例，导出的表不注册；
+//! - 模块解码后先经过 `WasmModule::validate` 检查再实例化；模块断言只检查失败的阶段，
+//!   不比较错误信息；文本格式的 `assert_malformed` 跳过；
+//! - 每个动作超过 timeout 后通过 CancellationToken 取消，panic 记为失败；
+//!   执行到还没有实现的指令（SIMD）同样记为失败，用到它的模块上的动作也都记为失败，
+//!   失败信息以 `unimplemented:` 开头。
+//!
+//! 测试脚本导入的 `spectest` 模块由 [`Linker::define_spectest`] 提供。
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use super::cancel::CancellationToken;
+use super::config::RuntimeConfig;
+use super::decoder::{WasmModule, WasmValue};
+use super::linker::{shared_instance, Func, Linker, SharedInstance};
+use super::minimize::panic_message;
+use super::section::export::ExportKind;
+use super::section::typings::ValueType;
+use super::trap::Trap;
+use super::wast::{
+    parse_script, Action, Command, Directive, Expected, ModuleAssert, ModuleDef, ModuleSource,
+};
+use super::wat;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub passed: usize,
+    pub skipped: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed,
+            self.failures.len(),
+            self.skipped
+        )
+    }
+}
+
+/// 加载模块失败的阶段
+enum LoadError {
+    /// 文本编译或二进制解码失败
+    Decode(String),
+    /// 函数体没有通过类型检查
+    Invalid(String),
+    /// 导入无法满足
+    Link(String),
+    /// 实例化（start 函数）时 trap
+    Trap(String),
+    /// 用到了还没有实现的指令
+    Unsupported(String),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Decode(msg) => write!(f, "decode error: {msg}"),
+            LoadError::Invalid(msg) => write!(f, "validation error: {msg}"),
+            LoadError::Link(msg) => write!(f, "link error: {msg}"),
+            LoadError::Trap(msg) => write!(f, "trap: {msg}"),
+            LoadError::Unsupported(msg) => write!(f, "unimplemented: {msg}"),
+        }
+    }
+}
+
+/// 动作执行失败
+enum ActionError {
+    Trap(Trap),
+    /// 执行到还没有实现的指令，或者模块本身用到了没有实现的指令
+    Unsupported(String),
+    Other(String),
+}
+
+impl Display for ActionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionError::Trap(trap) => write!(f, "{trap}"),
+            ActionError::Unsupported(msg) => write!(f, "unimplemented: {msg}"),
+            ActionError::Other(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+enum Outcome {
+    Pass,
+    Skip,
+}
+
+pub struct WastRunner {
+    pub config: RuntimeConfig,
+    /// 单个动作的最长执行时间
+    pub timeout: Duration,
+    pub linker: Linker,
+    /// 用到了还没有实现的指令的模块为 None
+    instances: Vec<Option<SharedInstance>>,
+    names: HashMap<String, usize>,
+    current: Option<usize>,
+}
+
+/// 与参考解释器一致，每个参数输出一行 `值 : 类型`
+fn spectest_print(_: &mut WasmModule, args: &Vec<WasmValue>) -> Vec<WasmValue> {
+    for arg in args {
+        match *arg {
+            WasmValue::I32(v) => println!("{v} : i32"),
+            WasmValue::I64(v) => println!("{v} : i64"),
+            WasmValue::F32(v) => println!("{v} : f32"),
+            WasmValue::F64(v) => println!("{v} : f64"),
+            v => println!("{v:?}"),
+        }
+    }
+    vec![]
+}
+
+impl Linker {
+    /// 定义测试套件使用的 `spectest` 模块：`print*` 函数、`global_*` 常量、
+    /// `table`（10 ~ 20 个 funcref）和 `memory`（1 ~ 2 页）
+    pub fn define_spectest(&mut self) -> anyhow::Result<&mut Self> {
+        use ValueType::*;
+        let prints: [(&str, &[ValueType]); 7] = [
+            ("print", &[]),
+            ("print_i32", &[I32]),
+            ("print_i64", &[I64]),
+            ("print_f32", &[F32]),
+            ("print_f64", &[F64]),
+            ("print_i32_f32", &[I32, F32]),
+            ("print_f64_f64", &[F64, F64]),
+        ];
+        for (name, params) in prints {
+            self.define("spectest", name, Func::wrap(params, &[], spectest_print))?;
+        }
+        self.define_global("spectest", "global_i32", WasmValue::I32(666), false)?
+            .define_global("spectest", "global_i64", WasmValue::I64(666), false)?
+            .define_global("spectest", "global_f32", WasmValue::F32(666.6), false)?
+            .define_global("spectest", "global_f64", WasmValue::F64(666.6), false)?
+            .define_table("spectest", "table", FuncRef, 10, Some(20))?
+            .define_memory("spectest", "memory", 1, Some(2))
+    }
+}
+
+/// 在 timeout 内执行 f，超时后取消 token；f 中的 panic 转为 Err
+pub(crate) fn guarded<T>(
+    token: CancellationToken,
+    timeout: Duration,
+    f: impl FnOnce() -> T,
+) -> Result<T, String> {
+    let (tx, rx) = mpsc::channel::<()>();
+    let watchdog = thread::spawn(move || {
+        if rx.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+            token.cancel();
+        }
+    });
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    drop(tx);
+    let _ = watchdog.join();
+    result.map_err(|payload| format!("panic: {}", panic_message(payload)))
+}
+
+/// `guarded` 捕获的 panic 是否来自还没有实现的指令（`todo!`）
+fn is_unimplemented(msg: &str) -> bool {
+    msg.starts_with("panic: not yet implemented")
+}
+
+fn matches(expected: &Expected, actual: WasmValue) -> bool {
+    match (expected, actual) {
+        (Expected::Value(WasmValue::F32(e)), WasmValue::F32(a)) => e.to_bits() == a.to_bits(),
+        (Expected::Value(WasmValue::F64(e)), WasmValue::F64(a)) => e.to_bits() == a.to_bits(),
+        (Expected::Value(e), a) => *e == a,
+        (Expected::CanonicalNan(ValueType::F32), WasmValue::F32(a)) => {
+            a.to_bits() & 0x7fff_ffff == 0x7fc0_0000
+        }
+        (Expected::CanonicalNan(ValueType::F64), WasmValue::F64(a)) => {
+            a.to_bits() & 0x7fff_ffff_ffff_ffff == 0x7ff8_0000_0000_0000
+        }
+        (Expected::ArithmeticNan(ValueType::F32), WasmValue::F32(a)) => {
+            a.is_nan() && a.to_bits() & 0x0040_0000 != 0
+        }
+        (Expected::ArithmeticNan(ValueType::F64), WasmValue::F64(a)) => {
+            a.is_nan() && a.to_bits() & 0x0008_0000_0000_0000 != 0
+        }
+        (Expected::Either(options), a) => options.iter().any(|e| matches(e, a)),
+        _ => false,
+    }
+}
+
+impl WastRunner {
+    pub fn new(config: RuntimeConfig) -> Self {
+        let mut linker = Linker::new();
+        linker
+            .define_spectest()
+            .expect("spectest definitions are valid");
+        Self {
+            config,
+            timeout: Duration::from_secs(10),
+            linker,
+            instances: vec![],
+            names: HashMap::new(),
+            current: None,
+        }
+    }
+
+    /// 解析并执行脚本，脚本本身无法解析时返回 Err
+    pub fn run_script(&mut self, src: &str) -> anyhow::Result<Report> {
+        let commands = parse_script(src)?;
+        let mut report = Report::default();
+        for command in commands.iter() {
+            match self.command(command) {
+                Ok(Outcome::Pass) => report.passed += 1,
+                Ok(Outcome::Skip) => report.skipped += 1,
+                Err(message) => report.failures.push(Failure {
+                    line: command.line,
+                    message,
+                }),
+            }
+        }
+        Ok(report)
+    }
+
+    fn load(&self, def: &ModuleDef) -> Result<WasmModule, LoadError> {
+        let buf = match &def.source {
+            ModuleSource::Text(expr) => wat::compile_module(expr),
+            ModuleSource::Binary(bytes) => Ok(bytes.clone()),
+            ModuleSource::Quote(text) => wat::compile(text),
+        }
+        .map_err(|err| LoadError::Decode(format!("{err:#}")))?;
+        let token = CancellationToken::new();
+        let config = self.config.clone().cancellation_token(token.clone());
+        let load = || {
+            let mut wasm = WasmModule::default(buf);
+            wasm.config = config;
+            wasm.decode()
+                .map_err(|err| LoadError::Decode(format!("{err:#}")))?;
+            // 验证器不支持的指令（SIMD）不代表模块不合法
+            if let Some(err) = wasm.validate().first() {
+                return Err(match err.message.ends_with("not supported") {
+                    true => LoadError::Unsupported(err.to_string()),
+                    false => LoadError::Invalid(err.to_string()),
+                });
+            }
+            if let Err(err) = self.linker.instantiate(&mut wasm) {
+                return Err(match err.downcast::<Trap>() {
+                    Ok(trap) => LoadError::Trap(trap.to_string()),
+                    Err(err) => LoadError::Link(format!("{err:#}")),
+                });
+            }
+            Ok(wasm)
+        };
+        guarded(token, self.timeout, load).unwrap_or_else(|msg| match is_unimplemented(&msg) {
+            true => Err(LoadError::Unsupported(msg)),
+            false => Err(LoadError::Decode(msg)),
+        })
+    }
+
+    fn instance(&self, name: &Option<String>) -> Result<SharedInstance, ActionError> {
+        let idx = match name {
+            Some(name) => self.names.get(name).copied(),
+            None => self.current,
+        };
+        let Some(idx) = idx else {
+            return Err(ActionError::Other(match name {
+                Some(name) => format!("unknown module {name}"),
+                None => "no module has been instantiated".to_string(),
+            }));
+        };
+        self.instances[idx]
+            .clone()
+            .ok_or_else(|| ActionError::Unsupported("module was not instantiated".to_string()))
+    }
+
+    /// 执行动作，返回按声明顺序排列的结果
+    fn action(&mut self, action: &Action) -> Result<Vec<WasmValue>, ActionError> {
+        let timeout = self.timeout;
+        match action {
+            Action::Invoke { module, name, args } => {
+                let instance = self.instance(module)?;
+                let mut guard = instance.lock().unwrap();
+                let wasm = &mut *guard;
+                let idx = match wasm.exports.get(name) {
+                    Some(ExportKind::Func(idx)) => *idx,
+                    _ => {
+                        return Err(ActionError::Other(format!(
+                            "unknown function export `{name}`"
+                        )))
+                    }
+                };
+                let token = CancellationToken::new();
+                wasm.config.cancellation = Some(token.clone());
+                wasm.sp = 0;
+                wasm.fp = 0;
+                wasm.csp = 0;
+                wasm.stack_check();
+                for arg in args {
+                    wasm.sp += 1;
+                    wasm.stack[wasm.sp] = *arg;
+                }
+                guarded(token, timeout, || wasm.call(idx))
+                    .map_err(|msg| match is_unimplemented(&msg) {
+                        true => ActionError::Unsupported(msg),
+                        false => ActionError::Other(msg),
+                    })?
+                    .map_err(ActionError::Trap)
+            }
+            Action::Get { module, name } => {
+                let instance = self.instance(module)?;
+                let mut wasm = instance.lock().unwrap();
+                let global = wasm
+                    .get_global(name)
+                    .map_err(|err| ActionError::Other(format!("{err:#}")))?;
+                Ok(vec![global.get()])
+            }
+        }
+    }
+
+    /// 把模块的导出以 name 注册到 linker：函数在这个实例上执行，内存和全局变量与导入它们的模块共享
+    fn register(&mut self, name: &str, module: &Option<String>) -> Result<(), ActionError> {
+        let instance = self.instance(module)?;
+        self.linker
+            .define_shared_instance(name, &instance)
+            .map_err(|err| ActionError::Other(format!("{err:#}")))?;
+        Ok(())
+    }
+
+    fn command(&mut self, command: &Command) -> Result<Outcome, String> {
+        match &command.directive {
+            Directive::Module(def) => {
+                self.current = None;
+                // 用到没有实现的指令的模块仍然占一个位置，后面对它的动作报告同样的原因
+                let (wasm, result) = match self.load(def) {
+                    Ok(wasm) => (Some(shared_instance(wasm)), Ok(Outcome::Pass)),
+                    Err(err @ LoadError::Unsupported(_)) => (None, Err(err.to_string())),
+                    Err(err) => return Err(err.to_string()),
+                };
+                self.instances.push(wasm);
+                let idx = self.instances.len() - 1;
+                if let Some(name) = &def.name {
+                    self.names.insert(name.clone(), idx);
+                }
+                self.current = Some(idx);
+                return result;
+            }
+            Directive::Register { name, module } => {
+                self.register(name, module).map_err(|err| err.to_string())?
+            }
+            Directive::Action(action) => {
+                self.action(action).map_err(|err| err.to_string())?;
+            }
+            Directive::AssertReturn { action, expected } => {
+                let results = self.action(action).map_err(|err| err.to_string())?;
+                let ok = results.len() == expected.len()
+                    && expected.iter().zip(&results).all(|(e, a)| matches(e, *a));
+                if !ok {
+                    return Err(format!("expected {expected:?}, got {results:?}"));
+                }
+            }
+            Directive::AssertTrap { action, message } => match self.action(action) {
+                Err(ActionError::Trap(trap)) if trap.to_string().contains(message.as_str()) => {}
+                Err(ActionError::Trap(trap)) => {
+                    return Err(format!("expected trap `{message}`, got `{trap}`"))
+                }
+                Err(err) => return Err(err.to_string()),
+                Ok(results) => return Err(format!("expected trap `{message}`, got {results:?}")),
+            },
+            Directive::AssertExhaustion { action, message } => match self.action(action) {
+                Err(ActionError::Trap(Trap::StackOverflow)) => {}
+                Err(err @ ActionError::Unsupported(_)) => return Err(err.to_string()),
+                Err(err) => return Err(format!("expected `{message}`, got `{err}`")),
+                Ok(results) => return Err(format!("expected `{message}`, got {results:?}")),
+            },
+            Directive::AssertModule {
+                kind: ModuleAssert::Malformed,
+                module:
+                    ModuleDef {
+                        source: ModuleSource::Quote(_),
+                        ..
+                    },
+                ..
+            } => return Ok(Outcome::Skip),
+            Directive::AssertModule {
+                kind,
+                module,
+                message,
+            } => match (kind, self.load(module)) {
+                (_, Ok(_)) => {
+                    return Err(format!("expected module to fail with `{message}`"));
+                }
+                (_, Err(err @ LoadError::Unsupported(_))) => return Err(err.to_string()),
+                // 解码时已经做了一部分验证（例如标签深度），两个阶段的错误都算作不合法
+                (
+                    ModuleAssert::Invalid | ModuleAssert::Malformed,
+                    Err(LoadError::Decode(_) | LoadError::Invalid(_)),
+                ) => {}
+                (ModuleAssert::Unlinkable, Err(LoadError::Link(_))) => {}
+                (ModuleAssert::Uninstantiable, Err(LoadError::Trap(_))) => {}
+                (_, Err(err)) => return Err(format!("expected `{message}`, got {err}")),
+            },
+            Directive::Unsupported(_) => return Ok(Outcome::Skip),
+        }
+        Ok(Outcome::Pass)
+    }
+}
+
+#[test]
+fn test_run_script() {
+    let script = r#"
+        (module $a
+          (global (export "g") i32 (i32.const 7))
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+          (func (export "nan") (result f32) (f32.div (f32.const 0) (f32.const 0)))
+          (func (export "boom") unreachable)
+          (func $rec (export "rec") (call $rec)))
+        (assert_return (invoke "add" (i32.const 1) (i32.const 2)) (i32.const 3))
+        (assert_return (invoke "nan") (f32.const nan:arithmetic))
+        (assert_trap (invoke "boom") "unreachable")
+        (assert_exhaustion (invoke "rec") "call stack exhausted")
+        (assert_return (get "g") (i32.const 7))
+        (register "a" $a)
+        (module (import "a" "g" (global i32)) (func (export "f") (result i32) (global.get 0)))
+        (assert_return (invoke "f") (i32.const 7))
+        (assert_return (invoke $a "add" (i32.const 1) (i32.const 1)) (i32.const 3))
+        (assert_malformed (module binary "\00asm") "unexpected end")
+        (assert_malformed (module quote "(func") "unclosed")
+        (assert_unlinkable (module (import "a" "missing" (func))) "unknown import")
+        (assert_return (invoke "f") (ref.null extern))
+        (module
+          (import "spectest" "print_i32" (func $print (param i32)))
+          (import "spectest" "global_f64" (global f64))
+          (import "spectest" "table" (table 10 funcref))
+          (import "spectest" "memory" (memory 1))
+          (elem (i32.const 9) $print)
+          (func (export "size") (result i32)
+            (call_indirect (param i32) (i32.const 42) (i32.const 9))
+            (memory.size)))
+        (assert_return (invoke "size") (i32.const 1))
+        (assert_return (get "missing") (f64.const 666.6))
+    "#;
+    let limits = super::config::Limits {
+        max_call_depth: Some(20),
+        ..Default::default()
+    };
+    let mut runner = WastRunner::new(RuntimeConfig::default().limits(limits));
+    let report = runner.run_script(script).unwrap();
+    assert_eq!(report.passed, 13);
+    assert_eq!(report.skipped, 2);
+    assert_eq!(report.failures.len(), 2);
+    assert_eq!(report.failures[0].line, 17);
+    assert!(report.failures[0]
+        .message
+        .contains("expected [Value(I32(3))]"));
+    assert_eq!(report.failures[1].line, 32);
+    assert_eq!(report.to_string(), "13 passed, 2 failed, 2 skipped");
+}
+
+#[test]
+fn test_register() {
+    use super::testing::all_engine_configs;
+    let script = r#"
+        (module $a
+          (memory (export "mem") 1)
+          (global (export "g") (mut i32) (i32.const 1))
+          (func (export "inc") (global.set 0 (i32.add (global.get 0) (i32.const 1))))
+          (func (export "load") (result i32) (i32.load8_u (i32.const 0))))
+        (register "a" $a)
+        (module $b
+          (import "a" "mem" (memory 1))
+          (import "a" "g" (global (mut i32)))
+          (import "a" "inc" (func $inc))
+          (func (export "run") (result i32)
+            (i32.store8 (i32.const 0) (i32.const 42))
+            (call $inc)
+            (global.get 0)))
+        (assert_return (invoke $b "run") (i32.const 2))
+        (assert_return (invoke $a "load") (i32.const 42))
+        (assert_return (get $a "g") (i32.const 2))
+        (assert_return (invoke $b "run") (i32.const 3))
+    "#;
+    for config in all_engine_configs() {
+        let report = WastRunner::new(config).run_script(script).unwrap();
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(report.passed, 7);
+    }
+}
+
+#[test]
+fn test_assert_invalid() {
+    let script = r#"
+        (assert_invalid (module (func (result i32) (i64.const 1))) "type mismatch")
+        (assert_invalid (module (func (br 1))) "unknown label")
+        (assert_invalid (module (func (result i32) (i32.const 1))) "type mismatch")
+        (assert_unlinkable (module (func (result i32) (f32.const 1))) "unknown import")
+        (module (func (export "f") (result i32) (i64.const 1)))
+    "#;
+    let report = WastRunner::new(RuntimeConfig::default())
+        .run_script(script)
+        .unwrap();
+    assert_eq!(report.passed, 2);
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| (failure.line, failure.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures[0],
+        (4, "expected module to fail with `type mismatch`")
+    );
+    assert!(failures[1]
+        .1
+        .starts_with("expected `unknown import`, got validation error: type mismatch"));
+    assert_eq!(failures[1].0, 5);
+    assert!(failures[2].1.starts_with("validation error: type mismatch"));
+}
+
+#[test]
+fn test_integer_traps() {
+    use super::testing::all_engine_configs;
+    let script = r#"
+        (module
+          (func (export "i32.add") (param i32 i32) (result i32) (i32.add (local.get 0) (local.get 1)))
+          (func (export "i32.mul") (param i32 i32) (result i32) (i32.mul (local.get 0) (local.get 1)))
+          (func (export "i32.shl") (param i32 i32) (result i32) (i32.shl (local.get 0) (local.get 1)))
+          (func (export "i32.div_s") (param i32 i32) (result i32) (i32.div_s (local.get 0) (local.get 1)))
+          (func (export "i32.div_u") (param i32 i32) (result i32) (i32.div_u (local.get 0) (local.get 1)))
+          (func (export "i32.rem_s") (param i32 i32) (result i32) (i32.rem_s (local.get 0) (local.get 1)))
+          (func (export "i32.rem_u") (param i32 i32) (result i32) (i32.rem_u (local.get 0) (local.get 1)))
+          (func (export "i64.sub") (param i64 i64) (result i64) (i64.sub (local.get 0) (local.get 1)))
+          (func (export "i64.mul") (param i64 i64) (result i64) (i64.mul (local.get 0) (local.get 1)))
+          (func (export "i64.shl") (param i64 i64) (result i64) (i64.shl (local.get 0) (local.get 1)))
+          (func (export "i64.div_s") (param i64 i64) (result i64) (i64.div_s (local.get 0) (local.get 1)))
+          (func (export "i64.rem_s") (param i64 i64) (result i64) (i64.rem_s (local.get 0) (local.get 1)))
+          (func (export "i64.rem_u") (param i64 i64) (result i64) (i64.rem_u (local.get 0) (local.get 1))))
+        (assert_return (invoke "i32.add" (i32.const 0x7fffffff) (i32.const 1)) (i32.const 0x80000000))
+        (assert_return (invoke "i32.mul" (i32.const 0x10000) (i32.const 0x10000)) (i32.const 0))
+        (assert_return (invoke "i32.shl" (i32.const 1) (i32.const 33)) (i32.const 2))
+        (assert_return (invoke "i32.div_s" (i32.const -7) (i32.const 2)) (i32.const -3))
+        (assert_trap (invoke "i32.div_s" (i32.const 1) (i32.const 0)) "integer divide by zero")
+        (assert_trap (invoke "i32.div_s" (i32.const 0x80000000) (i32.const -1)) "integer overflow")
+        (assert_trap (invoke "i32.div_u" (i32.const 1) (i32.const 0)) "integer divide by zero")
+        (assert_return (invoke "i32.rem_s" (i32.const -7) (i32.const 2)) (i32.const -1))
+        (assert_return (invoke "i32.rem_s" (i32.const 0x80000000) (i32.const -1)) (i32.const 0))
+        (assert_trap (invoke "i32.rem_s" (i32.const 1) (i32.const 0)) "integer divide by zero")
+        (assert_return (invoke "i32.rem_u" (i32.const -1) (i32.const 10)) (i32.const 5))
+        (assert_trap (invoke "i32.rem_u" (i32.const 1) (i32.const 0)) "integer divide by zero")
+        (assert_return (invoke "i64.sub" (i64.const 0x8000000000000000) (i64.const 1)) (i64.const 0x7fffffffffffffff))
+        (assert_return (invoke "i64.mul" (i64.const 0x7fffffffffffffff) (i64.const 2)) (i64.const -2))
+        (assert_return (invoke "i64.shl" (i64.const 1) (i64.const 65)) (i64.const 2))
+        (assert_trap (invoke "i64.div_s" (i64.const 1) (i64.const 0)) "integer divide by zero")
+        (assert_trap (invoke "i64.div_s" (i64.const 0x8000000000000000) (i64.const -1)) "integer overflow")
+        (assert_return (invoke "i64.rem_s" (i64.const 0x8000000000000000) (i64.const -1)) (i64.const 0))
+        (assert_return (invoke "i64.rem_u" (i64.const -1) (i64.const 10)) (i64.const 5))
+        (assert_trap (invoke "i64.rem_u" (i64.const 1) (i64.const 0)) "integer divide by zero")
+    "#;
+    let configs = all_engine_configs();
+    for config in configs {
+        let report = WastRunner::new(config).run_script(script).unwrap();
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(report.passed, 21);
+    }
+}
+
+#[test]
+fn test_numeric_ops() {
+    use super::testing::all_engine_configs;
+    let script = r#"
+        (module
+          (memory 1)
+          (table $t 2 funcref)
+          (func $f)
+          (func (export "i32.clz") (param i32) (result i32) (i32.clz (local.get 0)))
+          (func (export "i64.popcnt") (param i64) (result i64) (i64.popcnt (local.get 0)))
+          (func (export "i64.and") (param i64 i64) (result i64) (i64.and (local.get 0) (local.get 1)))
+          (func (export "i32.rotl") (param i32 i32) (result i32) (i32.rotl (local.get 0) (local.get 1)))
+          (func (export "i64.rotr") (param i64 i64) (result i64) (i64.rotr (local.get 0) (local.get 1)))
+          (func (export "i64.extend32_s") (param i64) (result i64) (i64.extend32_s (local.get 0)))
+          (func (export "i32.trunc_f32_s") (param f32) (result i32) (i32.trunc_f32_s (local.get 0)))
+          (func (export "i32.trunc_f64_u") (param f64) (result i32) (i32.trunc_f64_u (local.get 0)))
+          (func (export "i64.trunc_f64_s") (param f64) (result i64) (i64.trunc_f64_s (local.get 0)))
+          (func (export "i32.trunc_sat_f32_u") (param f32) (result i32) (i32.trunc_sat_f32_u (local.get 0)))
+          (func (export "fill_copy") (result i32)
+            (memory.fill (i32.const 1) (i32.const 0xab) (i32.const 2))
+            (memory.copy (i32.const 2) (i32.const 1) (i32.const 2))
+            (i32.load (i32.const 0)))
+          (func (export "fill_oob") (memory.fill (i32.const 65535) (i32.const 0) (i32.const 2)))
+          (func (export "table") (result i32)
+            (drop (table.grow $t (ref.func $f) (i32.const 1)))
+            (table.set $t (i32.const 0) (table.get $t (i32.const 2)))
+            (table.fill $t (i32.const 1) (ref.null func) (i32.const 2))
+            (table.copy $t $t (i32.const 1) (i32.const 0) (i32.const 1))
+            (i32.add (table.size $t) (ref.is_null (table.get $t (i32.const 2))))))
+        (assert_return (invoke "i32.clz" (i32.const 1)) (i32.const 31))
+        (assert_return (invoke "i64.popcnt" (i64.const -1)) (i64.const 64))
+        (assert_return (invoke "i64.and" (i64.const 0xff00) (i64.const 0x0ff0)) (i64.const 0x0f00))
+        (assert_return (invoke "i32.rotl" (i32.const 0x80000001) (i32.const 33)) (i32.const 3))
+        (assert_return (invoke "i64.rotr" (i64.const 1) (i64.const 1)) (i64.const 0x8000000000000000))
+        (assert_return (invoke "i64.extend32_s" (i64.const 0x80000000)) (i64.const -0x80000000))
+        (assert_return (invoke "i32.trunc_f32_s" (f32.const -1.9)) (i32.const -1))
+        (assert_trap (invoke "i32.trunc_f32_s" (f32.const 2147483648)) "integer overflow")
+        (assert_trap (invoke "i32.trunc_f32_s" (f32.const nan)) "invalid conversion to integer")
+        (assert_return (invoke "i32.trunc_f64_u" (f64.const -0.9)) (i32.const 0))
+        (assert_return (invoke "i32.trunc_f64_u" (f64.const 4294967295.9)) (i32.const -1))
+        (assert_trap (invoke "i32.trunc_f64_u" (f64.const -1)) "integer overflow")
+        (assert_return (invoke "i64.trunc_f64_s" (f64.const -9223372036854775808)) (i64.const 0x8000000000000000))
+        (assert_trap (invoke "i64.trunc_f64_s" (f64.const 9223372036854775808)) "integer overflow")
+        (assert_return (invoke "i32.trunc_sat_f32_u" (f32.const -5)) (i32.const 0))
+        (assert_return (invoke "i32.trunc_sat_f32_u" (f32.const nan)) (i32.const 0))
+        (assert_return (invoke "i32.trunc_sat_f32_u" (f32.const inf)) (i32.const -1))
+        (assert_return (invoke "fill_copy") (i32.const 0xababab00))
+        (assert_trap (invoke "fill_oob") "out of bounds memory access")
+        (assert_return (invoke "table") (i32.const 4))
+        (module binary
+          "\00asm" "\01\00\00\00"
+          "\01\04\01\60\00\00" "\03\02\01\00" "\07\08\01\04simd\00\00"
+          "\0a\17\01\15\00\fd\0c\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00\1a\0b")
+        (assert_return (invoke "simd"))
+    "#;
+    for config in all_engine_configs() {
+        let report = WastRunner::new(config).run_script(script).unwrap();
+        assert_eq!(report.passed, 21);
+        // SIMD 还没有实现，模块和它上面的动作都记为失败
+        let failures = report
+            .failures
+            .iter()
+            .map(|failure| (failure.line, failure.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(failures.len(), 2, "{failures:?}");
+        assert_eq!(failures[0].0, 47);
+        assert!(failures[0]
+            .1
+            .starts_with("unimplemented: vector instructions"));
+        assert_eq!(
+            failures[1],
+            (51, "unimplemented: module was not instantiated")
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! `oxygen stats` 的模块统计：指令频率、各段大小、函数大小分布、导入导出个数、内存和数据段占用
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display};
+
+use serde_json::json;
+
+use super::decoder::WasmModule;
+use super::section::data::DataKind;
+use super::section::export::ExportKind;
+use super::section::import;
+
+/// 函数大小分布的区间上限（字节，不含），最后一个区间没有上限
+const SIZE_BUCKETS: [usize; 5] = [16, 64, 256, 1024, 4096];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ModuleStats {
+    pub size: usize,
+    /// (段名, 字节数)，按在模块中的顺序；custom section 为 `custom:名字`
+    pub sections: Vec<(String, usize)>,
+    /// (操作码名, 出现次数)，按次数从多到少
+    pub opcodes: Vec<(String, usize)>,
+    /// 每个函数体的字节数，按函数索引（不含导入函数）
+    pub func_sizes: Vec<usize>,
+    /// 按类型 (func, table, memory, global) 的导入和导出个数
+    pub imports: BTreeMap<&'static str, usize>,
+    pub exports: BTreeMap<&'static str, usize>,
+    /// 每个内存（包括导入的）的初始页数
+    pub memory_pages: Vec<u32>,
+    pub active_data: usize,
+    pub passive_data: usize,
+    /// 数据段内容的总字节数
+    pub data_bytes: usize,
+}
+
+/// 操作码的名字，即去掉立即数的 Debug 输出
+pub(crate) fn op_name(op: &impl fmt::Debug) -> String {
+    let text = format!("{op:?}");
+    match text.find('(') {
+        Some(idx) => text[..idx].to_string(),
+        None => text,
+    }
+}
+
+impl ModuleStats {
+    pub fn new(wasm: &WasmModule) -> Self {
+        let section = &wasm.section;
+        let mut sections = [
+            ("types", section.types.offset, section.types.byte_count),
+            ("imports", section.import.offset, section.import.byte_count),
+            ("functions", section.func.offset, section.func.byte_count),
+            ("tables", section.table.offset, section.table.byte_count),
+            ("memories", section.memory.offset, section.memory.byte_count),
+            ("globals", section.global.offset, section.global.byte_count),
+            ("exports", section.export.offset, section.export.byte_count),
+            ("start", section.start.offset, section.start.byte_count),
+            (
+                "elements",
+                section.element.offset,
+                section.element.byte_count,
+            ),
+            (
+                "data-count",
+                section.data_count.offset,
+                section.data_count.byte_count,
+            ),
+            ("code", section.code.offset, section.code.byte_count),
+            ("data", section.data.offset, section.data.byte_count),
+        ]
+        .into_iter()
+        .filter(|(_, _, size)| *size > 0)
+        .map(|(name, offset, size)| (name.to_string(), offset, size as usize))
+        .chain(
+            section
+                .custom
+                .entries
+                .iter()
+                .map(|(name, range)| (format!("custom:{name}"), range.start, range.len())),
+        )
+        .collect::<Vec<_>>();
+        sections.sort_by_key(|(_, offset, _)| *offset);
+
+        let mut opcodes = HashMap::<String, usize>::new();
+        for body in &section.code.entries {
+            let (start, _, last) = body.code;
+            for op in &wasm.ops[start..=last] {
+                *opcodes.entry(op_name(op)).or_default() += 1;
+            }
+        }
+        let mut opcodes = opcodes.into_iter().collect::<Vec<_>>();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut imports = BTreeMap::new();
+        let mut memory_pages = vec![];
+        for ipt in &section.import.entries {
+            let kind = match &ipt.kind {
+                import::Kind::Func(_) => "func",
+                import::Kind::Table(..) => "table",
+                import::Kind::Memory(limit) => {
+                    memory_pages.push(limit.minimum);
+                    "memory"
+                }
+                import::Kind::Global(_) => "global",
+            };
+            *imports.entry(kind).or_default() += 1;
+        }
+        memory_pages.extend(section.memory.entries.iter().map(|m| m.limits.minimum));
+
+        let mut exports = BTreeMap::new();
+        for export in &section.export.entries {
+            let kind = match export.kind {
+                ExportKind::Func(_) => "func",
+                ExportKind::Table(_) => "table",
+                ExportKind::Memory(_) => "memory",
+                ExportKind::GLobal(_) => "global",
+            };
+            *exports.entry(kind).or_default() += 1;
+        }
+
+        let mut stats = Self {
+            size: wasm.raw.len(),
+            sections: sections
+                .into_iter()
+                .map(|(name, _, size)| (name, size))
+                .collect(),
+            opcodes,
+            func_sizes: section.code.entries.iter().map(|body| body.size).collect(),
+            imports,
+            exports,
+            memory_pages,
+            ..Default::default()
+        };
+        for data in &section.data.entries {
+            let bytes = match &data.kind {
+                DataKind::Expr(_, bytes) | DataKind::MemIdx(_, _, bytes) => {
+                    stats.active_data += 1;
+                    bytes
+                }
+                DataKind::Vec(bytes) => {
+                    stats.passive_data += 1;
+                    bytes
+                }
+            };
+            stats.data_bytes += bytes.len();
+        }
+        stats
+    }
+
+    /// (区间描述, 函数个数, 总字节数)
+    pub fn size_distribution(&self) -> Vec<(String, usize, usize)> {
+        let mut buckets = vec![(0, 0); SIZE_BUCKETS.len() + 1];
+        for &size in &self.func_sizes {
+            let idx = SIZE_BUCKETS.partition_point(|&limit| limit <= size);
+            buckets[idx].0 += 1;
+            buckets[idx].1 += size;
+        }
+        buckets
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (count, bytes))| {
+                let low = idx.checked_sub(1).map_or(0, |i| SIZE_BUCKETS[i]);
+                let range = match SIZE_BUCKETS.get(idx) {
+                    Some(high) => format!("{low}..{high}"),
+                    None => format!("{low}.."),
+                };
+                (range, count, bytes)
+            })
+            .collect()
+    }
+
+    /// 最大的 n 个函数 (函数索引, 字节数)，索引包括导入函数
+    pub fn largest_funcs(&self, n: usize) -> Vec<(usize, usize)> {
+        let imported = self.imports.get("func").copied().unwrap_or(0);
+        let mut funcs = self
+            .func_sizes
+            .iter()
+            .enumerate()
+            .map(|(idx, &size)| (idx + imported, size))
+            .collect::<Vec<_>>();
+        funcs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        funcs.truncate(n);
+        funcs
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let sizes = &self.func_sizes;
+        json!({
+            "size": self.size,
+            "sections": self
+                .sections
+                .iter()
+                .map(|(name, size)| json!({ "name": name, "size": size }))
+                .collect::<Vec<_>>(),
+            "opcodes": self
+                .opcodes
+                .iter()
+                .map(|(name, count)| json!({ "name": name, "count": count }))
+                .collect::<Vec<_>>(),
+            "functions": {
+                "count": sizes.len(),
+                "bytes": sizes.iter().sum::<usize>(),
+                "max": sizes.iter().max(),
+                "distribution": self
+                    .size_distribution()
+                    .into_iter()
+                    .map(|(range, count, bytes)| json!({ "range": range, "count": count, "bytes": bytes }))
+                    .collect::<Vec<_>>(),
+                "largest": self
+                    .largest_funcs(10)
+                    .into_iter()
+                    .map(|(func, size)| json!({ "func": func, "size": size }))
+                    .collect::<Vec<_>>(),
+            },
+            "imports": self.imports,
+            "exports": self.exports,
+            "memory": {
+                "pages": self.memory_pages,
+                "bytes": self.memory_pages.iter().map(|&p| p as u64 * 65536).sum::<u64>(),
+            },
+            "data": {
+                "active": self.active_data,
+                "passive": self.passive_data,
+                "bytes": self.data_bytes,
+            },
+        })
+    }
+}
+
+impl Display for ModuleStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |size: usize| size as f64 * 100.0 / self.size.max(1) as f64;
+        writeln!(f, "Size: {} bytes\n", self.size)?;
+        writeln!(f, "Sections:")?;
+        for (name, size) in &self.sections {
+            writeln!(f, "  {name:<24} {size:>10} {:>6.1}%", percent(*size))?;
+        }
+
+        let total = self.opcodes.iter().map(|(_, count)| count).sum::<usize>();
+        writeln!(f, "\nOpcodes: {total}")?;
+        for (name, count) in &self.opcodes {
+            let share = *count as f64 * 100.0 / total as f64;
+            writeln!(f, "  {name:<24} {count:>10} {share:>6.1}%")?;
+        }
+
+        let bytes = self.func_sizes.iter().sum::<usize>();
+        writeln!(f, "\nFunctions: {} ({bytes} bytes)", self.func_sizes.len())?;
+        for (range, count, bytes) in self.size_distribution() {
+            writeln!(f, "  {range:<24} {count:>10} {bytes:>10} bytes")?;
+        }
+        let largest = self
+            .largest_funcs(5)
+            .into_iter()
+            .map(|(func, size)| format!("func{func} ({size})"))
+            .collect::<Vec<_>>();
+        if !largest.is_empty() {
+            writeln!(f, "  largest: {}", largest.join(", "))?;
+        }
+
+        let counts = |counts: &BTreeMap<&str, usize>| {
+            let total = counts.values().sum::<usize>();
+            let kinds = counts
+                .iter()
+                .map(|(kind, count)| format!("{count} {kind}"))
+                .collect::<Vec<_>>();
+            match kinds.is_empty() {
+                true => "0".to_string(),
+                false => format!("{total} ({})", kinds.join(", ")),
+            }
+        };
+        writeln!(f, "\nImports: {}", counts(&self.imports))?;
+        writeln!(f, "Exports: {}", counts(&self.exports))?;
+
+        let pages = self.memory_pages.iter().map(|&p| p as u64).sum::<u64>();
+        writeln!(
+            f,
+            "\nMemory: {} memories, {pages} initial pages ({} bytes)",
+            self.memory_pages.len(),
+            pages * 65536
+        )?;
+        writeln!(
+            f,
+            "Data: {} active + {} passive segments, {} bytes",
+            self.active_data, self.passive_data, self.data_bytes
+        )
+    }
+}
+
+#[test]
+fn test_module_stats() {
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "log" (func $log (param i32)))
+          (import "env" "mem" (memory 2))
+          (global (export "g") i32 (i32.const 0))
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+          (func (export "big") (param i32)
+            (call $log (i32.add (local.get 0) (i32.const 1)))
+            (call $log (i32.add (local.get 0) (i32.const 2)))
+            (call $log (i32.add (local.get 0) (i32.const 3)))
+            (call $log (i32.add (local.get 0) (i32.const 4))))
+          (data (i32.const 8) "hello")
+          (data "passive!"))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let stats = ModuleStats::new(&wasm);
+
+    assert_eq!(stats.size, wasm.raw.len());
+    let names = stats
+        .sections
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "types",
+            "imports",
+            "functions",
+            "globals",
+            "exports",
+            "code",
+            "data"
+        ]
+    );
+    assert_eq!(stats.opcodes[0], ("LocalGet".to_string(), 6));
+    assert_eq!(stats.opcodes[1], ("I32Add".to_string(), 5));
+    assert!(stats.opcodes.contains(&("Call".to_string(), 4)));
+    assert_eq!(stats.func_sizes.len(), 2);
+    assert_eq!(stats.largest_funcs(1)[0].0, 2);
+    let distribution = stats.size_distribution();
+    assert_eq!(distribution[0].0, "0..16");
+    assert_eq!(distribution.last().unwrap().0, "4096..");
+    assert_eq!(distribution.iter().map(|d| d.1).sum::<usize>(), 2);
+    assert_eq!(stats.imports.get("func"), Some(&1));
+    assert_eq!(stats.imports.get("memory"), Some(&1));
+    assert_eq!(stats.exports.get("func"), Some(&2));
+    assert_eq!(stats.exports.get("global"), Some(&1));
+    assert_eq!(stats.memory_pages, [2]);
+    assert_eq!((stats.active_data, stats.passive_data), (1, 1));
+    assert_eq!(stats.data_bytes, 13);
+
+    let text = stats.to_string();
+    assert!(text.contains("Imports: 2 (1 func, 1 memory)"), "{text}");
+    assert!(text.contains("Memory: 1 memories, 2 initial pages (131072 bytes)"));
+    assert!(text.contains("Data: 1 active + 1 passive segments, 13 bytes"));
+    let json = stats.to_json();
+    assert_eq!(json["functions"]["count"], 2);
+    assert_eq!(json["memory"]["bytes"], 131072);
+}
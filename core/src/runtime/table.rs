@@ -0,0 +1,171 @@
+//! 表的存储：元素放在 `Arc` 中，宿主创建或实例导出的表导入到多个实例时共享同一份元素
+//!
+//! 函数索引只在写入它的实例中有意义，所以表项同时记录写入它的实例（`Elem::owner`）。
+//! 其他实例读取时通过 `Table::register` 登记的句柄调用那个实例中的函数；
+//! 写入它的实例没有登记（没有放进 `SharedInstance`）或已经释放时调用会 trap。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+
+use anyhow::ensure;
+
+use super::decoder::{WasmModule, NULL_REF};
+use super::linker::SharedInstance;
+use super::section::typings::{Limit, ValueType};
+
+/// 表项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elem {
+    /// 写入它的实例的 `WasmModule::id`；宿主写入的为 0，按读取它的实例的函数索引解释
+    pub owner: u64,
+    /// 函数在 owner 中的索引或 externref 的值，空引用为 `NULL_REF`
+    pub value: usize,
+}
+
+impl Elem {
+    pub const NULL: Elem = Elem::host(NULL_REF);
+
+    pub const fn host(value: usize) -> Elem {
+        Elem { owner: 0, value }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    elements: Arc<RwLock<Vec<Elem>>>,
+    /// 可以调用的写入者，按 `WasmModule::id`
+    owners: Arc<RwLock<HashMap<u64, Weak<Mutex<WasmModule>>>>>,
+    ty: ValueType,
+    /// 最大元素个数，没有声明时为 u32::MAX
+    maximum: u32,
+}
+
+impl Table {
+    /// 创建 limits.minimum 个空引用的表，元素类型为 funcref 或 externref
+    pub fn new(limits: Limit, ty: ValueType) -> anyhow::Result<Table> {
+        ensure!(
+            matches!(ty, ValueType::FuncRef | ValueType::ExternRef),
+            "table element type must be a reference, found {ty}"
+        );
+        let maximum = match limits.flag & 0x01 {
+            0 => u32::MAX,
+            _ => limits.maximum,
+        };
+        ensure!(
+            limits.minimum <= maximum,
+            "table minimum {} is larger than maximum {maximum}",
+            limits.minimum
+        );
+        Ok(Table {
+            elements: Arc::new(RwLock::new(vec![Elem::NULL; limits.minimum as usize])),
+            owners: Arc::default(),
+            ty,
+            maximum,
+        })
+    }
+
+    pub fn ty(&self) -> ValueType {
+        self.ty
+    }
+
+    pub fn maximum(&self) -> u32 {
+        self.maximum
+    }
+
+    pub fn size(&self) -> u32 {
+        self.elements().len() as u32
+    }
+
+    /// 读取表项，返回函数在写入它的实例中的索引
+    pub fn get(&self, index: u32) -> Option<usize> {
+        self.get_elem(index).map(|elem| elem.value)
+    }
+
+    pub fn get_elem(&self, index: u32) -> Option<Elem> {
+        self.elements().get(index as usize).copied()
+    }
+
+    /// 以宿主的身份写入，越界时返回 false
+    pub fn set(&self, index: u32, func: usize) -> bool {
+        self.set_elem(index, Elem::host(func))
+    }
+
+    pub fn set_elem(&self, index: u32, elem: Elem) -> bool {
+        match self.elements_mut().get_mut(index as usize) {
+            Some(item) => {
+                *item = elem;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 增加 delta 个表项并用 init 填充，返回增长前的大小；超出 maximum 时返回 None
+    pub fn grow(&self, delta: u32, init: Elem, maximum: u32) -> Option<u32> {
+        let mut elements = self.elements_mut();
+        let old = elements.len() as u32;
+        let new = old as u64 + delta as u64;
+        if new > self.maximum.min(maximum) as u64 {
+            return None;
+        }
+        elements.resize(new as usize, init);
+        Some(old)
+    }
+
+    pub fn elements(&self) -> RwLockReadGuard<'_, Vec<Elem>> {
+        self.elements.read().unwrap()
+    }
+
+    pub fn elements_mut(&self) -> RwLockWriteGuard<'_, Vec<Elem>> {
+        self.elements.write().unwrap()
+    }
+
+    /// 所有表项的值，见 `get`
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.elements().iter().map(|elem| elem.value).collect()
+    }
+
+    /// 登记 owner 对应的实例，之后其他实例可以调用它写入的函数
+    pub fn register(&self, owner: u64, instance: &SharedInstance) {
+        let mut owners = self.owners.write().unwrap();
+        owners.retain(|_, instance| instance.strong_count() > 0);
+        owners.insert(owner, Arc::downgrade(instance));
+    }
+
+    /// owner 登记的实例，没有登记时返回空的 `Weak`
+    pub fn owner(&self, owner: u64) -> Weak<Mutex<WasmModule>> {
+        let owners = self.owners.read().unwrap();
+        owners.get(&owner).cloned().unwrap_or_default()
+    }
+
+    /// 两个 Table 是否是同一个表（共享元素）
+    pub fn same(&self, other: &Table) -> bool {
+        Arc::ptr_eq(&self.elements, &other.elements)
+    }
+}
+
+#[test]
+fn test_table() {
+    let limits = Limit {
+        flag: 1,
+        minimum: 2,
+        maximum: 3,
+    };
+    let table = Table::new(limits.clone(), ValueType::FuncRef).unwrap();
+    let shared = table.clone();
+    assert!(table.set(1, 7));
+    assert!(!table.set(2, 7));
+    assert_eq!(shared.to_vec(), [NULL_REF, 7]);
+    assert!(shared.same(&table));
+
+    assert_eq!(shared.grow(1, Elem::host(4), u32::MAX), Some(2));
+    assert_eq!(table.grow(1, Elem::host(4), u32::MAX), None);
+    assert_eq!(table.get(2), Some(4));
+    assert_eq!(
+        Table::new(Limit::default(), ValueType::ExternRef)
+            .unwrap()
+            .maximum(),
+        u32::MAX
+    );
+    assert!(Table::new(limits, ValueType::I32).is_err());
+}
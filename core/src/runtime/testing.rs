@@ -0,0 +1,108 @@
+//! 测试用的模块构造工具，自动计算 section 和函数体的长度
+
+use super::config::{Engine, RuntimeConfig};
+use super::decoder::{WasmModule, WasmValue};
+use super::trap::Trap;
+
+pub fn leb_u32(value: u32) -> Vec<u8> {
+    let mut buf = vec![];
+    crate::leb::encode_leb_u32(value, &mut buf);
+    buf
+}
+
+/// 带长度前缀的向量
+pub fn vec(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = leb_u32(items.len() as u32);
+    items.iter().for_each(|item| buf.extend(item));
+    buf
+}
+
+/// 拼接 wasm 二进制，sections 为 (id, 内容)，按规范要求的顺序输出（data_count 在 code 之前）
+pub fn wasm(sections: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    let mut sections = sections.to_vec();
+    sections.sort_by_key(|(id, _)| if *id == 12 { 19 } else { *id as u32 * 2 });
+    let mut buf = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    for (id, content) in sections {
+        buf.push(id);
+        buf.extend(leb_u32(content.len() as u32));
+        buf.extend(content);
+    }
+    buf
+}
+
+/// 所有函数签名均为 `params -> results`，bodies 为不含局部变量声明和 end 的指令字节，
+/// extra 为额外的 section（memory、table、element 等）
+pub fn func_bytes(
+    params: &[u8],
+    results: &[u8],
+    bodies: &[&[u8]],
+    extra: &[(u8, Vec<u8>)],
+) -> Vec<u8> {
+    let mut ty = vec![0x60];
+    ty.extend(vec(&params.iter().map(|p| vec![*p]).collect::<Vec<_>>()));
+    ty.extend(vec(&results.iter().map(|r| vec![*r]).collect::<Vec<_>>()));
+    let codes = bodies
+        .iter()
+        .map(|body| {
+            let mut code = vec![0x00]; // 无局部变量
+            code.extend(*body);
+            code.push(0x0b);
+            let mut buf = leb_u32(code.len() as u32);
+            buf.extend(code);
+            buf
+        })
+        .collect::<Vec<_>>();
+    let mut sections = vec![
+        (1, vec(&[ty])),
+        (3, vec(&vec![vec![0x00]; bodies.len()])),
+        (10, vec(&codes)),
+    ];
+    sections.extend_from_slice(extra);
+    wasm(&sections)
+}
+
+/// 解码并实例化 func_bytes 构造的模块
+pub fn func_module(
+    params: &[u8],
+    results: &[u8],
+    bodies: &[&[u8]],
+    extra: &[(u8, Vec<u8>)],
+) -> WasmModule {
+    let mut wasm = WasmModule::default(func_bytes(params, results, bodies, extra));
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    wasm
+}
+
+/// 以 args 为参数调用第 idx 个函数
+pub fn invoke(
+    wasm: &mut WasmModule,
+    idx: usize,
+    args: &[WasmValue],
+) -> Result<Vec<WasmValue>, Trap> {
+    wasm.sp = 0;
+    wasm.fp = 0;
+    wasm.stack_check();
+    for arg in args {
+        wasm.sp += 1;
+        wasm.stack[wasm.sp] = *arg;
+    }
+    wasm.call(idx)
+}
+
+/// 调用并返回第一个结果
+pub fn call_with(wasm: &mut WasmModule, idx: usize, args: &[WasmValue]) -> WasmValue {
+    invoke(wasm, idx, args).unwrap()[0]
+}
+
+/// 每个执行引擎的配置：match、threaded 和 untyped_stack，开启 `jit` feature 时还有 jit
+pub fn all_engine_configs() -> Vec<RuntimeConfig> {
+    let configs = vec![
+        RuntimeConfig::default(),
+        RuntimeConfig::default().engine(Engine::Threaded),
+        RuntimeConfig::default().untyped_stack(true),
+    ];
+    #[cfg(feature = "jit")]
+    let configs = [configs, vec![RuntimeConfig::default().engine(Engine::Jit)]].concat();
+    configs
+}
@@ -0,0 +1,286 @@
+//! 间接线程化的执行引擎
+//!
+//! 每条降级后的指令预先解析为 (处理函数, 立即数)，执行时直接调用函数指针，
+//! 不再对指令做 match。由 `RuntimeConfig::engine(Engine::Threaded)` 启用。
+
+use super::decoder::{WasmModule, WasmValue, CANCEL_CHECK_INTERVAL};
+use super::ir::Instr;
+use super::section::typings::ValueType;
+use super::trap::Trap;
+
+/// 执行 pc 处的指令，返回下一条指令的位置
+type Handler = fn(&mut WasmModule, usize, u64) -> Result<usize, Trap>;
+
+/// 当前函数执行结束
+const DONE: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadedOp {
+    handler: Handler,
+    imm: u64,
+    /// 对应的原指令条数
+    width: u64,
+}
+
+fn compile(module: &WasmModule) -> Vec<ThreadedOp> {
+    module
+        .code
+        .instrs
+        .iter()
+        .map(|instr| {
+            let (handler, imm): (Handler, u64) = match *instr {
+                Instr::Op => (op, 0),
+                Instr::Nop => (nop, 0),
+                // 不需要整理栈的分支直接以目标为立即数
+                Instr::Jump(branch) if branch.drop == 0 => (jump, branch.target as u64),
+                Instr::Jump(_) => (jump_unwind, 0),
+                Instr::BrIf(branch) if branch.drop == 0 => (br_if, branch.target as u64),
+                Instr::BrIf(_) => (br_if_unwind, 0),
+                Instr::BrTable(table) => (br_table, table as u64),
+                Instr::If(other) => (if_, other as u64),
+                Instr::Return => (done, 0),
+                Instr::LocalGet(idx) => (local_get, idx as u64),
+                Instr::LocalSet(idx) => (local_set, idx as u64),
+                Instr::LocalTee(idx) => (local_tee, idx as u64),
+                Instr::I32Const(value) => (i32_const, value as u32 as u64),
+                Instr::LocalGet2(a, b) => (local_get2, a as u64 | (b as u64) << 32),
+                Instr::I32AddImm(value) => (i32_add_imm, value as u32 as u64),
+                Instr::I32SubImm(value) => (i32_sub_imm, value as u32 as u64),
+                Instr::BrIfEqz(branch) if branch.drop == 0 => (br_if_eqz, branch.target as u64),
+                Instr::BrIfEqz(_) => (br_if_eqz_unwind, 0),
+                Instr::BrOnNull(_) => (br_on_null, 0),
+                Instr::BrOnNonNull(_) => (br_on_non_null, 0),
+            };
+            ThreadedOp {
+                handler,
+                imm,
+                width: instr.width(),
+            }
+        })
+        .collect()
+}
+
+pub fn run(module: &mut WasmModule, offset: usize) -> Result<(), Trap> {
+    if module.threaded.len() != module.code.instrs.len() {
+        module.threaded = compile(module);
+    }
+    let mut pc = offset;
+    while pc != DONE {
+        let op = module.threaded[pc];
+        let count = module.stats.instructions;
+        module.stats.instructions += op.width;
+        // 每执行 1024 条指令检查一次是否被取消或需要让出
+        if count / CANCEL_CHECK_INTERVAL != module.stats.instructions / CANCEL_CHECK_INTERVAL {
+            module.pc = pc;
+            module.checkpoint(count)?;
+        }
+        if let Some(tracer) = &module.config.tracer {
+            let stack = module
+                .stack
+                .get(module.fp..module.sp + 1)
+                .unwrap_or_default();
+            tracer.lock().unwrap().op(pc, &module.ops[pc], stack);
+        }
+        pc = (op.handler)(module, pc, op.imm)?;
+    }
+    Ok(())
+}
+
+fn op(m: &mut WasmModule, pc: usize, _: u64) -> Result<usize, Trap> {
+    m.pc = pc;
+    m.step()?;
+    Ok(pc + 1)
+}
+
+fn nop(_: &mut WasmModule, pc: usize, _: u64) -> Result<usize, Trap> {
+    Ok(pc + 1)
+}
+
+fn done(_: &mut WasmModule, _: usize, _: u64) -> Result<usize, Trap> {
+    Ok(DONE)
+}
+
+fn jump(_: &mut WasmModule, _: usize, target: u64) -> Result<usize, Trap> {
+    Ok(target as usize)
+}
+
+/// 需要整理栈的分支，Branch 从 `Code::instrs` 中读取
+fn unwind(m: &mut WasmModule, pc: usize) -> usize {
+    let (Instr::Jump(branch)
+    | Instr::BrIf(branch)
+    | Instr::BrIfEqz(branch)
+    | Instr::BrOnNull(branch)
+    | Instr::BrOnNonNull(branch)) = m.code.instrs[pc]
+    else {
+        unreachable!("{:?} is not a branch", m.code.instrs[pc]);
+    };
+    m.branch(&branch);
+    branch.target
+}
+
+fn jump_unwind(m: &mut WasmModule, pc: usize, _: u64) -> Result<usize, Trap> {
+    Ok(unwind(m, pc))
+}
+
+fn br_if_unwind(m: &mut WasmModule, pc: usize, _: u64) -> Result<usize, Trap> {
+    Ok(if m.pop_i32()? != 0 {
+        unwind(m, pc)
+    } else {
+        pc + 1
+    })
+}
+
+fn br_if(m: &mut WasmModule, pc: usize, target: u64) -> Result<usize, Trap> {
+    Ok(if m.pop_i32()? != 0 {
+        target as usize
+    } else {
+        pc + 1
+    })
+}
+
+fn br_table(m: &mut WasmModule, _: usize, table: u64) -> Result<usize, Trap> {
+    let v = m.pop_i32()? as u32 as usize;
+    let targets = &m.code.tables[table as usize];
+    let branch = targets[v.min(targets.len() - 1)];
+    m.branch(&branch);
+    Ok(branch.target)
+}
+
+fn if_(m: &mut WasmModule, pc: usize, other: u64) -> Result<usize, Trap> {
+    Ok(if m.pop_i32()? != 0 {
+        pc + 1
+    } else {
+        other as usize
+    })
+}
+
+fn local_get(m: &mut WasmModule, pc: usize, idx: u64) -> Result<usize, Trap> {
+    m.sp += 1;
+    m.stack[m.sp] = m.stack[m.fp + idx as usize];
+    Ok(pc + 1)
+}
+
+fn local_set(m: &mut WasmModule, pc: usize, idx: u64) -> Result<usize, Trap> {
+    m.stack[m.fp + idx as usize] = m.stack[m.sp];
+    m.sp -= 1;
+    Ok(pc + 1)
+}
+
+fn local_tee(m: &mut WasmModule, pc: usize, idx: u64) -> Result<usize, Trap> {
+    m.stack[m.fp + idx as usize] = m.stack[m.sp];
+    Ok(pc + 1)
+}
+
+fn i32_const(m: &mut WasmModule, pc: usize, value: u64) -> Result<usize, Trap> {
+    m.sp += 1;
+    m.stack[m.sp] = WasmValue::I32(value as u32 as i32);
+    Ok(pc + 1)
+}
+
+fn local_get2(m: &mut WasmModule, pc: usize, idx: u64) -> Result<usize, Trap> {
+    m.stack[m.sp + 1] = m.stack[m.fp + (idx as u32) as usize];
+    m.stack[m.sp + 2] = m.stack[m.fp + (idx >> 32) as usize];
+    m.sp += 2;
+    Ok(pc + 2)
+}
+
+fn top_i32(m: &WasmModule) -> Result<WasmValue, Trap> {
+    let v = m.stack[m.sp];
+    match v.value_type() {
+        Some(ValueType::I32) => Ok(v),
+        _ => Err(Trap::mismatch(ValueType::I32, v)),
+    }
+}
+
+fn i32_add_imm(m: &mut WasmModule, pc: usize, value: u64) -> Result<usize, Trap> {
+    m.stack[m.sp] = top_i32(m)? + WasmValue::I32(value as u32 as i32);
+    Ok(pc + 2)
+}
+
+fn i32_sub_imm(m: &mut WasmModule, pc: usize, value: u64) -> Result<usize, Trap> {
+    m.stack[m.sp] = top_i32(m)? - WasmValue::I32(value as u32 as i32);
+    Ok(pc + 2)
+}
+
+fn br_if_eqz(m: &mut WasmModule, pc: usize, target: u64) -> Result<usize, Trap> {
+    Ok(if m.pop_i32()? == 0 {
+        target as usize
+    } else {
+        pc + 2
+    })
+}
+
+fn br_if_eqz_unwind(m: &mut WasmModule, pc: usize, _: u64) -> Result<usize, Trap> {
+    Ok(if m.pop_i32()? == 0 {
+        unwind(m, pc)
+    } else {
+        pc + 2
+    })
+}
+
+fn br_on_null(m: &mut WasmModule, pc: usize, _: u64) -> Result<usize, Trap> {
+    Ok(if m.top_is_null()? {
+        m.sp -= 1;
+        unwind(m, pc)
+    } else {
+        pc + 1
+    })
+}
+
+fn br_on_non_null(m: &mut WasmModule, pc: usize, _: u64) -> Result<usize, Trap> {
+    Ok(if m.top_is_null()? {
+        m.sp -= 1;
+        pc + 1
+    } else {
+        unwind(m, pc)
+    })
+}
+
+#[test]
+fn test_threaded_engine() {
+    use super::config::{Engine, RuntimeConfig};
+    use super::testing::{invoke, leb_u32, vec, wasm};
+
+    // fib(n) = n < 2 ? n : fib(n - 1) + fib(n - 2)
+    let fib: &[u8] = &[
+        0x20, 0x00, 0x41, 0x02, 0x48, 0x04, 0x7f, 0x20, 0x00, 0x05, 0x20, 0x00, 0x41, 0x01, 0x6b,
+        0x10, 0x00, 0x20, 0x00, 0x41, 0x02, 0x6b, 0x10, 0x00, 0x6a, 0x0b,
+    ];
+    // block loop local.get 0 i32.eqz br_if 1 ... br 0 end end：累加 n..1
+    let sum: &[u8] = &[
+        0x02, 0x40, 0x03, 0x40, 0x20, 0x00, 0x45, 0x0d, 0x01, 0x20, 0x01, 0x20, 0x00, 0x6a, 0x21,
+        0x01, 0x20, 0x00, 0x41, 0x01, 0x6b, 0x21, 0x00, 0x0c, 0x00, 0x0b, 0x0b, 0x20, 0x01,
+    ];
+    let body = |locals: &[u8], code: &[u8]| {
+        let mut body = locals.to_vec();
+        body.extend(code);
+        body.push(0x0b);
+        let mut buf = leb_u32(body.len() as u32);
+        buf.extend(body);
+        buf
+    };
+    let buf = wasm(&[
+        (1, vec(&[vec![0x60, 0x01, 0x7f, 0x01, 0x7f]])),
+        (3, vec(&[vec![0x00], vec![0x00]])),
+        // sum 有一个 i32 局部变量
+        (
+            10,
+            vec(&[body(&[0x00], fib), body(&[0x01, 0x01, 0x7f], sum)]),
+        ),
+    ]);
+
+    let mut results = vec![];
+    for engine in [Engine::Match, Engine::Threaded] {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default().engine(engine);
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        let fib = invoke(&mut wasm, 0, &[WasmValue::I32(10)]).unwrap();
+        let sum = invoke(&mut wasm, 1, &[WasmValue::I32(100)]).unwrap();
+        let trap = invoke(&mut wasm, 0, &[WasmValue::I64(1)]).unwrap_err();
+        results.push((fib, sum, trap.to_string(), wasm.stats.instructions));
+    }
+    assert_eq!(results[0].0, [WasmValue::I32(55)]);
+    assert_eq!(results[0].1, [WasmValue::I32(5050)]);
+    assert_eq!(results[0], results[1]);
+}
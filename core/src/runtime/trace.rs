@@ -0,0 +1,350 @@
+//! 执行跟踪，由 `RuntimeConfig::tracer` 开启
+//!
+//! 解释器在执行指令、访问内存、调用和返回时通知 `Tracer`；
+//! `TraceLog` 按 `TraceFilter` 过滤后以文本或 JSONL 输出。
+
+use std::fmt::{self, Debug};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+
+use super::debuginfo::SourceLocation;
+use super::decoder::WasmValue;
+use super::section::opcode::{OpClass, Opcode};
+use super::trap::Trap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Load,
+    Store,
+}
+
+/// 各方法默认不做任何事，按需实现
+///
+/// 融合指令只报告第一条原指令；开启跟踪时不使用 `untyped_stack`。
+pub trait Tracer: Debug {
+    /// 每条指令执行前调用，stack 为当前函数的局部变量和操作数栈
+    fn op(&mut self, _pc: usize, _op: &Opcode, _stack: &[WasmValue]) {}
+    /// 模块带有调试信息时，在 op 之前调用，loc 为这条指令的源码位置
+    fn location(&mut self, _loc: &SourceLocation) {}
+    /// 访存指令读到或将要写入的字节
+    fn memory(&mut self, _access: Access, _memory: u32, _addr: usize, _bytes: &[u8]) {}
+    /// 进入函数（包括导入函数）
+    fn call(&mut self, _func: usize, _args: &[WasmValue]) {}
+    /// 函数正常返回，results 与 `WasmModule::call` 的返回值一致
+    fn ret(&mut self, _func: usize, _results: &[WasmValue]) {}
+    /// 函数因 trap 退出
+    fn trap(&mut self, _func: usize, _trap: &Trap) {}
+}
+
+pub type SharedTracer = Arc<Mutex<dyn Tracer + Send>>;
+
+/// 依次转发给多个 tracer，如同时开启 `--trace` 和 `--profile`
+impl Tracer for Vec<SharedTracer> {
+    fn op(&mut self, pc: usize, op: &Opcode, stack: &[WasmValue]) {
+        for tracer in self.iter() {
+            tracer.lock().unwrap().op(pc, op, stack);
+        }
+    }
+    fn location(&mut self, loc: &SourceLocation) {
+        for tracer in self.iter() {
+            tracer.lock().unwrap().location(loc);
+        }
+    }
+    fn memory(&mut self, access: Access, memory: u32, addr: usize, bytes: &[u8]) {
+        for tracer in self.iter() {
+            tracer.lock().unwrap().memory(access, memory, addr, bytes);
+        }
+    }
+    fn call(&mut self, func: usize, args: &[WasmValue]) {
+        for tracer in self.iter() {
+            tracer.lock().unwrap().call(func, args);
+        }
+    }
+    fn ret(&mut self, func: usize, results: &[WasmValue]) {
+        for tracer in self.iter() {
+            tracer.lock().unwrap().ret(func, results);
+        }
+    }
+    fn trap(&mut self, func: usize, trap: &Trap) {
+        for tracer in self.iter() {
+            tracer.lock().unwrap().trap(func, trap);
+        }
+    }
+}
+
+/// 要记录的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    Ops,
+    Memory,
+    Calls,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFilter {
+    pub events: Vec<TraceEvent>,
+    /// 只记录这些函数内的指令和访存（调用按被调函数过滤），None 表示不限
+    pub funcs: Option<RangeInclusive<usize>>,
+    /// 只记录这些类别的指令，None 表示不限
+    pub classes: Option<Vec<OpClass>>,
+}
+
+impl Default for TraceFilter {
+    fn default() -> Self {
+        Self {
+            events: vec![TraceEvent::Ops, TraceEvent::Memory, TraceEvent::Calls],
+            funcs: None,
+            classes: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// 每个事件一行，调用按深度缩进
+    #[default]
+    Text,
+    /// 每个事件一个 JSON 对象
+    Jsonl,
+}
+
+/// 按过滤条件把事件写到 out
+pub struct TraceLog {
+    pub filter: TraceFilter,
+    pub format: TraceFormat,
+    out: Box<dyn Write + Send>,
+    /// 当前调用链，栈顶为正在执行的函数
+    frames: Vec<usize>,
+    /// 上一次输出的源码位置，只在变化时输出
+    location: Option<SourceLocation>,
+}
+
+impl Debug for TraceLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceLog")
+            .field("filter", &self.filter)
+            .field("format", &self.format)
+            .field("frames", &self.frames)
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+impl TraceLog {
+    pub fn new(filter: TraceFilter, format: TraceFormat, out: impl Write + Send + 'static) -> Self {
+        Self {
+            filter,
+            format,
+            out: Box::new(out),
+            frames: vec![],
+            location: None,
+        }
+    }
+
+    pub fn stderr(filter: TraceFilter, format: TraceFormat) -> Self {
+        Self::new(filter, format, std::io::stderr())
+    }
+
+    fn wants(&self, event: TraceEvent, func: Option<usize>) -> bool {
+        self.filter.events.contains(&event)
+            && match (&self.filter.funcs, func) {
+                (Some(range), Some(func)) => range.contains(&func),
+                (Some(_), None) => false,
+                (None, _) => true,
+            }
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.frames.last().copied()
+    }
+
+    fn indent(&self) -> String {
+        "  ".repeat(self.frames.len().saturating_sub(1))
+    }
+
+    fn write(
+        &mut self,
+        text: impl FnOnce(&Self) -> String,
+        json: impl FnOnce() -> serde_json::Value,
+    ) {
+        let line = match self.format {
+            TraceFormat::Text => text(self),
+            TraceFormat::Jsonl => json().to_string(),
+        };
+        // 跟踪输出失败不影响执行
+        let _ = writeln!(self.out, "{line}");
+    }
+}
+
+fn values(values: &[WasmValue]) -> Vec<String> {
+    values.iter().map(|v| format!("{v:?}")).collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+impl Tracer for TraceLog {
+    fn op(&mut self, pc: usize, op: &Opcode, stack: &[WasmValue]) {
+        let func = self.current();
+        if !self.wants(TraceEvent::Ops, func)
+            || self
+                .filter
+                .classes
+                .as_ref()
+                .is_some_and(|classes| !classes.contains(&op.class()))
+        {
+            return;
+        }
+        self.write(
+            |log| format!("{}{pc:>8} {op:?} {stack:?}", log.indent()),
+            || json!({ "event": "op", "func": func, "pc": pc, "op": format!("{op:?}"), "stack": values(stack) }),
+        );
+    }
+
+    fn location(&mut self, loc: &SourceLocation) {
+        let func = self.current();
+        if self.location.as_ref() == Some(loc) || !self.wants(TraceEvent::Ops, func) {
+            return;
+        }
+        self.location = Some(loc.clone());
+        self.write(
+            |log| format!("{}at {loc}", log.indent()),
+            || json!({ "event": "location", "func": func, "file": loc.file, "line": loc.line, "column": loc.column }),
+        );
+    }
+
+    fn memory(&mut self, access: Access, memory: u32, addr: usize, bytes: &[u8]) {
+        let func = self.current();
+        if !self.wants(TraceEvent::Memory, func) {
+            return;
+        }
+        let kind = match access {
+            Access::Load => "load",
+            Access::Store => "store",
+        };
+        self.write(
+            |log| format!("{}{kind} mem{memory}[{addr:#x}] {}", log.indent(), hex(bytes)),
+            || json!({ "event": kind, "func": func, "memory": memory, "addr": addr, "bytes": hex(bytes) }),
+        );
+    }
+
+    fn call(&mut self, func: usize, args: &[WasmValue]) {
+        self.frames.push(func);
+        if !self.wants(TraceEvent::Calls, Some(func)) {
+            return;
+        }
+        self.write(
+            |log| format!("{}call func{func} {args:?}", log.indent()),
+            || json!({ "event": "call", "func": func, "args": values(args) }),
+        );
+    }
+
+    fn ret(&mut self, func: usize, results: &[WasmValue]) {
+        if self.wants(TraceEvent::Calls, Some(func)) {
+            self.write(
+                |log| format!("{}ret func{func} {results:?}", log.indent()),
+                || json!({ "event": "ret", "func": func, "results": values(results) }),
+            );
+        }
+        self.frames.pop();
+    }
+
+    fn trap(&mut self, func: usize, trap: &Trap) {
+        if self.wants(TraceEvent::Calls, Some(func)) {
+            self.write(
+                |log| format!("{}trap func{func} {trap}", log.indent()),
+                || json!({ "event": "trap", "func": func, "message": trap.to_string() }),
+            );
+        }
+        self.frames.pop();
+    }
+}
+
+#[test]
+fn test_trace_log() {
+    use super::config::RuntimeConfig;
+    use super::decoder::WasmModule;
+    use super::testing::{invoke, leb_u32, vec, wasm};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let body = |code: &[u8]| {
+        let mut body = vec![0x00];
+        body.extend(code);
+        body.push(0x0b);
+        let mut buf = leb_u32(body.len() as u32);
+        buf.extend(body);
+        buf
+    };
+    let buf = wasm(&[
+        (1, vec(&[vec![0x60, 0x01, 0x7f, 0x01, 0x7f]])),
+        (3, vec(&[vec![0x00], vec![0x00]])),
+        (5, vec(&[vec![0x00, 0x01]])),
+        (
+            10,
+            vec(&[
+                // call 1 (local.get 0)
+                body(&[0x20, 0x00, 0x10, 0x01]),
+                // i32.store (0, local.get 0)；i32.load (0)
+                body(&[
+                    0x41, 0x00, 0x20, 0x00, 0x36, 0x02, 0x00, 0x41, 0x00, 0x28, 0x02, 0x00,
+                ]),
+            ]),
+        ),
+    ]);
+
+    let run = |filter: TraceFilter, format| {
+        let out = Buffer::default();
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default().tracer(TraceLog::new(filter, format, out.clone()));
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        let res = invoke(&mut wasm, 0, &[WasmValue::I32(7)]).unwrap();
+        assert_eq!(res, [WasmValue::I32(7)]);
+        let out = String::from_utf8(std::mem::take(&mut *out.0.lock().unwrap())).unwrap();
+        out.lines().map(str::to_string).collect::<Vec<_>>()
+    };
+
+    let filter = TraceFilter {
+        events: vec![TraceEvent::Memory, TraceEvent::Calls],
+        funcs: Some(1..=1),
+        classes: None,
+    };
+    let lines = run(filter, TraceFormat::Jsonl);
+    let events = lines
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            json!({ "event": "call", "func": 1, "args": ["I32(7)"] }),
+            json!({ "event": "store", "func": 1, "memory": 0, "addr": 0, "bytes": "07000000" }),
+            json!({ "event": "load", "func": 1, "memory": 0, "addr": 0, "bytes": "07000000" }),
+            json!({ "event": "ret", "func": 1, "results": ["I32(7)"] }),
+        ]
+    );
+
+    let filter = TraceFilter {
+        classes: Some(vec![OpClass::Control]),
+        ..Default::default()
+    };
+    let lines = run(filter, TraceFormat::Text);
+    assert_eq!(lines[0], "call func0 [I32(7)]");
+    assert_eq!(lines[1].trim(), "1 Call(1) [I32(7), I32(7)]");
+    assert_eq!(lines[2], "  call func1 [I32(7)]");
+    assert_eq!(lines.last().unwrap(), "ret func0 [I32(7)]");
+}
@@ -0,0 +1,513 @@
+//! 在字节层面改写模块：删除 custom 段（去掉调试信息）、重命名导出、注入导出的内存，
+//! 以及在每个基本块前插入计费调用（gas）
+//!
+//! 只改动涉及的段，其他段原样保留；输入先完整解码一遍，保证是合法的模块。
+
+use anyhow::{bail, ensure};
+
+use super::decoder::WasmModule;
+use super::extract::{copy_element, copy_expr, copy_instr, Index, Reader};
+use super::section::import;
+use super::section::typings::ValueType;
+use crate::leb::{encode_leb_i64 as leb_i64, encode_leb_u32 as leb_u32};
+
+const CUSTOM: u8 = 0;
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const MEMORY: u8 = 5;
+const GLOBAL: u8 = 6;
+const EXPORT: u8 = 7;
+const START: u8 = 8;
+const ELEMENT: u8 = 9;
+const CODE: u8 = 10;
+const EXPORT_FUNC: u8 = 0x00;
+const EXPORT_MEMORY: u8 = 0x02;
+
+/// 调试信息相关的 custom 段：DWARF、函数名和 source map
+pub fn is_debug_section(name: &str) -> bool {
+    name.starts_with(".debug_")
+        || matches!(name, "name" | "sourceMappingURL" | "external_debug_info")
+}
+
+/// 非 custom 段在模块中的顺序（data count 在 code 之前）
+fn section_order(id: u8) -> u8 {
+    match id {
+        12 => 10,
+        10 | 11 => id + 1,
+        _ => id,
+    }
+}
+
+/// ```ignore
+/// let out = ModuleBuilder::parse(&buf)?
+///     .strip_custom(is_debug_section)
+///     .rename_export("_start", "main")?
+///     .finish();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleBuilder {
+    header: Vec<u8>,
+    /// (段 id, 段内容)，按在模块中的顺序
+    sections: Vec<(u8, Vec<u8>)>,
+}
+
+impl ModuleBuilder {
+    pub fn parse(buf: &[u8]) -> anyhow::Result<Self> {
+        WasmModule::default(buf.to_vec()).decode()?;
+        let mut r = Reader::new(&buf[8..]);
+        let mut sections = vec![];
+        while !r.is_empty() {
+            let id = r.byte()?;
+            let len = r.u32()? as usize;
+            sections.push((id, r.bytes(len)?.to_vec()));
+        }
+        Ok(Self {
+            header: buf[..8].to_vec(),
+            sections,
+        })
+    }
+
+    /// 所有 custom 段的名字，按出现顺序
+    pub fn custom_sections(&self) -> Vec<String> {
+        self.sections
+            .iter()
+            .filter(|(id, _)| *id == CUSTOM)
+            .filter_map(|(_, content)| custom_name(content))
+            .collect()
+    }
+
+    /// 删除名字满足 strip 的 custom 段
+    pub fn strip_custom(&mut self, strip: impl Fn(&str) -> bool) -> &mut Self {
+        self.sections.retain(|(id, content)| {
+            *id != CUSTOM || !custom_name(content).is_some_and(|name| strip(&name))
+        });
+        self
+    }
+
+    /// 在模块末尾添加一个 custom 段
+    pub fn add_custom(&mut self, name: &str, payload: &[u8]) -> &mut Self {
+        let mut content = vec![];
+        leb_u32(name.len() as u32, &mut content);
+        content.extend(name.as_bytes());
+        content.extend(payload);
+        self.sections.push((CUSTOM, content));
+        self
+    }
+
+    pub fn rename_export(&mut self, from: &str, to: &str) -> anyhow::Result<&mut Self> {
+        let mut exports = self.exports()?;
+        ensure!(
+            exports.iter().all(|(name, _)| name != to),
+            "export `{to}` already exists"
+        );
+        let Some(export) = exports.iter_mut().find(|(name, _)| name == from) else {
+            bail!("unknown export `{from}`");
+        };
+        export.0 = to.to_string();
+        self.set_exports(&exports);
+        Ok(self)
+    }
+
+    /// 给没有内存的模块添加一个内存并以 name 导出
+    pub fn export_memory(
+        &mut self,
+        name: &str,
+        minimum: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<&mut Self> {
+        let wasm = self.decode()?;
+        ensure!(
+            wasm.section.memory.entries.is_empty()
+                && !wasm
+                    .section
+                    .import
+                    .entries
+                    .iter()
+                    .any(|ipt| matches!(ipt.kind, import::Kind::Memory(_))),
+            "module already has a memory"
+        );
+        ensure!(
+            maximum.map_or(minimum <= 0x10000, |max| minimum <= max && max <= 0x10000),
+            "invalid memory limits {minimum}..{maximum:?}"
+        );
+        let mut exports = self.exports()?;
+        ensure!(
+            exports.iter().all(|(export, _)| export != name),
+            "export `{name}` already exists"
+        );
+
+        let mut content = vec![1];
+        match maximum {
+            Some(max) => {
+                content.push(0x01);
+                leb_u32(minimum, &mut content);
+                leb_u32(max, &mut content);
+            }
+            None => {
+                content.push(0x00);
+                leb_u32(minimum, &mut content);
+            }
+        }
+        self.set_section(MEMORY, content);
+
+        let mut raw = vec![EXPORT_MEMORY];
+        leb_u32(0, &mut raw);
+        exports.push((name.to_string(), raw));
+        self.set_exports(&exports);
+        Ok(self)
+    }
+
+    /// 导入宿主函数 `module.name: [i64] -> []`，在每个基本块开始时以块中的指令条数调用它，
+    /// 在其他引擎上运行时也能按指令计费
+    ///
+    /// 基本块以 block、loop、if、else、end、br、br_if、br_table、return 和 unreachable 结束；
+    /// 新的导入排在已有的函数导入之后，其余函数的索引加 1，
+    /// 因此依赖函数索引和代码偏移的 `name` 段和调试信息会被删除
+    pub fn inject_gas(&mut self, module: &str, name: &str) -> anyhow::Result<&mut Self> {
+        let wasm = self.decode()?;
+        ensure!(
+            !wasm
+                .section
+                .import
+                .entries
+                .iter()
+                .any(|ipt| ipt.mod_name == module && ipt.field_name == name),
+            "module already imports `{module}::{name}`"
+        );
+        let gas = wasm.imported_funcs() as u32;
+        let map = &mut |idx| match idx {
+            Index::Func(idx) if idx >= gas => idx + 1,
+            Index::Func(idx) | Index::Type(idx) | Index::Table(idx) => idx,
+        };
+
+        let types = &wasm.section.types.entries;
+        let ty = match types
+            .iter()
+            .position(|ty| ty.params == [ValueType::I64] && ty.results.is_empty())
+        {
+            Some(ty) => ty as u32,
+            None => {
+                self.append_items(TYPE, &[0x60, 0x01, 0x7e, 0x00])?;
+                types.len() as u32
+            }
+        };
+        let mut item = vec![];
+        for text in [module, name] {
+            leb_u32(text.len() as u32, &mut item);
+            item.extend(text.as_bytes());
+        }
+        item.push(EXPORT_FUNC);
+        leb_u32(ty, &mut item);
+        // 新的函数导入放在最后，前面的函数导入索引不变
+        self.append_items(IMPORT, &item)?;
+
+        self.strip_custom(is_debug_section);
+        for (id, content) in &mut self.sections {
+            let mut r = Reader::new(content);
+            let mut out = vec![];
+            match *id {
+                GLOBAL => rewrite_items(&mut r, &mut out, |r, out| {
+                    // 值类型和可变性
+                    out.extend(r.bytes(2)?);
+                    copy_expr(r, out, map)
+                })?,
+                EXPORT => rewrite_items(&mut r, &mut out, |r, out| {
+                    out.extend(r.name()?);
+                    let kind = r.byte()?;
+                    out.push(kind);
+                    let idx = r.u32()?;
+                    match kind {
+                        EXPORT_FUNC => leb_u32(map(Index::Func(idx)), out),
+                        _ => leb_u32(idx, out),
+                    }
+                    Ok(())
+                })?,
+                START => leb_u32(map(Index::Func(r.u32()?)), &mut out),
+                ELEMENT => rewrite_items(&mut r, &mut out, |r, out| copy_element(r, out, map))?,
+                CODE => rewrite_items(&mut r, &mut out, |r, out| {
+                    let size = r.u32()? as usize;
+                    let body = metered_body(r.bytes(size)?, gas, map)?;
+                    leb_u32(body.len() as u32, out);
+                    out.extend(body);
+                    Ok(())
+                })?,
+                _ => continue,
+            }
+            *content = out;
+        }
+        self.decode()?;
+        Ok(self)
+    }
+
+    pub fn finish(&self) -> Vec<u8> {
+        let mut out = self.header.clone();
+        for (id, content) in &self.sections {
+            out.push(*id);
+            leb_u32(content.len() as u32, &mut out);
+            out.extend(content);
+        }
+        out
+    }
+
+    fn decode(&self) -> anyhow::Result<WasmModule> {
+        let mut wasm = WasmModule::default(self.finish());
+        wasm.decode()?;
+        Ok(wasm)
+    }
+
+    /// (导出名, kind 和索引的原始字节)
+    fn exports(&self) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        let Some((_, content)) = self.sections.iter().find(|(id, _)| *id == EXPORT) else {
+            return Ok(vec![]);
+        };
+        let mut r = Reader::new(content);
+        let mut exports = vec![];
+        for _ in 0..r.u32()? {
+            let len = r.u32()? as usize;
+            let name = String::from_utf8(r.bytes(len)?.to_vec())?;
+            let start = r.pos;
+            r.byte()?;
+            r.u32()?;
+            exports.push((name, content[start..r.pos].to_vec()));
+        }
+        Ok(exports)
+    }
+
+    fn set_exports(&mut self, exports: &[(String, Vec<u8>)]) {
+        let mut content = vec![];
+        leb_u32(exports.len() as u32, &mut content);
+        for (name, raw) in exports {
+            leb_u32(name.len() as u32, &mut content);
+            content.extend(name.as_bytes());
+            content.extend(raw);
+        }
+        self.set_section(EXPORT, content);
+    }
+
+    /// 在 id 段的 vec 末尾添加一项，没有这个段时创建
+    fn append_items(&mut self, id: u8, item: &[u8]) -> anyhow::Result<()> {
+        let (count, rest) = match self.sections.iter().find(|(other, _)| *other == id) {
+            Some((_, content)) => {
+                let mut r = Reader::new(content);
+                (r.u32()?, content[r.pos..].to_vec())
+            }
+            None => (0, vec![]),
+        };
+        let mut content = vec![];
+        leb_u32(count + 1, &mut content);
+        content.extend(rest);
+        content.extend(item);
+        self.set_section(id, content);
+        Ok(())
+    }
+
+    /// 替换 id 段的内容，没有时按段的顺序插入
+    fn set_section(&mut self, id: u8, content: Vec<u8>) {
+        if let Some(section) = self.sections.iter_mut().find(|(other, _)| *other == id) {
+            section.1 = content;
+            return;
+        }
+        let index = self
+            .sections
+            .iter()
+            .position(|(other, _)| *other != CUSTOM && section_order(*other) > section_order(id))
+            .unwrap_or(self.sections.len());
+        self.sections.insert(index, (id, content));
+    }
+}
+
+/// 逐项改写一个 vec
+fn rewrite_items(
+    r: &mut Reader,
+    out: &mut Vec<u8>,
+    mut item: impl FnMut(&mut Reader, &mut Vec<u8>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let count = r.u32()?;
+    leb_u32(count, out);
+    for _ in 0..count {
+        item(r, out)?;
+    }
+    ensure!(r.is_empty(), "trailing bytes at offset {}", r.pos);
+    Ok(())
+}
+
+/// 在函数体的每个基本块前插入 `i64.const 块中指令条数; call gas`
+fn metered_body(
+    body: &[u8],
+    gas: u32,
+    map: &mut impl FnMut(Index) -> u32,
+) -> anyhow::Result<Vec<u8>> {
+    let mut r = Reader::new(body);
+    let mut out = vec![];
+    let count = r.u32()?;
+    leb_u32(count, &mut out);
+    for _ in 0..count {
+        out.extend(r.leb()?);
+        out.push(r.byte()?);
+    }
+
+    let mut depth = 0;
+    let mut block = vec![];
+    let mut cost = 0u32;
+    loop {
+        let op = copy_instr(&mut r, &mut block, map)?;
+        cost += 1;
+        let last = op == 0x0b && depth == 0;
+        match op {
+            0x02..=0x04 => depth += 1,
+            0x0b if depth > 0 => depth -= 1,
+            _ => {}
+        }
+        if matches!(op, 0x00 | 0x02..=0x05 | 0x0b..=0x0f) {
+            // i64.const cost; call gas
+            out.push(0x42);
+            leb_i64(cost as i64, &mut out);
+            out.push(0x10);
+            leb_u32(gas, &mut out);
+            out.append(&mut block);
+            cost = 0;
+        }
+        if last {
+            break;
+        }
+    }
+    ensure!(r.is_empty(), "trailing bytes after function body");
+    Ok(out)
+}
+
+fn custom_name(content: &[u8]) -> Option<String> {
+    let mut r = Reader::new(content);
+    let len = r.u32().ok()? as usize;
+    String::from_utf8(r.bytes(len).ok()?.to_vec()).ok()
+}
+
+#[test]
+fn test_module_builder() {
+    use super::decoder::WasmValue;
+    use super::section::export::ExportKind;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (func (export "answer") (result i32) (i32.const 42))
+          (func (export "other")))"#,
+    )
+    .unwrap();
+    let mut builder = ModuleBuilder::parse(&buf).unwrap();
+    builder
+        .add_custom("name", b"\x00")
+        .add_custom(".debug_line", b"")
+        .add_custom("producers", b"\x00");
+    assert_eq!(
+        builder.custom_sections(),
+        ["name", ".debug_line", "producers"]
+    );
+    builder.strip_custom(is_debug_section);
+    assert_eq!(builder.custom_sections(), ["producers"]);
+
+    builder.rename_export("answer", "main").unwrap();
+    assert!(builder.rename_export("missing", "x").is_err());
+    assert!(builder.rename_export("main", "other").is_err());
+    builder.export_memory("memory", 1, Some(2)).unwrap();
+    assert!(builder.export_memory("memory2", 1, None).is_err());
+
+    let mut wasm = WasmModule::default(builder.finish());
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    assert_eq!(wasm.invoke("main", &[]).unwrap(), [WasmValue::I32(42)]);
+    assert!(wasm.invoke("answer", &[]).is_err());
+    assert!(matches!(
+        wasm.exports.get("memory"),
+        Some(ExportKind::Memory(0))
+    ));
+    assert_eq!(wasm.memory(0).unwrap().size(), 1);
+
+    // 没有 export 段时按段的顺序插入
+    let buf = wat::compile(r#"(module (global i32 (i32.const 1)) (func))"#).unwrap();
+    let mut builder = ModuleBuilder::parse(&buf).unwrap();
+    builder.export_memory("memory", 0, None).unwrap();
+    let ids = builder
+        .sections
+        .iter()
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [1, 3, 5, 6, 7, 10]);
+    assert!(ModuleBuilder::parse(b"\0asm\x01\0\0\0\x07").is_err());
+}
+
+#[test]
+fn test_inject_gas() {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use super::decoder::WasmValue;
+    use super::linker::{Func, Linker};
+    use super::wat;
+
+    static GAS: AtomicI64 = AtomicI64::new(0);
+
+    let buf = wat::compile(
+        r#"(module
+          (import "env" "log" (func $log (param i32)))
+          (table 1 funcref)
+          (elem (i32.const 0) $sum)
+          (func $sum (export "sum") (param i32) (result i32)
+            (local i32)
+            (block
+              (loop
+                (br_if 1 (i32.eqz (local.get 0)))
+                (local.set 1 (i32.add (local.get 1) (local.get 0)))
+                (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                (br 0)))
+            (call $log (local.get 1))
+            (local.get 1))
+          (func (export "indirect") (param i32) (result i32)
+            (call_indirect (param i32) (result i32) (local.get 0) (i32.const 0))))"#,
+    )
+    .unwrap();
+    let mut builder = ModuleBuilder::parse(&buf).unwrap();
+    builder.add_custom("name", b"");
+    builder.inject_gas("env", "consume_gas").unwrap();
+    assert!(builder.custom_sections().is_empty());
+    assert!(builder.inject_gas("env", "consume_gas").is_err());
+
+    let mut wasm = WasmModule::default(builder.finish());
+    wasm.decode().unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define(
+            "env",
+            "log",
+            Func::wrap(&[ValueType::I32], &[], |_, _| vec![]),
+        )
+        .unwrap()
+        .define(
+            "env",
+            "consume_gas",
+            Func::wrap(&[ValueType::I64], &[], |_, arg| {
+                if let WasmValue::I64(n) = arg[0] {
+                    GAS.fetch_add(n, Ordering::SeqCst);
+                }
+                vec![]
+            }),
+        )
+        .unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+
+    // 函数和元素段中的索引都已经调整
+    assert_eq!(
+        wasm.invoke("indirect", &[WasmValue::I32(3)]).unwrap(),
+        [WasmValue::I32(6)]
+    );
+    let sum = |wasm: &mut WasmModule, n| {
+        GAS.store(0, Ordering::SeqCst);
+        assert_eq!(
+            wasm.invoke("sum", &[WasmValue::I32(n)]).unwrap(),
+            [WasmValue::I32(n * (n + 1) / 2)]
+        );
+        GAS.load(Ordering::SeqCst)
+    };
+    // 每次循环执行 12 条指令，进入和离开循环是固定开销
+    let base = sum(&mut wasm, 0);
+    assert_eq!(sum(&mut wasm, 1) - base, 12);
+    assert_eq!(sum(&mut wasm, 10) - base, 120);
+}
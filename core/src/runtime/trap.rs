@@ -0,0 +1,162 @@
+use std::fmt::Display;
+
+use super::decoder::WasmValue;
+use super::section::typings::ValueType;
+
+/// 执行过程中的运行时错误，发生时立即终止执行
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trap {
+    Unreachable,
+    /// 操作数类型与指令要求不一致
+    TypeMismatch {
+        expected: ValueType,
+        found: WasmValue,
+    },
+    /// call_indirect 的表索引越界
+    UndefinedElement {
+        index: u32,
+    },
+    /// call_indirect 取到的表项为空引用
+    UninitializedElement {
+        index: u32,
+    },
+    /// 访问范围超出内存（或数据段）大小
+    MemoryOutOfBounds {
+        addr: u64,
+        size: usize,
+    },
+    /// 执行被 CancellationToken 取消，或被调试器中止
+    Cancelled,
+    /// 调用了被配置禁止（或不在 call_indirect 允许列表中）的函数
+    Forbidden {
+        func: usize,
+    },
+    /// 调用深度或值栈超出 `Limits` 的限制
+    StackOverflow,
+    /// 访问范围超出表（或元素段）大小
+    TableOutOfBounds {
+        index: u64,
+        size: usize,
+    },
+    /// 宿主函数调用了 `WasmModule::suspend`，可以用 `WasmModule::resume` 继续
+    Suspended,
+    /// 执行的指令数达到 `WasmModule::yield_at`，可以用 `WasmModule::resume` 继续
+    Yielded,
+    /// 在 `untyped_stack` 上执行的函数、本地代码或宿主函数调用的函数中挂起，
+    /// 这些函数帧不能保存
+    SuspendUnsupported,
+    /// 整数除法或取余的除数为 0
+    DivideByZero,
+    /// 有符号整数除法的结果溢出（INT_MIN / -1），或浮点数截断后超出整数范围
+    IntegerOverflow,
+    /// 浮点数截断为整数时操作数为 NaN
+    InvalidConversion,
+    /// 从其他实例导入的函数所在的实例正在执行，不支持重入
+    InstanceBusy {
+        func: usize,
+    },
+    /// call_ref、ref.as_non_null 的操作数为空引用
+    NullReference,
+    /// call_indirect、call_ref 调用的函数与指令声明的类型不一致
+    SignatureMismatch {
+        func: usize,
+    },
+    /// 共享表中的函数属于另一个实例，而它没有放进 `SharedInstance`（见 `linker::shared_instance`）
+    /// 或已经释放，不能调用
+    ForeignFunction {
+        func: usize,
+    },
+    /// 宿主函数调用了 `WasmModule::raise`，例如 emscripten 的 abort
+    Host {
+        message: String,
+    },
+    /// 延迟解码的函数体在第一次调用时解析失败，或执行到栈高度不合法、不能降级的分支
+    MalformedBody {
+        func: usize,
+        message: String,
+    },
+    /// guest 调用了 WASI 的 proc_exit，由嵌入方按退出码处理，不结束宿主进程
+    Exit {
+        code: i32,
+    },
+}
+
+impl Trap {
+    pub fn mismatch(expected: ValueType, found: WasmValue) -> Self {
+        Trap::TypeMismatch { expected, found }
+    }
+
+    /// err 是 `Trap::Exit` 时返回退出码
+    pub fn exit_code(err: &anyhow::Error) -> Option<i32> {
+        match err.downcast_ref::<Trap>() {
+            Some(Trap::Exit { code }) => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trap::Unreachable => write!(f, "RuntimeError: unreachable"),
+            Trap::TypeMismatch { expected, found } => write!(
+                f,
+                "RuntimeError: type mismatch, expected {expected} but found {found:?}"
+            ),
+            Trap::UndefinedElement { index } => {
+                write!(f, "RuntimeError: undefined element {index}")
+            }
+            Trap::UninitializedElement { index } => {
+                write!(f, "RuntimeError: uninitialized element {index}")
+            }
+            Trap::Cancelled => write!(f, "RuntimeError: execution cancelled"),
+            Trap::StackOverflow => write!(f, "RuntimeError: call stack exhausted"),
+            Trap::Forbidden { func } => {
+                write!(f, "RuntimeError: calling function {func} is not allowed")
+            }
+            Trap::MemoryOutOfBounds { addr, size } => write!(
+                f,
+                "RuntimeError: out of bounds memory access, addr = {addr}, size = {size}"
+            ),
+            Trap::TableOutOfBounds { index, size } => write!(
+                f,
+                "RuntimeError: out of bounds table access, index = {index}, size = {size}"
+            ),
+            Trap::Suspended => write!(f, "RuntimeError: execution suspended by host function"),
+            Trap::Yielded => write!(
+                f,
+                "RuntimeError: execution yielded after its instruction budget"
+            ),
+            Trap::SuspendUnsupported => write!(
+                f,
+                "RuntimeError: can't suspend inside a function whose frames can't be saved"
+            ),
+            Trap::DivideByZero => write!(f, "RuntimeError: integer divide by zero"),
+            Trap::IntegerOverflow => write!(f, "RuntimeError: integer overflow"),
+            Trap::InvalidConversion => write!(f, "RuntimeError: invalid conversion to integer"),
+            Trap::InstanceBusy { func } => write!(
+                f,
+                "RuntimeError: the instance exporting function {func} is already executing"
+            ),
+            Trap::NullReference => write!(f, "RuntimeError: null reference"),
+            Trap::SignatureMismatch { func } => write!(
+                f,
+                "RuntimeError: indirect call type mismatch (function {func})"
+            ),
+            Trap::ForeignFunction { func } => write!(
+                f,
+                "RuntimeError: function {func} belongs to an instance that is not shared"
+            ),
+            Trap::Host { message } => write!(f, "RuntimeError: {message}"),
+            Trap::MalformedBody { func, message } => {
+                write!(
+                    f,
+                    "RuntimeError: malformed body of function {func}: {message}"
+                )
+            }
+            Trap::Exit { code } => write!(f, "exit with code {code}"),
+        }
+    }
+}
+
+impl std::error::Error for Trap {}
@@ -0,0 +1,1005 @@
+//! 不带类型标记的 u64 栈（`RuntimeConfig::untyped_stack` 开启）
+//!
+//! 函数第一次被调用时做一遍校验：按指令签名推导操作数栈上每个值的类型和高度，
+//! 通过校验的函数编译为 `RawOp`，在 u64 栈上执行：
+//! - i32 存放在低 32 位（高位为 0），浮点数存放位模式，指令本身决定如何解释
+//! - 分支目标和需要保留的值个数、目标高度在编译时确定，跳转时整理栈
+//!
+//! 只支持整数运算、局部/全局变量、整数访存、直接调用和结构化控制流，
+//! 其他函数（以及导入函数）仍然用带类型的 WasmValue 栈执行，调用边界按签名转换。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::decoder::{FuncKind, Global, WasmModule, WasmValue, CANCEL_CHECK_INTERVAL};
+use super::section::opcode::{BlockType, MemArg, Opcode};
+use super::section::typings::ValueType;
+use super::trap::Trap;
+
+/// 跳转：保留栈顶 arity 个值，放到操作数栈高度 height 处
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Branch {
+    pub target: usize,
+    pub height: u32,
+    pub arity: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum IntBin {
+    Add,
+    Sub,
+    Mul,
+    And,
+    Or,
+    Xor,
+    Shl,
+    ShrS,
+    ShrU,
+    Rotl,
+    Rotr,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum IntCmp {
+    Eq,
+    Ne,
+    LtS,
+    LtU,
+    GtS,
+    GtU,
+    LeS,
+    LeU,
+    GeS,
+    GeU,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum IntUn {
+    Clz,
+    Ctz,
+    Popcnt,
+    Extend8S,
+    Extend16S,
+    Extend32S,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum RawOp {
+    Unreachable,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32, ValueType),
+    Const(u64),
+    Drop,
+    Select,
+    Br(Branch),
+    BrIf(Branch),
+    /// 下标指向 `RawFunc::tables`
+    BrTable(usize),
+    /// 栈顶为 0 时跳转（if 的条件）
+    BrUnless(usize),
+    Return,
+    Call(usize),
+    /// (字节数, 是否符号扩展, 结果是否为 i64)
+    Load(u8, bool, bool, MemArg),
+    Store(u8, MemArg),
+    I32Eqz,
+    I64Eqz,
+    I32Bin(IntBin),
+    I64Bin(IntBin),
+    I32Cmp(IntCmp),
+    I64Cmp(IntCmp),
+    I32Un(IntUn),
+    I64Un(IntUn),
+    I32WrapI64,
+    I64ExtendI32S,
+    I64ExtendI32U,
+}
+
+#[derive(Debug)]
+pub struct RawFunc {
+    pub(crate) params: usize,
+    pub(crate) results: Vec<ValueType>,
+    /// 参数和局部变量
+    pub(crate) locals: Vec<ValueType>,
+    pub(crate) code: Vec<RawOp>,
+    pub(crate) tables: Vec<Box<[Branch]>>,
+    /// 操作数栈的最大高度
+    pub(crate) max_height: usize,
+}
+
+/// 已编译的函数，None 表示没有通过校验（或使用了不支持的指令）
+pub type RawFuncs = HashMap<usize, Option<Arc<RawFunc>>>;
+
+#[derive(PartialEq)]
+enum Kind {
+    Block,
+    Loop,
+    If,
+}
+
+enum Patch {
+    Op(usize),
+    Table(usize, usize),
+}
+
+struct Frame {
+    kind: Kind,
+    params: Vec<ValueType>,
+    results: Vec<ValueType>,
+    /// 进入块时操作数栈高度（不含参数）
+    height: usize,
+    /// loop 的开始位置
+    start: usize,
+    /// 跳转到块结束的指令
+    patches: Vec<Patch>,
+    /// if 条件为假时的跳转指令，遇到 else 后为 None
+    else_patch: Option<usize>,
+    unreachable: bool,
+}
+
+impl Frame {
+    fn label_types(&self) -> &[ValueType] {
+        match self.kind {
+            Kind::Loop => &self.params,
+            _ => &self.results,
+        }
+    }
+}
+
+struct Compiler<'a> {
+    module: &'a WasmModule,
+    locals: Vec<ValueType>,
+    types: Vec<ValueType>,
+    frames: Vec<Frame>,
+    code: Vec<RawOp>,
+    tables: Vec<Box<[Branch]>>,
+    max_height: usize,
+}
+
+fn is_scalar(ty: &ValueType) -> bool {
+    matches!(
+        ty,
+        ValueType::I32 | ValueType::I64 | ValueType::F32 | ValueType::F64
+    )
+}
+
+fn numeric(op: &Opcode) -> Option<(&'static [ValueType], ValueType, RawOp)> {
+    use ValueType::{I32, I64};
+    use {IntBin as B, IntCmp as C, IntUn as U, Opcode as O, RawOp as R};
+    let i32_bin = |op| Some((&[I32, I32][..], I32, R::I32Bin(op)));
+    let i64_bin = |op| Some((&[I64, I64][..], I64, R::I64Bin(op)));
+    let i32_cmp = |op| Some((&[I32, I32][..], I32, R::I32Cmp(op)));
+    let i64_cmp = |op| Some((&[I64, I64][..], I32, R::I64Cmp(op)));
+    let i32_un = |op| Some((&[I32][..], I32, R::I32Un(op)));
+    let i64_un = |op| Some((&[I64][..], I64, R::I64Un(op)));
+    match op {
+        O::I32Eqz => Some((&[I32], I32, R::I32Eqz)),
+        O::I64Eqz => Some((&[I64], I32, R::I64Eqz)),
+        O::I32Eq => i32_cmp(C::Eq),
+        O::I32Ne => i32_cmp(C::Ne),
+        O::I32Lts => i32_cmp(C::LtS),
+        O::I32Ltu => i32_cmp(C::LtU),
+        O::I32Gts => i32_cmp(C::GtS),
+        O::I32Gtu => i32_cmp(C::GtU),
+        O::I32Les => i32_cmp(C::LeS),
+        O::I32Leu => i32_cmp(C::LeU),
+        O::I32Ges => i32_cmp(C::GeS),
+        O::I32Geu => i32_cmp(C::GeU),
+        O::I64Eq => i64_cmp(C::Eq),
+        O::I64Ne => i64_cmp(C::Ne),
+        O::I64Lts => i64_cmp(C::LtS),
+        O::I64Ltu => i64_cmp(C::LtU),
+        O::I64Gts => i64_cmp(C::GtS),
+        O::I64Gtu => i64_cmp(C::GtU),
+        O::I64Les => i64_cmp(C::LeS),
+        O::I64Leu => i64_cmp(C::LeU),
+        O::I64Ges => i64_cmp(C::GeS),
+        O::I64Geu => i64_cmp(C::GeU),
+        O::I32Clz => i32_un(U::Clz),
+        O::I32Ctz => i32_un(U::Ctz),
+        O::I32Popcnt => i32_un(U::Popcnt),
+        O::I32Extends8s => i32_un(U::Extend8S),
+        O::I32Extends16s => i32_un(U::Extend16S),
+        O::I64Clz => i64_un(U::Clz),
+        O::I64Ctz => i64_un(U::Ctz),
+        O::I64Popcnt => i64_un(U::Popcnt),
+        O::I64Extends8s => i64_un(U::Extend8S),
+        O::I64Extends16s => i64_un(U::Extend16S),
+        O::I64Extends32s => i64_un(U::Extend32S),
+        O::I32Add => i32_bin(B::Add),
+        O::I32Sub => i32_bin(B::Sub),
+        O::I32Mul => i32_bin(B::Mul),
+        O::I32And => i32_bin(B::And),
+        O::I32Or => i32_bin(B::Or),
+        O::I32Xor => i32_bin(B::Xor),
+        O::I32Shl => i32_bin(B::Shl),
+        O::I32ShlS => i32_bin(B::ShrS),
+        O::I32ShlU => i32_bin(B::ShrU),
+        O::I32Rotl => i32_bin(B::Rotl),
+        O::I32Rotr => i32_bin(B::Rotr),
+        O::I64Add => i64_bin(B::Add),
+        O::I64Sub => i64_bin(B::Sub),
+        O::I64Mul => i64_bin(B::Mul),
+        O::I64And => i64_bin(B::And),
+        O::I64Or => i64_bin(B::Or),
+        O::I64Xor => i64_bin(B::Xor),
+        O::I64Shl => i64_bin(B::Shl),
+        O::I64ShlS => i64_bin(B::ShrS),
+        O::I64ShlU => i64_bin(B::ShrU),
+        O::I64Rotl => i64_bin(B::Rotl),
+        O::I64Rotr => i64_bin(B::Rotr),
+        O::I32WrapI64 => Some((&[I64], I32, R::I32WrapI64)),
+        O::I64ExtendsI32s => Some((&[I32], I64, R::I64ExtendI32S)),
+        O::I64ExtendsI32u => Some((&[I32], I64, R::I64ExtendI32U)),
+        _ => None,
+    }
+}
+
+/// 访存指令：(字节数, 是否符号扩展, 值类型, 是否为 store)
+fn memory_access(op: &Opcode) -> Option<(u8, bool, ValueType, bool, MemArg)> {
+    use ValueType::{I32, I64};
+    let access = match op {
+        Opcode::I32Load(m) => (4, false, I32, false, *m),
+        Opcode::I64Load(m) => (8, false, I64, false, *m),
+        Opcode::I32Load8s(m) => (1, true, I32, false, *m),
+        Opcode::I32Load8u(m) => (1, false, I32, false, *m),
+        Opcode::I32Load16s(m) => (2, true, I32, false, *m),
+        Opcode::I32Load16u(m) => (2, false, I32, false, *m),
+        Opcode::I64Load8s(m) => (1, true, I64, false, *m),
+        Opcode::I64Load8u(m) => (1, false, I64, false, *m),
+        Opcode::I64Load16s(m) => (2, true, I64, false, *m),
+        Opcode::I64Load16u(m) => (2, false, I64, false, *m),
+        Opcode::I64Load32s(m) => (4, true, I64, false, *m),
+        Opcode::I64Load32u(m) => (4, false, I64, false, *m),
+        Opcode::I32Store(m) => (4, false, I32, true, *m),
+        Opcode::I64Store(m) => (8, false, I64, true, *m),
+        Opcode::I32Store8(m) => (1, false, I32, true, *m),
+        Opcode::I32Store16(m) => (2, false, I32, true, *m),
+        Opcode::I64Store8(m) => (1, false, I64, true, *m),
+        Opcode::I64Store16(m) => (2, false, I64, true, *m),
+        Opcode::I64Store32(m) => (4, false, I64, true, *m),
+        _ => return None,
+    };
+    Some(access)
+}
+
+impl Compiler<'_> {
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn push(&mut self, ty: ValueType) {
+        self.types.push(ty);
+        self.max_height = self.max_height.max(self.types.len());
+    }
+
+    fn pop(&mut self, expected: ValueType) -> Option<()> {
+        let height = self.frames.last()?.height;
+        (self.types.len() > height && self.types.pop()? == expected).then_some(())
+    }
+
+    fn pop_any(&mut self) -> Option<ValueType> {
+        let height = self.frames.last()?.height;
+        (self.types.len() > height).then(|| self.types.pop())?
+    }
+
+    /// 检查栈顶的值与 types 一致（不弹出）
+    fn check_top(&self, types: &[ValueType]) -> Option<()> {
+        let height = self.frames.last()?.height;
+        let start = self.types.len().checked_sub(types.len())?;
+        (start >= height && self.types[start..] == *types).then_some(())
+    }
+
+    fn func_type(&self, idx: usize) -> Option<(Vec<ValueType>, Vec<ValueType>)> {
+        let ty = match self.module.func.get(idx)? {
+            FuncKind::Import(ty, _) | FuncKind::Local((ty, _)) => *ty,
+        };
+        let ty = self.module.section.types.entries.get(ty)?;
+        Some((ty.params.clone(), ty.results.clone()))
+    }
+
+    fn block_type(&self, bt: &BlockType) -> Option<(Vec<ValueType>, Vec<ValueType>)> {
+        match bt {
+            BlockType::NOP => Some((vec![], vec![])),
+            BlockType::ValueType(ty) => Some((vec![], vec![*ty])),
+            BlockType::Value(idx) => {
+                let ty = self.module.section.types.entries.get(*idx as usize)?;
+                Some((ty.params.clone(), ty.results.clone()))
+            }
+        }
+    }
+
+    /// 生成跳转到第 label 层块的 Branch，target 在块结束时回填
+    fn branch(&mut self, label: usize, patch: Patch) -> Option<Branch> {
+        let index = self.frames.len().checked_sub(label + 1)?;
+        let frame = &self.frames[index];
+        let types = frame.label_types().to_vec();
+        let (height, start, is_loop) = (frame.height, frame.start, frame.kind == Kind::Loop);
+        self.check_top(&types)?;
+        if !is_loop {
+            self.frames[index].patches.push(patch);
+        }
+        Some(Branch {
+            target: start,
+            height: height as u32,
+            arity: types.len() as u32,
+        })
+    }
+
+    fn enter(&mut self, kind: Kind, bt: &BlockType) -> Option<()> {
+        let (params, results) = self.block_type(bt)?;
+        for ty in params.iter().rev() {
+            self.pop(*ty)?;
+        }
+        let height = self.types.len();
+        params.iter().for_each(|ty| self.push(*ty));
+        self.frames.push(Frame {
+            kind,
+            params,
+            results,
+            height,
+            start: self.code.len(),
+            patches: vec![],
+            else_patch: None,
+            unreachable: false,
+        });
+        Some(())
+    }
+
+    fn unreachable(&mut self) {
+        let frame = self.frame();
+        frame.unreachable = true;
+        let height = frame.height;
+        self.types.truncate(height);
+    }
+
+    /// 块结束时栈上恰好是块的结果
+    fn check_results(&self) -> Option<()> {
+        let frame = self.frames.last()?;
+        if frame.unreachable {
+            return Some(());
+        }
+        (self.types.len() == frame.height + frame.results.len()).then_some(())?;
+        self.check_top(&frame.results)
+    }
+
+    fn patch(&mut self, patch: Patch, target: usize) {
+        match patch {
+            Patch::Op(index) => match &mut self.code[index] {
+                RawOp::Br(branch) | RawOp::BrIf(branch) => branch.target = target,
+                RawOp::BrUnless(other) => *other = target,
+                _ => unreachable!(),
+            },
+            Patch::Table(table, entry) => self.tables[table][entry].target = target,
+        }
+    }
+
+    fn compile_op(&mut self, op: &Opcode) -> Option<()> {
+        use ValueType::*;
+        let next = self.code.len();
+        match op {
+            Opcode::Nop => {}
+            Opcode::Unreachable => {
+                self.code.push(RawOp::Unreachable);
+                self.unreachable();
+            }
+            Opcode::Block(bt, _) => self.enter(Kind::Block, bt)?,
+            Opcode::Loop(bt, _) => self.enter(Kind::Loop, bt)?,
+            Opcode::If(bt, _) => {
+                self.pop(I32)?;
+                self.enter(Kind::If, bt)?;
+                self.frame().else_patch = Some(next);
+                self.code.push(RawOp::BrUnless(0));
+            }
+            Opcode::Else(_) => {
+                self.check_results()?;
+                let frame = self.frames.last_mut()?;
+                (frame.kind == Kind::If).then_some(())?;
+                let else_patch = frame.else_patch.take()?;
+                frame.unreachable = false;
+                let (height, params) = (frame.height, frame.params.clone());
+                self.patch(Patch::Op(else_patch), next);
+                self.types.truncate(height);
+                params.iter().for_each(|ty| self.push(*ty));
+            }
+            Opcode::End(_) => {
+                self.check_results()?;
+                let frame = self.frames.pop()?;
+                // 没有 else 的 if，条件为假时参数原样作为结果
+                if let Some(else_patch) = frame.else_patch {
+                    (frame.params == frame.results).then_some(())?;
+                    self.patch(Patch::Op(else_patch), next);
+                }
+                for patch in frame.patches {
+                    self.patch(patch, next);
+                }
+                self.types.truncate(frame.height);
+                frame.results.iter().for_each(|ty| self.push(*ty));
+                if self.frames.is_empty() {
+                    self.code.push(RawOp::Return);
+                }
+            }
+            Opcode::Br(label, _) => {
+                let branch = self.branch(*label, Patch::Op(next))?;
+                self.code.push(RawOp::Br(branch));
+                self.unreachable();
+            }
+            Opcode::BrIf(label, _) => {
+                self.pop(I32)?;
+                let branch = self.branch(*label, Patch::Op(next))?;
+                self.code.push(RawOp::BrIf(branch));
+            }
+            Opcode::BrTable(_, entries, default) => {
+                self.pop(I32)?;
+                let table = self.tables.len();
+                let mut branches = vec![];
+                let mut arity = None;
+                for (entry, (label, _)) in entries.iter().chain([default]).enumerate() {
+                    let branch = self.branch(*label, Patch::Table(table, entry))?;
+                    (*arity.get_or_insert(branch.arity) == branch.arity).then_some(())?;
+                    branches.push(branch);
+                }
+                self.tables.push(branches.into());
+                self.code.push(RawOp::BrTable(table));
+                self.unreachable();
+            }
+            Opcode::Return => {
+                let results = self.frames.first()?.results.clone();
+                self.check_top(&results)?;
+                self.code.push(RawOp::Return);
+                self.unreachable();
+            }
+            Opcode::Call(idx) => {
+                let (params, results) = self.func_type(*idx as usize)?;
+                for ty in params.iter().rev() {
+                    self.pop(*ty)?;
+                }
+                results.iter().for_each(|ty| self.push(*ty));
+                self.code.push(RawOp::Call(*idx as usize));
+            }
+            Opcode::Drop => {
+                self.pop_any()?;
+                self.code.push(RawOp::Drop);
+            }
+            Opcode::Select => {
+                self.pop(I32)?;
+                let ty = self.pop_any()?;
+                self.pop(ty)?;
+                self.push(ty);
+                self.code.push(RawOp::Select);
+            }
+            Opcode::SelectType(_, types) => {
+                let ty = ValueType::from_u8(*types.first()? as u8).ok()?;
+                self.pop(I32)?;
+                self.pop(ty)?;
+                self.pop(ty)?;
+                self.push(ty);
+                self.code.push(RawOp::Select);
+            }
+            Opcode::LocalGet(idx) => {
+                self.push(*self.locals.get(*idx as usize)?);
+                self.code.push(RawOp::LocalGet(*idx));
+            }
+            Opcode::LocalSet(idx) => {
+                self.pop(*self.locals.get(*idx as usize)?)?;
+                self.code.push(RawOp::LocalSet(*idx));
+            }
+            Opcode::LocalTee(idx) => {
+                let ty = *self.locals.get(*idx as usize)?;
+                self.pop(ty)?;
+                self.push(ty);
+                self.code.push(RawOp::LocalTee(*idx));
+            }
+            // 全局变量的类型取实例化后的值
+            Opcode::GlobalGet(idx) => {
+                let ty = global_type(self.module.global.get(*idx as usize)?)?;
+                self.push(ty);
+                self.code.push(RawOp::GlobalGet(*idx));
+            }
+            Opcode::GlobalSet(idx) => {
+                let ty = global_type(self.module.global.get(*idx as usize)?)?;
+                self.pop(ty)?;
+                self.code.push(RawOp::GlobalSet(*idx, ty));
+            }
+            Opcode::I32Const(v) => {
+                self.push(I32);
+                self.code.push(RawOp::Const(*v as u32 as u64));
+            }
+            Opcode::I64Const(v) => {
+                self.push(I64);
+                self.code.push(RawOp::Const(*v as u64));
+            }
+            Opcode::F32Const(v) => {
+                self.push(F32);
+                self.code.push(RawOp::Const(v.to_bits() as u64));
+            }
+            Opcode::F64Const(v) => {
+                self.push(F64);
+                self.code.push(RawOp::Const(v.to_bits()));
+            }
+            op => {
+                if let Some((bytes, signed, ty, store, memarg)) = memory_access(op) {
+                    if store {
+                        self.pop(ty)?;
+                        self.pop(I32)?;
+                        self.code.push(RawOp::Store(bytes, memarg));
+                    } else {
+                        self.pop(I32)?;
+                        self.push(ty);
+                        self.code
+                            .push(RawOp::Load(bytes, signed, ty == I64, memarg));
+                    }
+                } else {
+                    let (params, result, raw) = numeric(op)?;
+                    for ty in params.iter().rev() {
+                        self.pop(*ty)?;
+                    }
+                    self.push(result);
+                    self.code.push(raw);
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+pub(crate) fn global_type(global: &Global) -> Option<ValueType> {
+    global.get().value_type().filter(is_scalar)
+}
+
+fn compile(module: &WasmModule, idx: usize) -> Option<RawFunc> {
+    let FuncKind::Local((ty, body)) = module.func.get(idx)? else {
+        return None;
+    };
+    let ty = module.section.types.entries.get(*ty)?;
+    let mut locals = ty.params.clone();
+    for (count, ty) in &body.locales {
+        locals.extend(std::iter::repeat_n(*ty, *count as usize));
+    }
+    if !locals.iter().chain(&ty.results).all(is_scalar) {
+        return None;
+    }
+
+    let mut compiler = Compiler {
+        module,
+        locals,
+        types: vec![],
+        frames: vec![],
+        code: vec![],
+        tables: vec![],
+        max_height: 0,
+    };
+    compiler.frames.push(Frame {
+        kind: Kind::Block,
+        params: vec![],
+        results: ty.results.clone(),
+        height: 0,
+        start: 0,
+        patches: vec![],
+        else_patch: None,
+        unreachable: false,
+    });
+    // 不可达的指令不需要编译，跳到当前块的 else/end
+    let mut depth = 0;
+    for op in &module.ops[body.code.0..=body.code.1] {
+        if compiler.frames.last()?.unreachable {
+            match op {
+                Opcode::Block(..) | Opcode::Loop(..) | Opcode::If(..) => depth += 1,
+                Opcode::End(_) | Opcode::Else(_) if depth == 0 => {}
+                Opcode::End(_) => depth -= 1,
+                _ => {}
+            }
+            if depth > 0 || !matches!(op, Opcode::End(_) | Opcode::Else(_)) {
+                continue;
+            }
+        }
+        compiler.compile_op(op)?;
+    }
+    compiler.frames.is_empty().then_some(())?;
+
+    Some(RawFunc {
+        params: ty.params.len(),
+        results: ty.results.clone(),
+        locals: compiler.locals,
+        code: compiler.code,
+        tables: compiler.tables,
+        max_height: compiler.max_height,
+    })
+}
+
+/// 取出（必要时编译）函数的 u64 栈版本，导入函数和不支持的函数返回 None
+pub(crate) fn raw_func(module: &mut WasmModule, idx: usize) -> Option<Arc<RawFunc>> {
+    if let Some(func) = module.raw_funcs.get(&idx) {
+        return func.clone();
+    }
+    // 解析失败时按原方式调用，由 call 报告 trap
+    if module.decode_pending(idx).is_err() {
+        return None;
+    }
+    let func = compile(module, idx).map(Arc::new);
+    module.raw_funcs.insert(idx, func.clone());
+    func
+}
+
+pub(crate) fn to_bits(value: WasmValue) -> u64 {
+    match value {
+        WasmValue::I32(v) => v as u32 as u64,
+        WasmValue::I64(v) => v as u64,
+        WasmValue::F32(v) => v.to_bits() as u64,
+        WasmValue::F64(v) => v.to_bits(),
+        WasmValue::V128(_) | WasmValue::FuncRef(_) | WasmValue::ExternRef(_) | WasmValue::NOP => 0,
+    }
+}
+
+pub(crate) fn to_value(bits: u64, ty: ValueType) -> WasmValue {
+    match ty {
+        ValueType::I32 => WasmValue::I32(bits as u32 as i32),
+        ValueType::I64 => WasmValue::I64(bits as i64),
+        ValueType::F32 => WasmValue::F32(f32::from_bits(bits as u32)),
+        ValueType::F64 => WasmValue::F64(f64::from_bits(bits)),
+        _ => WasmValue::NOP,
+    }
+}
+
+/// 由带类型的 `WasmModule::call` 进入：参数在 WasmValue 栈顶
+pub(crate) fn call(module: &mut WasmModule, func: Arc<RawFunc>) -> Result<Vec<WasmValue>, Trap> {
+    let sp = module.sp - func.params;
+    let fp = module.raw_sp;
+    ensure_stack(module, fp + func.params);
+    for i in 0..func.params {
+        module.raw_stack[fp + i] = to_bits(module.stack[sp + 1 + i]);
+    }
+    module.sp = sp;
+    let result = exec(module, &func, fp);
+    module.raw_sp = fp;
+    result?;
+    // 与带类型的调用一致，结果按声明的顺序排列
+    Ok(func
+        .results
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| to_value(module.raw_stack[fp + i], *ty))
+        .collect())
+}
+
+fn ensure_stack(module: &mut WasmModule, len: usize) {
+    if module.raw_stack.len() < len {
+        module.raw_stack.resize(len + 512, 0);
+    }
+}
+
+fn int_bin32(op: IntBin, a: u32, b: u32) -> u32 {
+    match op {
+        IntBin::Add => a.wrapping_add(b),
+        IntBin::Sub => a.wrapping_sub(b),
+        IntBin::Mul => a.wrapping_mul(b),
+        IntBin::And => a & b,
+        IntBin::Or => a | b,
+        IntBin::Xor => a ^ b,
+        IntBin::Shl => a.wrapping_shl(b),
+        IntBin::ShrS => (a as i32).wrapping_shr(b) as u32,
+        IntBin::ShrU => a.wrapping_shr(b),
+        IntBin::Rotl => a.rotate_left(b),
+        IntBin::Rotr => a.rotate_right(b),
+    }
+}
+
+fn int_bin64(op: IntBin, a: u64, b: u64) -> u64 {
+    match op {
+        IntBin::Add => a.wrapping_add(b),
+        IntBin::Sub => a.wrapping_sub(b),
+        IntBin::Mul => a.wrapping_mul(b),
+        IntBin::And => a & b,
+        IntBin::Or => a | b,
+        IntBin::Xor => a ^ b,
+        IntBin::Shl => a.wrapping_shl(b as u32),
+        IntBin::ShrS => (a as i64).wrapping_shr(b as u32) as u64,
+        IntBin::ShrU => a.wrapping_shr(b as u32),
+        IntBin::Rotl => a.rotate_left(b as u32),
+        IntBin::Rotr => a.rotate_right(b as u32),
+    }
+}
+
+/// 有符号比较先把两个操作数转为对应位宽的有符号数（此处已符号扩展到 i64）
+fn int_cmp(op: IntCmp, a: u64, b: u64, sa: i64, sb: i64) -> bool {
+    match op {
+        IntCmp::Eq => a == b,
+        IntCmp::Ne => a != b,
+        IntCmp::LtS => sa < sb,
+        IntCmp::LtU => a < b,
+        IntCmp::GtS => sa > sb,
+        IntCmp::GtU => a > b,
+        IntCmp::LeS => sa <= sb,
+        IntCmp::LeU => a <= b,
+        IntCmp::GeS => sa >= sb,
+        IntCmp::GeU => a >= b,
+    }
+}
+
+fn int_un32(op: IntUn, a: u32) -> u32 {
+    match op {
+        IntUn::Clz => a.leading_zeros(),
+        IntUn::Ctz => a.trailing_zeros(),
+        IntUn::Popcnt => a.count_ones(),
+        IntUn::Extend8S => a as i8 as i32 as u32,
+        IntUn::Extend16S | IntUn::Extend32S => a as i16 as i32 as u32,
+    }
+}
+
+fn int_un64(op: IntUn, a: u64) -> u64 {
+    match op {
+        IntUn::Clz => a.leading_zeros() as u64,
+        IntUn::Ctz => a.trailing_zeros() as u64,
+        IntUn::Popcnt => a.count_ones() as u64,
+        IntUn::Extend8S => a as i8 as i64 as u64,
+        IntUn::Extend16S => a as i16 as i64 as u64,
+        IntUn::Extend32S => a as i32 as i64 as u64,
+    }
+}
+
+fn out_of_bounds(module: &WasmModule, memarg: &MemArg, addr: u64) -> Trap {
+    let size = module
+        .mem
+        .get(memarg.memory as usize)
+        .map_or(0, |mem| mem.len());
+    Trap::MemoryOutOfBounds { addr, size }
+}
+
+/// 读取 bytes 个字节并零扩展为 u64
+fn load(module: &WasmModule, memarg: &MemArg, base: u64, bytes: u8) -> Result<u64, Trap> {
+    let addr = (base as u32) as u64 + memarg.offset as u64;
+    let v = module
+        .mem
+        .get(memarg.memory as usize)
+        .and_then(|mem| match bytes {
+            1 => mem.load::<1>(addr).map(|b| u8::from_le_bytes(b) as u64),
+            2 => mem.load::<2>(addr).map(|b| u16::from_le_bytes(b) as u64),
+            4 => mem.load::<4>(addr).map(|b| u32::from_le_bytes(b) as u64),
+            _ => mem.load::<8>(addr).map(u64::from_le_bytes),
+        });
+    v.ok_or_else(|| out_of_bounds(module, memarg, addr))
+}
+
+/// 执行 func，参数已经在 raw_stack[fp..]，返回时结果放在 raw_stack[fp..]
+fn exec(module: &mut WasmModule, func: &RawFunc, fp: usize) -> Result<(), Trap> {
+    let base = fp + func.locals.len();
+    if let Some(max) = module.config.limits.max_value_stack {
+        if base + func.max_height >= max {
+            return Err(Trap::StackOverflow);
+        }
+    }
+    ensure_stack(module, base + func.max_height + 1);
+    module.raw_stack[fp + func.params..base].fill(0);
+    module.raw_sp = base;
+    let mut sp = base;
+    let mut pc = 0;
+
+    macro_rules! stack {
+        () => {
+            module.raw_stack
+        };
+    }
+    macro_rules! pop {
+        () => {{
+            sp -= 1;
+            stack!()[sp]
+        }};
+    }
+    macro_rules! push {
+        ($v:expr) => {{
+            let v = $v;
+            stack!()[sp] = v;
+            sp += 1;
+        }};
+    }
+    let branch = |stack: &mut Vec<u64>, sp: &mut usize, branch: &Branch| {
+        let dst = base + branch.height as usize;
+        let arity = branch.arity as usize;
+        stack.copy_within(*sp - arity..*sp, dst);
+        *sp = dst + arity;
+        branch.target
+    };
+
+    loop {
+        let op = func.code[pc];
+        pc += 1;
+        module.stats.instructions += 1;
+        if module
+            .stats
+            .instructions
+            .is_multiple_of(CANCEL_CHECK_INTERVAL)
+            && module.config.is_cancelled()
+        {
+            return Err(Trap::Cancelled);
+        }
+        match op {
+            RawOp::Unreachable => return Err(Trap::Unreachable),
+            RawOp::LocalGet(idx) => push!(stack!()[fp + idx as usize]),
+            RawOp::LocalSet(idx) => {
+                let v = pop!();
+                stack!()[fp + idx as usize] = v;
+            }
+            RawOp::LocalTee(idx) => stack!()[fp + idx as usize] = stack!()[sp - 1],
+            RawOp::GlobalGet(idx) => push!(to_bits(module.global[idx as usize].get())),
+            RawOp::GlobalSet(idx, ty) => {
+                let v = pop!();
+                module.global[idx as usize].set(to_value(v, ty));
+            }
+            RawOp::Const(v) => push!(v),
+            RawOp::Drop => sp -= 1,
+            RawOp::Select => {
+                let c = pop!();
+                let b = pop!();
+                if c as u32 == 0 {
+                    stack!()[sp - 1] = b;
+                }
+            }
+            RawOp::Br(b) => pc = branch(&mut stack!(), &mut sp, &b),
+            RawOp::BrIf(b) => {
+                if pop!() as u32 != 0 {
+                    pc = branch(&mut stack!(), &mut sp, &b);
+                }
+            }
+            RawOp::BrTable(table) => {
+                let v = pop!() as u32 as usize;
+                let targets = &func.tables[table];
+                let b = targets[v.min(targets.len() - 1)];
+                pc = branch(&mut stack!(), &mut sp, &b);
+            }
+            RawOp::BrUnless(target) => {
+                if pop!() as u32 == 0 {
+                    pc = target;
+                }
+            }
+            RawOp::Return => {
+                let n = func.results.len();
+                stack!().copy_within(sp - n..sp, fp);
+                return Ok(());
+            }
+            RawOp::Call(idx) => {
+                if module.policy.denied.contains(&idx) {
+                    return Err(Trap::Forbidden { func: idx });
+                }
+                module.raw_sp = sp;
+                match raw_func(module, idx) {
+                    Some(callee) => {
+                        let limits = &module.config.limits;
+                        if limits.max_call_depth.is_some_and(|max| module.csp >= max) {
+                            return Err(Trap::StackOverflow);
+                        }
+                        let callee_fp = sp - callee.params;
+                        module.csp += 1;
+                        let res = exec(module, &callee, callee_fp);
+                        module.csp -= 1;
+                        res?;
+                        sp = callee_fp + callee.results.len();
+                    }
+                    None => {
+                        // 导入函数或不支持的函数，参数转为 WasmValue 后按原方式调用
+                        let ty = match &module.func[idx] {
+                            FuncKind::Import(ty, _) | FuncKind::Local((ty, _)) => *ty,
+                        };
+                        let params = module.section.types.entries[ty].params.clone();
+                        sp -= params.len();
+                        for (i, ty) in params.iter().enumerate() {
+                            module.sp += 1;
+                            module.stack_check();
+                            module.stack[module.sp] = to_value(stack!()[sp + i], *ty);
+                        }
+                        let res = module.call(idx)?;
+                        for v in res {
+                            push!(to_bits(v));
+                        }
+                    }
+                }
+                module.raw_sp = base;
+            }
+            RawOp::Load(bytes, signed, wide, memarg) => {
+                let mut v = load(module, &memarg, pop!(), bytes)?;
+                if signed {
+                    let shift = 64 - bytes as u32 * 8;
+                    v = ((v << shift) as i64 >> shift) as u64;
+                }
+                push!(if wide { v } else { v as u32 as u64 });
+            }
+            RawOp::Store(bytes, memarg) => {
+                let v = pop!();
+                let addr = (pop!() as u32) as u64 + memarg.offset as u64;
+                let stored = module
+                    .mem
+                    .get_mut(memarg.memory as usize)
+                    .is_some_and(|mem| mem.store(addr, &v.to_le_bytes()[..bytes as usize]));
+                if !stored {
+                    return Err(out_of_bounds(module, &memarg, addr));
+                }
+            }
+            RawOp::I32Eqz => stack!()[sp - 1] = (stack!()[sp - 1] as u32 == 0) as u64,
+            RawOp::I64Eqz => stack!()[sp - 1] = (stack!()[sp - 1] == 0) as u64,
+            RawOp::I32Bin(op) => {
+                let b = pop!() as u32;
+                let a = stack!()[sp - 1] as u32;
+                stack!()[sp - 1] = int_bin32(op, a, b) as u64;
+            }
+            RawOp::I64Bin(op) => {
+                let b = pop!();
+                let a = stack!()[sp - 1];
+                stack!()[sp - 1] = int_bin64(op, a, b);
+            }
+            RawOp::I32Cmp(op) => {
+                let b = pop!() as u32;
+                let a = stack!()[sp - 1] as u32;
+                let r = int_cmp(op, a as u64, b as u64, a as i32 as i64, b as i32 as i64);
+                stack!()[sp - 1] = r as u64;
+            }
+            RawOp::I64Cmp(op) => {
+                let b = pop!();
+                let a = stack!()[sp - 1];
+                stack!()[sp - 1] = int_cmp(op, a, b, a as i64, b as i64) as u64;
+            }
+            RawOp::I32Un(op) => stack!()[sp - 1] = int_un32(op, stack!()[sp - 1] as u32) as u64,
+            RawOp::I64Un(op) => stack!()[sp - 1] = int_un64(op, stack!()[sp - 1]),
+            RawOp::I32WrapI64 => stack!()[sp - 1] = stack!()[sp - 1] as u32 as u64,
+            RawOp::I64ExtendI32S => stack!()[sp - 1] = stack!()[sp - 1] as u32 as i32 as i64 as u64,
+            RawOp::I64ExtendI32U => {}
+        }
+    }
+}
+
+#[test]
+fn test_untyped_stack() {
+    use super::config::RuntimeConfig;
+    use super::testing::{invoke, leb_u32, vec, wasm};
+
+    // fib(n) = n < 2 ? n : fib(n - 1) + fib(n - 2)
+    let fib: &[u8] = &[
+        0x20, 0x00, 0x41, 0x02, 0x48, 0x04, 0x7f, 0x20, 0x00, 0x05, 0x20, 0x00, 0x41, 0x01, 0x6b,
+        0x10, 0x00, 0x20, 0x00, 0x41, 0x02, 0x6b, 0x10, 0x00, 0x6a, 0x0b,
+    ];
+    // i32.store8 (n, n * 3)；i32.load16_s (n)
+    let mem: &[u8] = &[
+        0x20, 0x00, 0x20, 0x00, 0x41, 0x03, 0x6c, 0x3a, 0x00, 0x00, 0x20, 0x00, 0x2e, 0x01, 0x00,
+    ];
+    // f32.add 不支持，整个函数使用带类型的栈：drop (1.5 + 0.5)；fib(n)
+    let float: &[u8] = &[
+        0x43, 0x00, 0x00, 0xc0, 0x3f, 0x43, 0x00, 0x00, 0x00, 0x3f, 0x92, 0x1a, 0x20, 0x00, 0x10,
+        0x00,
+    ];
+    let body = |code: &[u8]| {
+        let mut body = vec![0x00];
+        body.extend(code);
+        body.push(0x0b);
+        let mut buf = leb_u32(body.len() as u32);
+        buf.extend(body);
+        buf
+    };
+    let buf = wasm(&[
+        (1, vec(&[vec![0x60, 0x01, 0x7f, 0x01, 0x7f]])),
+        (3, vec(&[vec![0x00], vec![0x00], vec![0x00]])),
+        (5, vec(&[vec![0x00, 0x01]])),
+        (10, vec(&[body(fib), body(mem), body(float)])),
+    ]);
+
+    let mut results = vec![];
+    for untyped in [false, true] {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default().untyped_stack(untyped);
+        wasm.decode().unwrap();
+        wasm.instance(None).unwrap();
+        let fib = invoke(&mut wasm, 0, &[WasmValue::I32(20)]).unwrap();
+        let mem = invoke(&mut wasm, 1, &[WasmValue::I32(100)]).unwrap();
+        let float = invoke(&mut wasm, 2, &[WasmValue::I32(10)]).unwrap();
+        let oob = invoke(&mut wasm, 1, &[WasmValue::I32(65535)]).unwrap_err();
+        results.push((fib, mem, float, oob.to_string()));
+        if untyped {
+            assert!(wasm.raw_funcs[&0].is_some());
+            assert!(wasm.raw_funcs[&2].is_none());
+        }
+    }
+    assert_eq!(results[0].0, [WasmValue::I32(6765)]);
+    // 300 截断为 44，高字节为 0
+    assert_eq!(results[0].1, [WasmValue::I32(44)]);
+    assert_eq!(results[0], results[1]);
+}
@@ -0,0 +1,645 @@
+//! 函数体的类型检查：按规范附录中的验证算法跟踪操作数栈和控制栈，
+//! 检查操作数类型、块的结果、分支的标签以及各种索引
+//!
+//! 解码时已经检查了编码本身（操作码、标签深度、data count），这里只做类型相关的检查。
+//! 每个函数只报告第一处错误，错误的位置是出错指令在模块中的偏移。
+
+use std::fmt::Display;
+
+use anyhow::{bail, ensure};
+
+use super::decoder::{FuncKind, WasmModule};
+use super::section::import;
+use super::section::opcode::{BlockType, MemArg, Opcode};
+use super::section::types::CompositeType;
+use super::section::typings::{RefKind, ValueType};
+
+/// 函数体验证失败的原因和位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// 函数索引，包含导入的函数
+    pub func: usize,
+    /// 出错指令相对模块开头的偏移
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (function {})", self.message, self.func)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+struct Frame {
+    params: Vec<ValueType>,
+    results: Vec<ValueType>,
+    /// 进入块时操作数栈的高度
+    height: usize,
+    unreachable: bool,
+    is_loop: bool,
+    /// 还没有遇到 else 的 if
+    is_if: bool,
+}
+
+impl Frame {
+    /// 分支到这个块时需要的值
+    fn labels(&self) -> &[ValueType] {
+        match self.is_loop {
+            true => &self.params,
+            false => &self.results,
+        }
+    }
+}
+
+/// 模块中各种索引空间的类型，导入的在前
+struct Context<'a> {
+    module: &'a WasmModule,
+    funcs: Vec<usize>,
+    globals: Vec<(ValueType, bool)>,
+    tables: Vec<ValueType>,
+    memories: usize,
+}
+
+impl<'a> Context<'a> {
+    fn new(module: &'a WasmModule) -> Self {
+        let section = &module.section;
+        let mut ctx = Context {
+            module,
+            funcs: vec![],
+            globals: vec![],
+            tables: vec![],
+            memories: section.memory.entries.len(),
+        };
+        for ipt in &section.import.entries {
+            match &ipt.kind {
+                import::Kind::Func(ty) => ctx.funcs.push(*ty),
+                import::Kind::Table(0x6f, _) => ctx.tables.push(ValueType::ExternRef),
+                import::Kind::Table(..) => ctx.tables.push(ValueType::FuncRef),
+                import::Kind::Memory(_) => ctx.memories += 1,
+                import::Kind::Global(g) => ctx.globals.push((g.val_ty, g.mutability)),
+            }
+        }
+        ctx.funcs.extend(section.func.entries.iter().copied());
+        ctx.globals.extend(
+            section
+                .global
+                .entries
+                .iter()
+                .map(|g| (g.val_ty, g.mutability)),
+        );
+        ctx.tables
+            .extend(section.table.entries.iter().map(|t| match t.kind {
+                RefKind::FuncRef => ValueType::FuncRef,
+                RefKind::ExternRef => ValueType::ExternRef,
+            }));
+        ctx
+    }
+
+    fn func_type(&self, idx: u32) -> anyhow::Result<(&'a [ValueType], &'a [ValueType])> {
+        let Some(ty) = self.funcs.get(idx as usize) else {
+            bail!("unknown function {idx}");
+        };
+        self.ty(*ty as u32)
+    }
+
+    fn ty(&self, idx: u32) -> anyhow::Result<(&'a [ValueType], &'a [ValueType])> {
+        let types = &self.module.section.types;
+        let sub = types.subtypes.get(idx as usize);
+        match types.entries.get(idx as usize) {
+            _ if sub.is_some_and(|sub| sub.composite != CompositeType::Func) => {
+                bail!("type {idx} is not a function type")
+            }
+            Some(ty) => Ok((&ty.params, &ty.results)),
+            None => bail!("unknown type {idx}"),
+        }
+    }
+
+    fn block_type(&self, bt: &BlockType) -> anyhow::Result<(Vec<ValueType>, Vec<ValueType>)> {
+        Ok(match bt {
+            BlockType::NOP => (vec![], vec![]),
+            BlockType::ValueType(ty) => (vec![], vec![*ty]),
+            BlockType::Value(idx) => {
+                let (params, results) = self.ty(*idx)?;
+                (params.to_vec(), results.to_vec())
+            }
+        })
+    }
+
+    fn table(&self, idx: usize) -> anyhow::Result<ValueType> {
+        match self.tables.get(idx) {
+            Some(ty) => Ok(*ty),
+            None => bail!("unknown table {idx}"),
+        }
+    }
+
+    fn memory(&self, idx: u32) -> anyhow::Result<()> {
+        ensure!((idx as usize) < self.memories, "unknown memory {idx}");
+        Ok(())
+    }
+
+    /// 对齐不能超过访问的字节数
+    fn memarg(&self, memarg: &MemArg, bytes: u32) -> anyhow::Result<()> {
+        self.memory(memarg.memory)?;
+        ensure!(
+            1u64.checked_shl(memarg.align).unwrap_or(u64::MAX) <= bytes as u64,
+            "alignment must not be larger than natural"
+        );
+        Ok(())
+    }
+}
+
+/// 一个函数体的操作数栈和控制栈，None 表示不可达代码中类型未知的值
+struct Validator<'a> {
+    ctx: &'a Context<'a>,
+    locals: Vec<ValueType>,
+    values: Vec<Option<ValueType>>,
+    frames: Vec<Frame>,
+}
+
+impl Validator<'_> {
+    fn push(&mut self, ty: ValueType) {
+        self.values.push(Some(ty));
+    }
+
+    fn push_all(&mut self, types: &[ValueType]) {
+        types.iter().for_each(|ty| self.push(*ty));
+    }
+
+    fn pop(&mut self) -> anyhow::Result<Option<ValueType>> {
+        let frame = self.frames.last().unwrap();
+        if self.values.len() == frame.height {
+            ensure!(frame.unreachable, "type mismatch: operand stack is empty");
+            return Ok(None);
+        }
+        Ok(self.values.pop().unwrap())
+    }
+
+    fn pop_expect(&mut self, expected: ValueType) -> anyhow::Result<()> {
+        match self.pop()? {
+            Some(ty) if ty != expected => bail!("type mismatch: expected {expected}, found {ty}"),
+            _ => Ok(()),
+        }
+    }
+
+    fn pop_all(&mut self, types: &[ValueType]) -> anyhow::Result<()> {
+        for ty in types.iter().rev() {
+            self.pop_expect(*ty)?;
+        }
+        Ok(())
+    }
+
+    /// 弹出引用类型的值
+    /// 弹出一个引用，不可达代码中为 None
+    fn pop_ref(&mut self) -> anyhow::Result<Option<ValueType>> {
+        match self.pop()? {
+            Some(ty) if !matches!(ty, ValueType::FuncRef | ValueType::ExternRef) => {
+                bail!("type mismatch: expected a reference, found {ty}")
+            }
+            ty => Ok(ty),
+        }
+    }
+
+    fn push_frame(&mut self, params: Vec<ValueType>, results: Vec<ValueType>, op: &Opcode) {
+        let height = self.values.len();
+        self.push_all(&params);
+        self.frames.push(Frame {
+            params,
+            results,
+            height,
+            unreachable: false,
+            is_loop: matches!(op, Opcode::Loop(..)),
+            is_if: matches!(op, Opcode::If(..)),
+        });
+    }
+
+    /// 块结束时栈上必须正好是块的结果
+    fn pop_frame(&mut self) -> anyhow::Result<Frame> {
+        let results = self.frames.last().unwrap().results.clone();
+        self.pop_all(&results)?;
+        let frame = self.frames.pop().unwrap();
+        ensure!(
+            self.values.len() == frame.height,
+            "type mismatch: {} extra values at the end of the block",
+            self.values.len() - frame.height
+        );
+        Ok(frame)
+    }
+
+    fn set_unreachable(&mut self) {
+        let frame = self.frames.last_mut().unwrap();
+        self.values.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label(&self, label: usize) -> anyhow::Result<Vec<ValueType>> {
+        match self.frames.len().checked_sub(label + 1) {
+            Some(idx) => Ok(self.frames[idx].labels().to_vec()),
+            None => bail!("unknown label {label}"),
+        }
+    }
+
+    fn local(&self, idx: u32) -> anyhow::Result<ValueType> {
+        match self.locals.get(idx as usize) {
+            Some(ty) => Ok(*ty),
+            None => bail!("unknown local {idx}"),
+        }
+    }
+
+    fn global(&self, idx: u32) -> anyhow::Result<(ValueType, bool)> {
+        match self.ctx.globals.get(idx as usize) {
+            Some(global) => Ok(*global),
+            None => bail!("unknown global {idx}"),
+        }
+    }
+
+    fn load(&mut self, memarg: &MemArg, bytes: u32, ty: ValueType) -> anyhow::Result<()> {
+        self.ctx.memarg(memarg, bytes)?;
+        self.pop_expect(ValueType::I32)?;
+        self.push(ty);
+        Ok(())
+    }
+
+    fn store(&mut self, memarg: &MemArg, bytes: u32, ty: ValueType) -> anyhow::Result<()> {
+        self.ctx.memarg(memarg, bytes)?;
+        self.pop_expect(ty)?;
+        self.pop_expect(ValueType::I32)?;
+        Ok(())
+    }
+
+    /// ops 中 pc 处的指令，next 为下一条指令
+    fn step(&mut self, op: &Opcode, next: Option<&Opcode>) -> anyhow::Result<()> {
+        use Opcode::*;
+        use ValueType::*;
+        match op {
+            Unreachable => self.set_unreachable(),
+            Nop => {}
+            Block(bt, _) | Loop(bt, _) | If(bt, _) => {
+                if matches!(op, If(..)) {
+                    self.pop_expect(I32)?;
+                }
+                let (params, results) = self.ctx.block_type(bt)?;
+                self.pop_all(&params)?;
+                self.push_frame(params, results, op);
+            }
+            // 解码时在 else 前插入了跳到 if 结尾的 br，由 else 检查
+            Br(..) if matches!(next, Some(Else(_))) => {}
+            Else(_) => {
+                ensure!(
+                    self.frames.last().is_some_and(|frame| frame.is_if),
+                    "else without a matching if"
+                );
+                let frame = self.pop_frame()?;
+                self.push_frame(frame.params, frame.results, op);
+            }
+            End(_) => {
+                let frame = self.pop_frame()?;
+                // 没有 else 时 false 分支直接把参数作为结果
+                ensure!(
+                    !frame.is_if || frame.params == frame.results,
+                    "type mismatch: if without else must not change the stack"
+                );
+                self.push_all(&frame.results);
+            }
+            Br(label, _) => {
+                let types = self.label(*label)?;
+                self.pop_all(&types)?;
+                self.set_unreachable();
+            }
+            BrIf(label, _) => {
+                self.pop_expect(I32)?;
+                let types = self.label(*label)?;
+                self.pop_all(&types)?;
+                self.push_all(&types);
+            }
+            BrOnNull(label, _) => {
+                let ty = self.pop_ref()?;
+                let types = self.label(*label)?;
+                self.pop_all(&types)?;
+                self.push_all(&types);
+                self.values.push(ty);
+            }
+            BrOnNonNull(label, _) => {
+                self.pop_ref()?;
+                let mut types = self.label(*label)?;
+                ensure!(
+                    matches!(types.pop(), Some(FuncRef | ExternRef)),
+                    "type mismatch: br_on_non_null target must take a reference"
+                );
+                self.pop_all(&types)?;
+                self.push_all(&types);
+            }
+            BrTable(_, entries, default) => {
+                self.pop_expect(I32)?;
+                let expected = self.label(default.0)?;
+                for (label, _) in entries {
+                    let types = self.label(*label)?;
+                    ensure!(
+                        types.len() == expected.len(),
+                        "type mismatch: br_table labels have different arities"
+                    );
+                    // 每个标签都要检查栈顶的值，检查完恢复原状
+                    let values = self.values.clone();
+                    self.pop_all(&types)?;
+                    self.values = values;
+                }
+                self.pop_all(&expected)?;
+                self.set_unreachable();
+            }
+            Return => {
+                let results = self.frames[0].results.clone();
+                self.pop_all(&results)?;
+                self.set_unreachable();
+            }
+            Call(idx) => {
+                let (params, results) = self.ctx.func_type(*idx)?;
+                self.pop_all(params)?;
+                self.push_all(results);
+            }
+            CallIndirect(ty, table) => {
+                let elem = self.ctx.table(*table as usize)?;
+                ensure!(elem == FuncRef, "call_indirect on a table of {elem}");
+                let (params, results) = self.ctx.ty(*ty)?;
+                self.pop_expect(I32)?;
+                self.pop_all(params)?;
+                self.push_all(results);
+            }
+            CallRef(ty) => {
+                let (params, results) = self.ctx.ty(*ty)?;
+                self.pop_expect(FuncRef)?;
+                self.pop_all(params)?;
+                self.push_all(results);
+            }
+            RefAsNonNull => {
+                let ty = self.pop_ref()?;
+                self.values.push(ty);
+            }
+            RefNull(0x6f) => self.push(ExternRef),
+            RefNull(_) => self.push(FuncRef),
+            RefIsNull => {
+                self.pop_ref()?;
+                self.push(I32);
+            }
+            RefFunc(idx) => {
+                self.ctx.func_type(*idx)?;
+                self.push(FuncRef);
+            }
+            Drop => {
+                self.pop()?;
+            }
+            Select => {
+                self.pop_expect(I32)?;
+                let (b, a) = (self.pop()?, self.pop()?);
+                let ty = match (a, b) {
+                    (Some(a), Some(b)) if a != b => {
+                        bail!("type mismatch: select operands are {a} and {b}")
+                    }
+                    (a, b) => a.or(b),
+                };
+                if let Some(ty) = ty {
+                    ensure!(
+                        !matches!(ty, FuncRef | ExternRef),
+                        "type mismatch: select without a type can't choose {ty}"
+                    );
+                }
+                self.values.push(ty);
+            }
+            SelectType(_, types) => {
+                let ty = ValueType::from_u8(types[0] as u8)?;
+                self.pop_expect(I32)?;
+                self.pop_expect(ty)?;
+                self.pop_expect(ty)?;
+                self.push(ty);
+            }
+            LocalGet(idx) => {
+                let ty = self.local(*idx)?;
+                self.push(ty);
+            }
+            LocalSet(idx) => {
+                let ty = self.local(*idx)?;
+                self.pop_expect(ty)?;
+            }
+            LocalTee(idx) => {
+                let ty = self.local(*idx)?;
+                self.pop_expect(ty)?;
+                self.push(ty);
+            }
+            GlobalGet(idx) => {
+                let (ty, _) = self.global(*idx)?;
+                self.push(ty);
+            }
+            GlobalSet(idx) => {
+                let (ty, mutable) = self.global(*idx)?;
+                ensure!(mutable, "global {idx} is immutable");
+                self.pop_expect(ty)?;
+            }
+            TableGet(idx) => {
+                let ty = self.ctx.table(*idx as usize)?;
+                self.pop_expect(I32)?;
+                self.push(ty);
+            }
+            TableSet(idx) => {
+                let ty = self.ctx.table(*idx as usize)?;
+                self.pop_expect(ty)?;
+                self.pop_expect(I32)?;
+            }
+            I32Load(m) => self.load(m, 4, I32)?,
+            I64Load(m) => self.load(m, 8, I64)?,
+            F32Load(m) => self.load(m, 4, F32)?,
+            F64Load(m) => self.load(m, 8, F64)?,
+            I32Load8s(m) | I32Load8u(m) => self.load(m, 1, I32)?,
+            I32Load16s(m) | I32Load16u(m) => self.load(m, 2, I32)?,
+            I64Load8s(m) | I64Load8u(m) => self.load(m, 1, I64)?,
+            I64Load16s(m) | I64Load16u(m) => self.load(m, 2, I64)?,
+            I64Load32s(m) | I64Load32u(m) => self.load(m, 4, I64)?,
+            I32Store(m) => self.store(m, 4, I32)?,
+            I64Store(m) => self.store(m, 8, I64)?,
+            F32Store(m) => self.store(m, 4, F32)?,
+            F64Store(m) => self.store(m, 8, F64)?,
+            I32Store8(m) => self.store(m, 1, I32)?,
+            I32Store16(m) => self.store(m, 2, I32)?,
+            I64Store8(m) => self.store(m, 1, I64)?,
+            I64Store16(m) => self.store(m, 2, I64)?,
+            I64Store32(m) => self.store(m, 4, I64)?,
+            MemorySize(idx) => {
+                self.ctx.memory(*idx)?;
+                self.push(I32);
+            }
+            MemoryGrow(idx) => {
+                self.ctx.memory(*idx)?;
+                self.pop_expect(I32)?;
+                self.push(I32);
+            }
+            I32Const(_) => self.push(I32),
+            I64Const(_) => self.push(I64),
+            F32Const(_) => self.push(F32),
+            F64Const(_) => self.push(F64),
+            MemoryInit(_, idx) | MemoryFill(idx) => {
+                self.ctx.memory(*idx)?;
+                self.pop_all(&[I32, I32, I32])?;
+            }
+            MemoryCopy(dst, src) => {
+                self.ctx.memory(*dst)?;
+                self.ctx.memory(*src)?;
+                self.pop_all(&[I32, I32, I32])?;
+            }
+            DataDrop(_) => {}
+            TableInit(elem, table) => {
+                self.ctx.table(*table)?;
+                let count = self.ctx.module.section.element.entries.len();
+                ensure!(*elem < count, "unknown element segment {elem}");
+                self.pop_all(&[I32, I32, I32])?;
+            }
+            ElemDrop(elem) => {
+                let count = self.ctx.module.section.element.entries.len();
+                ensure!(*elem < count, "unknown element segment {elem}");
+            }
+            TableCopy(dst, src) => {
+                let (dst, src) = (self.ctx.table(*dst)?, self.ctx.table(*src)?);
+                ensure!(dst == src, "type mismatch: table.copy from {src} to {dst}");
+                self.pop_all(&[I32, I32, I32])?;
+            }
+            TableGrow(idx) => {
+                let ty = self.ctx.table(*idx)?;
+                self.pop_all(&[ty, I32])?;
+                self.push(I32);
+            }
+            TableSize(idx) => {
+                self.ctx.table(*idx)?;
+                self.push(I32);
+            }
+            TableFill(idx) => {
+                let ty = self.ctx.table(*idx)?;
+                self.pop_all(&[I32, ty, I32])?;
+            }
+            FD(_) => bail!("vector instructions are not supported"),
+            Reserved(op) => bail!("reserved opcode {op:#x}"),
+            op => {
+                let (Some(operand), Some(result)) = (op.operand_type(), op.result_type()) else {
+                    bail!("{op:?} is not supported");
+                };
+                for _ in 0..op.operand_count() {
+                    self.pop_expect(operand)?;
+                }
+                self.push(result);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WasmModule {
+    /// 检查所有已解码的函数体，延迟解码还没有解析的函数体跳过
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let ctx = Context::new(self);
+        let section = &self.section;
+        let imported = ctx.funcs.len() - section.func.entries.len();
+        let mut errors = vec![];
+        for (index, ty) in section.func.entries.iter().enumerate() {
+            let func = imported + index;
+            // 实例化之后函数体从 code 段移到了 `WasmModule::func` 中
+            let body = match self.func.get(func) {
+                Some(FuncKind::Local((_, body))) => body,
+                _ => match section.code.entries.get(index) {
+                    Some(body) => body,
+                    None => continue,
+                },
+            };
+            if body.pending || body.code.2 >= self.ops.len() {
+                continue;
+            }
+            let error = |pc: Option<usize>, message: String| ValidationError {
+                func,
+                offset: pc.and_then(|pc| section.code.op_offset(pc)),
+                message,
+            };
+            let (params, results) = match ctx.ty(*ty as u32) {
+                Ok(ty) => ty,
+                Err(err) => {
+                    errors.push(error(None, err.to_string()));
+                    continue;
+                }
+            };
+            let mut validator = Validator {
+                ctx: &ctx,
+                locals: params.to_vec(),
+                values: vec![],
+                frames: vec![Frame {
+                    params: vec![],
+                    results: results.to_vec(),
+                    height: 0,
+                    unreachable: false,
+                    is_loop: false,
+                    is_if: false,
+                }],
+            };
+            for (count, ty) in &body.locales {
+                validator
+                    .locals
+                    .extend(std::iter::repeat_n(*ty, *count as usize));
+            }
+            for pc in body.code.0..=body.code.2 {
+                let next = self.ops.get(pc + 1);
+                if let Err(err) = validator.step(&self.ops[pc], next) {
+                    errors.push(error(Some(pc), err.to_string()));
+                    break;
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[test]
+fn test_validate() {
+    use super::wat;
+
+    let errors = |src: &str| {
+        let mut wasm = WasmModule::default(wat::compile(src).unwrap());
+        wasm.decode().unwrap();
+        wasm.validate()
+    };
+    let valid = r#"(module
+      (memory 1)
+      (global $g (mut i64) (i64.const 0))
+      (func $f (param i32) (result i32 i64)
+        (block $out (result i32)
+          (local.get 0)
+          (loop $l (param i32) (result i32)
+            (br_if $out (i32.eqz (local.get 0)))
+            (br $l (i32.sub (local.get 0) (i32.const 1)))))
+        (if (param i32) (result i32) (i32.const 1)
+          (then (i32.add (i32.const 1)))
+          (else (i32.load8_u offset=3)))
+        (global.get $g))
+      (func (result i64)
+        (call $f (i32.const 3))
+        (drop)
+        (unreachable)
+        (i64.add)))"#;
+    assert_eq!(errors(valid), []);
+
+    let errors = errors(
+        r#"(module
+          (global i32 (i32.const 0))
+          (func (result i32) (i64.const 1))
+          (func (i32.add (i32.const 1) (f32.const 2)) (drop))
+          (func (global.set 0 (i32.const 1)))
+          (func (param i32) (if (local.get 0) (then (i32.const 1)))))"#,
+    );
+    let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "type mismatch: expected I32, found I64 (function 0) at offset 0x2c",
+            "type mismatch: expected I32, found F32 (function 1) at offset 0x36",
+            "global 0 is immutable (function 2) at offset 0x3d",
+            "type mismatch: 1 extra values at the end of the block (function 3) at offset 0x48",
+        ]
+    );
+}
@@ -0,0 +1,1187 @@
+//! wasi_snapshot_preview1 中命令行参数、环境变量、时钟、随机数、标准输入输出和 socket 相关的宿主函数
+//!
+//! 时钟、随机数和标准输入输出通过 `WasiClock`、`WasiRandom` 和 `WasiFile` 提供，
+//! 默认使用 std；嵌入方可以换成固定的时钟和带种子的随机数，使执行结果可以重现。
+//! guest 不能自己创建 socket，只能使用宿主预先打开的监听 socket 和从它接受的连接。
+
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+use super::decoder::{HostFunc, WasmModule, WasmValue};
+use super::linker::{Func, Linker};
+use super::memory::MemoryView;
+use super::section::typings::ValueType;
+use super::trap::Trap;
+
+/// WASI errno: Resource unavailable, or operation would block
+pub const ERRNO_AGAIN: i32 = 6;
+/// WASI errno: Bad file descriptor
+pub const ERRNO_BADF: i32 = 8;
+/// WASI errno: Connection reset
+pub const ERRNO_CONNRESET: i32 = 15;
+/// WASI errno: Bad address
+pub const ERRNO_FAULT: i32 = 21;
+/// WASI errno: Invalid argument
+pub const ERRNO_INVAL: i32 = 28;
+/// WASI errno: I/O error
+pub const ERRNO_IO: i32 = 29;
+/// WASI errno: The socket is not connected
+pub const ERRNO_NOTCONN: i32 = 53;
+/// WASI errno: Not a socket
+pub const ERRNO_NOTSOCK: i32 = 57;
+/// WASI errno: Broken pipe
+pub const ERRNO_PIPE: i32 = 64;
+/// WASI errno: Extension: Capabilities insufficient
+pub const ERRNO_NOTCAPABLE: i32 = 76;
+
+/// fdflags 中的 nonblock
+const FDFLAGS_NONBLOCK: u32 = 0x4;
+/// riflags
+const RIFLAGS_RECV_PEEK: u32 = 0x1;
+const RIFLAGS_RECV_WAITALL: u32 = 0x2;
+
+/// WASI 的时钟
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockId {
+    Realtime,
+    Monotonic,
+    ProcessCputime,
+    ThreadCputime,
+}
+
+impl ClockId {
+    pub fn from_u32(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(ClockId::Realtime),
+            1 => Some(ClockId::Monotonic),
+            2 => Some(ClockId::ProcessCputime),
+            3 => Some(ClockId::ThreadCputime),
+            _ => None,
+        }
+    }
+}
+
+/// 时钟的时间和精度都以纳秒为单位，返回 None 时 guest 得到 EINVAL
+pub trait WasiClock: Debug {
+    fn now(&mut self, id: ClockId) -> Option<u64>;
+    fn resolution(&mut self, _id: ClockId) -> Option<u64> {
+        Some(1)
+    }
+}
+
+/// 填充 random_get 的缓冲区
+pub trait WasiRandom: Debug {
+    fn fill(&mut self, buf: &mut [u8]);
+}
+
+/// 标准输入输出，默认不支持读写，guest 得到 EBADF
+pub trait WasiFile: Debug {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+pub type SharedClock = Arc<Mutex<dyn WasiClock + Send>>;
+pub type SharedRandom = Arc<Mutex<dyn WasiRandom + Send>>;
+pub type SharedFile = Arc<Mutex<dyn WasiFile + Send>>;
+
+/// 系统时钟：realtime 为 UNIX 时间，其余时钟从创建时开始计时
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl WasiClock for SystemClock {
+    fn now(&mut self, id: ClockId) -> Option<u64> {
+        let elapsed = match id {
+            ClockId::Realtime => SystemTime::now().duration_since(UNIX_EPOCH).ok()?,
+            _ => self.start.elapsed(),
+        };
+        u64::try_from(elapsed.as_nanos()).ok()
+    }
+}
+
+/// 固定的时钟：所有时钟都从 time 开始，每次读取后前进 step 纳秒
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock {
+    pub time: u64,
+    pub step: u64,
+}
+
+impl WasiClock for FixedClock {
+    fn now(&mut self, _id: ClockId) -> Option<u64> {
+        let time = self.time;
+        self.time = self.time.wrapping_add(self.step);
+        Some(time)
+    }
+}
+
+/// 由系统随机种子（`RandomState`）产生的随机数
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsRandom;
+
+impl WasiRandom for OsRandom {
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let value = RandomState::new().build_hasher().finish();
+            chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+/// 带种子的伪随机数（splitmix64），相同的种子得到相同的序列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl WasiRandom for SeededRandom {
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let value = self.next_u64();
+            chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+impl WasiFile for io::Stdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(self, buf)
+    }
+}
+
+impl WasiFile for io::Stdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = Write::write(self, buf)?;
+        self.flush()?;
+        Ok(n)
+    }
+}
+
+impl WasiFile for io::Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(self, buf)
+    }
+}
+
+/// 以任意的 `Write` 作为输出，例如文件、管道或 `Box<dyn Write + Send>`：
+/// `ctx.stdout(WriteFile(file))`
+pub struct WriteFile<W>(pub W);
+
+impl<W> Debug for WriteFile<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WriteFile")
+    }
+}
+
+impl<W: Write> WasiFile for WriteFile<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+}
+
+/// 收集写入的内容，clone 得到的 OutputCapture 共用同一个缓冲区；
+/// 作为标准输出或标准错误，在执行结束后取得 guest 的输出
+///
+/// ```ignore
+/// let stdout = OutputCapture::new();
+/// let config = RuntimeConfig::default().wasi(WasiCtx::new().stdout(stdout.clone()));
+/// // ...
+/// assert_eq!(stdout.contents(), "hello\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OutputCapture {
+    buf: Arc<Mutex<Vec<u8>>>,
+}
+
+impl OutputCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 到目前为止写入的字节
+    pub fn bytes(&self) -> Vec<u8> {
+        self.buf.lock().unwrap().clone()
+    }
+
+    /// 到目前为止写入的内容，不是 UTF-8 的部分替换为 U+FFFD
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buf.lock().unwrap()).into_owned()
+    }
+
+    /// 取出写入的字节并清空缓冲区
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.buf.lock().unwrap())
+    }
+}
+
+impl WasiFile for OutputCapture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+impl Write for OutputCapture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        WasiFile::write(self, buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WasiFile for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(self, buf)
+    }
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(self, buf)
+    }
+}
+
+/// 内存中的文件，可以作为预先准备好的标准输入，或收集写入的内容
+impl WasiFile for io::Cursor<Vec<u8>> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(self, buf)
+    }
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(self, buf)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Socket {
+    Listener(Arc<TcpListener>),
+    Stream(Arc<Mutex<TcpStream>>),
+}
+
+/// guest 可以使用的 socket，按 fd 存放
+#[derive(Debug, Default)]
+pub struct Sockets {
+    entries: BTreeMap<i32, Socket>,
+}
+
+impl Sockets {
+    /// 放到最大的 fd 之后（至少为 3），返回分配的 fd
+    pub fn insert(&mut self, socket: Socket) -> i32 {
+        let fd = self.entries.keys().next_back().map_or(3, |fd| fd + 1);
+        self.entries.insert(fd, socket);
+        fd
+    }
+
+    pub fn get(&self, fd: i32) -> Option<Socket> {
+        self.entries.get(&fd).cloned()
+    }
+
+    pub fn remove(&mut self, fd: i32) -> Option<Socket> {
+        self.entries.remove(&fd)
+    }
+}
+
+/// guest 看到的命令行参数、环境变量、时钟、随机数、标准输入输出和 socket，
+/// 通过 `RuntimeConfig::wasi` 设置
+///
+/// clone 得到的 WasiCtx 与原来共用时钟、随机数、文件和 socket。
+///
+/// ```ignore
+/// let ctx = WasiCtx::new().arg("app.wasm").arg("-v").env("HOME", "/");
+/// let ctx = ctx.clock(FixedClock { time: 0, step: 1000 }).random(SeededRandom::new(42));
+/// let config = RuntimeConfig::default().wasi(ctx);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WasiCtx {
+    /// argv，第一个通常是程序名
+    pub args: Vec<String>,
+    /// 环境变量，按设置的顺序传给 guest
+    pub env: Vec<(String, String)>,
+    pub clock: SharedClock,
+    pub random: SharedRandom,
+    pub stdin: SharedFile,
+    pub stdout: SharedFile,
+    pub stderr: SharedFile,
+    /// 从 fd 3 开始的 socket
+    pub sockets: Arc<Mutex<Sockets>>,
+    /// 是否允许 sock_accept 接受新连接，默认不允许
+    pub sock_accept: bool,
+}
+
+impl Default for WasiCtx {
+    fn default() -> Self {
+        Self {
+            args: vec![],
+            env: vec![],
+            clock: Arc::new(Mutex::new(SystemClock::default())),
+            random: Arc::new(Mutex::new(OsRandom)),
+            stdin: Arc::new(Mutex::new(io::stdin())),
+            stdout: Arc::new(Mutex::new(io::stdout())),
+            stderr: Arc::new(Mutex::new(io::stderr())),
+            sockets: Arc::default(),
+            sock_accept: false,
+        }
+    }
+}
+
+impl WasiCtx {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clock(mut self, clock: impl WasiClock + Send + 'static) -> Self {
+        self.clock = Arc::new(Mutex::new(clock));
+        self
+    }
+
+    pub fn random(mut self, random: impl WasiRandom + Send + 'static) -> Self {
+        self.random = Arc::new(Mutex::new(random));
+        self
+    }
+
+    pub fn stdin(mut self, file: impl WasiFile + Send + 'static) -> Self {
+        self.stdin = Arc::new(Mutex::new(file));
+        self
+    }
+
+    pub fn stdout(mut self, file: impl WasiFile + Send + 'static) -> Self {
+        self.stdout = Arc::new(Mutex::new(file));
+        self
+    }
+
+    pub fn stderr(mut self, file: impl WasiFile + Send + 'static) -> Self {
+        self.stderr = Arc::new(Mutex::new(file));
+        self
+    }
+
+    /// 预先打开监听 socket，guest 按调用的顺序从 fd 3 开始看到它们
+    pub fn tcp_listen(self, listener: TcpListener) -> Self {
+        self.sockets
+            .lock()
+            .unwrap()
+            .insert(Socket::Listener(Arc::new(listener)));
+        self
+    }
+
+    /// 允许 guest 用 sock_accept 从监听 socket 接受连接
+    pub fn allow_sock_accept(mut self, allow: bool) -> Self {
+        self.sock_accept = allow;
+        self
+    }
+
+    /// fd 0、1、2 对应的文件，以及已经接受的连接
+    fn file(&self, fd: i32) -> Option<SharedFile> {
+        match fd {
+            0 => Some(self.stdin.clone()),
+            1 => Some(self.stdout.clone()),
+            2 => Some(self.stderr.clone()),
+            _ => match self.sockets.lock().unwrap().get(fd)? {
+                Socket::Stream(stream) => Some(stream),
+                Socket::Listener(_) => None,
+            },
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn envs<K: Into<String>, V: Into<String>>(
+        mut self,
+        env: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.env
+            .extend(env.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// `KEY=VALUE` 形式的环境变量
+    fn environ(&self) -> Vec<String> {
+        self.env.iter().map(|(k, v)| format!("{k}={v}")).collect()
+    }
+}
+
+/// 参数中的 N 个 i32 指针
+fn pointers<const N: usize>(arg: &[WasmValue]) -> Option<[u32; N]> {
+    let mut out = [0; N];
+    for (slot, value) in out.iter_mut().zip(arg) {
+        match value {
+            WasmValue::I32(v) => *slot = *v as u32,
+            _ => return None,
+        }
+    }
+    (arg.len() >= N).then_some(out)
+}
+
+fn errno(result: anyhow::Result<()>) -> Vec<WasmValue> {
+    vec![WasmValue::I32(if result.is_ok() { 0 } else { ERRNO_FAULT })]
+}
+
+/// 写入字符串个数和以 \0 结尾的总字节数
+fn write_sizes(wasm: &mut WasmModule, list: &[String], arg: &[WasmValue]) -> Vec<WasmValue> {
+    let Some([count, size]) = pointers(arg) else {
+        return errno(Err(anyhow::anyhow!("bad arguments")));
+    };
+    let bytes = list.iter().map(|s| s.len() + 1).sum::<usize>();
+    errno(wasm.memory_view(0).and_then(|mut mem| {
+        mem.write_le(count, list.len() as u32)?;
+        mem.write_le(size, bytes as u32)
+    }))
+}
+
+/// 字符串依次以 \0 结尾写到 buf，每个字符串的地址写到 ptrs 数组
+fn write_strings(mem: &mut MemoryView, list: &[String], ptrs: u32, buf: u32) -> anyhow::Result<()> {
+    let mut addr = buf;
+    for (i, s) in list.iter().enumerate() {
+        mem.write_le(ptrs.wrapping_add(4 * i as u32), addr)?;
+        mem.write_bytes(addr, s.as_bytes())?;
+        addr = addr.wrapping_add(s.len() as u32);
+        mem.write_le(addr, 0u8)?;
+        addr = addr.wrapping_add(1);
+    }
+    Ok(())
+}
+
+fn write_list(wasm: &mut WasmModule, list: &[String], arg: &[WasmValue]) -> Vec<WasmValue> {
+    let Some([ptrs, buf]) = pointers(arg) else {
+        return errno(Err(anyhow::anyhow!("bad arguments")));
+    };
+    errno(
+        wasm.memory_view(0)
+            .and_then(|mut mem| write_strings(&mut mem, list, ptrs, buf)),
+    )
+}
+
+/// iovec { buf: u32, buf_len: u32 } 数组
+fn iovecs(mem: &MemoryView, iovs: u32, len: u32) -> anyhow::Result<Vec<(u32, u32)>> {
+    (0..len)
+        .map(|i| {
+            let iov: u32 = (iovs as u64 + i as u64 * 8).try_into()?;
+            let buf_len = iov.checked_add(4).context("iovec out of bounds")?;
+            Ok((mem.read_le(iov)?, mem.read_le(buf_len)?))
+        })
+        .collect()
+}
+
+fn io_errno(err: &io::Error) -> i32 {
+    match err.kind() {
+        io::ErrorKind::WouldBlock => ERRNO_AGAIN,
+        io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => ERRNO_CONNRESET,
+        io::ErrorKind::NotConnected => ERRNO_NOTCONN,
+        io::ErrorKind::BrokenPipe => ERRNO_PIPE,
+        io::ErrorKind::Unsupported => ERRNO_BADF,
+        io::ErrorKind::InvalidInput => ERRNO_FAULT,
+        _ => ERRNO_IO,
+    }
+}
+
+/// fd_read：读取标准输入或已经接受的连接
+pub fn fd_read(wasm: &mut WasmModule, arg: &[WasmValue]) -> Vec<WasmValue> {
+    fd_io(wasm, arg, false)
+}
+
+/// fd_write：写到标准输出、标准错误或已经接受的连接
+pub fn fd_write(wasm: &mut WasmModule, arg: &[WasmValue]) -> Vec<WasmValue> {
+    fd_io(wasm, arg, true)
+}
+
+/// 读写标准输入输出，返回 errno；读写到的字节数写到 done
+fn fd_io(wasm: &mut WasmModule, arg: &[WasmValue], write: bool) -> Vec<WasmValue> {
+    let (Some(&WasmValue::I32(fd)), Some([iovs, len, done])) = (arg.first(), pointers(&arg[1..]))
+    else {
+        return vec![WasmValue::I32(ERRNO_INVAL)];
+    };
+    let Some(file) = wasm.config.wasi.file(fd) else {
+        return vec![WasmValue::I32(ERRNO_BADF)];
+    };
+    let Ok(mut mem) = wasm.memory_view(0) else {
+        return vec![WasmValue::I32(ERRNO_FAULT)];
+    };
+    let Ok(iovs) = iovecs(&mem, iovs, len) else {
+        return vec![WasmValue::I32(ERRNO_FAULT)];
+    };
+    let mut file = file.lock().unwrap();
+    let mut total = 0u32;
+    for (ptr, len) in iovs {
+        let res = if write {
+            let Ok(bytes) = mem.read_bytes(ptr, len) else {
+                return vec![WasmValue::I32(ERRNO_FAULT)];
+            };
+            file.write(bytes)
+        } else {
+            let mut buf = vec![0; len as usize];
+            file.read(&mut buf).and_then(|n| {
+                mem.write_bytes(ptr, &buf[..n])
+                    .map(|_| n)
+                    .map_err(|_| io::ErrorKind::InvalidInput.into())
+            })
+        };
+        match res {
+            Ok(n) => {
+                total = total.wrapping_add(n as u32);
+                // 读到的比请求的少时不再继续（输入暂时没有更多数据）
+                if n < len as usize {
+                    break;
+                }
+            }
+            Err(err) => return vec![WasmValue::I32(io_errno(&err))],
+        }
+    }
+    errno(mem.write_le(done, total))
+}
+
+impl Linker {
+    /// 定义 args_get、args_sizes_get、environ_get 和 environ_sizes_get，
+    /// 内容来自实例配置中的 `WasiCtx`
+    pub fn define_wasi_environ(&mut self) -> anyhow::Result<&mut Self> {
+        use ValueType::I32;
+        let module = "wasi_snapshot_preview1";
+        let func = |func: HostFunc| Func::wrap(&[I32, I32], &[I32], func);
+        self.define(
+            module,
+            "args_sizes_get",
+            func(|wasm, arg| {
+                let args = wasm.config.wasi.args.clone();
+                write_sizes(wasm, &args, arg)
+            }),
+        )?
+        .define(
+            module,
+            "args_get",
+            func(|wasm, arg| {
+                let args = wasm.config.wasi.args.clone();
+                write_list(wasm, &args, arg)
+            }),
+        )?
+        .define(
+            module,
+            "environ_sizes_get",
+            func(|wasm, arg| {
+                let env = wasm.config.wasi.environ();
+                write_sizes(wasm, &env, arg)
+            }),
+        )?
+        .define(
+            module,
+            "environ_get",
+            func(|wasm, arg| {
+                let env = wasm.config.wasi.environ();
+                write_list(wasm, &env, arg)
+            }),
+        )
+    }
+
+    /// 定义 proc_exit：以 `Trap::Exit` 结束执行，`invoke` 和 `start` 返回这个 trap，
+    /// 不会结束宿主进程
+    pub fn define_wasi_proc_exit(&mut self) -> anyhow::Result<&mut Self> {
+        use ValueType::I32;
+        self.define(
+            "wasi_snapshot_preview1",
+            "proc_exit",
+            Func::wrap(&[I32], &[], |wasm, arg| match arg[..] {
+                [WasmValue::I32(code)] => wasm.raise(Trap::Exit { code }),
+                _ => vec![],
+            }),
+        )
+    }
+}
+
+impl Linker {
+    /// 定义 clock_time_get 和 clock_res_get，时间来自实例配置中 `WasiCtx` 的时钟
+    pub fn define_wasi_clock(&mut self) -> anyhow::Result<&mut Self> {
+        use ValueType::{I32, I64};
+        let module = "wasi_snapshot_preview1";
+        self.define(
+            module,
+            "clock_time_get",
+            Func::wrap(&[I32, I64, I32], &[I32], |wasm, arg| {
+                let (WasmValue::I32(id), WasmValue::I32(ptr)) = (arg[0], arg[2]) else {
+                    return vec![WasmValue::I32(ERRNO_INVAL)];
+                };
+                let clock = wasm.config.wasi.clock.clone();
+                let time =
+                    ClockId::from_u32(id as u32).and_then(|id| clock.lock().unwrap().now(id));
+                match time {
+                    Some(time) => errno(
+                        wasm.memory_view(0)
+                            .and_then(|mut mem| mem.write_le(ptr as u32, time)),
+                    ),
+                    None => vec![WasmValue::I32(ERRNO_INVAL)],
+                }
+            }),
+        )?
+        .define(
+            module,
+            "clock_res_get",
+            Func::wrap(&[I32, I32], &[I32], |wasm, arg| {
+                let Some([id, ptr]) = pointers(arg) else {
+                    return vec![WasmValue::I32(ERRNO_INVAL)];
+                };
+                let clock = wasm.config.wasi.clock.clone();
+                let res = ClockId::from_u32(id).and_then(|id| clock.lock().unwrap().resolution(id));
+                match res {
+                    Some(res) => errno(
+                        wasm.memory_view(0)
+                            .and_then(|mut mem| mem.write_le(ptr, res)),
+                    ),
+                    None => vec![WasmValue::I32(ERRNO_INVAL)],
+                }
+            }),
+        )
+    }
+
+    /// 定义 random_get，随机数来自实例配置中 `WasiCtx` 的随机数源
+    pub fn define_wasi_random(&mut self) -> anyhow::Result<&mut Self> {
+        use ValueType::I32;
+        self.define(
+            "wasi_snapshot_preview1",
+            "random_get",
+            Func::wrap(&[I32, I32], &[I32], |wasm, arg| {
+                let Some([ptr, len]) = pointers(arg) else {
+                    return vec![WasmValue::I32(ERRNO_INVAL)];
+                };
+                let mut buf = vec![0; len as usize];
+                wasm.config
+                    .wasi
+                    .random
+                    .clone()
+                    .lock()
+                    .unwrap()
+                    .fill(&mut buf);
+                errno(
+                    wasm.memory_view(0)
+                        .and_then(|mut mem| mem.write_bytes(ptr, &buf)),
+                )
+            }),
+        )
+    }
+
+    /// 定义 fd_read 和 fd_write，fd 0、1、2 读写实例配置中 `WasiCtx` 的标准输入输出
+    pub fn define_wasi_stdio(&mut self) -> anyhow::Result<&mut Self> {
+        use ValueType::I32;
+        let module = "wasi_snapshot_preview1";
+        let func = |func: HostFunc| Func::wrap(&[I32, I32, I32, I32], &[I32], func);
+        self.define(module, "fd_read", func(|wasm, arg| fd_read(wasm, arg)))?
+            .define(module, "fd_write", func(|wasm, arg| fd_write(wasm, arg)))
+    }
+}
+
+fn errno_result(result: Result<(), i32>) -> Vec<WasmValue> {
+    vec![WasmValue::I32(result.err().unwrap_or(0))]
+}
+
+/// fd 对应的 socket；标准输入输出得到 ENOTSOCK，没有打开的 fd 得到 EBADF
+fn socket(wasm: &WasmModule, fd: i32) -> Result<Socket, i32> {
+    match fd {
+        0..=2 => Err(ERRNO_NOTSOCK),
+        _ => wasm
+            .config
+            .wasi
+            .sockets
+            .lock()
+            .unwrap()
+            .get(fd)
+            .ok_or(ERRNO_BADF),
+    }
+}
+
+fn stream(wasm: &WasmModule, fd: i32) -> Result<Arc<Mutex<TcpStream>>, i32> {
+    match socket(wasm, fd)? {
+        Socket::Stream(stream) => Ok(stream),
+        Socket::Listener(_) => Err(ERRNO_NOTCONN),
+    }
+}
+
+/// sock_accept(fd, flags, ret_fd)：监听 socket 阻塞时等待连接，flags 带 nonblock 时
+/// 没有连接立即返回 EAGAIN，接受的连接也是非阻塞的
+fn sock_accept(wasm: &mut WasmModule, arg: &[WasmValue]) -> Result<(), i32> {
+    let [fd, flags, ret] = pointers(arg).ok_or(ERRNO_INVAL)?;
+    if !wasm.config.wasi.sock_accept {
+        return Err(ERRNO_NOTCAPABLE);
+    }
+    let Socket::Listener(listener) = socket(wasm, fd as i32)? else {
+        return Err(ERRNO_INVAL);
+    };
+    let nonblock = flags & FDFLAGS_NONBLOCK != 0;
+    // 接受连接时不持有 socket 表的锁，其他线程上的实例可以继续使用它
+    listener
+        .set_nonblocking(nonblock)
+        .map_err(|err| io_errno(&err))?;
+    let (stream, _) = listener.accept().map_err(|err| io_errno(&err))?;
+    stream
+        .set_nonblocking(nonblock)
+        .map_err(|err| io_errno(&err))?;
+    let sockets = wasm.config.wasi.sockets.clone();
+    // 先检查 ret_fd 可写，避免分配了 fd 而 guest 不知道
+    let mut mem = wasm.memory_view(0).map_err(|_| ERRNO_FAULT)?;
+    mem.read_le::<u32>(ret).map_err(|_| ERRNO_FAULT)?;
+    let new = sockets
+        .lock()
+        .unwrap()
+        .insert(Socket::Stream(Arc::new(Mutex::new(stream))));
+    mem.write_le(ret, new as u32).map_err(|_| ERRNO_FAULT)
+}
+
+/// sock_recv(fd, ri_data, ri_data_len, ri_flags, ro_datalen, ro_flags)：
+/// 一次读到所有 iovec 中，带 waitall 时读满或读到连接关闭为止
+fn sock_recv(wasm: &mut WasmModule, arg: &[WasmValue]) -> Result<(), i32> {
+    let [fd, iovs, len, flags, ro_len, ro_flags] = pointers(arg).ok_or(ERRNO_INVAL)?;
+    let stream = stream(wasm, fd as i32)?;
+    let mut mem = wasm.memory_view(0).map_err(|_| ERRNO_FAULT)?;
+    let iovs = iovecs(&mem, iovs, len).map_err(|_| ERRNO_FAULT)?;
+    let mut buf = vec![0; iovs.iter().map(|(_, len)| *len as usize).sum()];
+    let mut stream = stream.lock().unwrap();
+    let n = if flags & RIFLAGS_RECV_PEEK != 0 {
+        stream.peek(&mut buf)
+    } else if flags & RIFLAGS_RECV_WAITALL != 0 {
+        let mut n = 0;
+        loop {
+            match Read::read(&mut *stream, &mut buf[n..]) {
+                Ok(0) => break Ok(n),
+                Ok(m) => n += m,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => break Err(err),
+            }
+            if n == buf.len() {
+                break Ok(n);
+            }
+        }
+    } else {
+        Read::read(&mut *stream, &mut buf)
+    };
+    let n = n.map_err(|err| io_errno(&err))?;
+    let mut rest = &buf[..n];
+    for (ptr, len) in iovs {
+        let (head, tail) = rest.split_at(rest.len().min(len as usize));
+        mem.write_bytes(ptr, head).map_err(|_| ERRNO_FAULT)?;
+        rest = tail;
+    }
+    mem.write_le(ro_len, n as u32).map_err(|_| ERRNO_FAULT)?;
+    mem.write_le(ro_flags, 0u16).map_err(|_| ERRNO_FAULT)
+}
+
+/// sock_send(fd, si_data, si_data_len, si_flags, so_datalen)
+fn sock_send(wasm: &mut WasmModule, arg: &[WasmValue]) -> Result<(), i32> {
+    let [fd, iovs, len, _, so_len] = pointers(arg).ok_or(ERRNO_INVAL)?;
+    let stream = stream(wasm, fd as i32)?;
+    let mut mem = wasm.memory_view(0).map_err(|_| ERRNO_FAULT)?;
+    let mut buf = vec![];
+    for (ptr, len) in iovecs(&mem, iovs, len).map_err(|_| ERRNO_FAULT)? {
+        buf.extend_from_slice(mem.read_bytes(ptr, len).map_err(|_| ERRNO_FAULT)?);
+    }
+    let n = Write::write(&mut *stream.lock().unwrap(), &buf).map_err(|err| io_errno(&err))?;
+    mem.write_le(so_len, n as u32).map_err(|_| ERRNO_FAULT)
+}
+
+/// sock_shutdown(fd, how)：how 为 sdflags，1 关闭读，2 关闭写
+fn sock_shutdown(wasm: &mut WasmModule, arg: &[WasmValue]) -> Result<(), i32> {
+    let [fd, how] = pointers(arg).ok_or(ERRNO_INVAL)?;
+    let how = match how {
+        1 => Shutdown::Read,
+        2 => Shutdown::Write,
+        3 => Shutdown::Both,
+        _ => return Err(ERRNO_INVAL),
+    };
+    let stream = stream(wasm, fd as i32)?;
+    let result = stream.lock().unwrap().shutdown(how);
+    result.map_err(|err| io_errno(&err))
+}
+
+/// fd_close：只能关闭 socket，关闭标准输入输出没有效果
+fn fd_close(wasm: &mut WasmModule, arg: &[WasmValue]) -> Result<(), i32> {
+    let [fd] = pointers(arg).ok_or(ERRNO_INVAL)?;
+    match fd as i32 {
+        0..=2 => Ok(()),
+        fd => {
+            let mut sockets = wasm.config.wasi.sockets.lock().unwrap();
+            sockets.remove(fd).map(drop).ok_or(ERRNO_BADF)
+        }
+    }
+}
+
+impl Linker {
+    /// 定义 sock_accept、sock_recv、sock_send、sock_shutdown 和 fd_close，
+    /// socket 来自实例配置中的 `WasiCtx`；sock_accept 需要 `WasiCtx::allow_sock_accept`，
+    /// 否则 guest 得到 ENOTCAPABLE
+    pub fn define_wasi_sockets(&mut self) -> anyhow::Result<&mut Self> {
+        use ValueType::I32;
+        let module = "wasi_snapshot_preview1";
+        let func = |params: usize, func: HostFunc| Func::wrap(&vec![I32; params], &[I32], func);
+        self.define(
+            module,
+            "sock_accept",
+            func(3, |wasm, arg| errno_result(sock_accept(wasm, arg))),
+        )?
+        .define(
+            module,
+            "sock_recv",
+            func(6, |wasm, arg| errno_result(sock_recv(wasm, arg))),
+        )?
+        .define(
+            module,
+            "sock_send",
+            func(5, |wasm, arg| errno_result(sock_send(wasm, arg))),
+        )?
+        .define(
+            module,
+            "sock_shutdown",
+            func(2, |wasm, arg| errno_result(sock_shutdown(wasm, arg))),
+        )?
+        .define(
+            module,
+            "fd_close",
+            func(1, |wasm, arg| errno_result(fd_close(wasm, arg))),
+        )
+    }
+}
+
+#[test]
+fn test_wasi_environ() {
+    use super::config::RuntimeConfig;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "args_get" (func $args (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "environ_sizes_get" (func $env_sizes (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "environ_get" (func $env (param i32 i32) (result i32)))
+          (memory 1)
+          (func (export "main") (result i32)
+            (drop (call $args_sizes (i32.const 0) (i32.const 4)))
+            (drop (call $args (i32.const 16) (i32.const 64)))
+            (drop (call $env_sizes (i32.const 8) (i32.const 12)))
+            (call $env (i32.const 32) (i32.const 128)))
+          (func (export "fault") (result i32)
+            (call $args (i32.const 65535) (i32.const 64))))"#,
+    )
+    .unwrap();
+    let ctx = WasiCtx::new()
+        .arg("app.wasm")
+        .args(["-v", "输入"])
+        .env("HOME", "/")
+        .envs([("A", "1=2")]);
+    let mut wasm = WasmModule::default(buf);
+    wasm.config = RuntimeConfig::default().wasi(ctx);
+    wasm.decode().unwrap();
+    let mut linker = Linker::new();
+    linker.define_wasi_environ().unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+
+    assert_eq!(wasm.call(4).unwrap(), vec![WasmValue::I32(0)]);
+    let mem = wasm.memory_view(0).unwrap();
+    let u32_at = |ptr| mem.read_le::<u32>(ptr).unwrap();
+    assert_eq!((u32_at(0), u32_at(4)), (3, 9 + 3 + 7));
+    assert_eq!((u32_at(8), u32_at(12)), (2, 7 + 6));
+    let args = (0..3)
+        .map(|i| mem.read_cstr(u32_at(16 + 4 * i)).unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(args, ["app.wasm", "-v", "输入"]);
+    assert_eq!(u32_at(32), 128);
+    assert_eq!(mem.read_bytes(128, 13).unwrap(), b"HOME=/\0A=1=2\0");
+    // 指针数组越界时返回 EFAULT
+    assert_eq!(wasm.call(5).unwrap(), vec![WasmValue::I32(ERRNO_FAULT)]);
+}
+
+#[test]
+fn test_wasi_clock_random_stdio() {
+    use super::config::RuntimeConfig;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "wasi_snapshot_preview1" "clock_time_get" (func $time (param i32 i64 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "clock_res_get" (func $res (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "random_get" (func $random (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_read" (func $read (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_write" (func $write (param i32 i32 i32 i32) (result i32)))
+          (memory 1)
+          (func (export "time") (param i32) (result i32)
+            (call $time (local.get 0) (i64.const 0) (i32.const 0)))
+          (func (export "res") (result i32)
+            (call $res (i32.const 1) (i32.const 8)))
+          (func (export "random") (result i32)
+            (call $random (i32.const 16) (i32.const 12)))
+          ;; iovec { 64, 3 }、{ 72, 8 }
+          (func (export "read") (result i32)
+            (i32.store (i32.const 32) (i32.const 64))
+            (i32.store (i32.const 36) (i32.const 3))
+            (i32.store (i32.const 40) (i32.const 72))
+            (i32.store (i32.const 44) (i32.const 8))
+            (call $read (i32.const 0) (i32.const 32) (i32.const 2) (i32.const 48)))
+          (func (export "write") (param i32) (result i32)
+            (call $write (local.get 0) (i32.const 32) (i32.const 2) (i32.const 52))))"#,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define_wasi_clock()
+        .unwrap()
+        .define_wasi_random()
+        .unwrap()
+        .define_wasi_stdio()
+        .unwrap();
+
+    let run = || {
+        let stdout = Arc::new(Mutex::new(io::Cursor::new(vec![])));
+        let mut ctx = WasiCtx::new()
+            .clock(FixedClock {
+                time: 100,
+                step: 10,
+            })
+            .random(SeededRandom::new(7))
+            .stdin(io::Cursor::new(b"hello".to_vec()));
+        ctx.stdout = stdout.clone();
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default().wasi(ctx);
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).unwrap();
+        let ok = [WasmValue::I32(0)];
+        assert_eq!(wasm.invoke("time", &[WasmValue::I32(0)]).unwrap(), ok);
+        assert_eq!(wasm.memory_view(0).unwrap().read_le::<u64>(0).unwrap(), 100);
+        assert_eq!(wasm.invoke("time", &[WasmValue::I32(1)]).unwrap(), ok);
+        assert_eq!(wasm.memory_view(0).unwrap().read_le::<u64>(0).unwrap(), 110);
+        let inval = [WasmValue::I32(ERRNO_INVAL)];
+        assert_eq!(wasm.invoke("time", &[WasmValue::I32(9)]).unwrap(), inval);
+        assert_eq!(wasm.invoke("res", &[]).unwrap(), ok);
+        assert_eq!(wasm.memory_view(0).unwrap().read_le::<u64>(8).unwrap(), 1);
+        assert_eq!(wasm.invoke("random", &[]).unwrap(), ok);
+
+        assert_eq!(wasm.invoke("read", &[]).unwrap(), ok);
+        let mem = wasm.memory_view(0).unwrap();
+        assert_eq!(mem.read_le::<u32>(48).unwrap(), 5);
+        assert_eq!(mem.read_bytes(64, 3).unwrap(), b"hel");
+        assert_eq!(mem.read_bytes(72, 2).unwrap(), b"lo");
+        let random = mem.read_bytes(16, 12).unwrap().to_vec();
+
+        // 与读入时相同的 iovec，写出 "hel" 和 "lo\0\0\0\0\0\0"
+        assert_eq!(wasm.invoke("write", &[WasmValue::I32(1)]).unwrap(), ok);
+        assert_eq!(wasm.memory_view(0).unwrap().read_le::<u32>(52).unwrap(), 11);
+        let badf = [WasmValue::I32(ERRNO_BADF)];
+        assert_eq!(wasm.invoke("write", &[WasmValue::I32(5)]).unwrap(), badf);
+        let out = stdout.lock().unwrap().get_ref().clone();
+        assert_eq!(out, b"hello\0\0\0\0\0\0");
+        random
+    };
+    // 相同的种子得到相同的随机数
+    let random = run();
+    assert_eq!(random, run());
+    assert_ne!(random, [0; 12]);
+}
+
+#[test]
+fn test_wasi_sockets() {
+    use std::thread;
+
+    use super::config::RuntimeConfig;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "wasi_snapshot_preview1" "sock_accept" (func $accept (param i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "sock_recv" (func $recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "sock_send" (func $send (param i32 i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "sock_shutdown" (func $shutdown (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_close" (func $close (param i32) (result i32)))
+          (memory 1)
+          (func (export "accept") (param i32 i32) (result i32)
+            (call $accept (local.get 0) (local.get 1) (i32.const 0)))
+          ;; iovec { 64, 2 }、{ 72, 8 }，收到的字节数在 8，发送的字节数在 12
+          (func (export "recv") (param i32 i32) (result i32)
+            (i32.store (i32.const 32) (i32.const 64))
+            (i32.store (i32.const 36) (i32.const 2))
+            (i32.store (i32.const 40) (i32.const 72))
+            (i32.store (i32.const 44) (i32.const 8))
+            (call $recv (local.get 0) (i32.const 32) (i32.const 2) (local.get 1) (i32.const 8) (i32.const 4)))
+          (func (export "send") (param i32) (result i32)
+            (i32.store (i32.const 48) (i32.const 64))
+            (i32.store (i32.const 52) (i32.const 6))
+            (call $send (local.get 0) (i32.const 48) (i32.const 1) (i32.const 0) (i32.const 12)))
+          (func (export "shutdown") (param i32 i32) (result i32)
+            (call $shutdown (local.get 0) (local.get 1)))
+          (func (export "close") (param i32) (result i32)
+            (call $close (local.get 0))))"#,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker.define_wasi_sockets().unwrap();
+    let instance = |ctx: WasiCtx| {
+        let mut wasm = WasmModule::default(buf.clone());
+        wasm.config = RuntimeConfig::default().wasi(ctx);
+        wasm.decode().unwrap();
+        linker.instantiate(&mut wasm).unwrap();
+        wasm
+    };
+    let i32 = WasmValue::I32;
+    let call = |wasm: &mut WasmModule, name, args: &[i32]| {
+        let args = args.iter().map(|v| WasmValue::I32(*v)).collect::<Vec<_>>();
+        wasm.invoke(name, &args).unwrap()
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut wasm = instance(WasiCtx::new().tcp_listen(listener));
+    // 没有 sock_accept 权限
+    assert_eq!(call(&mut wasm, "accept", &[3, 0]), [i32(ERRNO_NOTCAPABLE)]);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr2 = listener.local_addr().unwrap();
+    assert_ne!(addr, addr2);
+    let mut wasm = instance(WasiCtx::new().tcp_listen(listener).allow_sock_accept(true));
+    assert_eq!(call(&mut wasm, "accept", &[0, 0]), [i32(ERRNO_NOTSOCK)]);
+    assert_eq!(call(&mut wasm, "accept", &[9, 0]), [i32(ERRNO_BADF)]);
+    let nonblock = FDFLAGS_NONBLOCK as i32;
+    assert_eq!(
+        call(&mut wasm, "accept", &[3, nonblock]),
+        [i32(ERRNO_AGAIN)]
+    );
+    assert_eq!(call(&mut wasm, "recv", &[3, 0]), [i32(ERRNO_NOTCONN)]);
+
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr2).unwrap();
+        stream.write_all(b"hello!").unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut reply = vec![];
+        stream.read_to_end(&mut reply).unwrap();
+        reply
+    });
+    assert_eq!(call(&mut wasm, "accept", &[3, 0]), [i32(0)]);
+    let fd = wasm.memory_view(0).unwrap().read_le::<u32>(0).unwrap() as i32;
+    assert_eq!(fd, 4);
+    // peek 不取走数据，waitall 读满两个 iovec 或读到连接关闭
+    let peek = RIFLAGS_RECV_PEEK as i32;
+    assert_eq!(call(&mut wasm, "recv", &[fd, peek]), [i32(0)]);
+    let waitall = RIFLAGS_RECV_WAITALL as i32;
+    assert_eq!(call(&mut wasm, "recv", &[fd, waitall]), [i32(0)]);
+    let mem = wasm.memory_view(0).unwrap();
+    assert_eq!(mem.read_le::<u32>(8).unwrap(), 6);
+    assert_eq!(mem.read_bytes(64, 2).unwrap(), b"he");
+    assert_eq!(mem.read_bytes(72, 4).unwrap(), b"llo!");
+
+    wasm.memory_view(0)
+        .unwrap()
+        .write_bytes(64, b"pong!!")
+        .unwrap();
+    assert_eq!(call(&mut wasm, "send", &[fd]), [i32(0)]);
+    assert_eq!(wasm.memory_view(0).unwrap().read_le::<u32>(12).unwrap(), 6);
+    assert_eq!(call(&mut wasm, "shutdown", &[fd, 9]), [i32(ERRNO_INVAL)]);
+    assert_eq!(call(&mut wasm, "shutdown", &[fd, 2]), [i32(0)]);
+    assert_eq!(client.join().unwrap(), b"pong!!");
+
+    assert_eq!(call(&mut wasm, "close", &[fd]), [i32(0)]);
+    assert_eq!(call(&mut wasm, "close", &[fd]), [i32(ERRNO_BADF)]);
+    assert_eq!(call(&mut wasm, "send", &[fd]), [i32(ERRNO_BADF)]);
+}
+
+#[test]
+fn test_wasi_output_capture() {
+    use super::config::RuntimeConfig;
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "wasi_snapshot_preview1" "fd_write" (func $write (param i32 i32 i32 i32) (result i32)))
+          (memory 1)
+          (data (i32.const 64) "out\nerr\n")
+          ;; iovec { 64, 4 } 和 { 68, 4 }
+          (func (export "main") (result i32)
+            (i32.store (i32.const 0) (i32.const 64))
+            (i32.store (i32.const 4) (i32.const 4))
+            (i32.store (i32.const 8) (i32.const 68))
+            (i32.store (i32.const 12) (i32.const 4))
+            (drop (call $write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 32)))
+            (drop (call $write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 32)))
+            (call $write (i32.const 2) (i32.const 8) (i32.const 1) (i32.const 32))))"#,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker.define_wasi_stdio().unwrap();
+
+    let (stdout, stderr) = (OutputCapture::new(), OutputCapture::new());
+    let mut wasm = WasmModule::default(buf.clone());
+    let ctx = WasiCtx::new().stdout(stdout.clone()).stderr(stderr.clone());
+    wasm.config = RuntimeConfig::default().wasi(ctx);
+    wasm.decode().unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+    assert_eq!(wasm.invoke("main", &[]).unwrap(), [WasmValue::I32(0)]);
+    // 输出原样保留，不追加换行
+    assert_eq!(stdout.contents(), "out\nout\n");
+    assert_eq!(stderr.take(), b"err\n");
+    assert!(stderr.bytes().is_empty());
+
+    let sink: Box<dyn Write + Send> = Box::new(stdout.clone());
+    let mut wasm = WasmModule::default(buf);
+    let ctx = WasiCtx::new()
+        .stdout(WriteFile(sink))
+        .stderr(WriteFile(io::sink()));
+    wasm.config = RuntimeConfig::default().wasi(ctx);
+    wasm.decode().unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+    assert_eq!(wasm.invoke("main", &[]).unwrap(), [WasmValue::I32(0)]);
+    assert_eq!(stdout.contents(), "out\nout\nout\nout\n");
+}
+
+#[test]
+fn test_wasi_proc_exit() {
+    use super::wat;
+
+    let buf = wat::compile(
+        r#"(module
+          (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+          (func (export "_start") (call $exit (i32.const 3)) unreachable)
+          (func (export "ok") (result i32) (i32.const 1)))"#,
+    )
+    .unwrap();
+    let mut linker = Linker::new();
+    linker.define_wasi_proc_exit().unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+    let err = wasm.start().unwrap_err();
+    assert_eq!(Trap::exit_code(&err), Some(3));
+    // 退出后实例仍然可以调用
+    assert_eq!(wasm.invoke("ok", &[]).unwrap(), [WasmValue::I32(1)]);
+    assert_eq!(Trap::exit_code(&anyhow::anyhow!("other")), None);
+}
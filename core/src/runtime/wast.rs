@@ -0,0 +1,571 @@
+//! .wast 脚本的词法、语法分析
+//!
+//! 脚本由 S 表达式组成：模块定义（文本、`binary`、`quote`）、`register`、
+//! `invoke`/`get` 动作以及各种 `assert_*` 断言。模块文本由 `wat` 编译。
+
+use anyhow::{bail, ensure, Context};
+
+use super::decoder::WasmValue;
+use super::literal::parse_value;
+use super::section::typings::ValueType;
+
+/// S 表达式，带起始行号（从 1 开始）
+#[derive(Debug, Clone, PartialEq)]
+pub enum SExpr {
+    /// 关键字、标识符（`$x`）、数字等
+    Atom(String, usize),
+    Str(Vec<u8>, usize),
+    List(Vec<SExpr>, usize),
+}
+
+impl SExpr {
+    pub fn line(&self) -> usize {
+        match self {
+            SExpr::Atom(_, line) | SExpr::Str(_, line) | SExpr::List(_, line) => *line,
+        }
+    }
+
+    pub fn atom(&self) -> Option<&str> {
+        match self {
+            SExpr::Atom(atom, _) => Some(atom),
+            _ => None,
+        }
+    }
+
+    pub fn string(&self) -> Option<&[u8]> {
+        match self {
+            SExpr::Str(bytes, _) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn list(&self) -> Option<&[SExpr]> {
+        match self {
+            SExpr::List(items, _) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// 列表的第一个元素（关键字）
+    pub fn head(&self) -> Option<&str> {
+        self.list()?.first()?.atom()
+    }
+
+    /// `$` 开头的标识符
+    pub fn id(&self) -> Option<&str> {
+        self.atom().filter(|atom| atom.starts_with('$'))
+    }
+
+    /// UTF-8 字符串，用于导出名、模块名
+    pub fn name(&self) -> anyhow::Result<String> {
+        let bytes = self
+            .string()
+            .with_context(|| format!("line {}: expected a string", self.line()))?;
+        String::from_utf8(bytes.to_vec())
+            .with_context(|| format!("line {}: malformed UTF-8 encoding", self.line()))
+    }
+}
+
+struct Lexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+fn is_idchar(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&c)
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == b'\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// 跳过空白、行注释和（可嵌套的）块注释
+    fn skip(&mut self) -> anyhow::Result<()> {
+        loop {
+            match (self.peek(), self.src.get(self.pos + 1)) {
+                (Some(c), _) if c.is_ascii_whitespace() => {
+                    self.bump();
+                }
+                (Some(b';'), Some(b';')) => {
+                    while self.peek().is_some_and(|c| c != b'\n') {
+                        self.bump();
+                    }
+                }
+                (Some(b'('), Some(b';')) => {
+                    let line = self.line;
+                    let mut depth = 0;
+                    loop {
+                        match (self.bump(), self.peek()) {
+                            (Some(b'('), Some(b';')) => {
+                                self.bump();
+                                depth += 1;
+                            }
+                            (Some(b';'), Some(b')')) => {
+                                self.bump();
+                                depth -= 1;
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            (None, _) => bail!("line {line}: unterminated block comment"),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn string(&mut self) -> anyhow::Result<Vec<u8>> {
+        let line = self.line;
+        let mut out = vec![];
+        self.bump();
+        loop {
+            let c = self
+                .bump()
+                .with_context(|| format!("line {line}: unterminated string"))?;
+            match c {
+                b'"' => return Ok(out),
+                b'\\' => {
+                    let c = self
+                        .bump()
+                        .with_context(|| format!("line {line}: unterminated string"))?;
+                    match c {
+                        b't' => out.push(b'\t'),
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b'"' | b'\'' | b'\\' => out.push(c),
+                        b'u' => {
+                            ensure!(self.bump() == Some(b'{'), "line {line}: malformed escape");
+                            let mut hex = String::new();
+                            while let Some(c) = self.bump().filter(|c| *c != b'}') {
+                                hex.push(c as char);
+                            }
+                            let ch = u32::from_str_radix(&hex.replace('_', ""), 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .with_context(|| format!("line {line}: malformed escape"))?;
+                            out.extend(ch.to_string().bytes());
+                        }
+                        c if c.is_ascii_hexdigit() => {
+                            let d = self
+                                .bump()
+                                .filter(u8::is_ascii_hexdigit)
+                                .with_context(|| format!("line {line}: malformed escape"))?;
+                            let hex = [c, d];
+                            let hex = std::str::from_utf8(&hex).unwrap();
+                            out.push(u8::from_str_radix(hex, 16).unwrap());
+                        }
+                        c => bail!("line {line}: unknown escape \\{}", c as char),
+                    }
+                }
+                b'\n' => bail!("line {line}: newline in string"),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn expr(&mut self) -> anyhow::Result<Option<SExpr>> {
+        self.skip()?;
+        let line = self.line;
+        let expr = match self.peek() {
+            None | Some(b')') => return Ok(None),
+            Some(b'(') => {
+                self.bump();
+                let mut items = vec![];
+                while let Some(item) = self.expr()? {
+                    items.push(item);
+                }
+                ensure!(
+                    self.bump() == Some(b')'),
+                    "line {line}: unclosed parenthesis"
+                );
+                SExpr::List(items, line)
+            }
+            Some(b'"') => SExpr::Str(self.string()?, line),
+            Some(c) if is_idchar(c) => {
+                let start = self.pos;
+                while self.peek().is_some_and(is_idchar) {
+                    self.bump();
+                }
+                let atom = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+                SExpr::Atom(atom.to_string(), line)
+            }
+            Some(c) => bail!("line {line}: unexpected character {:?}", c as char),
+        };
+        Ok(Some(expr))
+    }
+}
+
+/// 解析整个文件为 S 表达式序列
+pub fn parse(src: &str) -> anyhow::Result<Vec<SExpr>> {
+    let mut lexer = Lexer {
+        src: src.as_bytes(),
+        pos: 0,
+        line: 1,
+    };
+    let mut exprs = vec![];
+    while let Some(expr) = lexer.expr()? {
+        exprs.push(expr);
+    }
+    ensure!(
+        lexer.peek().is_none(),
+        "line {}: unexpected `)`",
+        lexer.line
+    );
+    Ok(exprs)
+}
+
+#[derive(Debug, Clone)]
+pub enum ModuleSource {
+    Text(SExpr),
+    Binary(Vec<u8>),
+    /// `(module quote ...)`，内容为模块文本
+    Quote(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleDef {
+    pub name: Option<String>,
+    pub source: ModuleSource,
+}
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    Invoke {
+        module: Option<String>,
+        name: String,
+        args: Vec<WasmValue>,
+    },
+    Get {
+        module: Option<String>,
+        name: String,
+    },
+}
+
+/// assert_return 期望的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    /// 按位比较（浮点数也按位比较）
+    Value(WasmValue),
+    CanonicalNan(ValueType),
+    ArithmeticNan(ValueType),
+    /// 任意一个匹配即可
+    Either(Vec<Expected>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleAssert {
+    Invalid,
+    Malformed,
+    Unlinkable,
+    /// 实例化时（start 函数）trap
+    Uninstantiable,
+}
+
+#[derive(Debug, Clone)]
+pub enum Directive {
+    Module(ModuleDef),
+    Register {
+        name: String,
+        module: Option<String>,
+    },
+    Action(Action),
+    AssertReturn {
+        action: Action,
+        expected: Vec<Expected>,
+    },
+    AssertTrap {
+        action: Action,
+        message: String,
+    },
+    AssertExhaustion {
+        action: Action,
+        message: String,
+    },
+    AssertModule {
+        kind: ModuleAssert,
+        module: ModuleDef,
+        message: String,
+    },
+    /// 使用了尚不支持的写法（引用类型、SIMD 等），跳过
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub line: usize,
+    pub directive: Directive,
+}
+
+fn module_def(expr: &SExpr) -> anyhow::Result<ModuleDef> {
+    let items = expr.list().unwrap_or_default();
+    let line = expr.line();
+    let mut rest = &items[1..];
+    let name = rest.first().and_then(SExpr::id).map(str::to_string);
+    if name.is_some() {
+        rest = &rest[1..];
+    }
+    let source = match rest.first().and_then(SExpr::atom) {
+        Some("binary") => {
+            let mut bytes = vec![];
+            for item in &rest[1..] {
+                let s = item
+                    .string()
+                    .with_context(|| format!("line {line}: expected a string"))?;
+                bytes.extend(s);
+            }
+            ModuleSource::Binary(bytes)
+        }
+        Some("quote") => {
+            let mut text = String::new();
+            for item in &rest[1..] {
+                text.push_str(&item.name()?);
+                text.push(' ');
+            }
+            ModuleSource::Quote(text)
+        }
+        _ => ModuleSource::Text(expr.clone()),
+    };
+    Ok(ModuleDef { name, source })
+}
+
+/// `(i32.const 1)` 等常量
+fn constant(expr: &SExpr) -> anyhow::Result<Option<WasmValue>> {
+    let line = expr.line();
+    let items = expr.list().unwrap_or_default();
+    let ty = match expr.head() {
+        Some("i32.const") => ValueType::I32,
+        Some("i64.const") => ValueType::I64,
+        Some("f32.const") => ValueType::F32,
+        Some("f64.const") => ValueType::F64,
+        Some(_) => return Ok(None),
+        None => bail!("line {line}: expected a constant"),
+    };
+    let text = items
+        .get(1)
+        .and_then(SExpr::atom)
+        .with_context(|| format!("line {line}: missing constant value"))?;
+    parse_value(text, ty)
+        .map(Some)
+        .with_context(|| format!("line {line}: bad constant"))
+}
+
+fn expected(expr: &SExpr) -> anyhow::Result<Option<Expected>> {
+    let items = expr.list().unwrap_or_default();
+    if expr.head() == Some("either") {
+        let options = items[1..]
+            .iter()
+            .map(expected)
+            .collect::<anyhow::Result<Option<Vec<_>>>>()?;
+        return Ok(options.map(Expected::Either));
+    }
+    let nan = |ty| match items.get(1).and_then(SExpr::atom) {
+        Some("nan:canonical") => Some(Expected::CanonicalNan(ty)),
+        Some("nan:arithmetic") => Some(Expected::ArithmeticNan(ty)),
+        _ => None,
+    };
+    match expr.head() {
+        Some("f32.const") if nan(ValueType::F32).is_some() => Ok(nan(ValueType::F32)),
+        Some("f64.const") if nan(ValueType::F64).is_some() => Ok(nan(ValueType::F64)),
+        _ => Ok(constant(expr)?.map(Expected::Value)),
+    }
+}
+
+/// 返回 None 表示动作中有不支持的参数
+fn action(expr: &SExpr) -> anyhow::Result<Option<Action>> {
+    let line = expr.line();
+    let items = expr.list().unwrap_or_default();
+    let mut rest = items.get(1..).unwrap_or_default();
+    let module = rest.first().and_then(SExpr::id).map(str::to_string);
+    if module.is_some() {
+        rest = &rest[1..];
+    }
+    let name = rest
+        .first()
+        .with_context(|| format!("line {line}: missing export name"))?
+        .name()?;
+    let action = match expr.head() {
+        Some("invoke") => {
+            let mut args = vec![];
+            for arg in &rest[1..] {
+                match constant(arg)? {
+                    Some(arg) => args.push(arg),
+                    None => return Ok(None),
+                }
+            }
+            Action::Invoke { module, name, args }
+        }
+        Some("get") => Action::Get { module, name },
+        _ => bail!("line {line}: expected invoke or get"),
+    };
+    Ok(Some(action))
+}
+
+fn message(items: &[SExpr], index: usize) -> anyhow::Result<String> {
+    match items.get(index) {
+        Some(item) => item.name(),
+        None => Ok(String::new()),
+    }
+}
+
+fn directive(expr: &SExpr) -> anyhow::Result<Directive> {
+    let line = expr.line();
+    let items = expr.list().unwrap_or_default();
+    let unsupported = |what: &str| Directive::Unsupported(what.to_string());
+    let directive = match expr.head() {
+        Some("module") => Directive::Module(module_def(expr)?),
+        Some("register") => Directive::Register {
+            name: items
+                .get(1)
+                .with_context(|| format!("line {line}: missing name"))?
+                .name()?,
+            module: items.get(2).and_then(SExpr::id).map(str::to_string),
+        },
+        Some("invoke") | Some("get") => match action(expr)? {
+            Some(action) => Directive::Action(action),
+            None => unsupported("reference or vector argument"),
+        },
+        Some("assert_return") => {
+            let target = items
+                .get(1)
+                .with_context(|| format!("line {line}: missing action"))?;
+            let expected = items[2..]
+                .iter()
+                .map(expected)
+                .collect::<anyhow::Result<Option<Vec<_>>>>()?;
+            match (action(target)?, expected) {
+                (Some(action), Some(expected)) => Directive::AssertReturn { action, expected },
+                _ => unsupported("reference or vector value"),
+            }
+        }
+        Some("assert_trap") | Some("assert_exhaustion") => {
+            let target = items
+                .get(1)
+                .with_context(|| format!("line {line}: missing action"))?;
+            let message = message(items, 2)?;
+            if target.head() == Some("module") {
+                Directive::AssertModule {
+                    kind: ModuleAssert::Uninstantiable,
+                    module: module_def(target)?,
+                    message,
+                }
+            } else {
+                match action(target)? {
+                    Some(action) if expr.head() == Some("assert_trap") => {
+                        Directive::AssertTrap { action, message }
+                    }
+                    Some(action) => Directive::AssertExhaustion { action, message },
+                    None => unsupported("reference or vector argument"),
+                }
+            }
+        }
+        Some(
+            head @ ("assert_invalid"
+            | "assert_malformed"
+            | "assert_unlinkable"
+            | "assert_uninstantiable"),
+        ) => {
+            let kind = match head {
+                "assert_invalid" => ModuleAssert::Invalid,
+                "assert_malformed" => ModuleAssert::Malformed,
+                "assert_unlinkable" => ModuleAssert::Unlinkable,
+                _ => ModuleAssert::Uninstantiable,
+            };
+            let module = items
+                .get(1)
+                .with_context(|| format!("line {line}: missing module"))?;
+            Directive::AssertModule {
+                kind,
+                module: module_def(module)?,
+                message: message(items, 2)?,
+            }
+        }
+        Some(head) => unsupported(head),
+        None => bail!("line {line}: expected a command"),
+    };
+    Ok(directive)
+}
+
+/// 解析 .wast 脚本
+pub fn parse_script(src: &str) -> anyhow::Result<Vec<Command>> {
+    parse(src)?
+        .iter()
+        .map(|expr| {
+            Ok(Command {
+                line: expr.line(),
+                directive: directive(expr)?,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_script() {
+    let script = r#"
+        ;; comment
+        (module $m (func (export "f") (param i32) (result i32) (local.get 0)))
+        (; block (; nested ;) comment ;)
+        (module binary "\00asm" "\01\00\00\00")
+        (assert_return (invoke $m "f" (i32.const -1)) (i32.const 0xffffffff))
+        (assert_return (invoke "g") (either (f32.const nan:canonical) (f32.const 1.5)))
+        (assert_trap (invoke "f" (i32.const 0)) "unreachable")
+        (assert_malformed (module quote "(func)" "(x)") "unknown operator")
+        (register "M" $m)
+        (assert_return (invoke "h" (ref.null func)))
+    "#;
+    let commands = parse_script(script).unwrap();
+    assert_eq!(commands.len(), 8);
+    assert_eq!(commands[0].line, 3);
+    match &commands[1].directive {
+        Directive::Module(ModuleDef {
+            source: ModuleSource::Binary(bytes),
+            name: None,
+        }) => assert_eq!(bytes, b"\0asm\x01\0\0\0"),
+        d => panic!("{d:?}"),
+    }
+    match &commands[2].directive {
+        Directive::AssertReturn {
+            action: Action::Invoke { module, args, .. },
+            expected,
+        } => {
+            assert_eq!(module.as_deref(), Some("$m"));
+            assert_eq!(args, &[WasmValue::I32(-1)]);
+            assert_eq!(expected, &[Expected::Value(WasmValue::I32(-1))]);
+        }
+        d => panic!("{d:?}"),
+    }
+    match &commands[3].directive {
+        Directive::AssertReturn { expected, .. } => assert_eq!(
+            expected,
+            &[Expected::Either(vec![
+                Expected::CanonicalNan(ValueType::F32),
+                Expected::Value(WasmValue::F32(1.5))
+            ])]
+        ),
+        d => panic!("{d:?}"),
+    }
+    assert!(matches!(
+        &commands[5].directive,
+        Directive::AssertModule {
+            kind: ModuleAssert::Malformed,
+            module: ModuleDef { source: ModuleSource::Quote(text), .. },
+            message,
+        } if text == "(func) (x) " && message == "unknown operator"
+    ));
+    assert!(matches!(&commands[7].directive, Directive::Unsupported(_)));
+    assert!(parse("(module").is_err());
+    assert!(parse("(; unterminated").is_err());
+}
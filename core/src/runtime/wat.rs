@@ -0,0 +1,1590 @@
+//! 文本格式（.wat）到二进制的编译，供 .wast 脚本中的文本模块使用
+//!
+//! 支持 MVP 指令以及符号扩展、饱和截断、批量内存和引用类型指令，
+//! 指令可以是平铺或折叠写法；function-references 提案只支持指令，不支持 (ref $t) 类型；
+//! 不支持 SIMD 等其它提案。
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, ensure, Context};
+
+use crate::leb::{encode_leb_i64 as leb_i64, encode_leb_u32 as leb_u32};
+
+use super::decoder::WasmValue;
+use super::literal::parse_value;
+use super::section::typings::ValueType;
+use super::wast::{parse, SExpr};
+
+type FuncType = (Vec<ValueType>, Vec<ValueType>);
+
+fn name(text: &str, out: &mut Vec<u8>) {
+    leb_u32(text.len() as u32, out);
+    out.extend(text.as_bytes());
+}
+
+fn value_type_byte(ty: ValueType) -> u8 {
+    match ty {
+        ValueType::ExternRef => 0x6f,
+        ValueType::FuncRef => 0x70,
+        ValueType::I32 => 0x7f,
+        ValueType::I64 => 0x7e,
+        ValueType::F32 => 0x7d,
+        ValueType::F64 => 0x7c,
+        ValueType::V128 => 0x7b,
+        ValueType::AnyRef => 0x6e,
+    }
+}
+
+fn value_type(expr: &SExpr) -> anyhow::Result<ValueType> {
+    let ty = match expr {
+        SExpr::Atom(atom, _) => match atom.as_str() {
+            "i32" => ValueType::I32,
+            "i64" => ValueType::I64,
+            "f32" => ValueType::F32,
+            "f64" => ValueType::F64,
+            "v128" => ValueType::V128,
+            "funcref" => ValueType::FuncRef,
+            "externref" => ValueType::ExternRef,
+            _ => bail!("line {}: unknown value type `{atom}`", expr.line()),
+        },
+        SExpr::List(items, line) => match items.iter().map(SExpr::atom).collect::<Vec<_>>()[..] {
+            [Some("ref"), Some("null"), Some("func")] => ValueType::FuncRef,
+            [Some("ref"), Some("null"), Some("extern")] => ValueType::ExternRef,
+            _ => bail!("line {line}: unsupported reference type"),
+        },
+        SExpr::Str(_, line) => bail!("line {line}: unexpected string"),
+    };
+    Ok(ty)
+}
+
+fn heap_type(expr: &SExpr) -> anyhow::Result<u8> {
+    match expr.atom() {
+        Some("func") => Ok(0x70),
+        Some("extern") => Ok(0x6f),
+        _ => bail!("line {}: unknown heap type", expr.line()),
+    }
+}
+
+fn number(expr: &SExpr) -> anyhow::Result<u32> {
+    let atom = expr
+        .atom()
+        .with_context(|| format!("line {}: expected a number", expr.line()))?;
+    let digits = atom.replace('_', "");
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+    value.with_context(|| format!("line {}: malformed number `{atom}`", expr.line()))
+}
+
+fn is_index(expr: &SExpr) -> bool {
+    expr.atom()
+        .is_some_and(|atom| atom.starts_with('$') || atom.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// 一个索引空间：按声明顺序编号，可以用 `$name` 引用
+#[derive(Debug, Default)]
+struct Space {
+    names: HashMap<String, u32>,
+    count: u32,
+}
+
+impl Space {
+    fn add(&mut self, id: Option<&str>, line: usize) -> anyhow::Result<u32> {
+        let idx = self.count;
+        if let Some(id) = id {
+            ensure!(
+                self.names.insert(id.to_string(), idx).is_none(),
+                "line {line}: duplicate identifier {id}"
+            );
+        }
+        self.count += 1;
+        Ok(idx)
+    }
+
+    fn resolve(&self, expr: &SExpr) -> anyhow::Result<u32> {
+        match expr.id() {
+            Some(id) => self
+                .names
+                .get(id)
+                .copied()
+                .with_context(|| format!("line {}: unknown identifier {id}", expr.line())),
+            None => number(expr),
+        }
+    }
+}
+
+/// 指令的立即数格式
+#[derive(Debug, Clone, Copy)]
+enum Imm {
+    None,
+    Block,
+    Label,
+    BrTable,
+    Func,
+    CallIndirect,
+    /// 类型索引
+    Type,
+    Local,
+    Global,
+    /// 可省略的表索引
+    Table,
+    /// 参数为自然对齐（log2）
+    MemArg(u32),
+    /// 可省略的内存索引
+    Memory,
+    MemoryCopy,
+    MemoryInit,
+    Data,
+    Elem,
+    TableInit,
+    TableCopy,
+    Const(ValueType),
+    Select,
+    RefNull,
+}
+
+/// 0x45 起的单字节数值指令
+const NUMERIC: &[&str] = &[
+    "i32.eqz",
+    "i32.eq",
+    "i32.ne",
+    "i32.lt_s",
+    "i32.lt_u",
+    "i32.gt_s",
+    "i32.gt_u",
+    "i32.le_s",
+    "i32.le_u",
+    "i32.ge_s",
+    "i32.ge_u",
+    "i64.eqz",
+    "i64.eq",
+    "i64.ne",
+    "i64.lt_s",
+    "i64.lt_u",
+    "i64.gt_s",
+    "i64.gt_u",
+    "i64.le_s",
+    "i64.le_u",
+    "i64.ge_s",
+    "i64.ge_u",
+    "f32.eq",
+    "f32.ne",
+    "f32.lt",
+    "f32.gt",
+    "f32.le",
+    "f32.ge",
+    "f64.eq",
+    "f64.ne",
+    "f64.lt",
+    "f64.gt",
+    "f64.le",
+    "f64.ge",
+    "i32.clz",
+    "i32.ctz",
+    "i32.popcnt",
+    "i32.add",
+    "i32.sub",
+    "i32.mul",
+    "i32.div_s",
+    "i32.div_u",
+    "i32.rem_s",
+    "i32.rem_u",
+    "i32.and",
+    "i32.or",
+    "i32.xor",
+    "i32.shl",
+    "i32.shr_s",
+    "i32.shr_u",
+    "i32.rotl",
+    "i32.rotr",
+    "i64.clz",
+    "i64.ctz",
+    "i64.popcnt",
+    "i64.add",
+    "i64.sub",
+    "i64.mul",
+    "i64.div_s",
+    "i64.div_u",
+    "i64.rem_s",
+    "i64.rem_u",
+    "i64.and",
+    "i64.or",
+    "i64.xor",
+    "i64.shl",
+    "i64.shr_s",
+    "i64.shr_u",
+    "i64.rotl",
+    "i64.rotr",
+    "f32.abs",
+    "f32.neg",
+    "f32.ceil",
+    "f32.floor",
+    "f32.trunc",
+    "f32.nearest",
+    "f32.sqrt",
+    "f32.add",
+    "f32.sub",
+    "f32.mul",
+    "f32.div",
+    "f32.min",
+    "f32.max",
+    "f32.copysign",
+    "f64.abs",
+    "f64.neg",
+    "f64.ceil",
+    "f64.floor",
+    "f64.trunc",
+    "f64.nearest",
+    "f64.sqrt",
+    "f64.add",
+    "f64.sub",
+    "f64.mul",
+    "f64.div",
+    "f64.min",
+    "f64.max",
+    "f64.copysign",
+    "i32.wrap_i64",
+    "i32.trunc_f32_s",
+    "i32.trunc_f32_u",
+    "i32.trunc_f64_s",
+    "i32.trunc_f64_u",
+    "i64.extend_i32_s",
+    "i64.extend_i32_u",
+    "i64.trunc_f32_s",
+    "i64.trunc_f32_u",
+    "i64.trunc_f64_s",
+    "i64.trunc_f64_u",
+    "f32.convert_i32_s",
+    "f32.convert_i32_u",
+    "f32.convert_i64_s",
+    "f32.convert_i64_u",
+    "f32.demote_f64",
+    "f64.convert_i32_s",
+    "f64.convert_i32_u",
+    "f64.convert_i64_s",
+    "f64.convert_i64_u",
+    "f64.promote_f32",
+    "i32.reinterpret_f32",
+    "i64.reinterpret_f64",
+    "f32.reinterpret_i32",
+    "f64.reinterpret_i64",
+    "i32.extend8_s",
+    "i32.extend16_s",
+    "i64.extend8_s",
+    "i64.extend16_s",
+    "i64.extend32_s",
+];
+
+/// 0x28 起的访存指令及其自然对齐
+const MEMORY: &[(&str, u32)] = &[
+    ("i32.load", 2),
+    ("i64.load", 3),
+    ("f32.load", 2),
+    ("f64.load", 3),
+    ("i32.load8_s", 0),
+    ("i32.load8_u", 0),
+    ("i32.load16_s", 1),
+    ("i32.load16_u", 1),
+    ("i64.load8_s", 0),
+    ("i64.load8_u", 0),
+    ("i64.load16_s", 1),
+    ("i64.load16_u", 1),
+    ("i64.load32_s", 2),
+    ("i64.load32_u", 2),
+    ("i32.store", 2),
+    ("i64.store", 3),
+    ("f32.store", 2),
+    ("f64.store", 3),
+    ("i32.store8", 0),
+    ("i32.store16", 1),
+    ("i64.store8", 0),
+    ("i64.store16", 1),
+    ("i64.store32", 2),
+];
+
+/// 0xfc 前缀指令
+const PREFIXED: &[(&str, Imm)] = &[
+    ("i32.trunc_sat_f32_s", Imm::None),
+    ("i32.trunc_sat_f32_u", Imm::None),
+    ("i32.trunc_sat_f64_s", Imm::None),
+    ("i32.trunc_sat_f64_u", Imm::None),
+    ("i64.trunc_sat_f32_s", Imm::None),
+    ("i64.trunc_sat_f32_u", Imm::None),
+    ("i64.trunc_sat_f64_s", Imm::None),
+    ("i64.trunc_sat_f64_u", Imm::None),
+    ("memory.init", Imm::MemoryInit),
+    ("data.drop", Imm::Data),
+    ("memory.copy", Imm::MemoryCopy),
+    ("memory.fill", Imm::Memory),
+    ("table.init", Imm::TableInit),
+    ("elem.drop", Imm::Elem),
+    ("table.copy", Imm::TableCopy),
+    ("table.grow", Imm::Table),
+    ("table.size", Imm::Table),
+    ("table.fill", Imm::Table),
+];
+
+fn opcode(op: &str) -> Option<(Vec<u8>, Imm)> {
+    let single = |code: u8, imm| Some((vec![code], imm));
+    match op {
+        "unreachable" => single(0x00, Imm::None),
+        "nop" => single(0x01, Imm::None),
+        "block" => single(0x02, Imm::Block),
+        "loop" => single(0x03, Imm::Block),
+        "if" => single(0x04, Imm::Block),
+        "br" => single(0x0c, Imm::Label),
+        "br_if" => single(0x0d, Imm::Label),
+        "br_table" => single(0x0e, Imm::BrTable),
+        "return" => single(0x0f, Imm::None),
+        "call" => single(0x10, Imm::Func),
+        "call_indirect" => single(0x11, Imm::CallIndirect),
+        "call_ref" => single(0x14, Imm::Type),
+        "drop" => single(0x1a, Imm::None),
+        "select" => single(0x1b, Imm::Select),
+        "local.get" => single(0x20, Imm::Local),
+        "local.set" => single(0x21, Imm::Local),
+        "local.tee" => single(0x22, Imm::Local),
+        "global.get" => single(0x23, Imm::Global),
+        "global.set" => single(0x24, Imm::Global),
+        "table.get" => single(0x25, Imm::Table),
+        "table.set" => single(0x26, Imm::Table),
+        "memory.size" => single(0x3f, Imm::Memory),
+        "memory.grow" => single(0x40, Imm::Memory),
+        "i32.const" => single(0x41, Imm::Const(ValueType::I32)),
+        "i64.const" => single(0x42, Imm::Const(ValueType::I64)),
+        "f32.const" => single(0x43, Imm::Const(ValueType::F32)),
+        "f64.const" => single(0x44, Imm::Const(ValueType::F64)),
+        "ref.null" => single(0xd0, Imm::RefNull),
+        "ref.is_null" => single(0xd1, Imm::None),
+        "ref.func" => single(0xd2, Imm::Func),
+        "ref.as_non_null" => single(0xd4, Imm::None),
+        "br_on_null" => single(0xd5, Imm::Label),
+        "br_on_non_null" => single(0xd6, Imm::Label),
+        _ => {
+            if let Some(i) = NUMERIC.iter().position(|name| *name == op) {
+                return single(0x45 + i as u8, Imm::None);
+            }
+            if let Some(i) = MEMORY.iter().position(|(name, _)| *name == op) {
+                return single(0x28 + i as u8, Imm::MemArg(MEMORY[i].1));
+            }
+            let i = PREFIXED.iter().position(|(name, _)| *name == op)?;
+            Some((vec![0xfc, i as u8], PREFIXED[i].1))
+        }
+    }
+}
+
+/// 函数体（或常量表达式）的编译状态
+#[derive(Debug, Default)]
+struct Body {
+    locals: Space,
+    labels: Vec<Option<String>>,
+}
+
+impl Body {
+    fn label(&self, expr: &SExpr) -> anyhow::Result<u32> {
+        match expr.id() {
+            Some(id) => {
+                let pos = self
+                    .labels
+                    .iter()
+                    .rposition(|label| label.as_deref() == Some(id))
+                    .with_context(|| format!("line {}: unknown label {id}", expr.line()))?;
+                Ok((self.labels.len() - 1 - pos) as u32)
+            }
+            None => number(expr),
+        }
+    }
+}
+
+struct FuncDef<'a> {
+    ty: u32,
+    body: Body,
+    /// 局部变量声明之后的指令
+    code: &'a [SExpr],
+    locals: Vec<ValueType>,
+}
+
+enum ElemItems<'a> {
+    Funcs(&'a [SExpr]),
+    Exprs(ValueType, Vec<&'a [SExpr]>),
+}
+
+enum Mode<'a> {
+    Passive,
+    Declare,
+    /// (表或内存索引, 偏移表达式)
+    Active(Option<&'a SExpr>, &'a [SExpr]),
+    /// 表或内存定义中内联的段，偏移为 0
+    Inline(u32),
+}
+
+struct ElemDef<'a> {
+    mode: Mode<'a>,
+    items: ElemItems<'a>,
+}
+
+struct DataDef<'a> {
+    mode: Mode<'a>,
+    bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Builder<'a> {
+    types: Vec<FuncType>,
+    type_names: Space,
+    funcs: Space,
+    tables: Space,
+    memories: Space,
+    globals: Space,
+    elems: Space,
+    datas: Space,
+    imports: Vec<Vec<u8>>,
+    func_defs: Vec<FuncDef<'a>>,
+    table_defs: Vec<Vec<u8>>,
+    memory_defs: Vec<Vec<u8>>,
+    global_defs: Vec<(Vec<u8>, &'a [SExpr])>,
+    /// (导出名, 类型, 索引)
+    exports: Vec<(String, u8, &'a SExpr)>,
+    inline_exports: Vec<(String, u8, u32)>,
+    start: Option<&'a SExpr>,
+    elem_defs: Vec<ElemDef<'a>>,
+    data_defs: Vec<DataDef<'a>>,
+    /// 使用了 memory.init 或 data.drop，需要 data count 段
+    data_count: bool,
+}
+
+fn list_head<'a>(items: &'a [SExpr], pos: usize, head: &str) -> Option<&'a [SExpr]> {
+    let item = items.get(pos)?;
+    (item.head() == Some(head)).then(|| item.list().unwrap())
+}
+
+fn string(items: &[SExpr], pos: usize, line: usize) -> anyhow::Result<String> {
+    items
+        .get(pos)
+        .with_context(|| format!("line {line}: expected a string"))?
+        .name()
+}
+
+impl<'a> Builder<'a> {
+    fn func_type(&mut self, ty: FuncType) -> u32 {
+        match self.types.iter().position(|t| *t == ty) {
+            Some(idx) => idx as u32,
+            None => {
+                self.types.push(ty);
+                self.type_names.count += 1;
+                self.types.len() as u32 - 1
+            }
+        }
+    }
+
+    /// `(param ...)* (result ...)*`
+    fn signature(
+        items: &[SExpr],
+        pos: &mut usize,
+        mut params: Option<&mut Space>,
+    ) -> anyhow::Result<FuncType> {
+        let mut ty: FuncType = (vec![], vec![]);
+        while let Some(param) = list_head(items, *pos, "param") {
+            let line = items[*pos].line();
+            match param.get(1).and_then(SExpr::id) {
+                Some(id) => {
+                    ensure!(param.len() == 3, "line {line}: malformed parameter");
+                    ty.0.push(value_type(&param[2])?);
+                    if let Some(space) = params.as_deref_mut() {
+                        space.add(Some(id), line)?;
+                    }
+                }
+                None => {
+                    for p in &param[1..] {
+                        ty.0.push(value_type(p)?);
+                        if let Some(space) = params.as_deref_mut() {
+                            space.add(None, line)?;
+                        }
+                    }
+                }
+            }
+            *pos += 1;
+        }
+        while let Some(result) = list_head(items, *pos, "result") {
+            for r in &result[1..] {
+                ty.1.push(value_type(r)?);
+            }
+            *pos += 1;
+        }
+        Ok(ty)
+    }
+
+    /// `(type x)? (param ...)* (result ...)*`，params 不为空时登记参数名
+    fn type_use(
+        &mut self,
+        items: &[SExpr],
+        pos: &mut usize,
+        mut params: Option<&mut Space>,
+    ) -> anyhow::Result<u32> {
+        let mut explicit = None;
+        if let Some(ty) = list_head(items, *pos, "type") {
+            let line = items[*pos].line();
+            let idx = self.type_names.resolve(
+                ty.get(1)
+                    .with_context(|| format!("line {line}: missing type index"))?,
+            )?;
+            ensure!(
+                (idx as usize) < self.types.len(),
+                "line {line}: unknown type {idx}"
+            );
+            explicit = Some(idx);
+            *pos += 1;
+        }
+        let ty = Self::signature(items, pos, params.as_deref_mut())?;
+        match explicit {
+            Some(idx) => {
+                let declared = &self.types[idx as usize];
+                let inline = !ty.0.is_empty() || !ty.1.is_empty();
+                ensure!(
+                    !inline || *declared == ty,
+                    "line {}: inline function type does not match the type index",
+                    items.get(*pos).or(items.last()).map_or(0, SExpr::line)
+                );
+                if let Some(space) = params {
+                    space.count = space.count.max(declared.0.len() as u32);
+                }
+                Ok(idx)
+            }
+            None => Ok(self.func_type(ty)),
+        }
+    }
+
+    fn block_type(&mut self, items: &[SExpr], pos: &mut usize) -> anyhow::Result<Vec<u8>> {
+        let mut out = vec![];
+        if list_head(items, *pos, "type").is_some() {
+            let idx = self.type_use(items, pos, None)?;
+            leb_i64(idx as i64, &mut out);
+            return Ok(out);
+        }
+        let start = *pos;
+        let idx = self.type_use(items, pos, None)?;
+        if *pos == start {
+            out.push(0x40);
+            return Ok(out);
+        }
+        match &self.types[idx as usize] {
+            (params, results) if params.is_empty() && results.len() == 1 => {
+                out.push(value_type_byte(results[0]))
+            }
+            (params, results) if params.is_empty() && results.is_empty() => out.push(0x40),
+            _ => leb_i64(idx as i64, &mut out),
+        }
+        Ok(out)
+    }
+
+    fn opt_index<'s>(items: &'s [SExpr], pos: &mut usize) -> Option<&'s SExpr> {
+        let item = items.get(*pos).filter(|item| is_index(item))?;
+        *pos += 1;
+        Some(item)
+    }
+
+    fn index<'s>(items: &'s [SExpr], pos: &mut usize, line: usize) -> anyhow::Result<&'s SExpr> {
+        Self::opt_index(items, pos).with_context(|| format!("line {line}: expected an index"))
+    }
+
+    /// 解析 op 的立即数，返回编码后的指令（不含操作数）
+    fn instr(
+        &mut self,
+        body: &mut Body,
+        op: &str,
+        line: usize,
+        items: &[SExpr],
+        pos: &mut usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (mut out, imm) =
+            opcode(op).with_context(|| format!("line {line}: unknown operator `{op}`"))?;
+        match imm {
+            Imm::None => {}
+            Imm::Block => bail!("line {line}: unexpected `{op}`"),
+            Imm::Label => leb_u32(body.label(Self::index(items, pos, line)?)?, &mut out),
+            Imm::BrTable => {
+                let mut labels = vec![];
+                while let Some(label) = Self::opt_index(items, pos) {
+                    labels.push(body.label(label)?);
+                }
+                let default = labels
+                    .pop()
+                    .with_context(|| format!("line {line}: br_table needs a default label"))?;
+                leb_u32(labels.len() as u32, &mut out);
+                labels.iter().for_each(|label| leb_u32(*label, &mut out));
+                leb_u32(default, &mut out);
+            }
+            Imm::Func => {
+                let idx = self.funcs.resolve(Self::index(items, pos, line)?)?;
+                leb_u32(idx, &mut out);
+            }
+            Imm::CallIndirect => {
+                let table = match Self::opt_index(items, pos) {
+                    Some(table) => self.tables.resolve(table)?,
+                    None => 0,
+                };
+                let ty = self.type_use(items, pos, None)?;
+                leb_u32(ty, &mut out);
+                leb_u32(table, &mut out);
+            }
+            Imm::Type => {
+                let idx = self.type_names.resolve(Self::index(items, pos, line)?)?;
+                ensure!(
+                    (idx as usize) < self.types.len(),
+                    "line {line}: unknown type {idx}"
+                );
+                leb_u32(idx, &mut out);
+            }
+            Imm::Local => {
+                let idx = body.locals.resolve(Self::index(items, pos, line)?)?;
+                leb_u32(idx, &mut out);
+            }
+            Imm::Global => {
+                let idx = self.globals.resolve(Self::index(items, pos, line)?)?;
+                leb_u32(idx, &mut out);
+            }
+            Imm::Table => {
+                let idx = match Self::opt_index(items, pos) {
+                    Some(table) => self.tables.resolve(table)?,
+                    None => 0,
+                };
+                leb_u32(idx, &mut out);
+            }
+            Imm::MemArg(natural) => {
+                let mut offset = 0;
+                let mut align = 1 << natural;
+                while let Some(atom) = items.get(*pos).and_then(SExpr::atom) {
+                    let (key, value) = match atom.split_once('=') {
+                        Some(kv) => kv,
+                        None => break,
+                    };
+                    let value = SExpr::Atom(value.to_string(), line);
+                    match key {
+                        "offset" => offset = number(&value)?,
+                        "align" => align = number(&value)?,
+                        _ => bail!("line {line}: unknown memory argument `{atom}`"),
+                    }
+                    *pos += 1;
+                }
+                ensure!(
+                    align.is_power_of_two(),
+                    "line {line}: alignment must be a power of two"
+                );
+                leb_u32(align.trailing_zeros(), &mut out);
+                leb_u32(offset, &mut out);
+            }
+            Imm::Memory => {
+                let idx = match Self::opt_index(items, pos) {
+                    Some(memory) => self.memories.resolve(memory)?,
+                    None => 0,
+                };
+                leb_u32(idx, &mut out);
+            }
+            Imm::MemoryCopy => out.extend([0x00, 0x00]),
+            Imm::MemoryInit => {
+                self.data_count = true;
+                let idx = self.datas.resolve(Self::index(items, pos, line)?)?;
+                leb_u32(idx, &mut out);
+                out.push(0x00);
+            }
+            Imm::Data => {
+                self.data_count = true;
+                let idx = self.datas.resolve(Self::index(items, pos, line)?)?;
+                leb_u32(idx, &mut out);
+            }
+            Imm::Elem => {
+                let idx = self.elems.resolve(Self::index(items, pos, line)?)?;
+                leb_u32(idx, &mut out);
+            }
+            Imm::TableInit => {
+                let first = Self::index(items, pos, line)?;
+                let (table, elem) = match Self::opt_index(items, pos) {
+                    Some(elem) => (self.tables.resolve(first)?, elem),
+                    None => (0, first),
+                };
+                leb_u32(self.elems.resolve(elem)?, &mut out);
+                leb_u32(table, &mut out);
+            }
+            Imm::TableCopy => {
+                let (dst, src) = match Self::opt_index(items, pos) {
+                    Some(dst) => {
+                        let src = Self::index(items, pos, line)?;
+                        (self.tables.resolve(dst)?, self.tables.resolve(src)?)
+                    }
+                    None => (0, 0),
+                };
+                leb_u32(dst, &mut out);
+                leb_u32(src, &mut out);
+            }
+            Imm::Const(ty) => {
+                let text = items
+                    .get(*pos)
+                    .and_then(SExpr::atom)
+                    .with_context(|| format!("line {line}: missing constant"))?;
+                *pos += 1;
+                let value =
+                    parse_value(text, ty).with_context(|| format!("line {line}: bad constant"))?;
+                match value {
+                    WasmValue::I32(v) => leb_i64(v as i64, &mut out),
+                    WasmValue::I64(v) => leb_i64(v, &mut out),
+                    WasmValue::F32(v) => out.extend(v.to_bits().to_le_bytes()),
+                    WasmValue::F64(v) => out.extend(v.to_bits().to_le_bytes()),
+                    _ => unreachable!(),
+                }
+            }
+            Imm::Select => {
+                let mut types = vec![];
+                while let Some(result) = list_head(items, *pos, "result") {
+                    for r in &result[1..] {
+                        types.push(value_type(r)?);
+                    }
+                    *pos += 1;
+                }
+                if !types.is_empty() {
+                    out = vec![0x1c];
+                    leb_u32(types.len() as u32, &mut out);
+                    out.extend(types.into_iter().map(value_type_byte));
+                }
+            }
+            Imm::RefNull => {
+                let ty = items
+                    .get(*pos)
+                    .with_context(|| format!("line {line}: missing heap type"))?;
+                out.push(heap_type(ty)?);
+                *pos += 1;
+            }
+        }
+        Ok(out)
+    }
+
+    /// 编译指令序列，遇到 stop 中的关键字（不消耗）或序列结束时返回
+    fn instrs(
+        &mut self,
+        body: &mut Body,
+        items: &[SExpr],
+        pos: &mut usize,
+        stop: &[&str],
+        out: &mut Vec<u8>,
+    ) -> anyhow::Result<()> {
+        while let Some(item) = items.get(*pos) {
+            let line = item.line();
+            let op = match item {
+                SExpr::List(..) => {
+                    self.folded(body, item, out)?;
+                    *pos += 1;
+                    continue;
+                }
+                SExpr::Atom(op, _) if stop.contains(&op.as_str()) => return Ok(()),
+                SExpr::Atom(op, _) => op,
+                SExpr::Str(..) => bail!("line {line}: unexpected string"),
+            };
+            *pos += 1;
+            match op.as_str() {
+                "block" | "loop" | "if" => {
+                    let label = items.get(*pos).and_then(SExpr::id).map(str::to_string);
+                    if label.is_some() {
+                        *pos += 1;
+                    }
+                    out.push(opcode(op).unwrap().0[0]);
+                    out.extend(self.block_type(items, pos)?);
+                    body.labels.push(label);
+                    let stop: &[&str] = if op == "if" {
+                        &["else", "end"]
+                    } else {
+                        &["end"]
+                    };
+                    self.instrs(body, items, pos, stop, out)?;
+                    if items.get(*pos).and_then(SExpr::atom) == Some("else") {
+                        *pos += 1;
+                        Self::opt_label(items, pos);
+                        out.push(0x05);
+                        self.instrs(body, items, pos, &["end"], out)?;
+                    }
+                    ensure!(
+                        items.get(*pos).and_then(SExpr::atom) == Some("end"),
+                        "line {line}: missing `end` for `{op}`"
+                    );
+                    *pos += 1;
+                    Self::opt_label(items, pos);
+                    out.push(0x0b);
+                    body.labels.pop();
+                }
+                _ => {
+                    let code = self.instr(body, op, line, items, pos)?;
+                    out.extend(code);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn opt_label(items: &[SExpr], pos: &mut usize) {
+        if items.get(*pos).and_then(SExpr::id).is_some() {
+            *pos += 1;
+        }
+    }
+
+    /// 折叠写法：先编译操作数，再输出指令本身
+    fn folded(&mut self, body: &mut Body, expr: &SExpr, out: &mut Vec<u8>) -> anyhow::Result<()> {
+        let line = expr.line();
+        let items = expr.list().unwrap();
+        let op = expr
+            .head()
+            .with_context(|| format!("line {line}: expected an instruction"))?;
+        let mut pos = 1;
+        let label = items.get(1).and_then(SExpr::id).map(str::to_string);
+        match op {
+            "block" | "loop" => {
+                if label.is_some() {
+                    pos += 1;
+                }
+                out.push(opcode(op).unwrap().0[0]);
+                out.extend(self.block_type(items, &mut pos)?);
+                body.labels.push(label);
+                self.instrs(body, items, &mut pos, &[], out)?;
+                out.push(0x0b);
+                body.labels.pop();
+            }
+            "if" => {
+                if label.is_some() {
+                    pos += 1;
+                }
+                let block_type = self.block_type(items, &mut pos)?;
+                while let Some(item) = items.get(pos) {
+                    if matches!(item.head(), Some("then") | Some("else")) {
+                        break;
+                    }
+                    ensure!(
+                        item.list().is_some(),
+                        "line {line}: expected a folded condition"
+                    );
+                    self.folded(body, item, out)?;
+                    pos += 1;
+                }
+                out.push(0x04);
+                out.extend(block_type);
+                body.labels.push(label);
+                let then = list_head(items, pos, "then")
+                    .with_context(|| format!("line {line}: missing `then`"))?;
+                self.instrs(body, then, &mut 1, &[], out)?;
+                if let Some(els) = list_head(items, pos + 1, "else") {
+                    out.push(0x05);
+                    self.instrs(body, els, &mut 1, &[], out)?;
+                    pos += 1;
+                }
+                ensure!(pos + 1 == items.len(), "line {line}: malformed `if`");
+                out.push(0x0b);
+                body.labels.pop();
+            }
+            _ => {
+                let code = self.instr(body, op, line, items, &mut pos)?;
+                for operand in &items[pos..] {
+                    ensure!(
+                        operand.list().is_some(),
+                        "line {}: unexpected token in folded instruction",
+                        operand.line()
+                    );
+                    self.folded(body, operand, out)?;
+                }
+                out.extend(code);
+            }
+        }
+        Ok(())
+    }
+
+    /// 常量表达式，以 end 结尾
+    fn const_expr(&mut self, items: &[SExpr]) -> anyhow::Result<Vec<u8>> {
+        let mut out = vec![];
+        self.instrs(&mut Body::default(), items, &mut 0, &[], &mut out)?;
+        out.push(0x0b);
+        Ok(out)
+    }
+
+    /// `(offset instr*)` 或单条折叠指令
+    fn offset(item: &'a SExpr) -> &'a [SExpr] {
+        match item.head() {
+            Some("offset") => &item.list().unwrap()[1..],
+            _ => std::slice::from_ref(item),
+        }
+    }
+
+    /// 字段开头的 `$id? (export "n")* (import "m" "n")?`
+    fn prefix(
+        &mut self,
+        items: &'a [SExpr],
+        pos: &mut usize,
+        kind: u8,
+        idx: u32,
+    ) -> anyhow::Result<Option<(String, String)>> {
+        while let Some(export) = list_head(items, *pos, "export") {
+            let line = items[*pos].line();
+            self.inline_exports
+                .push((string(export, 1, line)?, kind, idx));
+            *pos += 1;
+        }
+        match list_head(items, *pos, "import") {
+            Some(import) => {
+                let line = items[*pos].line();
+                *pos += 1;
+                Ok(Some((string(import, 1, line)?, string(import, 2, line)?)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn import(&mut self, module: &str, field: &str, kind: u8, desc: Vec<u8>) {
+        let mut entry = vec![];
+        name(module, &mut entry);
+        name(field, &mut entry);
+        entry.push(kind);
+        entry.extend(desc);
+        self.imports.push(entry);
+    }
+
+    fn limits(items: &[SExpr], pos: &mut usize, line: usize) -> anyhow::Result<Vec<u8>> {
+        let min = number(
+            items
+                .get(*pos)
+                .with_context(|| format!("line {line}: missing limits"))?,
+        )?;
+        *pos += 1;
+        let max = match items.get(*pos).filter(|item| is_index(item)) {
+            Some(max) => {
+                *pos += 1;
+                Some(number(max)?)
+            }
+            None => None,
+        };
+        let mut out = vec![];
+        match max {
+            Some(max) => {
+                out.push(0x01);
+                leb_u32(min, &mut out);
+                leb_u32(max, &mut out);
+            }
+            None => {
+                out.push(0x00);
+                leb_u32(min, &mut out);
+            }
+        }
+        Ok(out)
+    }
+
+    fn global_type(expr: &SExpr) -> anyhow::Result<Vec<u8>> {
+        match expr.head() {
+            Some("mut") => {
+                let items = expr.list().unwrap();
+                let ty = items
+                    .get(1)
+                    .with_context(|| format!("line {}: missing global type", expr.line()))?;
+                Ok(vec![value_type_byte(value_type(ty)?), 0x01])
+            }
+            _ => Ok(vec![value_type_byte(value_type(expr)?), 0x00]),
+        }
+    }
+
+    fn elem_items(items: &'a [SExpr], pos: usize) -> anyhow::Result<ElemItems<'a>> {
+        let rest = &items[pos..];
+        let exprs = |ty, rest: &'a [SExpr]| {
+            let exprs = rest
+                .iter()
+                .map(|item| match item.head() {
+                    Some("item") => &item.list().unwrap()[1..],
+                    _ => std::slice::from_ref(item),
+                })
+                .collect();
+            ElemItems::Exprs(ty, exprs)
+        };
+        let items = match rest.first() {
+            Some(SExpr::Atom(atom, _)) if atom == "func" => ElemItems::Funcs(&rest[1..]),
+            Some(ty @ (SExpr::Atom(..) | SExpr::List(..))) if value_type(ty).is_ok() => {
+                exprs(value_type(ty)?, &rest[1..])
+            }
+            Some(SExpr::List(..)) => exprs(ValueType::FuncRef, rest),
+            _ => ElemItems::Funcs(rest),
+        };
+        Ok(items)
+    }
+
+    /// 第一遍：登记所有定义的索引和名字
+    fn declare(&mut self, field: &'a SExpr) -> anyhow::Result<()> {
+        let line = field.line();
+        let items = field
+            .list()
+            .with_context(|| format!("line {line}: expected a module field"))?;
+        let id = items.get(1).and_then(SExpr::id);
+        let mut pos = if id.is_some() { 2 } else { 1 };
+        match field.head() {
+            Some("type") => {}
+            Some("func") => {
+                let idx = self.funcs.add(id, line)?;
+                let import = self.prefix(items, &mut pos, 0x00, idx)?;
+                let mut body = Body::default();
+                let ty = self.type_use(items, &mut pos, Some(&mut body.locals))?;
+                match import {
+                    Some((module, field)) => {
+                        let mut desc = vec![];
+                        leb_u32(ty, &mut desc);
+                        self.import(&module, &field, 0x00, desc);
+                    }
+                    None => {
+                        let mut locals = vec![];
+                        while let Some(local) = list_head(items, pos, "local") {
+                            let line = items[pos].line();
+                            match local.get(1).and_then(SExpr::id) {
+                                Some(id) => {
+                                    ensure!(local.len() == 3, "line {line}: malformed local");
+                                    locals.push(value_type(&local[2])?);
+                                    body.locals.add(Some(id), line)?;
+                                }
+                                None => {
+                                    for l in &local[1..] {
+                                        locals.push(value_type(l)?);
+                                        body.locals.add(None, line)?;
+                                    }
+                                }
+                            }
+                            pos += 1;
+                        }
+                        self.func_defs.push(FuncDef {
+                            ty,
+                            body,
+                            code: &items[pos..],
+                            locals,
+                        });
+                    }
+                }
+            }
+            Some("table") => {
+                let idx = self.tables.add(id, line)?;
+                let import = self.prefix(items, &mut pos, 0x01, idx)?;
+                let mut desc = vec![];
+                match items.get(pos).map(value_type) {
+                    // `(table funcref (elem ...))`
+                    Some(Ok(ty)) => {
+                        let elem = list_head(items, pos + 1, "elem")
+                            .with_context(|| format!("line {line}: missing table limits"))?;
+                        let items = Self::elem_items(elem, 1)?;
+                        let count = match &items {
+                            ElemItems::Funcs(funcs) => funcs.len(),
+                            ElemItems::Exprs(_, exprs) => exprs.len(),
+                        } as u32;
+                        desc.push(value_type_byte(ty));
+                        desc.push(0x01);
+                        leb_u32(count, &mut desc);
+                        leb_u32(count, &mut desc);
+                        self.elems.count += 1;
+                        self.elem_defs.push(ElemDef {
+                            mode: Mode::Inline(idx),
+                            items,
+                        });
+                    }
+                    _ => {
+                        let limits = Self::limits(items, &mut pos, line)?;
+                        let ty = items
+                            .get(pos)
+                            .with_context(|| format!("line {line}: missing table type"))?;
+                        desc.push(value_type_byte(value_type(ty)?));
+                        desc.extend(limits);
+                    }
+                }
+                match import {
+                    Some((module, field)) => self.import(&module, &field, 0x01, desc),
+                    None => self.table_defs.push(desc),
+                }
+            }
+            Some("memory") => {
+                let idx = self.memories.add(id, line)?;
+                let import = self.prefix(items, &mut pos, 0x02, idx)?;
+                let desc = match list_head(items, pos, "data") {
+                    Some(data) => {
+                        let mut bytes = vec![];
+                        for s in &data[1..] {
+                            bytes.extend(
+                                s.string()
+                                    .with_context(|| format!("line {line}: expected a string"))?,
+                            );
+                        }
+                        let pages = bytes.len().div_ceil(super::constants::PAGE_SIZE) as u32;
+                        let mut desc = vec![0x01];
+                        leb_u32(pages, &mut desc);
+                        leb_u32(pages, &mut desc);
+                        self.datas.count += 1;
+                        self.data_defs.push(DataDef {
+                            mode: Mode::Inline(idx),
+                            bytes,
+                        });
+                        desc
+                    }
+                    None => Self::limits(items, &mut pos, line)?,
+                };
+                match import {
+                    Some((module, field)) => self.import(&module, &field, 0x02, desc),
+                    None => self.memory_defs.push(desc),
+                }
+            }
+            Some("global") => {
+                let idx = self.globals.add(id, line)?;
+                let import = self.prefix(items, &mut pos, 0x03, idx)?;
+                let ty = Self::global_type(
+                    items
+                        .get(pos)
+                        .with_context(|| format!("line {line}: missing global type"))?,
+                )?;
+                match import {
+                    Some((module, field)) => self.import(&module, &field, 0x03, ty),
+                    None => self.global_defs.push((ty, &items[pos + 1..])),
+                }
+            }
+            Some("import") => {
+                let module = string(items, 1, line)?;
+                let field_name = string(items, 2, line)?;
+                let desc = items
+                    .get(3)
+                    .and_then(SExpr::list)
+                    .with_context(|| format!("line {line}: missing import description"))?;
+                let id = desc.get(1).and_then(SExpr::id);
+                let mut pos = if id.is_some() { 2 } else { 1 };
+                let (kind, desc) = match desc.first().and_then(SExpr::atom) {
+                    Some("func") => {
+                        self.funcs.add(id, line)?;
+                        let ty = self.type_use(desc, &mut pos, None)?;
+                        let mut out = vec![];
+                        leb_u32(ty, &mut out);
+                        (0x00, out)
+                    }
+                    Some("table") => {
+                        self.tables.add(id, line)?;
+                        let limits = Self::limits(desc, &mut pos, line)?;
+                        let ty = desc
+                            .get(pos)
+                            .with_context(|| format!("line {line}: missing table type"))?;
+                        let mut out = vec![value_type_byte(value_type(ty)?)];
+                        out.extend(limits);
+                        (0x01, out)
+                    }
+                    Some("memory") => {
+                        self.memories.add(id, line)?;
+                        (0x02, Self::limits(desc, &mut pos, line)?)
+                    }
+                    Some("global") => {
+                        self.globals.add(id, line)?;
+                        let ty = desc
+                            .get(pos)
+                            .with_context(|| format!("line {line}: missing global type"))?;
+                        (0x03, Self::global_type(ty)?)
+                    }
+                    _ => bail!("line {line}: unknown import kind"),
+                };
+                self.import(&module, &field_name, kind, desc);
+            }
+            Some("export") => {
+                let export = string(items, 1, line)?;
+                let desc = items
+                    .get(2)
+                    .and_then(SExpr::list)
+                    .with_context(|| format!("line {line}: missing export description"))?;
+                let kind = match desc.first().and_then(SExpr::atom) {
+                    Some("func") => 0x00,
+                    Some("table") => 0x01,
+                    Some("memory") => 0x02,
+                    Some("global") => 0x03,
+                    _ => bail!("line {line}: unknown export kind"),
+                };
+                let idx = desc
+                    .get(1)
+                    .with_context(|| format!("line {line}: missing export index"))?;
+                self.exports.push((export, kind, idx));
+            }
+            Some("start") => {
+                ensure!(self.start.is_none(), "line {line}: multiple start sections");
+                self.start = Some(
+                    items
+                        .get(1)
+                        .with_context(|| format!("line {line}: missing start function"))?,
+                );
+            }
+            Some("elem") => {
+                self.elems.add(id, line)?;
+                let mode = match items.get(pos) {
+                    Some(SExpr::Atom(atom, _)) if atom == "declare" => {
+                        pos += 1;
+                        Mode::Declare
+                    }
+                    Some(SExpr::List(..)) => {
+                        let table = list_head(items, pos, "table").map(|table| {
+                            pos += 1;
+                            &table[1]
+                        });
+                        let offset = items
+                            .get(pos)
+                            .filter(|item| item.list().is_some() && item.head() != Some("item"))
+                            .with_context(|| format!("line {line}: missing element offset"))?;
+                        pos += 1;
+                        Mode::Active(table, Self::offset(offset))
+                    }
+                    _ => Mode::Passive,
+                };
+                let items = Self::elem_items(items, pos)?;
+                self.elem_defs.push(ElemDef { mode, items });
+            }
+            Some("data") => {
+                self.datas.add(id, line)?;
+                let mode = match items.get(pos) {
+                    Some(SExpr::List(..)) => {
+                        let memory = list_head(items, pos, "memory").map(|memory| {
+                            pos += 1;
+                            &memory[1]
+                        });
+                        let offset = items
+                            .get(pos)
+                            .with_context(|| format!("line {line}: missing data offset"))?;
+                        pos += 1;
+                        Mode::Active(memory, Self::offset(offset))
+                    }
+                    _ => Mode::Passive,
+                };
+                let mut bytes = vec![];
+                for s in &items[pos..] {
+                    bytes.extend(
+                        s.string()
+                            .with_context(|| format!("line {line}: expected a string"))?,
+                    );
+                }
+                self.data_defs.push(DataDef { mode, bytes });
+            }
+            Some(head) => bail!("line {line}: unknown module field `{head}`"),
+            None => bail!("line {line}: expected a module field"),
+        }
+        Ok(())
+    }
+}
+
+fn section(id: u8, items: &[Vec<u8>], out: &mut Vec<u8>) {
+    if items.is_empty() {
+        return;
+    }
+    let mut content = vec![];
+    leb_u32(items.len() as u32, &mut content);
+    items.iter().for_each(|item| content.extend(item));
+    out.push(id);
+    leb_u32(content.len() as u32, out);
+    out.extend(content);
+}
+
+impl Builder<'_> {
+    fn segment_offset(
+        &mut self,
+        mode: &Mode,
+        space: fn(&Self) -> &Space,
+    ) -> anyhow::Result<(u32, Vec<u8>)> {
+        match mode {
+            Mode::Active(target, offset) => {
+                let idx = match target {
+                    Some(target) => space(self).resolve(target)?,
+                    None => 0,
+                };
+                Ok((idx, self.const_expr(offset)?))
+            }
+            Mode::Inline(idx) => Ok((*idx, vec![0x41, 0x00, 0x0b])),
+            Mode::Passive | Mode::Declare => Ok((0, vec![])),
+        }
+    }
+
+    fn elem(&mut self, def: &ElemDef) -> anyhow::Result<Vec<u8>> {
+        let (table, offset) = self.segment_offset(&def.mode, |b| &b.tables)?;
+        let explicit = matches!(def.mode, Mode::Active(Some(_), _) | Mode::Inline(1..));
+        let mut out = vec![];
+        let mut flag = match def.mode {
+            Mode::Active(..) | Mode::Inline(_) if explicit => 0x02,
+            Mode::Active(..) | Mode::Inline(_) => 0x00,
+            Mode::Passive => 0x01,
+            Mode::Declare => 0x03,
+        };
+        match &def.items {
+            ElemItems::Funcs(funcs) => {
+                leb_u32(flag, &mut out);
+                if explicit {
+                    leb_u32(table, &mut out);
+                }
+                out.extend(offset);
+                if flag != 0x00 {
+                    out.push(0x00);
+                }
+                leb_u32(funcs.len() as u32, &mut out);
+                for func in funcs.iter() {
+                    leb_u32(self.funcs.resolve(func)?, &mut out);
+                }
+            }
+            ElemItems::Exprs(ty, exprs) => {
+                // 非 funcref 的 active 段必须显式写出类型
+                if flag == 0x00 && *ty != ValueType::FuncRef {
+                    flag = 0x02;
+                }
+                leb_u32(flag | 0x04, &mut out);
+                if flag == 0x02 {
+                    leb_u32(table, &mut out);
+                }
+                out.extend(offset);
+                if flag != 0x00 {
+                    out.push(value_type_byte(*ty));
+                }
+                leb_u32(exprs.len() as u32, &mut out);
+                for expr in exprs {
+                    out.extend(self.const_expr(expr)?);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn data(&mut self, def: &DataDef) -> anyhow::Result<Vec<u8>> {
+        let (memory, offset) = self.segment_offset(&def.mode, |b| &b.memories)?;
+        let mut out = vec![];
+        match def.mode {
+            Mode::Passive | Mode::Declare => out.push(0x01),
+            _ if memory == 0 => {
+                out.push(0x00);
+                out.extend(offset);
+            }
+            _ => {
+                out.push(0x02);
+                leb_u32(memory, &mut out);
+                out.extend(offset);
+            }
+        }
+        leb_u32(def.bytes.len() as u32, &mut out);
+        out.extend(&def.bytes);
+        Ok(out)
+    }
+
+    /// 第二遍：编译函数体、常量表达式并输出二进制
+    fn finish(mut self) -> anyhow::Result<Vec<u8>> {
+        let mut codes = vec![];
+        for mut def in std::mem::take(&mut self.func_defs) {
+            let mut code = vec![];
+            let mut groups: Vec<(u32, ValueType)> = vec![];
+            for ty in &def.locals {
+                match groups.last_mut() {
+                    Some((count, last)) if last == ty => *count += 1,
+                    _ => groups.push((1, *ty)),
+                }
+            }
+            leb_u32(groups.len() as u32, &mut code);
+            for (count, ty) in groups {
+                leb_u32(count, &mut code);
+                code.push(value_type_byte(ty));
+            }
+            self.instrs(&mut def.body, def.code, &mut 0, &[], &mut code)?;
+            code.push(0x0b);
+            let mut entry = vec![];
+            leb_u32(code.len() as u32, &mut entry);
+            entry.extend(code);
+            codes.push((def.ty, entry));
+        }
+        let mut globals = vec![];
+        for (ty, init) in std::mem::take(&mut self.global_defs) {
+            let mut global = ty;
+            global.extend(self.const_expr(init)?);
+            globals.push(global);
+        }
+        let mut seen = HashSet::new();
+        let mut exports = vec![];
+        let inline = std::mem::take(&mut self.inline_exports);
+        let explicit = std::mem::take(&mut self.exports)
+            .into_iter()
+            .map(|(export, kind, idx)| {
+                let space = match kind {
+                    0x00 => &self.funcs,
+                    0x01 => &self.tables,
+                    0x02 => &self.memories,
+                    _ => &self.globals,
+                };
+                Ok((export, kind, space.resolve(idx)?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for (export, kind, idx) in inline.into_iter().chain(explicit) {
+            ensure!(
+                seen.insert(export.clone()),
+                "duplicate export name `{export}`"
+            );
+            let mut out = vec![];
+            name(&export, &mut out);
+            out.push(kind);
+            leb_u32(idx, &mut out);
+            exports.push(out);
+        }
+        let mut elems = vec![];
+        for def in std::mem::take(&mut self.elem_defs) {
+            elems.push(self.elem(&def)?);
+        }
+        let mut datas = vec![];
+        for def in std::mem::take(&mut self.data_defs) {
+            datas.push(self.data(&def)?);
+        }
+
+        let mut out = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        let types = self
+            .types
+            .iter()
+            .map(|(params, results)| {
+                let mut ty = vec![0x60];
+                leb_u32(params.len() as u32, &mut ty);
+                ty.extend(params.iter().map(|p| value_type_byte(*p)));
+                leb_u32(results.len() as u32, &mut ty);
+                ty.extend(results.iter().map(|r| value_type_byte(*r)));
+                ty
+            })
+            .collect::<Vec<_>>();
+        section(1, &types, &mut out);
+        section(2, &self.imports, &mut out);
+        let funcs = codes
+            .iter()
+            .map(|(ty, _)| {
+                let mut buf = vec![];
+                leb_u32(*ty, &mut buf);
+                buf
+            })
+            .collect::<Vec<_>>();
+        section(3, &funcs, &mut out);
+        section(4, &self.table_defs, &mut out);
+        section(5, &self.memory_defs, &mut out);
+        section(6, &globals, &mut out);
+        section(7, &exports, &mut out);
+        if let Some(start) = self.start {
+            let mut content = vec![];
+            leb_u32(self.funcs.resolve(start)?, &mut content);
+            out.push(8);
+            leb_u32(content.len() as u32, &mut out);
+            out.extend(content);
+        }
+        section(9, &elems, &mut out);
+        if self.data_count {
+            let mut content = vec![];
+            leb_u32(datas.len() as u32, &mut content);
+            out.push(12);
+            leb_u32(content.len() as u32, &mut out);
+            out.extend(content);
+        }
+        let codes = codes.into_iter().map(|(_, code)| code).collect::<Vec<_>>();
+        section(10, &codes, &mut out);
+        section(11, &datas, &mut out);
+        Ok(out)
+    }
+}
+
+/// 编译 `(module $id? field*)` 表达式
+pub fn compile_module(module: &SExpr) -> anyhow::Result<Vec<u8>> {
+    let items = module
+        .list()
+        .filter(|_| module.head() == Some("module"))
+        .with_context(|| format!("line {}: expected a module", module.line()))?;
+    let start = if items.get(1).and_then(SExpr::id).is_some() {
+        2
+    } else {
+        1
+    };
+    compile_fields(&items[start..])
+}
+
+fn compile_fields(fields: &[SExpr]) -> anyhow::Result<Vec<u8>> {
+    let mut builder = Builder::default();
+    // 类型定义先于其它字段登记，函数可以引用后面定义的类型
+    for field in fields.iter().filter(|field| field.head() == Some("type")) {
+        let line = field.line();
+        let items = field.list().unwrap();
+        let id = items.get(1).and_then(SExpr::id);
+        let func = items
+            .get(if id.is_some() { 2 } else { 1 })
+            .filter(|func| func.head() == Some("func"))
+            .with_context(|| format!("line {line}: expected a function type"))?;
+        let func = func.list().unwrap();
+        let mut pos = 1;
+        let ty = Builder::signature(func, &mut pos, None)?;
+        ensure!(pos == func.len(), "line {line}: malformed function type");
+        builder.type_names.add(id, line)?;
+        builder.types.push(ty);
+    }
+    for field in fields.iter() {
+        builder.declare(field)?;
+    }
+    builder.finish()
+}
+
+/// 编译模块文本，可以是 `(module ...)`，也可以省略外层直接写字段
+pub fn compile(src: &str) -> anyhow::Result<Vec<u8>> {
+    let exprs = parse(src)?;
+    match &exprs[..] {
+        [module] if module.head() == Some("module") => compile_module(module),
+        fields => compile_fields(fields),
+    }
+}
+
+#[test]
+fn test_compile() {
+    use super::decoder::WasmModule;
+    use super::testing::invoke;
+
+    let src = r#"
+        (module
+          (type $unary (func (param i32) (result i32)))
+          (memory (export "mem") 1)
+          (global $g (mut i32) (i32.const 10))
+          (table funcref (elem $double $fac))
+          (data (i32.const 8) "\01\02")
+          (func $double (type $unary) (i32.mul (local.get 0) (i32.const 2)))
+          (func $fac (export "fac") (param $n i32) (result i32) (local $acc i32)
+            (local.set $acc (i32.const 1))
+            (block $done
+              (loop $next
+                (br_if $done (i32.eqz (local.get $n)))
+                (local.set $acc (i32.mul (local.get $acc) (local.get $n)))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                (br $next)))
+            local.get $acc)
+          (func (export "indirect") (param i32 i32) (result i32)
+            local.get 1
+            local.get 0
+            call_indirect (type $unary))
+          (func (export "load") (result i32)
+            (i32.load8_u offset=9 (i32.const 0)))
+          (func (export "global") (result i32)
+            (global.set $g (i32.add (global.get $g) (i32.const 1)))
+            global.get $g)
+          (func (export "pick") (param i32) (result i64)
+            (if (result i64) (local.get 0)
+              (then (i64.const -1))
+              (else (i64.const 0x7fff_ffff_ffff)))))
+    "#;
+    let buf = compile(src).unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    let mut call = |name: &str, args: &[WasmValue]| {
+        let idx = match wasm.exports[name] {
+            super::section::export::ExportKind::Func(idx) => idx,
+            _ => unreachable!(),
+        };
+        invoke(&mut wasm, idx, args).unwrap()
+    };
+    assert_eq!(call("fac", &[WasmValue::I32(5)]), [WasmValue::I32(120)]);
+    assert_eq!(
+        call("indirect", &[WasmValue::I32(0), WasmValue::I32(21)]),
+        [WasmValue::I32(42)]
+    );
+    assert_eq!(call("load", &[]), [WasmValue::I32(2)]);
+    assert_eq!(call("global", &[]), [WasmValue::I32(11)]);
+    assert_eq!(call("pick", &[WasmValue::I32(1)]), [WasmValue::I64(-1)]);
+    assert_eq!(
+        call("pick", &[WasmValue::I32(0)]),
+        [WasmValue::I64(0x7fff_ffff_ffff)]
+    );
+
+    let mut leb = vec![];
+    leb_i64(-129, &mut leb);
+    assert_eq!(leb, [0xff, 0x7e]);
+    assert!(compile("(module (func i32.frob))").is_err());
+    assert!(compile("(module (func (export \"a\")) (func (export \"a\")))").is_err());
+    assert!(compile("(func $f) (func $f)").is_err());
+}
@@ -20,8 +20,8 @@ pub fn derive(input: TokenStream) -> TokenStream {
             fn length(&self) -> usize {
                 self.byte_count as usize
             }
-            fn get(&self, offset: usize) -> Option<&u8> {
-                self.raw.get(offset)
+            fn bytes(&self) -> &[u8] {
+                &self.raw
             }
             fn skip(&mut self, num: u32) {
                 self.offset += num as usize
@@ -0,0 +1,10 @@
+//! 任意字节都不应该让解码器 panic：`cargo fuzz run decode`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxygen::runtime::decoder::decode_unchecked;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_unchecked(data);
+});
@@ -0,0 +1,18 @@
+//! wasm-smith 由任意输入生成合法模块，实例化并调用导出函数都不应该 panic：
+//! `cargo fuzz run instantiate`
+
+#![no_main]
+
+use std::time::Duration;
+
+use libfuzzer_sys::fuzz_target;
+use oxygen::runtime::smith::{exercise, generate};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(buf) = generate(data) else {
+        return;
+    };
+    if let Err(msg) = exercise(&buf, Duration::from_secs(1)) {
+        panic!("{msg}");
+    }
+});
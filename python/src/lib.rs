@@ -0,0 +1,272 @@
+//! oxygen 的 Python 绑定
+//!
+//! ```python
+//! import oxygen
+//! module = oxygen.Module(open("add.wasm", "rb").read())
+//! instance = oxygen.Instance(module)
+//! instance.invoke("add", 1, 2)
+//! ```
+//!
+//! 目前不能由 Python 定义宿主函数，带导入的模块无法实例化。
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyTuple};
+
+use oxygen::runtime::decoder::{WasmModule, WasmValue, NULL_REF};
+use oxygen::runtime::linker::Linker;
+use oxygen::runtime::section::export::ExportKind;
+use oxygen::runtime::section::typings::ValueType;
+
+create_exception!(oxygen, Error, PyException, "解码、实例化失败或执行时 trap");
+
+fn error(err: anyhow::Error) -> PyErr {
+    Error::new_err(format!("{err:#}"))
+}
+
+/// 解码后的模块
+#[pyclass(frozen)]
+struct Module {
+    wasm: WasmModule,
+}
+
+#[pymethods]
+impl Module {
+    #[new]
+    fn new(bytes: &[u8]) -> PyResult<Self> {
+        let mut wasm = WasmModule::default(bytes.to_vec());
+        wasm.decode().map_err(|err| error(err.into()))?;
+        Ok(Self { wasm })
+    }
+
+    /// 导出项的名字，按名字排序
+    #[getter]
+    fn exports(&self) -> Vec<String> {
+        let entries = &self.wasm.section.export.entries;
+        let mut names = entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// 导出函数的名字，按名字排序
+    #[getter]
+    fn functions(&self) -> Vec<String> {
+        let mut names = self
+            .wasm
+            .section
+            .export
+            .entries
+            .iter()
+            .filter(|e| matches!(e.kind, ExportKind::Func(_)))
+            .map(|e| e.name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
+
+/// 模块的实例，创建时执行 start 函数
+#[pyclass]
+struct Instance {
+    wasm: WasmModule,
+}
+
+#[pymethods]
+impl Instance {
+    #[new]
+    fn new(module: &Module) -> PyResult<Self> {
+        let mut wasm = WasmModule::default(module.wasm.raw.clone());
+        wasm.config = module.wasm.config.clone();
+        wasm.decode().map_err(|err| error(err.into()))?;
+        Linker::new().instantiate(&mut wasm).map_err(error)?;
+        Ok(Self { wasm })
+    }
+
+    /// 调用导出函数 name：没有返回值时返回 None，一个返回值时返回它本身，否则返回元组；
+    /// 执行期间释放 GIL
+    #[pyo3(signature = (name, *args))]
+    fn invoke(
+        &mut self,
+        py: Python<'_>,
+        name: &str,
+        args: &Bound<'_, PyTuple>,
+    ) -> PyResult<PyObject> {
+        let (_, ty) = self.wasm.export_func(name).map_err(error)?;
+        if args.len() != ty.params.len() {
+            return Err(Error::new_err(format!(
+                "`{name}` takes {} arguments but {} were given",
+                ty.params.len(),
+                args.len()
+            )));
+        }
+        let args = ty
+            .params
+            .iter()
+            .zip(args.iter())
+            .map(|(ty, arg)| to_value(*ty, &arg))
+            .collect::<PyResult<Vec<_>>>()?;
+        let wasm = &mut self.wasm;
+        let values = py
+            .allow_threads(|| wasm.invoke(name, &args))
+            .map_err(error)?;
+        let mut values = values
+            .into_iter()
+            .map(|value| from_value(py, value))
+            .collect::<PyResult<Vec<_>>>()?;
+        match values.len() {
+            0 => Ok(py.None()),
+            1 => Ok(values.remove(0)),
+            _ => Ok(PyTuple::new(py, values)?.into_any().unbind()),
+        }
+    }
+
+    /// 第 0 个内存的字节数，没有内存时为 0
+    fn memory_size(&self) -> usize {
+        self.wasm.mem.first().map_or(0, |mem| mem.len())
+    }
+
+    /// 读取第 0 个内存 offset 开始的 length 个字节
+    fn read_memory<'py>(
+        &self,
+        py: Python<'py>,
+        offset: usize,
+        length: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let mem = self
+            .wasm
+            .mem
+            .first()
+            .ok_or_else(|| Error::new_err("instance has no memory"))?;
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| mem.get(offset..end))
+            .ok_or_else(|| Error::new_err("out of bounds memory access"))?;
+        Ok(PyBytes::new(py, bytes))
+    }
+
+    /// 把 data 写到第 0 个内存的 offset
+    fn write_memory(&mut self, offset: usize, data: &[u8]) -> PyResult<()> {
+        let mem = self
+            .wasm
+            .mem
+            .first_mut()
+            .ok_or_else(|| Error::new_err("instance has no memory"))?;
+        let dst = offset
+            .checked_add(data.len())
+            .and_then(|end| mem.get_mut(offset..end))
+            .ok_or_else(|| Error::new_err("out of bounds memory access"))?;
+        dst.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+/// 按参数类型转换：整数可以是有符号或无符号的位模式，空引用为 None
+fn to_value(ty: ValueType, arg: &Bound<'_, PyAny>) -> PyResult<WasmValue> {
+    Ok(match ty {
+        ValueType::I32 => match arg.extract::<i32>() {
+            Ok(v) => WasmValue::I32(v),
+            Err(_) => WasmValue::I32(arg.extract::<u32>()? as i32),
+        },
+        ValueType::I64 => match arg.extract::<i64>() {
+            Ok(v) => WasmValue::I64(v),
+            Err(_) => WasmValue::I64(arg.extract::<u64>()? as i64),
+        },
+        ValueType::F32 => WasmValue::F32(arg.extract()?),
+        ValueType::F64 => WasmValue::F64(arg.extract()?),
+        ValueType::V128 => match arg.extract::<i128>() {
+            Ok(v) => WasmValue::V128(v),
+            Err(_) => WasmValue::V128(arg.extract::<u128>()? as i128),
+        },
+        ValueType::FuncRef => {
+            WasmValue::FuncRef(arg.extract::<Option<usize>>()?.unwrap_or(NULL_REF))
+        }
+        ValueType::ExternRef => {
+            WasmValue::ExternRef(arg.extract::<Option<usize>>()?.unwrap_or(NULL_REF))
+        }
+        ValueType::AnyRef => return Err(Error::new_err("anyref arguments are not supported")),
+    })
+}
+
+fn from_value(py: Python<'_>, value: WasmValue) -> PyResult<PyObject> {
+    let reference = |r: usize| (r != NULL_REF).then_some(r);
+    Ok(match value {
+        WasmValue::I32(v) => v.into_pyobject(py)?.into_any().unbind(),
+        WasmValue::I64(v) => v.into_pyobject(py)?.into_any().unbind(),
+        WasmValue::F32(v) => v.into_pyobject(py)?.into_any().unbind(),
+        WasmValue::F64(v) => v.into_pyobject(py)?.into_any().unbind(),
+        WasmValue::V128(v) => v.into_pyobject(py)?.into_any().unbind(),
+        WasmValue::FuncRef(r) | WasmValue::ExternRef(r) => {
+            reference(r).into_pyobject(py)?.into_any().unbind()
+        }
+        WasmValue::NOP => py.None(),
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "oxygen")]
+fn oxygen_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Module>()?;
+    m.add_class::<Instance>()?;
+    m.add("Error", m.py().get_type::<Error>())?;
+    Ok(())
+}
+
+#[test]
+fn test_python() {
+    use pyo3::ffi::c_str;
+
+    let src = r#"(module
+      (memory 1)
+      (data (i32.const 0) "hi")
+      (func (export "add") (param i32 i32) (result i32) (i32.add (local.get 0) (local.get 1)))
+      (func (export "swap") (param i64 f64) (result f64 i64) (local.get 1) (local.get 0))
+      (func (export "boom") (unreachable)))"#;
+    let buf = oxygen::runtime::wat::compile(src).unwrap();
+    pyo3::append_to_inittab!(oxygen_py);
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let locals = pyo3::types::PyDict::new(py);
+        locals.set_item("buf", PyBytes::new(py, &buf)).unwrap();
+        py.run(
+            c_str!(
+                r#"
+import oxygen
+module = oxygen.Module(buf)
+assert module.functions == ["add", "boom", "swap"]
+instance = oxygen.Instance(module)
+assert instance.invoke("add", 2, 3) == 5
+assert instance.invoke("add", 0xffffffff, 1) == 0
+assert instance.invoke("swap", -1, 0.5) == (0.5, -1)
+try:
+    instance.invoke("boom")
+    assert False
+except oxygen.Error as err:
+    assert "unreachable" in str(err)
+try:
+    instance.invoke("add", 1)
+    assert False
+except oxygen.Error as err:
+    assert "takes 2 arguments" in str(err)
+assert instance.memory_size() == 65536
+assert instance.read_memory(0, 2) == b"hi"
+instance.write_memory(1, b"o")
+assert instance.read_memory(0, 2) == b"ho"
+try:
+    instance.read_memory(65535, 2)
+    assert False
+except oxygen.Error:
+    pass
+try:
+    oxygen.Module(buf[:-1])
+    assert False
+except oxygen.Error:
+    pass
+"#
+            ),
+            None,
+            Some(&locals),
+        )
+        .unwrap();
+    });
+}
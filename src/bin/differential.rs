@@ -0,0 +1,97 @@
+//! 差分测试工具：`cargo run --features differential --bin differential -- module.wasm`
+
+use std::{fs::read, path::PathBuf, process, time::Duration};
+
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use oxygen::runtime::{
+    config::{Engine, Limits, RuntimeConfig},
+    differential::{Differential, Options},
+};
+
+/// Call the exports of a module with random arguments under oxygen and wasmi,
+/// and report every call whose results or traps differ
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Arguments {
+    url: PathBuf,
+    /// Only call this exported function
+    #[arg(long, value_name = "NAME")]
+    invoke: Option<String>,
+    /// Calls per exported function that takes parameters
+    #[arg(long, default_value_t = 100)]
+    iterations: usize,
+    /// Seed of the argument generator; the same seed repeats the same calls
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Engine oxygen executes with
+    #[arg(long, value_enum, default_value_t = EngineArg::Match)]
+    engine: EngineArg,
+    /// Time limit of each call under oxygen, in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    timeout_ms: u64,
+    /// Fuel of each call under wasmi (roughly the number of instructions)
+    #[arg(long, default_value_t = 100_000_000)]
+    fuel: u64,
+    /// Maximum call depth under oxygen
+    #[arg(long, default_value_t = 10_000)]
+    max_call_depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum EngineArg {
+    Match,
+    Threaded,
+    Jit,
+}
+
+impl EngineArg {
+    fn engine(self) -> anyhow::Result<Engine> {
+        Ok(match self {
+            EngineArg::Match => Engine::Match,
+            EngineArg::Threaded => Engine::Threaded,
+            #[cfg(feature = "jit")]
+            EngineArg::Jit => Engine::Jit,
+            #[cfg(not(feature = "jit"))]
+            EngineArg::Jit => {
+                anyhow::bail!("--engine jit needs oxygen built with the `jit` feature")
+            }
+        })
+    }
+}
+
+/// 深度递归时每层 wasm 调用占用较多原生栈
+const STACK_SIZE: usize = 1 << 30;
+
+fn main() -> anyhow::Result<()> {
+    let args = Arguments::parse();
+    let buf = read(&args.url).with_context(|| format!("can't read file {:?}", args.url))?;
+    let limits = Limits {
+        max_call_depth: Some(args.max_call_depth),
+        ..Default::default()
+    };
+    let options = Options {
+        config: RuntimeConfig::default()
+            .engine(args.engine.engine()?)
+            .limits(limits),
+        iterations: args.iterations,
+        seed: args.seed,
+        timeout: Duration::from_millis(args.timeout_ms),
+        fuel: args.fuel,
+    };
+    // 未实现的指令等 panic 会被捕获并报告，不输出 panic 信息
+    std::panic::set_hook(Box::new(|_| {}));
+    let report = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let diff = Differential::new(buf, options)?;
+            diff.run(args.invoke.as_deref())
+        })?
+        .join()
+        .map_err(|_| anyhow::anyhow!("differential testing panicked"))??;
+    print!("{report}");
+    if !report.mismatches.is_empty() {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -1,134 +1,1718 @@
 use anyhow::Context;
+#[cfg(feature = "serde")]
+use oxygen::runtime::cache::module_hash;
 use oxygen::runtime::{
-    decoder::{ImportKind, WasmModule, WasmValue},
+    callgraph::CallGraph,
+    check::CheckReport,
+    component::unwrap_core,
+    config::{Engine, Limits, RuntimeConfig},
+    coverage::Coverage,
+    debug::CommandDebugger,
+    debuginfo::SourceMap,
+    decoder::WasmModule,
+    extract::extract,
+    hexdump::HexDump,
+    linker::Linker,
+    minimize::{minimize, run_module},
+    profile::{func_names, Profiler},
+    repl::{invoke_text, Repl},
+    section::opcode::OpClass,
+    section::{
+        custom::{Dylink, FeaturePrefix, Producers, TargetFeatures},
+        data::DataKind,
+        element::Element,
+        export::ExportKind,
+        import,
+        types::{CompositeType, FieldType, StorageType, SubType},
+        typings::{Limit, ValueType},
+    },
+    spectest::WastRunner,
+    stats::ModuleStats,
+    trace::{SharedTracer, TraceEvent, TraceFilter, TraceFormat, TraceLog},
+    transform::{is_debug_section, ModuleBuilder},
+    trap::Trap,
+    wasi::WasiCtx,
     OxygenRuntime,
 };
-use std::{collections::HashMap, fs::read, path::Path, process};
+use std::{
+    cell::RefCell,
+    fs::{read, write},
+    io::BufReader,
+    net::{SocketAddr, TcpListener},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
 
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde_json::json;
 
 #[derive(clap::Parser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, arg_required_else_help = true)]
 struct Arguments {
+    /// Print a machine-readable description of all commands and flags as JSON
+    #[arg(long)]
+    help_json: bool,
+    /// Log decoding and instantiation to stderr; repeat for more detail
+    /// (-v: info, -vv: debug, -vvv: every function call)
+    #[cfg(feature = "log")]
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
     #[command(subcommand)]
-    command: Command,
+    command: Option<Command>,
 }
 #[derive(Debug, Subcommand)]
 enum Command {
-    Run(RunArgs),
-    Inspect(RunArgs),
+    /// Instantiate a wasm module and call its exported `_start`
+    Run(ExecArgs),
+    /// Like `run`, but pause before the first instruction and read debugger commands
+    /// (breakpoints, single-step, locals, stack, memory) from stdin
+    Debug(ExecArgs),
+    /// Print the decoded sections and instructions of a wasm module
+    Inspect(InspectArgs),
+    /// Report opcode frequency, section sizes, function sizes, imports/exports and
+    /// memory/data footprint of a wasm module
+    Stats(StatsArgs),
+    /// List the opcodes a module uses that the interpreter doesn't implement yet,
+    /// and the functions that would hit them
+    Coverage(CoverageArgs),
+    /// Report without running whether a module can run: unresolved imports,
+    /// unimplemented opcodes, unsupported proposals and memory requirements;
+    /// exit with status 1 if it can't
+    Check(CheckArgs),
+    /// Build the static call graph, find functions unreachable from the exports and
+    /// report their sizes, or print the graph with `--callgraph dot`
+    Analyze(AnalyzeArgs),
+    /// Decode and type-check a wasm module, print `ok` or every error with its offset,
+    /// and exit with status 1 if the module is invalid
+    Validate(ValidateArgs),
+    /// Decode and lower a module ahead of time into an artifact that `oxygen run`
+    /// loads directly, e.g. `oxygen compile app.wasm -o app.oxy`
+    Compile(CompileArgs),
+    /// Write a new module containing only the given functions and their dependencies
+    Extract(ExtractArgs),
+    /// Write a copy of a module without its custom sections (or only without debug
+    /// info), optionally renaming exports or adding an exported memory
+    Strip(StripArgs),
+    /// Shrink a module that fails to decode, traps or panics, keeping the same failure
+    Minimize(MinimizeArgs),
+    /// Run a .wast script (the WebAssembly spec test format) and report failed assertions
+    Wast(WastArgs),
+    /// Load a module and call functions, inspect memory and globals interactively
+    Repl(ReplArgs),
+    /// Generate a shell completion script, e.g. `oxygen completions bash > oxygen.bash`
+    Completions { shell: clap_complete::Shell },
+}
+
+#[derive(Debug, Args)]
+struct InspectArgs {
+    url: String,
+    /// Only print these sections, e.g. `--section types,imports,exports`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    section: Vec<InspectSection>,
+    /// Print the module structure as JSON
+    #[arg(long)]
+    json: bool,
+    /// Only print section headers (offset, size, count), not their entries
+    #[arg(long)]
+    headers_only: bool,
+    /// Dump the raw bytes annotated with sections, LEB fields and instructions;
+    /// malformed modules are dumped up to the decode error
+    #[arg(long, conflicts_with_all = ["json", "section", "headers_only"])]
+    hex: bool,
+}
+
+#[derive(Debug, Args)]
+struct StatsArgs {
+    url: String,
+    /// Print the statistics as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct CoverageArgs {
+    url: String,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct CheckArgs {
+    url: String,
+    /// Provide the WASI imports that `oxygen run` defines
+    #[arg(long)]
+    wasi: bool,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct AnalyzeArgs {
+    url: String,
+    /// Print the whole call graph instead of the dead-code report
+    #[arg(long, value_enum)]
+    callgraph: Option<GraphFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz, e.g. `oxygen analyze app.wasm --callgraph dot | dot -Tsvg`
+    Dot,
+    Json,
+}
+
+#[derive(Debug, Args)]
+struct ValidateArgs {
+    url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum InspectSection {
+    Custom,
+    Types,
+    Imports,
+    Functions,
+    Tables,
+    Memories,
+    Globals,
+    Exports,
+    Start,
+    Elements,
+    Code,
+    Data,
+    DataCount,
+}
+
+#[derive(Debug, Args)]
+struct ExecArgs {
+    url: String,
+    /// Only instantiate the module (including its start section), don't call the exported `_start`
+    #[arg(long)]
+    no_entry: bool,
+    /// Print a report (exit status or trap, instructions executed, memory, wall time,
+    /// host call counts) to stderr when the module finishes
+    #[arg(long, value_enum)]
+    report: Option<ReportFormat>,
+    /// Log execution events to stderr: `ops` (every instruction with the frame's stack),
+    /// `memory` (loads and stores), `calls` (calls, returns and traps) or `all`
+    #[arg(long, value_enum, value_delimiter = ',', num_args = 0.., require_equals = true,
+          default_missing_value = "all")]
+    trace: Vec<TraceWhat>,
+    /// Format of `--trace` output
+    #[arg(long, value_enum, default_value_t = TraceOutput::Text)]
+    trace_format: TraceOutput,
+    /// Only trace inside these functions, e.g. `--trace-funcs 3` or `--trace-funcs 3-10`
+    #[arg(long, value_parser = parse_func_range)]
+    trace_funcs: Option<RangeInclusive<usize>>,
+    /// Only trace instructions of these classes, e.g. `--trace-ops control,memory`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    trace_ops: Vec<TraceOpClass>,
+    /// Count instructions and time per function, call edges and hot loops; write
+    /// folded stacks (instructions per call stack, for flamegraph tools) to this file
+    /// and print a summary to stderr
+    #[arg(long, value_name = "PATH")]
+    profile: Option<PathBuf>,
+    /// Keep precompiled modules in this directory, so later runs of the same module
+    /// skip decoding
+    #[arg(long, value_name = "DIR")]
+    cache: Option<PathBuf>,
+    /// Parse function bodies on their first call instead of at load time
+    #[arg(long)]
+    lazy: bool,
+    /// Execution engine; `jit` compiles functions to native code with Cranelift
+    /// (needs oxygen built with the `jit` feature)
+    #[arg(long, value_enum, default_value_t = EngineArg::Match)]
+    engine: EngineArg,
+    /// Trap with a stack overflow when calls nest deeper than this, instead of
+    /// exhausting the native stack
+    #[arg(long, default_value_t = 1000)]
+    max_call_depth: usize,
+    /// Set an environment variable for the guest, e.g. `--env KEY=VAL` (repeatable)
+    #[arg(long = "env", value_parser = parse_env)]
+    env: Vec<(String, String)>,
+    /// Listen on a TCP address and give the socket to the guest as a preopened fd
+    /// (the first one is fd 3) that it can `sock_accept` connections from,
+    /// e.g. `--tcplisten 127.0.0.1:8080` (repeatable)
+    #[arg(long = "tcplisten", value_name = "ADDR")]
+    tcplisten: Vec<SocketAddr>,
+    /// Instantiate a helper module first and make its exports importable under NAME,
+    /// e.g. `--register math=math.wasm` (repeatable, registered in order)
+    #[arg(long = "register", value_parser = parse_register, value_name = "NAME=PATH")]
+    register: Vec<(String, PathBuf)>,
+    /// Provide the `env` imports emscripten-compiled modules expect (abort, __assert_fail,
+    /// emscripten_resize_heap, ...) and create the `env` memory and table they import
+    #[arg(long)]
+    emscripten: bool,
+    /// Call this exported function instead of `_start` and print its results,
+    /// e.g. `oxygen run math.wasm --invoke add 1 -2`
+    #[arg(long, value_name = "NAME")]
+    invoke: Option<String>,
+    /// Arguments of `--invoke`, parsed by the export's parameter types (i32/i64/f32/f64)
+    #[arg(requires = "invoke", allow_hyphen_values = true, value_name = "VALUES")]
+    values: Vec<String>,
+    /// Arguments passed to the guest after `--`, e.g. `oxygen run app.wasm -- -v input.txt`
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum EngineArg {
+    Match,
+    Threaded,
+    Jit,
+}
+
+impl EngineArg {
+    fn engine(self) -> anyhow::Result<Engine> {
+        Ok(match self {
+            EngineArg::Match => Engine::Match,
+            EngineArg::Threaded => Engine::Threaded,
+            #[cfg(feature = "jit")]
+            EngineArg::Jit => Engine::Jit,
+            #[cfg(not(feature = "jit"))]
+            EngineArg::Jit => {
+                anyhow::bail!("--engine jit needs oxygen built with the `jit` feature")
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum TraceWhat {
+    All,
+    Ops,
+    Memory,
+    Calls,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TraceOutput {
+    Text,
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TraceOpClass {
+    Control,
+    Reference,
+    Parametric,
+    Variable,
+    Table,
+    Memory,
+    Numeric,
+    Vector,
+}
+
+impl From<TraceOpClass> for OpClass {
+    fn from(value: TraceOpClass) -> Self {
+        match value {
+            TraceOpClass::Control => OpClass::Control,
+            TraceOpClass::Reference => OpClass::Reference,
+            TraceOpClass::Parametric => OpClass::Parametric,
+            TraceOpClass::Variable => OpClass::Variable,
+            TraceOpClass::Table => OpClass::Table,
+            TraceOpClass::Memory => OpClass::Memory,
+            TraceOpClass::Numeric => OpClass::Numeric,
+            TraceOpClass::Vector => OpClass::Vector,
+        }
+    }
+}
+
+fn parse_func_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    let parse = |s: &str| s.trim().parse::<usize>().map_err(|e| format!("{s:?}: {e}"));
+    match s.split_once('-') {
+        Some((start, end)) => Ok(parse(start)?..=parse(end)?),
+        None => parse(s).map(|idx| idx..=idx),
+    }
+}
+
+fn parse_env(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("{s:?}: expected KEY=VAL")),
+    }
+}
+
+fn parse_register(s: &str) -> Result<(String, PathBuf), String> {
+    match s.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((name.to_string(), PathBuf::from(path)))
+        }
+        _ => Err(format!("{s:?}: expected NAME=PATH")),
+    }
+}
+
+impl ExecArgs {
+    /// guest 的 argv[0] 是模块路径；有 `--tcplisten` 时允许 sock_accept
+    fn wasi_ctx(&self) -> anyhow::Result<WasiCtx> {
+        let mut ctx = WasiCtx::new()
+            .arg(&self.url)
+            .args(self.args.iter())
+            .envs(self.env.iter().cloned())
+            .allow_sock_accept(!self.tcplisten.is_empty());
+        for addr in &self.tcplisten {
+            let listener =
+                TcpListener::bind(addr).with_context(|| format!("can't listen on {addr}"))?;
+            ctx = ctx.tcp_listen(listener);
+        }
+        Ok(ctx)
+    }
+
+    fn trace_log(&self) -> Option<TraceLog> {
+        if self.trace.is_empty() {
+            return None;
+        }
+        let mut filter = TraceFilter {
+            funcs: self.trace_funcs.clone(),
+            ..Default::default()
+        };
+        if !self.trace.contains(&TraceWhat::All) {
+            filter.events = self
+                .trace
+                .iter()
+                .map(|what| match what {
+                    TraceWhat::Ops => TraceEvent::Ops,
+                    TraceWhat::Memory => TraceEvent::Memory,
+                    _ => TraceEvent::Calls,
+                })
+                .collect();
+        }
+        if !self.trace_ops.is_empty() {
+            filter.classes = Some(self.trace_ops.iter().map(|&c| c.into()).collect());
+        }
+        let format = match self.trace_format {
+            TraceOutput::Text => TraceFormat::Text,
+            TraceOutput::Jsonl => TraceFormat::Jsonl,
+        };
+        Some(TraceLog::stderr(filter, format))
+    }
+}
+
+#[derive(Debug, Args)]
+struct CompileArgs {
+    url: String,
+    /// Output file
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ExtractArgs {
+    url: String,
+    /// Export names or function indices to keep, e.g. `--funcs main,1`
+    #[arg(long, value_delimiter = ',', required = true)]
+    funcs: Vec<String>,
+    /// Output file
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct StripArgs {
+    url: String,
+    /// Output file
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Only drop debug info (`.debug_*`, `name`, `sourceMappingURL`), keep other custom sections
+    #[arg(long)]
+    debug_only: bool,
+    /// Rename an export, e.g. `--rename-export _start=main`
+    #[arg(long, value_name = "OLD=NEW")]
+    rename_export: Vec<String>,
+    /// Add a one-page memory exported under this name to a module without memory
+    #[arg(long, value_name = "NAME")]
+    export_memory: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct MinimizeArgs {
+    url: String,
+    /// Output file
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Give up on a candidate after this many milliseconds (e.g. an introduced infinite loop)
+    #[arg(long, default_value_t = 1000)]
+    timeout_ms: u64,
+}
+
+#[derive(Debug, Args)]
+struct WastArgs {
+    url: String,
+    /// Cancel an action after this many milliseconds and count it as failed
+    #[arg(long, default_value_t = 10_000)]
+    timeout_ms: u64,
+    /// Call depth at which `assert_exhaustion` expects the stack to be exhausted
+    #[arg(long, default_value_t = 1000)]
+    max_call_depth: usize,
 }
 
 #[derive(Debug, Args)]
-struct RunArgs {
+struct ReplArgs {
     url: String,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Json,
+}
+
+/// 执行 wasm 的线程栈大小：每层 wasm 调用占用较多原生栈，调用深度由 `Limits` 限制
+const EXEC_STACK_SIZE: usize = 1 << 30;
+
+/// 开启 `--report` 时记录开始时间
+static REPORT_START: OnceLock<Instant> = OnceLock::new();
+
+thread_local! {
+    /// `--profile` 的输出文件和统计
+    static PROFILE: RefCell<Option<(PathBuf, Arc<Mutex<Profiler>>)>> = const { RefCell::new(None) };
+}
+
 fn main() -> anyhow::Result<()> {
     let cmd = Arguments::parse();
+    #[cfg(feature = "log")]
+    StderrLogger::init(cmd.verbose);
+    if cmd.help_json {
+        let mut cmd = Arguments::command();
+        cmd.build();
+        let json = help_json(&cmd);
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    let Some(command) = cmd.command else {
+        Arguments::command().print_help()?;
+        return Ok(());
+    };
 
-    match cmd.command {
-        Command::Run(args) => {
+    match command {
+        Command::Run(args) => on_exec_stack(move || run(&args, RuntimeConfig::default()))?,
+        Command::Debug(args) => on_exec_stack(move || {
+            let debugger =
+                CommandDebugger::new(BufReader::new(std::io::stdin()), std::io::stdout());
+            let config = RuntimeConfig::default().debugger(Arc::new(Mutex::new(debugger)));
+            run(&args, config)
+        })?,
+        Command::Inspect(args) => {
             let url = Path::new(&args.url);
             let buf = read(url).context(format!("can't read file {:?}", url))?;
+            if args.hex {
+                let mut wasm = WasmModule::default(buf);
+                let result = wasm.decode();
+                let dump = HexDump::new(&wasm);
+                match &result {
+                    Ok(()) => print!("{dump}"),
+                    Err(err) => {
+                        print!("{}", dump.with_error(err));
+                        process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
 
             let mut rt = OxygenRuntime::default();
             rt.load(buf)?;
+            let sections = if args.section.is_empty() {
+                InspectSection::value_variants()
+            } else {
+                &args.section[..]
+            };
             for wasm in &mut rt.modes {
-                let mut import_object = HashMap::new();
-                let mut wasi_snapshot_preview1 = HashMap::new();
-                wasi_snapshot_preview1.insert(
-                    format!("fd_write"),
-                    ImportKind::Func(wasi_snapshot_preview1_fd_write),
-                );
-                wasi_snapshot_preview1.insert(
-                    format!("proc_exit"),
-                    ImportKind::Func(wasi_snapshot_preview1_proc_exit),
-                );
-                import_object.insert(format!("wasi_snapshot_preview1"), wasi_snapshot_preview1);
-
-                wasm.instance(Some(import_object))?;
-                wasm.start()?;
+                if args.json {
+                    let json = inspect_json(wasm, sections, args.headers_only);
+                    println!("{}", serde_json::to_string_pretty(&json)?);
+                    continue;
+                }
+                println!("{:?}", url.display());
+                if args.section.is_empty() && !args.headers_only {
+                    println!("{}", wasm);
+                    continue;
+                }
+                println!("Version: {:x?}", wasm.version);
+                println!("Size: {:?}\n", wasm.raw.len());
+                for section in sections {
+                    let text = section.text(wasm);
+                    match args.headers_only {
+                        true => println!("{}", text.lines().next().unwrap_or_default()),
+                        false => print!("{text}"),
+                    }
+                }
             }
         }
-        Command::Inspect(args) => {
+        Command::Stats(args) => {
             let url = Path::new(&args.url);
             let buf = read(url).context(format!("can't read file {:?}", url))?;
 
             let mut rt = OxygenRuntime::default();
             rt.load(buf)?;
-            for wasm in &mut rt.modes {
-                println!("{:?}", url.display());
-                println!("{}", wasm);
+            for wasm in &rt.modes {
+                let stats = ModuleStats::new(wasm);
+                match args.json {
+                    true => println!("{}", serde_json::to_string_pretty(&stats.to_json())?),
+                    false => print!("{stats}"),
+                }
+            }
+        }
+        Command::Coverage(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+
+            let mut rt = OxygenRuntime::default();
+            rt.load(buf)?;
+            for wasm in &rt.modes {
+                let coverage = Coverage::new(wasm);
+                match args.json {
+                    true => println!("{}", serde_json::to_string_pretty(&coverage.to_json())?),
+                    false => print!("{coverage}"),
+                }
+            }
+        }
+        Command::Check(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+
+            let mut rt = OxygenRuntime::default();
+            rt.load(buf)?;
+            let linker = match args.wasi {
+                true => wasi_linker()?,
+                false => Linker::new(),
+            };
+            let mut ready = true;
+            for wasm in &rt.modes {
+                let report = CheckReport::new(wasm, &linker);
+                ready &= report.is_ready();
+                match args.json {
+                    true => println!("{}", serde_json::to_string_pretty(&report.to_json())?),
+                    false => print!("{report}"),
+                }
+            }
+            if !ready {
+                process::exit(1);
+            }
+        }
+        Command::Analyze(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+
+            let mut rt = OxygenRuntime::default();
+            rt.load(buf)?;
+            for wasm in &rt.modes {
+                let graph = CallGraph::new(wasm);
+                match args.callgraph {
+                    Some(GraphFormat::Dot) => print!("{}", graph.to_dot()),
+                    Some(GraphFormat::Json) => {
+                        println!("{}", serde_json::to_string_pretty(&graph.to_json())?)
+                    }
+                    None => print!("{graph}"),
+                }
+            }
+        }
+        Command::Validate(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+            let errors = validate(buf);
+            if errors.is_empty() {
+                println!("ok");
+                return Ok(());
+            }
+            for error in &errors {
+                println!("{error}");
+            }
+            process::exit(1);
+        }
+        Command::Compile(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+            write(&args.output, compile(buf)?)
+                .with_context(|| format!("can't write file {:?}", args.output))?;
+        }
+        Command::Extract(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+            let out = extract(&buf, &args.funcs)?;
+            write(&args.output, out)
+                .with_context(|| format!("can't write file {:?}", args.output))?;
+        }
+        Command::Strip(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+            let mut builder = ModuleBuilder::parse(&buf)?;
+            match args.debug_only {
+                true => builder.strip_custom(is_debug_section),
+                false => builder.strip_custom(|_| true),
+            };
+            for rename in &args.rename_export {
+                let (from, to) = rename
+                    .split_once('=')
+                    .with_context(|| format!("expected OLD=NEW, found `{rename}`"))?;
+                builder.rename_export(from, to)?;
+            }
+            if let Some(name) = &args.export_memory {
+                builder.export_memory(name, 1, None)?;
+            }
+            let out = builder.finish();
+            eprintln!("{} -> {} bytes", buf.len(), out.len());
+            write(&args.output, out)
+                .with_context(|| format!("can't write file {:?}", args.output))?;
+        }
+        Command::Minimize(args) => {
+            let url = Path::new(&args.url);
+            let buf = read(url).context(format!("can't read file {:?}", url))?;
+            let timeout = Duration::from_millis(args.timeout_ms);
+            // 候选模块的 panic 会被捕获，不输出 panic 信息
+            std::panic::set_hook(Box::new(|_| {}));
+            eprintln!("failure: {}", run_module(&buf, timeout));
+            let out = minimize(&buf, timeout)?;
+            eprintln!("{} -> {} bytes", buf.len(), out.len());
+            write(&args.output, out)
+                .with_context(|| format!("can't write file {:?}", args.output))?;
+        }
+        Command::Wast(args) => {
+            let url = Path::new(&args.url);
+            let src = std::fs::read_to_string(url)
+                .with_context(|| format!("can't read file {:?}", url))?;
+            std::panic::set_hook(Box::new(|_| {}));
+            // 每层 wasm 调用占用较多原生栈，在足够大的线程栈上执行
+            let report = std::thread::Builder::new()
+                .stack_size(EXEC_STACK_SIZE)
+                .spawn(move || {
+                    let limits = Limits {
+                        max_call_depth: Some(args.max_call_depth),
+                        ..Default::default()
+                    };
+                    let mut runner = WastRunner::new(RuntimeConfig::default().limits(limits));
+                    runner.timeout = Duration::from_millis(args.timeout_ms);
+                    runner.run_script(&src)
+                })?
+                .join()
+                .map_err(|_| anyhow::anyhow!("wast runner panicked"))??;
+            for failure in &report.failures {
+                println!("{}:{}: {}", url.display(), failure.line, failure.message);
+            }
+            println!("{report}");
+            if !report.is_ok() {
+                process::exit(1);
             }
         }
+        Command::Repl(args) => {
+            let config = RuntimeConfig::default().wasi(WasiCtx::new().arg(&args.url));
+            let mut repl = Repl::new(&args.url, config, wasi_linker()?)?;
+            println!("loaded {}, type `help` for commands", args.url);
+            repl.run(std::io::stdin().lock(), std::io::stdout())?;
+        }
+        Command::Completions { shell } => {
+            let mut cmd = Arguments::command();
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(shell, &mut cmd, name, &mut std::io::stdout());
+        }
     };
 
     Ok(())
 }
 
-pub fn wasi_snapshot_preview1_fd_write(
-    wasm: &mut WasmModule,
-    arg: &Vec<WasmValue>,
-) -> Vec<WasmValue> {
-    let arg = (arg[0], arg[1], arg[2], arg[3]);
-    let mem = &mut wasm.mem[0];
-    match arg {
-        (
-            WasmValue::I32(_fd),
-            WasmValue::I32(offset),
-            WasmValue::I32(len),
-            WasmValue::I32(nwritten),
-        ) => {
-            let mut offset = offset;
-            let mut data = vec![];
-            let mut num = 0;
-            for _ in 0..len {
-                // let oft = offset >> 2;
-                let mut ptr = [0; 4];
-                for k in 0..4 {
-                    ptr[k] = mem[offset as usize + k];
-                }
-                let ptr = u32::from_le_bytes(ptr);
-                let mut l = [0; 4];
-                for k in 4..8 {
-                    l[k - 4] = mem[offset as usize + k];
-                }
-                let l = u32::from_le_bytes(l);
-                offset += 8;
-                for j in 0..l {
-                    let p = ptr + j;
-                    data.push(mem[p as usize]);
+/// `oxygen validate` 的输出：解码失败时为带十六进制上下文的诊断，否则为每个验证错误一行
+fn validate(buf: Vec<u8>) -> Vec<String> {
+    let mut wasm = WasmModule::default(buf);
+    if let Err(err) = wasm.decode() {
+        return vec![err.diagnostic(&wasm.raw)];
+    }
+    wasm.validate()
+        .iter()
+        .map(|err| format!("error: {err}"))
+        .collect()
+}
+
+/// 实例化模块并调用 `_start` 或 `--invoke` 指定的导出
+fn run(args: &ExecArgs, config: RuntimeConfig) -> anyhow::Result<()> {
+    let url = Path::new(&args.url);
+    let buf = read(url).context(format!("can't read file {:?}", url))?;
+    // 组件按其中的 core module 运行，缓存也按 core module 计算
+    let buf = unwrap_core(buf).with_context(|| format!("can't run component {url:?}"))?;
+
+    let limits = Limits {
+        max_call_depth: Some(args.max_call_depth),
+        ..config.limits.clone()
+    };
+    let mut config = config
+        .wasi(args.wasi_ctx()?)
+        .lazy_decode(args.lazy)
+        .engine(args.engine.engine()?)
+        .limits(limits);
+    let mut tracers: Vec<SharedTracer> = vec![];
+    if let Some(log) = args.trace_log() {
+        tracers.push(Arc::new(Mutex::new(log)));
+    }
+    if let Some(path) = &args.profile {
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        tracers.push(profiler.clone());
+        PROFILE.with(|profile| *profile.borrow_mut() = Some((path.clone(), profiler)));
+    }
+    config.tracer = match tracers.len() {
+        0 => None,
+        1 => tracers.pop(),
+        _ => Some(Arc::new(Mutex::new(tracers))),
+    };
+    let mut rt = OxygenRuntime::new(config);
+    match &args.cache {
+        _ if is_artifact(&buf) => load_artifact(&mut rt, &buf)?,
+        Some(dir) => load_cached(&mut rt, buf, dir)?,
+        None => rt.load(buf)?,
+    }
+    for wasm in &mut rt.modes {
+        load_source_map(wasm, url);
+    }
+    let mut linker = wasi_linker()?;
+    for (name, path) in &args.register {
+        register(&mut linker, name, path, &rt.config)?;
+    }
+    if let (true, Some(wasm)) = (args.emscripten, rt.modes.first()) {
+        linker.define_emscripten(wasm)?;
+    }
+    if args.report.is_some() {
+        REPORT_START.get_or_init(Instant::now);
+    }
+    for wasm in &mut rt.modes {
+        let result = linker.instantiate(wasm).and_then(|_| {
+            if let Some(name) = &args.invoke {
+                for line in invoke_text(wasm, name, &args.values)? {
+                    println!("{line}");
                 }
-                num += l;
+            } else if !args.no_entry && wasm.exports.contains_key("_start") {
+                wasm.start()?;
+            }
+            Ok(())
+        });
+        if args.report.is_some() {
+            let status = match &result {
+                Ok(_) => json!({ "kind": "ok" }),
+                Err(err) => match err.downcast_ref::<Trap>() {
+                    Some(Trap::Exit { code }) => json!({ "kind": "exit", "code": code }),
+                    Some(trap) => json!({ "kind": "trap", "message": trap.to_string() }),
+                    None => json!({ "kind": "error", "message": format!("{err:#}") }),
+                },
+            };
+            emit_report(wasm, status);
+        }
+        emit_profile(wasm)?;
+        // guest 的退出码作为进程的退出码
+        if let Some(code) = result.as_ref().err().and_then(Trap::exit_code) {
+            process::exit(code);
+        }
+        result?;
+    }
+    Ok(())
+}
+
+/// 在栈大小为 `EXEC_STACK_SIZE` 的线程上执行 f，panic 照原样传出
+fn on_exec_stack<T: Send + 'static>(
+    f: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<T> {
+    let handle = std::thread::Builder::new()
+        .stack_size(EXEC_STACK_SIZE)
+        .spawn(f)?;
+    handle
+        .join()
+        .unwrap_or_else(|err| std::panic::resume_unwind(err))
+}
+
+/// 实例化 `--register` 的模块，之后注册的模块和主模块可以导入它的导出
+fn register(
+    linker: &mut Linker,
+    name: &str,
+    path: &Path,
+    config: &RuntimeConfig,
+) -> anyhow::Result<()> {
+    let buf = read(path).context(format!("can't read file {:?}", path))?;
+    let buf = unwrap_core(buf).with_context(|| format!("can't run component {path:?}"))?;
+    let mut wasm = WasmModule::default(buf);
+    wasm.config = config.clone();
+    wasm.decode()
+        .with_context(|| format!("can't decode {path:?}"))?;
+    linker
+        .instantiate(&mut wasm)
+        .with_context(|| format!("can't instantiate {path:?}"))?;
+    linker.define_instance(name, wasm)?;
+    Ok(())
+}
+
+/// 按模块哈希在 dir 中查找预编译模块，没有或不能使用时解码并写入缓存
+#[cfg(feature = "serde")]
+fn load_cached(rt: &mut OxygenRuntime, buf: Vec<u8>, dir: &Path) -> anyhow::Result<()> {
+    let path = dir.join(format!("{:016x}.oxc", module_hash(&buf)));
+    if let Ok(compiled) = read(&path) {
+        if rt.load_compiled(buf.clone(), &compiled).is_ok() {
+            return Ok(());
+        }
+    }
+    rt.load(buf)?;
+    let wasm = rt.modes.last_mut().unwrap();
+    wasm.decode_bodies()?;
+    let compiled = wasm.serialize()?;
+    std::fs::create_dir_all(dir).with_context(|| format!("can't create directory {dir:?}"))?;
+    write(&path, compiled).with_context(|| format!("can't write file {path:?}"))?;
+    Ok(())
+}
+
+/// `oxygen compile` 的产物，函数体全部解码后再序列化
+#[cfg(feature = "serde")]
+fn compile(buf: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode()?;
+    wasm.decode_bodies()?;
+    wasm.compile_artifact()
+}
+
+#[cfg(not(feature = "serde"))]
+fn compile(_buf: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("compile needs oxygen built with the `serde` feature")
+}
+
+fn is_artifact(buf: &[u8]) -> bool {
+    buf.starts_with(b"\0oxy")
+}
+
+#[cfg(feature = "serde")]
+fn load_artifact(rt: &mut OxygenRuntime, buf: &[u8]) -> anyhow::Result<()> {
+    rt.load_artifact(buf)
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_artifact(_rt: &mut OxygenRuntime, _buf: &[u8]) -> anyhow::Result<()> {
+    anyhow::bail!("running compiled artifacts needs oxygen built with the `serde` feature")
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_cached(_rt: &mut OxygenRuntime, _buf: Vec<u8>, _dir: &Path) -> anyhow::Result<()> {
+    anyhow::bail!("--cache needs oxygen built with the `serde` feature")
+}
+
+/// 模块没有 DWARF 行号信息但有 sourceMappingURL 时，读取模块旁边的 map 文件；
+/// 远程地址和读取失败时忽略
+fn load_source_map(wasm: &mut WasmModule, module: &Path) {
+    let info = &wasm.debug_info;
+    let Some(url) = info.source_mapping_url.as_ref() else {
+        return;
+    };
+    if info.lines.is_some() || url.contains("://") {
+        return;
+    }
+    let path = module.parent().unwrap_or(Path::new(".")).join(url);
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    if let Ok(map) = SourceMap::parse(&text) {
+        wasm.debug_info = std::mem::take(&mut wasm.debug_info).with_source_map(map);
+    }
+}
+
+/// 描述命令及其参数，供外部工具集成
+fn help_json(cmd: &clap::Command) -> serde_json::Value {
+    let args = cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .map(|arg| {
+            json!({
+                "id": arg.get_id().as_str(),
+                "long": arg.get_long(),
+                "short": arg.get_short().map(String::from),
+                "positional": arg.is_positional(),
+                "required": arg.is_required_set(),
+                "takes_value": arg.get_num_args().is_some_and(|n| n.takes_values()),
+                "help": arg.get_help().map(|h| h.to_string()),
+                "possible_values": arg
+                    .get_possible_values()
+                    .iter()
+                    .map(|v| v.get_name().to_string())
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "name": cmd.get_name(),
+        "version": cmd.get_version(),
+        "about": cmd.get_about().map(|h| h.to_string()),
+        "args": args,
+        "subcommands": cmd.get_subcommands().map(help_json).collect::<Vec<_>>(),
+    })
+}
+
+impl InspectSection {
+    fn name(self) -> String {
+        self.to_possible_value().unwrap().get_name().to_string()
+    }
+
+    fn text(self, wasm: &WasmModule) -> String {
+        let section = &wasm.section;
+        match self {
+            Self::Custom => section.custom.to_string(),
+            Self::Types => section.types.to_string(),
+            Self::Imports => section.import.to_string(),
+            Self::Functions => section.func.to_string(),
+            Self::Tables => section.table.to_string(),
+            Self::Memories => section.memory.to_string(),
+            Self::Globals => section.global.to_string(),
+            Self::Exports => section.export.to_string(),
+            Self::Start => section.start.to_string(),
+            Self::Elements => section.element.to_string(),
+            Self::Code => section.code.to_string(),
+            Self::Data => section.data.to_string(),
+            Self::DataCount => section.data_count.to_string(),
+        }
+    }
+
+    /// section 的 offset 和 size，以及每一项的描述
+    fn json(self, wasm: &WasmModule) -> (usize, u32, Vec<serde_json::Value>) {
+        let section = &wasm.section;
+        let ty = |ty: &ValueType| ty.to_string().to_lowercase();
+        let limits = |limit: &Limit| {
+            json!({
+                "min": limit.minimum,
+                "max": (limit.flag == 1).then_some(limit.maximum),
+            })
+        };
+        match self {
+            Self::Custom => {
+                let custom = &section.custom;
+                let entries = custom
+                    .entries
+                    .iter()
+                    .map(|(name, range)| {
+                        let mut entry =
+                            json!({ "name": name, "offset": range.start, "size": range.len() });
+                        let bytes = wasm.raw.get(range.clone()).unwrap_or_default();
+                        if let Some(decoded) = custom_json(name, bytes) {
+                            entry["decoded"] = decoded;
+                        }
+                        entry
+                    })
+                    .collect();
+                (custom.offset, custom.byte_count, entries)
+            }
+            Self::Types => {
+                let types = &section.types;
+                let entries = types
+                    .entries
+                    .iter()
+                    .zip(
+                        types
+                            .subtypes
+                            .iter()
+                            .map(Some)
+                            .chain(std::iter::repeat(None)),
+                    )
+                    .map(|(t, sub)| match sub {
+                        Some(sub) if sub.is_gc() => subtype_json(sub),
+                        _ => json!({
+                            "params": t.params.iter().map(ty).collect::<Vec<_>>(),
+                            "results": t.results.iter().map(ty).collect::<Vec<_>>(),
+                        }),
+                    })
+                    .collect();
+                (types.offset, types.byte_count, entries)
+            }
+            Self::Imports => {
+                let import = &section.import;
+                let entries = import
+                    .entries
+                    .iter()
+                    .map(|ipt| {
+                        let desc = match &ipt.kind {
+                            import::Kind::Func(idx) => json!({ "kind": "func", "type": idx }),
+                            import::Kind::Table(reftype, limit) => json!({
+                                "kind": "table",
+                                "type": if *reftype == 0x6f { "externref" } else { "funcref" },
+                                "limits": limits(limit),
+                            }),
+                            import::Kind::Memory(limit) => {
+                                json!({ "kind": "memory", "limits": limits(limit) })
+                            }
+                            import::Kind::Global(g) => json!({
+                                "kind": "global",
+                                "type": ty(&g.val_ty),
+                                "mutable": g.mutability,
+                            }),
+                        };
+                        json!({ "module": ipt.mod_name, "name": ipt.field_name, "desc": desc })
+                    })
+                    .collect();
+                (import.offset, import.byte_count, entries)
+            }
+            Self::Functions => {
+                let func = &section.func;
+                let entries = func.entries.iter().map(|t| json!({ "type": t })).collect();
+                (func.offset, func.byte_count, entries)
+            }
+            Self::Tables => {
+                let table = &section.table;
+                let entries = table
+                    .entries
+                    .iter()
+                    .map(|t| {
+                        json!({
+                            "type": t.kind.to_string().to_lowercase(),
+                            "limits": limits(&t.limits),
+                        })
+                    })
+                    .collect();
+                (table.offset, table.byte_count, entries)
+            }
+            Self::Memories => {
+                let memory = &section.memory;
+                let entries = memory
+                    .entries
+                    .iter()
+                    .map(|m| json!({ "limits": limits(&m.limits) }))
+                    .collect();
+                (memory.offset, memory.byte_count, entries)
+            }
+            Self::Globals => {
+                let global = &section.global;
+                let entries = global
+                    .entries
+                    .iter()
+                    .map(|g| json!({ "type": ty(&g.val_ty), "mutable": g.mutability }))
+                    .collect();
+                (global.offset, global.byte_count, entries)
+            }
+            Self::Exports => {
+                let export = &section.export;
+                let entries = export
+                    .entries
+                    .iter()
+                    .map(|e| {
+                        let (kind, index) = match e.kind {
+                            ExportKind::Func(idx) => ("func", idx),
+                            ExportKind::Table(idx) => ("table", idx),
+                            ExportKind::Memory(idx) => ("memory", idx),
+                            ExportKind::GLobal(idx) => ("global", idx),
+                        };
+                        json!({ "name": e.name, "kind": kind, "index": index })
+                    })
+                    .collect();
+                (export.offset, export.byte_count, entries)
             }
-            let num = num.to_le_bytes();
-            for (i, v) in num.iter().enumerate() {
-                mem[nwritten as usize + i] = *v;
+            Self::Start => {
+                let start = &section.start;
+                let entries = match start.has_start {
+                    true => vec![json!({ "func": start.start_func })],
+                    false => vec![],
+                };
+                (start.offset, start.byte_count, entries)
+            }
+            Self::Elements => {
+                let element = &section.element;
+                let entries = element
+                    .entries
+                    .iter()
+                    .map(|e| {
+                        let (flag, offset, funcs) = match e {
+                            Element::E0x00(k) => (0, k.offset, Some(&k.ele.1)),
+                            Element::E0x01(k) => (1, k.offset, Some(&k.ele.1)),
+                            Element::E0x02(k) => (2, k.offset, Some(&k.ele.3)),
+                            Element::E0x03(k) => (3, k.offset, Some(&k.ele.1)),
+                            Element::E0x04(k) => (4, k.offset, None),
+                            Element::E0x05(k) => (5, k.offset, None),
+                            Element::E0x06(k) => (6, k.offset, None),
+                            Element::E0x07(k) => (7, k.offset, None),
+                        };
+                        // 表达式形式的元素段只给出标记
+                        json!({ "flag": flag, "offset": offset, "funcs": funcs })
+                    })
+                    .collect();
+                (element.offset, element.byte_count, entries)
+            }
+            Self::Code => {
+                let code = &section.code;
+                let entries = code
+                    .entries
+                    .iter()
+                    .map(|body| {
+                        let locals = body
+                            .locales
+                            .iter()
+                            .map(|(count, t)| json!({ "count": count, "type": ty(t) }))
+                            .collect::<Vec<_>>();
+                        json!({ "offset": body.offset, "size": body.size, "locals": locals })
+                    })
+                    .collect();
+                (code.offset, code.byte_count, entries)
+            }
+            Self::Data => {
+                let data = &section.data;
+                let entries = data
+                    .entries
+                    .iter()
+                    .map(|d| {
+                        let (mode, memory, bytes) = match &d.kind {
+                            DataKind::Expr(_, bytes) => ("active", Some(0), bytes),
+                            DataKind::Vec(bytes) => ("passive", None, bytes),
+                            DataKind::MemIdx(idx, _, bytes) => ("active", Some(*idx), bytes),
+                        };
+                        json!({
+                            "offset": d.offset,
+                            "mode": mode,
+                            "memory": memory,
+                            "size": bytes.len(),
+                        })
+                    })
+                    .collect();
+                (data.offset, data.byte_count, entries)
+            }
+            Self::DataCount => {
+                let data_count = &section.data_count;
+                let entries = match data_count.has_count {
+                    true => vec![json!({ "count": data_count.u32 })],
+                    false => vec![],
+                };
+                (data_count.offset, data_count.byte_count, entries)
             }
-            let s = String::from_utf8(data).unwrap();
-            println!("{s}");
         }
-        _ => {}
     }
-    return vec![WasmValue::I32(0)];
 }
 
-pub fn wasi_snapshot_preview1_proc_exit(
-    _wasm: &mut WasmModule,
-    arg: &Vec<WasmValue>,
-) -> Vec<WasmValue> {
-    let code = arg[0];
-    match code {
-        WasmValue::I32(code) => process::exit(code),
-        _ => {}
+/// 已知的 custom section 解析后的内容，内容损坏时为 `{ "error": ... }`
+fn custom_json(name: &str, bytes: &[u8]) -> Option<serde_json::Value> {
+    let decoded = match name {
+        "producers" => Producers::parse(bytes).map(|producers| {
+            producers
+                .fields
+                .iter()
+                .map(|(field, values)| {
+                    let values = values
+                        .iter()
+                        .map(|(name, version)| json!({ "name": name, "version": version }))
+                        .collect::<Vec<_>>();
+                    (field.clone(), json!(values))
+                })
+                .collect::<serde_json::Map<_, _>>()
+                .into()
+        }),
+        "target_features" => TargetFeatures::parse(bytes).map(|features| {
+            let features = features
+                .features
+                .iter()
+                .map(|(prefix, name)| {
+                    let prefix = match prefix {
+                        FeaturePrefix::Used => "used",
+                        FeaturePrefix::Disallowed => "disallowed",
+                        FeaturePrefix::Required => "required",
+                    };
+                    json!({ "name": name, "prefix": prefix })
+                })
+                .collect::<Vec<_>>();
+            json!(features)
+        }),
+        "dylink.0" => Dylink::parse(bytes).map(|dylink| {
+            json!({
+                "mem_info": dylink.mem_info.map(|mem| json!({
+                    "memory_size": mem.memory_size,
+                    "memory_alignment": mem.memory_alignment,
+                    "table_size": mem.table_size,
+                    "table_alignment": mem.table_alignment,
+                })),
+                "needed": dylink.needed,
+                "export_info": dylink
+                    .export_info
+                    .iter()
+                    .map(|(name, flags)| json!({ "name": name, "flags": flags }))
+                    .collect::<Vec<_>>(),
+                "import_info": dylink
+                    .import_info
+                    .iter()
+                    .map(|(module, name, flags)| {
+                        json!({ "module": module, "name": name, "flags": flags })
+                    })
+                    .collect::<Vec<_>>(),
+                "runtime_path": dylink.runtime_path,
+            })
+        }),
+        _ => return None,
+    };
+    Some(decoded.unwrap_or_else(|err| json!({ "error": err.to_string() })))
+}
+
+/// 模块结构的 JSON 描述，只包含选中的 section
+/// GC 提案的类型（struct/array、有父类型或不是 final 的类型）
+fn subtype_json(sub: &SubType) -> serde_json::Value {
+    let field = |field: &FieldType| {
+        let storage = match field.storage {
+            StorageType::I8 => "i8".to_string(),
+            StorageType::I16 => "i16".to_string(),
+            StorageType::Value(ty) => ty.to_string().to_lowercase(),
+        };
+        json!({ "type": storage, "mutable": field.mutable })
+    };
+    let mut value = match &sub.composite {
+        CompositeType::Func => json!({ "kind": "func" }),
+        CompositeType::Struct(fields) => {
+            json!({ "kind": "struct", "fields": fields.iter().map(field).collect::<Vec<_>>() })
+        }
+        CompositeType::Array(element) => json!({ "kind": "array", "element": field(element) }),
+    };
+    value["final"] = json!(sub.is_final);
+    value["supertypes"] = json!(sub.supertypes);
+    value["rec_group"] = json!(sub.rec_group);
+    value
+}
+
+fn inspect_json(
+    wasm: &WasmModule,
+    sections: &[InspectSection],
+    headers_only: bool,
+) -> serde_json::Value {
+    let sections = sections
+        .iter()
+        .map(|section| {
+            let (offset, size, entries) = section.json(wasm);
+            let mut value = json!({ "offset": offset, "size": size, "count": entries.len() });
+            if !headers_only {
+                value["entries"] = json!(entries);
+            }
+            (section.name(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    json!({
+        "version": wasm.version,
+        "size": wasm.raw.len(),
+        "features": wasm.required_features(),
+        "sections": sections,
+    })
+}
+
+/// 运行报告，输出到 stderr 以免和 guest 的输出混在一起
+fn emit_report(wasm: &WasmModule, status: serde_json::Value) {
+    let Some(start) = REPORT_START.get() else {
+        return;
+    };
+    let report = run_report(wasm, status, start.elapsed().as_secs_f64() * 1000.0);
+    eprintln!("{report}");
+}
+
+/// 写出 `--profile` 的 folded 文件，并把摘要输出到 stderr
+fn emit_profile(wasm: &WasmModule) -> anyhow::Result<()> {
+    let Some((path, profiler)) = PROFILE.with(|profile| profile.borrow_mut().take()) else {
+        return Ok(());
+    };
+    let mut profiler = profiler.lock().unwrap();
+    profiler.finish();
+    let names = func_names(wasm);
+    write(&path, profiler.folded(&names))
+        .with_context(|| format!("can't write profile {path:?}"))?;
+    eprint!("{}", profiler.summary(&names, 10));
+    Ok(())
+}
+
+fn run_report(
+    wasm: &WasmModule,
+    status: serde_json::Value,
+    wall_time_ms: f64,
+) -> serde_json::Value {
+    // 导入函数排在函数索引空间的最前面
+    let host_calls = wasm
+        .section
+        .import
+        .entries
+        .iter()
+        .filter(|ipt| matches!(ipt.kind, import::Kind::Func(_)))
+        .enumerate()
+        .map(|(idx, ipt)| {
+            let count = wasm.stats.host_calls.get(&idx).copied().unwrap_or(0);
+            (
+                format!("{}::{}", ipt.mod_name, ipt.field_name),
+                json!(count),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>();
+    json!({
+        "status": status,
+        "instructions": wasm.stats.instructions,
+        // 解释器目前没有 fuel 计量
+        "fuel_used": null,
+        // 内存只增不减，当前大小即峰值
+        "peak_memory_bytes": wasm.mem.iter().map(|mem| mem.len()).sum::<usize>(),
+        "wall_time_ms": wall_time_ms,
+        "host_calls": host_calls,
+    })
+}
+
+/// `-v` 的日志输出到 stderr，不影响 stdout 上的命令输出
+#[cfg(feature = "log")]
+struct StderrLogger;
+
+#[cfg(feature = "log")]
+impl StderrLogger {
+    fn init(verbose: u8) {
+        static LOGGER: StderrLogger = StderrLogger;
+        let level = match verbose {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        };
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(level);
+        }
     }
-    return vec![WasmValue::I32(0)];
+}
+
+#[cfg(feature = "log")]
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// guest 的标准输入输出为 `WasiCtx` 中的文件，默认为进程的标准输入输出
+pub fn wasi_linker() -> anyhow::Result<Linker> {
+    let mut linker = Linker::new();
+    linker
+        .define_wasi_proc_exit()?
+        .define_wasi_stdio()?
+        .define_wasi_environ()?
+        .define_wasi_clock()?
+        .define_wasi_random()?
+        .define_wasi_sockets()?;
+    Ok(linker)
+}
+
+#[test]
+fn test_help_json() {
+    let mut cmd = Arguments::command();
+    cmd.build();
+    let json = help_json(&cmd);
+    assert_eq!(json["name"], "oxygen");
+    let sub = |name: &str| {
+        json["subcommands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == name)
+            .unwrap()
+            .clone()
+    };
+    let run = sub("run");
+    let no_entry = run["args"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|a| a["id"] == "no_entry")
+        .unwrap();
+    assert_eq!(no_entry["long"], "no-entry");
+    assert_eq!(no_entry["takes_value"], false);
+    let shell = &sub("completions")["args"][0];
+    assert_eq!(shell["positional"], true);
+    assert!(shell["possible_values"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("bash")));
+}
+
+#[test]
+fn test_run_report() {
+    use oxygen::runtime::linker::Func;
+    use oxygen::runtime::section::typings::ValueType::I32;
+
+    // (import "env" "log" (func (param i32))) (func $main (call $log (i32.const 1)) ...)
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x08, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x00, 0x00, // types
+        0x02, 0x0b, 0x01, 0x03, b'e', b'n', b'v', 0x03, b'l', b'o', b'g', 0x00,
+        0x00, // import
+        0x03, 0x02, 0x01, 0x01, // func
+        0x05, 0x03, 0x01, 0x00, 0x01, // memory
+        0x0a, 0x0e, 0x01, 0x0c, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0x02, 0x10, 0x00, 0x0b, 0x0b,
+        0x0b, // code
+    ];
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let mut linker = Linker::new();
+    linker
+        .define("env", "log", Func::wrap(&[I32], &[], |_, _| vec![]))
+        .unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+    wasm.call(1).unwrap();
+
+    let report = run_report(&wasm, json!({ "kind": "ok" }), 1.5);
+    assert_eq!(report["status"]["kind"], "ok");
+    assert_eq!(report["instructions"], 5);
+    assert_eq!(report["fuel_used"], serde_json::Value::Null);
+    assert_eq!(report["peak_memory_bytes"], 65536);
+    assert_eq!(report["wall_time_ms"], 1.5);
+    assert_eq!(report["host_calls"]["env::log"], 2);
+}
+
+#[test]
+fn test_run_wasi_args() {
+    use oxygen::runtime::wasi::Socket;
+
+    let cmd = Arguments::try_parse_from([
+        "oxygen", "run", "app.wasm", "--env", "A=1", "--env", "B=x=y", "--", "-v", "--env",
+    ])
+    .unwrap();
+    let Some(Command::Run(args)) = cmd.command else {
+        panic!("expected run");
+    };
+    let ctx = args.wasi_ctx().unwrap();
+    assert_eq!(ctx.args, ["app.wasm", "-v", "--env"]);
+    assert!(!ctx.sock_accept);
+    assert_eq!(
+        ctx.env,
+        [("A".into(), "1".into()), ("B".into(), "x=y".into())]
+    );
+    assert!(Arguments::try_parse_from(["oxygen", "run", "app.wasm", "--env", "A"]).is_err());
+
+    let cmd =
+        Arguments::try_parse_from(["oxygen", "run", "app.wasm", "--tcplisten", "127.0.0.1:0"])
+            .unwrap();
+    let Some(Command::Run(args)) = cmd.command else {
+        panic!("expected run");
+    };
+    let ctx = args.wasi_ctx().unwrap();
+    assert!(ctx.sock_accept);
+    assert!(matches!(
+        ctx.sockets.lock().unwrap().get(3),
+        Some(Socket::Listener(_))
+    ));
+    assert!(Arguments::try_parse_from(["oxygen", "run", "a.wasm", "--tcplisten", "x"]).is_err());
+}
+
+#[test]
+fn test_run_register() {
+    let cmd = Arguments::try_parse_from([
+        "oxygen",
+        "run",
+        "app.wasm",
+        "--register",
+        "math=lib/math.wasm",
+        "--register",
+        "io=io.wasm",
+    ])
+    .unwrap();
+    let Some(Command::Run(args)) = cmd.command else {
+        panic!("expected run");
+    };
+    assert_eq!(
+        args.register,
+        [
+            ("math".to_string(), PathBuf::from("lib/math.wasm")),
+            ("io".to_string(), PathBuf::from("io.wasm"))
+        ]
+    );
+    assert!(
+        Arguments::try_parse_from(["oxygen", "run", "app.wasm", "--register", "math"]).is_err()
+    );
+
+    let dir = std::env::temp_dir().join(format!("oxygen-register-{}", process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("math.wasm");
+    let buf = oxygen::runtime::wat::compile(
+        r#"(module (func (export "double") (param i32) (result i32)
+          (i32.mul (local.get 0) (i32.const 2))))"#,
+    )
+    .unwrap();
+    write(&path, buf).unwrap();
+    let mut linker = Linker::new();
+    register(&mut linker, "math", &path, &RuntimeConfig::default()).unwrap();
+    let buf = oxygen::runtime::wat::compile(
+        r#"(module
+          (import "math" "double" (func $double (param i32) (result i32)))
+          (func (export "quad") (param i32) (result i32)
+            (call $double (call $double (local.get 0)))))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    linker.instantiate(&mut wasm).unwrap();
+    assert_eq!(
+        invoke_text(&mut wasm, "quad", &["3".to_string()]).unwrap(),
+        ["i32: 12 (0xc)"]
+    );
+    assert!(register(
+        &mut linker,
+        "missing",
+        &dir.join("missing.wasm"),
+        &RuntimeConfig::default()
+    )
+    .is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_run_invoke() {
+    let cmd = Arguments::try_parse_from([
+        "oxygen",
+        "run",
+        "math.wasm",
+        "--invoke",
+        "add",
+        "-1",
+        "-0x2",
+    ])
+    .unwrap();
+    let Some(Command::Run(args)) = cmd.command else {
+        panic!("expected run");
+    };
+    assert_eq!(args.invoke.as_deref(), Some("add"));
+    assert_eq!(args.values, ["-1", "-0x2"]);
+    // 没有 --invoke 时不接受多余的参数
+    assert!(Arguments::try_parse_from(["oxygen", "run", "app.wasm", "1"]).is_err());
+
+    let buf = oxygen::runtime::wat::compile(
+        r#"(module
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+          (func (export "pair") (param f64) (result i64 f64)
+            (i64.const -1) (local.get 0))
+          (global (export "g") i32 (i32.const 0)))"#,
+    )
+    .unwrap();
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    wasm.instance(None).unwrap();
+    assert_eq!(
+        invoke_text(&mut wasm, "add", &args.values).unwrap(),
+        ["i32: -3 (0xfffffffd)"]
+    );
+    assert_eq!(
+        invoke_text(&mut wasm, "pair", &["0.5".to_string()]).unwrap(),
+        [
+            "i64: -1 (0xffffffffffffffff)",
+            "f64: 0.5 (0x3fe0000000000000)"
+        ]
+    );
+    assert!(invoke_text(&mut wasm, "add", &["1".to_string()]).is_err());
+    assert!(invoke_text(&mut wasm, "add", &["1".to_string(), "x".to_string()]).is_err());
+    assert!(invoke_text::<&str>(&mut wasm, "g", &[]).is_err());
+    assert!(invoke_text::<&str>(&mut wasm, "missing", &[]).is_err());
+}
+
+#[test]
+fn test_run_call_depth() {
+    let cmd = Arguments::try_parse_from(["oxygen", "run", "a.wasm"]).unwrap();
+    let Some(Command::Run(args)) = cmd.command else {
+        panic!("expected run");
+    };
+    assert_eq!(args.max_call_depth, 1000);
+
+    let dir = std::env::temp_dir().join(format!("oxygen-depth-{}", process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("rec.wasm");
+    let buf = oxygen::runtime::wat::compile(
+        r#"(module (func $f (export "f") (param i32) (result i32)
+          (if (result i32) (i32.eqz (local.get 0))
+            (then (i32.const 0))
+            (else (i32.add (i32.const 1) (call $f (i32.sub (local.get 0) (i32.const 1))))))))"#,
+    )
+    .unwrap();
+    write(&path, buf).unwrap();
+    let url = path.to_str().unwrap();
+    // 超出默认深度时 trap，而不是耗尽原生栈
+    let cmd = Arguments::try_parse_from(["oxygen", "run", url, "--invoke", "f", "100000"]).unwrap();
+    let Some(Command::Run(args)) = cmd.command else {
+        panic!("expected run");
+    };
+    let err = on_exec_stack(move || run(&args, RuntimeConfig::default())).unwrap_err();
+    assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::StackOverflow));
+    let cmd = Arguments::try_parse_from(["oxygen", "run", url, "--invoke", "f", "900"]).unwrap();
+    let Some(Command::Run(args)) = cmd.command else {
+        panic!("expected run");
+    };
+    on_exec_stack(move || run(&args, RuntimeConfig::default())).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_inspect_json() {
+    // (import "env" "log" (func (param i32))) (memory 1) (func (export "main"))
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x08, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x00, 0x00, // types
+        0x02, 0x0b, 0x01, 0x03, b'e', b'n', b'v', 0x03, b'l', b'o', b'g', 0x00,
+        0x00, // import
+        0x03, 0x02, 0x01, 0x01, // func
+        0x05, 0x04, 0x01, 0x01, 0x01, 0x02, // memory
+        0x07, 0x08, 0x01, 0x04, b'm', b'a', b'i', b'n', 0x00, 0x01, // export
+        0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // code
+    ];
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+
+    let all = InspectSection::value_variants();
+    let json = inspect_json(&wasm, all, false);
+    assert_eq!(json["features"], json!([]));
+    let sections = &json["sections"];
+    assert_eq!(sections.as_object().unwrap().len(), all.len());
+    assert_eq!(
+        sections["types"]["entries"][0],
+        json!({ "params": ["i32"], "results": [] })
+    );
+    assert_eq!(
+        sections["imports"]["entries"][0],
+        json!({ "module": "env", "name": "log", "desc": { "kind": "func", "type": 0 } })
+    );
+    assert_eq!(
+        sections["memories"]["entries"][0]["limits"],
+        json!({ "min": 1, "max": 2 })
+    );
+    assert_eq!(
+        sections["exports"]["entries"][0],
+        json!({ "name": "main", "kind": "func", "index": 1 })
+    );
+    assert_eq!(sections["code"]["count"], 1);
+    assert_eq!(sections["data-count"]["count"], 0);
+
+    let json = inspect_json(&wasm, &[InspectSection::Exports], true);
+    assert_eq!(
+        json["sections"],
+        json!({ "exports": { "offset": 41, "size": 8, "count": 1 } })
+    );
+}
+
+#[test]
+fn test_inspect_custom_json() {
+    #[rustfmt::skip]
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x00, 0x1a, 0x0f, b't', b'a', b'r', b'g', b'e', b't', b'_', b'f', b'e', b'a', b't',
+        b'u', b'r', b'e', b's', 0x01, b'+', 0x07, b's', b'i', b'm', b'd', b'1', b'2', b'8',
+        0x00, 0x0c, 0x08, b'd', b'y', b'l', b'i', b'n', b'k', b'.', b'0', 0x01, 0x04, 0x00,
+    ];
+    let mut wasm = WasmModule::default(buf);
+    wasm.decode().unwrap();
+    let features = wasm.target_features().unwrap().unwrap();
+    assert_eq!(features.required().collect::<Vec<_>>(), ["simd128"]);
+    assert!(wasm.dylink().is_err());
+
+    let json = inspect_json(&wasm, &[InspectSection::Custom], false);
+    let entries = &json["sections"]["custom"]["entries"];
+    assert_eq!(
+        entries[0],
+        json!({
+            "name": "target_features",
+            "offset": 26,
+            "size": 10,
+            "decoded": [{ "name": "simd128", "prefix": "used" }],
+        })
+    );
+    assert_eq!(entries[1]["name"], "dylink.0");
+    assert_eq!(entries[1]["decoded"]["error"], "unexpected end");
+    let text = InspectSection::Custom.text(&wasm);
+    assert!(text.contains("        features: +simd128\n"), "{text}");
 }
 
 #[test]
 fn test_run() {
-    use std::collections::HashMap;
     use std::{env, fs::read, path::Path};
 
     let mut rt = OxygenRuntime::default();
@@ -140,20 +1724,10 @@ fn test_run() {
     let buf = read(url).unwrap();
     let r = rt.load(buf);
 
+    let linker = wasi_linker().unwrap();
     for wasm in &mut rt.modes {
         // println!("{}", wasm);
-        let mut import_object = HashMap::new();
-        let mut wasi_snapshot_preview1 = HashMap::new();
-        wasi_snapshot_preview1.insert(
-            format!("fd_write"),
-            ImportKind::Func(wasi_snapshot_preview1_fd_write),
-        );
-        wasi_snapshot_preview1.insert(
-            format!("proc_exit"),
-            ImportKind::Func(wasi_snapshot_preview1_proc_exit),
-        );
-        import_object.insert(format!("wasi_snapshot_preview1"), wasi_snapshot_preview1);
-        wasm.instance(Some(import_object)).unwrap();
+        linker.instantiate(wasm).unwrap();
 
         let _ = wasm.start();
     }
@@ -165,3 +1739,41 @@ fn test_run() {
     };
     assert!(r, "Failed to load wasm elem.2.wasm");
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_compile_artifact() {
+    let buf = oxygen::runtime::wat::compile(
+        r#"(module
+          (func (export "double") (param i32) (result i32)
+            (i32.mul (local.get 0) (i32.const 2))))"#,
+    )
+    .unwrap();
+    let artifact = compile(buf.clone()).unwrap();
+    assert!(is_artifact(&artifact) && !is_artifact(&buf));
+
+    let mut rt = OxygenRuntime::default();
+    rt.load_artifact(&artifact).unwrap();
+    let wasm = &mut rt.modes[0];
+    wasm.instance(None).unwrap();
+    assert_eq!(
+        invoke_text(wasm, "double", &["21"]).unwrap(),
+        ["i32: 42 (0x2a)"]
+    );
+    assert!(compile(b"\0asm".to_vec()).is_err());
+}
+
+#[test]
+fn test_validate() {
+    use oxygen::runtime::wat;
+
+    let buf = wat::compile("(module (func (result i32) (i32.const 1)))").unwrap();
+    assert!(validate(buf).is_empty());
+    let buf = wat::compile("(module (func (result i32) (f32.const 1)))").unwrap();
+    assert_eq!(
+        validate(buf),
+        ["error: type mismatch: expected I32, found F32 (function 0) at offset 0x1d"]
+    );
+    let errors = validate(b"\0asm\x02\0\0\0".to_vec());
+    assert!(errors[0].starts_with("error: unknown binary version 0x2"));
+}
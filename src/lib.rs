@@ -1,2 +1,4 @@
-pub mod leb;
-pub mod runtime;
+//! WebAssembly 解释器。嵌入时通常只需要 `prelude`，各部分的实现见 `runtime`
+//!
+//! 实现都在 `oxygen-core` 中，这里重新导出，`oxygen::runtime::...` 等路径保持不变。
+pub use oxygen_core::{leb, prelude, runtime};
@@ -0,0 +1,64 @@
+//! wasm-smith 生成的模块不应该让解释器 panic：`cargo test --features smith --test smith`，
+//! 环境变量 `OXYGEN_SMITH_CASES` 设置生成的模块个数，`OXYGEN_SMITH_SEED` 设置起始种子
+use oxygen::runtime::smith::{exercise, generate, Exercise};
+use std::{env, time::Duration};
+
+/// 每层 wasm 调用占用较多原生栈，在足够大的线程栈上执行
+const STACK_SIZE: usize = 256 << 20;
+
+fn env_or(name: &str, default: u64) -> u64 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// 由种子得到 wasm-smith 的输入字节（xorshift64*）
+fn input(seed: u64) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let len = 512 + (seed % 8) as usize * 1024;
+    (0..len)
+        .map(|_| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            (state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 56) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn test_smith_no_panics() {
+    let cases = env_or("OXYGEN_SMITH_CASES", 200);
+    let start = env_or("OXYGEN_SMITH_SEED", 0);
+    // panic 由 exercise 捕获后汇总报告
+    std::panic::set_hook(Box::new(|_| {}));
+    let (failures, ran, skipped) = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let (mut failures, mut ran, mut skipped) = (vec![], 0, vec![]);
+            for seed in start..start + cases {
+                let Ok(buf) = generate(&input(seed)) else {
+                    continue;
+                };
+                match exercise(&buf, Duration::from_secs(1)) {
+                    Ok(Exercise::Ran { .. }) => ran += 1,
+                    Ok(Exercise::Skipped(msg)) => skipped.push(format!("seed {seed}: {msg}")),
+                    Err(msg) => failures.push(format!("seed {seed}: {msg}")),
+                }
+            }
+            (failures, ran, skipped)
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    let _ = std::panic::take_hook();
+    // 生成的都是合法模块，只有少数用到解释器不支持的特性（例如引用类型的全局变量）不能实例化
+    assert!(
+        ran >= skipped.len() * 3,
+        "only {ran} modules ran, {} skipped:\n{}",
+        skipped.len(),
+        skipped.join("\n")
+    );
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}